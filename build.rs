@@ -1,10 +1,189 @@
 use std::env;
 use std::fs;
 use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+// Home directories are owned by the desktop user (uid 501, group staff);
+// everything else in the image belongs to root.
+const HOME_PREFIX: &str = "Users/user";
+const USER_OWNER: (u32, u32) = (501, 20);
+// Space reserved in the kernel for its symbol map; src/symbols.rs has the
+// same constant, and the build fails if the two disagree
+const SYMBOLS_CAPACITY: usize = 1 << 20;
 
 fn main() {
     let out_dir = env::var_os("OUT_DIR").unwrap();
     let dest_path = Path::new(&out_dir).join("linker.ld");
     fs::write(&dest_path, include_str!("linker.ld")).unwrap();
     println!("cargo:rustc-link-search={}", out_dir.to_string_lossy());
+
+    // Pack ./assets into the initramfs archive embedded by src/initramfs.rs,
+    // with the apps from `make apps` in /Applications
+    let apps = env::var_os("RUSTOS_APPS");
+    let archive = pack_assets(Path::new("assets"), apps.as_ref().map(Path::new));
+    fs::write(Path::new(&out_dir).join("initramfs.tar"), archive).unwrap();
+    println!("cargo:rerun-if-changed=assets");
+    println!("cargo:rerun-if-env-changed=RUSTOS_APPS");
+
+    // Build date for the About dialog; SOURCE_DATE_EPOCH pins it for
+    // reproducible builds
+    let epoch = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or_else(|| std::time::SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs());
+    let (year, month, day) = civil_from_days(epoch / 86400);
+    println!("cargo:rustc-env=BUILD_DATE={:04}-{:02}-{:02}", year, month, day);
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+    // The symbol map listed from a previous build of the kernel with
+    // `nm -n -S -C --defined-only`; see `make bootimage`
+    let mut symbols = match env::var("RUSTOS_SYMBOLS") {
+        Ok(path) => {
+            println!("cargo:rerun-if-changed={}", path);
+            pack_symbols(&fs::read_to_string(&path).unwrap_or_default())
+        }
+        Err(_) => Vec::new(),
+    };
+    if symbols.len() > SYMBOLS_CAPACITY {
+        println!("cargo:warning=symbol map is {} bytes, more than the {} reserved; leaving it out", symbols.len(), SYMBOLS_CAPACITY);
+        symbols.clear();
+    }
+    symbols.resize(SYMBOLS_CAPACITY, 0);
+    fs::write(Path::new(&out_dir).join("symbols.bin"), symbols).unwrap();
+    println!("cargo:rerun-if-env-changed=RUSTOS_SYMBOLS");
+}
+
+// One line of nm output: address, size (when known), type and name. Only
+// code symbols are kept, without the hash rustc appends to their names.
+fn parse_symbol(line: &str) -> Option<(u64, u32, &str)> {
+    let (address, rest) = line.split_once(' ')?;
+    let (size, rest) = match rest.split_once(' ')? {
+        (kind, _) if kind.len() == 1 => (0, rest),
+        (size, rest) => (u32::from_str_radix(size, 16).ok()?, rest),
+    };
+    let (kind, name) = rest.split_once(' ')?;
+    if !matches!(kind, "T" | "t" | "W" | "w") {
+        return None;
+    }
+    let name = match name.rsplit_once("::h") {
+        Some((stem, hash)) if hash.len() == 16 && hash.bytes().all(|b| b.is_ascii_hexdigit()) => stem,
+        _ => name,
+    };
+    Some((u64::from_str_radix(address, 16).ok()?, size, name))
+}
+
+// The map as src/symbols.rs reads it: "RSYM", the symbol count and the
+// length of the names, all u32; then per symbol its address (u64), size
+// and the start of its name (u32s); then the names, back to back. All
+// little-endian, in address order.
+fn pack_symbols(listing: &str) -> Vec<u8> {
+    let mut symbols: Vec<(u64, u32, &str)> = listing.lines().filter_map(parse_symbol).collect();
+    symbols.sort_by_key(|&(address, _, _)| address);
+    symbols.dedup_by_key(|&mut (address, _, _)| address);
+    if symbols.is_empty() {
+        return Vec::new();
+    }
+
+    let mut names = Vec::new();
+    let mut table = Vec::new();
+    for (address, size, name) in symbols.iter() {
+        table.extend_from_slice(&address.to_le_bytes());
+        table.extend_from_slice(&size.to_le_bytes());
+        table.extend_from_slice(&(names.len() as u32).to_le_bytes());
+        names.extend_from_slice(name.as_bytes());
+    }
+    let mut map = Vec::new();
+    map.extend_from_slice(b"RSYM");
+    map.extend_from_slice(&(symbols.len() as u32).to_le_bytes());
+    map.extend_from_slice(&(names.len() as u32).to_le_bytes());
+    map.extend_from_slice(&table);
+    map.extend_from_slice(&names);
+    map
+}
+
+// Days since 1970-01-01 to a Gregorian date
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn pack_assets(root: &Path, apps: Option<&Path>) -> Vec<u8> {
+    let mut archive = Vec::new();
+    if root.is_dir() {
+        append_dir(&mut archive, root, "", 0o644);
+    }
+    if let Some(apps) = apps.filter(|apps| apps.is_dir()) {
+        append_header(&mut archive, "Applications/", 0, b'5', 0o755, 0);
+        append_dir(&mut archive, apps, "Applications/", 0o755);
+    }
+    archive.extend_from_slice(&[0u8; 1024]);
+    archive
+}
+
+fn append_dir(archive: &mut Vec<u8>, dir: &Path, prefix: &str, file_mode: u32) {
+    let mut entries: Vec<_> = fs::read_dir(dir).unwrap().map(|e| e.unwrap()).collect();
+    entries.sort_by_key(|e| e.file_name());
+
+    for entry in entries {
+        let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+        let path = entry.path();
+        println!("cargo:rerun-if-changed={}", path.display());
+        let mtime = fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_secs());
+
+        if path.is_dir() {
+            append_header(archive, &format!("{}/", name), 0, b'5', 0o755, mtime);
+            append_dir(archive, &path, &format!("{}/", name), file_mode);
+        } else {
+            let data = fs::read(&path).unwrap();
+            append_header(archive, &name, data.len(), b'0', file_mode, mtime);
+            archive.extend_from_slice(&data);
+            let padding = (512 - data.len() % 512) % 512;
+            archive.extend(std::iter::repeat(0).take(padding));
+        }
+    }
+}
+
+fn append_header(archive: &mut Vec<u8>, name: &str, size: usize, kind: u8, mode: u32, mtime: u64) {
+    let mut header = [0u8; 512];
+    let (uid, gid) = if name.starts_with(HOME_PREFIX) { USER_OWNER } else { (0, 0) };
+    // Long paths are split at a '/' into the ustar prefix field
+    let (prefix, name) = match name.len() {
+        0..=100 => ("", name),
+        _ => {
+            let split = name
+                .match_indices('/')
+                .map(|(i, _)| i)
+                .find(|&i| name.len() - i - 1 <= 100)
+                .expect("asset path too long for ustar");
+            (&name[..split], &name[split + 1..])
+        }
+    };
+
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    header[100..108].copy_from_slice(format!("{:07o}\0", mode).as_bytes());
+    header[108..116].copy_from_slice(format!("{:07o}\0", uid).as_bytes());
+    header[116..124].copy_from_slice(format!("{:07o}\0", gid).as_bytes());
+    header[124..136].copy_from_slice(format!("{:011o}\0", size).as_bytes());
+    header[136..148].copy_from_slice(format!("{:011o}\0", mtime).as_bytes());
+    header[148..156].copy_from_slice(b"        ");
+    header[156] = kind;
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+    header[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+
+    let checksum: u32 = header.iter().map(|&b| b as u32).sum();
+    header[148..156].copy_from_slice(format!("{:06o}\0 ", checksum).as_bytes());
+    archive.extend_from_slice(&header);
 }
\ No newline at end of file
@@ -0,0 +1,8 @@
+use std::env;
+
+// Apps are linked where the kernel loads them; see link.ld
+fn main() {
+    let dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    println!("cargo:rustc-link-arg-bins=-T{}/link.ld", dir);
+    println!("cargo:rerun-if-changed=link.ld");
+}
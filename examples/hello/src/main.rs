@@ -0,0 +1,79 @@
+// examples/hello/src/main.rs
+// Hello RustOS: opens a window, says hello and counts the clicks on its
+// button until the window is closed or Escape is pressed. Only the count
+// is committed when it changes.
+#![no_std]
+#![no_main]
+
+use core::panic::PanicInfo;
+use rustos_sdk::syscall::{self, exit};
+use rustos_sdk::{Button, Canvas, Color, Event, Key, Rect, Surface};
+
+const WIDTH: usize = 260;
+const HEIGHT: usize = 110;
+const MARGIN: usize = 20;
+const BUTTON: Button = Button::new("Click Me");
+const BUTTON_Y: usize = 72;
+const COUNT: Rect = Rect { x: 130, y: BUTTON_Y + 5, width: 80, height: 8 };
+const BACKGROUND: Color = Color::LIGHT_GRAY;
+
+// `n` in decimal, written into the end of `buf`
+fn decimal(mut n: u32, buf: &mut [u8; 10]) -> &str {
+    let mut start = buf.len();
+    loop {
+        start -= 1;
+        buf[start] = b'0' + (n % 10) as u8;
+        n /= 10;
+        if n == 0 {
+            break;
+        }
+    }
+    core::str::from_utf8(&buf[start..]).unwrap()
+}
+
+fn draw_count(surface: &mut Surface, clicks: u32) {
+    let mut buf = [0u8; 10];
+    surface.draw_rect(COUNT.x, COUNT.y, COUNT.width, COUNT.height, BACKGROUND);
+    surface.draw_text(decimal(clicks, &mut buf), COUNT.x, COUNT.y, Color::BLUE);
+}
+
+fn draw(surface: &mut Surface) {
+    surface.draw_rect(0, 0, WIDTH, HEIGHT, BACKGROUND);
+    surface.draw_text("HELLO RUSTOS", MARGIN, MARGIN, Color::BLACK);
+    surface.draw_rect(MARGIN, MARGIN + 14, 12 * 8, 2, Color::BLUE);
+    surface.draw_text("FROM USER MODE", MARGIN, MARGIN + 26, Color::DARK_GRAY);
+    BUTTON.draw(surface, MARGIN, BUTTON_Y);
+    draw_count(surface, 0);
+}
+
+#[no_mangle]
+pub extern "C" fn _start() -> ! {
+    let mut surface = match Surface::create("Hello RustOS", WIDTH, HEIGHT) {
+        Ok(surface) => surface,
+        Err(_) => exit(1),
+    };
+    draw(&mut surface);
+    let _ = surface.commit_all();
+    
+    let mut clicks = 0;
+    loop {
+        match syscall::wait_event() {
+            Event::Click { x, y, .. } if BUTTON.contains(x, y, MARGIN, BUTTON_Y) => {
+                clicks += 1;
+                draw_count(&mut surface, clicks);
+                let _ = surface.commit(&[COUNT]);
+            }
+            Event::Key { event, .. } if event.pressed && event.key == Key::Escape => {
+                let _ = surface.destroy();
+                exit(0);
+            }
+            Event::Closed { .. } => exit(0),
+            _ => {}
+        }
+    }
+}
+
+#[panic_handler]
+fn panic(_info: &PanicInfo) -> ! {
+    exit(101)
+}
\ No newline at end of file
@@ -0,0 +1,59 @@
+// examples/wc/src/main.rs
+// wc: reads its input to the end and prints how many lines, words and
+// bytes there were, then the longest line. A program for the shell rather
+// than the desktop, on the SDK's stdio and heap: the lines are kept in a
+// Vec only to show the heap at work.
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::panic::PanicInfo;
+use rustos_sdk::heap::Heap;
+use rustos_sdk::syscall::exit;
+use rustos_sdk::{eprintln, println, File};
+
+#[global_allocator]
+static HEAP: Heap = Heap::new();
+
+#[no_mangle]
+pub extern "C" fn _start() -> ! {
+    let mut input = File::stdin();
+    let mut lines: Vec<Vec<u8>> = Vec::new();
+    let mut line = Vec::new();
+    let mut buf = [0u8; 256];
+    loop {
+        let count = match input.read(&mut buf) {
+            Ok(0) => break,
+            Ok(count) => count,
+            Err(err) => {
+                eprintln!("wc: {:?}", err);
+                exit(1);
+            }
+        };
+        for &byte in &buf[..count] {
+            line.push(byte);
+            if byte == b'\n' {
+                lines.push(core::mem::take(&mut line));
+            }
+        }
+    }
+    // A last line without a newline isn't counted as one, as wc has it
+    let newlines = lines.len();
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    let bytes: usize = lines.iter().map(Vec::len).sum();
+    let words: usize = lines.iter()
+        .map(|line| line.split(|byte| byte.is_ascii_whitespace()).filter(|word| !word.is_empty()).count())
+        .sum();
+    let longest = lines.iter().map(|line| line.strip_suffix(b"\n").unwrap_or(line).len()).max().unwrap_or(0);
+    println!("{:>8}{:>8}{:>8}  longest line {}", newlines, words, bytes, longest);
+    exit(0)
+}
+
+#[panic_handler]
+fn panic(_info: &PanicInfo) -> ! {
+    exit(101)
+}
@@ -0,0 +1,26 @@
+// host_tests/src/lib.rs
+// Kernel modules that need no hardware, built from the kernel's own
+// sources. Graphics draws into memory here: see Graphics::headless.
+#![no_std]
+// The kernel builds without clippy's style lints on these
+#![allow(clippy::new_without_default)]
+extern crate alloc;
+
+#[path = "../../src/chrome.rs"]
+pub mod chrome;
+#[path = "../../src/config.rs"]
+pub mod config;
+#[path = "../../src/glyph_cache.rs"]
+pub mod glyph_cache;
+#[path = "../../src/graphics.rs"]
+pub mod graphics;
+#[path = "../../src/icons.rs"]
+pub mod icons;
+#[path = "../../src/json.rs"]
+pub mod json;
+#[path = "../../src/keyboard.rs"]
+pub mod keyboard;
+#[path = "../../src/sync.rs"]
+pub mod sync;
+#[path = "../../src/widgets.rs"]
+pub mod widgets;
\ No newline at end of file
@@ -0,0 +1,47 @@
+// host_tests/tests/config.rs
+// The INI and TOML shapes the files under /etc are written in, and where
+// a broken one is reported.
+use host_tests::config::{self, ConfigError, ConfigValue};
+
+#[test]
+fn values_and_sections() {
+    let config = config::parse(
+        "top = 'level'\n\
+         [dock]\n\
+         autohide = true   # a comment\n\
+         size = 1_024\n\
+         scale = 1.5\n\
+         ; another comment\n\
+         [hot-corners]\n\
+         top-left = \"mission-control\"\n\
+         label = plain text\n\
+         escaped = \"a\\tb\"\n",
+    )
+    .unwrap();
+    assert_eq!(config.get_str("", "top"), Some("level"));
+    assert_eq!(config.get_bool("dock", "autohide"), Some(true));
+    assert_eq!(config.get_integer("dock", "size"), Some(1024));
+    assert_eq!(config.get("dock", "scale"), Some(&ConfigValue::Float(1.5)));
+    assert_eq!(config.get_str("hot-corners", "top-left"), Some("mission-control"));
+    assert_eq!(config.get_str("hot-corners", "label"), Some("plain text"));
+    assert_eq!(config.get_str("hot-corners", "escaped"), Some("a\tb"));
+    assert_eq!(config.get_str("dock", "missing"), None);
+    assert_eq!(config.sections().collect::<Vec<_>>(), ["", "dock", "hot-corners"]);
+}
+
+#[test]
+fn last_value_wins() {
+    let config = config::parse("[a]\nkey = 1\n[b]\n[a]\nkey = 2\n").unwrap();
+    assert_eq!(config.get_integer("a", "key"), Some(2));
+    assert_eq!(config.entries("a").len(), 1);
+}
+
+#[test]
+fn errors_name_the_line() {
+    let error = |text| config::parse(text).err().map(|err: ConfigError| err.line);
+    assert_eq!(error("[dock\nautohide = true"), Some(1));
+    assert_eq!(error("[dock]\nautohide true"), Some(2));
+    assert_eq!(error("# fine\n\n[dock] extra\n"), Some(3));
+    assert_eq!(error("[dock]\nname = \"unterminated\n"), Some(2));
+    assert!(config::parse("").is_ok());
+}
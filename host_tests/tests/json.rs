@@ -0,0 +1,65 @@
+// host_tests/tests/json.rs
+// JSON written out reads back as the same value, and a text that isn't JSON
+// is refused at the byte where it goes wrong.
+use host_tests::json::{self, JsonError, Value};
+
+fn sample() -> Value {
+    let mut tags = Value::array();
+    tags.push("system");
+    tags.push(Value::Null);
+    Value::object()
+        .with("name", "Finder")
+        .with("pid", 3)
+        .with("load", 0.25)
+        .with("visible", true)
+        .with("path", "/Users/user/\"quoted\"\n\ttabbed\\")
+        .with("tags", tags)
+        .with("window", Value::object().with("x", -40).with("title", "Ünïcødé ✓"))
+}
+
+#[test]
+fn compact_round_trip() {
+    let value = sample();
+    assert_eq!(json::parse(&value.to_string()), Ok(value));
+}
+
+#[test]
+fn pretty_round_trip() {
+    let value = sample();
+    let text = value.pretty();
+    assert!(text.contains("\n  \"name\": \"Finder\""));
+    assert_eq!(json::parse(&text), Ok(value));
+}
+
+#[test]
+fn compact_output() {
+    let value = Value::object().with("a", 1).with("b", Value::array()).with("c", "x\u{1}");
+    assert_eq!(value.to_string(), r#"{"a":1,"b":[],"c":"x\u0001"}"#);
+}
+
+#[test]
+fn escapes_and_surrogate_pairs() {
+    let value = json::parse(r#"["\u00e9", "\ud83d\ude00", "a\/b"]"#).unwrap();
+    assert_eq!(value.at(0).and_then(Value::as_str), Some("é"));
+    assert_eq!(value.at(1).and_then(Value::as_str), Some("😀"));
+    assert_eq!(value.at(2).and_then(Value::as_str), Some("a/b"));
+}
+
+#[test]
+fn error_positions() {
+    assert_eq!(json::parse(r#"{"a": tru}"#), Err(JsonError::Unexpected(6)));
+    assert_eq!(json::parse("[1, 2,]"), Err(JsonError::Unexpected(6)));
+    assert_eq!(json::parse(r#"{"a" 1}"#), Err(JsonError::Unexpected(5)));
+    assert_eq!(json::parse("\"tab\there\""), Err(JsonError::Unexpected(4)));
+    assert_eq!(json::parse("1 2"), Err(JsonError::Unexpected(2)));
+    assert_eq!(json::parse(r#"{"a": [1, 2"#), Err(JsonError::Truncated));
+    assert_eq!(json::parse(""), Err(JsonError::Truncated));
+}
+
+#[test]
+fn nesting_is_limited() {
+    let deep = "[".repeat(200) + &"]".repeat(200);
+    assert_eq!(json::parse(&deep), Err(JsonError::TooDeep));
+    let shallow = "[".repeat(32) + &"]".repeat(32);
+    assert!(json::parse(&shallow).is_ok());
+}
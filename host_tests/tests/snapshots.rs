@@ -0,0 +1,181 @@
+// host_tests/tests/snapshots.rs
+// Each test draws one thing on a cleared headless screen and compares a
+// hash of the frame with the one it had when it last looked right. When a
+// change is meant to alter a frame, check it in QEMU and update the hash.
+use host_tests::chrome::{self, WindowFrame};
+use host_tests::graphics::{Color, Graphics, SCREEN_HEIGHT, SCREEN_WIDTH};
+use host_tests::widgets::{Button, Checkbox, FocusChain, Marquee, SearchBar, Slider, TextField};
+use rustos_sdk::surface::scroll_pixels;
+use rustos_sdk::Rect;
+
+// FNV-1a over the colors on screen
+fn frame_hash(graphics: &Graphics) -> u64 {
+    let mut hash = 0xCBF2_9CE4_8422_2325u64;
+    for y in 0..SCREEN_HEIGHT {
+        for x in 0..SCREEN_WIDTH {
+            let color = graphics.get_pixel(x, y);
+            for b in [color.r, color.g, color.b] {
+                hash = (hash ^ b as u64).wrapping_mul(0x0100_0000_01B3);
+            }
+        }
+    }
+    hash
+}
+
+fn render(draw: impl FnOnce(&mut Graphics)) -> u64 {
+    let mut graphics = Graphics::headless();
+    graphics.clear_screen(Color::WHITE);
+    draw(&mut graphics);
+    frame_hash(&graphics)
+}
+
+#[test]
+fn headless_starts_black_and_clips() {
+    let mut graphics = Graphics::headless();
+    assert_eq!(graphics.get_pixel(0, 0), Color::BLACK);
+    graphics.draw_rect(SCREEN_WIDTH - 4, SCREEN_HEIGHT - 4, 10, 10, Color::RED);
+    assert_ne!(graphics.get_pixel(SCREEN_WIDTH - 1, SCREEN_HEIGHT - 1), Color::BLACK);
+    assert_eq!(graphics.get_pixel(SCREEN_WIDTH - 5, SCREEN_HEIGHT - 1), Color::BLACK);
+    graphics.draw_rect(SCREEN_WIDTH, 0, 10, 10, Color::RED);
+}
+
+#[test]
+fn rects() {
+    let hash = render(|graphics| {
+        graphics.draw_rect(10, 10, 100, 50, Color::BLUE);
+        graphics.draw_rect_outline(20, 80, 60, 40, Color::RED);
+        graphics.draw_rounded_rect(120, 10, 80, 60, Color::GREEN);
+    });
+    assert_eq!(hash, 2882396667591846281);
+}
+
+#[test]
+fn text() {
+    let hash = render(|graphics| {
+        graphics.draw_text("HELLO WORLD", 8, 8, Color::BLACK);
+        graphics.draw_text_italic("STATUS", 8, 24, Color::BLUE);
+    });
+    assert_eq!(hash, 3112675603485896303);
+}
+
+// A label comes from the span cache but must look the same as the text
+#[test]
+fn label_matches_text() {
+    let text = render(|graphics| graphics.draw_text("FILE EDIT", 8, 8, Color::BLACK));
+    let label = render(|graphics| graphics.draw_label("FILE EDIT", 8, 8, Color::BLACK));
+    assert_eq!(text, label);
+}
+
+// An emoji with an icon draws it in the icon's colors, and a variation
+// selector after it takes no space
+#[test]
+fn emoji_draw_as_icons() {
+    let mut graphics = Graphics::headless();
+    graphics.clear_screen(Color::WHITE);
+    graphics.draw_text("\u{1F4C1}", 8, 8, Color::BLACK);
+    assert_eq!(graphics.get_pixel(8, 10), Color::new(255, 255, 85));
+    let plain = render(|graphics| graphics.draw_text("\u{2699}A", 8, 8, Color::BLACK));
+    let selected = render(|graphics| graphics.draw_label("\u{2699}\u{FE0F}A", 8, 8, Color::BLACK));
+    assert_eq!(plain, selected);
+}
+
+#[test]
+fn widgets() {
+    let hash = render(|graphics| {
+        Button::new("OK").draw(graphics, 10, 10);
+        let mut checkbox = Checkbox::new("SHOW");
+        checkbox.checked = true;
+        checkbox.draw(graphics, 10, 40);
+        Slider { value: 0.25, width: 120 }.draw(graphics, 10, 70);
+        let mut field = TextField::new("SEARCH");
+        field.insert('A');
+        field.is_focused = true;
+        field.draw(graphics, 10, 100, 200);
+        let mut search = SearchBar::new();
+        search.show();
+        search.draw(graphics, 10, 140, 300);
+    });
+    assert_eq!(hash, 8274334192108707173);
+}
+
+// The wallpaper, menu bar and dock as the desktop draws them under its
+// own items
+#[test]
+fn desktop() {
+    let hash = render(|graphics| {
+        chrome::draw_wallpaper(graphics, Color::new(30, 130, 180));
+        chrome::draw_menu_bar(graphics, 24, Color::new(248, 248, 248), Color::new(220, 220, 220));
+        chrome::draw_dock(graphics, 100, SCREEN_HEIGHT - 70, 440, 60);
+    });
+    assert_eq!(hash, 3975469441613758757);
+}
+
+// A focused window with unsaved changes over one behind it
+#[test]
+fn window_frames() {
+    let frame = |x, y, title, focused| WindowFrame {
+        x,
+        y,
+        width: 300,
+        height: 200,
+        title,
+        background: Color::WHITE,
+        shadow_offset: 4,
+        focused,
+        edited: focused,
+    };
+    let hash = render(|graphics| {
+        frame(20, 40, "NOTES", false).draw(graphics);
+        frame(120, 120, "TEXTEDIT", true).draw(graphics);
+    });
+    assert_eq!(hash, 3019857148998071771);
+}
+
+// Text that fits is drawn as is; longer text rests at its start, then
+// scrolls
+#[test]
+fn marquee() {
+    let marquee = Marquee::new(10, Color::BLACK, Color::WHITE);
+    let short = render(|graphics| marquee.draw(graphics, "SHORT", 8, 8, 80, 0));
+    assert_eq!(short, render(|graphics| graphics.draw_text("SHORT", 8, 8, Color::BLACK)));
+    let long = "A TITLE TOO LONG FOR ITS SPACE";
+    let at = |millis| render(|graphics| marquee.draw(graphics, long, 8, 8, 80, millis));
+    assert_eq!(at(0), at(1000));
+    assert_ne!(at(0), at(2000));
+}
+
+// Tab wraps around the controls and Shift+Tab goes back; a focused
+// control that went away counts as none
+#[test]
+fn focus_chain() {
+    let mut chain = FocusChain::new();
+    chain.advance(3, true);
+    assert_eq!(chain.current(3), Some(2));
+    chain.advance(3, false);
+    assert_eq!(chain.current(3), Some(0));
+    chain.advance(3, true);
+    assert_eq!(chain.current(3), Some(2));
+    assert_eq!(chain.current(2), None);
+    chain.advance(2, false);
+    assert_eq!(chain.current(2), Some(0));
+    chain.advance(0, false);
+    assert_eq!(chain.focused, None);
+}
+
+// Only the area's columns move, the rows it uncovers keep their pixels,
+// and a move down copies from the bottom so nothing is read after it's
+// overwritten
+#[test]
+fn surface_scroll() {
+    // Four rows of four, each pixel ten times its row plus its column
+    let rows = || (0..16u32).map(|i| i / 4 * 10 + i % 4).collect::<Vec<u32>>();
+    let mut pixels = rows();
+    scroll_pixels(&mut pixels, 4, Rect { x: 1, y: 0, width: 2, height: 3 }, 1);
+    assert_eq!(pixels, [0, 11, 12, 3, 10, 21, 22, 13, 20, 21, 22, 23, 30, 31, 32, 33]);
+    let mut pixels = rows();
+    scroll_pixels(&mut pixels, 4, Rect { x: 0, y: 0, width: 4, height: 4 }, -2);
+    assert_eq!(pixels, [0, 1, 2, 3, 10, 11, 12, 13, 0, 1, 2, 3, 10, 11, 12, 13]);
+    let mut pixels = rows();
+    scroll_pixels(&mut pixels, 4, Rect { x: 0, y: 0, width: 4, height: 4 }, 4);
+    assert_eq!(pixels, rows());
+}
\ No newline at end of file
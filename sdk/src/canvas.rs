@@ -0,0 +1,57 @@
+// sdk/src/canvas.rs
+// Anything widgets can draw on: a surface in an app, the screen in the
+// kernel. Only `set_pixel` is needed; the rest can be done faster by
+// canvases that know their memory.
+use crate::color::Color;
+use crate::font::{self, GLYPH_HEIGHT, GLYPH_WIDTH};
+
+pub trait Canvas {
+    // Pixels outside the canvas are ignored
+    fn set_pixel(&mut self, x: usize, y: usize, color: Color);
+    
+    fn draw_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: Color) {
+        for dy in 0..height {
+            for dx in 0..width {
+                self.set_pixel(x + dx, y + dy, color);
+            }
+        }
+    }
+    
+    fn draw_rect_outline(&mut self, x: usize, y: usize, width: usize, height: usize, color: Color) {
+        for dx in 0..width {
+            self.set_pixel(x + dx, y, color);
+            self.set_pixel(x + dx, y + height - 1, color);
+        }
+        for dy in 0..height {
+            self.set_pixel(x, y + dy, color);
+            self.set_pixel(x + width - 1, y + dy, color);
+        }
+    }
+    
+    // Corners are cut in the window background color
+    fn draw_rounded_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: Color) {
+        self.draw_rect(x, y, width, height, color);
+        if width > 4 && height > 4 {
+            for (cx, cy) in [(x, y), (x + width - 1, y), (x, y + height - 1), (x + width - 1, y + height - 1)] {
+                let (inner_x, inner_y) = (if cx == x { cx + 1 } else { cx - 1 }, if cy == y { cy + 1 } else { cy - 1 });
+                self.set_pixel(cx, cy, Color::LIGHT_GRAY);
+                self.set_pixel(inner_x, cy, Color::LIGHT_GRAY);
+                self.set_pixel(cx, inner_y, Color::LIGHT_GRAY);
+            }
+        }
+    }
+    
+    // One cell per character, top left at (x, y)
+    fn draw_text(&mut self, text: &str, x: usize, y: usize, color: Color) {
+        for (i, ch) in text.chars().enumerate() {
+            let rows = font::font_data(ch);
+            for (row, bits) in rows.iter().enumerate().take(GLYPH_HEIGHT) {
+                for col in 0..GLYPH_WIDTH {
+                    if bits >> (7 - col) & 1 == 1 {
+                        self.set_pixel(x + i * GLYPH_WIDTH + col, y + row, color);
+                    }
+                }
+            }
+        }
+    }
+}
\ No newline at end of file
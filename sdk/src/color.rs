@@ -0,0 +1,42 @@
+// sdk/src/color.rs
+// 24-bit RGB. Surfaces hold it as 0x00RRGGBB words; the kernel's screen
+// turns it into the nearest palette entry.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+    
+    // `amount` of the way from this color to `other`
+    pub fn mix(self, other: Color, amount: f32) -> Color {
+        let channel = |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * amount) as u8;
+        Color::new(channel(self.r, other.r), channel(self.g, other.g), channel(self.b, other.b))
+    }
+    
+    // As a surface pixel
+    pub const fn to_rgb(self) -> u32 {
+        (self.r as u32) << 16 | (self.g as u32) << 8 | self.b as u32
+    }
+    
+    pub const fn from_rgb(pixel: u32) -> Color {
+        Color::new((pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8)
+    }
+    
+    pub const WHITE: Color = Color::new(255, 255, 255);
+    pub const BLACK: Color = Color::new(0, 0, 0);
+    pub const GRAY: Color = Color::new(128, 128, 128);
+    pub const LIGHT_GRAY: Color = Color::new(240, 240, 245);
+    pub const DARK_GRAY: Color = Color::new(60, 60, 60);
+    pub const BLUE: Color = Color::new(0, 122, 255);
+    pub const RED: Color = Color::new(255, 59, 48);
+    pub const GREEN: Color = Color::new(52, 199, 89);
+    pub const YELLOW: Color = Color::new(255, 204, 0);
+    pub const TRANSPARENT: Color = Color::new(0, 0, 1);
+}
\ No newline at end of file
@@ -0,0 +1,53 @@
+// sdk/src/event.rs
+// Keys as the kernel reports them, whatever keyboard they came from. Both
+// have a fixed layout, since they reach apps inside events.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum Key {
+    A, B, C, D, E, F, G, H, I, J, K, L, M,
+    N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    Digit0, Digit1, Digit2, Digit3, Digit4,
+    Digit5, Digit6, Digit7, Digit8, Digit9,
+    Space, Enter, Backspace, Tab, Escape,
+    LeftShift, RightShift, LeftCtrl, RightCtrl,
+    LeftAlt, RightAlt, LeftCmd, RightCmd,
+    ArrowUp, ArrowDown, ArrowLeft, ArrowRight,
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+}
+
+pub const LETTERS: [Key; 26] = [
+    Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I, Key::J, Key::K, Key::L, Key::M,
+    Key::N, Key::O, Key::P, Key::Q, Key::R, Key::S, Key::T, Key::U, Key::V, Key::W, Key::X, Key::Y, Key::Z,
+];
+
+pub const DIGITS: [Key; 10] = [
+    Key::Digit0, Key::Digit1, Key::Digit2, Key::Digit3, Key::Digit4,
+    Key::Digit5, Key::Digit6, Key::Digit7, Key::Digit8, Key::Digit9,
+];
+
+impl Key {
+    // The lower-case letter or space the key types
+    pub fn to_char(self) -> Option<char> {
+        match self {
+            Key::Space => Some(' '),
+            _ => LETTERS.iter().position(|&key| key == self).map(|i| (b'a' + i as u8) as char),
+        }
+    }
+    
+    // The digit on a number row key
+    pub fn to_digit(self) -> Option<u32> {
+        DIGITS.iter().position(|&key| key == self).map(|i| i as u32)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct KeyEvent {
+    pub key: Key,
+    pub pressed: bool,
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub cmd: bool,
+}
\ No newline at end of file
@@ -0,0 +1,130 @@
+// sdk/src/heap.rs
+// A heap for apps that want `alloc`'s Vec, String and Box. Memory comes
+// from `mmap` a chunk at a time and is handed out first fit from a list of
+// free blocks kept in address order, merged with their neighbours when
+// freed; chunks are never given back. An app opts in with
+//
+//     extern crate alloc;
+//
+//     #[global_allocator]
+//     static HEAP: rustos_sdk::heap::Heap = rustos_sdk::heap::Heap::new();
+//
+// Apps have a single thread, so the heap takes no lock.
+use crate::syscall;
+use core::alloc::{GlobalAlloc, Layout};
+use core::cell::UnsafeCell;
+use core::mem::size_of;
+use core::ptr::null_mut;
+
+const PAGE_SIZE: usize = 4096;
+// Asked of `mmap` at a time, unless one allocation needs more
+const CHUNK: usize = 64 * 1024;
+// Every block can hold a FreeBlock, and starts where one can
+const MIN_BLOCK: usize = size_of::<FreeBlock>();
+
+// Kept at the start of each free block
+struct FreeBlock {
+    size: usize,
+    next: *mut FreeBlock,
+}
+
+pub struct Heap {
+    free: UnsafeCell<*mut FreeBlock>,
+}
+
+unsafe impl Sync for Heap {}
+
+impl Heap {
+    pub const fn new() -> Self {
+        Self { free: UnsafeCell::new(null_mut()) }
+    }
+
+    // The size and alignment a layout gets, so that what's left either side
+    // of a block is big enough to be a free block itself
+    fn fit(layout: Layout) -> (usize, usize) {
+        let size = layout.size().max(MIN_BLOCK).next_multiple_of(MIN_BLOCK);
+        (size, layout.align().max(MIN_BLOCK))
+    }
+
+    // Puts [addr, addr + size) on the free list, merged with the blocks
+    // either side if they touch it
+    unsafe fn release(&self, addr: usize, size: usize) {
+        let mut prev: *mut FreeBlock = null_mut();
+        let mut next = *self.free.get();
+        while !next.is_null() && (next as usize) < addr {
+            prev = next;
+            next = (*next).next;
+        }
+        let block = addr as *mut FreeBlock;
+        block.write(FreeBlock { size, next });
+        if !next.is_null() && addr + size == next as usize {
+            (*block).size += (*next).size;
+            (*block).next = (*next).next;
+        }
+        if prev.is_null() {
+            *self.free.get() = block;
+        } else if prev as usize + (*prev).size == addr {
+            (*prev).size += (*block).size;
+            (*prev).next = (*block).next;
+        } else {
+            (*prev).next = block;
+        }
+    }
+
+    // The first free block with room; null if there is none
+    unsafe fn take(&self, size: usize, align: usize) -> *mut u8 {
+        let mut prev: *mut FreeBlock = null_mut();
+        let mut block = *self.free.get();
+        while !block.is_null() {
+            let start = block as usize;
+            let end = start + (*block).size;
+            let aligned = start.next_multiple_of(align);
+            if aligned + size <= end {
+                let next = (*block).next;
+                if prev.is_null() {
+                    *self.free.get() = next;
+                } else {
+                    (*prev).next = next;
+                }
+                if aligned > start {
+                    self.release(start, aligned - start);
+                }
+                if aligned + size < end {
+                    self.release(aligned + size, end - aligned - size);
+                }
+                return aligned as *mut u8;
+            }
+            prev = block;
+            block = (*block).next;
+        }
+        null_mut()
+    }
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl GlobalAlloc for Heap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let (size, align) = Self::fit(layout);
+        let memory = self.take(size, align);
+        if !memory.is_null() {
+            return memory;
+        }
+        let chunk = (size + align).max(CHUNK).next_multiple_of(PAGE_SIZE);
+        match syscall::mmap(chunk, None) {
+            Ok(memory) => {
+                self.release(memory as usize, chunk);
+                self.take(size, align)
+            }
+            Err(_) => null_mut(),
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.release(ptr as usize, Self::fit(layout).0);
+    }
+}
@@ -0,0 +1,255 @@
+// sdk/src/io.rs
+// Files the way C's stdio has them. A File is a descriptor with a buffer,
+// so small reads and writes don't each cost a system call, and opens with
+// fopen's modes. What's written waits in the buffer until it fills, until
+// `flush` and until the File is dropped; stdout and stderr also flush at
+// each newline, as a terminal's would. print! and println! go to stdout,
+// eprint! and eprintln! to stderr. ReadDir lists a directory a name at a
+// time. None of it needs the heap.
+use crate::protocol::*;
+use crate::syscall;
+use core::fmt;
+
+const BUFFER_LEN: usize = 512;
+
+pub struct File {
+    fd: usize,
+    buffer: [u8; BUFFER_LEN],
+    // Read ahead and not taken yet: buffer[position..filled]
+    position: usize,
+    filled: usize,
+    // Written and not flushed yet: buffer[..written]. Never at the same
+    // time as read-ahead.
+    written: usize,
+    line_buffered: bool,
+    // stdin, stdout and stderr stay open when dropped
+    owned: bool,
+}
+
+impl File {
+    fn from_fd(fd: usize, owned: bool) -> Self {
+        Self {
+            fd,
+            buffer: [0; BUFFER_LEN],
+            position: 0,
+            filled: 0,
+            written: 0,
+            line_buffered: fd == STDOUT || fd == STDERR,
+            owned,
+        }
+    }
+
+    pub fn stdin() -> Self {
+        Self::from_fd(STDIN, false)
+    }
+
+    pub fn stdout() -> Self {
+        Self::from_fd(STDOUT, false)
+    }
+
+    pub fn stderr() -> Self {
+        Self::from_fd(STDERR, false)
+    }
+
+    // `mode` as fopen takes it: r, w or a, with + to both read and write;
+    // a b is allowed and changes nothing
+    pub fn open(path: &str, mode: &str) -> Result<File, Error> {
+        let plus = mode.contains('+');
+        let flags = match (mode.trim_end_matches(['b', '+']), plus) {
+            ("r", false) => O_RDONLY,
+            ("r", true) => O_RDWR,
+            ("w", false) => O_WRONLY | O_CREAT | O_TRUNC,
+            ("w", true) => O_RDWR | O_CREAT | O_TRUNC,
+            ("a", false) => O_WRONLY | O_CREAT | O_APPEND,
+            ("a", true) => O_RDWR | O_CREAT | O_APPEND,
+            _ => return Err(Error::InvalidArgument),
+        };
+        Ok(Self::from_fd(syscall::open(path, flags)?, true))
+    }
+
+    pub fn fd(&self) -> usize {
+        self.fd
+    }
+
+    // Gives back read-ahead to the file, so the next write lands where the
+    // reader was. A pipe keeps what it gave.
+    fn unread(&mut self) -> Result<(), Error> {
+        let unread = self.filled - self.position;
+        self.position = 0;
+        self.filled = 0;
+        match unread {
+            0 => Ok(()),
+            _ => match syscall::lseek(self.fd, -(unread as i64), SEEK_CUR) {
+                Ok(_) | Err(Error::InvalidArgument) => Ok(()),
+                Err(err) => Err(err),
+            },
+        }
+    }
+
+    // 0 at the end of the file
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.flush()?;
+        if self.position == self.filled {
+            // Big reads skip the buffer
+            if buf.len() >= BUFFER_LEN {
+                return syscall::read(self.fd, buf);
+            }
+            self.filled = syscall::read(self.fd, &mut self.buffer)?;
+            self.position = 0;
+        }
+        let count = buf.len().min(self.filled - self.position);
+        buf[..count].copy_from_slice(&self.buffer[self.position..self.position + count]);
+        self.position += count;
+        Ok(count)
+    }
+
+    // Reads up to and including a newline, as fgets does, or until `buf`
+    // is full. 0 at the end of the file.
+    pub fn read_line(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let mut len = 0;
+        while len < buf.len() {
+            let mut byte = [0];
+            if self.read(&mut byte)? == 0 {
+                break;
+            }
+            buf[len] = byte[0];
+            len += 1;
+            if byte[0] == b'\n' {
+                break;
+            }
+        }
+        Ok(len)
+    }
+
+    // All of `data`, or an error
+    pub fn write(&mut self, mut data: &[u8]) -> Result<(), Error> {
+        self.unread()?;
+        while !data.is_empty() {
+            if self.written == BUFFER_LEN {
+                self.flush()?;
+            }
+            let count = data.len().min(BUFFER_LEN - self.written);
+            self.buffer[self.written..self.written + count].copy_from_slice(&data[..count]);
+            self.written += count;
+            data = &data[count..];
+        }
+        if self.line_buffered && self.buffer[..self.written].contains(&b'\n') {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), Error> {
+        let written = core::mem::take(&mut self.written);
+        syscall::write_all(self.fd, &self.buffer[..written])
+    }
+
+    // SEEK_SET, SEEK_CUR or SEEK_END; the new offset from the start
+    pub fn seek(&mut self, offset: i64, whence: u64) -> Result<u64, Error> {
+        self.flush()?;
+        self.unread()?;
+        syscall::lseek(self.fd, offset, whence)
+    }
+}
+
+impl fmt::Write for File {
+    fn write_str(&mut self, text: &str) -> fmt::Result {
+        self.write(text.as_bytes()).map_err(|_| fmt::Error)
+    }
+}
+
+impl Drop for File {
+    fn drop(&mut self) {
+        let _ = self.flush();
+        if self.owned {
+            let _ = syscall::close(self.fd);
+        }
+    }
+}
+
+// A directory's entries, as readdir gives them
+pub struct ReadDir {
+    fd: usize,
+    buffer: [u8; BUFFER_LEN],
+    position: usize,
+    filled: usize,
+}
+
+pub struct DirEntry<'a> {
+    pub name: &'a str,
+    pub inode: u64,
+    // DT_*
+    pub kind: u8,
+}
+
+impl DirEntry<'_> {
+    pub fn is_dir(&self) -> bool {
+        self.kind == DT_DIR
+    }
+}
+
+impl ReadDir {
+    pub fn open(path: &str) -> Result<ReadDir, Error> {
+        let fd = syscall::open(path, O_RDONLY | O_DIRECTORY)?;
+        Ok(ReadDir { fd, buffer: [0; BUFFER_LEN], position: 0, filled: 0 })
+    }
+
+    // None after the last entry
+    pub fn next_entry(&mut self) -> Result<Option<DirEntry<'_>>, Error> {
+        if self.position == self.filled {
+            self.filled = syscall::getdents(self.fd, &mut self.buffer)?;
+            self.position = 0;
+            if self.filled == 0 {
+                return Ok(None);
+            }
+        }
+        let record = &self.buffer[self.position..self.filled];
+        let field = |range: core::ops::Range<usize>| {
+            let mut bytes = [0; 8];
+            bytes[..range.len()].copy_from_slice(&record[range]);
+            u64::from_le_bytes(bytes)
+        };
+        let record_len = field(16..18) as usize;
+        let name = &record[DIRENT_NAME_OFFSET..record_len];
+        let name = &name[..name.iter().position(|&byte| byte == 0).unwrap_or(name.len())];
+        self.position += record_len;
+        Ok(Some(DirEntry {
+            name: core::str::from_utf8(name).unwrap_or("?"),
+            inode: field(0..8),
+            kind: record[18],
+        }))
+    }
+}
+
+impl Drop for ReadDir {
+    fn drop(&mut self) {
+        let _ = syscall::close(self.fd);
+    }
+}
+
+#[doc(hidden)]
+pub fn _print(fd: usize, args: fmt::Arguments) {
+    let _ = fmt::Write::write_fmt(&mut File::from_fd(fd, false), args);
+}
+
+#[macro_export]
+macro_rules! print {
+    ($($arg:tt)*) => ($crate::io::_print($crate::protocol::STDOUT, format_args!($($arg)*)));
+}
+
+#[macro_export]
+macro_rules! println {
+    () => ($crate::print!("\n"));
+    ($($arg:tt)*) => ($crate::print!("{}\n", format_args!($($arg)*)));
+}
+
+#[macro_export]
+macro_rules! eprint {
+    ($($arg:tt)*) => ($crate::io::_print($crate::protocol::STDERR, format_args!($($arg)*)));
+}
+
+#[macro_export]
+macro_rules! eprintln {
+    () => ($crate::eprint!("\n"));
+    ($($arg:tt)*) => ($crate::eprint!("{}\n", format_args!($($arg)*)));
+}
@@ -0,0 +1,32 @@
+// sdk/src/lib.rs
+// The RustOS SDK. An app asks the display server for a surface, draws into
+// it with the widgets and anything else that draws on a Canvas, commits
+// what it changed and waits for events; examples/hello is the smallest
+// such app. Text that keeps coming, like a log, goes in a Terminal, which
+// scrolls what's already drawn instead of drawing it again. Apps are plain
+// ELF executables linked in the user window and started from the Finder,
+// with `open` or from the shell. Programs without a window get stdio-style
+// files in io and, if they ask for it, a heap for `alloc` in heap;
+// examples/wc is one.
+#![no_std]
+
+pub mod canvas;
+pub mod color;
+pub mod event;
+pub mod font;
+pub mod heap;
+pub mod io;
+pub mod protocol;
+pub mod surface;
+pub mod syscall;
+pub mod terminal;
+pub mod widgets;
+
+pub use canvas::Canvas;
+pub use color::Color;
+pub use event::{Key, KeyEvent};
+pub use io::{File, ReadDir};
+pub use protocol::{Error, Event, Rect, SurfaceId};
+pub use surface::Surface;
+pub use terminal::Terminal;
+pub use widgets::{Button, Checkbox};
\ No newline at end of file
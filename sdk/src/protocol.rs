@@ -0,0 +1,193 @@
+// sdk/src/protocol.rs
+// The display server protocol as apps see it, and the small POSIX subset
+// (files, directories, memory and clocks) that ported programs need. A
+// call is `int 0x80` with
+// its number in rax and arguments in rdi, rsi, rdx, r10 and r8, in the
+// order listed by each number; rax comes back with the result, or with an
+// Error's code, which is negative. Everything passed by pointer has the
+// layout given here.
+use crate::event::KeyEvent;
+
+pub type SurfaceId = u32;
+
+pub const SYSCALL_VECTOR: u8 = 0x80;
+
+// (code) — never returns
+pub const SYS_EXIT: u64 = 0;
+// (title, title length, width, height, *mut SurfaceInfo)
+pub const SYS_CREATE_SURFACE: u64 = 1;
+// (surface, *const Rect, count)
+pub const SYS_COMMIT: u64 = 2;
+// (surface, title, title length)
+pub const SYS_SET_TITLE: u64 = 3;
+// (surface)
+pub const SYS_DESTROY_SURFACE: u64 = 4;
+// (*mut Event) — sleeps until there is one
+pub const SYS_WAIT_EVENT: u64 = 5;
+// (*mut Event) — 1 if one was written, 0 if there was none
+pub const SYS_POLL_EVENT: u64 = 6;
+// (surface, *const Rect, dy) — moves the committed rows of the rectangle
+// up by dy pixels, down when negative
+pub const SYS_SCROLL: u64 = 7;
+// (descriptor, *mut u8, length) — the bytes read, 0 at the end of the
+// input; sleeps until there's something to read
+pub const SYS_READ: u64 = 8;
+// (descriptor, *const u8, length) — the bytes written, maybe fewer than
+// asked; sleeps while there's no room for any
+pub const SYS_WRITE: u64 = 9;
+// (path, path length, O_* flags) — the new descriptor, the lowest free
+pub const SYS_OPEN: u64 = 10;
+// (descriptor)
+pub const SYS_CLOSE: u64 = 11;
+// (descriptor, offset, SEEK_*) — the new offset from the start
+pub const SYS_LSEEK: u64 = 12;
+// (length, descriptor or -1) — the address of that many zeroed, writable
+// bytes, rounded up to pages, with the file's contents copied in from its
+// current offset when there is one. Mappings last until the app exits.
+pub const SYS_MMAP: u64 = 13;
+// (CLOCK_*, *mut Timespec)
+pub const SYS_CLOCK_GETTIME: u64 = 14;
+// (descriptor, *mut u8, length) — the bytes of directory entries written,
+// 0 once all have been; see DIRENT_NAME_OFFSET for their layout
+pub const SYS_GETDENTS: u64 = 15;
+
+// The descriptors an app starts with. Output with nothing attached goes
+// to the kernel log.
+pub const STDIN: usize = 0;
+pub const STDOUT: usize = 1;
+pub const STDERR: usize = 2;
+
+// SYS_OPEN's flags, with Linux's values
+pub const O_RDONLY: u64 = 0;
+pub const O_WRONLY: u64 = 1;
+pub const O_RDWR: u64 = 2;
+pub const O_ACCMODE: u64 = 3;
+pub const O_CREAT: u64 = 0x40;
+// With O_CREAT, fail if the file exists
+pub const O_EXCL: u64 = 0x80;
+pub const O_TRUNC: u64 = 0x200;
+pub const O_APPEND: u64 = 0x400;
+pub const O_DIRECTORY: u64 = 0x1_0000;
+
+pub const SEEK_SET: u64 = 0;
+pub const SEEK_CUR: u64 = 1;
+pub const SEEK_END: u64 = 2;
+
+// Seconds since 1970, and time since boot
+pub const CLOCK_REALTIME: u64 = 0;
+pub const CLOCK_MONOTONIC: u64 = 1;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct Timespec {
+    pub seconds: i64,
+    pub nanoseconds: i64,
+}
+
+// A directory entry from SYS_GETDENTS is laid out as Linux's
+// linux_dirent64: inode (u64), offset of the next entry (i64), length of
+// this record (u16) and DT_* type (u8), then the name and a NUL, padded to
+// a multiple of 8 bytes
+pub const DIRENT_NAME_OFFSET: usize = 19;
+pub const DT_UNKNOWN: u8 = 0;
+pub const DT_DIR: u8 = 4;
+pub const DT_REG: u8 = 8;
+pub const DT_LNK: u8 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+// Where a new surface's pixels are: `stride` pixels a row, 0x00RRGGBB
+// each, mapped writable at `pixels` until the app exits
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct SurfaceInfo {
+    pub surface: SurfaceId,
+    pub stride: usize,
+    pub pixels: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub enum Event {
+    Key { surface: SurfaceId, event: KeyEvent },
+    // Relative to the surface's top-left corner
+    Click { surface: SurfaceId, x: usize, y: usize },
+    // Pointer movement with the button held
+    Drag { surface: SurfaceId, dx: i32, dy: i32 },
+    // A character put in without a key for it, from the character picker
+    Text { surface: SurfaceId, ch: char },
+    // The user closed the window; the surface is already gone
+    Closed { surface: SurfaceId },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i64)]
+pub enum Error {
+    NoSuchCall = -1,
+    // A pointer outside the app's memory
+    BadAddress = -2,
+    // Not a surface, or one that belongs to another app
+    NoSuchSurface = -3,
+    // Empty, or bigger than the screen
+    BadSize = -4,
+    TooManySurfaces = -5,
+    NoMemory = -6,
+    // Nothing open as that descriptor, or not open that way
+    BadDescriptor = -7,
+    // Writing to a pipe nobody reads any more
+    BrokenPipe = -8,
+    NotFound = -9,
+    AlreadyExists = -10,
+    IsADirectory = -11,
+    NotADirectory = -12,
+    PermissionDenied = -13,
+    // A flag, whence or clock that isn't one, or seeking a pipe
+    InvalidArgument = -14,
+    NoSpace = -15,
+    ReadOnly = -16,
+    Io = -17,
+    TooManyFiles = -18,
+}
+
+impl Error {
+    const ALL: [Error; 18] = [
+        Error::NoSuchCall,
+        Error::BadAddress,
+        Error::NoSuchSurface,
+        Error::BadSize,
+        Error::TooManySurfaces,
+        Error::NoMemory,
+        Error::BadDescriptor,
+        Error::BrokenPipe,
+        Error::NotFound,
+        Error::AlreadyExists,
+        Error::IsADirectory,
+        Error::NotADirectory,
+        Error::PermissionDenied,
+        Error::InvalidArgument,
+        Error::NoSpace,
+        Error::ReadOnly,
+        Error::Io,
+        Error::TooManyFiles,
+    ];
+    
+    pub fn code(self) -> i64 {
+        self as i64
+    }
+    
+    // Unknown negative codes are taken as NoSuchCall, as an app built for
+    // a newer kernel would get
+    pub fn from_code(code: i64) -> Option<Error> {
+        match code {
+            0.. => None,
+            _ => Some(Error::ALL.iter().copied().find(|err| err.code() == code).unwrap_or(Error::NoSuchCall)),
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,108 @@
+// sdk/src/surface.rs
+// A window's contents as an app holds them. Drawing goes straight into the
+// shared pixels; the window shows none of it until it's committed.
+use crate::canvas::Canvas;
+use crate::color::Color;
+use crate::protocol::{Error, Rect, SurfaceId};
+use crate::syscall;
+
+pub struct Surface {
+    id: SurfaceId,
+    width: usize,
+    height: usize,
+    stride: usize,
+    pixels: &'static mut [u32],
+}
+
+impl Surface {
+    // A new window of `width` by `height` pixels, white until the first
+    // commit
+    pub fn create(title: &str, width: usize, height: usize) -> Result<Surface, Error> {
+        let info = syscall::create_surface(title, width, height)?;
+        // The kernel keeps it mapped until the app exits
+        let pixels = unsafe { core::slice::from_raw_parts_mut(info.pixels as *mut u32, info.stride * height) };
+        Ok(Surface { id: info.surface, width, height, stride: info.stride, pixels })
+    }
+    
+    pub fn id(&self) -> SurfaceId {
+        self.id
+    }
+    
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    
+    pub fn bounds(&self) -> Rect {
+        Rect { x: 0, y: 0, width: self.width, height: self.height }
+    }
+    
+    // Shows the rectangles drawn since the last commit
+    pub fn commit(&mut self, damage: &[Rect]) -> Result<(), Error> {
+        syscall::commit(self.id, damage)
+    }
+    
+    pub fn commit_all(&mut self) -> Result<(), Error> {
+        self.commit(&[self.bounds()])
+    }
+    
+    // Moves the rows of `area` up by `dy` pixels, down when negative, here
+    // and in what the window shows, without a commit. The rows it uncovers
+    // keep their old pixels until they're drawn and committed.
+    pub fn scroll(&mut self, area: Rect, dy: isize) -> Result<(), Error> {
+        scroll_pixels(self.pixels, self.stride, area, dy);
+        syscall::scroll(self.id, &area, dy)
+    }
+    
+    pub fn set_title(&mut self, title: &str) -> Result<(), Error> {
+        syscall::set_title(self.id, title)
+    }
+    
+    // Closes the window
+    pub fn destroy(self) -> Result<(), Error> {
+        syscall::destroy_surface(self.id)
+    }
+}
+
+// Moves the rows of `area`, cut to the buffer, up by `dy` pixels or down
+// when negative, in pixels laid out `stride` to a row. Rows moved past the
+// edge of the area are gone and the ones uncovered are left as they were.
+// The display server does the same to its copy of a surface.
+pub fn scroll_pixels(pixels: &mut [u32], stride: usize, area: Rect, dy: isize) {
+    let right = area.x.saturating_add(area.width).min(stride);
+    let bottom = area.y.saturating_add(area.height).min(pixels.len() / stride.max(1));
+    let shift = dy.unsigned_abs();
+    if area.x >= right || area.y >= bottom || shift == 0 || shift >= bottom - area.y {
+        return;
+    }
+    let width = right - area.x;
+    let rows = area.y..bottom - shift;
+    let moves = rows.map(|row| if dy > 0 { (row + shift, row) } else { (row, row + shift) });
+    let copy = |pixels: &mut [u32], (from, to): (usize, usize)| {
+        pixels.copy_within(from * stride + area.x..from * stride + area.x + width, to * stride + area.x);
+    };
+    // Moving up, the top row is written first; moving down, the bottom one
+    if dy > 0 {
+        moves.for_each(|step| copy(pixels, step));
+    } else {
+        moves.rev().for_each(|step| copy(pixels, step));
+    }
+}
+
+impl Canvas for Surface {
+    fn set_pixel(&mut self, x: usize, y: usize, color: Color) {
+        if x < self.width && y < self.height {
+            self.pixels[y * self.stride + x] = color.to_rgb();
+        }
+    }
+    
+    fn draw_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: Color) {
+        let (right, bottom) = ((x + width).min(self.width), (y + height).min(self.height));
+        for row in y.min(bottom)..bottom {
+            self.pixels[row * self.stride + x.min(right)..row * self.stride + right].fill(color.to_rgb());
+        }
+    }
+}
\ No newline at end of file
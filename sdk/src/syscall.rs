@@ -0,0 +1,123 @@
+// sdk/src/syscall.rs
+// The system calls, one function each. See protocol.rs for the calling
+// convention.
+use crate::protocol::*;
+use core::arch::asm;
+use core::mem::MaybeUninit;
+
+unsafe fn syscall(number: u64, args: [u64; 5]) -> i64 {
+    let result: i64;
+    asm!(
+        "int 0x80",
+        inlateout("rax") number as i64 => result,
+        in("rdi") args[0],
+        in("rsi") args[1],
+        in("rdx") args[2],
+        in("r10") args[3],
+        in("r8") args[4],
+        options(nostack),
+    );
+    result
+}
+
+fn check(result: i64) -> Result<u64, Error> {
+    match Error::from_code(result) {
+        Some(err) => Err(err),
+        None => Ok(result as u64),
+    }
+}
+
+pub fn exit(code: i32) -> ! {
+    unsafe { syscall(SYS_EXIT, [code as u64, 0, 0, 0, 0]) };
+    unreachable!("exit returned")
+}
+
+pub fn create_surface(title: &str, width: usize, height: usize) -> Result<SurfaceInfo, Error> {
+    let mut info = SurfaceInfo::default();
+    let args = [title.as_ptr() as u64, title.len() as u64, width as u64, height as u64, &mut info as *mut _ as u64];
+    check(unsafe { syscall(SYS_CREATE_SURFACE, args) })?;
+    Ok(info)
+}
+
+pub fn commit(surface: SurfaceId, damage: &[Rect]) -> Result<(), Error> {
+    check(unsafe { syscall(SYS_COMMIT, [surface as u64, damage.as_ptr() as u64, damage.len() as u64, 0, 0]) })?;
+    Ok(())
+}
+
+pub fn set_title(surface: SurfaceId, title: &str) -> Result<(), Error> {
+    check(unsafe { syscall(SYS_SET_TITLE, [surface as u64, title.as_ptr() as u64, title.len() as u64, 0, 0]) })?;
+    Ok(())
+}
+
+pub fn scroll(surface: SurfaceId, area: &Rect, dy: isize) -> Result<(), Error> {
+    check(unsafe { syscall(SYS_SCROLL, [surface as u64, area as *const Rect as u64, dy as u64, 0, 0]) })?;
+    Ok(())
+}
+
+pub fn destroy_surface(surface: SurfaceId) -> Result<(), Error> {
+    check(unsafe { syscall(SYS_DESTROY_SURFACE, [surface as u64, 0, 0, 0, 0]) })?;
+    Ok(())
+}
+
+pub fn read(fd: usize, buf: &mut [u8]) -> Result<usize, Error> {
+    check(unsafe { syscall(SYS_READ, [fd as u64, buf.as_mut_ptr() as u64, buf.len() as u64, 0, 0]) }).map(|count| count as usize)
+}
+
+pub fn write(fd: usize, data: &[u8]) -> Result<usize, Error> {
+    check(unsafe { syscall(SYS_WRITE, [fd as u64, data.as_ptr() as u64, data.len() as u64, 0, 0]) }).map(|count| count as usize)
+}
+
+// Writes all of `data`, as many calls as it takes
+pub fn write_all(fd: usize, mut data: &[u8]) -> Result<(), Error> {
+    while !data.is_empty() {
+        let count = write(fd, data)?;
+        data = &data[count..];
+    }
+    Ok(())
+}
+
+pub fn open(path: &str, flags: u64) -> Result<usize, Error> {
+    check(unsafe { syscall(SYS_OPEN, [path.as_ptr() as u64, path.len() as u64, flags, 0, 0]) }).map(|fd| fd as usize)
+}
+
+pub fn close(fd: usize) -> Result<(), Error> {
+    check(unsafe { syscall(SYS_CLOSE, [fd as u64, 0, 0, 0, 0]) }).map(|_| ())
+}
+
+pub fn lseek(fd: usize, offset: i64, whence: u64) -> Result<u64, Error> {
+    check(unsafe { syscall(SYS_LSEEK, [fd as u64, offset as u64, whence, 0, 0]) })
+}
+
+// Fresh zeroed memory, or a copy of the file at `fd` from its offset
+pub fn mmap(len: usize, fd: Option<usize>) -> Result<*mut u8, Error> {
+    let fd = fd.map_or(u64::MAX, |fd| fd as u64);
+    check(unsafe { syscall(SYS_MMAP, [len as u64, fd, 0, 0, 0]) }).map(|addr| addr as *mut u8)
+}
+
+pub fn clock_gettime(clock: u64) -> Result<Timespec, Error> {
+    let mut time = Timespec::default();
+    check(unsafe { syscall(SYS_CLOCK_GETTIME, [clock, &mut time as *mut Timespec as u64, 0, 0, 0]) })?;
+    Ok(time)
+}
+
+pub fn getdents(fd: usize, buf: &mut [u8]) -> Result<usize, Error> {
+    check(unsafe { syscall(SYS_GETDENTS, [fd as u64, buf.as_mut_ptr() as u64, buf.len() as u64, 0, 0]) }).map(|count| count as usize)
+}
+
+pub fn wait_event() -> Event {
+    let mut event = MaybeUninit::<Event>::uninit();
+    unsafe {
+        syscall(SYS_WAIT_EVENT, [event.as_mut_ptr() as u64, 0, 0, 0, 0]);
+        event.assume_init()
+    }
+}
+
+pub fn poll_event() -> Option<Event> {
+    let mut event = MaybeUninit::<Event>::uninit();
+    unsafe {
+        match syscall(SYS_POLL_EVENT, [event.as_mut_ptr() as u64, 0, 0, 0, 0]) {
+            1 => Some(event.assume_init()),
+            _ => None,
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,63 @@
+// sdk/src/terminal.rs
+// Lines of text running up part of a surface, as a terminal prints them.
+// Once the area is full a new line scrolls the others up with
+// `Surface::scroll` and is the only thing drawn and committed, so output
+// costs one line of drawing however many are on screen.
+use crate::canvas::Canvas;
+use crate::color::Color;
+use crate::font::{GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::protocol::{Error, Rect};
+use crate::surface::Surface;
+
+pub struct Terminal {
+    area: Rect,
+    background: Color,
+    // Lines printed so far, up to as many as fit
+    used: usize,
+}
+
+impl Terminal {
+    pub const LINE_HEIGHT: usize = GLYPH_HEIGHT + 4;
+    // Between the area's left edge and the text
+    const MARGIN: usize = 4;
+    
+    pub const fn new(area: Rect, background: Color) -> Self {
+        Self { area, background, used: 0 }
+    }
+    
+    // Lines that fit in the area
+    pub fn rows(&self) -> usize {
+        self.area.height / Self::LINE_HEIGHT
+    }
+    
+    // Fills the area with the background and starts again at the top
+    pub fn clear(&mut self, surface: &mut Surface) -> Result<(), Error> {
+        let area = self.area;
+        surface.draw_rect(area.x, area.y, area.width, area.height, self.background);
+        self.used = 0;
+        surface.commit(&[area])
+    }
+    
+    // Prints `text` on a line of its own under the others, cut at the
+    // area's right edge
+    pub fn print(&mut self, surface: &mut Surface, text: &str, color: Color) -> Result<(), Error> {
+        let rows = self.rows();
+        if rows == 0 {
+            return Ok(());
+        }
+        let row = if self.used < rows {
+            self.used += 1;
+            self.used - 1
+        } else {
+            let lines = Rect { height: rows * Self::LINE_HEIGHT, ..self.area };
+            surface.scroll(lines, Self::LINE_HEIGHT as isize)?;
+            rows - 1
+        };
+        let line = Rect { y: self.area.y + row * Self::LINE_HEIGHT, height: Self::LINE_HEIGHT, ..self.area };
+        surface.draw_rect(line.x, line.y, line.width, line.height, self.background);
+        let fits = line.width.saturating_sub(2 * Self::MARGIN) / GLYPH_WIDTH;
+        let end = text.char_indices().nth(fits).map_or(text.len(), |(i, _)| i);
+        surface.draw_text(&text[..end], line.x + Self::MARGIN, line.y + 2, color);
+        surface.commit(&[line])
+    }
+}
\ No newline at end of file
@@ -0,0 +1,75 @@
+// sdk/src/widgets.rs
+// Controls shared by the kernel's apps and apps of their own. Like the
+// kernel's other widgets they only hold state and draw themselves where
+// they're told; the app lays them out and hands them clicks.
+use crate::canvas::Canvas;
+use crate::color::Color;
+use crate::font::GLYPH_WIDTH;
+
+// Push button sized to its label
+pub struct Button {
+    pub label: &'static str,
+}
+
+impl Button {
+    pub const HEIGHT: usize = 18;
+    
+    pub const fn new(label: &'static str) -> Self {
+        Self { label }
+    }
+    
+    pub fn width(&self) -> usize {
+        self.label.len() * GLYPH_WIDTH + 16
+    }
+    
+    // Whether the point hits the button drawn at (x, y)
+    pub fn contains(&self, px: usize, py: usize, x: usize, y: usize) -> bool {
+        px >= x && px < x + self.width() && py >= y && py < y + Self::HEIGHT
+    }
+    
+    pub fn draw(&self, canvas: &mut impl Canvas, x: usize, y: usize) {
+        canvas.draw_rounded_rect(x, y, self.width(), Self::HEIGHT, Color::WHITE);
+        canvas.draw_rect_outline(x, y, self.width(), Self::HEIGHT, Color::new(200, 200, 200));
+        canvas.draw_text(self.label, x + 8, y + 5, Color::BLACK);
+    }
+}
+
+// Check box with its label to the right
+pub struct Checkbox {
+    pub label: &'static str,
+    pub checked: bool,
+}
+
+impl Checkbox {
+    pub const SIZE: usize = 12;
+    
+    pub const fn new(label: &'static str) -> Self {
+        Self { label, checked: false }
+    }
+    
+    pub fn width(&self) -> usize {
+        Self::SIZE + 6 + self.label.len() * GLYPH_WIDTH
+    }
+    
+    // Whether the point hits the box or its label drawn at (x, y)
+    pub fn contains(&self, px: usize, py: usize, x: usize, y: usize) -> bool {
+        px >= x && px < x + self.width() && py >= y && py < y + Self::SIZE
+    }
+    
+    pub fn draw(&self, canvas: &mut impl Canvas, x: usize, y: usize) {
+        if self.checked {
+            canvas.draw_rounded_rect(x, y, Self::SIZE, Self::SIZE, Color::BLUE);
+            // A tick: short stroke down, long stroke up
+            for i in 0..3 {
+                canvas.draw_rect(x + 2 + i, y + 5 + i, 2, 2, Color::WHITE);
+            }
+            for i in 0..5 {
+                canvas.draw_rect(x + 5 + i, y + 6 - i, 2, 2, Color::WHITE);
+            }
+        } else {
+            canvas.draw_rounded_rect(x, y, Self::SIZE, Self::SIZE, Color::WHITE);
+            canvas.draw_rect_outline(x, y, Self::SIZE, Self::SIZE, Color::new(170, 170, 170));
+        }
+        canvas.draw_text(self.label, x + Self::SIZE + 6, y + 2, Color::BLACK);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,312 @@
+// src/acpi.rs
+// ACPI tables, read once at boot. The RSDP is wherever the firmware left
+// it, in the first KiB of the EBDA or the BIOS area below 1 MiB; it points
+// to the XSDT, or the RSDT on ACPI 1.0 firmware like QEMU's, which lists
+// the rest. Every table is checked against its checksum. The MADT says
+// which processors there are, where the I/O APICs are and which ISA IRQs
+// are wired to other inputs; the FADT has the power management registers,
+// and the values for soft off come from the \_S5 object in the DSDT. The
+// HPET table says where the HPET is. The
+// PICs still deliver interrupts and only the boot CPU runs; this is what
+// the APIC and SMP code will start from.
+use crate::memory;
+use crate::{kinfo, kwarn};
+use alloc::string::String;
+use alloc::vec::Vec;
+use spin::Once;
+use x86_64::PhysAddr;
+
+const RSDP_SIGNATURE: &[u8] = b"RSD PTR ";
+// Up to the RSDT address, all ACPI 1.0 has
+const RSDP_V1_LEN: usize = 20;
+const RSDP_V2_LEN: usize = 36;
+// Where the BIOS data area keeps the EBDA's segment
+const EBDA_SEGMENT: u64 = 0x40E;
+const EBDA_SEARCH: usize = 1024;
+const BIOS_AREA: u64 = 0xE0000;
+const BIOS_AREA_LEN: usize = 0x20000;
+const HEADER_LEN: usize = 36;
+
+// MADT entry types
+const LOCAL_APIC: u8 = 0;
+const IO_APIC: u8 = 1;
+const SOURCE_OVERRIDE: u8 = 2;
+const LOCAL_APIC_ADDRESS: u8 = 5;
+const LOCAL_X2APIC: u8 = 9;
+const MADT_PCAT_COMPAT: u32 = 1;
+const PROCESSOR_ENABLED: u32 = 1;
+const PROCESSOR_ONLINE_CAPABLE: u32 = 2;
+
+const HPET_LEN: usize = 56;
+const GAS_MEMORY: u8 = 0;
+
+// The 64-bit DSDT address, there in FADTs past ACPI 1.0
+const FADT_X_DSDT: usize = 140;
+const AML_NAME: u8 = 0x08;
+const AML_PACKAGE: u8 = 0x12;
+const AML_BYTE: u8 = 0x0A;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AcpiError {
+    NoRsdp,
+    // A table whose bytes don't add up to zero
+    BadChecksum([u8; 4]),
+    NoTable([u8; 4]),
+}
+
+impl AcpiError {
+    pub fn message(self) -> &'static str {
+        match self {
+            AcpiError::NoRsdp => "no RSDP",
+            AcpiError::BadChecksum(_) => "bad table checksum",
+            AcpiError::NoTable(_) => "no MADT",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Processor {
+    pub apic_id: u32,
+    // Disabled ones that are online capable can be brought up later
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct IoApic {
+    pub id: u8,
+    pub address: u64,
+    // The first global system interrupt it takes
+    pub gsi_base: u32,
+}
+
+// An ISA IRQ wired to another global system interrupt
+#[derive(Debug, Clone, Copy)]
+pub struct SourceOverride {
+    pub irq: u8,
+    pub gsi: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PowerManagement {
+    pub sci_irq: u16,
+    // Written `acpi_enable` to hand the registers over from SMM; 0 when
+    // they're always in ACPI mode
+    pub smi_command: u16,
+    pub acpi_enable: u8,
+    pub pm1a_control: u16,
+    pub pm1b_control: u16,
+    // SLP_TYPa and SLP_TYPb for soft off
+    pub s5: Option<(u8, u8)>,
+}
+
+pub struct Tables {
+    pub revision: u8,
+    pub oem: String,
+    pub local_apic: u64,
+    // The machine has 8259 PICs as well, which have to be masked before
+    // the APICs take over
+    pub has_pics: bool,
+    pub processors: Vec<Processor>,
+    pub io_apics: Vec<IoApic>,
+    pub overrides: Vec<SourceOverride>,
+    pub power: Option<PowerManagement>,
+    // The HPET's registers
+    pub hpet: Option<u64>,
+}
+
+static TABLES: Once<Tables> = Once::new();
+
+fn u16_at(buf: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([buf[at], buf[at + 1]])
+}
+
+fn u32_at(buf: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([buf[at], buf[at + 1], buf[at + 2], buf[at + 3]])
+}
+
+fn u64_at(buf: &[u8], at: usize) -> u64 {
+    u32_at(buf, at) as u64 | (u32_at(buf, at + 4) as u64) << 32
+}
+
+// Physical memory through the kernel's mapping of all of it
+fn physical(addr: u64, len: usize) -> &'static [u8] {
+    unsafe { core::slice::from_raw_parts(memory::phys_to_virt(PhysAddr::new(addr)).as_ptr(), len) }
+}
+
+fn checksum(bytes: &[u8]) -> bool {
+    bytes.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte)) == 0
+}
+
+// The RSDP sits on a 16-byte boundary
+fn find_rsdp() -> Option<&'static [u8]> {
+    let ebda = (u16_at(physical(EBDA_SEGMENT, 2), 0) as u64) << 4;
+    let areas = [(ebda, EBDA_SEARCH), (BIOS_AREA, BIOS_AREA_LEN)];
+    areas.into_iter().filter(|&(start, _)| start != 0).find_map(|(start, len)| {
+        let area = physical(start, len);
+        (0..len - RSDP_V2_LEN).step_by(16).map(|at| &area[at..at + RSDP_V2_LEN]).find(|rsdp| {
+            &rsdp[..8] == RSDP_SIGNATURE
+                && checksum(&rsdp[..RSDP_V1_LEN])
+                && (rsdp[15] < 2 || checksum(&rsdp[..(u32_at(rsdp, 20) as usize).min(RSDP_V2_LEN)]))
+        })
+    })
+}
+
+// The whole table at `addr`, checksum checked
+fn table(addr: u64) -> Result<&'static [u8], AcpiError> {
+    let header = physical(addr, HEADER_LEN);
+    let signature = [header[0], header[1], header[2], header[3]];
+    let table = physical(addr, (u32_at(header, 4) as usize).max(HEADER_LEN));
+    if checksum(table) {
+        Ok(table)
+    } else {
+        Err(AcpiError::BadChecksum(signature))
+    }
+}
+
+fn madt(madt: &[u8], tables: &mut Tables) {
+    tables.local_apic = u32_at(madt, 36) as u64;
+    tables.has_pics = u32_at(madt, 40) & MADT_PCAT_COMPAT != 0;
+    let mut at = 44;
+    while at + 2 <= madt.len() {
+        let (kind, len) = (madt[at], madt[at + 1] as usize);
+        if len < 2 || at + len > madt.len() {
+            break;
+        }
+        let entry = &madt[at..at + len];
+        match kind {
+            LOCAL_APIC if len >= 8 => {
+                let flags = u32_at(entry, 4);
+                if flags & (PROCESSOR_ENABLED | PROCESSOR_ONLINE_CAPABLE) != 0 {
+                    tables.processors.push(Processor { apic_id: entry[3] as u32, enabled: flags & PROCESSOR_ENABLED != 0 });
+                }
+            }
+            LOCAL_X2APIC if len >= 16 => {
+                let flags = u32_at(entry, 8);
+                if flags & (PROCESSOR_ENABLED | PROCESSOR_ONLINE_CAPABLE) != 0 {
+                    tables.processors.push(Processor { apic_id: u32_at(entry, 4), enabled: flags & PROCESSOR_ENABLED != 0 });
+                }
+            }
+            IO_APIC if len >= 12 => tables.io_apics.push(IoApic { id: entry[2], address: u32_at(entry, 4) as u64, gsi_base: u32_at(entry, 8) }),
+            SOURCE_OVERRIDE if len >= 10 => tables.overrides.push(SourceOverride { irq: entry[3], gsi: u32_at(entry, 4) }),
+            LOCAL_APIC_ADDRESS if len >= 12 => tables.local_apic = u64_at(entry, 4),
+            _ => {}
+        }
+        at += len;
+    }
+}
+
+// SLP_TYPa and SLP_TYPb from the DSDT's `Name (_S5, Package () {a, b, ...})`.
+// Nothing else in the AML is understood, so the package is found by name.
+fn s5(dsdt: &[u8]) -> Option<(u8, u8)> {
+    let name = dsdt.windows(4).position(|window| window == b"_S5_")?;
+    let named = (name >= 1 && dsdt[name - 1] == AML_NAME) || (name >= 2 && dsdt[name - 2] == AML_NAME && dsdt[name - 1] == b'\\');
+    let mut at = name + 4;
+    if !named || *dsdt.get(at)? != AML_PACKAGE {
+        return None;
+    }
+    // The package length's top bits say how many bytes it takes, then
+    // the element count
+    at += 1 + ((dsdt.get(at + 1)? >> 6) as usize + 1) + 1;
+    let mut value = || {
+        if *dsdt.get(at)? == AML_BYTE {
+            at += 1;
+        }
+        at += 1;
+        dsdt.get(at - 1).copied()
+    };
+    Some((value()?, value()?))
+}
+
+fn fadt(fadt: &[u8]) -> Result<PowerManagement, AcpiError> {
+    let dsdt_addr = match fadt.len() >= FADT_X_DSDT + 8 {
+        true if u64_at(fadt, FADT_X_DSDT) != 0 => u64_at(fadt, FADT_X_DSDT),
+        _ => u32_at(fadt, 40) as u64,
+    };
+    Ok(PowerManagement {
+        sci_irq: u16_at(fadt, 46),
+        smi_command: u32_at(fadt, 48) as u16,
+        acpi_enable: fadt[52],
+        pm1a_control: u32_at(fadt, 64) as u16,
+        pm1b_control: u32_at(fadt, 68) as u16,
+        s5: if dsdt_addr != 0 { s5(table(dsdt_addr)?) } else { None },
+    })
+}
+
+pub fn init() -> Result<(), AcpiError> {
+    let rsdp = find_rsdp().ok_or(AcpiError::NoRsdp)?;
+    let revision = rsdp[15];
+    let xsdt = u64_at(rsdp, 24);
+    // The XSDT's entries are 64 bits, the RSDT's 32
+    let (root, entry_len) = if revision >= 2 && xsdt != 0 { (table(xsdt)?, 8) } else { (table(u32_at(rsdp, 16) as u64)?, 4) };
+    let mut tables = Tables {
+        revision,
+        oem: String::from_utf8_lossy(&rsdp[9..15]).trim_end().into(),
+        local_apic: 0,
+        has_pics: true,
+        processors: Vec::new(),
+        io_apics: Vec::new(),
+        overrides: Vec::new(),
+        power: None,
+        hpet: None,
+    };
+    let mut found_madt = false;
+    for at in (HEADER_LEN..root.len()).step_by(entry_len) {
+        let addr = if entry_len == 8 { u64_at(root, at) } else { u32_at(root, at) as u64 };
+        let table = match table(addr) {
+            Ok(table) => table,
+            Err(err) => {
+                kwarn!("acpi: skipping table at {:#x}: {:?}", addr, err);
+                continue;
+            }
+        };
+        match &table[..4] {
+            b"APIC" => {
+                madt(table, &mut tables);
+                found_madt = true;
+            }
+            b"FACP" => match fadt(table) {
+                Ok(power) => tables.power = Some(power),
+                Err(err) => kwarn!("acpi: no DSDT: {:?}", err),
+            },
+            // The base address is a generic address; the HPET's is always
+            // in memory space
+            b"HPET" if table.len() >= HPET_LEN && table[40] == GAS_MEMORY => tables.hpet = Some(u64_at(table, 44)),
+            _ => {}
+        }
+    }
+    if !found_madt {
+        return Err(AcpiError::NoTable(*b"APIC"));
+    }
+    kinfo!(
+        "acpi: revision {} from {}, {} processors, {} I/O APICs, {} IRQ overrides",
+        revision, tables.oem, tables.processors.len(), tables.io_apics.len(), tables.overrides.len()
+    );
+    TABLES.call_once(|| tables);
+    Ok(())
+}
+
+// None when there's no ACPI or the tables couldn't be read
+pub fn tables() -> Option<&'static Tables> {
+    TABLES.r#try()
+}
+
+// Empty without ACPI
+pub fn processors() -> &'static [Processor] {
+    tables().map_or(&[], |tables| &tables.processors)
+}
+
+pub fn power() -> Option<&'static PowerManagement> {
+    tables()?.power.as_ref()
+}
+
+pub fn hpet() -> Option<u64> {
+    tables()?.hpet
+}
+
+// The global system interrupt an ISA IRQ arrives on; the same number
+// unless the MADT overrides it
+pub fn isa_gsi(irq: u8) -> u32 {
+    tables()
+        .and_then(|tables| tables.overrides.iter().find(|o| o.irq == irq))
+        .map_or(irq as u32, |o| o.gsi)
+}
\ No newline at end of file
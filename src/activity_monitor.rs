@@ -0,0 +1,189 @@
+// src/activity_monitor.rs
+use crate::graphics::{Graphics, Color};
+use crate::{allocator, block_cache, clock, cpu, cpu_usage, frame_profiler, heap_profiler, interrupts, task};
+use alloc::format;
+use alloc::string::String;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+const TABS: [&str; 5] = ["CPU", "Memory", "Disk", "Frames", "Interrupts"];
+pub const TAB_CPU: usize = 0;
+pub const TAB_MEMORY: usize = 1;
+pub const TAB_DISK: usize = 2;
+pub const TAB_FRAMES: usize = 3;
+pub const TAB_INTERRUPTS: usize = 4;
+const TAB_X: usize = 20;
+const TAB_GAP: usize = 30;
+const GRAPH_HEIGHT: usize = 60;
+const MAX_PROCESSES: usize = 5;
+
+static SELECTED_TAB: AtomicUsize = AtomicUsize::new(TAB_DISK);
+
+pub fn select_tab(tab: usize) {
+    SELECTED_TAB.store(tab.min(TABS.len() - 1), Ordering::Relaxed);
+}
+
+pub fn draw(graphics: &mut Graphics, x: usize, y: usize, width: usize, height: usize) {
+    let selected = SELECTED_TAB.load(Ordering::Relaxed);
+    
+    // Draw tab strip
+    graphics.draw_rect(x + 1, y, width - 2, 30, Color::new(236, 236, 236));
+    let mut tab_x = x + TAB_X;
+    for (i, tab) in TABS.iter().enumerate() {
+        let color = if i == selected { Color::BLUE } else { Color::DARK_GRAY };
+        graphics.draw_text(tab, tab_x, y + 11, color);
+        tab_x += tab.len() * 8 + TAB_GAP;
+    }
+    
+    match selected {
+        TAB_CPU => draw_cpu_panel(graphics, x + 20, y + 45, width - 40, height - 55),
+        TAB_MEMORY => draw_memory_panel(graphics, x + 20, y + 45, width - 40, height - 55),
+        TAB_FRAMES => draw_frames_panel(graphics, x + 20, y + 45, width - 40, height - 55),
+        TAB_INTERRUPTS => draw_interrupts_panel(graphics, x + 20, y + 45, width - 40, height - 55),
+        _ => draw_disk_panel(graphics, x + 20, y + 45, width - 40, height - 55),
+    }
+}
+
+// `x` and `y` are relative to the window content; clicking a tab selects it
+pub fn click(x: usize, y: usize) {
+    if y >= 30 {
+        return;
+    }
+    let mut tab_x = TAB_X;
+    for (i, tab) in TABS.iter().enumerate() {
+        if x >= tab_x && x < tab_x + tab.len() * 8 {
+            select_tab(i);
+            return;
+        }
+        tab_x += tab.len() * 8 + TAB_GAP;
+    }
+}
+
+// How busy the CPU has been each second, and who kept it busy
+fn draw_cpu_panel(graphics: &mut Graphics, x: usize, y: usize, width: usize, _height: usize) {
+    let speed = if clock::uses_tsc() { format!(" at {} MHz", clock::tsc_hz() / 1_000_000) } else { String::new() };
+    graphics.draw_text(&format!("{}{}", cpu::info().brand, speed), x, y, Color::BLACK);
+    
+    // One bar a second, newest on the right
+    let graph_y = y + 16;
+    graphics.draw_rect(x, graph_y, width, GRAPH_HEIGHT, Color::new(236, 236, 236));
+    let bar_width = (width / cpu_usage::HISTORY).max(1);
+    let history = cpu_usage::history();
+    let right = x + bar_width * cpu_usage::HISTORY;
+    for (i, &busy) in history.iter().rev().enumerate() {
+        let bar_height = GRAPH_HEIGHT * busy as usize / 100;
+        graphics.draw_rect(right - (i + 1) * bar_width, graph_y + GRAPH_HEIGHT - bar_height, bar_width, bar_height, Color::GREEN);
+    }
+    
+    let rows_y = graph_y + GRAPH_HEIGHT + 12;
+    graphics.draw_text("Process                % CPU", x, rows_y, Color::GRAY);
+    graphics.draw_rect(x, rows_y + 12, width, 1, Color::new(220, 220, 220));
+    let processes = task::list();
+    let name = |pid| processes.iter().find(|process| Some(process.pid) == pid).map_or("kernel_task", |process| process.name.as_str());
+    let tasks = cpu_usage::tasks();
+    let rows = tasks.iter().take(MAX_PROCESSES).map(|&(pid, percent)| (name(pid), percent));
+    for (i, (name, percent)) in rows.chain(core::iter::once(("idle", cpu_usage::idle_percent()))).enumerate() {
+        graphics.draw_text(&format!("{:<22} {:>5}", name, percent), x, rows_y + 20 + i * 16, Color::BLACK);
+    }
+}
+
+// Where the desktop loop's frames go, section by section
+fn draw_frames_panel(graphics: &mut Graphics, x: usize, y: usize, width: usize, _height: usize) {
+    let stats = frame_profiler::stats();
+    graphics.draw_text(
+        &format!("{} FPS   frame {} us   {} frames", stats.fps, stats.frame_time.as_micros(), stats.frames),
+        x, y, Color::BLACK,
+    );
+    
+    graphics.draw_text("Section          Last us   Avg us   Max us", x, y + 24, Color::GRAY);
+    graphics.draw_rect(x, y + 36, width, 1, Color::new(220, 220, 220));
+    let frame = stats.frame_time.as_nanos().max(1);
+    for (i, section) in stats.sections.iter().enumerate() {
+        let row = y + 44 + i * 24;
+        let line = format!(
+            "{:<15} {:>8} {:>8} {:>8}",
+            section.name, section.last.as_micros(), section.average.as_micros(), section.max.as_micros()
+        );
+        graphics.draw_text(&line, x, row, Color::BLACK);
+        // Share of the average frame spent in this section
+        let filled = (width as u128 * section.average.as_nanos() / frame).min(width as u128) as usize;
+        graphics.draw_rect(x, row + 12, width, 4, Color::LIGHT_GRAY);
+        graphics.draw_rect(x, row + 12, filled, 4, Color::GREEN);
+    }
+}
+
+// Lines in use, with how often each fired in the last second; a yellow
+// bar means some of its interrupts were nobody's
+fn draw_interrupts_panel(graphics: &mut Graphics, x: usize, y: usize, width: usize, _height: usize) {
+    graphics.draw_text("IRQ  Owners            Per sec     Total", x, y, Color::GRAY);
+    graphics.draw_rect(x, y + 12, width, 1, Color::new(220, 220, 220));
+    let lines: alloc::vec::Vec<_> = interrupts::irq_lines().into_iter().filter(|irq| !irq.owners.is_empty() || irq.count > 0).collect();
+    let vectors = interrupts::msi_vectors();
+    let busiest = lines.iter().map(|irq| irq.rate).chain(vectors.iter().map(|msi| msi.rate)).max().unwrap_or(0).max(1);
+    for (i, irq) in lines.iter().enumerate() {
+        let row_y = y + 20 + i * 24;
+        let owners = if irq.owners.is_empty() { String::from("-") } else { irq.owners.join(", ") };
+        let line = format!("{:>3}  {:<17} {:>7} {:>9}", irq.line, owners, irq.rate, irq.count);
+        graphics.draw_text(&line, x, row_y, Color::BLACK);
+        let filled = (width as u64 * irq.rate / busiest) as usize;
+        graphics.draw_rect(x, row_y + 12, width, 4, Color::LIGHT_GRAY);
+        graphics.draw_rect(x, row_y + 12, filled, 4, if irq.unclaimed > 0 { Color::YELLOW } else { Color::BLUE });
+    }
+    // Message-signalled vectors under the lines, labelled MSI
+    for (i, msi) in vectors.iter().enumerate() {
+        let row_y = y + 20 + (lines.len() + i) * 24;
+        let line = format!("MSI  {:<17} {:>7} {:>9}", msi.owner, msi.rate, msi.count);
+        graphics.draw_text(&line, x, row_y, Color::BLACK);
+        let filled = (width as u64 * msi.rate / busiest) as usize;
+        graphics.draw_rect(x, row_y + 12, width, 4, Color::LIGHT_GRAY);
+        graphics.draw_rect(x, row_y + 12, filled, 4, Color::BLUE);
+    }
+}
+
+fn draw_memory_panel(graphics: &mut Graphics, x: usize, y: usize, width: usize, _height: usize) {
+    let heap = allocator::usage();
+    graphics.draw_text(&format!("Kernel heap: {} / {} KB used", heap.used / 1024, heap.size / 1024), x, y, Color::BLACK);
+    let filled = width * heap.used / heap.size.max(1);
+    graphics.draw_rect(x, y + 16, width, 8, Color::LIGHT_GRAY);
+    graphics.draw_rect(x, y + 16, filled, 8, Color::BLUE);
+    
+    if !heap_profiler::enabled() {
+        graphics.draw_text("Build with --features heap-profiler", x, y + 40, Color::GRAY);
+        graphics.draw_text("for per-subsystem statistics", x, y + 56, Color::GRAY);
+        return;
+    }
+    
+    graphics.draw_text("Tag             Live KB  Peak KB  Allocs", x, y + 40, Color::GRAY);
+    graphics.draw_rect(x, y + 52, width, 1, Color::new(220, 220, 220));
+    for (i, (tag, stats)) in heap_profiler::tag_stats().iter().enumerate() {
+        let line = format!(
+            "{:<15} {:>7}  {:>7}  {}",
+            tag, stats.live_bytes / 1024, stats.peak_bytes / 1024, stats.allocs - stats.frees
+        );
+        graphics.draw_text(&line, x, y + 60 + i * 16, Color::BLACK);
+    }
+}
+
+fn draw_disk_panel(graphics: &mut Graphics, x: usize, y: usize, width: usize, _height: usize) {
+    graphics.draw_text("Device  Hits    Misses  Hit %  Dirty", x, y, Color::GRAY);
+    graphics.draw_rect(x, y + 12, width, 1, Color::new(220, 220, 220));
+    
+    let caches = block_cache::stats();
+    if caches.is_empty() {
+        graphics.draw_text("No disks attached", x, y + 24, Color::GRAY);
+        return;
+    }
+    
+    for (i, (name, stats)) in caches.iter().enumerate() {
+        let row_y = y + 24 + i * 40;
+        let line = format!(
+            "{:<7} {:<7} {:<7} {:>4}%  {}/{}",
+            name, stats.hits, stats.misses, stats.hit_rate(), stats.dirty, stats.cached
+        );
+        graphics.draw_text(&line, x, row_y, Color::BLACK);
+        
+        // Cache occupancy bar
+        let filled = width * stats.cached / stats.capacity.max(1);
+        graphics.draw_rect(x, row_y + 16, width, 8, Color::LIGHT_GRAY);
+        graphics.draw_rect(x, row_y + 16, filled, 8, Color::BLUE);
+    }
+}
\ No newline at end of file
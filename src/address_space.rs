@@ -0,0 +1,442 @@
+// src/address_space.rs
+// Per-process page tables. Every address space shares the kernel's mappings
+// (all level 4 entries outside the user window are copied from the boot
+// tables) and owns the user window privately. Forking shares user pages
+// copy-on-write instead of duplicating them.
+use crate::memory::{self, PAGE_SIZE};
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use crate::sync::Mutex;
+use x86_64::instructions::tlb;
+use x86_64::registers::control::{Cr3, Cr3Flags};
+use x86_64::structures::idt::PageFaultErrorCode;
+use x86_64::structures::paging::page_table::PageTableEntry;
+use x86_64::structures::paging::mapper::MapToError;
+use x86_64::structures::paging::{FrameAllocator, Page, PageTable, PageTableFlags, PhysFrame, Size4KiB};
+use x86_64::VirtAddr;
+
+// Level 4 slots 64..128 (0x2000_0000_0000..0x4000_0000_0000), clear of the
+// kernel image, the heap and the mmap window
+const USER_L4_START: usize = 64;
+const USER_L4_END: usize = 128;
+pub const USER_BASE: u64 = (USER_L4_START as u64) << 39;
+pub const USER_END: u64 = (USER_L4_END as u64) << 39;
+
+// Software-defined PTE bit marking a write-protected page as copy-on-write
+const COPY_ON_WRITE: PageTableFlags = PageTableFlags::BIT_9;
+
+// User stacks start small and grow down on demand up to their limit; the
+// page below the limit is never mapped and acts as the guard.
+pub const USER_STACK_TOP: u64 = USER_END;
+pub const USER_STACK_INITIAL: u64 = 16 * 1024;
+pub const USER_STACK_LIMIT: u64 = 8 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy)]
+struct StackRegion {
+    // Lowest address the stack may grow to
+    limit: u64,
+    top: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackFault {
+    Grown,
+    Overflow,
+}
+
+// Stack regions of every address space, keyed by the physical address of its
+// level 4 table so the fault handler can find those of the live tables
+static STACKS: Mutex<BTreeMap<u64, Vec<StackRegion>>> = Mutex::new(BTreeMap::new());
+
+// Zeroed frames for stacks to grow into, page tables included, set aside
+// before a process runs so the fault handler never goes to the frame
+// allocator. A stack that outgrows the reserve within one run faults.
+const STACK_RESERVE_FRAMES: usize = 16;
+static STACK_RESERVE: Mutex<Vec<PhysFrame>> = Mutex::new(Vec::new());
+
+struct StackReserve<'a>(&'a mut Vec<PhysFrame>);
+
+unsafe impl FrameAllocator<Size4KiB> for StackReserve<'_> {
+    fn allocate_frame(&mut self) -> Option<PhysFrame> {
+        self.0.pop()
+    }
+}
+
+fn refill_stack_reserve() {
+    let mut reserve = STACK_RESERVE.lock();
+    while reserve.len() < STACK_RESERVE_FRAMES {
+        match memory::allocate_zeroed_frame() {
+            Some(frame) => reserve.push(frame),
+            None => break,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapError {
+    OutOfRange,
+    AlreadyMapped,
+    NoMemory,
+}
+
+fn table(frame: PhysFrame) -> &'static mut PageTable {
+    unsafe { &mut *memory::phys_to_virt(frame.start_address()).as_mut_ptr() }
+}
+
+fn user_range(addr: u64, len: u64) -> bool {
+    addr >= USER_BASE && len <= USER_END - addr
+}
+
+pub struct AddressSpace {
+    level_4: PhysFrame,
+}
+
+impl AddressSpace {
+    pub fn new() -> Option<Self> {
+        let space = Self { level_4: memory::allocate_zeroed_frame()? };
+        space.sync_kernel_entries();
+        Some(space)
+    }
+    
+    // The kernel may have grown new top-level mappings (heap, mmap window)
+    // since this address space was created
+    fn sync_kernel_entries(&self) {
+        let kernel = table(memory::kernel_page_table());
+        let own = table(self.level_4);
+        for i in (0..USER_L4_START).chain(USER_L4_END..512) {
+            own[i] = kernel[i].clone();
+        }
+    }
+    
+    pub fn is_active(&self) -> bool {
+        Cr3::read().0 == self.level_4
+    }
+    
+    pub fn activate(&self) {
+        refill_stack_reserve();
+        self.sync_kernel_entries();
+        unsafe { Cr3::write(self.level_4, Cr3Flags::empty()) };
+    }
+    
+    // Back to the kernel's own tables, with no process's user window
+    pub fn activate_kernel() {
+        unsafe { Cr3::write(memory::kernel_page_table(), Cr3Flags::empty()) };
+    }
+    
+    // Maps zeroed, user-accessible pages over [addr, addr + len)
+    pub fn map_user(&mut self, addr: VirtAddr, len: u64, writable: bool) -> Result<(), MapError> {
+        if !user_range(addr.as_u64(), len) || len == 0 {
+            return Err(MapError::OutOfRange);
+        }
+        let mut flags = PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE;
+        if writable {
+            flags |= PageTableFlags::WRITABLE;
+        }
+        
+        let first = Page::containing_address(addr);
+        let last = Page::containing_address(addr + (len - 1));
+        for page in Page::range_inclusive(first, last) {
+            let frame = memory::allocate_zeroed_frame().ok_or(MapError::NoMemory)?;
+            if memory::map_page_in(self.level_4, page, frame, flags).is_err() {
+                memory::free_frame(frame);
+                return Err(MapError::AlreadyMapped);
+            }
+        }
+        Ok(())
+    }
+    
+    // Maps `frames`, which belong to someone else too, at `addr` onwards.
+    // Each gets a reference, dropped with the address space.
+    pub fn map_shared(&mut self, addr: VirtAddr, frames: &[PhysFrame], writable: bool) -> Result<(), MapError> {
+        if !user_range(addr.as_u64(), frames.len() as u64 * PAGE_SIZE) || frames.is_empty() {
+            return Err(MapError::OutOfRange);
+        }
+        let mut flags = PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE | PageTableFlags::NO_EXECUTE;
+        if writable {
+            flags |= PageTableFlags::WRITABLE;
+        }
+        
+        let first = Page::containing_address(addr);
+        for (page, frame) in Page::range(first, first + frames.len() as u64).zip(frames) {
+            memory::share_frame(*frame);
+            if memory::map_page_in(self.level_4, page, *frame, flags).is_err() {
+                memory::release_frame(*frame);
+                return Err(MapError::AlreadyMapped);
+            }
+        }
+        Ok(())
+    }
+    
+    // Reserves [top - max_size, top) for a stack and maps its initial pages
+    pub fn map_stack(&mut self, top: VirtAddr, max_size: u64) -> Result<(), MapError> {
+        let top = top.align_down(PAGE_SIZE).as_u64();
+        let max_size = (max_size + PAGE_SIZE - 1) / PAGE_SIZE * PAGE_SIZE;
+        if max_size == 0 || top < max_size + PAGE_SIZE || !user_range(top - max_size - PAGE_SIZE, max_size + PAGE_SIZE) {
+            return Err(MapError::OutOfRange);
+        }
+        
+        let initial = USER_STACK_INITIAL.min(max_size);
+        self.map_user(VirtAddr::new(top - initial), initial, true)?;
+        STACKS
+            .lock()
+            .entry(self.level_4.start_address().as_u64())
+            .or_insert_with(Vec::new)
+            .push(StackRegion { limit: top - max_size, top });
+        Ok(())
+    }
+    
+    // Visits every present 4 KiB page in the user window
+    fn for_each_user_page(&self, mut f: impl FnMut(Page, &mut PageTableEntry)) {
+        let level_4 = table(self.level_4);
+        for i4 in USER_L4_START..USER_L4_END {
+            let level_3 = match level_4[i4].frame() {
+                Ok(frame) => table(frame),
+                Err(_) => continue,
+            };
+            for i3 in 0..512 {
+                let level_2 = match level_3[i3].frame() {
+                    Ok(frame) => table(frame),
+                    Err(_) => continue,
+                };
+                for i2 in 0..512 {
+                    let level_1 = match level_2[i2].frame() {
+                        Ok(frame) => table(frame),
+                        Err(_) => continue,
+                    };
+                    for i1 in 0..512 {
+                        let entry = &mut level_1[i1];
+                        if entry.flags().contains(PageTableFlags::PRESENT) {
+                            let addr = (i4 << 39 | i3 << 30 | i2 << 21 | i1 << 12) as u64;
+                            f(Page::containing_address(VirtAddr::new(addr)), entry);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    
+    // Returns (resident, shared) page counts for the user window
+    pub fn page_counts(&self) -> (usize, usize) {
+        let mut resident = 0;
+        let mut shared = 0;
+        self.for_each_user_page(|_, entry| {
+            resident += 1;
+            if entry.frame().map_or(false, |frame| memory::frame_refs(frame) > 1) {
+                shared += 1;
+            }
+        });
+        (resident, shared)
+    }
+
+    // Every present page in the user window, with its frame and flags
+    pub fn user_pages(&self, mut f: impl FnMut(Page, PhysFrame, PageTableFlags)) {
+        self.for_each_user_page(|page, entry| {
+            if let Ok(frame) = entry.frame() {
+                f(page, frame, entry.flags());
+            }
+        });
+    }
+    
+    // Copies `contents` into the page at `page`: into the frame already
+    // mapped there, or a fresh one mapped with `flags`
+    pub fn restore_page(&mut self, page: Page, flags: PageTableFlags, contents: &[u8]) -> Result<(), MapError> {
+        if !user_range(page.start_address().as_u64(), PAGE_SIZE) {
+            return Err(MapError::OutOfRange);
+        }
+        let fresh = memory::allocate_zeroed_frame().ok_or(MapError::NoMemory)?;
+        let frame = match memory::map_page_in(self.level_4, page, fresh, flags) {
+            Ok(()) => fresh,
+            Err(MapToError::PageAlreadyMapped(frame)) => {
+                memory::free_frame(fresh);
+                frame
+            }
+            Err(_) => {
+                memory::free_frame(fresh);
+                return Err(MapError::NoMemory);
+            }
+        };
+        let len = contents.len().min(PAGE_SIZE as usize);
+        unsafe {
+            let dst: *mut u8 = memory::phys_to_virt(frame.start_address()).as_mut_ptr();
+            core::ptr::copy_nonoverlapping(contents.as_ptr(), dst, len);
+        }
+        Ok(())
+    }
+    
+    // Creates a child that shares every user page with this one. Writable
+    // pages become read-only + copy-on-write on both sides; the first write
+    // from either side gets its own copy. If the child can't be completed
+    // this address space is left as it was.
+    pub fn fork(&mut self) -> Option<AddressSpace> {
+        let child = AddressSpace::new()?;
+        
+        let mut pages = Vec::new();
+        // The pages this fork made copy-on-write, in address order
+        let mut protected = Vec::new();
+        self.for_each_user_page(|page, entry| {
+            let mut flags = entry.flags();
+            if flags.contains(PageTableFlags::WRITABLE) {
+                flags = (flags - PageTableFlags::WRITABLE) | COPY_ON_WRITE;
+                entry.set_flags(flags);
+                protected.push(page);
+            }
+            if let Ok(frame) = entry.frame() {
+                pages.push((page, frame, flags));
+            }
+        });
+        if self.is_active() {
+            tlb::flush_all();
+        }
+        
+        for (page, frame, flags) in pages {
+            memory::share_frame(frame);
+            if memory::map_page_in(child.level_4, page, frame, flags).is_err() {
+                memory::release_frame(frame);
+                // Dropping the child gives back its references, leaving the
+                // protected pages this address space's alone again
+                drop(child);
+                self.unprotect(&protected);
+                return None;
+            }
+        }
+        
+        let mut stacks = STACKS.lock();
+        if let Some(regions) = stacks.get(&self.level_4.start_address().as_u64()).cloned() {
+            stacks.insert(child.level_4.start_address().as_u64(), regions);
+        }
+        Some(child)
+    }
+    
+    // Makes `pages`, sorted, writable again rather than copy-on-write
+    fn unprotect(&mut self, pages: &[Page]) {
+        self.for_each_user_page(|page, entry| {
+            if pages.binary_search(&page).is_ok() {
+                entry.set_flags((entry.flags() - COPY_ON_WRITE) | PageTableFlags::WRITABLE);
+            }
+        });
+        if self.is_active() {
+            tlb::flush_all();
+        }
+    }
+}
+
+impl Drop for AddressSpace {
+    fn drop(&mut self) {
+        if self.is_active() {
+            unsafe { Cr3::write(memory::kernel_page_table(), Cr3Flags::empty()) };
+        }
+        STACKS.lock().remove(&self.level_4.start_address().as_u64());
+        
+        self.for_each_user_page(|_, entry| {
+            if let Ok(frame) = entry.frame() {
+                memory::release_frame(frame);
+            }
+        });
+        
+        // Free the user half of the page table tree, then the root
+        let level_4 = table(self.level_4);
+        for i4 in USER_L4_START..USER_L4_END {
+            let level_3_frame = match level_4[i4].frame() {
+                Ok(frame) => frame,
+                Err(_) => continue,
+            };
+            let level_3 = table(level_3_frame);
+            for i3 in 0..512 {
+                let level_2_frame = match level_3[i3].frame() {
+                    Ok(frame) => frame,
+                    Err(_) => continue,
+                };
+                let level_2 = table(level_2_frame);
+                for i2 in 0..512 {
+                    if let Ok(frame) = level_2[i2].frame() {
+                        memory::free_frame(frame);
+                    }
+                }
+                memory::free_frame(level_2_frame);
+            }
+            memory::free_frame(level_3_frame);
+        }
+        memory::free_frame(self.level_4);
+    }
+}
+
+// Called from the page fault handler for writes to write-protected user
+// pages. Returns true if the page was copy-on-write and is now writable.
+pub fn handle_cow_fault(addr: VirtAddr, error_code: PageFaultErrorCode) -> bool {
+    let required = PageFaultErrorCode::PROTECTION_VIOLATION | PageFaultErrorCode::CAUSED_BY_WRITE;
+    if !error_code.contains(required) || !user_range(addr.as_u64(), 1) {
+        return false;
+    }
+    
+    let page = Page::containing_address(addr);
+    let (frame, flags) = match memory::translate_page(page) {
+        Some((frame, flags)) if flags.contains(COPY_ON_WRITE) => (frame, flags),
+        _ => return false,
+    };
+    let writable = (flags - COPY_ON_WRITE) | PageTableFlags::WRITABLE;
+    
+    // Last owner: no need to copy, just take the page back. The tables
+    // above it are writable already; map_page_in makes them so.
+    if memory::frame_refs(frame) == 1 {
+        return memory::set_page_flags(page, writable);
+    }
+    
+    let copy = match memory::allocate_frame() {
+        Some(copy) => copy,
+        None => return false,
+    };
+    unsafe {
+        let src: *const u8 = memory::phys_to_virt(frame.start_address()).as_ptr();
+        let dst: *mut u8 = memory::phys_to_virt(copy.start_address()).as_mut_ptr();
+        core::ptr::copy_nonoverlapping(src, dst, PAGE_SIZE as usize);
+    }
+    memory::unmap_page(page);
+    if memory::map_page(page, copy, writable).is_err() {
+        memory::free_frame(copy);
+        return false;
+    }
+    memory::release_frame(frame);
+    true
+}
+
+// Called from the page fault handler for accesses to unmapped user pages.
+// Touching below a stack's mapped pages grows it; touching its guard page
+// is an overflow.
+pub fn handle_stack_fault(addr: VirtAddr, error_code: PageFaultErrorCode) -> Option<StackFault> {
+    if error_code.contains(PageFaultErrorCode::PROTECTION_VIOLATION) || !user_range(addr.as_u64(), 1) {
+        return None;
+    }
+    
+    let level_4 = Cr3::read().0.start_address().as_u64();
+    let region = {
+        // Never wait on the lock from a fault handler
+        let stacks = STACKS.try_lock()?;
+        let regions = stacks.get(&level_4)?;
+        *regions
+            .iter()
+            .find(|r| (r.limit - PAGE_SIZE..r.top).contains(&addr.as_u64()))?
+    };
+    if addr.as_u64() < region.limit {
+        return Some(StackFault::Overflow);
+    }
+    
+    // Fill in every page between the fault and the part already mapped
+    let flags = PageTableFlags::PRESENT
+        | PageTableFlags::WRITABLE
+        | PageTableFlags::USER_ACCESSIBLE
+        | PageTableFlags::NO_EXECUTE;
+    let mut reserve = STACK_RESERVE.try_lock()?;
+    let first = Page::containing_address(addr);
+    let last = Page::containing_address(VirtAddr::new(region.top - 1));
+    for page in Page::range_inclusive(first, last) {
+        if memory::translate_page(page).is_some() {
+            break;
+        }
+        let frame = reserve.pop()?;
+        if memory::map_page_with(Cr3::read().0, page, frame, flags, &mut StackReserve(&mut reserve)).is_err() {
+            reserve.push(frame);
+            return None;
+        }
+    }
+    Some(StackFault::Grown)
+}
\ No newline at end of file
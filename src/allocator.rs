@@ -1,4 +1,11 @@
 // src/allocator.rs
+// The kernel heap. An allocation that doesn't fit first drops clean cached
+// disk sectors, then takes the reserve kept past the end of the heap, and
+// flags the failure so the desktop can free memory for real; only once the
+// reserve is gone does a failed allocation panic.
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr;
+use core::sync::atomic::{AtomicBool, Ordering};
 use linked_list_allocator::LockedHeap;
 use x86_64::{
     structures::paging::{
@@ -7,11 +14,83 @@ use x86_64::{
     VirtAddr,
 };
 
+static HEAP: LockedHeap = LockedHeap::empty();
+// An allocation failed since the desktop last asked
+static FAILED: AtomicBool = AtomicBool::new(false);
+static RESERVE_USED: AtomicBool = AtomicBool::new(false);
+
 #[global_allocator]
-static ALLOCATOR: LockedHeap = LockedHeap::empty();
+static ALLOCATOR: KernelAllocator = KernelAllocator;
+
+// Thin wrapper over the heap so the profiler can see every allocation
+struct KernelAllocator;
+
+unsafe impl GlobalAlloc for KernelAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let mut ptr = HEAP.alloc(layout);
+        if ptr.is_null() {
+            ptr = recover(layout);
+        }
+        #[cfg(feature = "heap-profiler")]
+        if !ptr.is_null() {
+            crate::heap_profiler::record_alloc(ptr, layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        #[cfg(feature = "heap-profiler")]
+        crate::heap_profiler::record_free(ptr);
+        HEAP.dealloc(ptr, layout)
+    }
+}
+
+// Nothing here may allocate: it runs inside the allocator
+unsafe fn recover(layout: Layout) -> *mut u8 {
+    FAILED.store(true, Ordering::Relaxed);
+    if crate::block_cache::purge_clean() > 0 {
+        let ptr = HEAP.alloc(layout);
+        if !ptr.is_null() {
+            return ptr;
+        }
+    }
+    if !RESERVE_USED.swap(true, Ordering::Relaxed) {
+        HEAP.lock().extend(HEAP_RESERVE);
+        return HEAP.alloc(layout);
+    }
+    ptr::null_mut()
+}
+
+// Whether an allocation failed since the last call
+pub fn take_failure() -> bool {
+    FAILED.swap(false, Ordering::Relaxed)
+}
+
+// Once it's used, the next allocation nothing can be freed for panics
+pub fn reserve_used() -> bool {
+    RESERVE_USED.load(Ordering::Relaxed)
+}
+
+pub struct HeapUsage {
+    pub used: usize,
+    pub free: usize,
+    pub size: usize,
+}
+
+pub fn usage() -> HeapUsage {
+    let heap = HEAP.lock();
+    HeapUsage { used: heap.used(), free: heap.free(), size: heap.size() }
+}
+
+// True while some code is inside the allocator
+pub fn is_busy() -> bool {
+    HEAP.is_locked()
+}
 
 pub const HEAP_START: usize = 0x_4444_4444_0000;
 pub const HEAP_SIZE: usize = 100 * 1024; // 100 KiB
+// Mapped past the heap and only added to it when an allocation fails
+pub const HEAP_RESERVE: usize = 16 * 1024;
 
 pub fn init_heap(
     mapper: &mut impl Mapper<Size4KiB>,
@@ -19,7 +98,7 @@ pub fn init_heap(
 ) -> Result<(), MapToError<Size4KiB>> {
     let page_range = {
         let heap_start = VirtAddr::new(HEAP_START as u64);
-        let heap_end = heap_start + HEAP_SIZE - 1u64;
+        let heap_end = heap_start + HEAP_SIZE + HEAP_RESERVE - 1u64;
         let heap_start_page = Page::containing_address(heap_start);
         let heap_end_page = Page::containing_address(heap_end);
         Page::range_inclusive(heap_start_page, heap_end_page)
@@ -34,7 +113,7 @@ pub fn init_heap(
     }
 
     unsafe {
-        ALLOCATOR.lock().init(HEAP_START, HEAP_SIZE);
+        HEAP.lock().init(HEAP_START, HEAP_SIZE);
     }
 
     Ok(())
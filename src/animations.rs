@@ -1,5 +1,15 @@
 // src/animations.rs
-use crate::graphics::{Graphics, Color};
+// Animations run on the clock: a value moves from one number to another over
+// a duration in milliseconds, so they take as long however fast the desktop
+// loop spins. `then` chains more legs after the first, `reverse` heads back
+// to the start from wherever the value is now, and `cancel` stops it where
+// it stands. The completion callback runs once the last leg finishes.
+use crate::appearance;
+use crate::clock::Instant;
+use crate::pool::Pool;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::time::Duration;
 
 #[derive(Clone, Copy)]
 pub enum EasingType {
@@ -7,43 +17,24 @@ pub enum EasingType {
     EaseIn,
     EaseOut,
     Linear,
+    // Runs past the end and settles back
+    Overshoot,
+    // Bounces around the end a few times before coming to rest
+    Spring,
 }
 
-pub struct Animation {
-    pub start_value: f32,
-    pub end_value: f32,
-    pub duration: u32,
-    pub current_time: u32,
-    pub easing: EasingType,
-    pub is_complete: bool,
-}
+// How far back Overshoot pulls, the usual "back" easing constant
+const OVERSHOOT: f32 = 1.70158;
+// Spring constants in units of the whole animation, damped enough to settle
+// by the end
+const SPRING_STIFFNESS: f32 = 150.0;
+const SPRING_DAMPING: f32 = 10.0;
+const SPRING_STEPS: usize = 64;
 
-impl Animation {
-    pub fn new(start: f32, end: f32, duration: u32, easing: EasingType) -> Self {
-        Self {
-            start_value: start,
-            end_value: end,
-            duration,
-            current_time: 0,
-            easing,
-            is_complete: false,
-        }
-    }
-    
-    pub fn update(&mut self) -> f32 {
-        if self.is_complete {
-            return self.end_value;
-        }
-        
-        self.current_time += 1;
-        
-        if self.current_time >= self.duration {
-            self.is_complete = true;
-            return self.end_value;
-        }
-        
-        let t = self.current_time as f32 / self.duration as f32;
-        let progress = match self.easing {
+impl EasingType {
+    // Progress at `t` from 0 to 1; may leave 0..1 for Overshoot and Spring
+    pub fn apply(self, t: f32) -> f32 {
+        match self {
             EasingType::Linear => t,
             EasingType::EaseIn => t * t,
             EasingType::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
@@ -54,12 +45,154 @@ impl Animation {
                     1.0 - 2.0 * (1.0 - t) * (1.0 - t)
                 }
             }
-        };
-        
-        self.start_value + (self.end_value - self.start_value) * progress
+            EasingType::Overshoot => {
+                let u = t - 1.0;
+                1.0 + (OVERSHOOT + 1.0) * u * u * u + OVERSHOOT * u * u
+            }
+            EasingType::Spring => spring(t),
+        }
+    }
+}
+
+// A damped spring pulled from 0 towards 1, stepped up to `t`. There's no
+// exp or cos without std, and a few dozen steps are cheap.
+fn spring(t: f32) -> f32 {
+    let steps = (t * SPRING_STEPS as f32) as usize;
+    let dt = 1.0 / SPRING_STEPS as f32;
+    let (mut position, mut velocity) = (0.0f32, 0.0f32);
+    for _ in 0..steps {
+        velocity += (SPRING_STIFFNESS * (1.0 - position) - SPRING_DAMPING * velocity) * dt;
+        position += velocity * dt;
+    }
+    position
+}
+
+struct Leg {
+    from: f32,
+    to: f32,
+    duration: Duration,
+    easing: EasingType,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    Running,
+    Complete,
+    Cancelled,
+}
+
+pub struct Animation {
+    legs: Vec<Leg>,
+    leg: usize,
+    // When the current leg started
+    started: Instant,
+    value: f32,
+    state: State,
+    on_complete: Option<Box<dyn FnOnce() + Send>>,
+}
+
+impl Animation {
+    pub fn new(start: f32, end: f32, duration_ms: u32, easing: EasingType) -> Self {
+        let leg = Leg { from: start, to: end, duration: Duration::from_millis(duration_ms as u64), easing };
+        Self {
+            legs: alloc::vec![leg],
+            leg: 0,
+            started: Instant::now(),
+            value: start,
+            state: State::Running,
+            on_complete: None,
+        }
+    }
+    
+    // A value that goes up by `height` and comes back down
+    pub fn bounce(start: f32, height: f32, duration_ms: u32) -> Self {
+        Self::new(start, start + height, duration_ms / 2, EasingType::EaseOut)
+            .then(start, duration_ms / 2, EasingType::EaseIn)
+    }
+    
+    // Moves on to `end` once everything before has finished
+    pub fn then(mut self, end: f32, duration_ms: u32, easing: EasingType) -> Self {
+        let from = self.legs.last().map_or(self.value, |leg| leg.to);
+        self.legs.push(Leg { from, to: end, duration: Duration::from_millis(duration_ms as u64), easing });
+        self
+    }
+    
+    // Runs once, when the last leg finishes; not if it's cancelled
+    pub fn on_complete(mut self, callback: impl FnOnce() + Send + 'static) -> Self {
+        self.on_complete = Some(Box::new(callback));
+        self
+    }
+    
+    // Moves the value to where it should be now and returns it
+    pub fn update(&mut self) -> f32 {
+        while self.state == State::Running {
+            let leg = &self.legs[self.leg];
+            let elapsed = self.started.elapsed();
+            if elapsed < leg.duration {
+                let t = elapsed.as_nanos() as f32 / leg.duration.as_nanos() as f32;
+                self.value = leg.from + (leg.to - leg.from) * leg.easing.apply(t);
+                break;
+            }
+            // Legs start where the last one should have ended, not when it
+            // was noticed, so a slow frame doesn't stretch the chain
+            self.value = leg.to;
+            self.started = self.started + leg.duration;
+            self.leg += 1;
+            if self.leg == self.legs.len() {
+                self.state = State::Complete;
+                if let Some(callback) = self.on_complete.take() {
+                    callback();
+                }
+            }
+        }
+        self.value
+    }
+    
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+    
+    // Where the animation comes to rest
+    pub fn end_value(&self) -> f32 {
+        self.legs.last().map_or(self.value, |leg| leg.to)
+    }
+    
+    pub fn is_complete(&self) -> bool {
+        self.state != State::Running
+    }
+    
+    // Stops at the current value and drops the completion callback
+    pub fn cancel(&mut self) {
+        self.update();
+        self.state = State::Cancelled;
+        self.on_complete = None;
+    }
+    
+    // Heads back to the first value from where it is now, taking as long as
+    // it took to get here. Remaining legs are dropped; the callback is kept.
+    pub fn reverse(&mut self) {
+        self.update();
+        let start = self.legs[0].from;
+        let current = self.leg.min(self.legs.len() - 1);
+        let done: Duration = self.legs[..current].iter().map(|leg| leg.duration).sum();
+        let travelled = done + self.started.elapsed().min(self.legs[current].duration);
+        let easing = self.legs[current].easing;
+        self.legs = alloc::vec![Leg { from: self.value, to: start, duration: travelled, easing }];
+        self.leg = 0;
+        self.started = Instant::now();
+        self.state = State::Running;
     }
 }
 
+// Windows open from and close to this fraction of their size
+const WINDOW_ZOOM: f32 = 0.85;
+const WINDOW_OPEN_MS: u32 = 250;
+const WINDOW_CLOSE_MS: u32 = 200;
+const WINDOW_MORPH_MS: u32 = 250;
+
+// Window open/minimize animations come and go constantly; keep them pooled
+pub static WINDOW_ANIMATIONS: Pool<WindowAnimation> = Pool::new("window animations", 16);
+
 pub struct WindowAnimation {
     pub x: Animation,
     pub y: Animation,
@@ -71,21 +204,63 @@ pub struct WindowAnimation {
 impl WindowAnimation {
     pub fn minimize_to_dock(start_x: f32, start_y: f32, start_w: f32, start_h: f32, dock_x: f32, dock_y: f32) -> Self {
         Self {
-            x: Animation::new(start_x, dock_x, 30, EasingType::EaseInOut),
-            y: Animation::new(start_y, dock_y, 30, EasingType::EaseInOut),
-            width: Animation::new(start_w, 64.0, 30, EasingType::EaseInOut),
-            height: Animation::new(start_h, 64.0, 30, EasingType::EaseInOut),
-            alpha: Animation::new(1.0, 0.8, 30, EasingType::EaseOut),
+            x: Animation::new(start_x, dock_x, 500, EasingType::EaseInOut),
+            y: Animation::new(start_y, dock_y, 500, EasingType::EaseInOut),
+            width: Animation::new(start_w, 64.0, 500, EasingType::EaseInOut),
+            height: Animation::new(start_h, 64.0, 500, EasingType::EaseInOut),
+            alpha: Animation::new(1.0, 0.8, 500, EasingType::EaseOut),
         }
     }
     
     pub fn spring_open(start_x: f32, start_y: f32, end_w: f32, end_h: f32) -> Self {
         Self {
-            x: Animation::new(start_x, start_x, 20, EasingType::EaseOut),
-            y: Animation::new(start_y, start_y, 20, EasingType::EaseOut),
-            width: Animation::new(0.0, end_w, 20, EasingType::EaseOut),
-            height: Animation::new(0.0, end_h, 20, EasingType::EaseOut),
-            alpha: Animation::new(0.0, 1.0, 20, EasingType::EaseOut),
+            x: Animation::new(start_x, start_x, 350, EasingType::EaseOut),
+            y: Animation::new(start_y, start_y, 350, EasingType::EaseOut),
+            width: Animation::new(0.0, end_w, 350, EasingType::Spring),
+            height: Animation::new(0.0, end_h, 350, EasingType::Spring),
+            alpha: Animation::new(0.0, 1.0, 350, EasingType::EaseOut),
+        }
+    }
+    
+    // From one frame to another, for a window changing state, fading from
+    // `alpha.0` to `alpha.1` on the way; with Reduce motion on, only fades
+    pub fn morph(from: (f32, f32, f32, f32), to: (f32, f32, f32, f32), alpha: (f32, f32)) -> Self {
+        let from = if appearance::motion_reduced() { to } else { from };
+        let animate = |from: f32, to: f32| Animation::new(from, to, WINDOW_MORPH_MS, EasingType::EaseInOut);
+        Self {
+            x: animate(from.0, to.0),
+            y: animate(from.1, to.1),
+            width: animate(from.2, to.2),
+            height: animate(from.3, to.3),
+            alpha: animate(alpha.0, alpha.1),
+        }
+    }
+    
+    // Grows from slightly smaller while fading in; with Reduce motion on,
+    // only fades
+    pub fn open(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self::zoom(x, y, width, height, true)
+    }
+    
+    // `open` backwards
+    pub fn close(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self::zoom(x, y, width, height, false)
+    }
+    
+    fn zoom(x: f32, y: f32, width: f32, height: f32, opening: bool) -> Self {
+        let scale = if appearance::motion_reduced() { 1.0 } else { WINDOW_ZOOM };
+        let (small_width, small_height) = (width * scale, height * scale);
+        let (small_x, small_y) = (x + (width - small_width) / 2.0, y + (height - small_height) / 2.0);
+        let (duration, easing) = if opening { (WINDOW_OPEN_MS, EasingType::Overshoot) } else { (WINDOW_CLOSE_MS, EasingType::EaseIn) };
+        let animate = |small: f32, full: f32| {
+            if opening { Animation::new(small, full, duration, easing) } else { Animation::new(full, small, duration, easing) }
+        };
+        Self {
+            x: animate(small_x, x),
+            y: animate(small_y, y),
+            width: animate(small_width, width),
+            height: animate(small_height, height),
+            alpha: if opening { Animation::new(0.0, 1.0, duration, EasingType::EaseOut) } else { Animation::new(1.0, 0.0, duration, EasingType::EaseIn) },
         }
     }
     
@@ -100,7 +275,19 @@ impl WindowAnimation {
     }
     
     pub fn is_complete(&self) -> bool {
-        self.x.is_complete && self.y.is_complete && 
-        self.width.is_complete && self.height.is_complete
+        self.x.is_complete() && self.y.is_complete() &&
+        self.width.is_complete() && self.height.is_complete()
+    }
+    
+    pub fn cancel(&mut self) {
+        for animation in [&mut self.x, &mut self.y, &mut self.width, &mut self.height, &mut self.alpha] {
+            animation.cancel();
+        }
+    }
+    
+    pub fn reverse(&mut self) {
+        for animation in [&mut self.x, &mut self.y, &mut self.width, &mut self.height, &mut self.alpha] {
+            animation.reverse();
+        }
     }
 }
\ No newline at end of file
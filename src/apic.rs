@@ -0,0 +1,86 @@
+// src/apic.rs
+// The local APIC, turned on only as far as message-signalled interrupts
+// need it. A PCI device's MSI is a write to the APIC's address, which a
+// software-disabled APIC drops. The 8259s keep the legacy lines, reaching
+// the CPU through LINT0 in virtual wire mode, and the APIC timer stays
+// masked; the PIT or HPET still drives the clock.
+use crate::memory;
+use core::ptr;
+use core::sync::atomic::{AtomicU64, Ordering};
+use x86_64::registers::model_specific::Msr;
+use x86_64::PhysAddr;
+
+const APIC_BASE_MSR: u32 = 0x1B;
+const GLOBAL_ENABLE: u64 = 1 << 11;
+const X2APIC_MODE: u64 = 1 << 10;
+const BASE_MASK: u64 = 0x000F_FFFF_FFFF_F000;
+
+// Registers
+const ID: u64 = 0x020;
+const EOI: u64 = 0x0B0;
+const SPURIOUS: u64 = 0x0F0;
+const LVT_TIMER: u64 = 0x320;
+const LVT_LINT0: u64 = 0x350;
+const LVT_LINT1: u64 = 0x360;
+
+const SOFTWARE_ENABLE: u32 = 1 << 8;
+const MASKED: u32 = 1 << 16;
+const DELIVERY_NMI: u32 = 0b100 << 8;
+const DELIVERY_EXTINT: u32 = 0b111 << 8;
+
+// Where the APIC sends interrupts it raised and then withdrew; they need
+// no EOI
+pub const SPURIOUS_VECTOR: u8 = 0xFF;
+
+// Fixed-delivery messages to one processor, by its APIC ID
+const MSI_ADDRESS: u64 = 0xFEE0_0000;
+
+// The registers' virtual address, 0 until `init` turns the APIC on
+static BASE: AtomicU64 = AtomicU64::new(0);
+
+fn read(register: u64) -> u32 {
+    unsafe { ptr::read_volatile((BASE.load(Ordering::Relaxed) + register) as *const u32) }
+}
+
+fn write(register: u64, value: u32) {
+    unsafe { ptr::write_volatile((BASE.load(Ordering::Relaxed) + register) as *mut u32, value) }
+}
+
+// False when the firmware left the APIC off or in x2APIC mode, where the
+// registers aren't memory mapped; MSI isn't used then
+pub fn init() -> bool {
+    let msr = unsafe { Msr::new(APIC_BASE_MSR).read() };
+    if msr & GLOBAL_ENABLE == 0 || msr & X2APIC_MODE != 0 {
+        return false;
+    }
+    let base = match memory::map_mmio(PhysAddr::new(msr & BASE_MASK)) {
+        Some(base) => base,
+        None => return false,
+    };
+    BASE.store(base.as_u64(), Ordering::Relaxed);
+    write(LVT_TIMER, MASKED);
+    write(LVT_LINT0, DELIVERY_EXTINT);
+    write(LVT_LINT1, DELIVERY_NMI);
+    write(SPURIOUS, SOFTWARE_ENABLE | SPURIOUS_VECTOR as u32);
+    true
+}
+
+pub fn is_enabled() -> bool {
+    BASE.load(Ordering::Relaxed) != 0
+}
+
+pub fn id() -> u8 {
+    if is_enabled() { (read(ID) >> 24) as u8 } else { 0 }
+}
+
+// Ends an interrupt that came through the APIC rather than the PICs
+pub fn end_of_interrupt() {
+    if is_enabled() {
+        write(EOI, 0);
+    }
+}
+
+// The address a device writes its message to so it reaches this processor
+pub fn msi_address() -> u64 {
+    MSI_ADDRESS | (id() as u64) << 12
+}
\ No newline at end of file
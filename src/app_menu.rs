@@ -0,0 +1,127 @@
+// src/app_menu.rs
+// The menu bar's app menu, named in bold after the app in front, or after
+// RustOS when nothing is: About, Preferences…, the services that take what
+// the app has, Hide and Quit. The other menu titles follow the name, so
+// they move over when focus goes to an app with a longer one. It only
+// reports what was picked; the desktop does it.
+use crate::graphics::{Graphics, Color};
+use crate::share::Service;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+// Named in the menu bar when no app is in front
+pub const SYSTEM_NAME: &str = "RustOS";
+// The titles after the app menu's, in order
+pub const MENUS: [&str; 5] = ["File", "Edit", "View", "Window", "Help"];
+const TITLE_X: usize = 40;
+const CHAR_WIDTH: usize = 8;
+// Between titles in the menu bar
+const GAP: usize = 20;
+const WIDTH: usize = 220;
+const ROW_HEIGHT: usize = 20;
+const Y: usize = 24;
+
+#[derive(Clone, Copy)]
+pub enum AppMenuAction {
+    About,
+    Preferences,
+    Service(&'static Service),
+    Hide,
+    Quit,
+}
+
+// A title's width in the menu bar; the app menu's is a column wider, bold
+fn title_width(title: &str) -> usize {
+    title.chars().count() * CHAR_WIDTH
+}
+
+// Where `menu`'s title starts while `app` is in front
+pub fn title_x(app: Option<&str>, menu: &str) -> usize {
+    let mut x = TITLE_X + title_width(app.unwrap_or(SYSTEM_NAME)) + 1 + GAP;
+    for title in MENUS.iter().take_while(|&&title| title != menu) {
+        x += title_width(title) + GAP;
+    }
+    x
+}
+
+// The app's name, bold, where the menu bar starts its titles
+pub fn draw_title(graphics: &mut Graphics, app: Option<&str>, color: Color) {
+    let name = app.unwrap_or(SYSTEM_NAME);
+    graphics.draw_label(name, TITLE_X, 8, color);
+    graphics.draw_label(name, TITLE_X + 1, 8, color);
+}
+
+pub struct AppMenu {
+    pub is_visible: bool,
+    // App it's for; None for the system
+    app: Option<String>,
+    services: Vec<&'static Service>,
+}
+
+impl AppMenu {
+    pub fn new() -> Self {
+        Self { is_visible: false, app: None, services: Vec::new() }
+    }
+    
+    pub fn show(&mut self, app: Option<&str>, services: Vec<&'static Service>) {
+        self.app = app.map(String::from);
+        self.services = services;
+        self.is_visible = true;
+    }
+    
+    pub fn hide(&mut self) {
+        self.is_visible = false;
+    }
+    
+    pub fn title_contains(app: Option<&str>, x: usize, y: usize) -> bool {
+        x >= TITLE_X - 6 && x < TITLE_X + title_width(app.unwrap_or(SYSTEM_NAME)) + 7 && y < Y
+    }
+    
+    // Each row's label, shortcut and what picking it does; None for rows
+    // that can't be picked. The system can't be hidden or quit.
+    fn rows(&self) -> Vec<(String, &'static str, Option<AppMenuAction>)> {
+        let name = self.app.as_deref().unwrap_or(SYSTEM_NAME);
+        let for_app = |action| self.app.is_some().then_some(action);
+        let mut rows = vec![
+            (format!("About {}", name), "", Some(AppMenuAction::About)),
+            (String::from("Preferences…"), "", Some(AppMenuAction::Preferences)),
+        ];
+        if !self.services.is_empty() {
+            rows.push((String::from("Services"), "", None));
+            rows.extend(self.services.iter().map(|&service| (format!("  {}", service.name), "", Some(AppMenuAction::Service(service)))));
+        }
+        rows.push((format!("Hide {}", name), "Cmd+H", for_app(AppMenuAction::Hide)));
+        rows.push((format!("Quit {}", name), "Cmd+Q", for_app(AppMenuAction::Quit)));
+        rows
+    }
+    
+    pub fn draw(&self, graphics: &mut Graphics) {
+        if !self.is_visible {
+            return;
+        }
+        let name = self.app.as_deref().unwrap_or(SYSTEM_NAME);
+        let rows = self.rows();
+        graphics.draw_rect(TITLE_X - 6, 0, title_width(name) + 13, Y, Color::BLUE);
+        draw_title(graphics, self.app.as_deref(), Color::WHITE);
+        graphics.draw_rounded_rect(TITLE_X - 6, Y, WIDTH, ROW_HEIGHT * rows.len() + 8, Color::new(248, 248, 248));
+        graphics.draw_rect_outline(TITLE_X - 6, Y, WIDTH, ROW_HEIGHT * rows.len() + 8, Color::GRAY);
+        
+        for (i, (label, shortcut, action)) in rows.iter().enumerate() {
+            let row_y = Y + 4 + i * ROW_HEIGHT + 6;
+            let color = if action.is_some() { Color::BLACK } else { Color::GRAY };
+            graphics.draw_text(label, TITLE_X + 6, row_y, color);
+            graphics.draw_text(shortcut, TITLE_X + WIDTH - 16 - shortcut.len() * CHAR_WIDTH, row_y, Color::GRAY);
+        }
+    }
+    
+    // Any click closes the menu; None unless it picked a row that can be
+    pub fn click(&mut self, x: usize, y: usize) -> Option<AppMenuAction> {
+        self.hide();
+        if !(TITLE_X - 6..TITLE_X - 6 + WIDTH).contains(&x) || y < Y + 4 {
+            return None;
+        }
+        self.rows().get((y - Y - 4) / ROW_HEIGHT)?.2
+    }
+}
\ No newline at end of file
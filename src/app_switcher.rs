@@ -0,0 +1,152 @@
+// src/app_switcher.rs
+// The Cmd+Tab switcher: the apps with windows open in a row, the one used
+// last first, while Cmd stays down. Tab moves the highlight along and
+// Shift+Tab back; letting go of Cmd brings the highlighted app's last
+// window forward. An app with more than one window has a miniature of each
+// under its icon, and clicking one brings that window forward instead.
+use crate::apps;
+use crate::graphics::{Graphics, Color, SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::window_manager::WindowManager;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+const CELL_WIDTH: usize = 88;
+const ICON_SIZE: usize = 48;
+const PADDING: usize = 12;
+// Apps shown at once; the row moves along with the highlight
+const MAX_SHOWN: usize = 6;
+// Miniatures, two to a row under each icon
+const MINIATURE_WIDTH: usize = 38;
+const MINIATURE_HEIGHT: usize = 28;
+const MAX_MINIATURES: usize = 4;
+
+pub struct AppSwitcher {
+    pub is_visible: bool,
+    // Each app with its windows, by index in the window manager
+    apps: Vec<(String, Vec<usize>)>,
+    pub selected_index: usize,
+}
+
+impl AppSwitcher {
+    pub const fn new() -> Self {
+        Self { is_visible: false, apps: Vec::new(), selected_index: 0 }
+    }
+    
+    // Highlights the app used before the one in front, or with `backwards`
+    // the one used longest ago
+    pub fn show(&mut self, window_manager: &WindowManager, backwards: bool) {
+        self.apps = window_manager.apps_by_recency();
+        if self.apps.is_empty() {
+            return;
+        }
+        self.selected_index = if backwards { self.apps.len() - 1 } else { 1 % self.apps.len() };
+        self.is_visible = true;
+    }
+    
+    pub fn hide(&mut self) {
+        self.is_visible = false;
+    }
+    
+    pub fn move_selection(&mut self, direction: i32) {
+        if self.apps.is_empty() {
+            return;
+        }
+        self.selected_index = if direction > 0 {
+            (self.selected_index + 1) % self.apps.len()
+        } else {
+            (self.selected_index + self.apps.len() - 1) % self.apps.len()
+        };
+    }
+    
+    // Closes the switcher; the window to bring forward
+    pub fn confirm(&mut self) -> Option<usize> {
+        self.is_visible = false;
+        self.apps.get(self.selected_index).map(|(_, windows)| windows[0])
+    }
+    
+    // The window to bring forward for a click on an icon or a miniature;
+    // a click anywhere else closes the switcher
+    pub fn click(&mut self, x: usize, y: usize) -> Option<usize> {
+        let (panel_x, panel_y, first, shown) = self.layout();
+        for slot in 0..shown {
+            let (_, windows) = &self.apps[first + slot];
+            let cell_x = panel_x + PADDING + slot * CELL_WIDTH;
+            for (n, &index) in miniatures(windows).iter().enumerate() {
+                let (mini_x, mini_y) = miniature_at(cell_x, panel_y, n);
+                if x >= mini_x && x < mini_x + MINIATURE_WIDTH && y >= mini_y && y < mini_y + MINIATURE_HEIGHT {
+                    self.is_visible = false;
+                    return Some(index);
+                }
+            }
+            let icon_x = cell_x + (CELL_WIDTH - ICON_SIZE) / 2;
+            let icon_y = panel_y + PADDING;
+            if x >= icon_x && x < icon_x + ICON_SIZE && y >= icon_y && y < icon_y + ICON_SIZE + 22 {
+                self.selected_index = first + slot;
+                return self.confirm();
+            }
+        }
+        self.is_visible = false;
+        None
+    }
+    
+    // The panel's top left, the first app shown and how many are
+    fn layout(&self) -> (usize, usize, usize, usize) {
+        let shown = self.apps.len().min(MAX_SHOWN);
+        let first = (self.selected_index + 1).saturating_sub(shown);
+        let width = shown * CELL_WIDTH + 2 * PADDING;
+        ((SCREEN_WIDTH - width) / 2, (SCREEN_HEIGHT - self.height()) / 2, first, shown)
+    }
+    
+    // Taller when some app has miniatures to show
+    fn height(&self) -> usize {
+        let miniatures = self.apps.iter().map(|(_, windows)| miniatures(windows).len()).max().unwrap_or(0);
+        let rows = miniatures.div_ceil(2);
+        2 * PADDING + ICON_SIZE + 22 + rows * (MINIATURE_HEIGHT + 4)
+    }
+    
+    pub fn draw(&self, graphics: &mut Graphics, window_manager: &WindowManager) {
+        if !self.is_visible {
+            return;
+        }
+        let (panel_x, panel_y, first, shown) = self.layout();
+        let width = shown * CELL_WIDTH + 2 * PADDING;
+        graphics.draw_rounded_rect(panel_x, panel_y, width, self.height(), Color::new(50, 50, 50));
+        for slot in 0..shown {
+            let (name, windows) = &self.apps[first + slot];
+            let cell_x = panel_x + PADDING + slot * CELL_WIDTH;
+            let icon_x = cell_x + (CELL_WIDTH - ICON_SIZE) / 2;
+            let icon_y = panel_y + PADDING;
+            if first + slot == self.selected_index {
+                graphics.draw_rounded_rect(cell_x + 2, icon_y - 6, CELL_WIDTH - 4, ICON_SIZE + 30, Color::new(90, 90, 90));
+            }
+            graphics.draw_rounded_rect(icon_x, icon_y, ICON_SIZE, ICON_SIZE, Color::new(240, 240, 240));
+            match apps::find(name) {
+                Some(app) => graphics.draw_text(app.icon, icon_x + ICON_SIZE / 4, icon_y + ICON_SIZE / 4, Color::BLACK),
+                None => {
+                    let initial: String = name.chars().take(1).collect();
+                    graphics.draw_text(&initial, icon_x + (ICON_SIZE - 8) / 2, icon_y + (ICON_SIZE - 16) / 2, Color::BLACK);
+                }
+            }
+            let label: String = name.chars().take(CELL_WIDTH / 8 - 1).collect();
+            graphics.draw_text(&label, cell_x + (CELL_WIDTH - label.chars().count() * 8) / 2, icon_y + ICON_SIZE + 6, Color::WHITE);
+            
+            for (n, &index) in miniatures(windows).iter().enumerate() {
+                let Some(window) = window_manager.windows().get(index) else { continue };
+                let (mini_x, mini_y) = miniature_at(cell_x, panel_y, n);
+                window.draw_thumbnail(graphics, mini_x, mini_y, MINIATURE_WIDTH, MINIATURE_HEIGHT);
+            }
+        }
+    }
+}
+
+// The windows an app gets miniatures for: none unless it has several
+fn miniatures(windows: &[usize]) -> &[usize] {
+    if windows.len() > 1 { &windows[..windows.len().min(MAX_MINIATURES)] } else { &[] }
+}
+
+// Where the `n`th miniature under the app in the cell at `cell_x` goes
+fn miniature_at(cell_x: usize, panel_y: usize, n: usize) -> (usize, usize) {
+    let x = cell_x + (CELL_WIDTH - 2 * MINIATURE_WIDTH - 4) / 2 + (n % 2) * (MINIATURE_WIDTH + 4);
+    let y = panel_y + PADDING + ICON_SIZE + 22 + (n / 2) * (MINIATURE_HEIGHT + 4);
+    (x, y)
+}
\ No newline at end of file
@@ -0,0 +1,153 @@
+// src/appearance.rs
+// Light or Dark appearance, the desktop wallpaper color and the Reduce
+// motion accessibility setting, kept in the com.rustos.appearance
+// preferences and switched in System Preferences. /etc/desktop.conf can
+// set the appearance too, and a theme= boot argument wins over both for
+// that boot. With Reduce motion on,
+// transitions fade in place instead of sliding or zooming. An image made
+// the wallpaper lends it its average color; the screen has sixteen.
+use crate::bmp;
+use crate::bootargs::{self, Theme};
+use crate::color_picker;
+use crate::energy_saver;
+use crate::graphics::{Graphics, Color};
+use crate::kwarn;
+use crate::preferences;
+use crate::undo::{self, SetValue};
+use crate::vfs::{self, VfsError};
+use crate::widgets::{Button, Checkbox};
+use alloc::string::String;
+use core::sync::atomic::{AtomicBool, Ordering};
+use crate::sync::Mutex;
+
+const DOMAIN: &str = "com.rustos.appearance";
+const DARK_KEY: &str = "Dark";
+const REDUCE_MOTION_KEY: &str = "ReduceMotion";
+const WALLPAPER_KEY: &str = "WallpaperColor";
+
+// Where the settings sit in the System Preferences window, right of Sharing
+const PREFERENCE_X: usize = 300;
+const PREFERENCE_Y: usize = 236;
+const WALLPAPER: Button = Button::new("Wallpaper...");
+
+static DARK: AtomicBool = AtomicBool::new(false);
+static REDUCE_MOTION: AtomicBool = AtomicBool::new(false);
+// None keeps the appearance's own
+static WALLPAPER_COLOR: Mutex<Option<Color>> = Mutex::new(None);
+
+pub fn load() {
+    let dark = match bootargs::theme() {
+        Some(theme) => theme == Theme::Dark,
+        None => preferences::get_bool(DOMAIN, DARK_KEY, false),
+    };
+    DARK.store(dark, Ordering::Relaxed);
+    REDUCE_MOTION.store(preferences::get_bool(DOMAIN, REDUCE_MOTION_KEY, false), Ordering::Relaxed);
+    *WALLPAPER_COLOR.lock() = preferences::get(DOMAIN, WALLPAPER_KEY).and_then(|hex| color_picker::parse_hex(&hex));
+}
+
+// The theme /etc/desktop.conf asks for. It isn't saved, and a theme= boot
+// argument still wins.
+pub fn set_theme_for_boot(theme: Theme) {
+    if bootargs::theme().is_none() {
+        DARK.store(theme == Theme::Dark, Ordering::Relaxed);
+    }
+}
+
+pub fn theme() -> Theme {
+    if DARK.load(Ordering::Relaxed) { Theme::Dark } else { Theme::Light }
+}
+
+pub fn reduce_motion() -> bool {
+    REDUCE_MOTION.load(Ordering::Relaxed)
+}
+
+// Whether transitions should stay in place: Reduce motion is on, or Energy
+// Saver skips animations and the machine is on battery
+pub fn motion_reduced() -> bool {
+    reduce_motion() || energy_saver::reduce_animations()
+}
+
+// The wallpaper color picked in System Preferences, if any
+pub fn wallpaper() -> Option<Color> {
+    *WALLPAPER_COLOR.lock()
+}
+
+// The wallpaper each appearance comes with
+pub fn wallpaper_for(theme: Theme) -> Color {
+    match theme {
+        Theme::Light => Color::new(30, 130, 180),
+        Theme::Dark => Color::new(20, 30, 60),
+    }
+}
+
+fn set_wallpaper(color: Option<Color>) -> Result<(), VfsError> {
+    *WALLPAPER_COLOR.lock() = color;
+    preferences::set(DOMAIN, WALLPAPER_KEY, &color.map_or_else(String::new, color_picker::hex))
+}
+
+fn store(setting: &AtomicBool, key: &str, value: bool) -> Result<(), VfsError> {
+    setting.store(value, Ordering::Relaxed);
+    preferences::set_bool(DOMAIN, key, value)
+}
+
+fn set_dark(dark: bool) -> Result<(), VfsError> {
+    store(&DARK, DARK_KEY, dark)
+}
+
+fn set_reduce_motion(reduce: bool) -> Result<(), VfsError> {
+    store(&REDUCE_MOTION, REDUCE_MOTION_KEY, reduce)
+}
+
+// Changes go through System Preferences' undo history
+fn change<T: Clone + Send + 'static>(change: SetValue<T>) {
+    let name = change.name;
+    if let Err(err) = undo::perform(preferences::APP, change) {
+        kwarn!("appearance: cannot save {}: {:?}", name, err);
+    }
+}
+
+// The Set as Wallpaper service
+pub fn set_wallpaper_from_image(path: &str) {
+    let image = match vfs::read_file(path).map(|data| bmp::decode(&data)) {
+        Ok(Ok(image)) if !image.pixels().is_empty() => image,
+        _ => {
+            kwarn!("appearance: cannot use {} as the wallpaper", path);
+            return;
+        }
+    };
+    let count = image.pixels().len() as u64;
+    let (r, g, b) = image.pixels().iter().fold((0u64, 0u64, 0u64), |(r, g, b), pixel| {
+        (r + pixel.r as u64, g + pixel.g as u64, b + pixel.b as u64)
+    });
+    let average = Color::new((r / count) as u8, (g / count) as u8, (b / count) as u8);
+    change(SetValue { name: "Wallpaper Color", set: set_wallpaper, old: wallpaper(), new: Some(average) });
+}
+
+fn toggle(name: &'static str, setting: &AtomicBool, set: fn(bool) -> Result<(), VfsError>) {
+    let old = setting.load(Ordering::Relaxed);
+    change(SetValue { name, set, old, new: !old });
+}
+
+pub fn draw_preference(graphics: &mut Graphics, x: usize, y: usize) {
+    let (x, y) = (x + PREFERENCE_X, y + PREFERENCE_Y);
+    graphics.draw_text("Appearance", x, y, Color::BLACK);
+    Checkbox { label: "Dark", checked: theme() == Theme::Dark }.draw(graphics, x, y + 18);
+    Checkbox { label: "Reduce motion", checked: reduce_motion() }.draw(graphics, x, y + 36);
+    WALLPAPER.draw(graphics, x, y + 54);
+    let swatch = wallpaper().unwrap_or(wallpaper_for(theme()));
+    graphics.draw_rect(x + WALLPAPER.width() + 6, y + 55, 16, 16, swatch);
+}
+
+// `x` and `y` are relative to the window content
+pub fn click_preference(x: usize, y: usize) {
+    if Checkbox::new("Dark").contains(x, y, PREFERENCE_X, PREFERENCE_Y + 18) {
+        toggle("Dark Appearance", &DARK, set_dark);
+    } else if Checkbox::new("Reduce motion").contains(x, y, PREFERENCE_X, PREFERENCE_Y + 36) {
+        toggle("Reduce Motion", &REDUCE_MOTION, set_reduce_motion);
+    } else if WALLPAPER.contains(x, y, PREFERENCE_X, PREFERENCE_Y + 54) {
+        let old = wallpaper();
+        color_picker::pick(old.unwrap_or(wallpaper_for(theme())), move |color| {
+            change(SetValue { name: "Wallpaper Color", set: set_wallpaper, old, new: Some(color) });
+        });
+    }
+}
\ No newline at end of file
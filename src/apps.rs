@@ -0,0 +1,79 @@
+// src/apps.rs
+// The applications the desktop can launch: their names, icons and the
+// window a launch opens. Every app is built into the kernel, so an app runs
+// for as long as it has a window; there's no loader for programs on disk.
+// The dock shows the apps marked for it, in this order. Windows carry the
+// AppId of the app that opened them, so an app's windows can be hidden and
+// brought back together.
+use crate::graphics::Color;
+use crate::window_manager::Window;
+use alloc::string::String;
+
+pub struct App {
+    pub name: &'static str,
+    pub icon: &'static str,
+    // Shown under the name in Spotlight
+    pub category: &'static str,
+    pub in_dock: bool,
+    // The window a launch opens
+    title: &'static str,
+    frame: (usize, usize, usize, usize),
+    background: Color,
+}
+
+// An app's place in APPS
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AppId(usize);
+
+impl App {
+    pub fn id(&self) -> AppId {
+        AppId(APPS.iter().position(|app| app.name == self.name).unwrap_or(0))
+    }
+    
+    pub fn window(&self) -> Window {
+        let (x, y, width, height) = self.frame;
+        let mut window = Window::new(String::from(self.title), x, y, width, height, self.background);
+        window.app = Some(self.id());
+        window
+    }
+}
+
+const fn app(name: &'static str, icon: &'static str, category: &'static str, in_dock: bool, title: &'static str, frame: (usize, usize, usize, usize), background: Color) -> App {
+    App { name, icon, category, in_dock, title, frame, background }
+}
+
+pub const APPS: [App; 15] = [
+    app("Finder", "📁", "System", true, "Finder", (80, 80, 500, 350), Color::WHITE),
+    app("Safari", "🌐", "Applications", true, "Safari — RustOS Documentation", (120, 60, 520, 400), Color::WHITE),
+    app("Mail", "📧", "Applications", true, "Mail", (70, 50, 560, 360), Color::WHITE),
+    app("Calendar", "📅", "Applications", true, "Calendar", (130, 70, 440, 320), Color::WHITE),
+    app("Music", "🎵", "Applications", true, "Music", (140, 80, 440, 320), Color::WHITE),
+    app("Photos", "📸", "Applications", true, "Photos", (150, 90, 440, 320), Color::WHITE),
+    app("System Preferences", "⚙️", "System", true, "System Preferences", (110, 30, 460, 410), Color::new(248, 248, 248)),
+    app("Terminal", "💻", "Utilities", false, "Terminal — zsh — 80×24", (200, 120, 450, 300), Color::new(40, 44, 52)),
+    app("Activity Monitor", "📊", "Utilities", false, "Activity Monitor", (100, 90, 420, 300), Color::WHITE),
+    app("Disk Utility", "💽", "Utilities", false, "Disk Utility", (120, 70, 500, 330), Color::WHITE),
+    app("Console", "📋", "Utilities", false, "Console", (60, 70, 520, 340), Color::WHITE),
+    app("Keychain Access", "🔑", "Utilities", false, "Keychain Access", (130, 80, 460, 300), Color::WHITE),
+    app("Snake", "🐍", "Games", false, "Snake", (150, 100, 320, 280), Color::WHITE),
+    app("Preview", "🖼️", "Applications", false, "Preview", (90, 50, 460, 360), Color::WHITE),
+    app("Document Viewer", "📄", "Applications", false, "Document Viewer", (110, 60, 460, 360), Color::WHITE),
+];
+
+pub fn find(name: &str) -> Option<&'static App> {
+    APPS.iter().find(|app| app.name == name)
+}
+
+pub fn get(id: AppId) -> &'static App {
+    &APPS[id.0]
+}
+
+pub fn dock() -> impl Iterator<Item = &'static App> {
+    APPS.iter().filter(|app| app.in_dock)
+}
+
+// Apps with a word of their name starting with `query`, ignoring case
+pub fn search(query: &str) -> impl Iterator<Item = &'static App> {
+    let query = query.to_lowercase();
+    APPS.iter().filter(move |app| app.name.to_lowercase().split(' ').any(|word| word.starts_with(&query)))
+}
\ No newline at end of file
@@ -0,0 +1,397 @@
+// src/archive.rs
+// Making and unpacking .tar and .zip archives on the VFS, for the Finder's
+// Compress and Extract Here and the shell's `tar`. The work is a job that
+// goes an entry per step, so the Finder can show its progress and run it a
+// few steps a frame; data goes through a small buffer instead of the whole
+// archive sitting on the heap. Zip archives are written without
+// compression and only uncompressed ones can be extracted. Symlinks are
+// left out of archives that are made.
+use crate::tar::{self, EntryKind};
+use crate::vfs::{self, FileType, VfsError};
+use crate::zip::{self, ZipEntry};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+// Bytes copied per read
+const CHUNK: usize = 1024;
+// How much of a zip's end is read looking for its end record: first a
+// little, then as much as a record with the longest comment takes
+const ZIP_TAIL: u64 = 1024;
+const ZIP_TAIL_MAX: u64 = 22 + 65535;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Tar,
+    Zip,
+}
+
+impl Format {
+    pub fn of(path: &str) -> Option<Format> {
+        let path = path.to_lowercase();
+        if path.ends_with(".tar") {
+            Some(Format::Tar)
+        } else if path.ends_with(".zip") {
+            Some(Format::Zip)
+        } else {
+            None
+        }
+    }
+    
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Tar => ".tar",
+            Format::Zip => ".zip",
+        }
+    }
+}
+
+// "name.zip", or "name 2.zip" and so on if that's taken
+pub fn unique_path(dir: &str, name: &str, extension: &str) -> String {
+    let mut path = vfs::join(dir, &format!("{}{}", name, extension));
+    let mut n = 2;
+    while vfs::metadata(&path).is_ok() {
+        path = vfs::join(dir, &format!("{} {}{}", name, n, extension));
+        n += 1;
+    }
+    path
+}
+
+// The name without its archive extension
+pub fn stem(name: &str) -> &str {
+    match Format::of(name) {
+        Some(format) => &name[..name.len() - format.extension().len()],
+        None => name,
+    }
+}
+
+// Something going into an archive: where it is, and its path inside
+struct Item {
+    source: String,
+    path: String,
+    file_type: FileType,
+}
+
+enum Work {
+    Compress { format: Format, items: Vec<Item>, entries: Vec<ZipEntry> },
+    ExtractTar,
+    ExtractZip { entries: Vec<ZipEntry> },
+}
+
+pub struct Job {
+    // "Compressing" or "Extracting"
+    pub verb: &'static str,
+    archive: String,
+    // The folder entries are extracted into
+    destination: String,
+    work: Work,
+    // Where the next read or write in the archive goes
+    offset: u64,
+    archive_size: u64,
+    // Entries done
+    done: usize,
+}
+
+fn walk(source: &str, path: &str, items: &mut Vec<Item>) -> Result<(), VfsError> {
+    let file_type = vfs::metadata(source)?.file_type;
+    match file_type {
+        FileType::Directory => {
+            items.push(Item { source: String::from(source), path: format!("{}/", path), file_type });
+            for entry in vfs::read_dir(source)? {
+                walk(&vfs::join(source, &entry.name), &format!("{}/{}", path, entry.name), items)?;
+            }
+        }
+        FileType::File => items.push(Item { source: String::from(source), path: String::from(path), file_type }),
+        FileType::Symlink | FileType::Other => {}
+    }
+    Ok(())
+}
+
+// Copies `len` bytes between files; the CRC-32 of them
+fn copy(from: &str, from_offset: u64, to: &str, to_offset: u64, len: u64) -> Result<u32, VfsError> {
+    let mut buf = [0u8; CHUNK];
+    let (mut copied, mut crc) = (0, 0);
+    while copied < len {
+        let want = (len - copied).min(CHUNK as u64) as usize;
+        let read = vfs::read(from, from_offset + copied, &mut buf[..want])?;
+        if read == 0 {
+            return Err(VfsError::Corrupt);
+        }
+        vfs::write(to, to_offset + copied, &buf[..read])?;
+        crc = zip::crc32(crc, &buf[..read]);
+        copied += read as u64;
+    }
+    Ok(crc)
+}
+
+fn crc_of(path: &str, len: u64) -> Result<u32, VfsError> {
+    let mut buf = [0u8; CHUNK];
+    let (mut offset, mut crc) = (0, 0);
+    while offset < len {
+        let read = vfs::read(path, offset, &mut buf)?;
+        if read == 0 {
+            break;
+        }
+        crc = zip::crc32(crc, &buf[..read]);
+        offset += read as u64;
+    }
+    Ok(crc)
+}
+
+fn read_exact(path: &str, offset: u64, len: usize) -> Result<Vec<u8>, VfsError> {
+    let mut buf = vec![0u8; len];
+    if vfs::read(path, offset, &mut buf)? != len {
+        return Err(VfsError::Corrupt);
+    }
+    Ok(buf)
+}
+
+// Where an entry of the archive goes, refusing paths that climb out of it
+fn destination_of(destination: &str, path: &str) -> Result<String, VfsError> {
+    let path = path.trim_start_matches('/');
+    if path.split('/').any(|part| part == "..") {
+        return Err(VfsError::InvalidPath);
+    }
+    Ok(vfs::join(destination, path.trim_end_matches('/')))
+}
+
+// The zip's entries, from its central directory
+fn zip_entries(archive: &str, size: u64) -> Result<Vec<ZipEntry>, VfsError> {
+    let mut found = None;
+    for tail_len in [size.min(ZIP_TAIL), size.min(ZIP_TAIL_MAX)] {
+        let tail = read_exact(archive, size - tail_len, tail_len as usize)?;
+        found = zip::find_central_directory(&tail);
+        if found.is_some() || tail_len == size {
+            break;
+        }
+    }
+    let (directory_size, directory_offset) = found.ok_or(VfsError::Corrupt)?;
+    // Sizes from the archive itself; it can't hold more than it is
+    if directory_offset as u64 + directory_size as u64 > size {
+        return Err(VfsError::Corrupt);
+    }
+    let directory = read_exact(archive, directory_offset as u64, directory_size as usize)?;
+    zip::parse_central_directory(&directory).ok_or(VfsError::Corrupt)
+}
+
+// The paths inside an archive, for `tar -t`
+pub fn list(archive: &str) -> Result<Vec<String>, VfsError> {
+    let size = vfs::metadata(archive)?.size;
+    match Format::of(archive).ok_or(VfsError::Unsupported)? {
+        Format::Zip => Ok(zip_entries(archive, size)?.into_iter().map(|entry| entry.path).collect()),
+        Format::Tar => {
+            let mut paths = Vec::new();
+            let mut offset = 0;
+            while offset + tar::BLOCK as u64 <= size {
+                let header = match tar::parse_header(&read_exact(archive, offset, tar::BLOCK)?) {
+                    Some(header) => header,
+                    None => break,
+                };
+                offset += tar::BLOCK as u64 + tar::padded(header.size);
+                if header.kind.is_some() {
+                    paths.push(header.path);
+                }
+            }
+            Ok(paths)
+        }
+    }
+}
+
+impl Job {
+    // Archives `sources`, each under its own name, into a new file at
+    // `archive` in the format its extension names
+    pub fn compress(sources: &[String], archive: &str) -> Result<Job, VfsError> {
+        let format = Format::of(archive).ok_or(VfsError::Unsupported)?;
+        let mut items = Vec::new();
+        for source in sources {
+            walk(source, vfs::parent_and_name(source).1, &mut items)?;
+        }
+        vfs::create(archive, FileType::File)?;
+        Ok(Job {
+            verb: "Compressing",
+            archive: String::from(archive),
+            destination: String::new(),
+            work: Work::Compress { format, items, entries: Vec::new() },
+            offset: 0,
+            archive_size: 0,
+            done: 0,
+        })
+    }
+    
+    // Unpacks `archive` into the folder `destination`, made if needed
+    pub fn extract(archive: &str, destination: &str) -> Result<Job, VfsError> {
+        let archive_size = vfs::metadata(archive)?.size;
+        let work = match Format::of(archive).ok_or(VfsError::Unsupported)? {
+            Format::Tar => Work::ExtractTar,
+            Format::Zip => Work::ExtractZip { entries: zip_entries(archive, archive_size)? },
+        };
+        vfs::create_dir_all(destination)?;
+        Ok(Job {
+            verb: "Extracting",
+            archive: String::from(archive),
+            destination: String::from(destination),
+            work,
+            offset: 0,
+            archive_size,
+            done: 0,
+        })
+    }
+    
+    // The archive being made or unpacked
+    pub fn archive(&self) -> &str {
+        &self.archive
+    }
+    
+    // What's left when the job is done: the archive made, or the folder
+    // extracted into
+    pub fn output(&self) -> &str {
+        match self.work {
+            Work::Compress { .. } => &self.archive,
+            _ => &self.destination,
+        }
+    }
+    
+    // From 0 to 1
+    pub fn progress(&self) -> f32 {
+        let (done, total) = match &self.work {
+            Work::Compress { items, .. } => (self.done, items.len()),
+            Work::ExtractZip { entries } => (self.done, entries.len()),
+            Work::ExtractTar => (self.offset as usize, self.archive_size as usize),
+        };
+        if total == 0 { 1.0 } else { done as f32 / total as f32 }
+    }
+    
+    // Does the next entry; true once the job is done
+    pub fn step(&mut self) -> Result<bool, VfsError> {
+        match &mut self.work {
+            Work::Compress { format, items, entries } => match items.get(self.done) {
+                Some(item) => {
+                    self.offset += match format {
+                        Format::Tar => write_tar_item(&self.archive, self.offset, item)?,
+                        Format::Zip => write_zip_item(&self.archive, self.offset, item, entries)?,
+                    };
+                    self.done += 1;
+                    Ok(false)
+                }
+                None => {
+                    let trailer = match format {
+                        Format::Tar => vec![0u8; 2 * tar::BLOCK],
+                        Format::Zip => zip::central_directory(entries, self.offset as u32),
+                    };
+                    vfs::write(&self.archive, self.offset, &trailer)?;
+                    Ok(true)
+                }
+            },
+            Work::ExtractTar => {
+                if self.offset + tar::BLOCK as u64 > self.archive_size {
+                    return Ok(true);
+                }
+                let header = match tar::parse_header(&read_exact(&self.archive, self.offset, tar::BLOCK)?) {
+                    Some(header) => header,
+                    None => {
+                        self.offset = self.archive_size;
+                        return Ok(true);
+                    }
+                };
+                let data_offset = self.offset + tar::BLOCK as u64;
+                self.offset = data_offset + tar::padded(header.size);
+                let path = destination_of(&self.destination, &header.path)?;
+                match header.kind {
+                    Some(EntryKind::Directory) => vfs::create_dir_all(&path)?,
+                    Some(EntryKind::File) => {
+                        vfs::create_dir_all(vfs::parent_and_name(&path).0)?;
+                        vfs::write_file(&path, &[])?;
+                        copy(&self.archive, data_offset, &path, 0, header.size)?;
+                    }
+                    // Extension headers, links and devices are skipped
+                    _ => return Ok(false),
+                }
+                finish_entry(&path, header.mode, header.mtime);
+                self.done += 1;
+                Ok(false)
+            }
+            Work::ExtractZip { entries } => {
+                let entry = match entries.get(self.done) {
+                    Some(entry) => entry,
+                    None => return Ok(true),
+                };
+                let path = destination_of(&self.destination, &entry.path)?;
+                if entry.is_dir() {
+                    vfs::create_dir_all(&path)?;
+                } else if entry.method != zip::STORED {
+                    return Err(VfsError::Unsupported);
+                } else {
+                    let local_header = read_exact(&self.archive, entry.offset as u64, zip::LOCAL_HEADER_SIZE)?;
+                    let data_offset = zip::data_offset(entry, &local_header).ok_or(VfsError::Corrupt)?;
+                    vfs::create_dir_all(vfs::parent_and_name(&path).0)?;
+                    vfs::write_file(&path, &[])?;
+                    if copy(&self.archive, data_offset, &path, 0, entry.size as u64)? != entry.crc {
+                        return Err(VfsError::Corrupt);
+                    }
+                }
+                finish_entry(&path, entry.mode, entry.mtime);
+                self.done += 1;
+                Ok(false)
+            }
+        }
+    }
+    
+    // Stops making the archive and removes what was written of it;
+    // entries already extracted stay
+    pub fn cancel(self) {
+        if let Work::Compress { .. } = self.work {
+            let _ = vfs::remove(&self.archive);
+        }
+    }
+}
+
+// Restores what the archive kept about the entry, where it kept anything
+fn finish_entry(path: &str, mode: u32, mtime: u64) {
+    if mode & 0o777 != 0 {
+        let _ = vfs::chmod(path, mode as u16);
+    }
+    let _ = vfs::set_times(path, mtime, mtime);
+}
+
+// A header and the data padded to whole blocks; the bytes written
+fn write_tar_item(archive: &str, offset: u64, item: &Item) -> Result<u64, VfsError> {
+    let meta = vfs::metadata(&item.source)?;
+    let (kind, size) = match item.file_type {
+        FileType::Directory => (EntryKind::Directory, 0),
+        _ => (EntryKind::File, meta.size),
+    };
+    let header = tar::build_header(item.path.trim_end_matches('/'), kind, meta.mode as u32, size, meta.modified).ok_or(VfsError::InvalidPath)?;
+    vfs::write(archive, offset, &header)?;
+    copy(&item.source, 0, archive, offset + tar::BLOCK as u64, size)?;
+    let padding = tar::padded(size) - size;
+    if padding > 0 {
+        vfs::write(archive, offset + tar::BLOCK as u64 + size, &[0u8; tar::BLOCK][..padding as usize])?;
+    }
+    Ok(tar::BLOCK as u64 + tar::padded(size))
+}
+
+// A local header and the data, stored as it is; the bytes written. The
+// entry is kept for the central directory.
+fn write_zip_item(archive: &str, offset: u64, item: &Item, entries: &mut Vec<ZipEntry>) -> Result<u64, VfsError> {
+    let meta = vfs::metadata(&item.source)?;
+    let size = if item.file_type == FileType::Directory { 0 } else { meta.size };
+    if size > u32::MAX as u64 || offset > u32::MAX as u64 {
+        return Err(VfsError::NoSpace);
+    }
+    let entry = ZipEntry {
+        path: item.path.clone(),
+        mode: meta.mode as u32 | if item.file_type == FileType::Directory { 0o040000 } else { 0o100000 },
+        mtime: meta.modified,
+        crc: crc_of(&item.source, size)?,
+        compressed_size: size as u32,
+        size: size as u32,
+        method: zip::STORED,
+        offset: offset as u32,
+    };
+    let header = zip::local_header(&entry);
+    vfs::write(archive, offset, &header)?;
+    copy(&item.source, 0, archive, offset + header.len() as u64, size)?;
+    entries.push(entry);
+    Ok(header.len() as u64 + size)
+}
\ No newline at end of file
@@ -0,0 +1,240 @@
+// src/ata.rs
+// PIO driver for ATAPI optical drives on the legacy IDE channels, which is
+// where QEMU and VirtualBox attach `-cdrom` / the boot ISO. The drive uses
+// 2048-byte blocks; they are exposed as 512-byte sectors like every other
+// BlockDevice.
+use crate::block::{self, BlockDevice, BlockError, SECTOR_SIZE};
+use crate::kinfo;
+use alloc::format;
+use alloc::string::String;
+use x86_64::instructions::port::Port;
+
+pub const ATAPI_BLOCK_SIZE: usize = 2048;
+const SECTORS_PER_BLOCK: u64 = (ATAPI_BLOCK_SIZE / SECTOR_SIZE) as u64;
+
+const REG_DATA: u16 = 0;
+const REG_FEATURES: u16 = 1;
+const REG_LBA_MID: u16 = 4;
+const REG_LBA_HIGH: u16 = 5;
+const REG_DRIVE: u16 = 6;
+const REG_COMMAND: u16 = 7;
+
+const CMD_PACKET: u8 = 0xA0;
+const CMD_DEVICE_RESET: u8 = 0x08;
+
+const STATUS_ERR: u8 = 0x01;
+const STATUS_DRQ: u8 = 0x08;
+const STATUS_BSY: u8 = 0x80;
+
+const SCSI_READ_CAPACITY: u8 = 0x25;
+const SCSI_READ_12: u8 = 0xA8;
+
+const POLL_LIMIT: u32 = 1_000_000;
+
+const CHANNELS: [(u16, u16); 2] = [(0x1F0, 0x3F6), (0x170, 0x376)];
+
+pub struct AtapiDrive {
+    name: String,
+    io_base: u16,
+    control: u16,
+    slave: bool,
+    blocks: u64,
+    // Last block read, since filesystems usually walk one block sector by sector
+    cached_block: Option<u64>,
+    block_buf: [u8; ATAPI_BLOCK_SIZE],
+}
+
+impl AtapiDrive {
+    fn probe(io_base: u16, control: u16, slave: bool, index: usize) -> Option<Self> {
+        let mut drive = Self {
+            name: format!("cd{}", index),
+            io_base,
+            control,
+            slave,
+            blocks: 0,
+            cached_block: None,
+            block_buf: [0; ATAPI_BLOCK_SIZE],
+        };
+        
+        unsafe {
+            // Polled operation: keep nIEN set
+            Port::<u8>::new(control).write(0x02);
+            drive.select();
+            if drive.status() == 0xFF {
+                return None; // floating bus, no channel
+            }
+            Port::<u8>::new(io_base + REG_COMMAND).write(CMD_DEVICE_RESET);
+        }
+        drive.wait_not_busy().ok()?;
+        
+        // ATAPI devices leave their signature in the LBA mid/high registers
+        let (mid, high) = unsafe {
+            (
+                Port::<u8>::new(io_base + REG_LBA_MID).read(),
+                Port::<u8>::new(io_base + REG_LBA_HIGH).read(),
+            )
+        };
+        if (mid, high) != (0x14, 0xEB) {
+            return None;
+        }
+        
+        let mut capacity = [0u8; 8];
+        drive.packet(&[SCSI_READ_CAPACITY, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], &mut capacity).ok()?;
+        let last_lba = u32::from_be_bytes([capacity[0], capacity[1], capacity[2], capacity[3]]);
+        drive.blocks = last_lba as u64 + 1;
+        Some(drive)
+    }
+    
+    fn select(&mut self) {
+        unsafe {
+            Port::<u8>::new(self.io_base + REG_DRIVE).write(if self.slave { 0xB0 } else { 0xA0 });
+            // 400ns settle time: four reads of the alternate status register
+            for _ in 0..4 {
+                Port::<u8>::new(self.control).read();
+            }
+        }
+    }
+    
+    fn status(&self) -> u8 {
+        unsafe { Port::<u8>::new(self.io_base + REG_COMMAND).read() }
+    }
+    
+    fn wait_not_busy(&self) -> Result<u8, BlockError> {
+        for _ in 0..POLL_LIMIT {
+            let status = self.status();
+            if status & STATUS_BSY == 0 {
+                return Ok(status);
+            }
+            core::hint::spin_loop();
+        }
+        Err(BlockError::Timeout)
+    }
+    
+    fn wait_data(&self) -> Result<(), BlockError> {
+        for _ in 0..POLL_LIMIT {
+            let status = self.status();
+            if status & STATUS_ERR != 0 {
+                return Err(BlockError::DeviceError);
+            }
+            if status & STATUS_BSY == 0 && status & STATUS_DRQ != 0 {
+                return Ok(());
+            }
+            core::hint::spin_loop();
+        }
+        Err(BlockError::Timeout)
+    }
+    
+    // Issues a 12-byte SCSI packet and reads the PIO response into `out`
+    fn packet(&mut self, command: &[u8; 12], out: &mut [u8]) -> Result<(), BlockError> {
+        self.select();
+        self.wait_not_busy()?;
+        
+        let mut data: Port<u16> = Port::new(self.io_base + REG_DATA);
+        unsafe {
+            Port::<u8>::new(self.io_base + REG_FEATURES).write(0);
+            Port::<u8>::new(self.io_base + REG_LBA_MID).write(out.len() as u8);
+            Port::<u8>::new(self.io_base + REG_LBA_HIGH).write((out.len() >> 8) as u8);
+            Port::<u8>::new(self.io_base + REG_COMMAND).write(CMD_PACKET);
+        }
+        self.wait_data()?;
+        
+        for pair in command.chunks(2) {
+            unsafe { data.write(u16::from_le_bytes([pair[0], pair[1]])) };
+        }
+        
+        let mut done = 0;
+        while done < out.len() {
+            self.wait_data()?;
+            let available = unsafe {
+                Port::<u8>::new(self.io_base + REG_LBA_MID).read() as usize
+                    | (Port::<u8>::new(self.io_base + REG_LBA_HIGH).read() as usize) << 8
+            };
+            if available == 0 {
+                return Err(BlockError::DeviceError);
+            }
+            for _ in 0..available / 2 {
+                let word = unsafe { data.read() }.to_le_bytes();
+                if done < out.len() {
+                    out[done] = word[0];
+                }
+                if done + 1 < out.len() {
+                    out[done + 1] = word[1];
+                }
+                done += 2;
+            }
+        }
+        
+        let status = self.wait_not_busy()?;
+        if status & STATUS_ERR != 0 {
+            return Err(BlockError::DeviceError);
+        }
+        Ok(())
+    }
+    
+    fn read_block(&mut self, lba: u64) -> Result<(), BlockError> {
+        if self.cached_block == Some(lba) {
+            return Ok(());
+        }
+        
+        let b = (lba as u32).to_be_bytes();
+        let command = [SCSI_READ_12, 0, b[0], b[1], b[2], b[3], 0, 0, 0, 1, 0, 0];
+        let mut buf = [0u8; ATAPI_BLOCK_SIZE];
+        self.cached_block = None;
+        self.packet(&command, &mut buf)?;
+        self.block_buf = buf;
+        self.cached_block = Some(lba);
+        Ok(())
+    }
+}
+
+impl BlockDevice for AtapiDrive {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    
+    fn sector_count(&self) -> u64 {
+        self.blocks * SECTORS_PER_BLOCK
+    }
+    
+    fn read_sectors(&mut self, lba: u64, buf: &mut [u8]) -> Result<(), BlockError> {
+        block::check_request(self, lba, buf.len())?;
+        
+        for (i, chunk) in buf.chunks_mut(SECTOR_SIZE).enumerate() {
+            let sector = lba + i as u64;
+            self.read_block(sector / SECTORS_PER_BLOCK)?;
+            let offset = (sector % SECTORS_PER_BLOCK) as usize * SECTOR_SIZE;
+            chunk.copy_from_slice(&self.block_buf[offset..offset + SECTOR_SIZE]);
+        }
+        Ok(())
+    }
+    
+    fn write_sectors(&mut self, _lba: u64, _buf: &[u8]) -> Result<(), BlockError> {
+        Err(BlockError::ReadOnly)
+    }
+    
+    fn is_read_only(&self) -> bool {
+        true
+    }
+}
+
+pub fn init() -> usize {
+    let mut found = 0;
+    
+    for &(io_base, control) in CHANNELS.iter() {
+        for &slave in [false, true].iter() {
+            if let Some(drive) = AtapiDrive::probe(io_base, control, slave, found) {
+                kinfo!(
+                    "ata: {} on {:#x} {} ({} MiB)",
+                    drive.name,
+                    io_base,
+                    if slave { "slave" } else { "master" },
+                    drive.blocks * ATAPI_BLOCK_SIZE as u64 / (1024 * 1024)
+                );
+                block::register(drive);
+                found += 1;
+            }
+        }
+    }
+    
+    found
+}
\ No newline at end of file
@@ -0,0 +1,69 @@
+// src/backtrace.rs
+// Stack walking over saved frame pointers; the kernel is built with
+// -C force-frame-pointers so every frame links to its caller's. Each frame
+// pointer is checked against the page tables before it's read, so a
+// corrupt chain ends the walk instead of faulting inside a panic or an
+// interrupt. Walking never allocates or locks, so the heap profiler can
+// use it from inside the allocator and the timer from any interrupt.
+use crate::memory;
+use alloc::vec::Vec;
+use x86_64::VirtAddr;
+
+// Return addresses from the caller of `here` outwards
+pub struct Frames {
+    rbp: usize,
+}
+
+impl Frames {
+    #[inline(always)]
+    pub fn here() -> Self {
+        let rbp: usize;
+        unsafe { core::arch::asm!("mov {}, rbp", out(reg) rbp, options(nomem, nostack, preserves_flags)) };
+        Self { rbp }
+    }
+    
+    // From a function an interrupt handler without an error code calls
+    // directly, and which isn't inlined: the interrupted code's callers.
+    // The walk goes up through the handler's frame, which the handler
+    // pushed straight onto the interrupt frame.
+    #[inline(always)]
+    pub fn interrupted() -> Self {
+        let mut frames = Self::here();
+        frames.next();
+        frames.next();
+        frames
+    }
+}
+
+// Both words of the frame at `rbp`, the saved frame pointer and the return
+// address, can be read
+fn readable(rbp: usize) -> bool {
+    let words = [rbp, rbp.wrapping_add(8)];
+    words.iter().all(|&word| VirtAddr::try_new(word as u64).is_ok_and(memory::is_mapped))
+}
+
+impl Iterator for Frames {
+    type Item = usize;
+    
+    fn next(&mut self) -> Option<usize> {
+        if self.rbp == 0 || self.rbp % 8 != 0 || !readable(self.rbp) {
+            return None;
+        }
+        let frame = self.rbp as *const usize;
+        let (next, ret) = unsafe { (*frame, *frame.add(1)) };
+        if ret == 0 {
+            return None;
+        }
+        // Callers' frames always sit higher on the stack; anything else
+        // means the chain is broken, so stop after this one
+        self.rbp = if next > self.rbp { next } else { 0 };
+        Some(ret)
+    }
+}
+
+// Up to `depth` return addresses, innermost first. Resolve them with
+// addr2line against the kernel binary.
+#[inline(always)]
+pub fn capture(depth: usize) -> Vec<usize> {
+    Frames::here().take(depth).collect()
+}
\ No newline at end of file
@@ -0,0 +1,89 @@
+// src/bench.rs
+// Drawing micro-benchmarks, built with `--features bench` and run with
+// `make bench`. Once the desktop is up, instead of starting its loop, each
+// benchmark runs a fixed workload a fixed number of times; the best and
+// mean times go out over serial and QEMU exits. Nothing is random, so runs
+// before and after a change compare. There's no moving windows by their
+// title bars yet, so the drag is a resize by the corner handle.
+use crate::clock;
+use crate::desktop::Desktop;
+use crate::graphics::{Color, Graphics, SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::input::{self, InputEvent};
+use crate::mouse::MouseButton;
+use crate::serial_println;
+use x86_64::instructions::port::Port;
+
+const ITERATIONS: u64 = 20;
+const RECTS: usize = 1000;
+const TEXT_LINES: usize = 50;
+const SAMPLE_TEXT: &str = "The quick brown fox jumps over the lazy dog 0123456789";
+// Frames each way in a drag, and the pointer movement per frame
+const DRAG_FRAMES: usize = 40;
+const DRAG_STEP: i32 = 2;
+// QEMU's isa-debug-exit device, and the value it turns into exit code 33
+const EXIT_PORT: u16 = 0xF4;
+const EXIT_SUCCESS: u32 = 0x10;
+
+fn measure(name: &str, mut f: impl FnMut(u64)) {
+    let (mut best, mut total) = (u64::MAX, 0);
+    for i in 0..ITERATIONS {
+        let start = clock::nanos();
+        f(i);
+        let elapsed = clock::nanos() - start;
+        best = best.min(elapsed);
+        total += elapsed;
+    }
+    serial_println!("bench: {:<12} best {:>8} us  mean {:>8} us", name, best / 1000, total / ITERATIONS / 1000);
+}
+
+// Presses on the focused window's resize handle, drags it in and back out
+// again a few pixels a frame and lets go, through input like a real mouse
+fn drag(desktop: &mut Desktop, graphics: &mut Graphics) {
+    let (x, y) = match desktop.windows().iter().find(|window| window.is_focused) {
+        Some(window) => (window.x + window.width - 5, window.y + window.height - 5),
+        None => return,
+    };
+    let mut frame = |event: InputEvent| {
+        input::push(event);
+        desktop.handle_events();
+        desktop.draw(graphics);
+    };
+    // Into the corner, where the pointer's position is known, then over
+    frame(InputEvent::MouseMove { dx: -(SCREEN_WIDTH as i32), dy: -(SCREEN_HEIGHT as i32) });
+    frame(InputEvent::MouseMove { dx: x as i32, dy: y as i32 });
+    frame(InputEvent::MouseButton { button: MouseButton::Left, pressed: true });
+    for step in 0..DRAG_FRAMES * 2 {
+        let d = if step < DRAG_FRAMES { -DRAG_STEP } else { DRAG_STEP };
+        frame(InputEvent::MouseMove { dx: d, dy: d });
+    }
+    frame(InputEvent::MouseButton { button: MouseButton::Left, pressed: false });
+}
+
+pub fn run(desktop: &mut Desktop, graphics: &mut Graphics) -> ! {
+    serial_println!("bench: {} runs each, TSC at {} MHz", ITERATIONS, clock::tsc_hz() / 1_000_000);
+    measure("clear", |_| graphics.clear_screen(Color::BLACK));
+    measure("rects", |i| {
+        for r in 0..RECTS {
+            let x = (r * 37 + i as usize) % (SCREEN_WIDTH - 40);
+            let y = (r * 23) % (SCREEN_HEIGHT - 30);
+            graphics.draw_rect(x, y, 40, 30, Color::BLUE);
+        }
+    });
+    measure("text", |_| {
+        for line in 0..TEXT_LINES {
+            graphics.draw_text(SAMPLE_TEXT, 8, line * 9, Color::BLACK);
+        }
+    });
+    measure("labels", |_| {
+        for line in 0..TEXT_LINES {
+            graphics.draw_label(SAMPLE_TEXT, 8, line * 9, Color::BLACK);
+        }
+    });
+    measure("compose", |_| desktop.draw(graphics));
+    measure("drag", |_| drag(desktop, graphics));
+    serial_println!("bench: done");
+    unsafe {
+        Port::<u32>::new(EXIT_PORT).write(EXIT_SUCCESS);
+    }
+    crate::hlt_loop();
+}
\ No newline at end of file
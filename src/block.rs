@@ -0,0 +1,111 @@
+// src/block.rs
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::sync::Mutex;
+
+pub const SECTOR_SIZE: usize = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockError {
+    OutOfRange,
+    BadBuffer,
+    DeviceError,
+    ReadOnly,
+    Timeout,
+}
+
+pub trait BlockDevice: Send {
+    fn name(&self) -> &str;
+    
+    fn sector_count(&self) -> u64;
+    
+    // `buf` must be a whole number of sectors long
+    fn read_sectors(&mut self, lba: u64, buf: &mut [u8]) -> Result<(), BlockError>;
+    
+    fn write_sectors(&mut self, lba: u64, buf: &[u8]) -> Result<(), BlockError>;
+    
+    fn flush(&mut self) -> Result<(), BlockError> {
+        Ok(())
+    }
+    
+    fn is_read_only(&self) -> bool {
+        false
+    }
+}
+
+pub type SharedBlockDevice = Arc<Mutex<dyn BlockDevice>>;
+
+pub struct DeviceInfo {
+    pub index: usize,
+    pub name: String,
+    pub sector_count: u64,
+    pub read_only: bool,
+}
+
+static DEVICES: Mutex<Vec<SharedBlockDevice>> = Mutex::new(Vec::new());
+
+pub fn register<D: BlockDevice + 'static>(device: D) -> usize {
+    let mut devices = DEVICES.lock();
+    devices.push(Arc::new(Mutex::new(device)));
+    devices.len() - 1
+}
+
+pub fn get(index: usize) -> Option<SharedBlockDevice> {
+    DEVICES.lock().get(index).cloned()
+}
+
+pub fn list() -> Vec<DeviceInfo> {
+    DEVICES
+        .lock()
+        .iter()
+        .enumerate()
+        .map(|(index, dev)| {
+            let dev = dev.lock();
+            DeviceInfo {
+                index,
+                name: String::from(dev.name()),
+                sector_count: dev.sector_count(),
+                read_only: dev.is_read_only(),
+            }
+        })
+        .collect()
+}
+
+pub fn check_request(device: &dyn BlockDevice, lba: u64, len: usize) -> Result<u64, BlockError> {
+    if len == 0 || len % SECTOR_SIZE != 0 {
+        return Err(BlockError::BadBuffer);
+    }
+    
+    let count = (len / SECTOR_SIZE) as u64;
+    match lba.checked_add(count) {
+        Some(end) if end <= device.sector_count() => Ok(count),
+        _ => Err(BlockError::OutOfRange),
+    }
+}
+
+// Reads an arbitrary byte range from a sector-addressed device
+pub fn read_bytes(device: &SharedBlockDevice, offset: u64, buf: &mut [u8]) -> Result<(), BlockError> {
+    let first_sector = offset / SECTOR_SIZE as u64;
+    let skip = (offset % SECTOR_SIZE as u64) as usize;
+    let sectors = (skip + buf.len() + SECTOR_SIZE - 1) / SECTOR_SIZE;
+    
+    let mut tmp = vec![0u8; sectors * SECTOR_SIZE];
+    device.lock().read_sectors(first_sector, &mut tmp)?;
+    buf.copy_from_slice(&tmp[skip..skip + buf.len()]);
+    Ok(())
+}
+
+// Writes an arbitrary byte range, keeping the rest of the sectors it touches
+pub fn write_bytes(device: &SharedBlockDevice, offset: u64, buf: &[u8]) -> Result<(), BlockError> {
+    let first_sector = offset / SECTOR_SIZE as u64;
+    let skip = (offset % SECTOR_SIZE as u64) as usize;
+    let sectors = (skip + buf.len() + SECTOR_SIZE - 1) / SECTOR_SIZE;
+    
+    let mut tmp = vec![0u8; sectors * SECTOR_SIZE];
+    let mut device = device.lock();
+    device.read_sectors(first_sector, &mut tmp)?;
+    tmp[skip..skip + buf.len()].copy_from_slice(buf);
+    device.write_sectors(first_sector, &tmp)
+}
\ No newline at end of file
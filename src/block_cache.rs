@@ -0,0 +1,336 @@
+// src/block_cache.rs
+// Sector cache sitting between filesystems and a BlockDevice. Reads are
+// served from an LRU set of sectors with sequential read-ahead; writes are
+// buffered and only reach the disk on eviction, periodic write-back or sync.
+use crate::block::{self, BlockDevice, BlockError, SharedBlockDevice, SECTOR_SIZE};
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::sync::Mutex;
+
+pub const DEFAULT_CAPACITY: usize = 128;
+pub const READ_AHEAD_SECTORS: usize = 8;
+// Dirty sectors older than this many write-back ticks are flushed
+pub const WRITEBACK_AGE: u64 = 5;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub read_ahead: u64,
+    pub writes: u64,
+    pub write_backs: u64,
+    pub evictions: u64,
+    pub dirty: usize,
+    pub cached: usize,
+    pub capacity: usize,
+}
+
+impl CacheStats {
+    pub fn hit_rate(&self) -> u32 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0
+        } else {
+            (self.hits * 100 / total) as u32
+        }
+    }
+}
+
+struct CacheEntry {
+    data: Box<[u8; SECTOR_SIZE]>,
+    dirty: bool,
+    dirty_since: u64,
+    last_used: u64,
+}
+
+pub struct BlockCache {
+    name: String,
+    device: SharedBlockDevice,
+    entries: BTreeMap<u64, CacheEntry>,
+    capacity: usize,
+    clock: u64,
+    writeback_tick: u64,
+    stats: CacheStats,
+}
+
+impl BlockCache {
+    pub fn new(device: SharedBlockDevice, capacity: usize) -> Self {
+        let name = String::from(device.lock().name());
+        Self {
+            name,
+            device,
+            entries: BTreeMap::new(),
+            capacity: capacity.max(READ_AHEAD_SECTORS * 2),
+            clock: 0,
+            writeback_tick: 0,
+            stats: CacheStats::default(),
+        }
+    }
+    
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            dirty: self.entries.values().filter(|e| e.dirty).count(),
+            cached: self.entries.len(),
+            capacity: self.capacity,
+            ..self.stats
+        }
+    }
+    
+    fn touch(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+    
+    fn fill(&mut self, lba: u64) -> Result<(), BlockError> {
+        let mut device = self.device.lock();
+        let remaining = device.sector_count().saturating_sub(lba);
+        let count = (READ_AHEAD_SECTORS as u64).min(remaining) as usize;
+        if count == 0 {
+            return Err(BlockError::OutOfRange);
+        }
+        
+        let mut buf = vec![0u8; count * SECTOR_SIZE];
+        device.read_sectors(lba, &mut buf)?;
+        drop(device);
+        
+        self.stats.misses += 1;
+        self.stats.read_ahead += count as u64 - 1;
+        let now = self.touch();
+        for (i, chunk) in buf.chunks(SECTOR_SIZE).enumerate() {
+            let sector = lba + i as u64;
+            // Never clobber a sector that holds unwritten data
+            if self.entries.contains_key(&sector) {
+                continue;
+            }
+            let mut data = Box::new([0u8; SECTOR_SIZE]);
+            data.copy_from_slice(chunk);
+            self.entries.insert(sector, CacheEntry { data, dirty: false, dirty_since: 0, last_used: now });
+        }
+        self.evict()
+    }
+    
+    fn evict(&mut self) -> Result<(), BlockError> {
+        while self.entries.len() > self.capacity {
+            let victim = match self.entries.iter().min_by_key(|(_, e)| e.last_used) {
+                Some((&lba, _)) => lba,
+                None => break,
+            };
+            if let Some(entry) = self.entries.get(&victim) {
+                if entry.dirty {
+                    self.device.lock().write_sectors(victim, &entry.data[..])?;
+                    self.stats.write_backs += 1;
+                }
+            }
+            self.entries.remove(&victim);
+            self.stats.evictions += 1;
+        }
+        Ok(())
+    }
+    
+    // Writes dirty sectors matching `filter` back in LBA order, merging
+    // adjacent sectors into single device requests.
+    fn write_back(&mut self, filter: impl Fn(&CacheEntry) -> bool) -> Result<(), BlockError> {
+        let dirty: Vec<u64> = self
+            .entries
+            .iter()
+            .filter(|(_, e)| e.dirty && filter(e))
+            .map(|(&lba, _)| lba)
+            .collect();
+        
+        let mut device = self.device.lock();
+        let mut i = 0;
+        while i < dirty.len() {
+            let start = dirty[i];
+            let mut run = 1;
+            while i + run < dirty.len() && dirty[i + run] == start + run as u64 {
+                run += 1;
+            }
+            
+            let mut buf = Vec::with_capacity(run * SECTOR_SIZE);
+            for lba in start..start + run as u64 {
+                buf.extend_from_slice(&self.entries[&lba].data[..]);
+            }
+            device.write_sectors(start, &buf)?;
+            
+            for lba in start..start + run as u64 {
+                if let Some(entry) = self.entries.get_mut(&lba) {
+                    entry.dirty = false;
+                }
+            }
+            self.stats.write_backs += run as u64;
+            i += run;
+        }
+        Ok(())
+    }
+    
+    pub fn sync(&mut self) -> Result<(), BlockError> {
+        self.write_back(|_| true)?;
+        self.device.lock().flush()
+    }
+    
+    // Called periodically; flushes sectors that have been dirty for a while
+    pub fn periodic_writeback(&mut self) -> Result<(), BlockError> {
+        self.writeback_tick += 1;
+        let tick = self.writeback_tick;
+        self.write_back(|e| tick.saturating_sub(e.dirty_since) >= WRITEBACK_AGE)
+    }
+    
+    pub fn invalidate(&mut self) -> Result<(), BlockError> {
+        self.sync()?;
+        self.entries.clear();
+        Ok(())
+    }
+}
+
+impl BlockDevice for BlockCache {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    
+    fn sector_count(&self) -> u64 {
+        self.device.lock().sector_count()
+    }
+    
+    fn read_sectors(&mut self, lba: u64, buf: &mut [u8]) -> Result<(), BlockError> {
+        block::check_request(self, lba, buf.len())?;
+        
+        for (i, chunk) in buf.chunks_mut(SECTOR_SIZE).enumerate() {
+            let sector = lba + i as u64;
+            if self.entries.contains_key(&sector) {
+                self.stats.hits += 1;
+            } else {
+                self.fill(sector)?;
+            }
+            let now = self.touch();
+            if let Some(entry) = self.entries.get_mut(&sector) {
+                entry.last_used = now;
+                chunk.copy_from_slice(&entry.data[..]);
+            }
+        }
+        Ok(())
+    }
+    
+    fn write_sectors(&mut self, lba: u64, buf: &[u8]) -> Result<(), BlockError> {
+        if self.is_read_only() {
+            return Err(BlockError::ReadOnly);
+        }
+        block::check_request(self, lba, buf.len())?;
+        
+        for (i, chunk) in buf.chunks(SECTOR_SIZE).enumerate() {
+            let sector = lba + i as u64;
+            let now = self.touch();
+            let tick = self.writeback_tick;
+            let entry = self.entries.entry(sector).or_insert_with(|| CacheEntry {
+                data: Box::new([0u8; SECTOR_SIZE]),
+                dirty: false,
+                dirty_since: tick,
+                last_used: now,
+            });
+            entry.data.copy_from_slice(chunk);
+            if !entry.dirty {
+                entry.dirty = true;
+                entry.dirty_since = tick;
+            }
+            entry.last_used = now;
+            self.stats.writes += 1;
+        }
+        self.evict()
+    }
+    
+    fn flush(&mut self) -> Result<(), BlockError> {
+        self.sync()
+    }
+    
+    fn is_read_only(&self) -> bool {
+        self.device.lock().is_read_only()
+    }
+}
+
+static CACHES: Mutex<Vec<(usize, Arc<Mutex<BlockCache>>)>> = Mutex::new(Vec::new());
+
+// Returns the shared cache for a registered block device, creating it on
+// first use. Filesystems should always go through this rather than the raw
+// device so that every mount of a disk sees the same cached sectors.
+pub fn cached(index: usize) -> Option<Arc<Mutex<BlockCache>>> {
+    let mut caches = CACHES.lock();
+    if let Some((_, cache)) = caches.iter().find(|(i, _)| *i == index) {
+        return Some(cache.clone());
+    }
+    
+    let device = block::get(index)?;
+    let cache = Arc::new(Mutex::new(BlockCache::new(device, DEFAULT_CAPACITY)));
+    caches.push((index, cache.clone()));
+    Some(cache)
+}
+
+pub fn cached_device(index: usize) -> Option<SharedBlockDevice> {
+    let cache: SharedBlockDevice = cached(index)?;
+    Some(cache)
+}
+
+pub fn sync_all() -> Result<(), BlockError> {
+    let caches: Vec<_> = CACHES.lock().iter().map(|(_, c)| c.clone()).collect();
+    let mut result = Ok(());
+    for cache in caches {
+        if let Err(err) = cache.lock().sync() {
+            result = Err(err);
+        }
+    }
+    result
+}
+
+pub fn periodic_writeback() {
+    let caches: Vec<_> = CACHES.lock().iter().map(|(_, c)| c.clone()).collect();
+    for cache in caches {
+        let _ = cache.lock().periodic_writeback();
+    }
+}
+
+// Writes back and drops every cached sector, for when the heap runs low;
+// the number dropped
+pub fn reclaim() -> usize {
+    let caches: Vec<_> = CACHES.lock().iter().map(|(_, c)| c.clone()).collect();
+    let mut dropped = 0;
+    for cache in caches {
+        let mut cache = cache.lock();
+        let cached = cache.entries.len();
+        if cache.invalidate().is_ok() {
+            dropped += cached;
+        }
+    }
+    dropped
+}
+
+// Drops clean cached sectors without writing or allocating anything, for
+// the allocator when it runs out; caches in use are skipped. The number
+// dropped.
+pub fn purge_clean() -> usize {
+    let caches = match CACHES.try_lock() {
+        Some(caches) => caches,
+        None => return 0,
+    };
+    let mut dropped = 0;
+    for (_, cache) in caches.iter() {
+        if let Some(mut cache) = cache.try_lock() {
+            let cached = cache.entries.len();
+            cache.entries.retain(|_, entry| entry.dirty);
+            dropped += cached - cache.entries.len();
+        }
+    }
+    dropped
+}
+
+pub fn stats() -> Vec<(String, CacheStats)> {
+    CACHES
+        .lock()
+        .iter()
+        .map(|(_, cache)| {
+            let cache = cache.lock();
+            (cache.name.clone(), cache.stats())
+        })
+        .collect()
+}
\ No newline at end of file
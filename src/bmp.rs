@@ -0,0 +1,152 @@
+// src/bmp.rs
+// Windows BMP decoder: uncompressed 1, 4, 8, 24 and 32 bits per pixel,
+// bottom-up or top-down. Compressed (RLE) bitmaps and bitfield masks other
+// than the usual BGRA layout aren't supported. Images are written back as
+// plain 24-bit bottom-up bitmaps.
+use crate::graphics::Color;
+use alloc::vec::Vec;
+
+const FILE_HEADER_SIZE: usize = 14;
+// BITMAPINFOHEADER; later versions only add fields after it
+const INFO_HEADER_SIZE: usize = 40;
+const COMPRESSION_RGB: u32 = 0;
+const COMPRESSION_BITFIELDS: u32 = 3;
+// Larger images would take more heap than we'd like for one picture
+const MAX_PIXELS: usize = 4096 * 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BmpError {
+    NotBmp,
+    Unsupported,
+    Truncated,
+    TooLarge,
+}
+
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    // Row-major, top row first
+    pixels: Vec<Color>,
+}
+
+impl Image {
+    // `pixels` row-major, top row first
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<Color>) -> Image {
+        Image { width, height, pixels }
+    }
+    
+    pub fn pixel(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+    
+    pub fn pixels(&self) -> &[Color] {
+        &self.pixels
+    }
+}
+
+// A 24-bit BMP file of the image
+pub fn encode(image: &Image) -> Vec<u8> {
+    let row_size = (image.width * 3).div_ceil(4) * 4;
+    let pixel_offset = FILE_HEADER_SIZE + INFO_HEADER_SIZE;
+    let file_size = pixel_offset + row_size * image.height;
+    let mut data = Vec::with_capacity(file_size);
+    data.extend_from_slice(b"BM");
+    data.extend_from_slice(&(file_size as u32).to_le_bytes());
+    data.extend_from_slice(&[0; 4]);
+    data.extend_from_slice(&(pixel_offset as u32).to_le_bytes());
+    data.extend_from_slice(&(INFO_HEADER_SIZE as u32).to_le_bytes());
+    data.extend_from_slice(&(image.width as u32).to_le_bytes());
+    data.extend_from_slice(&(image.height as u32).to_le_bytes());
+    data.extend_from_slice(&1u16.to_le_bytes());
+    data.extend_from_slice(&24u16.to_le_bytes());
+    data.extend_from_slice(&COMPRESSION_RGB.to_le_bytes());
+    // Image size, resolution and palette sizes can all be left at 0
+    data.extend_from_slice(&[0; 20]);
+    for y in (0..image.height).rev() {
+        for x in 0..image.width {
+            let color = image.pixel(x, y);
+            data.extend_from_slice(&[color.b, color.g, color.r]);
+        }
+        data.resize(data.len() + row_size - image.width * 3, 0);
+    }
+    data
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Result<u16, BmpError> {
+    data.get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or(BmpError::Truncated)
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Result<u32, BmpError> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or(BmpError::Truncated)
+}
+
+pub fn decode(data: &[u8]) -> Result<Image, BmpError> {
+    if data.len() < FILE_HEADER_SIZE || &data[0..2] != b"BM" {
+        return Err(BmpError::NotBmp);
+    }
+    let pixel_offset = u32_at(data, 10)? as usize;
+    let header_size = u32_at(data, 14)? as usize;
+    if header_size < INFO_HEADER_SIZE {
+        return Err(BmpError::Unsupported);
+    }
+    let width = u32_at(data, 18)? as i32;
+    let height = u32_at(data, 22)? as i32;
+    let bits = u16_at(data, 28)?;
+    let compression = u32_at(data, 30)?;
+    let palette_size = u32_at(data, 46)? as usize;
+    
+    let supported = match compression {
+        COMPRESSION_RGB => matches!(bits, 1 | 4 | 8 | 24 | 32),
+        COMPRESSION_BITFIELDS => bits == 32,
+        _ => false,
+    };
+    if !supported || width <= 0 || height == 0 {
+        return Err(BmpError::Unsupported);
+    }
+    // A negative height means the rows are stored top-down
+    let top_down = height < 0;
+    let (width, height) = (width as usize, height.unsigned_abs() as usize);
+    if width * height > MAX_PIXELS {
+        return Err(BmpError::TooLarge);
+    }
+    
+    // The palette follows the info header, four bytes (BGR0) per entry
+    let mut palette = Vec::new();
+    if bits <= 8 {
+        let entries = if palette_size == 0 { 1 << bits } else { palette_size.min(256) };
+        let start = FILE_HEADER_SIZE + header_size;
+        for i in 0..entries {
+            let entry = data.get(start + i * 4..start + i * 4 + 3).ok_or(BmpError::Truncated)?;
+            palette.push(Color::new(entry[2], entry[1], entry[0]));
+        }
+    }
+    
+    // Rows are padded to four bytes
+    let row_size = (width * bits as usize).div_ceil(32) * 4;
+    let mut pixels = Vec::with_capacity(width * height);
+    for y in 0..height {
+        let stored_row = if top_down { y } else { height - 1 - y };
+        let start = pixel_offset + stored_row * row_size;
+        let row = data.get(start..start + row_size).ok_or(BmpError::Truncated)?;
+        for x in 0..width {
+            let color = match bits {
+                24 | 32 => {
+                    let p = &row[x * bits as usize / 8..];
+                    Color::new(p[2], p[1], p[0])
+                }
+                _ => {
+                    let bit = x * bits as usize;
+                    let shift = 8 - bits as usize - bit % 8;
+                    let index = (row[bit / 8] >> shift) & ((1u16 << bits) - 1) as u8;
+                    palette.get(index as usize).copied().unwrap_or(Color::BLACK)
+                }
+            };
+            pixels.push(color);
+        }
+    }
+    Ok(Image { width, height, pixels })
+}
\ No newline at end of file
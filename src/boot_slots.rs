@@ -0,0 +1,294 @@
+// src/boot_slots.rs
+// Two kernel slots on the boot disk, so an update that doesn't start can't
+// leave the machine unbootable. `make boot-disk` lays the disk out: GRUB in
+// the gap after the MBR and a FAT32 partition holding the slots, KERNELA
+// and KERNELB, GRUB's menu, GRUBCFG, and its environment block, GRUBENV,
+// each one run of clusters written in place like the swap file. An update
+// goes into the slot that isn't running, gets its menu entry, and is marked
+// to be tried once; GRUB clears the mark as it boots it, so if the new
+// kernel never reaches the desktop the next boot is the old slot's again.
+// Reaching the desktop sets the boot-success flag and makes the slot that
+// booted the default.
+use crate::block::{self, read_bytes, write_bytes, SharedBlockDevice, SECTOR_SIZE};
+use crate::fat32::Fat32;
+use crate::partition;
+use crate::system_info::KERNEL_VERSION;
+use crate::vfs::{self, FileSystem, VfsError};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::str;
+
+// Kernel images, as `make boot-disk` pads them
+const SLOT_SIZE: u64 = 32 * 1024 * 1024;
+const ENV_FILE: &str = "GRUBENV";
+const ENV_SIZE: usize = 1024;
+const ENV_HEADER: &str = "# GRUB Environment Block\n";
+// Padded with blank lines, which GRUB skips
+const CONFIG_FILE: &str = "GRUBCFG";
+const CONFIG_SIZE: usize = 4096;
+// Written a piece at a time, so a kernel image isn't copied whole
+const CHUNK: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Slot {
+    A,
+    B,
+}
+
+impl Slot {
+    const ALL: [Slot; 2] = [Slot::A, Slot::B];
+    
+    // Its menu entry's id
+    pub fn name(self) -> &'static str {
+        match self {
+            Slot::A => "a",
+            Slot::B => "b",
+        }
+    }
+    
+    fn file(self) -> &'static str {
+        match self {
+            Slot::A => "KERNELA",
+            Slot::B => "KERNELB",
+        }
+    }
+    
+    fn other(self) -> Slot {
+        match self {
+            Slot::A => Slot::B,
+            Slot::B => Slot::A,
+        }
+    }
+    
+    fn from_name(name: &str) -> Option<Slot> {
+        Slot::ALL.into_iter().find(|slot| slot.name() == name)
+    }
+}
+
+// How this boot went, as far as the slots go
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Boot {
+    Usual,
+    // An update's first boot
+    Updated,
+    // An update didn't reach the desktop and the slot before it booted
+    RolledBack,
+}
+
+pub struct Status {
+    pub booted: Option<Slot>,
+    // What boots when nothing is being tried
+    pub default: Option<Slot>,
+    // Tried once at the next boot
+    pub trial: Option<Slot>,
+    // Each slot's version, empty for one never written
+    pub versions: [String; 2],
+}
+
+// GRUB's variables, in the order they were read
+struct Env(Vec<(String, String)>);
+
+impl Env {
+    fn parse(block: &[u8]) -> Result<Env, VfsError> {
+        let text = str::from_utf8(block).map_err(|_| VfsError::Corrupt)?;
+        if !text.starts_with(ENV_HEADER) {
+            return Err(VfsError::Corrupt);
+        }
+        Ok(Env(text.lines()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| line.split_once('='))
+            .map(|(name, value)| (String::from(name), String::from(value)))
+            .collect()))
+    }
+    
+    fn get(&self, name: &str) -> &str {
+        self.0.iter().find(|(key, _)| key == name).map_or("", |(_, value)| value)
+    }
+    
+    fn slot(&self, name: &str) -> Option<Slot> {
+        Slot::from_name(self.get(name))
+    }
+    
+    fn set(&mut self, name: &str, value: &str) {
+        match self.0.iter_mut().find(|(key, _)| key == name) {
+            Some((_, old)) => *old = String::from(value),
+            None => self.0.push((String::from(name), String::from(value))),
+        }
+    }
+    
+    fn remove(&mut self, name: &str) {
+        self.0.retain(|(key, _)| key != name);
+    }
+    
+    fn version(&self, slot: Slot) -> &str {
+        self.get(&format!("version_{}", slot.name()))
+    }
+    
+    // Filled out with '#' to the block's size, as GRUB expects
+    fn encode(&self) -> Result<Vec<u8>, VfsError> {
+        let mut block = Vec::from(ENV_HEADER.as_bytes());
+        for (name, value) in &self.0 {
+            block.extend_from_slice(format!("{}={}\n", name, value).as_bytes());
+        }
+        if block.len() > ENV_SIZE {
+            return Err(VfsError::NoSpace);
+        }
+        block.resize(ENV_SIZE, b'#');
+        Ok(block)
+    }
+}
+
+// GRUB's menu: an entry per slot that has a kernel, and the script that
+// picks one. Each entry records that it booted and clears the
+// boot-success flag for the kernel to set again.
+fn config(env: &Env) -> Result<Vec<u8>, VfsError> {
+    let mut text = String::from(concat!(
+        "# Written by the kernel when it updates a slot\n",
+        "load_env -f /GRUBENV\n",
+        "if [ \"${boot_success}\" = \"1\" ]; then\n",
+        "    set timeout=2\n",
+        "else\n",
+        "    # The last boot didn't reach the desktop\n",
+        "    set timeout=10\n",
+        "fi\n",
+        "set default=\"${slot}\"\n",
+        "if [ -n \"${trial}\" ]; then\n",
+        "    set default=\"${trial}\"\n",
+        "    set trial=\n",
+        "    save_env -f /GRUBENV trial\n",
+        "fi\n",
+    ));
+    for slot in Slot::ALL {
+        let version = env.version(slot);
+        if version.is_empty() {
+            continue;
+        }
+        text.push_str(&format!(
+            "\nmenuentry \"RustOS {} ({})\" --id {} {{\n    set booted={}\n    set boot_success=0\n    save_env -f /GRUBENV booted boot_success\n    multiboot2 /{}\n}}\n",
+            version, slot.name().to_uppercase(), slot.name(), slot.name(), slot.file(),
+        ));
+    }
+    if text.len() > CONFIG_SIZE {
+        return Err(VfsError::NoSpace);
+    }
+    let mut config = text.into_bytes();
+    config.resize(CONFIG_SIZE, b'\n');
+    Ok(config)
+}
+
+struct BootVolume {
+    fs: Fat32,
+    // The whole disk, uncached, and where the partition starts on it
+    disk: SharedBlockDevice,
+    start: u64,
+}
+
+impl BootVolume {
+    // The first FAT32 volume with GRUB's environment block
+    fn find() -> Result<BootVolume, VfsError> {
+        for volume in partition::volumes() {
+            if vfs::probe(&volume.device) != Some("fat32") {
+                continue;
+            }
+            let (mut fs, disk) = match (Fat32::mount(volume.device.clone()), block::get(volume.disk)) {
+                (Ok(fs), Some(disk)) => (fs, disk),
+                _ => continue,
+            };
+            let root = fs.root_inode();
+            if fs.lookup(root, ENV_FILE).is_ok() {
+                let start = volume.partition.as_ref().map_or(0, |partition| partition.start) * SECTOR_SIZE as u64;
+                return Ok(BootVolume { fs, disk, start });
+            }
+        }
+        Err(VfsError::NotFound)
+    }
+    
+    // The byte offset of the file's data on the disk; a slot missing from
+    // the layout is made
+    fn offset(&mut self, name: &str, size: usize) -> Result<u64, VfsError> {
+        Ok(self.start + self.fs.preallocate(name, size as u64)?)
+    }
+    
+    fn read_env(&mut self) -> Result<Env, VfsError> {
+        let offset = self.offset(ENV_FILE, ENV_SIZE)?;
+        let mut block = [0u8; ENV_SIZE];
+        read_bytes(&self.disk, offset, &mut block)?;
+        Env::parse(&block)
+    }
+    
+    fn write(&mut self, name: &str, size: usize, data: &[u8]) -> Result<(), VfsError> {
+        let offset = self.offset(name, size)?;
+        for (i, chunk) in data.chunks(CHUNK).enumerate() {
+            write_bytes(&self.disk, offset + (i * CHUNK) as u64, chunk)?;
+        }
+        Ok(())
+    }
+    
+    fn write_env(&mut self, env: &Env) -> Result<(), VfsError> {
+        self.write(ENV_FILE, ENV_SIZE, &env.encode()?)?;
+        self.disk.lock().flush()?;
+        Ok(())
+    }
+}
+
+pub fn status() -> Result<Status, VfsError> {
+    let env = BootVolume::find()?.read_env()?;
+    Ok(Status {
+        booted: env.slot("booted"),
+        default: env.slot("slot"),
+        trial: env.slot("trial"),
+        versions: Slot::ALL.map(|slot| String::from(env.version(slot))),
+    })
+}
+
+// Writes the kernel image at `path` to the slot that isn't running and
+// has it tried at the next boot; the slot it went to
+pub fn install(path: &str, version: &str) -> Result<Slot, VfsError> {
+    let image = vfs::read_file(path)?;
+    if image.is_empty() || image.len() as u64 > SLOT_SIZE {
+        return Err(VfsError::NoSpace);
+    }
+    let mut volume = BootVolume::find()?;
+    let mut env = volume.read_env()?;
+    let running = env.slot("booted").or(env.slot("slot")).unwrap_or(Slot::A);
+    let slot = running.other();
+    // Its entry goes while it's half written
+    env.remove(&format!("version_{}", slot.name()));
+    env.remove("trial");
+    volume.write(CONFIG_FILE, CONFIG_SIZE, &config(&env)?)?;
+    volume.write_env(&env)?;
+    
+    volume.write(slot.file(), SLOT_SIZE as usize, &image)?;
+    env.set(&format!("version_{}", slot.name()), version);
+    env.set("trial", slot.name());
+    env.set("pending", slot.name());
+    volume.write(CONFIG_FILE, CONFIG_SIZE, &config(&env)?)?;
+    volume.write_env(&env)?;
+    Ok(slot)
+}
+
+// Called once the desktop is up: sets the boot-success flag and makes the
+// slot that booted the default. NotFound when this didn't boot from a disk
+// with slots.
+pub fn mark_success() -> Result<Boot, VfsError> {
+    let mut volume = BootVolume::find()?;
+    let mut env = volume.read_env()?;
+    let booted = env.slot("booted");
+    let boot = match (env.slot("pending"), booted) {
+        (Some(pending), Some(booted)) if pending == booted => Boot::Updated,
+        (Some(_), _) => Boot::RolledBack,
+        (None, _) => Boot::Usual,
+    };
+    if let Some(booted) = booted {
+        env.set("slot", booted.name());
+        if env.version(booted) != KERNEL_VERSION {
+            env.set(&format!("version_{}", booted.name()), KERNEL_VERSION);
+            volume.write(CONFIG_FILE, CONFIG_SIZE, &config(&env)?)?;
+        }
+    }
+    env.remove("pending");
+    env.set("boot_success", "1");
+    volume.write_env(&env)?;
+    Ok(boot)
+}
\ No newline at end of file
@@ -0,0 +1,164 @@
+// src/bootargs.rs
+// Boot arguments: space-separated `key=value` pairs and bare flags, like
+// "loglevel=debug theme=dark safe_mode". The bootloader has no command
+// line to hand over, so they are read from QEMU's firmware configuration,
+//   -fw_cfg name=opt/rustos/cmdline,string="loglevel=debug safe_mode"
+// falling back to RUSTOS_CMDLINE at build time. Later arguments override
+// earlier ones. Read in the first boot stage, before there's a heap, so
+// nothing here allocates.
+//
+//   loglevel=error|warn|info|debug  most detailed log level recorded
+//   video=WxH                       display mode; only 640x480 exists
+//   theme=light|dark                desktop appearance
+//   nosmp                           accepted; only the boot CPU runs anyway
+//   safe_mode                       no login items and no remote console
+//   record=PATH                     record the desktop's input to PATH
+//   replay=PATH                     play input recorded to PATH instead
+//   power=ac|battery                power source, as there's no battery driver
+//   hibernate                       save the session at shutdown, resume it at boot
+use crate::log::Level;
+use crate::kwarn;
+use spin::Once;
+use x86_64::instructions::port::Port;
+
+const MAX_LEN: usize = 512;
+const FW_CFG_FILE: &[u8] = b"opt/rustos/cmdline";
+const KNOWN: [&str; 9] = ["loglevel", "video", "theme", "nosmp", "safe_mode", "record", "replay", "power", "hibernate"];
+
+// QEMU fw_cfg I/O ports and items
+const FW_CFG_SELECTOR: u16 = 0x510;
+const FW_CFG_DATA: u16 = 0x511;
+const FW_CFG_SIGNATURE: u16 = 0x0000;
+const FW_CFG_FILE_DIR: u16 = 0x0019;
+// Size, select key, reserved, then a 56-byte name
+const FW_CFG_DIR_ENTRY: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Theme {
+    Light,
+    Dark,
+}
+
+struct Cmdline {
+    bytes: [u8; MAX_LEN],
+    len: usize,
+}
+
+static CMDLINE: Once<Cmdline> = Once::new();
+
+fn fw_cfg_select(item: u16) {
+    unsafe { Port::<u16>::new(FW_CFG_SELECTOR).write(item) };
+}
+
+fn fw_cfg_read(out: &mut [u8]) {
+    let mut data: Port<u8> = Port::new(FW_CFG_DATA);
+    for byte in out.iter_mut() {
+        *byte = unsafe { data.read() };
+    }
+}
+
+// Copies the named fw_cfg file into `out`, returning its length cut to fit
+fn fw_cfg_file(name: &[u8], out: &mut [u8]) -> Option<usize> {
+    let mut signature = [0u8; 4];
+    fw_cfg_select(FW_CFG_SIGNATURE);
+    fw_cfg_read(&mut signature);
+    if &signature != b"QEMU" {
+        return None;
+    }
+    let mut count = [0u8; 4];
+    fw_cfg_select(FW_CFG_FILE_DIR);
+    fw_cfg_read(&mut count);
+    for _ in 0..u32::from_be_bytes(count) {
+        let mut entry = [0u8; FW_CFG_DIR_ENTRY];
+        fw_cfg_read(&mut entry);
+        let entry_name = &entry[8..];
+        let name_len = entry_name.iter().position(|&b| b == 0).unwrap_or(entry_name.len());
+        if &entry_name[..name_len] == name {
+            let size = u32::from_be_bytes([entry[0], entry[1], entry[2], entry[3]]) as usize;
+            let len = size.min(out.len());
+            fw_cfg_select(u16::from_be_bytes([entry[4], entry[5]]));
+            fw_cfg_read(&mut out[..len]);
+            return Some(len);
+        }
+    }
+    None
+}
+
+pub fn init() {
+    CMDLINE.call_once(|| {
+        let mut cmdline = Cmdline { bytes: [0; MAX_LEN], len: 0 };
+        cmdline.len = match fw_cfg_file(FW_CFG_FILE, &mut cmdline.bytes) {
+            Some(len) => len,
+            None => {
+                let built_in = option_env!("RUSTOS_CMDLINE").unwrap_or("").as_bytes();
+                let len = built_in.len().min(MAX_LEN);
+                cmdline.bytes[..len].copy_from_slice(&built_in[..len]);
+                len
+            }
+        };
+        cmdline
+    });
+    for argument in args() {
+        let key = argument.split('=').next().unwrap_or("");
+        if !KNOWN.contains(&key) {
+            kwarn!("bootargs: unknown argument {}", argument);
+        }
+    }
+}
+
+// The whole command line, empty before `init`
+pub fn cmdline() -> &'static str {
+    match CMDLINE.r#try() {
+        Some(cmdline) => core::str::from_utf8(&cmdline.bytes[..cmdline.len]).unwrap_or("").trim_matches(|c: char| c == '\0' || c.is_whitespace()),
+        None => "",
+    }
+}
+
+fn args() -> impl Iterator<Item = &'static str> {
+    cmdline().split_ascii_whitespace()
+}
+
+// The value of the last `key=value`, or "" for a bare `key`
+pub fn get(key: &str) -> Option<&'static str> {
+    args()
+        .filter_map(|argument| match argument.split_once('=') {
+            Some((name, value)) => (name == key).then_some(value),
+            None => (argument == key).then_some(""),
+        })
+        .last()
+}
+
+pub fn flag(name: &str) -> bool {
+    get(name).is_some()
+}
+
+pub fn log_level() -> Option<Level> {
+    let name = get("loglevel")?;
+    let level = Level::from_name(name);
+    if level.is_none() {
+        kwarn!("bootargs: unknown log level {}", name);
+    }
+    level
+}
+
+// Width and height asked for with video=
+pub fn video() -> Option<(usize, usize)> {
+    let (width, height) = get("video")?.split_once('x')?;
+    Some((width.parse().ok()?, height.parse().ok()?))
+}
+
+// None without a theme= argument, or with one that isn't light or dark
+pub fn theme() -> Option<Theme> {
+    match get("theme")? {
+        "light" => Some(Theme::Light),
+        "dark" => Some(Theme::Dark),
+        other => {
+            kwarn!("bootargs: unknown theme {}", other);
+            None
+        }
+    }
+}
+
+pub fn safe_mode() -> bool {
+    flag("safe_mode")
+}
\ No newline at end of file
@@ -0,0 +1,57 @@
+// src/bus.rs
+// The message bus: a queue apps and services post to from wherever they
+// run, drained by the desktop once a frame, which hands each message to
+// the part of the UI it's for. It decouples the sender from whatever draws
+// the result, so a background fetch can update the Dock without touching
+// desktop state.
+use crate::display_server::SurfaceId;
+use crate::dock::Badge;
+use crate::share::Data;
+use crate::task::Pid;
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::sync::Mutex;
+use x86_64::instructions::interrupts;
+
+// Messages beyond this are dropped until the desktop catches up
+const QUEUE_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    // Dock icon decorations, by app name; None clears them
+    DockBadge { app: String, badge: Option<Badge> },
+    // Percent done
+    DockProgress { app: String, progress: Option<u8> },
+    // Open a file, in `app` or else the app that handles its kind
+    OpenFile { path: String, app: Option<String> },
+    // Whether an app's document has changes it hasn't saved
+    Edited { app: String, edited: bool },
+    // Power off, after asking about unsaved changes
+    ShutDown,
+    // Hand `data` to the service with this name
+    Service { name: &'static str, data: Data },
+    // A display server client's surface, which gets a window of its own
+    SurfaceCreated { surface: SurfaceId, client: Pid, title: String, width: usize, height: usize },
+    // Something new was committed to a surface
+    SurfaceDamaged,
+    SurfaceTitle { surface: SurfaceId, title: String },
+    // Destroyed by its client, or its client exited
+    SurfaceDestroyed { surface: SurfaceId },
+}
+
+static QUEUE: Mutex<VecDeque<Message>> = Mutex::new(VecDeque::new());
+
+pub fn post(message: Message) {
+    interrupts::without_interrupts(|| {
+        let mut queue = QUEUE.lock();
+        if queue.len() < QUEUE_CAPACITY {
+            queue.push_back(message);
+        }
+    });
+}
+
+// Everything posted since the last call, oldest first
+pub fn drain() -> Vec<Message> {
+    interrupts::without_interrupts(|| QUEUE.lock().drain(..).collect())
+}
\ No newline at end of file
@@ -0,0 +1,198 @@
+// src/character_picker.rs
+// The character picker (Ctrl+Cmd+Space): the emoji the system has icons
+// for, drawn twice their size in a grid, a category at a time. Typing
+// searches every category by name. The arrow keys or a click pick one, and
+// Enter or a click puts it into whatever has the focus, the way typing it
+// would if there were a key for it.
+use crate::graphics::{Graphics, Color, SCREEN_WIDTH};
+use crate::icons::{self, ICON_SIZE};
+use crate::keyboard::{Key, KeyEvent};
+use crate::widgets::TextField;
+use alloc::vec::Vec;
+
+const WIDTH: usize = 300;
+const HEIGHT: usize = 236;
+const Y: usize = 60;
+const MARGIN: usize = 10;
+const SEARCH_Y: usize = 26;
+const TABS_Y: usize = 54;
+const GRID_Y: usize = 76;
+const COLUMNS: usize = 10;
+const CELL: usize = 28;
+// Icons are drawn this many pixels to the icon's one
+const SCALE: usize = 2;
+
+// Each category with its characters and the names they're found by
+const CATEGORIES: [(&str, &[(char, &str)]); 4] = [
+    ("Objects", &[
+        ('📁', "folder"), ('📄', "document"), ('🔍', "magnifying glass"), ('🖥', "desktop computer"),
+        ('💻', "laptop"), ('📱', "phone"), ('💿', "disc"), ('⌨', "keyboard"), ('🔋', "battery"),
+        ('📷', "camera"), ('🗑', "wastebasket"), ('🔒', "lock"), ('📦', "package"), ('🔊', "speaker"),
+    ]),
+    ("Office", &[
+        ('📋', "clipboard"), ('📊', "bar chart"), ('📅', "calendar"), ('✉', "envelope"),
+        ('🖼', "framed picture"), ('🔖', "bookmark"), ('🏠', "house"), ('🌐', "globe"),
+    ]),
+    ("Nature", &[
+        ('🍎', "apple"), ('🦀', "crab"), ('🌈', "rainbow"), ('🔥', "fire"), ('✨', "sparkles"),
+    ]),
+    ("Symbols", &[
+        ('⚙', "gear"), ('⚠', "warning"), ('✅', "check mark"), ('🎯', "target"), ('📶', "signal bars"),
+        ('🎵', "musical note"),
+    ]),
+];
+
+pub struct CharacterPicker {
+    pub is_visible: bool,
+    search: TextField,
+    category: usize,
+    selected: usize,
+}
+
+impl CharacterPicker {
+    pub const fn new() -> Self {
+        Self { is_visible: false, search: TextField::new("Search"), category: 0, selected: 0 }
+    }
+    
+    pub fn show(&mut self) {
+        self.is_visible = true;
+        self.search.clear();
+        self.search.is_focused = true;
+        self.selected = 0;
+    }
+    
+    pub fn hide(&mut self) {
+        self.is_visible = false;
+    }
+    
+    // The category's characters, or those whose names have the search in
+    // them from every category
+    fn characters(&self) -> Vec<(char, &'static str)> {
+        let query = self.search.text.to_lowercase();
+        CATEGORIES.iter()
+            .enumerate()
+            .filter(|&(i, _)| !query.is_empty() || i == self.category)
+            .flat_map(|(_, (_, characters))| characters.iter().copied())
+            .filter(|(_, name)| name.contains(query.as_str()))
+            .collect()
+    }
+    
+    fn x() -> usize {
+        (SCREEN_WIDTH - WIDTH) / 2
+    }
+    
+    // Typing goes to the search, the arrows move through the grid and Tab
+    // through the categories. The character Enter picked, which closes the
+    // picker.
+    pub fn key(&mut self, event: &KeyEvent) -> Option<char> {
+        let count = self.characters().len();
+        match event.key {
+            Key::Escape => self.hide(),
+            Key::Enter => {
+                let picked = self.characters().get(self.selected).map(|&(ch, _)| ch);
+                if picked.is_some() {
+                    self.hide();
+                }
+                return picked;
+            }
+            Key::Tab => {
+                let step = if event.shift { CATEGORIES.len() - 1 } else { 1 };
+                self.category = (self.category + step) % CATEGORIES.len();
+                self.search.clear();
+                self.selected = 0;
+            }
+            Key::ArrowLeft => self.selected = self.selected.saturating_sub(1),
+            Key::ArrowRight => self.selected = (self.selected + 1).min(count.saturating_sub(1)),
+            Key::ArrowUp => self.selected = self.selected.checked_sub(COLUMNS).unwrap_or(self.selected),
+            Key::ArrowDown if self.selected + COLUMNS < count => self.selected += COLUMNS,
+            Key::Backspace => {
+                self.search.backspace();
+                self.selected = 0;
+            }
+            key => {
+                if let Some(ch) = key.to_char() {
+                    self.search.insert(ch);
+                    self.selected = 0;
+                }
+            }
+        }
+        None
+    }
+    
+    // A click on a tab shows its category and one on a character picks it;
+    // one outside closes the picker
+    pub fn click(&mut self, x: usize, y: usize) -> Option<char> {
+        let left = Self::x();
+        if !(left..left + WIDTH).contains(&x) || !(Y..Y + HEIGHT).contains(&y) {
+            self.hide();
+            return None;
+        }
+        let (x, y) = (x - left, y - Y);
+        if (TABS_Y..TABS_Y + 16).contains(&y) {
+            let mut tab_x = MARGIN;
+            for (i, (name, _)) in CATEGORIES.iter().enumerate() {
+                let width = name.len() * 8 + 8;
+                if (tab_x..tab_x + width).contains(&x) {
+                    self.category = i;
+                    self.search.clear();
+                    self.selected = 0;
+                }
+                tab_x += width + 4;
+            }
+            return None;
+        }
+        if y < GRID_Y || !(MARGIN..MARGIN + COLUMNS * CELL).contains(&x) {
+            return None;
+        }
+        let index = (y - GRID_Y) / CELL * COLUMNS + (x - MARGIN) / CELL;
+        let picked = self.characters().get(index).map(|&(ch, _)| ch);
+        if picked.is_some() {
+            self.hide();
+        }
+        picked
+    }
+    
+    pub fn draw(&self, graphics: &mut Graphics) {
+        if !self.is_visible {
+            return;
+        }
+        let x = Self::x();
+        graphics.draw_rounded_rect(x, Y, WIDTH, HEIGHT, Color::new(245, 245, 245));
+        graphics.draw_rect_outline(x, Y, WIDTH, HEIGHT, Color::GRAY);
+        graphics.draw_text("Characters", x + MARGIN, Y + 10, Color::BLACK);
+        self.search.draw(graphics, x + MARGIN, Y + SEARCH_Y, WIDTH - 2 * MARGIN);
+        
+        // Tabs, with none current while searching
+        let mut tab_x = x + MARGIN;
+        for (i, (name, _)) in CATEGORIES.iter().enumerate() {
+            let width = name.len() * 8 + 8;
+            if i == self.category && self.search.text.is_empty() {
+                graphics.draw_rounded_rect(tab_x, Y + TABS_Y, width, 16, Color::BLUE);
+                graphics.draw_text(name, tab_x + 4, Y + TABS_Y + 4, Color::WHITE);
+            } else {
+                graphics.draw_text(name, tab_x + 4, Y + TABS_Y + 4, Color::DARK_GRAY);
+            }
+            tab_x += width + 4;
+        }
+        
+        let mut selected_name = "";
+        for (i, (ch, name)) in self.characters().into_iter().enumerate() {
+            let cell_x = x + MARGIN + (i % COLUMNS) * CELL;
+            let cell_y = Y + GRID_Y + (i / COLUMNS) * CELL;
+            if i == self.selected {
+                graphics.draw_rounded_rect(cell_x, cell_y, CELL - 2, CELL - 2, Color::LIGHT_GRAY);
+                selected_name = name;
+            }
+            let Some(icon) = icons::emoji(ch) else { continue };
+            let inset = (CELL - 2 - ICON_SIZE * SCALE) / 2;
+            for py in 0..ICON_SIZE {
+                for px in 0..ICON_SIZE {
+                    if let Some(color) = icon.pixel(px, py) {
+                        graphics.draw_rect(cell_x + inset + px * SCALE, cell_y + inset + py * SCALE, SCALE, SCALE, color);
+                    }
+                }
+            }
+        }
+        graphics.draw_text(selected_name, x + MARGIN, Y + HEIGHT - 18, Color::DARK_GRAY);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,145 @@
+// src/chrome.rs
+// What the desktop and the windows draw around the apps: the wallpaper,
+// the menu bar and dock backgrounds and a window's frame. None of it needs
+// more than a Graphics, so the host tests can snapshot it; the desktop and
+// window manager draw what depends on their state on top.
+use crate::graphics::{Graphics, Color, SCREEN_WIDTH, SCREEN_HEIGHT};
+
+pub const TITLE_BAR_HEIGHT: usize = 36;
+
+// The wallpaper color, darkening towards the bottom of the screen
+pub fn draw_wallpaper(graphics: &mut Graphics, wallpaper: Color) {
+    for y in 0..SCREEN_HEIGHT {
+        let intensity = 1.0 - (y as f32 / SCREEN_HEIGHT as f32) * 0.3;
+        let r = (wallpaper.r as f32 * intensity) as u8;
+        let g = (wallpaper.g as f32 * intensity) as u8;
+        let b = (wallpaper.b as f32 * intensity) as u8;
+        
+        graphics.draw_rect(0, y, SCREEN_WIDTH, 1, Color::new(r, g, b));
+    }
+}
+
+pub fn draw_menu_bar(graphics: &mut Graphics, height: usize, background: Color, shadow: Color) {
+    graphics.draw_rect(0, 0, SCREEN_WIDTH, height, background);
+    graphics.draw_rect(0, height - 1, SCREEN_WIDTH, 1, shadow);
+}
+
+// The dock's shelf, with its shadow; the icons go on top
+pub fn draw_dock(graphics: &mut Graphics, dock_x: usize, dock_y: usize, dock_width: usize, dock_height: usize) {
+    // Draw dock reflection/shadow first
+    graphics.draw_rect(
+        dock_x + 2,
+        dock_y + 2,
+        dock_width,
+        dock_height + 10,
+        Color::new(0, 0, 0)
+    );
+    
+    // Draw dock background with glass effect
+    graphics.draw_rounded_rect(
+        dock_x,
+        dock_y,
+        dock_width,
+        dock_height,
+        Color::new(245, 245, 245)
+    );
+    
+    // Draw dock separator line
+    graphics.draw_rect(dock_x + 350, dock_y + 10, 2, dock_height - 20, Color::GRAY);
+}
+
+// A window without its content: the shadow, the background and the title
+// bar with the traffic lights and title
+pub struct WindowFrame<'a> {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    pub title: &'a str,
+    pub background: Color,
+    pub shadow_offset: usize,
+    pub focused: bool,
+    // Unsaved changes put a dot in the close button
+    pub edited: bool,
+}
+
+impl WindowFrame<'_> {
+    pub fn draw(&self, graphics: &mut Graphics) {
+        // Draw enhanced window shadow with blur effect
+        for i in 0..self.shadow_offset {
+            let shadow_color = Color::new(0, 0, 0);
+            graphics.draw_rounded_rect(
+                self.x + i + 2,
+                self.y + i + 2,
+                self.width,
+                self.height,
+                shadow_color
+            );
+        }
+        
+        // Draw window background with subtle gradient
+        self.draw_background_gradient(graphics);
+        
+        // Draw title bar with enhanced styling
+        self.draw_title_bar(graphics);
+    }
+    
+    fn draw_background_gradient(&self, graphics: &mut Graphics) {
+        for y in 0..self.height {
+            let intensity = 1.0 - (y as f32 / self.height as f32) * 0.05;
+            let r = (self.background.r as f32 * intensity) as u8;
+            let g = (self.background.g as f32 * intensity) as u8;
+            let b = (self.background.b as f32 * intensity) as u8;
+            
+            graphics.draw_rect(self.x, self.y + y, self.width, 1, Color::new(r, g, b));
+        }
+    }
+    
+    fn draw_title_bar(&self, graphics: &mut Graphics) {
+        let title_bar_color = if self.focused {
+            Color::new(240, 240, 240)
+        } else {
+            Color::new(250, 250, 250)
+        };
+        
+        // Draw title bar background
+        graphics.draw_rounded_rect(self.x, self.y, self.width, TITLE_BAR_HEIGHT, title_bar_color);
+        
+        // Draw title bar separator
+        graphics.draw_rect(self.x, self.y + TITLE_BAR_HEIGHT - 1, self.width, 1, Color::new(200, 200, 200));
+        
+        // Draw traffic light buttons with enhanced styling
+        let button_size = 16;
+        let button_y = self.y + 10;
+        let button_spacing = 24;
+        
+        // Close button (red) - enhanced with gradient
+        draw_traffic_light_button(graphics, self.x + 12, button_y, button_size, Color::new(255, 96, 96));
+        
+        if self.edited {
+            graphics.draw_rounded_rect(self.x + 17, button_y + 5, 6, 6, Color::new(120, 20, 20));
+        }
+        
+        // Minimize button (yellow)
+        draw_traffic_light_button(graphics, self.x + 12 + button_spacing, button_y, button_size, Color::new(255, 189, 68));
+        
+        // Maximize button (green)
+        draw_traffic_light_button(graphics, self.x + 12 + button_spacing * 2, button_y, button_size, Color::new(40, 200, 64));
+        
+        // Draw title text with enhanced typography
+        let title_x = self.x + 80;
+        let title_color = if self.focused { Color::BLACK } else { Color::GRAY };
+        graphics.draw_label(self.title, title_x, self.y + 12, title_color);
+    }
+}
+
+fn draw_traffic_light_button(graphics: &mut Graphics, x: usize, y: usize, size: usize, color: Color) {
+    // Draw button shadow
+    graphics.draw_rounded_rect(x + 1, y + 1, size, size, Color::new(0, 0, 0));
+    
+    // Draw button background
+    graphics.draw_rounded_rect(x, y, size, size, color);
+    
+    // Draw button highlight
+    graphics.draw_rounded_rect(x + 2, y + 2, size - 6, size - 8, Color::WHITE);
+}
@@ -0,0 +1,29 @@
+// src/clipboard.rs
+// The clipboard: the text Cmd+C last put there, for Cmd+V to take in any
+// app. Each copy replaces what was there and counts as a change, so an app
+// showing what's on it can tell when to look again.
+use alloc::string::String;
+use crate::sync::Mutex;
+
+struct Clipboard {
+    text: String,
+    changes: u64,
+}
+
+static CLIPBOARD: Mutex<Clipboard> = Mutex::new(Clipboard { text: String::new(), changes: 0 });
+
+pub fn set_text(text: &str) {
+    let mut clipboard = CLIPBOARD.lock();
+    clipboard.text = String::from(text);
+    clipboard.changes += 1;
+}
+
+// Empty when nothing has been copied
+pub fn text() -> String {
+    CLIPBOARD.lock().text.clone()
+}
+
+// Goes up with every copy
+pub fn change_count() -> u64 {
+    CLIPBOARD.lock().changes
+}
@@ -0,0 +1,191 @@
+// src/clock.rs
+// Monotonic time since boot, and the timer interrupt. The best time source
+// there is drives both: the HPET, then the local APIC's timer once the
+// APIC is set up, then the PIT. With an invariant TSC the counter is
+// calibrated once against the source and read directly; otherwise time is
+// read from the HPET, or advances with the PIT's timer interrupt.
+use crate::{cpu, hpet, kinfo};
+use core::arch::x86_64::_rdtsc;
+use core::ops::Add;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use core::time::Duration;
+use x86_64::instructions::port::Port;
+
+const PIT_FREQUENCY: u64 = 1_193_182;
+pub const TIMER_HZ: u64 = 100;
+const CALIBRATION_MS: u64 = 10;
+const CALIBRATION_RUNS: usize = 3;
+
+static TICKS: AtomicU64 = AtomicU64::new(0);
+static TSC_HZ: AtomicU64 = AtomicU64::new(0);
+static TSC_START: AtomicU64 = AtomicU64::new(0);
+static USES_HPET: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Hpet,
+    Pit,
+}
+
+impl Source {
+    pub fn name(self) -> &'static str {
+        match self {
+            Source::Hpet => "HPET",
+            Source::Pit => "PIT",
+        }
+    }
+}
+
+// Called from the timer interrupt
+pub fn tick() {
+    TICKS.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn ticks() -> u64 {
+    TICKS.load(Ordering::Relaxed)
+}
+
+fn program_timer() {
+    let divisor = (PIT_FREQUENCY / TIMER_HZ) as u16;
+    let mut command: Port<u8> = Port::new(0x43);
+    let mut channel0: Port<u8> = Port::new(0x40);
+    unsafe {
+        // Channel 0, lobyte/hibyte, rate generator
+        command.write(0b0011_0100);
+        channel0.write(divisor as u8);
+        channel0.write((divisor >> 8) as u8);
+    }
+}
+
+// Counts TSC cycles across CALIBRATION_MS of the HPET's counter
+fn calibrate_tsc_hpet() -> u64 {
+    let count = hpet::hz() * CALIBRATION_MS / 1000;
+    let first = hpet::counter();
+    let start = unsafe { _rdtsc() };
+    while hpet::counter() - first < count {
+        core::hint::spin_loop();
+    }
+    let end = unsafe { _rdtsc() };
+    (end - start) * (1000 / CALIBRATION_MS)
+}
+
+// Counts TSC cycles across a PIT channel 2 one-shot of CALIBRATION_MS
+fn calibrate_tsc() -> u64 {
+    let mut gate: Port<u8> = Port::new(0x61);
+    let mut command: Port<u8> = Port::new(0x43);
+    let mut channel2: Port<u8> = Port::new(0x42);
+    let count = (PIT_FREQUENCY * CALIBRATION_MS / 1000) as u16;
+    
+    unsafe {
+        // Speaker off, gate low while programming
+        let saved = gate.read();
+        gate.write(saved & !0x03);
+        // Channel 2, lobyte/hibyte, interrupt on terminal count
+        command.write(0b1011_0000);
+        channel2.write(count as u8);
+        channel2.write((count >> 8) as u8);
+        
+        gate.write((saved & !0x02) | 0x01);
+        let start = _rdtsc();
+        while gate.read() & 0x20 == 0 {
+            core::hint::spin_loop();
+        }
+        let end = _rdtsc();
+        gate.write(saved);
+        (end - start) * (1000 / CALIBRATION_MS)
+    }
+}
+
+pub fn init() {
+    let source = if hpet::init() && hpet::take_over_timer(TIMER_HZ) {
+        USES_HPET.store(true, Ordering::Relaxed);
+        Source::Hpet
+    } else {
+        program_timer();
+        Source::Pit
+    };
+    if cpu::features().invariant_tsc {
+        // Median of a few runs so one slow port access doesn't skew it
+        let mut runs = [0u64; CALIBRATION_RUNS];
+        for run in runs.iter_mut() {
+            *run = if source == Source::Hpet { calibrate_tsc_hpet() } else { calibrate_tsc() };
+        }
+        runs.sort_unstable();
+        TSC_HZ.store(runs[CALIBRATION_RUNS / 2], Ordering::Relaxed);
+        TSC_START.store(unsafe { _rdtsc() }, Ordering::Relaxed);
+    }
+    match source {
+        Source::Hpet => kinfo!("clock: time source HPET at {} MHz", hpet::hz() / 1_000_000),
+        Source::Pit => kinfo!("clock: time source PIT at {} Hz", TIMER_HZ),
+    }
+    if uses_tsc() {
+        kinfo!("clock: TSC at {} MHz, calibrated against the {}", tsc_hz() / 1_000_000, source.name());
+    }
+}
+
+pub fn source() -> Source {
+    if USES_HPET.load(Ordering::Relaxed) { Source::Hpet } else { Source::Pit }
+}
+
+// Whether time moves between timer interrupts
+pub fn is_continuous() -> bool {
+    uses_tsc() || source() == Source::Hpet
+}
+
+pub fn uses_tsc() -> bool {
+    TSC_HZ.load(Ordering::Relaxed) != 0
+}
+
+pub fn tsc_hz() -> u64 {
+    TSC_HZ.load(Ordering::Relaxed)
+}
+
+pub fn nanos() -> u64 {
+    let hz = TSC_HZ.load(Ordering::Relaxed);
+    if hz == 0 {
+        if source() == Source::Hpet {
+            return (hpet::counter() as u128 * 1_000_000_000 / hpet::hz() as u128) as u64;
+        }
+        return ticks() * (1_000_000_000 / TIMER_HZ);
+    }
+    let elapsed = unsafe { _rdtsc() } - TSC_START.load(Ordering::Relaxed);
+    (elapsed as u128 * 1_000_000_000 / hz as u128) as u64
+}
+
+pub fn millis() -> u64 {
+    nanos() / 1_000_000
+}
+
+// Busy-waits for driver setup delays; never call it from the desktop loop
+pub fn delay(duration: Duration) {
+    let end = Instant::now() + duration;
+    while Instant::now() < end {
+        core::hint::spin_loop();
+    }
+}
+
+// A point on the monotonic clock, like std's Instant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Instant(u64);
+
+impl Instant {
+    pub fn now() -> Self {
+        Instant(nanos())
+    }
+    
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        Duration::from_nanos(self.0.saturating_sub(earlier.0))
+    }
+    
+    pub fn elapsed(&self) -> Duration {
+        Instant::now().duration_since(*self)
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+    
+    fn add(self, rhs: Duration) -> Instant {
+        Instant(self.0 + rhs.as_nanos() as u64)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,288 @@
+// src/color_picker.rs
+// The system color picker: RGB sliders, a hex field, the theme's colors and
+// an eyedropper that picks any color on screen. Preference panes and apps
+// ask for a color with `pick` from wherever they run; the desktop opens the
+// dialog on its next frame and calls back with the color when OK is
+// clicked. Cancel drops the callback.
+use crate::appearance;
+use crate::bootargs::Theme;
+use crate::graphics::{Graphics, Color, SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::keyboard::{Key, KeyEvent};
+use crate::widgets::{Button, Slider, TextField};
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use crate::sync::Mutex;
+
+const WIDTH: usize = 260;
+const HEIGHT: usize = 250;
+// Layout, relative to the dialog
+const SWATCH_SIZE: usize = 56;
+const FIELD_X: usize = 88;
+const FIELD_WIDTH: usize = 100;
+const EYEDROPPER_Y: usize = 60;
+const SLIDER_X: usize = 32;
+const SLIDER_Y: usize = 104;
+const SLIDER_WIDTH: usize = 170;
+const SLIDER_SPACING: usize = 22;
+const PALETTE_Y: usize = 188;
+const PALETTE_SIZE: usize = 16;
+const PALETTE_SPACING: usize = 20;
+const BUTTON_Y: usize = HEIGHT - 30;
+const CANCEL_X: usize = 140;
+const OK_X: usize = 212;
+
+const EYEDROPPER: Button = Button::new("Eyedropper");
+const CANCEL: Button = Button::new("Cancel");
+const OK: Button = Button::new("OK");
+
+struct Request {
+    initial: Color,
+    on_pick: Box<dyn FnOnce(Color) + Send>,
+}
+
+// Asked for since the desktop last looked; a newer request replaces it
+static REQUEST: Mutex<Option<Request>> = Mutex::new(None);
+
+// Opens the color picker on `initial`; `on_pick` gets the color chosen
+pub fn pick(initial: Color, on_pick: impl FnOnce(Color) + Send + 'static) {
+    *REQUEST.lock() = Some(Request { initial, on_pick: Box::new(on_pick) });
+}
+
+// "#RRGGBB"
+pub fn hex(color: Color) -> String {
+    format!("#{:02X}{:02X}{:02X}", color.r, color.g, color.b)
+}
+
+// Six hex digits, with or without the leading '#'
+pub fn parse_hex(text: &str) -> Option<Color> {
+    let digits = text.strip_prefix('#').unwrap_or(text);
+    if digits.len() != 6 || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).ok();
+    Some(Color::new(channel(0)?, channel(2)?, channel(4)?))
+}
+
+// The current appearance's window background and text, then the system colors
+fn theme_colors() -> [Color; 11] {
+    let (background, text) = match appearance::theme() {
+        Theme::Light => (Color::new(248, 248, 248), Color::BLACK),
+        Theme::Dark => (Color::new(40, 40, 44), Color::WHITE),
+    };
+    [
+        background, text, Color::BLUE, Color::RED, Color::GREEN, Color::YELLOW,
+        Color::new(255, 149, 0), Color::new(175, 82, 222), Color::GRAY, Color::DARK_GRAY, Color::LIGHT_GRAY,
+    ]
+}
+
+pub struct ColorPicker {
+    pub is_visible: bool,
+    color: Color,
+    hex: TextField,
+    // Clicks pick the color under the pointer instead of using the dialog
+    eyedropper: bool,
+    // What the eyedropper is over, read from the last frame
+    sampled: Option<Color>,
+    // Channel whose slider is being dragged
+    dragging: Option<usize>,
+    on_pick: Option<Box<dyn FnOnce(Color) + Send>>,
+}
+
+impl ColorPicker {
+    pub fn new() -> Self {
+        Self {
+            is_visible: false,
+            color: Color::WHITE,
+            hex: TextField::new("#RRGGBB"),
+            eyedropper: false,
+            sampled: None,
+            dragging: None,
+            on_pick: None,
+        }
+    }
+    
+    // Opens the dialog if something asked for a color; whether it did
+    pub fn poll(&mut self) -> bool {
+        let request = match REQUEST.lock().take() {
+            Some(request) => request,
+            None => return false,
+        };
+        self.set_color(request.initial);
+        self.on_pick = Some(request.on_pick);
+        self.hex.is_focused = false;
+        self.eyedropper = false;
+        self.dragging = None;
+        self.is_visible = true;
+        true
+    }
+    
+    fn origin() -> (usize, usize) {
+        ((SCREEN_WIDTH - WIDTH) / 2, (SCREEN_HEIGHT - HEIGHT) / 2)
+    }
+    
+    fn set_color(&mut self, color: Color) {
+        self.color = color;
+        self.hex.text = hex(color);
+    }
+    
+    fn channel(&self, channel: usize) -> u8 {
+        [self.color.r, self.color.g, self.color.b][channel]
+    }
+    
+    fn set_channel(&mut self, channel: usize, value: f32) {
+        let value = (value * 255.0 + 0.5) as u8;
+        let mut color = self.color;
+        match channel {
+            0 => color.r = value,
+            1 => color.g = value,
+            _ => color.b = value,
+        }
+        self.set_color(color);
+    }
+    
+    fn slider(&self, channel: usize) -> Slider {
+        Slider { value: self.channel(channel) as f32 / 255.0, width: SLIDER_WIDTH }
+    }
+    
+    pub fn cancel(&mut self) {
+        self.is_visible = false;
+        self.on_pick = None;
+    }
+    
+    pub fn confirm(&mut self) {
+        self.is_visible = false;
+        if let Some(on_pick) = self.on_pick.take() {
+            on_pick(self.color);
+        }
+    }
+    
+    // The dialog, and with the eyedropper on, a loupe by the pointer. The
+    // color under the pointer is read before the loupe goes over it.
+    pub fn draw(&mut self, graphics: &mut Graphics, mouse_x: usize, mouse_y: usize) {
+        if !self.is_visible {
+            return;
+        }
+        let (x, y) = Self::origin();
+        graphics.draw_rounded_rect(x, y, WIDTH, HEIGHT, Color::new(236, 236, 236));
+        graphics.draw_rect_outline(x, y, WIDTH, HEIGHT, Color::GRAY);
+        graphics.draw_text("Colors", x + 12, y + 10, Color::BLACK);
+        
+        graphics.draw_rect(x + 16, y + 32, SWATCH_SIZE, SWATCH_SIZE, self.color);
+        graphics.draw_rect_outline(x + 16, y + 32, SWATCH_SIZE, SWATCH_SIZE, Color::GRAY);
+        self.hex.draw(graphics, x + FIELD_X, y + 32, FIELD_WIDTH);
+        EYEDROPPER.draw(graphics, x + FIELD_X, y + EYEDROPPER_Y);
+        if self.eyedropper {
+            graphics.draw_text("Click to pick, Esc to stop", x + 16, y + EYEDROPPER_Y + 30, Color::BLUE);
+        }
+        
+        for (channel, label) in ["R", "G", "B"].iter().enumerate() {
+            let row_y = y + SLIDER_Y + channel * SLIDER_SPACING;
+            graphics.draw_text(label, x + 16, row_y + 2, Color::BLACK);
+            self.slider(channel).draw(graphics, x + SLIDER_X, row_y);
+            graphics.draw_text(&format!("{}", self.channel(channel)), x + SLIDER_X + SLIDER_WIDTH + 10, row_y + 2, Color::DARK_GRAY);
+        }
+        
+        graphics.draw_text("Theme colors", x + 16, y + PALETTE_Y - 14, Color::DARK_GRAY);
+        for (i, color) in theme_colors().iter().enumerate() {
+            let swatch_x = x + 16 + i * PALETTE_SPACING;
+            graphics.draw_rect(swatch_x, y + PALETTE_Y, PALETTE_SIZE, PALETTE_SIZE, *color);
+            graphics.draw_rect_outline(swatch_x, y + PALETTE_Y, PALETTE_SIZE, PALETTE_SIZE, Color::GRAY);
+        }
+        
+        CANCEL.draw(graphics, x + CANCEL_X, y + BUTTON_Y);
+        graphics.draw_rounded_rect(x + OK_X, y + BUTTON_Y, OK.width(), Button::HEIGHT, Color::BLUE);
+        graphics.draw_text(OK.label, x + OK_X + 8, y + BUTTON_Y + 5, Color::WHITE);
+        
+        if self.eyedropper {
+            let sampled = graphics.get_pixel(mouse_x, mouse_y);
+            self.sampled = Some(sampled);
+            let loupe_x = (mouse_x + 12).min(SCREEN_WIDTH - 92);
+            let loupe_y = (mouse_y + 12).min(SCREEN_HEIGHT - 24);
+            graphics.draw_rect(loupe_x, loupe_y, 92, 24, Color::WHITE);
+            graphics.draw_rect_outline(loupe_x, loupe_y, 92, 24, Color::GRAY);
+            graphics.draw_rect(loupe_x + 4, loupe_y + 4, 16, 16, sampled);
+            graphics.draw_text(&hex(sampled), loupe_x + 26, loupe_y + 8, Color::BLACK);
+        }
+    }
+    
+    // The dialog is modal, so clicks outside it do nothing, except with the
+    // eyedropper on, where any click picks
+    pub fn click(&mut self, px: usize, py: usize) {
+        if self.eyedropper {
+            if let Some(color) = self.sampled.take() {
+                self.set_color(color);
+            }
+            self.eyedropper = false;
+            return;
+        }
+        let (x, y) = Self::origin();
+        if px < x || px >= x + WIDTH || py < y || py >= y + HEIGHT {
+            return;
+        }
+        self.hex.is_focused = px >= x + FIELD_X && px < x + FIELD_X + FIELD_WIDTH
+            && py >= y + 32 && py < y + 32 + TextField::HEIGHT;
+        
+        if EYEDROPPER.contains(px, py, x + FIELD_X, y + EYEDROPPER_Y) {
+            self.eyedropper = true;
+            self.sampled = None;
+        } else if CANCEL.contains(px, py, x + CANCEL_X, y + BUTTON_Y) {
+            self.cancel();
+        } else if OK.contains(px, py, x + OK_X, y + BUTTON_Y) {
+            self.confirm();
+        } else if let Some(channel) = (0..3).find(|&channel| {
+            self.slider(channel).contains(px, py, x + SLIDER_X, y + SLIDER_Y + channel * SLIDER_SPACING)
+        }) {
+            self.dragging = Some(channel);
+            self.drag(px);
+        } else if py >= y + PALETTE_Y && py < y + PALETTE_Y + PALETTE_SIZE && px >= x + 16 {
+            let offset = px - x - 16;
+            let colors = theme_colors();
+            if offset % PALETTE_SPACING < PALETTE_SIZE {
+                if let Some(&color) = colors.get(offset / PALETTE_SPACING) {
+                    self.set_color(color);
+                }
+            }
+        }
+    }
+    
+    // Pointer movement with the button held, for the sliders
+    pub fn drag(&mut self, px: usize) {
+        if let Some(channel) = self.dragging {
+            let (x, _) = Self::origin();
+            let value = self.slider(channel).value_at(px, x + SLIDER_X);
+            self.set_channel(channel, value);
+        }
+    }
+    
+    pub fn release(&mut self) {
+        self.dragging = None;
+    }
+    
+    // Esc stops the eyedropper or cancels, Enter takes the color; typing
+    // goes to the hex field, which takes the color once it's complete
+    pub fn key(&mut self, event: &KeyEvent) {
+        match event.key {
+            Key::Escape if self.eyedropper => self.eyedropper = false,
+            Key::Escape => self.cancel(),
+            Key::Enter => self.confirm(),
+            Key::Backspace if self.hex.is_focused => self.hex.backspace(),
+            key if self.hex.is_focused && self.hex.text.len() < 7 => {
+                let ch = match key.to_digit() {
+                    Some(digit) => char::from_digit(digit, 10),
+                    None => key.to_char().filter(|ch| ch.is_ascii_hexdigit()).map(|ch| ch.to_ascii_uppercase()),
+                };
+                if let Some(ch) = ch {
+                    if self.hex.text.is_empty() {
+                        self.hex.insert('#');
+                    }
+                    self.hex.insert(ch);
+                }
+            }
+            _ => return,
+        }
+        if let Some(color) = parse_hex(&self.hex.text) {
+            self.color = color;
+        }
+    }
+}
\ No newline at end of file
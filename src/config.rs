@@ -0,0 +1,210 @@
+// src/config.rs
+// Configuration files people edit by hand, in the shape INI and TOML
+// share: `[section]` headers over `key = value` lines, with `#` or `;`
+// starting a comment. Values are TOML's strings in double quotes (with
+// escapes) or single quotes (without), true and false, whole numbers
+// (underscores allowed) and decimals; anything else is taken as INI does,
+// as text to the end of the line. Keys before the first header belong to
+// the section named "". Arrays, tables in tables and dotted keys are
+// left out. A key set twice takes its last value.
+use alloc::string::String;
+use alloc::vec::Vec;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigValue {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+}
+
+impl ConfigValue {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            ConfigValue::String(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    // true and false, or the words INI files use for them
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            ConfigValue::Bool(value) => Some(*value),
+            ConfigValue::String(text) => match text.to_lowercase().as_str() {
+                "yes" | "on" | "1" => Some(true),
+                "no" | "off" | "0" => Some(false),
+                _ => None,
+            },
+            ConfigValue::Integer(number) => Some(*number != 0),
+            ConfigValue::Float(_) => None,
+        }
+    }
+
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            ConfigValue::Integer(number) => Some(*number),
+            _ => None,
+        }
+    }
+}
+
+// The line, counted from 1, and what's wrong with it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConfigError {
+    pub line: usize,
+    pub message: &'static str,
+}
+
+struct Section {
+    name: String,
+    entries: Vec<(String, ConfigValue)>,
+}
+
+pub struct Config {
+    // In file order, the "" section first
+    sections: Vec<Section>,
+}
+
+impl Config {
+    fn section_mut(&mut self, name: &str) -> &mut Vec<(String, ConfigValue)> {
+        let index = match self.sections.iter().position(|section| section.name == name) {
+            Some(index) => index,
+            None => {
+                self.sections.push(Section { name: String::from(name), entries: Vec::new() });
+                self.sections.len() - 1
+            }
+        };
+        &mut self.sections[index].entries
+    }
+
+    pub fn get(&self, section: &str, key: &str) -> Option<&ConfigValue> {
+        let section = self.sections.iter().find(|candidate| candidate.name == section)?;
+        section.entries.iter().find(|(name, _)| name == key).map(|(_, value)| value)
+    }
+
+    pub fn get_str(&self, section: &str, key: &str) -> Option<&str> {
+        self.get(section, key)?.as_str()
+    }
+
+    pub fn get_bool(&self, section: &str, key: &str) -> Option<bool> {
+        self.get(section, key)?.as_bool()
+    }
+
+    pub fn get_integer(&self, section: &str, key: &str) -> Option<i64> {
+        self.get(section, key)?.as_integer()
+    }
+
+    // Section names in file order, "" among them if keys come before the
+    // first header
+    pub fn sections(&self) -> impl Iterator<Item = &str> {
+        self.sections.iter().filter(|section| !section.entries.is_empty() || !section.name.is_empty()).map(|section| section.name.as_str())
+    }
+
+    // The keys and values of a section in file order
+    pub fn entries(&self, section: &str) -> &[(String, ConfigValue)] {
+        self.sections.iter().find(|candidate| candidate.name == section).map_or(&[], |section| &section.entries)
+    }
+}
+
+fn is_bare_key(key: &str) -> bool {
+    !key.is_empty() && key.bytes().all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'_' | b'-' | b'.'))
+}
+
+// A value in double quotes, from just after the opening one; returns it
+// and what follows the closing quote
+fn basic_string(text: &str) -> Result<(String, &str), &'static str> {
+    let mut value = String::new();
+    let mut chars = text.char_indices();
+    while let Some((i, ch)) = chars.next() {
+        match ch {
+            '"' => return Ok((value, &text[i + 1..])),
+            '\\' => {
+                let escaped = match chars.next().map(|(_, ch)| ch) {
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some('r') => '\r',
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    Some('u') => {
+                        let digits: String = chars.by_ref().take(4).map(|(_, ch)| ch).collect();
+                        let code = u32::from_str_radix(&digits, 16).map_err(|_| "bad \\u escape")?;
+                        char::from_u32(code).ok_or("bad \\u escape")?
+                    }
+                    _ => return Err("unknown escape"),
+                };
+                value.push(escaped);
+            }
+            ch => value.push(ch),
+        }
+    }
+    Err("unterminated string")
+}
+
+// What's left of a line once a comment is cut off
+fn before_comment(text: &str) -> &str {
+    let end = text.find(['#', ';']).unwrap_or(text.len());
+    text[..end].trim()
+}
+
+fn value(text: &str) -> Result<ConfigValue, &'static str> {
+    let (value, rest) = if let Some(quoted) = text.strip_prefix('"') {
+        let (value, rest) = basic_string(quoted)?;
+        (ConfigValue::String(value), rest)
+    } else if let Some(quoted) = text.strip_prefix('\'') {
+        let end = quoted.find('\'').ok_or("unterminated string")?;
+        (ConfigValue::String(String::from(&quoted[..end])), &quoted[end + 1..])
+    } else {
+        let text = before_comment(text);
+        let digits = text.replace('_', "");
+        let value = match text {
+            "true" => ConfigValue::Bool(true),
+            "false" => ConfigValue::Bool(false),
+            _ if digits.parse::<i64>().is_ok() => ConfigValue::Integer(digits.parse().unwrap_or(0)),
+            _ if text.contains('.') && digits.parse::<f64>().is_ok() => ConfigValue::Float(digits.parse().unwrap_or(0.0)),
+            _ => ConfigValue::String(String::from(text)),
+        };
+        (value, "")
+    };
+    if !before_comment(rest).is_empty() {
+        return Err("text after the value");
+    }
+    Ok(value)
+}
+
+pub fn parse(text: &str) -> Result<Config, ConfigError> {
+    let mut config = Config { sections: Vec::new() };
+    let mut section = String::new();
+    config.section_mut("");
+    for (i, line) in text.lines().enumerate() {
+        let error = |message| ConfigError { line: i + 1, message };
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(['#', ';']) {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('[') {
+            let (name, rest) = header.split_once(']').ok_or(error("missing ]"))?;
+            let name = name.trim();
+            if !is_bare_key(name) {
+                return Err(error("bad section name"));
+            }
+            if !before_comment(rest).is_empty() {
+                return Err(error("text after the section name"));
+            }
+            section = String::from(name);
+            config.section_mut(&section);
+            continue;
+        }
+        let (key, text) = line.split_once('=').ok_or(error("expected key = value"))?;
+        let key = key.trim().trim_matches('"');
+        if !is_bare_key(key) {
+            return Err(error("bad key"));
+        }
+        let value = value(text.trim()).map_err(error)?;
+        let entries = config.section_mut(&section);
+        match entries.iter_mut().find(|(name, _)| name == key) {
+            Some((_, old)) => *old = value,
+            None => entries.push((String::from(key), value)),
+        }
+    }
+    Ok(config)
+}
@@ -0,0 +1,257 @@
+// src/console.rs
+// The Console app. The sidebar has the live system log and the crash
+// reports from /var/crash. The log view tails the kernel log as it grows,
+// filtered by level and search text, and can be paused and exported. Lines
+// that scrolled out of the kernel's ring buffer before the view read them
+// show up as a gap marker.
+use crate::crash;
+use crate::graphics::{Graphics, Color};
+use crate::log::{self, Level, LogLine};
+use crate::styled_text::{Block, Paragraph, Span, Style, StyledText};
+use crate::users;
+use crate::vfs::{self, VfsError};
+use crate::widgets::{ScrollView, TextField};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::sync::Mutex;
+
+const SIDEBAR_WIDTH: usize = 150;
+const LINE_HEIGHT: usize = 14;
+const TOOLBAR_HEIGHT: usize = 56;
+// Lines kept by the view once read from the kernel log
+const MAX_LINES: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pane {
+    Log,
+    // Index into the report list, newest first
+    Report(usize),
+}
+
+struct ConsoleState {
+    pane: Pane,
+    // Least severe level shown
+    level: Level,
+    search: TextField,
+    scroll: ScrollView,
+    // Lines read so far, with gap markers
+    lines: Vec<LogLine>,
+    // Sequence number of the next line to read
+    cursor: u64,
+    paused: bool,
+    // Report names and the text of the one shown, read again when the file
+    // system changes or another report is shown, not every frame
+    reports: Vec<String>,
+    report: Option<(String, Option<String>)>,
+    generation: Option<u64>,
+}
+
+static STATE: Mutex<ConsoleState> = Mutex::new(ConsoleState {
+    pane: Pane::Log,
+    level: Level::Info,
+    search: TextField::new("Search"),
+    scroll: ScrollView { offset: 0, row_height: LINE_HEIGHT, follow_end: true },
+    lines: Vec::new(),
+    cursor: 0,
+    paused: false,
+    reports: Vec::new(),
+    report: None,
+    generation: None,
+});
+
+pub fn select_log() {
+    STATE.lock().pane = Pane::Log;
+}
+
+pub fn select_report(index: usize) {
+    STATE.lock().pane = Pane::Report(index);
+}
+
+pub fn set_level(level: Level) {
+    STATE.lock().level = level;
+}
+
+pub fn search_insert(ch: char) {
+    STATE.lock().search.insert(ch);
+}
+
+pub fn search_backspace() {
+    STATE.lock().search.backspace();
+}
+
+// `height` is the window content height, as passed to `draw`
+pub fn scroll(rows: isize, height: usize) {
+    let mut state = STATE.lock();
+    let total = filtered(&state).len();
+    state.scroll.scroll_by(rows, total, list_height(height));
+}
+
+fn list_height(height: usize) -> usize {
+    height - TOOLBAR_HEIGHT - 6
+}
+
+// Pausing freezes what is on screen; resuming catches up and jumps back to
+// the live tail
+pub fn toggle_pause() {
+    let mut state = STATE.lock();
+    state.paused = !state.paused;
+    if !state.paused {
+        state.scroll.follow_end = true;
+    }
+}
+
+// Reads lines logged since the last refresh
+fn refresh(state: &mut ConsoleState) {
+    let (lines, dropped) = log::read_since(state.cursor);
+    // Lines before the first read predate the view, so they aren't a gap
+    if dropped > 0 && state.cursor > 0 {
+        state.lines.push(LogLine {
+            seq: state.cursor,
+            level: Level::Warn,
+            time: lines.first().map_or(0, |line| line.time),
+            text: format!("--- {} lines dropped ---", dropped),
+        });
+    }
+    if let Some(last) = lines.last() {
+        state.cursor = last.seq + 1;
+    }
+    state.lines.extend(lines);
+    if state.lines.len() > MAX_LINES {
+        let excess = state.lines.len() - MAX_LINES;
+        state.lines.drain(..excess);
+    }
+}
+
+fn filtered(state: &ConsoleState) -> Vec<LogLine> {
+    let query = state.search.text.to_lowercase();
+    state.lines
+        .iter()
+        .cloned()
+        .filter(|line| line.level <= state.level)
+        .filter(|line| query.is_empty() || line.text.to_lowercase().contains(&query))
+        .collect()
+}
+
+// Writes the lines currently shown to the user's Documents folder
+pub fn export() -> Result<String, VfsError> {
+    let lines = filtered(&STATE.lock());
+    let mut text = String::new();
+    for line in lines.iter() {
+        text.push_str(&line.format());
+        text.push('\n');
+    }
+    let path = format!("/Users/{}/Documents/Console.log", users::user_name(users::current_uid()));
+    vfs::write_file(&path, text.as_bytes())?;
+    Ok(path)
+}
+
+// The lines currently shown, a code paragraph each so they print as is
+pub fn printable() -> StyledText {
+    let lines = filtered(&STATE.lock());
+    let paragraphs = lines
+        .iter()
+        .map(|line| Paragraph::new(Block::Code, alloc::vec![Span::new(&line.format(), Style::default())]))
+        .collect();
+    StyledText { paragraphs }
+}
+
+pub fn draw(graphics: &mut Graphics, x: usize, y: usize, width: usize, height: usize) {
+    let mut state = STATE.lock();
+    if !state.paused {
+        refresh(&mut state);
+    }
+    let generation = vfs::generation();
+    if state.generation != Some(generation) {
+        state.generation = Some(generation);
+        state.reports = crash::reports();
+        state.report = None;
+    }
+    
+    // Sidebar
+    graphics.draw_rect(x + 1, y, SIDEBAR_WIDTH, height - 1, Color::new(245, 245, 247));
+    graphics.draw_text("LOGS", x + 10, y + 12, Color::new(142, 142, 147));
+    draw_sidebar_row(graphics, x, y + 30, "System Log", state.pane == Pane::Log);
+    graphics.draw_text("CRASH REPORTS", x + 10, y + 58, Color::new(142, 142, 147));
+    for (i, name) in state.reports.iter().enumerate().take((height - 80) / 18) {
+        let label: String = name.trim_end_matches(".crash").chars().take(SIDEBAR_WIDTH / 8 - 2).collect();
+        draw_sidebar_row(graphics, x, y + 76 + i * 18, &label, state.pane == Pane::Report(i));
+    }
+    graphics.draw_rect(x + SIDEBAR_WIDTH + 1, y, 1, height - 1, Color::new(220, 220, 220));
+    
+    let main_x = x + SIDEBAR_WIDTH + 10;
+    let main_width = width - SIDEBAR_WIDTH - 20;
+    match state.pane {
+        Pane::Log => draw_log(graphics, &mut state, main_x, y, main_width, height),
+        Pane::Report(index) => draw_report(graphics, &mut state, index, main_x, y, main_width, height),
+    }
+}
+
+fn draw_sidebar_row(graphics: &mut Graphics, x: usize, row_y: usize, label: &str, selected: bool) {
+    if selected {
+        graphics.draw_rect(x + 1, row_y - 3, SIDEBAR_WIDTH, 18, Color::BLUE);
+    }
+    let color = if selected { Color::WHITE } else { Color::BLACK };
+    graphics.draw_text(label, x + 10, row_y, color);
+}
+
+fn draw_log(graphics: &mut Graphics, state: &mut ConsoleState, x: usize, y: usize, width: usize, height: usize) {
+    // Toolbar: level filter, pause and export, then the search field
+    let mut chip_x = x;
+    for level in Level::ALL.iter() {
+        let shown = *level <= state.level;
+        let color = if shown { Color::BLUE } else { Color::GRAY };
+        graphics.draw_text(level.name(), chip_x, y + 10, color);
+        chip_x += level.name().len() * 8 + 12;
+    }
+    let pause_label = if state.paused { "Resume" } else { "Pause" };
+    graphics.draw_text(pause_label, x + width - 110, y + 10, Color::BLACK);
+    graphics.draw_text("Export", x + width - 50, y + 10, Color::BLACK);
+    state.search.draw(graphics, x, y + 28, width);
+    
+    // Log lines
+    let lines = filtered(state);
+    let list_y = y + TOOLBAR_HEIGHT;
+    let list_height = list_height(height);
+    let columns = (width - 12) / 8;
+    let rows = state.scroll.visible_rows(lines.len(), list_height);
+    for (row, line) in lines[rows].iter().enumerate() {
+        let color = match line.level {
+            Level::Error => Color::RED,
+            Level::Warn => Color::new(200, 120, 0),
+            Level::Info => Color::BLACK,
+            Level::Debug => Color::GRAY,
+        };
+        let text: String = line.format().chars().take(columns).collect();
+        graphics.draw_text(&text, x, list_y + row * LINE_HEIGHT, color);
+    }
+    state.scroll.draw_scroller(graphics, x + width - 6, list_y, list_height, lines.len());
+}
+
+fn draw_report(graphics: &mut Graphics, state: &mut ConsoleState, index: usize, x: usize, y: usize, width: usize, height: usize) {
+    let columns = width / 8;
+    let name = match state.reports.get(index) {
+        Some(name) => name,
+        None => {
+            graphics.draw_text("No crash reports", x, y + 12, Color::GRAY);
+            return;
+        }
+    };
+    if state.report.as_ref().map_or(true, |(shown, _)| shown != name) {
+        let text = vfs::read_file(&vfs::join(crash::CRASH_DIR, name))
+            .ok()
+            .map(|data| String::from_utf8_lossy(&data).into_owned());
+        state.report = Some((name.clone(), text));
+    }
+    let text = match state.report.as_ref().and_then(|(_, text)| text.as_ref()) {
+        Some(text) => text,
+        None => {
+            graphics.draw_text("Cannot read report", x, y + 12, Color::GRAY);
+            return;
+        }
+    };
+    for (i, line) in text.lines().enumerate().take((height - 20) / LINE_HEIGHT) {
+        let line: String = line.chars().take(columns).collect();
+        graphics.draw_text(&line, x, y + 12 + i * LINE_HEIGHT, Color::BLACK);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,136 @@
+// src/cpu.rs
+// CPUID feature detection, done once at boot. Optional fast paths check
+// `features()` instead of assuming the hardware has them.
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::arch::x86_64::{CpuidResult, __cpuid_count};
+use spin::Once;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Features {
+    pub sse2: bool,
+    // Only set when the OS side (XSAVE enabled) is also there
+    pub avx: bool,
+    pub rdrand: bool,
+    pub rdseed: bool,
+    pub invariant_tsc: bool,
+    pub x2apic: bool,
+    // Enhanced REP MOVSB/STOSB: string instructions beat hand-written loops
+    pub erms: bool,
+    // MONITOR/MWAIT, for idling in C-states deeper than hlt's
+    pub mwait: bool,
+}
+
+impl Features {
+    pub fn names(&self) -> Vec<&'static str> {
+        let flags = [
+            (self.sse2, "SSE2"),
+            (self.avx, "AVX"),
+            (self.rdrand, "RDRAND"),
+            (self.rdseed, "RDSEED"),
+            (self.invariant_tsc, "Invariant TSC"),
+            (self.x2apic, "x2APIC"),
+            (self.erms, "ERMS"),
+            (self.mwait, "MWAIT"),
+        ];
+        flags.iter().filter(|(present, _)| *present).map(|(_, name)| *name).collect()
+    }
+}
+
+pub struct CpuInfo {
+    pub vendor: String,
+    pub brand: String,
+    pub family: u32,
+    pub model: u32,
+    pub stepping: u32,
+    pub features: Features,
+    // The MWAIT hint for the deepest C-state the CPU lists; 0, C1, when
+    // it lists none
+    pub deep_idle_hint: u32,
+}
+
+static INFO: Once<CpuInfo> = Once::new();
+
+fn cpuid(leaf: u32, subleaf: u32) -> CpuidResult {
+    // Leaves above the reported maximum return garbage, not zeros
+    let max = if leaf >= 0x8000_0000 {
+        unsafe { __cpuid_count(0x8000_0000, 0).eax }
+    } else {
+        unsafe { __cpuid_count(0, 0).eax }
+    };
+    if leaf > max {
+        return CpuidResult { eax: 0, ebx: 0, ecx: 0, edx: 0 };
+    }
+    unsafe { __cpuid_count(leaf, subleaf) }
+}
+
+fn register_bytes(registers: &[u32]) -> String {
+    let bytes: Vec<u8> = registers.iter().flat_map(|r| r.to_le_bytes()).collect();
+    String::from(String::from_utf8_lossy(&bytes).trim_matches(|c: char| c == '\0' || c == ' '))
+}
+
+fn detect() -> CpuInfo {
+    let leaf0 = cpuid(0, 0);
+    let vendor = register_bytes(&[leaf0.ebx, leaf0.edx, leaf0.ecx]);
+    
+    let leaf1 = cpuid(1, 0);
+    let leaf7 = cpuid(7, 0);
+    let power = cpuid(0x8000_0007, 0);
+    
+    let osxsave = leaf1.ecx & (1 << 27) != 0;
+    let features = Features {
+        sse2: leaf1.edx & (1 << 26) != 0,
+        avx: osxsave && leaf1.ecx & (1 << 28) != 0,
+        rdrand: leaf1.ecx & (1 << 30) != 0,
+        rdseed: leaf7.ebx & (1 << 18) != 0,
+        invariant_tsc: power.edx & (1 << 8) != 0,
+        x2apic: leaf1.ecx & (1 << 21) != 0,
+        erms: leaf7.ebx & (1 << 9) != 0,
+        mwait: leaf1.ecx & (1 << 3) != 0,
+    };
+    
+    // Leaf 5 counts each C-state's sub-states, four bits each from C0 up;
+    // the hint names a C-state less one and a sub-state
+    let mwait_leaf = cpuid(5, 0);
+    let deep_idle_hint = if features.mwait && mwait_leaf.ecx & 1 != 0 {
+        (1..8u32)
+            .rev()
+            .map(|state| (state, (mwait_leaf.edx >> (state * 4)) & 0xF))
+            .find(|&(_, sub_states)| sub_states != 0)
+            .map_or(0, |(state, sub_states)| ((state - 1) << 4) | (sub_states - 1))
+    } else {
+        0
+    };
+    
+    let mut brand_registers = Vec::new();
+    for leaf in 0x8000_0002..=0x8000_0004 {
+        let r = cpuid(leaf, 0);
+        brand_registers.extend_from_slice(&[r.eax, r.ebx, r.ecx, r.edx]);
+    }
+    let mut brand = register_bytes(&brand_registers);
+    if brand.is_empty() {
+        brand = vendor.clone();
+    }
+    
+    // Extended family/model only apply to family 0xF (and model for 6)
+    let base_family = (leaf1.eax >> 8) & 0xF;
+    let family = if base_family == 0xF { base_family + ((leaf1.eax >> 20) & 0xFF) } else { base_family };
+    let mut model = (leaf1.eax >> 4) & 0xF;
+    if base_family == 0x6 || base_family == 0xF {
+        model |= ((leaf1.eax >> 16) & 0xF) << 4;
+    }
+    
+    CpuInfo { vendor, brand, family, model, stepping: leaf1.eax & 0xF, features, deep_idle_hint }
+}
+
+pub fn init() -> &'static CpuInfo {
+    INFO.call_once(detect)
+}
+
+pub fn info() -> &'static CpuInfo {
+    init()
+}
+
+pub fn features() -> Features {
+    info().features
+}
\ No newline at end of file
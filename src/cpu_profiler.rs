@@ -0,0 +1,126 @@
+// src/cpu_profiler.rs
+// Sampling CPU profiler. While it runs, every timer tick records where the
+// interrupted code was: its instruction pointer and the return addresses
+// up its frame-pointer chain. Samples go into a fixed table, since they're
+// taken inside the interrupt; once it's full, further ticks are counted as
+// dropped. `export` folds them into collapsed stacks, one line per
+// distinct stack from the outermost frame in, with its count, which is
+// what flamegraph.pl and inferno take. Frames are named from the kernel's
+// symbol map, or left as addresses when it has none.
+use crate::backtrace::Frames;
+use crate::symbols;
+use crate::vfs::{self, VfsError};
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+use core::sync::atomic::{AtomicBool, Ordering};
+use crate::sync::Mutex;
+use x86_64::structures::idt::InterruptStackFrame;
+
+// About forty seconds at the timer rate
+const MAX_SAMPLES: usize = 4096;
+const MAX_DEPTH: usize = 16;
+
+struct Samples {
+    stacks: [[usize; MAX_DEPTH]; MAX_SAMPLES],
+    depths: [u8; MAX_SAMPLES],
+    count: usize,
+    dropped: u64,
+}
+
+static RUNNING: AtomicBool = AtomicBool::new(false);
+static SAMPLES: Mutex<Samples> = Mutex::new(Samples {
+    stacks: [[0; MAX_DEPTH]; MAX_SAMPLES],
+    depths: [0; MAX_SAMPLES],
+    count: 0,
+    dropped: 0,
+});
+
+#[derive(Debug, Clone, Copy)]
+pub struct Status {
+    pub running: bool,
+    pub samples: usize,
+    pub dropped: u64,
+}
+
+// Starts sampling afresh, throwing away the last run's samples
+pub fn start() {
+    let mut samples = SAMPLES.lock();
+    samples.count = 0;
+    samples.dropped = 0;
+    RUNNING.store(true, Ordering::Release);
+}
+
+pub fn stop() {
+    RUNNING.store(false, Ordering::Release);
+}
+
+pub fn status() -> Status {
+    let samples = SAMPLES.lock();
+    Status { running: RUNNING.load(Ordering::Acquire), samples: samples.count, dropped: samples.dropped }
+}
+
+// Called from the timer interrupt. Never inlined, so that the walk up from
+// its frame goes through the handler's to the interrupted code's.
+#[inline(never)]
+pub fn sample(stack_frame: &InterruptStackFrame) {
+    if !RUNNING.load(Ordering::Acquire) {
+        return;
+    }
+    // Someone is reading the samples; skip this tick
+    let mut samples = match SAMPLES.try_lock() {
+        Some(samples) => samples,
+        None => return,
+    };
+    if samples.count == MAX_SAMPLES {
+        samples.dropped += 1;
+        return;
+    }
+    let rip = stack_frame.instruction_pointer.as_u64() as usize;
+    
+    let index = samples.count;
+    let mut depth = 0;
+    for (slot, address) in samples.stacks[index].iter_mut().zip(core::iter::once(rip).chain(Frames::interrupted())) {
+        *slot = address;
+        depth += 1;
+    }
+    samples.depths[index] = depth;
+    samples.count += 1;
+}
+
+// The function a frame is in. Callers' frames are return addresses, just
+// after the call, so they're looked up a byte earlier in case the call
+// was the last thing in the function.
+fn frame_name(depth: usize, address: usize) -> String {
+    let lookup = if depth == 0 { address } else { address - 1 };
+    match symbols::resolve(lookup) {
+        // Semicolons separate frames; array types have them
+        Some(symbol) => symbol.name.replace(';', ","),
+        None => format!("{:#x}", address),
+    }
+}
+
+// The samples as collapsed stacks, outermost frame first
+pub fn collapsed() -> String {
+    let mut counts: BTreeMap<Vec<usize>, u64> = BTreeMap::new();
+    {
+        let samples = SAMPLES.lock();
+        for (stack, &depth) in samples.stacks.iter().zip(samples.depths.iter()).take(samples.count) {
+            *counts.entry(stack[..depth as usize].to_vec()).or_insert(0) += 1;
+        }
+    }
+    let mut out = String::new();
+    for (stack, count) in counts {
+        let frames: Vec<String> = stack.iter().enumerate().rev().map(|(i, &address)| frame_name(i, address)).collect();
+        let _ = writeln!(out, "{} {}", frames.join(";"), count);
+    }
+    out
+}
+
+// Writes the collapsed stacks to `path`; how many samples they hold
+pub fn export(path: &str) -> Result<usize, VfsError> {
+    vfs::write_file(path, collapsed().as_bytes())?;
+    Ok(status().samples)
+}
\ No newline at end of file
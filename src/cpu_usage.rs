@@ -0,0 +1,114 @@
+// src/cpu_usage.rs
+// Where the CPU's time goes, for the Activity Monitor. Every timer tick is
+// charged to whatever the CPU was doing: waiting in `power::idle`, working
+// for the process whose window is being drawn or handed input, or the
+// kernel's own work. Each second the counts become the last second's
+// shares and a point on the CPU history. Charging happens in the interrupt,
+// so everything is kept in fixed tables.
+use crate::clock;
+use crate::task::Pid;
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use crate::sync::Mutex;
+
+// Processes told apart in a second; the rest are charged to the kernel
+const MAX_TASKS: usize = 16;
+// Seconds of history the CPU graph shows
+pub const HISTORY: usize = 60;
+// Charged for work no process asked for
+const KERNEL: Pid = 0;
+
+static CURRENT: AtomicU32 = AtomicU32::new(KERNEL);
+static IDLE: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Copy)]
+struct Second {
+    // Ticks by process, the kernel's under KERNEL
+    tasks: [Option<(Pid, u32)>; MAX_TASKS],
+    idle: u32,
+    ticks: u32,
+}
+
+const EMPTY: Second = Second { tasks: [None; MAX_TASKS], idle: 0, ticks: 0 };
+
+struct Usage {
+    counting: Second,
+    last: Second,
+    // Percent busy each second, oldest first once it has wrapped
+    history: [u8; HISTORY],
+    next: usize,
+    seconds: usize,
+}
+
+static USAGE: Mutex<Usage> = Mutex::new(Usage { counting: EMPTY, last: EMPTY, history: [0; HISTORY], next: 0, seconds: 0 });
+
+// The window manager and the desktop bracket work for a process with
+// these, like `watchdog::drawing`
+pub fn running(pid: Option<Pid>) {
+    CURRENT.store(pid.unwrap_or(KERNEL), Ordering::Relaxed);
+}
+
+// Set around the CPU waiting for an interrupt
+pub fn set_idle(idle: bool) {
+    IDLE.store(idle, Ordering::Relaxed);
+}
+
+// Called from the timer interrupt
+pub fn tick() {
+    // Someone is reading the figures; this tick goes uncounted
+    let mut usage = match USAGE.try_lock() {
+        Some(usage) => usage,
+        None => return,
+    };
+    let second = &mut usage.counting;
+    second.ticks += 1;
+    if IDLE.load(Ordering::Relaxed) {
+        second.idle += 1;
+    } else {
+        let pid = CURRENT.load(Ordering::Relaxed);
+        let slot = second.tasks.iter().position(|slot| matches!(slot, Some((p, _)) if *p == pid))
+            .or_else(|| second.tasks.iter().position(Option::is_none))
+            .or_else(|| second.tasks.iter().position(|slot| matches!(slot, Some((p, _)) if *p == KERNEL)));
+        match slot.map(|slot| &mut second.tasks[slot]) {
+            Some(Some((_, ticks))) => *ticks += 1,
+            Some(slot) => *slot = Some((pid, 1)),
+            // Full, and no room even for the kernel's
+            None => {}
+        }
+    }
+    if second.ticks as u64 >= clock::TIMER_HZ {
+        let busy = 100 - second.idle * 100 / second.ticks;
+        let next = usage.next;
+        usage.history[next] = busy as u8;
+        usage.next = (next + 1) % HISTORY;
+        usage.seconds += 1;
+        usage.last = usage.counting;
+        usage.counting = EMPTY;
+    }
+}
+
+// Percent busy for each of the last seconds, oldest first
+pub fn history() -> Vec<u8> {
+    let usage = USAGE.lock();
+    let count = usage.seconds.min(HISTORY);
+    (0..count).map(|i| usage.history[(usage.next + HISTORY - count + i) % HISTORY]).collect()
+}
+
+// Percent of the last second each process had, busiest first; None is the
+// kernel
+pub fn tasks() -> Vec<(Option<Pid>, u8)> {
+    let last = USAGE.lock().last;
+    let mut tasks: Vec<(Option<Pid>, u8)> = last.tasks.iter()
+        .flatten()
+        .map(|&(pid, ticks)| ((pid != KERNEL).then_some(pid), (ticks * 100 / last.ticks.max(1)) as u8))
+        .collect();
+    tasks.sort_by_key(|&(_, percent)| Reverse(percent));
+    tasks
+}
+
+// Percent of the last second spent idle
+pub fn idle_percent() -> u8 {
+    let last = USAGE.lock().last;
+    (last.idle * 100 / last.ticks.max(1)) as u8
+}
\ No newline at end of file
@@ -0,0 +1,216 @@
+// src/crash.rs
+// Crash isolation for apps. Apps draw inside the desktop loop, so a panic or
+// CPU fault while a window is being drawn is pinned on that window's app.
+// The loop is abandoned the same way the watchdog does it, the app is
+// killed and the desktop carries on with a crash report dialog. Anything
+// that can't be pinned on an app, or that struck while a lock was held,
+// still takes the kernel down.
+//
+// Either way a crash report is written to /var/crash, where the Console app
+// lists them.
+use crate::backtrace::{self, Frames};
+use crate::task::Pid;
+use crate::vfs::{self, VfsError};
+use crate::{allocator, kerror, kinfo, log, rtc, serial, symbols, sync, users, watchdog};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+use core::panic::PanicInfo;
+use core::sync::atomic::{AtomicBool, Ordering};
+use crate::sync::Mutex;
+use x86_64::structures::idt::InterruptStackFrame;
+
+// Exit status of a crashed app, as if killed by SIGABRT
+pub const CRASH_EXIT_CODE: i32 = -6;
+pub const CRASH_DIR: &str = "/var/crash";
+const BACKTRACE_DEPTH: usize = 16;
+// Kernel log lines leading up to the crash included in the report
+const LOG_LINES: usize = 20;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Registers {
+    pub rip: u64,
+    pub rsp: u64,
+    // Zero when not known (faults)
+    pub rbp: u64,
+    pub rflags: u64,
+}
+
+impl Registers {
+    #[inline(always)]
+    fn here() -> Self {
+        let (rip, rsp, rbp, rflags): (u64, u64, u64, u64);
+        unsafe {
+            core::arch::asm!(
+                "lea {}, [rip]",
+                "mov {}, rsp",
+                "mov {}, rbp",
+                "pushfq",
+                "pop {}",
+                out(reg) rip, out(reg) rsp, out(reg) rbp, out(reg) rflags,
+            );
+        }
+        Self { rip, rsp, rbp, rflags }
+    }
+}
+
+pub struct Crash {
+    pub window: usize,
+    pub message: String,
+    pub registers: Registers,
+    pub backtrace: Vec<usize>,
+}
+
+static PENDING: Mutex<Option<Crash>> = Mutex::new(None);
+// Set from the moment a crash is caught until the desktop has taken it; a
+// second crash in between is not recoverable
+static RECOVERING: AtomicBool = AtomicBool::new(false);
+static KERNEL_PANICKED: AtomicBool = AtomicBool::new(false);
+
+// Claims the crash for the app being drawn, if there is one and the state
+// is sane enough to carry on: abandoning the loop while the heap or any
+// other lock is held would leave it locked
+fn claim(rsp: u64) -> Option<usize> {
+    let window = watchdog::current_window()?;
+    if !watchdog::on_loop_stack(rsp) || allocator::is_busy() || sync::locks_held() || RECOVERING.swap(true, Ordering::AcqRel) {
+        return None;
+    }
+    Some(window)
+}
+
+// Called first thing from the panic handler. Only returns if the panic
+// can't be isolated.
+pub fn handle_panic(info: &PanicInfo) {
+    let registers = Registers::here();
+    if let Some(window) = claim(registers.rsp) {
+        let message = format!("{}", info);
+        kerror!("crash: app in window {} panicked: {}", window, message);
+        let backtrace = backtrace::capture(BACKTRACE_DEPTH);
+        *PENDING.lock() = Some(Crash { window, message, registers, backtrace });
+        watchdog::unwind_to(recover);
+    }
+}
+
+// Called from exception handlers before they give up. Returns true if the
+// faulting app was isolated and the handler should return.
+pub fn handle_fault(stack_frame: &mut InterruptStackFrame, what: fmt::Arguments) -> bool {
+    let window = match claim(stack_frame.stack_pointer.as_u64()) {
+        Some(window) => window,
+        None => return false,
+    };
+    let message = format!("{} at {:?}", what, stack_frame.instruction_pointer);
+    kerror!("crash: app in window {} faulted: {}", window, message);
+    
+    let registers = Registers {
+        rip: stack_frame.instruction_pointer.as_u64(),
+        rsp: stack_frame.stack_pointer.as_u64(),
+        rbp: 0,
+        rflags: stack_frame.cpu_flags,
+    };
+    // The faulting instruction, then the handler's frames and on up
+    let mut backtrace = Vec::with_capacity(BACKTRACE_DEPTH);
+    backtrace.push(registers.rip as usize);
+    backtrace.extend(Frames::here().take(BACKTRACE_DEPTH - 1));
+    
+    *PENDING.lock() = Some(Crash { window, message, registers, backtrace });
+    watchdog::redirect(stack_frame, recover);
+    true
+}
+
+// Called from the panic handler once the panic is known to be fatal. Best
+// effort: the report only survives if /var is on a disk that gets synced.
+pub fn handle_kernel_panic(info: &PanicInfo) {
+    if KERNEL_PANICKED.swap(true, Ordering::AcqRel) {
+        return;
+    }
+    // Writing through the VFS could wait forever on a lock the panicking
+    // code holds, so then the serial port gets the gist instead
+    if allocator::is_busy() || sync::locks_held() {
+        serial::print_unlocked(format_args!("crash: kernel panic, no report written: {}\n", info));
+        for address in Frames::here().take(BACKTRACE_DEPTH) {
+            serial::print_unlocked(format_args!("  {:#018x}\n", address));
+        }
+        return;
+    }
+    let registers = Registers::here();
+    let backtrace = backtrace::capture(BACKTRACE_DEPTH);
+    let message = format!("{}", info);
+    match write_report("kernel", None, &message, &registers, &backtrace) {
+        Ok(path) => {
+            let _ = vfs::sync_all();
+            kinfo!("crash: report written to {}", path);
+        }
+        Err(err) => kerror!("crash: cannot write report: {:?}", err),
+    }
+}
+
+extern "C" fn recover() -> ! {
+    let crash = PENDING.lock().take();
+    if let Some(crash) = crash {
+        unsafe {
+            if let Some(desktop) = crate::DESKTOP.as_mut() {
+                desktop.report_crash(crash);
+            }
+        }
+    }
+    RECOVERING.store(false, Ordering::Release);
+    crate::run_desktop()
+}
+
+fn format_report(process: &str, pid: Option<Pid>, message: &str, registers: &Registers, backtrace: &[usize]) -> String {
+    let mut report = String::new();
+    let pid = pid.map_or(String::from("-"), |pid| format!("{}", pid));
+    let _ = writeln!(report, "Process:     {} [{}]", process, pid);
+    let _ = writeln!(report, "Date/Time:   {}", rtc::now().long());
+    let _ = writeln!(report, "OS Version:  RustOS {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(report, "Crashed:     {}", message);
+    let _ = writeln!(report);
+    let _ = writeln!(report, "Registers:");
+    let _ = writeln!(report, "  rip {:#018x}  rsp {:#018x}", registers.rip, registers.rsp);
+    let _ = writeln!(report, "  rbp {:#018x}  rflags {:#010x}", registers.rbp, registers.rflags);
+    let _ = writeln!(report);
+    let _ = writeln!(report, "Backtrace:");
+    for (i, &addr) in backtrace.iter().enumerate() {
+        let _ = match symbols::resolve(addr) {
+            Some(symbol) => writeln!(report, "  {:<2} {:#018x} {}+{:#x}", i, addr, symbol.name, symbol.offset),
+            None => writeln!(report, "  {:<2} {:#018x}", i, addr),
+        };
+    }
+    let _ = writeln!(report);
+    let _ = writeln!(report, "Recent log:");
+    for line in log::tail(LOG_LINES) {
+        let _ = writeln!(report, "  {}", line.format());
+    }
+    report
+}
+
+// Writes a report to /var/crash and returns its path. Reports are written
+// as root so any session can create the directory; they stay world-readable.
+pub fn write_report(
+    process: &str,
+    pid: Option<Pid>,
+    message: &str,
+    registers: &Registers,
+    backtrace: &[usize],
+) -> Result<String, VfsError> {
+    let report = format_report(process, pid, message, registers, backtrace);
+    let now = rtc::now();
+    let path = format!(
+        "{}/{}_{:04}-{:02}-{:02}-{:02}{:02}{:02}.crash",
+        CRASH_DIR, process.replace('/', "_"), now.year, now.month, now.day, now.hour, now.minute, now.second
+    );
+    users::as_root(|| {
+        vfs::create_dir_all(CRASH_DIR)?;
+        vfs::write_file(&path, report.as_bytes())
+    })?;
+    Ok(path)
+}
+
+// Report file names under /var/crash, newest first
+pub fn reports() -> Vec<String> {
+    let mut entries = vfs::read_dir(CRASH_DIR).unwrap_or_default();
+    entries.retain(|e| e.name.ends_with(".crash"));
+    entries.sort_by_key(|e| vfs::metadata(&vfs::join(CRASH_DIR, &e.name)).map_or(0, |m| m.modified));
+    entries.into_iter().rev().map(|e| e.name).collect()
+}
\ No newline at end of file
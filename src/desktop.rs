@@ -1,47 +1,258 @@
 // src/desktop.rs
+use crate::allocator;
+use crate::app_menu::{self, AppMenu, AppMenuAction};
+use crate::app_switcher::AppSwitcher;
+use crate::apps::{self, App};
+use crate::appearance;
+use crate::focus::{self, Policy};
+use crate::energy_saver;
+use crate::bootargs::{self, Theme};
+use crate::chrome;
 use crate::graphics::{Graphics, Color, SCREEN_WIDTH, SCREEN_HEIGHT};
-use crate::window_manager::{WindowManager, Window};
-use crate::notifications::NotificationCenter;
+use crate::window_manager::{WindowManager, WindowState, FORCE_QUIT_EXIT_CODE};
+use crate::notifications::{NotificationAction, NotificationCenter};
 use crate::spotlight::Spotlight;
-use crate::mission_control::MissionControl;
+use crate::mission_control::{MissionControl, MissionControlAction};
+use crate::get_info::GetInfoPanel;
+use crate::force_quit::{ForceQuitAction, ForceQuitDialog};
+use crate::crash::{self, Crash};
+use crate::bus::{self, Message};
+use crate::character_picker::CharacterPicker;
+use crate::clock;
+use crate::color_picker::ColorPicker;
+use crate::edit_menu::{EditMenu, EditMenuAction};
+use crate::cpu_usage;
+use crate::desktop_icons;
+use crate::do_not_disturb;
+use crate::dock::Dock;
+use crate::file_associations::{self, Handler};
+use crate::glyph_cache;
+use crate::hibernate;
+use crate::keychain;
+use crate::{disk_utility, display_server, elf, finder, userspace};
+use crate::input::{self, InputEvent};
+use crate::keyboard::{Key, KeyEvent, Keyboard};
+use crate::login_items;
+use crate::open_with::{Choice, OpenWithDialog};
+use crate::power;
+use crate::print;
+use crate::quick_look;
+use crate::print_dialog::{PrintDialog, PrintJob};
+use crate::save_sheet::{SaveChoice, SaveSheet};
+use crate::reminders;
+use crate::replay::{self, Check, Step};
+use crate::screen_recording;
+use crate::share::{self, ServicesMenu};
+use crate::swap::{self, Pressure};
+use crate::system_config::{self, Corner, CornerAction};
+use crate::thumbnails;
+use crate::block_cache;
+use crate::boot_slots::{self, Boot};
+use crate::{kinfo, kwarn};
+use crate::mouse::MouseButton;
+use crate::trackpad::{Gesture, SwipeDirection};
+use crate::transitions::{self, Transition};
+use crate::undo;
+use crate::users;
+use crate::weather;
+use crate::widgets::Marquee;
+use crate::window_menu::{WindowMenu, WindowMenuAction};
+use alloc::format;
 use alloc::string::String;
+use alloc::vec::Vec;
+
+// Dock layout, shared by drawing and clicking
+const DOCK_WIDTH: usize = 480;
+const DOCK_ICON_SIZE: usize = 48;
+const DOCK_ICON_SPACING: usize = 58;
+// Longer app names scroll through their tooltip
+const DOCK_TOOLTIP_WIDTH: usize = 96;
+const DOCK_TOOLTIP: Marquee = Marquee::new(6, Color::WHITE, Color::DARK_GRAY);
+// Hovering a running app's icon this long shows its windows over it
+const DOCK_PREVIEW_DELAY_MS: u64 = 600;
+const DOCK_PREVIEW_WIDTH: usize = 96;
+const DOCK_PREVIEW_HEIGHT: usize = 64;
+const DOCK_PREVIEW_MAX: usize = 5;
+// With the titles under the previews
+const DOCK_POPOVER_HEIGHT: usize = DOCK_PREVIEW_HEIGHT + 34;
+// Holding the green button this long takes the window full screen
+const LONG_PRESS_MS: u64 = 500;
+// The loop keeps its full frame rate this long after input, so drags and
+// typing stay smooth
+const ACTIVE_AFTER_INPUT_MS: u64 = 1000;
+// Left of the status icons and the Safe Boot label
+const RECORDING_INDICATOR_X: usize = SCREEN_WIDTH - 380;
+// Where the memory indicator ends: left of the status icons, or of the
+// Safe Boot label
+const MEMORY_INDICATOR_RIGHT: usize = SCREEN_WIDTH - 248;
+const SAFE_BOOT_MEMORY_INDICATOR_RIGHT: usize = SCREEN_WIDTH - 328;
+// The Do Not Disturb moon, the status icon furthest left
+const DO_NOT_DISTURB_X: usize = SCREEN_WIDTH - 180;
+// The weather, left of the status icons when it's turned on
+const WEATHER_X: usize = SCREEN_WIDTH - 240;
+
+// Apps open when the session starts, and whether they start minimized
+const STARTUP_APPS: [(&str, bool); 10] = [
+    ("Finder", false),
+    ("Terminal", false),
+    ("System Preferences", false),
+    ("Safari", false),
+    ("Activity Monitor", true),
+    ("Console", true),
+    ("Snake", true),
+    ("Preview", true),
+    ("Document Viewer", true),
+    ("Mail", true),
+];
 
 pub struct Desktop {
     window_manager: WindowManager,
     notification_center: NotificationCenter,
     spotlight: Spotlight,
     mission_control: MissionControl,
+    get_info: GetInfoPanel,
+    force_quit: ForceQuitDialog,
+    window_menu: WindowMenu,
+    edit_menu: EditMenu,
+    app_menu: AppMenu,
+    services_menu: ServicesMenu,
+    open_with: OpenWithDialog,
+    save_sheet: SaveSheet,
+    print_dialog: PrintDialog,
+    color_picker: ColorPicker,
+    dock: Dock,
+    keyboard: Keyboard,
+    // The first space's unless one was picked in System Preferences; the
+    // others have their own
     wallpaper_color: Color,
+    // Follows the appearance setting
+    theme: Theme,
+    // A slide or cross-fade playing instead of normal frames, and one asked
+    // for since the last frame
+    transition: Option<Transition>,
+    transition_requested: Option<transitions::Kind>,
+    // Input arrived since the last frame; it usually changes the screen
+    redraw_requested: bool,
+    // Seconds on the recording indicator, to redraw it when they change
+    recording_seconds: Option<u64>,
+    // Reclaiming happens when it rises
+    memory_pressure: Pressure,
+    // What the menu bar shows about memory, to redraw it when it changes
+    memory_indicator: Option<(String, Color)>,
     menu_bar_height: usize,
     dock_height: usize,
     dock_y: usize,
     time_counter: u32,
     mouse_x: usize,
     mouse_y: usize,
+    // Set once a real pointing device has moved the cursor
+    pointer_active: bool,
+    // When input last arrived, in clock millis
+    last_input_at: u64,
+    // Energy Saver turned the display off
+    display_asleep: bool,
+    // Left button held, so movement drags
+    mouse_down: bool,
+    // The dock app under the pointer, and since when, for its tooltip and
+    // window previews
+    dock_hover: Option<(&'static str, u64)>,
+    app_switcher: AppSwitcher,
+    character_picker: CharacterPicker,
+    // The window whose green button is held, and since when
+    zoom_press: Option<(usize, u64)>,
+    // In a full screen space, the pointer went to the top edge and the
+    // menu bar slid in
+    menu_bar_revealed: bool,
+    // Autohide's dock is up while the pointer is on it
+    dock_revealed: bool,
+    // The hot corner the pointer is in, which acts once on the way in
+    hot_corner: Option<Corner>,
     show_about_dialog: bool,
+    // Raised by the watchdog; the window is None if the hang was outside
+    // window drawing
+    show_hang_dialog: bool,
+    hung_window: Option<usize>,
+    // App name and panic message of the last app that crashed, and
+    // whether its report made it to /var/crash
+    crash_report: Option<(String, String, bool)>,
+    // Set from the Force Quit window; handled at the start of the next update
+    relaunch_requested: bool,
+    // Powering off once every window's unsaved changes are dealt with
+    shutting_down: bool,
 }
 
 impl Desktop {
     pub fn new() -> Self {
+        appearance::load();
+        focus::load();
+        energy_saver::load();
+        do_not_disturb::load();
+        weather::load();
+        system_config::load();
         Self {
             window_manager: WindowManager::new(),
             notification_center: NotificationCenter::new(),
             spotlight: Spotlight::new(),
             mission_control: MissionControl::new(),
-            wallpaper_color: Color::new(30, 130, 180),
+            get_info: GetInfoPanel::new(),
+            force_quit: ForceQuitDialog::new(),
+            window_menu: WindowMenu::new(),
+            edit_menu: EditMenu::new(),
+            app_menu: AppMenu::new(),
+            services_menu: ServicesMenu::new(),
+            open_with: OpenWithDialog::new(),
+            save_sheet: SaveSheet::new(),
+            print_dialog: PrintDialog::new(),
+            color_picker: ColorPicker::new(),
+            dock: Dock::new(),
+            keyboard: Keyboard::new(),
+            wallpaper_color: appearance::wallpaper_for(appearance::theme()),
+            theme: appearance::theme(),
+            transition: None,
+            transition_requested: None,
+            redraw_requested: false,
+            recording_seconds: None,
+            memory_pressure: Pressure::Normal,
+            memory_indicator: None,
             menu_bar_height: 24,
             dock_height: 60,
             dock_y: SCREEN_HEIGHT - 60,
             time_counter: 0,
             mouse_x: 320,
             mouse_y: 240,
+            pointer_active: false,
+            last_input_at: 0,
+            display_asleep: false,
+            mouse_down: false,
+            dock_hover: None,
+            app_switcher: AppSwitcher::new(),
+            character_picker: CharacterPicker::new(),
+            zoom_press: None,
+            menu_bar_revealed: false,
+            dock_revealed: false,
+            hot_corner: None,
             show_about_dialog: false,
+            show_hang_dialog: false,
+            hung_window: None,
+            crash_report: None,
+            relaunch_requested: false,
+            shutting_down: false,
         }
     }
     
     pub fn init(&mut self, graphics: &mut Graphics) {
-        // Create sample windows
-        self.create_sample_windows();
+        // The desktop session runs as the regular user
+        users::set_current(users::USER_UID, users::STAFF_GID);
+        if let Err(err) = keychain::unlock_at_login() {
+            kwarn!("keychain: login keychain stays locked: {:?}", err);
+        }
+        
+        // A hibernated session comes back instead of the usual windows
+        if !hibernate::resume(&mut self.window_manager, graphics) {
+            crate::heap_profiler::tagged("window_manager", || self.create_sample_windows());
+            self.open_login_items();
+        }
+        self.mark_boot_success();
         
         // Show welcome notification
         self.notification_center.show_notification(
@@ -56,7 +267,33 @@ impl Desktop {
         );
     }
     
+    // The kernel made it to the desktop, so its slot on the boot disk is the
+    // one to boot from now on
+    fn mark_boot_success(&mut self) {
+        let (title, message) = match boot_slots::mark_success() {
+            Ok(Boot::Usual) | Err(crate::vfs::VfsError::NotFound) => return,
+            Ok(Boot::Updated) => ("RustOS Updated", format!("Now running RustOS {}", crate::system_info::KERNEL_VERSION)),
+            Ok(Boot::RolledBack) => ("Update Didn't Start", String::from("RustOS went back to the version before it")),
+            Err(err) => {
+                kwarn!("boot: cannot mark the boot successful: {:?}", err);
+                return;
+            }
+        };
+        kinfo!("boot: {}", message);
+        self.notification_center.show_notification(String::from(title), message);
+    }
+    
+    // Back to front, for what drives the desktop from outside, like the
+    // benchmarks
+    #[cfg(feature = "bench")]
+    pub fn windows(&self) -> &[crate::window_manager::Window] {
+        self.window_manager.windows()
+    }
+    
     pub fn draw(&mut self, graphics: &mut Graphics) {
+        // Whole frame; "app drawing" below is the windows' share of it
+        crate::profile_scope!("compositing");
+        
         // Draw wallpaper with subtle gradient effect
         self.draw_wallpaper(graphics);
         
@@ -66,17 +303,44 @@ impl Desktop {
             return; // Don't draw desktop when Mission Control is active
         }
         
-        // Draw windows
-        self.window_manager.draw_all(graphics);
+        if !self.window_manager.is_fullscreen() {
+            desktop_icons::draw(graphics);
+        }
         
-        // Draw menu bar
-        self.draw_menu_bar(graphics);
+        // Draw windows
+        {
+            crate::profile_scope!("app drawing");
+            self.window_manager.draw_all(graphics);
+        }
+        self.save_sheet.draw(graphics);
         
-        // Draw dock with reflection effect
-        self.draw_dock(graphics);
+        // Draw menu bar and dock; a full screen space hides them, though the
+        // menu bar comes back while the pointer is at the top
+        if self.is_menu_bar_shown() {
+            self.draw_menu_bar(graphics);
+        }
+        if self.is_dock_shown() {
+            self.draw_dock(graphics);
+        }
         
         // Draw Spotlight if visible
         self.spotlight.draw(graphics);
+        self.character_picker.draw(graphics);
+        
+        // Draw Get Info panel if visible
+        self.get_info.draw(graphics);
+        if self.focused_app().as_deref() == Some("Finder") {
+            quick_look::draw(graphics);
+        }
+        
+        // Draw the window menu if a title bar was right-clicked
+        self.window_menu.draw(graphics);
+        self.edit_menu.draw(graphics);
+        self.app_menu.draw(graphics);
+        self.services_menu.draw(graphics);
+        if self.is_menu_bar_shown() {
+            weather::draw_popover(graphics, WEATHER_X);
+        }
         
         // Draw notifications
         self.notification_center.draw(graphics);
@@ -86,16 +350,66 @@ impl Desktop {
             self.draw_about_dialog(graphics);
         }
         
+        // Draw the not responding dialog if the watchdog fired
+        if self.show_hang_dialog {
+            self.draw_hang_dialog(graphics);
+        }
+        
+        // Draw the crash report if an app just crashed
+        if self.crash_report.is_some() {
+            self.draw_crash_dialog(graphics);
+        }
+        
+        // Draw the app chooser if a file opened that more than one app can open
+        self.open_with.draw(graphics);
+        self.print_dialog.draw(graphics);
+        
+        // Draw the color picker if something asked for a color
+        self.color_picker.draw(graphics, self.mouse_x, self.mouse_y);
+        
+        self.app_switcher.draw(graphics, &self.window_manager);
+        
+        // Draw Force Quit window if visible
+        self.force_quit.draw(graphics);
+        
         // Draw cursor
         self.draw_cursor(graphics, self.mouse_x, self.mouse_y);
     }
     
+    // Whether anything on screen is moving or about to, so the loop runs
+    // at its full frame rate instead of idling
+    pub fn is_animating(&self) -> bool {
+        // Nothing moves on a display that's off
+        if self.display_asleep {
+            return false;
+        }
+        self.transition.is_some()
+            || self.transition_requested.is_some()
+            || self.redraw_requested
+            || self.window_manager.needs_redraw()
+            || self.mission_control.is_visible
+            || !self.notification_center.is_empty()
+            || self.dock_hover.is_some()
+            || self.app_switcher.is_visible
+            || desktop_icons::is_animating()
+            || !self.pointer_active
+            || screen_recording::is_recording()
+            || replay::is_active()
+            || clock::millis() < self.last_input_at + ACTIVE_AFTER_INPUT_MS
+    }
+    
     pub fn update(&mut self, graphics: &mut Graphics) {
+        if self.relaunch_requested {
+            self.relaunch(graphics);
+            return;
+        }
+        
         self.time_counter += 1;
         
         // Update animations
         self.mission_control.update();
         self.notification_center.update();
+        self.sync_spaces();
         
         // Simulate some dynamic notifications
         if self.time_counter == 300 { // After 5 seconds
@@ -112,16 +426,1021 @@ impl Desktop {
             );
         }
         
-        // Simulate mouse movement
-        self.mouse_x = 320 + ((self.time_counter as f32 * 0.1).sin() * 50.0) as usize;
-        self.mouse_y = 240 + ((self.time_counter as f32 * 0.08).cos() * 30.0) as usize;
+        // Write back dirty disk blocks every few seconds
+        if self.time_counter % 300 == 0 {
+            crate::block_cache::periodic_writeback();
+        }
+        
+        // Simulate mouse movement until a real mouse shows up
+        if !self.pointer_active {
+            self.mouse_x = 320 + ((self.time_counter as f32 * 0.1).sin() * 50.0) as usize;
+            self.mouse_y = 240 + ((self.time_counter as f32 * 0.08).cos() * 30.0) as usize;
+        }
+        
+        // Appearance changes cross-fade to the new colors
+        let theme = appearance::theme();
+        if theme != self.theme {
+            self.theme = theme;
+            self.wallpaper_color = appearance::wallpaper_for(theme);
+            glyph_cache::invalidate();
+            self.transition_requested = Some(transitions::Kind::CrossFade);
+        }
+        
+        let recording_seconds = screen_recording::elapsed().map(|elapsed| elapsed.as_secs());
+        if recording_seconds != self.recording_seconds {
+            self.recording_seconds = recording_seconds;
+            self.redraw_requested = true;
+        }
+        
+        let memory_indicator = swap::indicator();
+        if memory_indicator != self.memory_indicator {
+            self.memory_indicator = memory_indicator;
+            self.redraw_requested = true;
+        }
+        
+        for reminder in reminders::due() {
+            let action = NotificationAction::Snooze(reminder.text.clone());
+            self.notification_center.show_with_action("Reminder".to_string(), reminder.text, action);
+            self.redraw_requested = true;
+        }
+        
+        // What came in while Do Not Disturb was on shows once it goes off
+        if do_not_disturb::update(self.window_manager.is_fullscreen()) {
+            if !do_not_disturb::is_active() {
+                self.notification_center.deliver_missed();
+            }
+            self.redraw_requested = true;
+        }
         
-        if self.window_manager.needs_redraw() {
+        // Energy Saver turns the display off after a while without input,
+        // and the next input turns it back on
+        let asleep = energy_saver::display_sleep_ms().is_some_and(|after| clock::millis() >= self.last_input_at + after);
+        if asleep != self.display_asleep {
+            self.display_asleep = asleep;
+            if asleep {
+                graphics.clear_screen(Color::BLACK);
+            } else {
+                self.redraw_requested = true;
+            }
+        }
+        if self.display_asleep {
+            return;
+        }
+        
+        if let Some(kind) = self.transition_requested.take() {
+            self.transition = Some(Transition::start(kind, graphics, |graphics| self.draw(graphics)));
+        }
+        if let Some(transition) = self.transition.as_mut() {
+            transition.draw(graphics);
+            if transition.is_complete() {
+                self.transition = None;
+                self.draw(graphics);
+            }
+        } else if self.redraw_requested || self.window_manager.needs_redraw() {
+            self.redraw_requested = false;
             self.draw(graphics);
         }
+        
+        if let Some(result) = screen_recording::capture(graphics) {
+            self.recording_ended(result);
+        }
+    }
+    
+    // Cmd+Shift+5 and the menu bar indicator
+    fn toggle_recording(&mut self) {
+        let (title, message) = match screen_recording::stop() {
+            Some(result) => return self.recording_ended(result),
+            None => match screen_recording::start() {
+                Ok(_) => ("Screen Recording", String::from("Recording; press Cmd+Shift+5 to stop")),
+                Err(err) => ("Screen Recording Failed", format!("{:?}", err)),
+            },
+        };
+        self.notification_center.show_notification(String::from(title), message);
+    }
+    
+    fn recording_ended(&mut self, result: Result<String, crate::vfs::VfsError>) {
+        let (title, message) = match result {
+            Ok(path) => ("Screen Recording Saved", String::from(crate::vfs::parent_and_name(&path).1)),
+            Err(err) => ("Screen Recording Failed", format!("{:?}", err)),
+        };
+        self.notification_center.show_notification(String::from(title), message);
+    }
+    
+    // The app in front, whose changes the Edit menu undoes
+    fn focused_app(&self) -> Option<String> {
+        let index = self.window_manager.focused_index()?;
+        Some(String::from(self.window_manager.windows().get(index)?.app_name()))
+    }
+    
+    // What the app in front has for the services
+    fn front_service_data(&self) -> Option<share::Data> {
+        let index = self.window_manager.focused_index()?;
+        self.window_manager.windows().get(index)?.service_data()
+    }
+    
+    fn hide_front_app(&mut self) {
+        if let Some(app) = self.window_manager.focused_index().and_then(|index| self.window_manager.windows()[index].app) {
+            self.window_manager.hide_app(app);
+        }
+    }
+    
+    // The app menu's rows, for the app in front; About with nothing in
+    // front is About This Mac
+    fn perform_app_menu(&mut self, action: AppMenuAction) {
+        match action {
+            AppMenuAction::About => match self.focused_app().as_deref().and_then(apps::find) {
+                Some(app) => self.get_info.show_app(app),
+                None => self.show_about_dialog = true,
+            },
+            AppMenuAction::Preferences => match self.focused_app().as_deref() {
+                Some("Terminal") => crate::terminal::show_preferences(),
+                _ => {
+                    self.window_manager.launch("System Preferences");
+                }
+            },
+            AppMenuAction::Service(service) => {
+                if let Some(data) = self.front_service_data() {
+                    share::request(service, data);
+                }
+            }
+            AppMenuAction::Hide => self.hide_front_app(),
+            AppMenuAction::Quit => {
+                if let Some(index) = self.window_manager.focused_index() {
+                    self.close(index);
+                }
+            }
+        }
+    }
+    
+    // Edit > Undo and Cmd+Z, Edit > Redo and Shift+Cmd+Z
+    fn undo(&mut self, redo: bool) {
+        let app = match self.focused_app() {
+            Some(app) => app,
+            None => return,
+        };
+        let (verb, result) = if redo { ("Redo", undo::redo(&app)) } else { ("Undo", undo::undo(&app)) };
+        if let Some(Err(err)) = result {
+            self.notification_center.show_notification(format!("Can't {}", verb), format!("{:?}", err));
+        }
+    }
+    
+    fn print(&mut self, job: PrintJob) {
+        let (title, message) = match print::print(&job.title, &job.text, job.format) {
+            Ok((path, 1)) => (String::from("Printed"), format!("1 page to {}", path)),
+            Ok((path, pages)) => (String::from("Printed"), format!("{} pages to {}", pages, path)),
+            Err(err) => (String::from("Couldn't Print"), format!("{:?}", err)),
+        };
+        self.notification_center.show_notification(title, message);
+    }
+    
+    // Opens the file in the app for its kind, asking which app if that
+    // isn't settled
+    fn open(&mut self, path: &str) {
+        // Apps built with the SDK run on their own
+        if elf::is_executable(path) {
+            if let Err(err) = userspace::launch(path) {
+                self.notification_center.show_notification(
+                    String::from("Can't Open App"),
+                    format!("{} couldn't start: {:?}", crate::vfs::parent_and_name(path).1, err),
+                );
+            }
+            return;
+        }
+        if self.window_manager.open_file(path) {
+            return;
+        }
+        match file_associations::handler(path) {
+            Handler::Choose(apps) => self.open_with.show(path, apps),
+            _ => self.notification_center.show_notification(
+                String::from("Can't Open File"),
+                format!("No app can open {}", crate::vfs::parent_and_name(path).1),
+            ),
+        }
+    }
+    
+    fn open_chosen(&mut self, choice: Choice) {
+        if choice.always {
+            if let Some(kind) = file_associations::kind_of(&choice.path) {
+                if let Err(err) = file_associations::set_default(kind, Some(choice.app)) {
+                    kwarn!("file associations: cannot save: {:?}", err);
+                }
+            }
+        }
+        self.window_manager.open_file_with(&choice.path, choice.app);
+    }
+    
+    // Called by the watchdog after it abandoned a stuck frame
+    pub fn report_hang(&mut self, window: Option<usize>) {
+        if let Some(index) = window {
+            self.window_manager.set_hung(index, true);
+        }
+        self.hung_window = window;
+        self.show_hang_dialog = true;
+    }
+    
+    // "Force Quit": kill the app whose window hung
+    pub fn force_quit_hung(&mut self) {
+        if let Some(index) = self.hung_window {
+            self.quit(index, FORCE_QUIT_EXIT_CODE);
+        }
+        self.show_hang_dialog = false;
+    }
+    
+    // "Wait": let the window draw again; the watchdog fires again if it
+    // is still stuck
+    pub fn wait_for_hung(&mut self) {
+        if let Some(index) = self.hung_window.take() {
+            self.window_manager.set_hung(index, false);
+        }
+        self.show_hang_dialog = false;
+    }
+    
+    // Called by crash isolation after an app panicked or faulted while
+    // drawing; the app is gone and its window goes with it
+    pub fn report_crash(&mut self, crash: Crash) {
+        let (app, pid) = match self.window_manager.windows().get(crash.window) {
+            Some(window) => (String::from(window.app_name()), window.pid),
+            None => return,
+        };
+        let saved = crash::write_report(&app, pid, &crash.message, &crash.registers, &crash.backtrace).is_ok();
+        self.quit(crash.window, crash::CRASH_EXIT_CODE);
+        self.crash_report = Some((app, crash.message, saved));
+    }
+    
+    pub fn dismiss_crash_report(&mut self) {
+        self.crash_report = None;
+    }
+    
+    // Ends the app behind the window with `exit_code`. The window closes,
+    // the dock forgets the app's badge and progress, and the hung window
+    // index keeps pointing at the same window.
+    fn quit(&mut self, index: usize, exit_code: i32) {
+        if let Some(window) = self.window_manager.windows().get(index) {
+            self.dock.forget(window.app_name());
+        }
+        // Window indexes shift, so the menu would point at the wrong one;
+        // the sheet too, and a shutdown it was part of stops
+        self.window_menu.hide();
+        if self.save_sheet.is_visible {
+            self.save_sheet.hide();
+            self.shutting_down = false;
+        }
+        self.window_manager.terminate(index, exit_code);
+        match self.hung_window {
+            Some(hung) if hung == index => {
+                self.hung_window = None;
+                self.show_hang_dialog = false;
+            }
+            Some(hung) if hung > index => self.hung_window = Some(hung - 1),
+            _ => {}
+        }
+    }
+    
+    // Quits the app in a window the user closed, asking first if it has
+    // unsaved changes
+    fn close(&mut self, index: usize) {
+        match self.window_manager.windows().get(index) {
+            Some(window) if window.is_edited => self.save_sheet.show(index, window),
+            Some(_) => self.quit(index, 0),
+            None => {}
+        }
+    }
+    
+    // The save sheet was answered
+    fn finish_close(&mut self, choice: SaveChoice) {
+        let index = self.save_sheet.window;
+        match choice {
+            SaveChoice::Cancel => {
+                self.shutting_down = false;
+                return;
+            }
+            SaveChoice::Save => {
+                let saved = self.window_manager.windows().get(index).map(|window| window.save());
+                if let Some(Err(err)) = saved {
+                    kwarn!("desktop: saving before closing failed: {:?}", err);
+                    self.notification_center.show_notification(String::from("Couldn't Save"), format!("The document stays open ({:?})", err));
+                    self.shutting_down = false;
+                    return;
+                }
+            }
+            SaveChoice::DontSave => {}
+        }
+        self.quit(index, 0);
+        if self.shutting_down {
+            self.shut_down();
+        }
+    }
+    
+    // Asks about each window with unsaved changes in turn, frontmost
+    // first, then powers off
+    fn shut_down(&mut self) {
+        self.shutting_down = true;
+        match self.window_manager.last_edited() {
+            Some(index) => self.save_sheet.show(index, &self.window_manager.windows()[index]),
+            None => {
+                self.hibernate();
+                power::shutdown();
+            }
+        }
+    }
+    
+    // With the hibernate boot flag the session goes to the sleep image
+    // before power off, to come back at the next boot
+    fn hibernate(&mut self) {
+        if !hibernate::enabled() {
+            return;
+        }
+        // Drawn afresh so the menu that led here isn't saved with it
+        let mut graphics = Graphics::new();
+        self.draw(&mut graphics);
+        match hibernate::save(&self.window_manager, &graphics) {
+            Ok(windows) => kinfo!("hibernate: saved {} windows", windows),
+            Err(err) => kwarn!("hibernate: cannot write the sleep image: {:?}", err),
+        }
+    }
+    
+    // Quits every app and starts the desktop session over, without a reboot
+    fn relaunch(&mut self, graphics: &mut Graphics) {
+        self.window_manager.close_all();
+        *self = Desktop::new();
+        self.init(graphics);
+        self.draw(graphics);
+    }
+    
+    pub fn handle_key(&mut self, event: &KeyEvent) {
+        if !event.pressed {
+            // Letting go of Cmd picks the app the switcher has highlighted
+            if self.app_switcher.is_visible && matches!(event.key, Key::LeftCmd | Key::RightCmd) {
+                if let Some(index) = self.app_switcher.confirm() {
+                    self.window_manager.bring_forward(index);
+                }
+            }
+            return;
+        }
+        
+        // Cmd+Alt+Esc opens Force Quit from anywhere
+        if event.key == Key::Escape && event.cmd && event.alt {
+            self.force_quit.show(&self.window_manager);
+            return;
+        }
+        
+        if self.crash_report.is_some() && !self.force_quit.is_visible {
+            if event.key == Key::Enter || event.key == Key::Escape {
+                self.dismiss_crash_report();
+            }
+            return;
+        }
+        
+        if self.force_quit.is_visible {
+            match event.key {
+                Key::ArrowUp => self.force_quit.move_selection(-1),
+                Key::ArrowDown => self.force_quit.move_selection(1),
+                Key::Escape => self.force_quit.hide(),
+                Key::Enter => match self.force_quit.confirm() {
+                    Some(ForceQuitAction::Quit(index)) => self.quit(index, FORCE_QUIT_EXIT_CODE),
+                    Some(ForceQuitAction::RelaunchDesktop) => self.relaunch_requested = true,
+                    None => {}
+                },
+                _ => {}
+            }
+            return;
+        }
+        
+        if self.color_picker.is_visible {
+            self.color_picker.key(event);
+            return;
+        }
+        
+        if self.open_with.is_visible {
+            if let Some(choice) = self.open_with.key(event.key) {
+                self.open_chosen(choice);
+            }
+            return;
+        }
+        
+        if self.save_sheet.is_visible {
+            if let Some(choice) = self.save_sheet.key(event) {
+                self.finish_close(choice);
+            }
+            return;
+        }
+        
+        if self.print_dialog.is_visible {
+            if let Some(job) = self.print_dialog.key(event.key) {
+                self.print(job);
+            }
+            return;
+        }
+        
+        if self.window_menu.is_visible && event.key == Key::Escape {
+            self.window_menu.hide();
+            return;
+        }
+        
+        if self.edit_menu.is_visible && event.key == Key::Escape {
+            self.edit_menu.hide();
+            return;
+        }
+        
+        if self.app_menu.is_visible && event.key == Key::Escape {
+            self.app_menu.hide();
+            return;
+        }
+        
+        if self.services_menu.is_visible && event.key == Key::Escape {
+            self.services_menu.hide();
+            return;
+        }
+        
+        if self.show_about_dialog && event.key == Key::Escape {
+            self.show_about_dialog = false;
+            return;
+        }
+        
+        // Ctrl+Cmd+Space opens and closes the character picker, whose
+        // characters go to the focused window
+        if event.key == Key::Space && event.cmd && event.ctrl {
+            if self.character_picker.is_visible {
+                self.character_picker.hide();
+            } else {
+                self.character_picker.show();
+            }
+            return;
+        }
+        
+        if self.character_picker.is_visible {
+            if let Some(ch) = self.character_picker.key(event) {
+                self.window_manager.text_focused(ch);
+            }
+            return;
+        }
+        
+        // Cmd+Space opens and closes Spotlight
+        if event.key == Key::Space && event.cmd {
+            if self.spotlight.is_visible {
+                self.spotlight.hide();
+            } else {
+                self.spotlight.show();
+            }
+            return;
+        }
+        
+        if self.spotlight.is_visible {
+            match event.key {
+                Key::Escape => self.spotlight.hide(),
+                Key::ArrowUp => self.spotlight.move_selection(-1),
+                Key::ArrowDown => self.spotlight.move_selection(1),
+                Key::Backspace => self.spotlight.backspace(),
+                Key::Tab => self.spotlight.complete(),
+                Key::Enter => {
+                    self.spotlight.record_launch();
+                    if let Some((text, delay)) = self.spotlight.selected_reminder() {
+                        reminders::add(&text, delay);
+                        self.notification_center.show_notification("Reminder Set".to_string(), format!("{} in {}", text, reminders::format_delay(delay)));
+                    } else if let Some(path) = self.spotlight.selected_path() {
+                        self.open(&path);
+                    } else if let Some(app) = self.spotlight.selected() {
+                        self.window_manager.launch(app);
+                    }
+                    self.spotlight.hide();
+                }
+                key => {
+                    if let Some(c) = key.to_char() {
+                        self.spotlight.add_character(c);
+                    }
+                }
+            }
+            return;
+        }
+        
+        if self.mission_control.is_visible {
+            match self.mission_control.key(event.key, &self.window_manager) {
+                Some(MissionControlAction::SwitchSpace(direction)) => self.switch_space(direction),
+                Some(MissionControlAction::Focus(index)) => {
+                    self.window_manager.focus_window(index);
+                    self.mission_control.hide();
+                }
+                Some(MissionControlAction::Exit) => self.mission_control.hide(),
+                None => {}
+            }
+            return;
+        }
+        
+        if self.app_switcher.is_visible {
+            match event.key {
+                Key::Tab => self.app_switcher.move_selection(if event.shift { -1 } else { 1 }),
+                Key::ArrowRight => self.app_switcher.move_selection(1),
+                Key::ArrowLeft => self.app_switcher.move_selection(-1),
+                Key::Escape => self.app_switcher.hide(),
+                Key::Enter => {
+                    if let Some(index) = self.app_switcher.confirm() {
+                        self.window_manager.bring_forward(index);
+                    }
+                }
+                _ => {}
+            }
+            return;
+        }
+        
+        if event.key == Key::Digit5 && event.cmd && event.shift {
+            self.toggle_recording();
+            return;
+        }
+        
+        // Cmd+Q quits the app in front
+        if event.key == Key::Q && event.cmd {
+            if let Some(index) = self.window_manager.focused_index() {
+                self.close(index);
+            }
+            return;
+        }
+        
+        // Cmd+H hides the app in front, all of its windows at once
+        if event.key == Key::H && event.cmd {
+            self.hide_front_app();
+            return;
+        }
+        
+        // Cmd+Tab opens the switcher on the app used before the one in
+        // front; Shift+Cmd+Tab on the one used longest ago
+        if event.key == Key::Tab && event.cmd {
+            self.app_switcher.show(&self.window_manager, event.shift);
+            return;
+        }
+        
+        // Cmd+P prints the document in front
+        if event.key == Key::P && event.cmd {
+            let printable = self.window_manager.focused_index().and_then(|index| self.window_manager.windows()[index].printable());
+            match printable {
+                Some((title, text)) => self.print_dialog.show(&title, text),
+                None => self.notification_center.show_notification(String::from("Nothing to Print"), String::from("The app in front has nothing to print")),
+            }
+            return;
+        }
+        
+        // Ctrl+Cmd+0 gathers the desktop's icons into stacks by kind, or
+        // spreads them out again
+        if event.key == Key::Digit0 && event.cmd && event.ctrl {
+            let message = if desktop_icons::toggle_stacks() { "Desktop items are grouped by kind" } else { "Desktop items are shown one by one" };
+            self.notification_center.show_notification(String::from("Stacks"), String::from(message));
+            return;
+        }
+        
+        // Ctrl+Cmd+F takes the window in front in and out of full screen
+        if event.key == Key::F && event.cmd && event.ctrl {
+            if let Some(index) = self.window_manager.focused_index() {
+                self.window_manager.toggle_fullscreen(index);
+            }
+            return;
+        }
+        
+        // Cmd+I: Get Info on the Finder's selection, or on the app in front
+        if event.key == Key::I && event.cmd {
+            let app = self.focused_app();
+            match finder::selected_path() {
+                Some(path) if app.as_deref() == Some("Finder") => self.get_info.show(&path),
+                _ => {
+                    if let Some(app) = app.as_deref().and_then(apps::find) {
+                        self.get_info.show_app(app);
+                    }
+                }
+            }
+            return;
+        }
+        
+        if event.key == Key::Z && event.cmd {
+            self.undo(event.shift);
+            return;
+        }
+        
+        self.window_manager.key_focused(event);
+    }
+    
+    // Two fingers scroll the focused window (content follows the fingers);
+    // three fingers open and close Mission Control and switch spaces
+    pub fn handle_gesture(&mut self, gesture: Gesture) {
+        if self.force_quit.is_visible || self.crash_report.is_some() || self.save_sheet.is_visible {
+            return;
+        }
+        match gesture {
+            Gesture::Scroll { dy, .. } => self.window_manager.scroll_focused(-dy),
+            Gesture::Swipe(SwipeDirection::Up) => self.mission_control.show(),
+            Gesture::Swipe(SwipeDirection::Down) => self.mission_control.hide(),
+            Gesture::Swipe(SwipeDirection::Left) => self.switch_space(1),
+            Gesture::Swipe(SwipeDirection::Right) => self.switch_space(-1),
+        }
+    }
+    
+    // Slides to the next space; inside Mission Control only the highlight moves
+    fn switch_space(&mut self, direction: i32) {
+        if self.mission_control.switch_space(direction) {
+            self.window_manager.show_space(self.mission_control.fullscreen_app());
+            if !self.mission_control.is_visible {
+                self.transition_requested = Some(transitions::Kind::slide(direction > 0));
+            }
+        }
+    }
+    
+    // Full screen windows each have a space after the desktops, made when
+    // the window goes full screen and taken away when it comes out or
+    // closes. Focusing a full screen window shows its space, and focusing
+    // any other window from one goes back to the first desktop.
+    fn sync_spaces(&mut self) {
+        // A space picked in Mission Control shows first, so its windows
+        // take focus from the one it left
+        self.window_manager.show_space(self.mission_control.fullscreen_app());
+        let before = self.mission_control.current_space;
+        let windows = self.window_manager.windows();
+        let fullscreen: Vec<String> = windows
+            .iter()
+            .filter(|w| w.state == WindowState::Fullscreen)
+            .map(|w| String::from(w.app_name()))
+            .collect();
+        let stale: Vec<String> = self.mission_control.desktop_spaces
+            .iter()
+            .filter_map(|space| space.fullscreen.clone())
+            .filter(|app| !fullscreen.contains(app))
+            .collect();
+        for app in stale.iter() {
+            self.mission_control.remove_fullscreen_space(app);
+        }
+        for app in fullscreen.iter() {
+            if !self.mission_control.desktop_spaces.iter().any(|space| space.fullscreen.as_ref() == Some(app)) {
+                self.mission_control.add_fullscreen_space(app);
+            }
+        }
+        match self.window_manager.focused_index().map(|i| &windows[i]) {
+            Some(window) if window.state == WindowState::Fullscreen => {
+                let app = window.app_name();
+                if let Some(index) = self.mission_control.desktop_spaces.iter().position(|space| space.fullscreen.as_deref() == Some(app)) {
+                    self.mission_control.current_space = index;
+                }
+            }
+            Some(window) if !window.is_pip && self.mission_control.fullscreen_app().is_some() => self.mission_control.current_space = 0,
+            _ => {}
+        }
+        self.window_manager.show_space(self.mission_control.fullscreen_app());
+        let after = self.mission_control.current_space;
+        if after != before && !self.mission_control.is_visible {
+            self.transition_requested = Some(transitions::Kind::slide(after > before));
+        }
+    }
+    
+    // The green button: full screen, for a long press or with Option held,
+    // or maximized otherwise. A full screen window comes out of it either way.
+    fn zoom(&mut self, index: usize, fullscreen: bool) {
+        let is_fullscreen = self.window_manager.windows().get(index).is_some_and(|w| w.state == WindowState::Fullscreen);
+        if fullscreen || is_fullscreen {
+            self.window_manager.toggle_fullscreen(index);
+        } else {
+            self.window_manager.toggle_maximized(index);
+        }
+    }
+    
+    fn is_menu_bar_shown(&self) -> bool {
+        !self.window_manager.is_fullscreen() || self.menu_bar_revealed
+    }
+    
+    // A full screen space hides the dock, and so does autohide until the
+    // pointer reaches the bottom of the screen
+    fn is_dock_shown(&self) -> bool {
+        !self.window_manager.is_fullscreen() && (!system_config::dock_autohide() || self.dock_revealed)
+    }
+    
+    fn perform_hot_corner(&mut self, action: CornerAction) {
+        match action {
+            CornerAction::MissionControl if self.mission_control.is_visible => self.mission_control.hide(),
+            CornerAction::MissionControl => self.mission_control.show(),
+            CornerAction::Spotlight => self.spotlight.show(),
+            CornerAction::DoNotDisturb => {
+                do_not_disturb::toggle(self.window_manager.is_fullscreen());
+                if !do_not_disturb::is_active() {
+                    self.notification_center.deliver_missed();
+                }
+            }
+        }
+    }
+    
+    // For focus follows mouse: the window under the pointer takes focus,
+    // unless a dialog, menu or the dock is in the way
+    fn focus_window_under_pointer(&mut self) {
+        let covered = self.save_sheet.is_visible || self.color_picker.is_visible || self.open_with.is_visible
+            || self.print_dialog.is_visible || self.window_menu.is_visible || self.edit_menu.is_visible || self.app_menu.is_visible
+            || self.services_menu.is_visible
+            || self.force_quit.is_visible || self.spotlight.is_visible || self.mission_control.is_visible
+            || self.character_picker.is_visible
+            || self.get_info.contains(self.mouse_x, self.mouse_y)
+            || self.dock_app_at(self.mouse_x, self.mouse_y).is_some();
+        if covered {
+            return;
+        }
+        if let Some(index) = self.window_manager.get_window_at_point(self.mouse_x, self.mouse_y) {
+            self.window_manager.focus_window(index);
+        }
+    }
+    
+    fn option_held(&self) -> bool {
+        self.keyboard.is_key_pressed(Key::LeftAlt) || self.keyboard.is_key_pressed(Key::RightAlt)
+    }
+    
+    fn handle_input(&mut self, event: InputEvent) {
+        match event {
+            InputEvent::Key { key, pressed } => {
+                let event = if pressed { self.keyboard.key_down(key) } else { self.keyboard.key_up(key) };
+                self.handle_key(&event);
+            }
+            InputEvent::MouseMove { dx, dy } => {
+                self.pointer_active = true;
+                self.mouse_x = (self.mouse_x as i32 + dx).clamp(0, SCREEN_WIDTH as i32 - 1) as usize;
+                self.mouse_y = (self.mouse_y as i32 + dy).clamp(0, SCREEN_HEIGHT as i32 - 1) as usize;
+                self.menu_bar_revealed = self.window_manager.is_fullscreen()
+                    && (self.mouse_y == 0 || (self.menu_bar_revealed && self.mouse_y < self.menu_bar_height));
+                let corner = system_config::hot_corner(self.mouse_x, self.mouse_y);
+                if corner.map(|(corner, _)| corner) != self.hot_corner {
+                    self.hot_corner = corner.map(|(corner, _)| corner);
+                    if let Some((_, Some(action))) = corner {
+                        self.perform_hot_corner(action);
+                    }
+                }
+                let hovered = self.dock_app_at(self.mouse_x, self.mouse_y).map(|app| app.name);
+                // On the way up to the previews the hover stays
+                let to_preview = self.dock_preview().is_some_and(|(x, y, width, _)| {
+                    self.mouse_x >= x && self.mouse_x < x + width && self.mouse_y >= y && self.mouse_y < self.dock_y
+                });
+                self.dock_revealed = self.mouse_y == SCREEN_HEIGHT - 1 || (self.dock_revealed && (self.mouse_y >= self.dock_y || to_preview));
+                if hovered != self.dock_hover.map(|(name, _)| name) && !(hovered.is_none() && to_preview) {
+                    self.dock_hover = hovered.map(|name| (name, clock::millis()));
+                }
+                if self.mouse_down && self.color_picker.is_visible {
+                    self.color_picker.drag(self.mouse_x);
+                } else if self.mouse_down {
+                    self.window_manager.drag_focused(dx, dy);
+                } else if focus::policy() == Policy::FollowsMouse {
+                    self.focus_window_under_pointer();
+                }
+            }
+            InputEvent::MouseButton { button: MouseButton::Left, pressed } => {
+                self.mouse_down = pressed;
+                if !pressed {
+                    self.window_manager.release();
+                    self.color_picker.release();
+                    if let Some((index, since)) = self.zoom_press.take() {
+                        if self.window_manager.zoom_button_at(self.mouse_x, self.mouse_y) == Some(index) {
+                            self.zoom(index, clock::millis() - since >= LONG_PRESS_MS);
+                        }
+                    }
+                }
+                if pressed {
+                    if self.character_picker.is_visible {
+                        if let Some(ch) = self.character_picker.click(self.mouse_x, self.mouse_y) {
+                            self.window_manager.text_focused(ch);
+                        }
+                    } else if self.app_switcher.is_visible {
+                        if let Some(index) = self.app_switcher.click(self.mouse_x, self.mouse_y) {
+                            self.window_manager.bring_forward(index);
+                        }
+                    } else if self.show_about_dialog {
+                        self.show_about_dialog = false;
+                    } else if self.save_sheet.is_visible {
+                        if let Some(choice) = self.save_sheet.click(self.mouse_x, self.mouse_y) {
+                            self.finish_close(choice);
+                        }
+                    } else if self.color_picker.is_visible {
+                        self.color_picker.click(self.mouse_x, self.mouse_y);
+                    } else if self.open_with.is_visible {
+                        if let Some(choice) = self.open_with.click(self.mouse_x, self.mouse_y) {
+                            self.open_chosen(choice);
+                        }
+                    } else if self.print_dialog.is_visible {
+                        if let Some(job) = self.print_dialog.click(self.mouse_x, self.mouse_y) {
+                            self.print(job);
+                        }
+                    } else if weather::is_popover_open() {
+                        weather::click_popover(self.mouse_x, self.mouse_y, WEATHER_X);
+                    } else if self.window_menu.is_visible {
+                        let index = self.window_menu.window;
+                        match self.window_menu.click(self.mouse_x, self.mouse_y) {
+                            Some(WindowMenuAction::Opacity(opacity)) => self.window_manager.set_opacity(index, opacity),
+                            Some(WindowMenuAction::PictureInPicture) => self.window_manager.toggle_pip(index),
+                            Some(WindowMenuAction::TileLeft) => self.window_manager.tile(index, WindowState::TiledLeft),
+                            Some(WindowMenuAction::TileRight) => self.window_manager.tile(index, WindowState::TiledRight),
+                            Some(WindowMenuAction::Close) => self.close(index),
+                            None => {}
+                        }
+                    } else if let Some(action) = self.notification_center.click(self.mouse_x, self.mouse_y) {
+                        match action {
+                            NotificationAction::Snooze(text) => {
+                                reminders::add(&text, reminders::SNOOZE_MS);
+                            }
+                        }
+                    } else if self.get_info.contains(self.mouse_x, self.mouse_y) {
+                        self.get_info.click(self.mouse_x, self.mouse_y);
+                    } else if self.edit_menu.is_visible {
+                        match self.edit_menu.click(self.mouse_x, self.mouse_y) {
+                            Some(EditMenuAction::Undo) => self.undo(false),
+                            Some(EditMenuAction::Redo) => self.undo(true),
+                            None => {}
+                        }
+                    } else if self.app_menu.is_visible {
+                        if let Some(action) = self.app_menu.click(self.mouse_x, self.mouse_y) {
+                            self.perform_app_menu(action);
+                        }
+                    } else if self.services_menu.is_visible {
+                        self.services_menu.click(self.mouse_x, self.mouse_y);
+                    } else if AppMenu::title_contains(self.focused_app().as_deref(), self.mouse_x, self.mouse_y) && self.is_menu_bar_shown() {
+                        let services = self.front_service_data().map(|data| share::for_data(&data).collect()).unwrap_or_default();
+                        self.app_menu.show(self.focused_app().as_deref(), services);
+                    } else if EditMenu::title_contains(app_menu::title_x(self.focused_app().as_deref(), "Edit"), self.mouse_x, self.mouse_y) && self.is_menu_bar_shown() {
+                        if let Some(app) = self.focused_app() {
+                            self.edit_menu.show(&app, app_menu::title_x(Some(&app), "Edit"));
+                        }
+                    } else if screen_recording::indicator_contains(self.mouse_x, self.mouse_y, RECORDING_INDICATOR_X, 8) && self.is_menu_bar_shown() {
+                        self.toggle_recording();
+                    } else if do_not_disturb::indicator_contains(self.mouse_x, self.mouse_y, DO_NOT_DISTURB_X, 8) && self.is_menu_bar_shown() {
+                        do_not_disturb::toggle(self.window_manager.is_fullscreen());
+                        if !do_not_disturb::is_active() {
+                            self.notification_center.deliver_missed();
+                        }
+                    } else if weather::indicator_contains(self.mouse_x, self.mouse_y, WEATHER_X, 8) && self.is_menu_bar_shown() {
+                        weather::toggle_popover();
+                    } else if let Some(index) = self.dock_preview_at(self.mouse_x, self.mouse_y) {
+                        self.window_manager.bring_forward(index);
+                        self.dock_hover = None;
+                    } else if self.window_manager.pip_window_at(self.mouse_x, self.mouse_y).is_some() {
+                        // PiP tiles don't take clicks; their menu has the controls
+                    } else if let Some(app) = self.dock_app_at(self.mouse_x, self.mouse_y) {
+                        self.window_manager.launch(app.name);
+                    } else if let Some(index) = self.window_manager.close_button_at(self.mouse_x, self.mouse_y) {
+                        self.close(index);
+                    } else if let Some(index) = self.window_manager.minimize_button_at(self.mouse_x, self.mouse_y) {
+                        self.window_manager.minimize_window(index);
+                    } else if let Some(index) = self.window_manager.zoom_button_at(self.mouse_x, self.mouse_y) {
+                        if self.option_held() {
+                            self.zoom(index, true);
+                        } else {
+                            self.zoom_press = Some((index, clock::millis()));
+                        }
+                    } else if self.window_manager.get_window_at_point(self.mouse_x, self.mouse_y).is_none() && !self.window_manager.is_fullscreen() {
+                        if let Some(path) = desktop_icons::click(self.mouse_x, self.mouse_y) {
+                            self.open(&path);
+                        }
+                    } else {
+                        self.window_manager.click_at(self.mouse_x, self.mouse_y, self.option_held());
+                    }
+                }
+            }
+            // Right-clicking a Dock icon adds the app to the login items or
+            // takes it off; right-clicking a title bar or PiP tile opens the
+            // window menu, and right-clicking inside a window goes to its app
+            InputEvent::MouseButton { button: MouseButton::Right, pressed: true } => {
+                let (x, y) = (self.mouse_x, self.mouse_y);
+                if let Some(app) = self.dock_app_at(x, y) {
+                    let message = if login_items::toggle(app.name) { "Opens at login" } else { "No longer opens at login" };
+                    self.notification_center.show_notification(String::from(app.name), String::from(message));
+                } else if let Some(index) = self.window_manager.pip_window_at(x, y).or_else(|| self.window_manager.title_bar_at(x, y)) {
+                    let window = &self.window_manager.windows()[index];
+                    self.window_menu.show(index, x, y, window.transparency, window.is_pip);
+                } else {
+                    // Apps without a menu of their own get the services
+                    // for what they have
+                    if !self.window_manager.right_click_at(x, y) {
+                        if let Some(data) = self.front_service_data().filter(|_| self.window_manager.get_window_at_point(x, y).is_some()) {
+                            self.services_menu.show(x, y, data);
+                        }
+                    }
+                    self.window_menu.hide();
+                }
+            }
+            InputEvent::MouseButton { .. } => {}
+            // Wheel up scrolls towards the top, like a two-finger swipe down
+            InputEvent::Scroll { delta } => self.window_manager.scroll_focused(-delta as isize),
+        }
+    }
+    
+    // Presses and releases `keys` in order, as typed on the keyboard
+    fn simulate_chord(&mut self, keys: &[Key]) {
+        for &key in keys {
+            let event = self.keyboard.key_down(key);
+            self.handle_key(&event);
+        }
+        for &key in keys.iter().rev() {
+            let event = self.keyboard.key_up(key);
+            self.handle_key(&event);
+        }
+    }
+    
+    // Messages from the bus go to the part of the desktop they're for
+    fn handle_message(&mut self, message: Message) {
+        match message {
+            Message::DockBadge { app, badge } => self.dock.apply_badge(app, badge),
+            Message::DockProgress { app, progress } => self.dock.apply_progress(app, progress),
+            Message::OpenFile { path, app } => {
+                if !app.is_some_and(|app| self.window_manager.open_file_with(&path, &app)) {
+                    self.open(&path);
+                }
+            }
+            Message::Edited { app, edited } => self.window_manager.set_edited(&app, edited),
+            Message::ShutDown => self.shut_down(),
+            Message::Service { name, data } => {
+                if let Some(service) = share::find(name) {
+                    if let Some(app) = service.app {
+                        self.window_manager.launch(app);
+                    }
+                    service.perform(&data);
+                }
+            }
+            Message::SurfaceCreated { surface, client, title, width, height } => {
+                self.window_manager.open_surface(surface, client, title, width, height);
+            }
+            Message::SurfaceDamaged => self.redraw_requested = true,
+            Message::SurfaceTitle { surface, title } => self.window_manager.set_surface_title(surface, title),
+            Message::SurfaceDestroyed { surface } => self.window_manager.close_surface(surface),
+        }
     }
     
     pub fn handle_events(&mut self) {
+        crate::profile_scope!("events");
+        
+        for gesture in crate::virtio_input::poll() {
+            // A replay's input is all there is
+            if replay::is_playing() {
+                continue;
+            }
+            self.handle_gesture(gesture);
+            self.redraw_requested = true;
+        }
+        crate::usb::poll();
+        crate::net::poll();
+        crate::remote_console::poll();
+        if crate::terminal::poll() {
+            self.redraw_requested = true;
+        }
+        if system_config::poll() {
+            self.redraw_requested = true;
+        }
+        if weather::poll() {
+            self.redraw_requested = true;
+        }
+        // Apps only work in the background while they're running, and the
+        // work is charged to their process
+        if self.window_manager.is_running("Safari") {
+            cpu_usage::running(self.window_manager.pid_of("Safari"));
+            crate::safari::poll();
+        }
+        if self.window_manager.is_running("Mail") {
+            cpu_usage::running(self.window_manager.pid_of("Mail"));
+            crate::mail::poll();
+        }
+        cpu_usage::running(None);
+        for step in replay::frame(input::drain()) {
+            match step {
+                Step::Input(event) => {
+                    self.handle_input(event);
+                    self.redraw_requested = true;
+                    self.last_input_at = clock::millis();
+                }
+                Step::Expect(check) => {
+                    let passed = self.check(&check);
+                    replay::report(&check, passed);
+                }
+            }
+        }
+        userspace::poll();
+        display_server::poll();
+        for message in bus::drain() {
+            self.handle_message(message);
+        }
+        if self.color_picker.poll() {
+            self.redraw_requested = true;
+        }
+        if self.get_info.poll() {
+            self.redraw_requested = true;
+        }
+        if finder::poll() {
+            self.redraw_requested = true;
+        }
+        // The Finder and the desktop's icons ask for thumbnails
+        if thumbnails::poll() {
+            self.redraw_requested = true;
+        }
+        if disk_utility::poll() {
+            self.redraw_requested = true;
+        }
+        
+        if allocator::take_failure() {
+            self.recover_memory();
+        }
+        let pressure = swap::pressure();
+        if pressure > self.memory_pressure {
+            self.reclaim(pressure);
+        }
+        self.memory_pressure = swap::pressure();
+        
+        // The demo below would get in the way of recorded input
+        if replay::is_active() {
+            return;
+        }
+        
         // Simulate keyboard events
         if self.time_counter == 180 { // Show Spotlight after 3 seconds
             self.spotlight.show();
@@ -149,18 +1468,74 @@ impl Desktop {
         if self.time_counter == 660 { // Hide About dialog
             self.show_about_dialog = false;
         }
+        
+        if self.time_counter == 720 { // Get Info on the welcome document
+            self.get_info.show("/Users/user/Documents/Welcome.txt");
+        }
+        
+        if self.time_counter == 840 { // Hide Get Info
+            self.get_info.hide();
+        }
+        
+        if self.time_counter == 900 { // Open Force Quit
+            self.simulate_chord(&[Key::LeftCmd, Key::LeftAlt, Key::Escape]);
+        }
+        
+        if self.time_counter == 960 { // Dismiss Force Quit
+            self.simulate_chord(&[Key::Escape]);
+        }
+    }
+    
+    // Whether the desktop is as a replay expects
+    fn check(&self, check: &Check) -> bool {
+        match check {
+            Check::Focused(app) => self.focused_app().as_deref() == Some(app.as_str()),
+            Check::Running(app) => self.window_manager.is_running(app),
+            Check::Stopped(app) => !self.window_manager.is_running(app),
+        }
+    }
+    
+    // Drops cached blocks first, then pages out what hidden windows keep if
+    // that wasn't enough
+    fn reclaim(&mut self, pressure: Pressure) {
+        let sectors = block_cache::reclaim();
+        let mut freed = sectors * crate::block::SECTOR_SIZE;
+        thumbnails::purge();
+        if pressure == Pressure::Critical || swap::pressure() != Pressure::Normal {
+            freed += self.window_manager.page_out_hidden();
+        }
+        kinfo!("swap: memory pressure {}, {} cached sectors dropped", pressure.name(), sectors);
+        swap::record(format!("Freed {} KiB", freed / 1024));
+    }
+    
+    // An allocation failed: reclaim everything there is, then close
+    // background apps, least recently used first, until pressure is down
+    fn recover_memory(&mut self) {
+        self.reclaim(Pressure::Critical);
+        let mut closed = Vec::new();
+        while swap::pressure() == Pressure::Critical {
+            match self.window_manager.close_least_recently_used() {
+                Some(name) => closed.push(name),
+                None => break,
+            }
+        }
+        kwarn!("allocator: out of memory, closed {:?}, reserve used: {}", closed, allocator::reserve_used());
+        let message = if closed.is_empty() {
+            String::from("Caches were purged to free memory")
+        } else {
+            format!("Closed {} to free memory", closed.join(", "))
+        };
+        self.notification_center.show_notification(String::from("Out of Memory"), message);
     }
     
     fn draw_wallpaper(&self, graphics: &mut Graphics) {
+        let wallpaper = match self.mission_control.current_space {
+            0 => appearance::wallpaper().unwrap_or(self.wallpaper_color),
+            _ => self.mission_control.current_wallpaper(),
+        };
+        
         // Create a gradient effect from top to bottom
-        for y in 0..SCREEN_HEIGHT {
-            let intensity = 1.0 - (y as f32 / SCREEN_HEIGHT as f32) * 0.3;
-            let r = (self.wallpaper_color.r as f32 * intensity) as u8;
-            let g = (self.wallpaper_color.g as f32 * intensity) as u8;
-            let b = (self.wallpaper_color.b as f32 * intensity) as u8;
-            
-            graphics.draw_rect(0, y, SCREEN_WIDTH, 1, Color::new(r, g, b));
-        }
+        chrome::draw_wallpaper(graphics, wallpaper);
         
         // Add some decorative elements
         self.draw_floating_particles(graphics);
@@ -195,78 +1570,58 @@ impl Desktop {
     }
     
     fn draw_menu_bar(&self, graphics: &mut Graphics) {
-        // Draw menu bar background with transparency
-        graphics.draw_rect(0, 0, SCREEN_WIDTH, self.menu_bar_height, Color::new(248, 248, 248));
+        let (background, shadow, text) = match self.theme {
+            Theme::Light => (Color::new(248, 248, 248), Color::new(220, 220, 220), Color::BLACK),
+            Theme::Dark => (Color::new(40, 40, 44), Color::new(20, 20, 22), Color::WHITE),
+        };
         
-        // Draw subtle shadow
-        graphics.draw_rect(0, self.menu_bar_height - 1, SCREEN_WIDTH, 1, Color::new(220, 220, 220));
+        // Draw menu bar background with a subtle shadow
+        chrome::draw_menu_bar(graphics, self.menu_bar_height, background, shadow);
         
         // Draw Apple logo
-        graphics.draw_text("🍎", 10, 8, Color::BLACK);
-        
-        // Draw application name
-        graphics.draw_text("RustOS", 40, 8, Color::BLACK);
+        graphics.draw_label("🍎", 10, 8, text);
         
-        // Draw menu items
-        let menus = ["File", "Edit", "View", "Window", "Help"];
-        let mut x = 100;
-        for menu in &menus {
-            graphics.draw_text(menu, x, 8, Color::BLACK);
-            x += menu.len() * 8 + 20;
+        // Draw the name of the app in front, then its menus
+        let app = self.focused_app();
+        app_menu::draw_title(graphics, app.as_deref(), text);
+        for menu in app_menu::MENUS {
+            graphics.draw_label(menu, app_menu::title_x(app.as_deref(), menu), 8, text);
         }
         
         // Draw right side status items
         let time_str = self.format_time();
-        graphics.draw_text(&time_str, SCREEN_WIDTH - 80, 8, Color::BLACK);
+        graphics.draw_text(&time_str, SCREEN_WIDTH - 80, 8, text);
         
         // System status icons
         graphics.draw_text("🔋", SCREEN_WIDTH - 120, 8, Color::GREEN);
-        graphics.draw_text("📶", SCREEN_WIDTH - 140, 8, Color::BLACK);
-        graphics.draw_text("🔍", SCREEN_WIDTH - 160, 8, Color::BLACK);
+        graphics.draw_text("📶", SCREEN_WIDTH - 140, 8, text);
+        graphics.draw_text("🔍", SCREEN_WIDTH - 160, 8, text);
+        do_not_disturb::draw_indicator(graphics, DO_NOT_DISTURB_X, 8);
+        weather::draw_indicator(graphics, WEATHER_X, 8, text);
+        if bootargs::safe_mode() {
+            graphics.draw_text("Safe Boot", SCREEN_WIDTH - 320, 8, Color::RED);
+        }
+        screen_recording::draw_indicator(graphics, RECORDING_INDICATOR_X, 8);
+        if let Some(indicator) = &self.memory_indicator {
+            let right = if bootargs::safe_mode() { SAFE_BOOT_MEMORY_INDICATOR_RIGHT } else { MEMORY_INDICATOR_RIGHT };
+            swap::draw_indicator(graphics, right, 8, indicator);
+        }
     }
     
     fn draw_dock(&self, graphics: &mut Graphics) {
-        let dock_width = 480;
+        let dock_width = DOCK_WIDTH;
         let dock_x = (SCREEN_WIDTH - dock_width) / 2;
         
-        // Draw dock reflection/shadow first
-        graphics.draw_rect(
-            dock_x + 2, 
-            self.dock_y + 2, 
-            dock_width, 
-            self.dock_height + 10, 
-            Color::new(0, 0, 0)
-        );
-        
-        // Draw dock background with glass effect
-        graphics.draw_rounded_rect(
-            dock_x, 
-            self.dock_y, 
-            dock_width, 
-            self.dock_height, 
-            Color::new(245, 245, 245)
-        );
-        
-        // Draw dock separator line
-        graphics.draw_rect(dock_x + 350, self.dock_y + 10, 2, self.dock_height - 20, Color::GRAY);
+        chrome::draw_dock(graphics, dock_x, self.dock_y, dock_width, self.dock_height);
         
         // Draw application icons
-        let apps = [
-            ("📁", "Finder"),
-            ("🌐", "Safari"),
-            ("📧", "Mail"),
-            ("📅", "Calendar"),
-            ("🎵", "Music"),
-            ("📸", "Photos"),
-            ("⚙️", "Preferences"),
-        ];
-        
-        let icon_size = 48;
-        let icon_spacing = 58;
+        let icon_size = DOCK_ICON_SIZE;
+        let icon_spacing = DOCK_ICON_SPACING;
         let start_x = dock_x + 20;
         let icon_y = self.dock_y + 6;
         
-        for (i, &(icon, _name)) in apps.iter().enumerate() {
+        for (i, app) in apps::dock().enumerate() {
+            let (icon, name) = (app.icon, app.name);
             let x = start_x + i * icon_spacing;
             
             // Add hover effect (simulate mouse over first icon)
@@ -289,22 +1644,94 @@ impl Desktop {
             // Draw app icon
             graphics.draw_text(icon, x + size/4, icon_y + size/4 + y_offset as usize, Color::BLACK);
             
-            // Draw running indicator (dot under icon)
-            if i < 3 { // First 3 apps are "running"
+            // Draw running indicator (dot under icon), faded while the
+            // app is hidden
+            if self.window_manager.is_running(name) {
                 graphics.draw_rounded_rect(
                     x + size/2 - 2, 
                     self.dock_y + self.dock_height - 8, 
                     4, 
                     4, 
-                    Color::BLACK
+                    if self.window_manager.is_hidden(name) { Color::GRAY } else { Color::BLACK }
                 );
             }
+            
+            // Badges and progress bars the app has set
+            self.dock.draw_decorations(graphics, name, x, (icon_y as i32 + y_offset) as usize, size);
         }
         
         // Draw trash icon
         let trash_x = dock_x + 370;
         graphics.draw_rounded_rect(trash_x, icon_y, icon_size, icon_size, Color::new(240, 240, 240));
         graphics.draw_text("🗑️", trash_x + 16, icon_y + 16, Color::BLACK);
+        
+        // The windows of the app under the pointer once it has stayed, or
+        // else its name, over its icon
+        if let Some((x, y, width, windows)) = self.dock_preview() {
+            self.draw_dock_preview(graphics, x, y, width, &windows);
+        } else if let Some((name, since)) = self.dock_hover {
+            if let Some(i) = apps::dock().position(|app| app.name == name) {
+                let width = (name.chars().count() * 8).min(DOCK_TOOLTIP_WIDTH) + 12;
+                let x = (start_x + i * icon_spacing + icon_size / 2).saturating_sub(width / 2);
+                let y = self.dock_y - 26;
+                graphics.draw_rounded_rect(x, y, width, 18, Color::DARK_GRAY);
+                DOCK_TOOLTIP.draw(graphics, name, x + 6, y + 5, DOCK_TOOLTIP_WIDTH, clock::millis() - since);
+            }
+        }
+    }
+    
+    fn draw_dock_preview(&self, graphics: &mut Graphics, x: usize, y: usize, width: usize, windows: &[usize]) {
+        graphics.draw_rounded_rect(x, y, width, DOCK_POPOVER_HEIGHT, Color::new(236, 236, 236));
+        graphics.draw_rect_outline(x, y, width, DOCK_POPOVER_HEIGHT, Color::GRAY);
+        for (n, &index) in windows.iter().enumerate() {
+            let window = &self.window_manager.windows()[index];
+            let preview_x = x + 8 + n * (DOCK_PREVIEW_WIDTH + 8);
+            window.draw_thumbnail(graphics, preview_x, y + 8, DOCK_PREVIEW_WIDTH, DOCK_PREVIEW_HEIGHT);
+            let title: String = window.title.chars().take(DOCK_PREVIEW_WIDTH / 8).collect();
+            let title_x = preview_x + (DOCK_PREVIEW_WIDTH - title.chars().count() * 8) / 2;
+            graphics.draw_text(&title, title_x, y + DOCK_PREVIEW_HEIGHT + 16, Color::BLACK);
+        }
+    }
+    
+    // The hovered dock app's windows, front first, and where their popover
+    // goes as x, y and width, once the pointer has stayed long enough
+    fn dock_preview(&self) -> Option<(usize, usize, usize, Vec<usize>)> {
+        let (name, since) = self.dock_hover?;
+        if clock::millis() - since < DOCK_PREVIEW_DELAY_MS {
+            return None;
+        }
+        let mut windows = self.window_manager.windows_of(name);
+        windows.truncate(DOCK_PREVIEW_MAX);
+        if windows.is_empty() {
+            return None;
+        }
+        let i = apps::dock().position(|app| app.name == name)?;
+        let width = windows.len() * (DOCK_PREVIEW_WIDTH + 8) + 8;
+        let center = (SCREEN_WIDTH - DOCK_WIDTH) / 2 + 20 + i * DOCK_ICON_SPACING + DOCK_ICON_SIZE / 2;
+        let x = center.saturating_sub(width / 2).min(SCREEN_WIDTH - width);
+        let y = self.dock_y.saturating_sub(DOCK_POPOVER_HEIGHT + 6);
+        Some((x, y, width, windows))
+    }
+    
+    // The window whose preview is under the point
+    fn dock_preview_at(&self, x: usize, y: usize) -> Option<usize> {
+        let (popover_x, popover_y, _, windows) = self.dock_preview()?;
+        if y < popover_y + 8 || y >= popover_y + DOCK_POPOVER_HEIGHT || x < popover_x + 8 {
+            return None;
+        }
+        let n = (x - popover_x - 8) / (DOCK_PREVIEW_WIDTH + 8);
+        let within = (x - popover_x - 8) % (DOCK_PREVIEW_WIDTH + 8) < DOCK_PREVIEW_WIDTH;
+        windows.get(n).copied().filter(|_| within)
+    }
+    
+    // The dock app whose icon is under the point, laid out like `draw_dock`
+    fn dock_app_at(&self, x: usize, y: usize) -> Option<&'static App> {
+        let start_x = (SCREEN_WIDTH - DOCK_WIDTH) / 2 + 20;
+        let icon_y = self.dock_y + 6;
+        if !self.is_dock_shown() || x < start_x || y < icon_y || y >= icon_y + DOCK_ICON_SIZE || (x - start_x) % DOCK_ICON_SPACING >= DOCK_ICON_SIZE {
+            return None;
+        }
+        apps::dock().nth((x - start_x) / DOCK_ICON_SPACING)
     }
     
     fn draw_about_dialog(&self, graphics: &mut Graphics) {
@@ -330,13 +1757,26 @@ impl Desktop {
         // Draw content
         let content_y = dialog_y + 60;
         graphics.draw_text("RustOS", dialog_x + 180, content_y, Color::BLACK);
-        graphics.draw_text("Version 2.0.0", dialog_x + 160, content_y + 30, Color::GRAY);
+        graphics.draw_text(&format!("Version {}", crate::system_info::KERNEL_VERSION), dialog_x + 160, content_y + 30, Color::GRAY);
         
-        // Draw system info
-        graphics.draw_text("Processor: Custom Rust CPU", dialog_x + 20, content_y + 80, Color::BLACK);
-        graphics.draw_text("Memory: 1024 MB", dialog_x + 20, content_y + 100, Color::BLACK);
-        graphics.draw_text("Graphics: VGA Compatible", dialog_x + 20, content_y + 120, Color::BLACK);
-        graphics.draw_text("Storage: Virtual Disk", dialog_x + 20, content_y + 140, Color::BLACK);
+        // Draw system info, cut to the dialog width
+        let info = crate::system_info::collect();
+        let storage = if info.storage.is_empty() { String::from("None") } else { info.storage.join(", ") };
+        let rows = [
+            format!("Processor: {}", info.processor),
+            format!("Memory: {}", info.memory),
+            format!("Kernel heap: {}", info.heap),
+            format!("Graphics: {}", info.display),
+            format!("Storage: {}", storage),
+            format!("Kernel: {}", info.kernel),
+        ];
+        let columns = (dialog_width - 40) / 8;
+        for (i, row) in rows.iter().enumerate() {
+            let row: String = row.chars().take(columns).collect();
+            graphics.draw_text(&row, dialog_x + 20, content_y + 60 + i * 18, Color::BLACK);
+        }
+        let features: String = format!("Features: {}", crate::cpu::features().names().join(" ")).chars().take(columns).collect();
+        graphics.draw_text(&features, dialog_x + 20, content_y + 60 + rows.len() * 18, Color::GRAY);
         
         // Draw system logo
         graphics.draw_rounded_rect(dialog_x + 50, content_y - 30, 80, 80, Color::BLUE);
@@ -348,6 +1788,66 @@ impl Desktop {
         graphics.draw_text("More Info", dialog_x + dialog_width - 100, button_y + 10, Color::WHITE);
     }
     
+    fn draw_hang_dialog(&self, graphics: &mut Graphics) {
+        let dialog_width = 360;
+        let dialog_height = 150;
+        let dialog_x = (SCREEN_WIDTH - dialog_width) / 2;
+        let dialog_y = (SCREEN_HEIGHT - dialog_height) / 2;
+        
+        graphics.draw_rounded_rect(dialog_x, dialog_y, dialog_width, dialog_height, Color::WHITE);
+        graphics.draw_rect_outline(dialog_x, dialog_y, dialog_width, dialog_height, Color::GRAY);
+        
+        // Name the application if the hang happened while drawing a window
+        let name = self.hung_window
+            .and_then(|index| self.window_manager.window_title(index))
+            .map(|title| title.split(" — ").next().unwrap_or(title))
+            .unwrap_or("The desktop");
+        graphics.draw_text("⚠️", dialog_x + 20, dialog_y + 25, Color::YELLOW);
+        graphics.draw_text(&format!("{} is not responding.", name), dialog_x + 50, dialog_y + 25, Color::BLACK);
+        graphics.draw_text(
+            &format!("No frame was drawn for {} seconds.", crate::watchdog::HANG_TIMEOUT_SECS),
+            dialog_x + 50, dialog_y + 50, Color::GRAY
+        );
+        graphics.draw_text("Force Quit?", dialog_x + 50, dialog_y + 70, Color::GRAY);
+        
+        // Draw buttons
+        let button_y = dialog_y + dialog_height - 45;
+        graphics.draw_rounded_rect(dialog_x + dialog_width - 230, button_y, 90, 30, Color::new(230, 230, 230));
+        graphics.draw_text("Wait", dialog_x + dialog_width - 200, button_y + 10, Color::BLACK);
+        graphics.draw_rounded_rect(dialog_x + dialog_width - 120, button_y, 100, 30, Color::RED);
+        graphics.draw_text("Force Quit", dialog_x + dialog_width - 110, button_y + 10, Color::WHITE);
+    }
+    
+    fn draw_crash_dialog(&self, graphics: &mut Graphics) {
+        let (app, message, saved) = match &self.crash_report {
+            Some(report) => report,
+            None => return,
+        };
+        let dialog_width = 400;
+        let dialog_height = 170;
+        let dialog_x = (SCREEN_WIDTH - dialog_width) / 2;
+        let dialog_y = (SCREEN_HEIGHT - dialog_height) / 2;
+        
+        graphics.draw_rounded_rect(dialog_x, dialog_y, dialog_width, dialog_height, Color::WHITE);
+        graphics.draw_rect_outline(dialog_x, dialog_y, dialog_width, dialog_height, Color::GRAY);
+        graphics.draw_text("⚠️", dialog_x + 20, dialog_y + 25, Color::YELLOW);
+        graphics.draw_text(&format!("{} quit unexpectedly.", app), dialog_x + 50, dialog_y + 25, Color::BLACK);
+        
+        // Wrap the panic message over a few lines
+        let chars: alloc::vec::Vec<char> = message.chars().collect();
+        for (i, line) in chars.chunks(40).take(4).enumerate() {
+            let line: String = line.iter().collect();
+            graphics.draw_text(&line, dialog_x + 50, dialog_y + 50 + i * 14, Color::GRAY);
+        }
+        
+        let button_y = dialog_y + dialog_height - 45;
+        if *saved {
+            graphics.draw_text("Report saved in Console.", dialog_x + 20, button_y + 10, Color::GRAY);
+        }
+        graphics.draw_rounded_rect(dialog_x + dialog_width - 120, button_y, 100, 30, Color::BLUE);
+        graphics.draw_text("OK", dialog_x + dialog_width - 80, button_y + 10, Color::WHITE);
+    }
+    
     fn draw_cursor(&self, graphics: &mut Graphics, x: usize, y: usize) {
         // Enhanced macOS-style cursor with shadow
         let cursor_data = [
@@ -405,38 +1905,36 @@ impl Desktop {
         }
     }
     
+    // Launches the user's login items after the session's own windows,
+    // except in safe mode
+    fn open_login_items(&mut self) {
+        let items = login_items::load();
+        if bootargs::safe_mode() {
+            return;
+        }
+        for item in items {
+            match self.window_manager.launch(&item.app) {
+                Some(index) if item.hidden => {
+                    if let Some(app) = self.window_manager.windows()[index].app {
+                        self.window_manager.hide_app(app);
+                    }
+                }
+                Some(_) => {}
+                None => kwarn!("login items: no app named {}", item.app),
+            }
+        }
+    }
+    
+    // Opens the session's windows, Finder in front
     fn create_sample_windows(&mut self) {
-        // Create enhanced Finder window
-        let mut finder = Window::new(
-            "Finder".to_string(),
-            80, 80, 500, 350,
-            Color::WHITE
-        );
-        finder.is_focused = true;
-        self.window_manager.add_window(finder);
-        
-        // Create Terminal window with dark theme
-        let terminal = Window::new(
-            "Terminal — zsh — 80×24".to_string(),
-            200, 120, 450, 300,
-            Color::new(40, 44, 52) // Dark theme
-        );
-        self.window_manager.add_window(terminal);
-        
-        // Create System Preferences window
-        let preferences = Window::new(
-            "System Preferences".to_string(),
-            150, 200, 400, 350,
-            Color::new(248, 248, 248)
-        );
-        self.window_manager.add_window(preferences);
-        
-        // Create Safari window
-        let safari = Window::new(
-            "Safari — RustOS Documentation".to_string(),
-            120, 60, 520, 400,
-            Color::WHITE
-        );
-        self.window_manager.add_window(safari);
+        for &(name, minimized) in STARTUP_APPS.iter() {
+            if let Some(app) = apps::find(name) {
+                let mut window = app.window();
+                if minimized {
+                    window.state = WindowState::Minimized;
+                }
+                self.window_manager.add_window(window);
+            }
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,228 @@
+// src/desktop_icons.rs
+// What's in the Desktop folder, as icons in columns from the top right of
+// the screen, each column filled down to the dock before the next starts
+// to its left. With Stacks on, images, documents and archives gather into
+// a stack for each kind, ahead of the other icons; clicking a stack fans
+// its items out into the places after it, pushing the icons behind along,
+// and clicking it again gathers them back. Stacks are turned on and off
+// with Ctrl+Cmd+0 and stay that way in the com.rustos.desktop preferences.
+// Clicking an item selects it and double-clicking opens it.
+use crate::clock;
+use crate::graphics::{Graphics, Color, SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::preferences;
+use crate::thumbnails;
+use crate::users;
+use crate::vfs::{self, FileType};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::sync::Mutex;
+
+const DOMAIN: &str = "com.rustos.desktop";
+// Under the menu bar and above the dock
+const TOP: usize = 36;
+const BOTTOM: usize = SCREEN_HEIGHT - 72;
+const RIGHT_MARGIN: usize = 12;
+const CELL_WIDTH: usize = 84;
+const CELL_HEIGHT: usize = 64;
+const DOUBLE_CLICK_MS: u64 = 500;
+// How long a stack takes to fan out
+const FAN_MS: u64 = 200;
+const MAX_ITEMS: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StackKind {
+    Images,
+    Documents,
+    Archives,
+}
+
+// Each kind's stack, in the order they're laid out, with what goes in it
+const STACKS: [(StackKind, &str, &[&str]); 3] = [
+    (StackKind::Images, "Images", &[".bmp", ".gif", ".png", ".jpg", ".jpeg"]),
+    (StackKind::Documents, "Documents", &[".txt", ".md", ".markdown", ".ps", ".pdf", ".rtf"]),
+    (StackKind::Archives, "Archives", &[".zip", ".tar"]),
+];
+
+impl StackKind {
+    fn of(name: &str, file_type: FileType) -> Option<StackKind> {
+        if file_type != FileType::File {
+            return None;
+        }
+        let name = name.to_lowercase();
+        STACKS.iter().find(|(_, _, extensions)| extensions.iter().any(|extension| name.ends_with(extension))).map(|&(kind, _, _)| kind)
+    }
+    
+    fn name(self) -> &'static str {
+        STACKS.iter().find(|(kind, _, _)| *kind == self).map_or("", |(_, name, _)| name)
+    }
+}
+
+// What fills a place in the layout
+enum Icon {
+    // In a stack that's fanned out, if `stack` is set
+    Item { name: String, file_type: FileType, stack: Option<StackKind> },
+    // With the number of items in it and the one drawn on top
+    Stack { kind: StackKind, count: usize, top: String },
+}
+
+struct State {
+    // The stack fanned out, and since when
+    expanded: Option<(StackKind, u64)>,
+    selected: Option<String>,
+    last_click: Option<(String, u64)>,
+}
+
+static STATE: Mutex<State> = Mutex::new(State { expanded: None, selected: None, last_click: None });
+
+fn desktop() -> String {
+    format!("/Users/{}/Desktop", users::user_name(users::current_uid()))
+}
+
+pub fn stacks_enabled() -> bool {
+    preferences::get_bool(DOMAIN, "stacks", true)
+}
+
+// Turns Stacks on or off; whether they're on now
+pub fn toggle_stacks() -> bool {
+    let enabled = !stacks_enabled();
+    let _ = preferences::set_bool(DOMAIN, "stacks", enabled);
+    STATE.lock().expanded = None;
+    enabled
+}
+
+// The icons in layout order: the stacks, each followed by its items when
+// it's fanned out, then everything else by name. Dot files stay hidden.
+fn layout(expanded: Option<StackKind>) -> Vec<Icon> {
+    let mut entries = vfs::read_dir(&desktop()).unwrap_or_default();
+    entries.retain(|entry| !entry.name.starts_with('.'));
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    entries.truncate(MAX_ITEMS);
+    let stacks = stacks_enabled();
+    let mut icons = Vec::new();
+    if stacks {
+        for &(kind, _, _) in STACKS.iter() {
+            let items: Vec<_> = entries.iter().filter(|entry| StackKind::of(&entry.name, entry.file_type) == Some(kind)).collect();
+            // The item changed last goes on top
+            let top = items.iter().max_by_key(|entry| vfs::metadata(&vfs::join(&desktop(), &entry.name)).map_or(0, |meta| meta.modified));
+            let Some(top) = top else { continue };
+            icons.push(Icon::Stack { kind, count: items.len(), top: top.name.clone() });
+            if expanded == Some(kind) {
+                for entry in items {
+                    icons.push(Icon::Item { name: entry.name.clone(), file_type: entry.file_type, stack: Some(kind) });
+                }
+            }
+        }
+    }
+    for entry in entries {
+        if !stacks || StackKind::of(&entry.name, entry.file_type).is_none() {
+            icons.push(Icon::Item { name: entry.name, file_type: entry.file_type, stack: None });
+        }
+    }
+    icons
+}
+
+// The top left of the `n`th place; None past the left edge
+fn place(n: usize) -> Option<(usize, usize)> {
+    let rows = (BOTTOM - TOP) / CELL_HEIGHT;
+    let (column, row) = (n / rows, n % rows);
+    let x = (SCREEN_WIDTH - RIGHT_MARGIN).checked_sub((column + 1) * CELL_WIDTH)?;
+    Some((x, TOP + row * CELL_HEIGHT))
+}
+
+// The file's thumbnail, or a plain icon while it has none
+fn draw_file(graphics: &mut Graphics, name: &str, file_type: FileType, x: usize, y: usize) {
+    let path = vfs::join(&desktop(), name);
+    let modified = vfs::metadata(&path).map_or(0, |meta| meta.modified);
+    let icon_x = x + (CELL_WIDTH - thumbnails::SIZE) / 2;
+    if file_type == FileType::File && thumbnails::draw(graphics, &path, modified, icon_x, y + 4) {
+        return;
+    }
+    let icon = if file_type == FileType::Directory { "📁" } else { "📄" };
+    graphics.draw_text(icon, x + CELL_WIDTH / 2 - 8, y + 12, Color::BLACK);
+}
+
+fn draw_label(graphics: &mut Graphics, text: &str, x: usize, y: usize, selected: bool) {
+    let label: String = text.chars().take(CELL_WIDTH / 8 - 1).collect();
+    let width = label.chars().count() * 8;
+    let label_x = x + (CELL_WIDTH - width) / 2;
+    if selected {
+        graphics.draw_rounded_rect(label_x - 3, y + 38, width + 6, 14, Color::BLUE);
+    }
+    graphics.draw_text(&label, label_x, y + 41, Color::WHITE);
+}
+
+pub fn draw(graphics: &mut Graphics) {
+    let state = STATE.lock();
+    let expanded = state.expanded.map(|(kind, _)| kind);
+    let fanned = state.expanded.map_or(1.0, |(_, since)| ((clock::millis() - since) as f32 / FAN_MS as f32).min(1.0));
+    let icons = layout(expanded);
+    // Where the open stack is, for its items to fan out from
+    let mut origin = (0, 0);
+    for (n, icon) in icons.iter().enumerate() {
+        let Some((mut x, mut y)) = place(n) else { break };
+        match icon {
+            Icon::Stack { kind, count, top } => {
+                origin = (x, y);
+                let pile_x = x + (CELL_WIDTH - 32) / 2;
+                for layer in (0..(*count).min(3)).rev() {
+                    graphics.draw_rounded_rect(pile_x + layer * 2, y + 2 + layer * 2, 32, 32, Color::new(235 - layer as u8 * 20, 235 - layer as u8 * 20, 235));
+                }
+                if expanded == Some(*kind) {
+                    graphics.draw_rect_outline(pile_x - 2, y, 38, 38, Color::BLUE);
+                }
+                draw_file(graphics, top, FileType::File, x, y);
+                // How many items, on the pile's corner
+                let badge = format!("{}", count);
+                let badge_width = badge.len() * 8 + 6;
+                graphics.draw_rounded_rect(pile_x + 36 - badge_width / 2, y, badge_width, 14, Color::RED);
+                graphics.draw_text(&badge, pile_x + 39 - badge_width / 2, y + 3, Color::WHITE);
+                draw_label(graphics, kind.name(), x, y, false);
+            }
+            Icon::Item { name, file_type, stack } => {
+                if stack.is_some() {
+                    // From the stack's place to its own
+                    x = (origin.0 as f32 + (x as f32 - origin.0 as f32) * fanned) as usize;
+                    y = (origin.1 as f32 + (y as f32 - origin.1 as f32) * fanned) as usize;
+                    graphics.draw_rounded_rect(x + 4, y, CELL_WIDTH - 8, CELL_HEIGHT - 4, Color::new(60, 60, 80));
+                }
+                draw_file(graphics, name, *file_type, x, y);
+                draw_label(graphics, name, x, y, state.selected.as_deref() == Some(name.as_str()));
+            }
+        }
+    }
+}
+
+// While a stack is fanning out
+pub fn is_animating() -> bool {
+    STATE.lock().expanded.is_some_and(|(_, since)| clock::millis() - since < FAN_MS)
+}
+
+// A click on the desktop: on a stack it fans out or gathers back, and on
+// an item it selects it. The path to open on a double-click.
+pub fn click(x: usize, y: usize) -> Option<String> {
+    let mut state = STATE.lock();
+    let icons = layout(state.expanded.map(|(kind, _)| kind));
+    let hit = icons.iter().enumerate().find(|&(n, _)| {
+        place(n).is_some_and(|(icon_x, icon_y)| x >= icon_x && x < icon_x + CELL_WIDTH && y >= icon_y && y < icon_y + CELL_HEIGHT)
+    });
+    let now = clock::millis();
+    match hit.map(|(_, icon)| icon) {
+        Some(Icon::Stack { kind, .. }) => {
+            let open = state.expanded.is_some_and(|(expanded, _)| expanded == *kind);
+            state.expanded = if open { None } else { Some((*kind, now)) };
+            state.selected = None;
+            None
+        }
+        Some(Icon::Item { name, .. }) => {
+            let double = matches!(&state.last_click, Some((last, at)) if last == name && now - at < DOUBLE_CLICK_MS);
+            state.last_click = if double { None } else { Some((name.clone(), now)) };
+            state.selected = Some(name.clone());
+            double.then(|| vfs::join(&desktop(), name))
+        }
+        None => {
+            state.selected = None;
+            None
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,322 @@
+// src/disk_utility.rs
+// Disk Utility: the attached disks and the volumes on them, each partition
+// or a whole disk without a partition table. A volume's details show its
+// capacity, filesystem and where it's mounted, with a bar for how full a
+// mounted one is, and buttons to mount or unmount it and to erase it as
+// FAT32. Erasing unmounts the volume first and mounts it again after; it
+// runs a few sectors a frame under a progress bar. The list is read when
+// the window is first drawn and again after every change.
+use crate::dock;
+use crate::fat32;
+use crate::graphics::{Graphics, Color};
+use crate::partition::{self, Scheme, Volume};
+use crate::system_info::format_size;
+use crate::vfs;
+use crate::block::{DeviceInfo, SECTOR_SIZE};
+use crate::widgets::Button;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::sync::Mutex;
+
+const APP: &str = "Disk Utility";
+const SIDEBAR_WIDTH: usize = 150;
+const ROW_HEIGHT: usize = 22;
+// The details, relative to the window content
+const DETAIL_X: usize = SIDEBAR_WIDTH + 20;
+const DETAIL_Y: usize = 16;
+const LINE_HEIGHT: usize = 18;
+const USAGE_Y: usize = DETAIL_Y + 104;
+const BUTTONS_Y: usize = DETAIL_Y + 150;
+const CONFIRM_Y: usize = BUTTONS_Y + 30;
+// Format steps per frame
+const ERASE_STEPS: usize = 16;
+const ERASED_LABEL: &str = "UNTITLED";
+
+const MOUNT: Button = Button::new("Mount");
+const UNMOUNT: Button = Button::new("Unmount");
+const ERASE: Button = Button::new("Erase...");
+const CANCEL: Button = Button::new("Cancel");
+const CONFIRM_ERASE: Button = Button::new("Erase");
+
+enum Row {
+    Disk(DeviceInfo, Scheme),
+    // With the type of its filesystem, if it's one that can be mounted
+    Volume(Volume, Option<&'static str>),
+}
+
+struct DiskUtilityState {
+    // None until first drawn
+    rows: Option<Vec<Row>>,
+    selected: usize,
+    confirm_erase: bool,
+    // The volume being erased
+    erase: Option<(String, fat32::Format)>,
+    status: Option<String>,
+}
+
+static STATE: Mutex<DiskUtilityState> = Mutex::new(DiskUtilityState {
+    rows: None,
+    selected: 0,
+    confirm_erase: false,
+    erase: None,
+    status: None,
+});
+
+fn read_rows() -> Vec<Row> {
+    let mut rows = Vec::new();
+    for disk in partition::disks() {
+        rows.push(Row::Disk(disk.info, disk.scheme));
+        for volume in disk.volumes {
+            let fs_type = vfs::probe(&volume.device);
+            rows.push(Row::Volume(volume, fs_type));
+        }
+    }
+    rows
+}
+
+fn format_name(fs_type: Option<&str>) -> &'static str {
+    match fs_type {
+        Some("ext2") => "ext2",
+        Some("iso9660") => "ISO 9660",
+        Some("fat32") => "FAT32",
+        _ => "Not recognized",
+    }
+}
+
+fn refresh(state: &mut DiskUtilityState) {
+    let rows = read_rows();
+    state.selected = state.selected.min(rows.len().saturating_sub(1));
+    state.rows = Some(rows);
+    state.confirm_erase = false;
+}
+
+// Unmounts the volume, then starts writing FAT32 over it
+fn start_erase(state: &mut DiskUtilityState, volume: &Volume) {
+    if let Some(path) = vfs::mount_point(volume) {
+        if let Err(err) = vfs::unmount(&path) {
+            state.status = Some(format!("Couldn't unmount {}: {:?}", volume.name, err));
+            return;
+        }
+    }
+    let hidden_sectors = volume.partition.as_ref().map_or(0, |p| p.start as u32);
+    match fat32::Format::new(volume.device.clone(), ERASED_LABEL, hidden_sectors) {
+        Ok(format) => {
+            state.erase = Some((volume.name.clone(), format));
+            state.status = None;
+        }
+        Err(vfs::VfsError::NoSpace) => state.status = Some(format!("{} is too small for FAT32", volume.name)),
+        Err(err) => state.status = Some(format!("Couldn't erase {}: {:?}", volume.name, err)),
+    }
+}
+
+// Runs the erase a few steps further; whether there was one, so the window
+// gets drawn again
+pub fn poll() -> bool {
+    let mut state = STATE.lock();
+    let state = &mut *state;
+    let (name, format) = match state.erase.as_mut() {
+        Some(erase) => erase,
+        None => return false,
+    };
+    let mut result = Ok(false);
+    for _ in 0..ERASE_STEPS {
+        result = format.step();
+        if result != Ok(false) {
+            break;
+        }
+    }
+    match result {
+        Ok(false) => {
+            dock::set_progress(APP, Some((format.progress() * 100.0) as u8));
+            return true;
+        }
+        Ok(true) => {
+            let name = name.clone();
+            refresh(state);
+            let volume = state.rows.iter().flatten().find_map(|row| match row {
+                Row::Volume(volume, _) if volume.name == name => Some(volume),
+                _ => None,
+            });
+            if let Some(Err(err)) = volume.map(vfs::mount_volume) {
+                state.status = Some(format!("Erased {}, but couldn't mount it: {:?}", name, err));
+            }
+        }
+        Err(err) => state.status = Some(format!("Couldn't erase {}: {:?}", name, err)),
+    }
+    state.erase = None;
+    dock::set_progress(APP, None);
+    true
+}
+
+// `x` and `y` are relative to the window content
+pub fn click(x: usize, y: usize) {
+    let mut state = STATE.lock();
+    if state.erase.is_some() {
+        return;
+    }
+    let state = &mut *state;
+    let rows = match &state.rows {
+        Some(rows) => rows,
+        None => return,
+    };
+    
+    if x < SIDEBAR_WIDTH {
+        let index = y.saturating_sub(8) / ROW_HEIGHT;
+        if y >= 8 && index < rows.len() {
+            state.selected = index;
+            state.confirm_erase = false;
+            state.status = None;
+        }
+        return;
+    }
+    
+    let volume = match rows.get(state.selected) {
+        Some(Row::Volume(volume, _)) => volume,
+        _ => return,
+    };
+    let mount_point = vfs::mount_point(volume);
+    let toggle = if mount_point.is_some() { UNMOUNT } else { MOUNT };
+    if state.confirm_erase {
+        if CANCEL.contains(x, y, DETAIL_X, CONFIRM_Y + 24) {
+            state.confirm_erase = false;
+        } else if CONFIRM_ERASE.contains(x, y, DETAIL_X + CANCEL.width() + 8, CONFIRM_Y + 24) {
+            let volume = volume.clone();
+            state.confirm_erase = false;
+            start_erase(state, &volume);
+        }
+        return;
+    }
+    
+    if toggle.contains(x, y, DETAIL_X, BUTTONS_Y) {
+        let result = match &mount_point {
+            Some(path) => vfs::unmount(path),
+            None => vfs::mount_volume(volume).map(|_| ()),
+        };
+        state.status = match result {
+            Ok(()) => None,
+            Err(vfs::VfsError::Unsupported) => Some(format!("{} has no filesystem that can be mounted", volume.name)),
+            Err(err) => Some(format!("Couldn't {} {}: {:?}", toggle.label.to_lowercase(), volume.name, err)),
+        };
+    } else if ERASE.contains(x, y, DETAIL_X + toggle.width() + 8, BUTTONS_Y) {
+        if volume.device.lock().is_read_only() {
+            state.status = Some(format!("{} is read-only", volume.name));
+        } else {
+            state.confirm_erase = true;
+            state.status = None;
+        }
+    }
+}
+
+fn draw_disk(graphics: &mut Graphics, x: usize, y: usize, info: &DeviceInfo, scheme: Scheme, volumes: usize) {
+    graphics.draw_text(&info.name, x, y, Color::BLACK);
+    let lines = [
+        format!("Capacity: {}", format_size(info.sector_count * SECTOR_SIZE as u64)),
+        format!("Partition map: {}", scheme.name()),
+        format!("Volumes: {}", volumes),
+        String::from(if info.read_only { "Read-only" } else { "Writable" }),
+    ];
+    for (i, line) in lines.iter().enumerate() {
+        graphics.draw_text(line, x, y + 24 + i * LINE_HEIGHT, Color::DARK_GRAY);
+    }
+}
+
+// `right` is the window's right edge
+fn draw_volume(graphics: &mut Graphics, x: usize, y: usize, right: usize, volume: &Volume, fs_type: Option<&str>) {
+    graphics.draw_text(&volume.name, x, y, Color::BLACK);
+    let mounts = vfs::mounts();
+    let source = format!("/dev/{}", volume.name);
+    let mount = mounts.iter().find(|m| m.source == source);
+    let sectors = volume.device.lock().sector_count();
+    let lines = [
+        format!("Capacity: {}", format_size(sectors * SECTOR_SIZE as u64)),
+        format!("Format: {}", format_name(fs_type)),
+        match &volume.partition {
+            Some(p) => format!("Partition {}: {}", p.number, p.type_name),
+            None => String::from("Whole disk"),
+        },
+        match mount {
+            Some(mount) => format!("Mounted at {}", mount.path),
+            None => String::from("Not mounted"),
+        },
+    ];
+    for (i, line) in lines.iter().enumerate() {
+        graphics.draw_text(line, x, y + 24 + i * LINE_HEIGHT, Color::DARK_GRAY);
+    }
+    
+    if let Some((used, total)) = mount.and_then(|mount| mount.usage) {
+        let bar_width = right.saturating_sub(x + 20);
+        let filled = (bar_width as u64 * used / total.max(1)) as usize;
+        graphics.draw_rounded_rect(x, y + USAGE_Y - DETAIL_Y, bar_width, 8, Color::LIGHT_GRAY);
+        graphics.draw_rounded_rect(x, y + USAGE_Y - DETAIL_Y, filled, 8, Color::BLUE);
+        let text = format!("{} used of {}", format_size(used), format_size(total));
+        graphics.draw_text(&text, x, y + USAGE_Y - DETAIL_Y + 14, Color::GRAY);
+    }
+}
+
+// `x` and `y` are the window content's top-left
+pub fn draw(graphics: &mut Graphics, x: usize, y: usize, width: usize, height: usize) {
+    let mut state = STATE.lock();
+    if state.rows.is_none() {
+        refresh(&mut state);
+    }
+    let rows = state.rows.as_deref().unwrap_or(&[]);
+    
+    graphics.draw_rect(x + 1, y, SIDEBAR_WIDTH, height - 1, Color::new(245, 245, 247));
+    if rows.is_empty() {
+        graphics.draw_text("No disks attached", x + 12, y + 14, Color::GRAY);
+        return;
+    }
+    for (i, row) in rows.iter().enumerate() {
+        let row_y = y + 8 + i * ROW_HEIGHT;
+        let (text, indent, color) = match row {
+            Row::Disk(info, _) => (format!("💽 {}", info.name), 8, Color::BLACK),
+            Row::Volume(volume, _) if vfs::mount_point(volume).is_some() => (volume.name.clone(), 28, Color::BLACK),
+            Row::Volume(volume, _) => (volume.name.clone(), 28, Color::GRAY),
+        };
+        let color = if i == state.selected {
+            graphics.draw_rounded_rect(x + 4, row_y, SIDEBAR_WIDTH - 8, ROW_HEIGHT - 2, Color::BLUE);
+            Color::WHITE
+        } else {
+            color
+        };
+        graphics.draw_text(&text, x + indent, row_y + 6, color);
+    }
+    
+    let (detail_x, detail_y) = (x + DETAIL_X, y + DETAIL_Y);
+    let volume = match rows.get(state.selected) {
+        Some(Row::Disk(info, scheme)) => {
+            let volumes = rows[state.selected + 1..].iter().take_while(|row| matches!(row, Row::Volume(..))).count();
+            draw_disk(graphics, detail_x, detail_y, info, *scheme, volumes);
+            None
+        }
+        Some(Row::Volume(volume, fs_type)) => {
+            draw_volume(graphics, detail_x, detail_y, x + width, volume, *fs_type);
+            Some(volume)
+        }
+        None => None,
+    };
+    
+    if let Some((name, format)) = &state.erase {
+        graphics.draw_text(&format!("Erasing \"{}\"...", name), detail_x, y + BUTTONS_Y, Color::BLACK);
+        let bar_width = width.saturating_sub(DETAIL_X + 20);
+        graphics.draw_rounded_rect(detail_x, y + BUTTONS_Y + 18, bar_width, 6, Color::LIGHT_GRAY);
+        graphics.draw_rounded_rect(detail_x, y + BUTTONS_Y + 18, (bar_width as f32 * format.progress()) as usize, 6, Color::BLUE);
+    } else if let Some(volume) = volume {
+        let toggle = if vfs::mount_point(volume).is_some() { UNMOUNT } else { MOUNT };
+        toggle.draw(graphics, detail_x, y + BUTTONS_Y);
+        ERASE.draw(graphics, detail_x + toggle.width() + 8, y + BUTTONS_Y);
+        if state.confirm_erase {
+            let text = format!("Erase \"{}\" as FAT32? Everything on it will be lost.", volume.name);
+            graphics.draw_text(&text, detail_x, y + CONFIRM_Y, Color::BLACK);
+            CANCEL.draw(graphics, detail_x, y + CONFIRM_Y + 24);
+            let confirm_x = detail_x + CANCEL.width() + 8;
+            graphics.draw_rounded_rect(confirm_x, y + CONFIRM_Y + 24, CONFIRM_ERASE.width(), Button::HEIGHT, Color::RED);
+            graphics.draw_text(CONFIRM_ERASE.label, confirm_x + 8, y + CONFIRM_Y + 29, Color::WHITE);
+        }
+    }
+    
+    if let Some(status) = &state.status {
+        graphics.draw_text(status, detail_x, y + height - 20, Color::RED);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,311 @@
+// src/display_server.rs
+// The display server: how processes outside the kernel will draw. A client
+// asks for a surface of a fixed size and gets the name of a shared-memory
+// buffer to map, one 0x00RRGGBB word per pixel, row by row. It draws there
+// whenever it likes and commits the rectangles it changed; committing
+// copies them to the server's own copy, so the compositor never shows a
+// half-drawn frame. The desktop hears about surfaces on the message bus and
+// gives each a window, drawn, moved, minimized and closed like any other;
+// keys and clicks in it come back to the client as events. A client that
+// scrolls has both copies move their rows in place and commits only the
+// rows it uncovered, so long output isn't drawn twice. Window thumbnails
+// are sampled from the same copy, so they're live too. Clients are
+// apps in user mode: their system calls come here through `request` and
+// `next_event`. The types they share are the SDK's.
+use crate::bus::{self, Message};
+use crate::graphics::{Graphics, Color, SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::keyboard::KeyEvent;
+use crate::task::{self, Pid, ProcessState};
+use crate::vm::{self, Protection, VmError};
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::sync::Mutex;
+use x86_64::VirtAddr;
+
+pub use rustos_sdk::protocol::{Event, Rect, SurfaceId};
+
+const MAX_SURFACES: usize = 8;
+// Events for a client beyond this are dropped until it reads some
+const EVENT_CAPACITY: usize = 64;
+const BYTES_PER_PIXEL: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtocolError {
+    // Not a surface, or one that belongs to another client
+    NoSuchSurface,
+    // Empty, or bigger than the screen
+    BadSize,
+    TooManySurfaces,
+    Vm(VmError),
+}
+
+impl From<VmError> for ProtocolError {
+    fn from(err: VmError) -> Self {
+        ProtocolError::Vm(err)
+    }
+}
+
+// Made by clients
+#[derive(Debug, Clone)]
+pub enum Request {
+    CreateSurface { title: String, width: usize, height: usize },
+    // The rectangles drawn since the last commit; they're shown from the
+    // next frame
+    Commit { surface: SurfaceId, damage: Vec<Rect> },
+    // Moves the committed rows of `area` up by `dy` pixels, down when
+    // negative; the client has done the same to its buffer
+    Scroll { surface: SurfaceId, area: Rect, dy: isize },
+    SetTitle { surface: SurfaceId, title: String },
+    Destroy { surface: SurfaceId },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reply {
+    // The buffer is mapped with `vm::shm_map`; `stride` is in pixels
+    Surface { surface: SurfaceId, buffer: String, stride: usize },
+    Done,
+}
+
+// A shared-memory region's name, and where it's mapped here
+type Buffer = (String, VirtAddr);
+
+struct Surface {
+    id: SurfaceId,
+    client: Pid,
+    width: usize,
+    height: usize,
+    // The client's buffer, mapped read-only here, and the server's copy of
+    // what was last committed
+    buffer: Buffer,
+    front: Buffer,
+    committed: bool,
+}
+
+impl Surface {
+    fn pixels(&self) -> usize {
+        self.width * self.height
+    }
+}
+
+struct Server {
+    surfaces: Vec<Surface>,
+    events: BTreeMap<Pid, VecDeque<Event>>,
+    next_id: SurfaceId,
+}
+
+static SERVER: Mutex<Server> = Mutex::new(Server { surfaces: Vec::new(), events: BTreeMap::new(), next_id: 1 });
+
+fn find(server: &mut Server, client: Pid, surface: SurfaceId) -> Result<&mut Surface, ProtocolError> {
+    server.surfaces.iter_mut().find(|s| s.id == surface && s.client == client).ok_or(ProtocolError::NoSuchSurface)
+}
+
+// Both buffers, mapped here
+fn create_buffers(id: SurfaceId, size: u64) -> Result<(Buffer, Buffer), VmError> {
+    let buffer = format!("display_server.{}", id);
+    let front = format!("display_server.{}.front", id);
+    vm::shm_create(&buffer, size)?;
+    let mapped = vm::shm_create(&front, size)
+        .and_then(|_| vm::shm_map(&front, Protection::ReadWrite))
+        .and_then(|front_addr| vm::shm_map(&buffer, Protection::ReadOnly).map(|buffer_addr| (buffer_addr, front_addr)));
+    match mapped {
+        Ok((buffer_addr, front_addr)) => Ok(((buffer, buffer_addr), (front, front_addr))),
+        Err(err) => {
+            let _ = vm::shm_unlink(&buffer);
+            let _ = vm::shm_unlink(&front);
+            Err(err)
+        }
+    }
+}
+
+// The buffers' memory goes once the client unmaps its mapping as well
+fn release(surface: Surface) {
+    for (name, addr) in [surface.buffer, surface.front] {
+        let _ = vm::munmap(addr);
+        let _ = vm::shm_unlink(&name);
+    }
+}
+
+// Copies the damaged rectangles, cut to the surface, to the server's copy
+fn commit(surface: &mut Surface, damage: &[Rect]) {
+    let (buffer, front) = unsafe {
+        (
+            core::slice::from_raw_parts(surface.buffer.1.as_ptr::<u32>(), surface.pixels()),
+            core::slice::from_raw_parts_mut(surface.front.1.as_mut_ptr::<u32>(), surface.pixels()),
+        )
+    };
+    for rect in damage {
+        let right = rect.x.saturating_add(rect.width).min(surface.width);
+        let bottom = rect.y.saturating_add(rect.height).min(surface.height);
+        for row in rect.y..bottom {
+            let start = row * surface.width;
+            if rect.x < right {
+                front[start + rect.x..start + right].copy_from_slice(&buffer[start + rect.x..start + right]);
+            }
+        }
+    }
+    surface.committed = true;
+}
+
+fn create(server: &mut Server, client: Pid, id: SurfaceId, title: String, width: usize, height: usize) -> Result<Reply, ProtocolError> {
+    if width == 0 || height == 0 || width > SCREEN_WIDTH || height > SCREEN_HEIGHT {
+        return Err(ProtocolError::BadSize);
+    }
+    if server.surfaces.len() >= MAX_SURFACES {
+        return Err(ProtocolError::TooManySurfaces);
+    }
+    let (buffer, front) = create_buffers(id, (width * height * BYTES_PER_PIXEL) as u64)?;
+    server.next_id = server.next_id.max(id + 1);
+    let name = buffer.0.clone();
+    server.surfaces.push(Surface { id, client, width, height, buffer, front, committed: false });
+    server.events.entry(client).or_default();
+    bus::post(Message::SurfaceCreated { surface: id, client, title, width, height });
+    Ok(Reply::Surface { surface: id, buffer: name, stride: width })
+}
+
+// A surface `client` had before hibernating, given back under its old id
+pub fn restore(client: Pid, surface: SurfaceId, title: String, width: usize, height: usize) -> Result<Reply, ProtocolError> {
+    let mut server = SERVER.lock();
+    if server.surfaces.iter().any(|s| s.id == surface) {
+        return Err(ProtocolError::NoSuchSurface);
+    }
+    create(&mut server, client, surface, title, width, height)
+}
+
+// The id and size of each of `client`'s surfaces
+pub fn surfaces(client: Pid) -> Vec<(SurfaceId, usize, usize)> {
+    SERVER.lock().surfaces.iter().filter(|s| s.client == client).map(|s| (s.id, s.width, s.height)).collect()
+}
+
+// Handles a request from `client`
+pub fn request(client: Pid, request: Request) -> Result<Reply, ProtocolError> {
+    let mut server = SERVER.lock();
+    match request {
+        Request::CreateSurface { title, width, height } => {
+            let id = server.next_id;
+            create(&mut server, client, id, title, width, height)
+        }
+        Request::Commit { surface, damage } => {
+            commit(find(&mut server, client, surface)?, &damage);
+            bus::post(Message::SurfaceDamaged);
+            Ok(Reply::Done)
+        }
+        Request::Scroll { surface, area, dy } => {
+            let surface = find(&mut server, client, surface)?;
+            let front = unsafe { core::slice::from_raw_parts_mut(surface.front.1.as_mut_ptr::<u32>(), surface.pixels()) };
+            rustos_sdk::surface::scroll_pixels(front, surface.width, area, dy);
+            bus::post(Message::SurfaceDamaged);
+            Ok(Reply::Done)
+        }
+        Request::SetTitle { surface, title } => {
+            find(&mut server, client, surface)?;
+            bus::post(Message::SurfaceTitle { surface, title });
+            Ok(Reply::Done)
+        }
+        Request::Destroy { surface } => {
+            let index = server.surfaces.iter()
+                .position(|s| s.id == surface && s.client == client)
+                .ok_or(ProtocolError::NoSuchSurface)?;
+            release(server.surfaces.remove(index));
+            bus::post(Message::SurfaceDestroyed { surface });
+            Ok(Reply::Done)
+        }
+    }
+}
+
+// The oldest event waiting for `client`
+pub fn next_event(client: Pid) -> Option<Event> {
+    SERVER.lock().events.get_mut(&client)?.pop_front()
+}
+
+pub fn has_events(client: Pid) -> bool {
+    SERVER.lock().events.get(&client).is_some_and(|queue| !queue.is_empty())
+}
+
+fn send(server: &mut Server, surface: SurfaceId, event: Event) {
+    let client = match server.surfaces.iter().find(|s| s.id == surface) {
+        Some(s) => s.client,
+        None => return,
+    };
+    let queue = server.events.entry(client).or_default();
+    if queue.len() < EVENT_CAPACITY {
+        queue.push_back(event);
+    }
+}
+
+// Input the window manager hands the surface's window
+pub fn key(surface: SurfaceId, event: &KeyEvent) {
+    send(&mut SERVER.lock(), surface, Event::Key { surface, event: *event });
+}
+
+pub fn click(surface: SurfaceId, x: usize, y: usize) {
+    send(&mut SERVER.lock(), surface, Event::Click { surface, x, y });
+}
+
+pub fn drag(surface: SurfaceId, dx: i32, dy: i32) {
+    send(&mut SERVER.lock(), surface, Event::Drag { surface, dx, dy });
+}
+
+pub fn text(surface: SurfaceId, ch: char) {
+    send(&mut SERVER.lock(), surface, Event::Text { surface, ch });
+}
+
+// The user closed the surface's window
+pub fn closed(surface: SurfaceId) {
+    let mut server = SERVER.lock();
+    send(&mut server, surface, Event::Closed { surface });
+    if let Some(index) = server.surfaces.iter().position(|s| s.id == surface) {
+        release(server.surfaces.remove(index));
+    }
+}
+
+// Takes down the surfaces of clients that have exited, and their windows
+pub fn poll() {
+    let mut server = SERVER.lock();
+    if server.surfaces.is_empty() && server.events.is_empty() {
+        return;
+    }
+    // A stopped client keeps its windows
+    let running: Vec<Pid> = task::list().into_iter().filter(|p| !matches!(p.state, ProcessState::Zombie(_))).map(|p| p.pid).collect();
+    let (live, gone): (Vec<Surface>, Vec<Surface>) = server.surfaces.drain(..).partition(|s| running.contains(&s.client));
+    server.surfaces = live;
+    server.events.retain(|client, _| running.contains(client));
+    for surface in gone {
+        bus::post(Message::SurfaceDestroyed { surface: surface.id });
+        release(surface);
+    }
+}
+
+// Draws what was last committed at the top left of the content, clipped to
+// `width` and `height`; white until the first commit
+pub fn draw(graphics: &mut Graphics, surface: SurfaceId, x: usize, y: usize, width: usize, height: usize) {
+    let server = SERVER.lock();
+    let surface = match server.surfaces.iter().find(|s| s.id == surface) {
+        Some(surface) if surface.committed => surface,
+        _ => return,
+    };
+    let front = unsafe { core::slice::from_raw_parts(surface.front.1.as_ptr::<u32>(), surface.pixels()) };
+    for row in 0..surface.height.min(height) {
+        for col in 0..surface.width.min(width) {
+            graphics.set_pixel(x + col, y + row, Color::from_rgb(front[row * surface.width + col]));
+        }
+    }
+}
+
+// Draws what was last committed stretched or shrunk to `width` by
+// `height`, taking the nearest pixel, for thumbnails
+pub fn draw_scaled(graphics: &mut Graphics, surface: SurfaceId, x: usize, y: usize, width: usize, height: usize) {
+    let server = SERVER.lock();
+    let surface = match server.surfaces.iter().find(|s| s.id == surface) {
+        Some(surface) if surface.committed => surface,
+        _ => return,
+    };
+    let front = unsafe { core::slice::from_raw_parts(surface.front.1.as_ptr::<u32>(), surface.pixels()) };
+    for row in 0..height {
+        let start = row * surface.height / height * surface.width;
+        for col in 0..width {
+            graphics.set_pixel(x + col, y + row, Color::from_rgb(front[start + col * surface.width / width]));
+        }
+    }
+}
\ No newline at end of file
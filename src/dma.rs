@@ -0,0 +1,95 @@
+// src/dma.rs
+// Memory that devices read and write by themselves. Buffers are whole
+// pages, physically contiguous, zeroed, and below 4 GiB so 32-bit DMA
+// engines like UHCI's reach them; the CPU gets at them through the
+// physical memory window. PCI DMA on x86 snoops the caches, so buffers stay
+// ordinary write-back memory and nothing is flushed. What drivers do need
+// is ordering: `sync_for_device` between filling a buffer and handing it
+// over, and `sync_for_cpu` between seeing the device finish and reading
+// what it wrote. Dropped buffers go back to a free list.
+use crate::memory::{self, PAGE_SIZE};
+use alloc::vec::Vec;
+use core::sync::atomic::{fence, AtomicUsize, Ordering};
+use crate::sync::Mutex;
+use x86_64::PhysAddr;
+
+// Every buffer has to be reachable with 32-bit addresses
+const LIMIT: u64 = 1 << 32;
+
+// Runs given back, as first address and page count
+static FREE: Mutex<Vec<(u64, usize)>> = Mutex::new(Vec::new());
+static PAGES_IN_USE: AtomicUsize = AtomicUsize::new(0);
+
+pub struct DmaBuffer {
+    phys: u64,
+    pages: usize,
+}
+
+impl DmaBuffer {
+    // At least `bytes`, rounded up to whole pages
+    pub fn new(bytes: usize) -> Option<Self> {
+        let pages = bytes.div_ceil(PAGE_SIZE as usize).max(1);
+        let phys = match reuse(pages) {
+            Some(phys) => phys,
+            None => memory::allocate_contiguous_frames(pages, LIMIT)?.start_address().as_u64(),
+        };
+        let buffer = Self { phys, pages };
+        unsafe { core::ptr::write_bytes(buffer.as_ptr(), 0, buffer.size()) };
+        PAGES_IN_USE.fetch_add(pages, Ordering::Relaxed);
+        Some(buffer)
+    }
+    
+    // The bus address of byte `offset`, for the device
+    pub fn phys(&self, offset: usize) -> u64 {
+        debug_assert!(offset <= self.size());
+        self.phys + offset as u64
+    }
+    
+    // Where the CPU sees the start of the buffer
+    pub fn as_ptr(&self) -> *mut u8 {
+        memory::phys_to_virt(PhysAddr::new(self.phys)).as_mut_ptr()
+    }
+    
+    pub fn size(&self) -> usize {
+        self.pages * PAGE_SIZE as usize
+    }
+    
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { core::slice::from_raw_parts_mut(self.as_ptr(), self.size()) }
+    }
+}
+
+impl Drop for DmaBuffer {
+    fn drop(&mut self) {
+        PAGES_IN_USE.fetch_sub(self.pages, Ordering::Relaxed);
+        FREE.lock().push((self.phys, self.pages));
+    }
+}
+
+// The smallest free run that fits, with what's left of it kept free
+fn reuse(pages: usize) -> Option<u64> {
+    let mut free = FREE.lock();
+    let index = (0..free.len()).filter(|&i| free[i].1 >= pages).min_by_key(|&i| free[i].1)?;
+    let (phys, run) = free.swap_remove(index);
+    if run > pages {
+        free.push((phys + (pages as u64) * PAGE_SIZE, run - pages));
+    }
+    Some(phys)
+}
+
+// Makes every write to DMA memory so far visible before the next store,
+// which is the one telling the device to look
+pub fn sync_for_device() {
+    fence(Ordering::SeqCst);
+}
+
+// Keeps reads of what the device wrote after the read that showed it was
+// done
+pub fn sync_for_cpu() {
+    fence(Ordering::SeqCst);
+}
+
+// Pages held by live buffers
+pub fn pages_in_use() -> usize {
+    PAGES_IN_USE.load(Ordering::Relaxed)
+}
\ No newline at end of file
@@ -0,0 +1,226 @@
+// src/dns.rs
+// Host name lookups: A-record queries over UDP to the interface's name
+// server, retried a few times, with answers cached for their TTL. A Query
+// is polled so windows can keep drawing while it is out; `resolve` waits
+// for one, polling the network meanwhile.
+use crate::clock::Instant;
+use crate::net::{self, Ipv4Addr, NetError};
+use crate::rand;
+use crate::socket::{self, Handle, Protocol};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::time::Duration;
+use crate::sync::Mutex;
+
+const PORT: u16 = 53;
+const HEADER: usize = 12;
+const TYPE_A: u16 = 1;
+const CLASS_IN: u16 = 1;
+// Recursion desired
+const FLAG_RD: u16 = 0x0100;
+const FLAG_QR: u16 = 0x8000;
+const RCODE_NXDOMAIN: u16 = 3;
+const RETRY_AFTER: Duration = Duration::from_secs(1);
+const ATTEMPTS: u32 = 3;
+const CACHE_SIZE: usize = 32;
+// Cached answers are dropped after their TTL, or this, whichever is sooner
+const MAX_TTL: Duration = Duration::from_secs(600);
+
+struct CacheEntry {
+    name: String,
+    addr: Ipv4Addr,
+    expires: Instant,
+}
+
+// Oldest first
+static CACHE: Mutex<Vec<CacheEntry>> = Mutex::new(Vec::new());
+
+fn cached(name: &str) -> Option<Ipv4Addr> {
+    let mut cache = CACHE.lock();
+    let now = Instant::now();
+    cache.retain(|entry| entry.expires > now);
+    cache.iter().find(|entry| entry.name == name).map(|entry| entry.addr)
+}
+
+fn remember(name: &str, addr: Ipv4Addr, ttl: Duration) {
+    let mut cache = CACHE.lock();
+    cache.retain(|entry| entry.name != name);
+    if cache.len() == CACHE_SIZE {
+        cache.remove(0);
+    }
+    cache.push(CacheEntry { name: String::from(name), addr, expires: Instant::now() + ttl.min(MAX_TTL) });
+}
+
+fn be16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*data.get(offset)?, *data.get(offset + 1)?]))
+}
+
+fn be32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(((be16(data, offset)? as u32) << 16) | be16(data, offset + 2)? as u32)
+}
+
+// Labels must be 1 to 63 bytes and the whole name at most 253
+fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name.len() <= 253 && name.split('.').all(|label| {
+        !label.is_empty() && label.len() <= 63 && label.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+    })
+}
+
+fn build_query(id: u16, name: &str) -> Vec<u8> {
+    let mut query = Vec::with_capacity(HEADER + name.len() + 6);
+    query.extend_from_slice(&id.to_be_bytes());
+    query.extend_from_slice(&FLAG_RD.to_be_bytes());
+    // One question, no answer, authority or additional records
+    query.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in name.split('.') {
+        query.push(label.len() as u8);
+        query.extend_from_slice(label.as_bytes());
+    }
+    query.push(0);
+    query.extend_from_slice(&TYPE_A.to_be_bytes());
+    query.extend_from_slice(&CLASS_IN.to_be_bytes());
+    query
+}
+
+// The offset just past a (possibly compressed) name
+fn skip_name(message: &[u8], mut offset: usize) -> Option<usize> {
+    loop {
+        let len = *message.get(offset)? as usize;
+        match len {
+            0 => return Some(offset + 1),
+            // A pointer ends the name
+            _ if len & 0xC0 == 0xC0 => return Some(offset + 2),
+            _ => offset += 1 + len,
+        }
+    }
+}
+
+// The first A record answering query `id`, with its TTL. CNAME records in
+// front of it are passed over, since the name server follows them for us.
+fn parse_response(id: u16, message: &[u8]) -> Option<Result<(Ipv4Addr, Duration), NetError>> {
+    if be16(message, 0)? != id {
+        return None;
+    }
+    let flags = be16(message, 2)?;
+    if flags & FLAG_QR == 0 {
+        return None;
+    }
+    if flags & 0x000F == RCODE_NXDOMAIN {
+        return Some(Err(NetError::NoSuchHost));
+    }
+    let questions = be16(message, 4)?;
+    let answers = be16(message, 6)?;
+    let mut offset = HEADER;
+    for _ in 0..questions {
+        offset = skip_name(message, offset)? + 4;
+    }
+    for _ in 0..answers {
+        offset = skip_name(message, offset)?;
+        let kind = be16(message, offset)?;
+        let class = be16(message, offset + 2)?;
+        let ttl = be32(message, offset + 4)?;
+        let len = be16(message, offset + 8)? as usize;
+        let data = message.get(offset + 10..offset + 10 + len)?;
+        if kind == TYPE_A && class == CLASS_IN && len == 4 {
+            return Some(Ok((Ipv4Addr([data[0], data[1], data[2], data[3]]), Duration::from_secs(ttl as u64))));
+        }
+        offset += 10 + len;
+    }
+    Some(Err(NetError::NoSuchHost))
+}
+
+enum Status {
+    Done(Result<Ipv4Addr, NetError>),
+    Waiting { socket: Handle, server: Ipv4Addr, id: u16, sent: Instant, attempts: u32 },
+}
+
+pub struct Query {
+    name: String,
+    status: Status,
+}
+
+impl Query {
+    // Starts looking up `name`. Addresses and cached names are answered
+    // without asking the name server.
+    pub fn new(name: &str) -> Self {
+        let name = name.trim_end_matches('.').to_ascii_lowercase();
+        let status = match Ipv4Addr::parse(&name).or_else(|| cached(&name)) {
+            Some(addr) => Status::Done(Ok(addr)),
+            None if !valid_name(&name) => Status::Done(Err(NetError::NoSuchHost)),
+            None => match Self::ask(&name) {
+                Ok(status) => status,
+                Err(err) => Status::Done(Err(err)),
+            },
+        };
+        Self { name, status }
+    }
+    
+    fn ask(name: &str) -> Result<Status, NetError> {
+        let server = net::name_server().ok_or(NetError::NoInterface)?;
+        let socket = socket::open(Protocol::Udp, 0)?;
+        let id = rand::u32() as u16;
+        if let Err(err) = socket::send_to(socket, server, PORT, &build_query(id, name)) {
+            socket::close(socket);
+            return Err(err);
+        }
+        Ok(Status::Waiting { socket, server, id, sent: Instant::now(), attempts: 1 })
+    }
+    
+    // The answer, once there is one. Call `net::poll` between polls.
+    pub fn poll(&mut self) -> Option<Result<Ipv4Addr, NetError>> {
+        let result = match &mut self.status {
+            Status::Done(result) => return Some(*result),
+            Status::Waiting { socket, server, id, sent, attempts } => {
+                let mut result = None;
+                while let Ok(Some(datagram)) = socket::recv_from(*socket) {
+                    if datagram.from != *server {
+                        continue;
+                    }
+                    if let Some(answer) = parse_response(*id, &datagram.data) {
+                        if let Ok((addr, ttl)) = answer {
+                            remember(&self.name, addr, ttl);
+                        }
+                        result = Some(answer.map(|(addr, _)| addr));
+                        break;
+                    }
+                }
+                if result.is_none() && sent.elapsed() >= RETRY_AFTER {
+                    if *attempts == ATTEMPTS {
+                        result = Some(Err(NetError::TimedOut));
+                    } else if let Err(err) = socket::send_to(*socket, *server, PORT, &build_query(*id, &self.name)) {
+                        result = Some(Err(err));
+                    } else {
+                        *sent = Instant::now();
+                        *attempts += 1;
+                    }
+                }
+                if result.is_some() {
+                    socket::close(*socket);
+                }
+                result?
+            }
+        };
+        self.status = Status::Done(result);
+        Some(result)
+    }
+}
+
+impl Drop for Query {
+    fn drop(&mut self) {
+        if let Status::Waiting { socket, .. } = self.status {
+            socket::close(socket);
+        }
+    }
+}
+
+// Looks `name` up and waits for the answer
+pub fn resolve(name: &str) -> Result<Ipv4Addr, NetError> {
+    let mut query = Query::new(name);
+    loop {
+        if let Some(result) = query.poll() {
+            return result;
+        }
+        net::poll();
+        core::hint::spin_loop();
+    }
+}
\ No newline at end of file
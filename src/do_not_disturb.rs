@@ -0,0 +1,204 @@
+// src/do_not_disturb.rs
+// Do Not Disturb: while it's on, notifications aren't shown. Those posted
+// meanwhile are kept and shown once it goes off, a few of them and a count
+// of the rest, unless the setting to show them afterwards is off, and then
+// they're dropped. It comes on from the moon in the menu bar, and on its
+// own while an app is fullscreen or between the hours it's scheduled for,
+// if those are set. Clicking the moon while it's on turns it off, even if
+// it came on by itself; it can come on by itself again once the fullscreen
+// app or the scheduled hours are over. Kept in the com.rustos.donotdisturb
+// preferences and set in System Preferences, next to Energy Saver.
+use crate::graphics::{Graphics, Color};
+use crate::kwarn;
+use crate::preferences;
+use crate::rtc;
+use crate::undo::{self, SetValue};
+use crate::vfs::VfsError;
+use crate::widgets::{Button, Checkbox};
+use alloc::format;
+use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+const DOMAIN: &str = "com.rustos.donotdisturb";
+const ENABLED_KEY: &str = "Enabled";
+const WHEN_FULLSCREEN_KEY: &str = "WhenFullscreen";
+const SCHEDULED_KEY: &str = "Scheduled";
+const FROM_KEY: &str = "From";
+const UNTIL_KEY: &str = "Until";
+const DELIVER_LATER_KEY: &str = "DeliverLater";
+
+const HOURS: [&str; 24] = [
+    "00:00", "01:00", "02:00", "03:00", "04:00", "05:00", "06:00", "07:00", "08:00", "09:00", "10:00", "11:00",
+    "12:00", "13:00", "14:00", "15:00", "16:00", "17:00", "18:00", "19:00", "20:00", "21:00", "22:00", "23:00",
+];
+
+// Where the settings sit in the System Preferences window, right of Energy
+// Saver
+const PREFERENCE_X: usize = 300;
+const PREFERENCE_Y: usize = 380;
+const FROM_X: usize = 100;
+const UNTIL_X: usize = 160;
+const WHEN_FULLSCREEN: Checkbox = Checkbox::new("When fullscreen");
+const SCHEDULED: Checkbox = Checkbox::new("Scheduled");
+const DELIVER_LATER: Checkbox = Checkbox::new("Show missed later");
+
+// The menu bar item's size
+const INDICATOR_WIDTH: usize = 16;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static WHEN_FULLSCREEN_ON: AtomicBool = AtomicBool::new(true);
+static SCHEDULED_ON: AtomicBool = AtomicBool::new(false);
+static FROM: AtomicU8 = AtomicU8::new(22);
+static UNTIL: AtomicU8 = AtomicU8::new(7);
+static DELIVER_LATER_ON: AtomicBool = AtomicBool::new(true);
+// Whether it's on, as of the last `update`
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+// Turned off from the menu bar while it had come on by itself
+static OVERRIDDEN: AtomicBool = AtomicBool::new(false);
+
+fn hour(key: &str, default: u8) -> u8 {
+    preferences::get(DOMAIN, key).and_then(|hour| hour.parse().ok()).filter(|&hour| hour < 24).unwrap_or(default)
+}
+
+pub fn load() {
+    ENABLED.store(preferences::get_bool(DOMAIN, ENABLED_KEY, false), Ordering::Relaxed);
+    WHEN_FULLSCREEN_ON.store(preferences::get_bool(DOMAIN, WHEN_FULLSCREEN_KEY, true), Ordering::Relaxed);
+    SCHEDULED_ON.store(preferences::get_bool(DOMAIN, SCHEDULED_KEY, false), Ordering::Relaxed);
+    FROM.store(hour(FROM_KEY, 22), Ordering::Relaxed);
+    UNTIL.store(hour(UNTIL_KEY, 7), Ordering::Relaxed);
+    DELIVER_LATER_ON.store(preferences::get_bool(DOMAIN, DELIVER_LATER_KEY, true), Ordering::Relaxed);
+}
+
+// Whether it's on now
+pub fn is_active() -> bool {
+    ACTIVE.load(Ordering::Relaxed)
+}
+
+// Whether notifications posted while it's on are shown once it goes off
+pub fn deliver_later() -> bool {
+    DELIVER_LATER_ON.load(Ordering::Relaxed)
+}
+
+// Whether the scheduled hours are on and it's between them now; the hours
+// can run past midnight
+fn in_scheduled_hours() -> bool {
+    if !SCHEDULED_ON.load(Ordering::Relaxed) {
+        return false;
+    }
+    let (from, until) = (FROM.load(Ordering::Relaxed), UNTIL.load(Ordering::Relaxed));
+    let now = rtc::now().hour;
+    if from <= until { (from..until).contains(&now) } else { now >= from || now < until }
+}
+
+// Whether it comes on by itself right now
+fn automatic(fullscreen: bool) -> bool {
+    (fullscreen && WHEN_FULLSCREEN_ON.load(Ordering::Relaxed)) || in_scheduled_hours()
+}
+
+// Works out whether it's on; called every frame. True when that changed.
+pub fn update(fullscreen: bool) -> bool {
+    let automatic = automatic(fullscreen);
+    if !automatic {
+        OVERRIDDEN.store(false, Ordering::Relaxed);
+    }
+    let active = ENABLED.load(Ordering::Relaxed) || (automatic && !OVERRIDDEN.load(Ordering::Relaxed));
+    ACTIVE.swap(active, Ordering::Relaxed) != active
+}
+
+// The menu bar's quick toggle
+pub fn toggle(fullscreen: bool) {
+    let on = !is_active();
+    OVERRIDDEN.store(!on && automatic(fullscreen), Ordering::Relaxed);
+    if let Err(err) = set_enabled(on) {
+        kwarn!("do not disturb: cannot save: {:?}", err);
+    }
+    update(fullscreen);
+}
+
+fn store(setting: &AtomicBool, key: &str, value: bool) -> Result<(), VfsError> {
+    setting.store(value, Ordering::Relaxed);
+    preferences::set_bool(DOMAIN, key, value)
+}
+
+fn set_enabled(on: bool) -> Result<(), VfsError> {
+    store(&ENABLED, ENABLED_KEY, on)
+}
+
+fn set_when_fullscreen(on: bool) -> Result<(), VfsError> {
+    store(&WHEN_FULLSCREEN_ON, WHEN_FULLSCREEN_KEY, on)
+}
+
+fn set_scheduled(on: bool) -> Result<(), VfsError> {
+    store(&SCHEDULED_ON, SCHEDULED_KEY, on)
+}
+
+fn set_deliver_later(on: bool) -> Result<(), VfsError> {
+    store(&DELIVER_LATER_ON, DELIVER_LATER_KEY, on)
+}
+
+fn set_from(hour: u8) -> Result<(), VfsError> {
+    FROM.store(hour, Ordering::Relaxed);
+    preferences::set(DOMAIN, FROM_KEY, &format!("{}", hour))
+}
+
+fn set_until(hour: u8) -> Result<(), VfsError> {
+    UNTIL.store(hour, Ordering::Relaxed);
+    preferences::set(DOMAIN, UNTIL_KEY, &format!("{}", hour))
+}
+
+// Changes go through System Preferences' undo history
+fn change<T: Clone + Send + 'static>(change: SetValue<T>) {
+    let name = change.name;
+    if let Err(err) = undo::perform(preferences::APP, change) {
+        kwarn!("do not disturb: cannot save {}: {:?}", name, err);
+    }
+}
+
+fn toggle_setting(name: &'static str, setting: &AtomicBool, set: fn(bool) -> Result<(), VfsError>) {
+    let old = setting.load(Ordering::Relaxed);
+    change(SetValue { name, set, old, new: !old });
+}
+
+fn hour_button(setting: &AtomicU8) -> Button {
+    Button::new(HOURS[setting.load(Ordering::Relaxed) as usize % 24])
+}
+
+pub fn draw_preference(graphics: &mut Graphics, x: usize, y: usize) {
+    let (x, y) = (x + PREFERENCE_X, y + PREFERENCE_Y);
+    graphics.draw_text("Do Not Disturb", x, y, Color::BLACK);
+    Checkbox { checked: WHEN_FULLSCREEN_ON.load(Ordering::Relaxed), ..WHEN_FULLSCREEN }.draw(graphics, x, y + 18);
+    Checkbox { checked: SCHEDULED_ON.load(Ordering::Relaxed), ..SCHEDULED }.draw(graphics, x, y + 38);
+    hour_button(&FROM).draw(graphics, x + FROM_X, y + 35);
+    hour_button(&UNTIL).draw(graphics, x + UNTIL_X, y + 35);
+    Checkbox { checked: DELIVER_LATER_ON.load(Ordering::Relaxed), ..DELIVER_LATER }.draw(graphics, x, y + 58);
+}
+
+// `x` and `y` are relative to the window content. The hours step along an
+// hour a click.
+pub fn click_preference(x: usize, y: usize) {
+    if WHEN_FULLSCREEN.contains(x, y, PREFERENCE_X, PREFERENCE_Y + 18) {
+        toggle_setting("Do Not Disturb When Fullscreen", &WHEN_FULLSCREEN_ON, set_when_fullscreen);
+    } else if SCHEDULED.contains(x, y, PREFERENCE_X, PREFERENCE_Y + 38) {
+        toggle_setting("Scheduled Do Not Disturb", &SCHEDULED_ON, set_scheduled);
+    } else if hour_button(&FROM).contains(x, y, PREFERENCE_X + FROM_X, PREFERENCE_Y + 35) {
+        let old = FROM.load(Ordering::Relaxed);
+        change(SetValue { name: "Do Not Disturb From", set: set_from, old, new: (old + 1) % 24 });
+    } else if hour_button(&UNTIL).contains(x, y, PREFERENCE_X + UNTIL_X, PREFERENCE_Y + 35) {
+        let old = UNTIL.load(Ordering::Relaxed);
+        change(SetValue { name: "Do Not Disturb Until", set: set_until, old, new: (old + 1) % 24 });
+    } else if DELIVER_LATER.contains(x, y, PREFERENCE_X, PREFERENCE_Y + 58) {
+        toggle_setting("Show Missed Notifications Later", &DELIVER_LATER_ON, set_deliver_later);
+    }
+}
+
+// The moon in the menu bar, on a highlight while it's on
+pub fn draw_indicator(graphics: &mut Graphics, x: usize, y: usize) {
+    if is_active() {
+        graphics.draw_rounded_rect(x - 4, y - 4, INDICATOR_WIDTH, INDICATOR_WIDTH, Color::LIGHT_GRAY);
+    }
+    graphics.draw_text("🌙", x, y, Color::BLACK);
+}
+
+// Whether the point hits the indicator drawn at (x, y)
+pub fn indicator_contains(px: usize, py: usize, x: usize, y: usize) -> bool {
+    px + 4 >= x && px < x + INDICATOR_WIDTH - 4 && py + 8 >= y && py < y + 16
+}
\ No newline at end of file
@@ -0,0 +1,79 @@
+// src/dock.rs
+// What apps show on their Dock icons: a badge, either a count or an alert
+// dot, and a progress bar along the bottom. Apps call `set_badge` and
+// `set_progress` from anywhere; those post to the message bus, and the
+// desktop applies the messages to its Dock and draws the decorations
+// over the icons.
+use crate::bus::{self, Message};
+use crate::graphics::{Graphics, Color};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Badge {
+    Count(usize),
+    Alert,
+}
+
+pub fn set_badge(app: &str, badge: Option<Badge>) {
+    bus::post(Message::DockBadge { app: String::from(app), badge });
+}
+
+// `progress` is percent done, or None to take the bar away
+pub fn set_progress(app: &str, progress: Option<u8>) {
+    bus::post(Message::DockProgress { app: String::from(app), progress: progress.map(|percent| percent.min(100)) });
+}
+
+// Decorations by app name
+pub struct Dock {
+    badges: Vec<(String, Badge)>,
+    progress: Vec<(String, u8)>,
+}
+
+fn update<T>(entries: &mut Vec<(String, T)>, app: String, value: Option<T>) {
+    entries.retain(|(name, _)| *name != app);
+    if let Some(value) = value {
+        entries.push((app, value));
+    }
+}
+
+impl Dock {
+    pub const fn new() -> Self {
+        Self { badges: Vec::new(), progress: Vec::new() }
+    }
+    
+    // For the bus messages `set_badge` and `set_progress` post
+    pub fn apply_badge(&mut self, app: String, badge: Option<Badge>) {
+        update(&mut self.badges, app, badge);
+    }
+    
+    pub fn apply_progress(&mut self, app: String, progress: Option<u8>) {
+        update(&mut self.progress, app, progress);
+    }
+    
+    // Takes everything off the icon of an app that quit
+    pub fn forget(&mut self, app: &str) {
+        self.badges.retain(|(name, _)| name != app);
+        self.progress.retain(|(name, _)| name != app);
+    }
+    
+    // Draws `app`'s decorations over its icon, drawn at (x, y) `size` pixels square
+    pub fn draw_decorations(&self, graphics: &mut Graphics, app: &str, x: usize, y: usize, size: usize) {
+        if let Some((_, percent)) = self.progress.iter().find(|(name, _)| name == app) {
+            let bar_width = size - 8;
+            graphics.draw_rounded_rect(x + 4, y + size - 10, bar_width, 6, Color::DARK_GRAY);
+            graphics.draw_rounded_rect(x + 4, y + size - 10, (bar_width * *percent as usize / 100).max(6), 6, Color::BLUE);
+        }
+        match self.badges.iter().find(|(name, _)| name == app).map(|(_, badge)| *badge) {
+            Some(Badge::Count(count)) => {
+                let text = if count > 999 { String::from("999+") } else { count.to_string() };
+                let width = (text.len() * 8 + 8).max(18);
+                let badge_x = x + size + 4 - width;
+                graphics.draw_rounded_rect(badge_x, y - 4, width, 18, Color::RED);
+                graphics.draw_text(&text, badge_x + (width - text.len() * 8) / 2, y + 1, Color::WHITE);
+            }
+            Some(Badge::Alert) => graphics.draw_rounded_rect(x + size - 8, y - 4, 12, 12, Color::RED),
+            None => {}
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,90 @@
+// src/document_viewer.rs
+// The Document Viewer app: shows a Markdown file rendered, scrolled with
+// the arrow keys, Space or the wheel. Cmd+F finds text in the document.
+use crate::graphics::{Graphics, Color};
+use crate::keyboard::{Key, KeyEvent};
+use crate::markdown;
+use crate::styled_text::{StyledText, TextView, LINE_HEIGHT};
+use crate::vfs;
+use crate::widgets::SearchBar;
+use alloc::format;
+use alloc::string::String;
+use crate::sync::Mutex;
+
+const MARGIN: usize = 8;
+
+static STATE: Mutex<Option<TextView>> = Mutex::new(None);
+static SEARCH: Mutex<SearchBar> = Mutex::new(SearchBar::new());
+// File name of the open document, for printing
+static NAME: Mutex<String> = Mutex::new(String::new());
+
+pub fn is_document(path: &str) -> bool {
+    let path = path.to_lowercase();
+    path.ends_with(".md") || path.ends_with(".markdown")
+}
+
+// Reads and parses a Markdown file, or describes why it couldn't be read
+pub fn load(path: &str) -> TextView {
+    let source = match vfs::read_file(path) {
+        Ok(data) => String::from_utf8_lossy(&data).into_owned(),
+        Err(err) => format!("# Cannot open {}\n\n{:?}", vfs::parent_and_name(path).1, err),
+    };
+    TextView::new(markdown::parse(&source))
+}
+
+pub fn open(path: &str) {
+    *STATE.lock() = Some(load(path));
+    *NAME.lock() = String::from(vfs::parent_and_name(path).1);
+}
+
+// The open document's name and text, for the Print dialog
+pub fn printable() -> Option<(String, StyledText)> {
+    let state = STATE.lock();
+    Some((NAME.lock().clone(), state.as_ref()?.text.clone()))
+}
+
+// `width` and `height` are the window content size, as passed to `draw`
+pub fn scroll(rows: isize, width: usize, height: usize) {
+    if let Some(view) = STATE.lock().as_mut() {
+        view.scroll_by(rows, width, height - 2 * MARGIN);
+    }
+}
+
+// Into the Find bar, if it's open
+pub fn insert_text(ch: char) {
+    SEARCH.lock().insert(ch);
+}
+
+pub fn handle_key(event: &KeyEvent, width: usize, height: usize) {
+    if SEARCH.lock().key(event) {
+        return;
+    }
+    let page = ((height - 2 * MARGIN) / LINE_HEIGHT) as isize;
+    match event.key {
+        Key::ArrowUp => scroll(-1, width, height),
+        Key::ArrowDown => scroll(1, width, height),
+        Key::Space if event.shift => scroll(-page, width, height),
+        Key::Space => scroll(page, width, height),
+        _ => {}
+    }
+}
+
+// `x` and `y` are relative to the window content, as drawn by `draw`
+pub fn click(x: usize, y: usize, width: usize) {
+    SEARCH.lock().click(x, y, 0, 0, width);
+}
+
+pub fn draw(graphics: &mut Graphics, x: usize, y: usize, width: usize, height: usize) {
+    let mut state = STATE.lock();
+    let mut search = SEARCH.lock();
+    // The text moves down to make room for the search bar
+    let top = if search.is_visible { SearchBar::HEIGHT } else { 0 };
+    match state.as_mut() {
+        Some(view) => {
+            view.find(&mut search, width, height - top - 2 * MARGIN);
+            view.draw(graphics, x, y + top + MARGIN, width, height - top - 2 * MARGIN);
+        }
+        None => graphics.draw_text("Open a Markdown file from Finder", x + width / 2 - 124, y + height / 2, Color::GRAY),
+    }
+    search.draw(graphics, x, y, width);
+}
\ No newline at end of file
@@ -0,0 +1,83 @@
+// src/edit_menu.rs
+// The menu bar's Edit menu: Undo and Redo for the app in front, named after
+// the change they'd step over and grayed out when there's none. Its title
+// sits after the app menu's, so where depends on the app. It only reports
+// what was picked; the desktop does it.
+use crate::graphics::{Graphics, Color};
+use crate::undo;
+use alloc::format;
+use alloc::string::String;
+
+// The "Edit" title in the menu bar
+pub const TITLE_WIDTH: usize = 32;
+const WIDTH: usize = 200;
+const ROW_HEIGHT: usize = 20;
+const Y: usize = 24;
+
+pub enum EditMenuAction {
+    Undo,
+    Redo,
+}
+
+pub struct EditMenu {
+    pub is_visible: bool,
+    // App whose changes it's for
+    app: String,
+    // Where the title was when it opened
+    title_x: usize,
+}
+
+impl EditMenu {
+    pub fn new() -> Self {
+        Self { is_visible: false, app: String::new(), title_x: 0 }
+    }
+    
+    pub fn show(&mut self, app: &str, title_x: usize) {
+        self.app = String::from(app);
+        self.title_x = title_x;
+        self.is_visible = true;
+    }
+    
+    pub fn hide(&mut self) {
+        self.is_visible = false;
+    }
+    
+    pub fn title_contains(title_x: usize, x: usize, y: usize) -> bool {
+        x >= title_x - 6 && x < title_x + TITLE_WIDTH + 6 && y < Y
+    }
+    
+    pub fn draw(&self, graphics: &mut Graphics) {
+        if !self.is_visible {
+            return;
+        }
+        let title_x = self.title_x;
+        graphics.draw_rect(title_x - 6, 0, TITLE_WIDTH + 12, Y, Color::BLUE);
+        graphics.draw_text("Edit", title_x, 8, Color::WHITE);
+        graphics.draw_rounded_rect(title_x - 6, Y, WIDTH, ROW_HEIGHT * 2 + 8, Color::new(248, 248, 248));
+        graphics.draw_rect_outline(title_x - 6, Y, WIDTH, ROW_HEIGHT * 2 + 8, Color::GRAY);
+        
+        let rows = [("Undo", undo::undo_name(&self.app), "Cmd+Z"), ("Redo", undo::redo_name(&self.app), "Shift+Cmd+Z")];
+        for (i, (verb, name, shortcut)) in rows.iter().enumerate() {
+            let row_y = Y + 4 + i * ROW_HEIGHT + 6;
+            let (label, color) = match name {
+                Some(name) => (format!("{} {}", verb, name), Color::BLACK),
+                None => (String::from(*verb), Color::GRAY),
+            };
+            graphics.draw_text(&label, title_x + 6, row_y, color);
+            graphics.draw_text(shortcut, title_x + WIDTH - 16 - shortcut.len() * 8, row_y, Color::GRAY);
+        }
+    }
+    
+    // Any click closes the menu; None unless it picked Undo or Redo
+    pub fn click(&mut self, x: usize, y: usize) -> Option<EditMenuAction> {
+        self.hide();
+        if x < self.title_x - 6 || x >= self.title_x - 6 + WIDTH || y < Y + 4 {
+            return None;
+        }
+        match (y - Y - 4) / ROW_HEIGHT {
+            0 => Some(EditMenuAction::Undo),
+            1 => Some(EditMenuAction::Redo),
+            _ => None,
+        }
+    }
+}
\ No newline at end of file
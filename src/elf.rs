@@ -0,0 +1,179 @@
+// src/elf.rs
+// Loads apps built with the SDK: 64-bit x86 ELF executables, statically
+// linked inside the user window like examples/hello. Each loadable segment
+// is read from the file straight into fresh pages of the process, the rest
+// of it left zeroed; pages end up writable only if a segment on them is
+// and executable only if one is code. There's no relocation and no dynamic
+// linker.
+use crate::address_space::{AddressSpace, MapError, USER_BASE, USER_END};
+use crate::memory::{self, PAGE_SIZE};
+use crate::users;
+use crate::vfs::{self, Access, VfsError};
+use alloc::collections::BTreeMap;
+use x86_64::structures::paging::{Page, PageTableFlags};
+use x86_64::VirtAddr;
+
+const MAGIC: &[u8; 4] = b"\x7fELF";
+const CLASS_64: u8 = 2;
+const LITTLE_ENDIAN: u8 = 1;
+const EXECUTABLE: u16 = 2;
+const MACHINE_X86_64: u16 = 0x3E;
+const HEADER_LEN: usize = 64;
+const PROGRAM_HEADER_LEN: usize = 56;
+// Far more than a linker makes for a static executable
+const MAX_PROGRAM_HEADERS: usize = 16;
+const PT_LOAD: u32 = 1;
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElfError {
+    // Not an ELF file, or not one this kernel runs
+    NotExecutable,
+    // Outside the user window
+    BadSegment,
+    NoMemory,
+    Vfs(VfsError),
+}
+
+impl From<VfsError> for ElfError {
+    fn from(err: VfsError) -> Self {
+        ElfError::Vfs(err)
+    }
+}
+
+impl From<MapError> for ElfError {
+    fn from(err: MapError) -> Self {
+        match err {
+            MapError::NoMemory => ElfError::NoMemory,
+            MapError::OutOfRange | MapError::AlreadyMapped => ElfError::BadSegment,
+        }
+    }
+}
+
+struct Segment {
+    flags: u32,
+    offset: u64,
+    addr: u64,
+    file_size: u64,
+    mem_size: u64,
+}
+
+impl Segment {
+    fn pages(&self) -> impl Iterator<Item = Page> {
+        let first = Page::containing_address(VirtAddr::new(self.addr));
+        let last = Page::containing_address(VirtAddr::new(self.addr + self.mem_size - 1));
+        Page::range_inclusive(first, last)
+    }
+}
+
+fn u16_at(buf: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([buf[at], buf[at + 1]])
+}
+
+fn u32_at(buf: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(buf[at..at + 4].try_into().unwrap())
+}
+
+fn u64_at(buf: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(buf[at..at + 8].try_into().unwrap())
+}
+
+fn read_header(path: &str) -> Result<[u8; HEADER_LEN], ElfError> {
+    let mut header = [0u8; HEADER_LEN];
+    if vfs::read(path, 0, &mut header)? < HEADER_LEN
+        || &header[..4] != MAGIC
+        || header[4] != CLASS_64
+        || header[5] != LITTLE_ENDIAN
+        || u16_at(&header, 16) != EXECUTABLE
+        || u16_at(&header, 18) != MACHINE_X86_64
+        || u16_at(&header, 54) as usize != PROGRAM_HEADER_LEN
+    {
+        return Err(ElfError::NotExecutable);
+    }
+    Ok(header)
+}
+
+// Whether the file is an app `load` can run
+pub fn is_executable(path: &str) -> bool {
+    read_header(path).is_ok()
+}
+
+// Maps the segment's pages writable and reads its part of the file in.
+// The address space being filled is the active one.
+fn copy_in(path: &str, space: &mut AddressSpace, segment: &Segment) -> Result<(), ElfError> {
+    if segment.file_size > segment.mem_size
+        || segment.addr < USER_BASE
+        || segment.addr >= USER_END
+        || segment.mem_size > USER_END - segment.addr
+    {
+        return Err(ElfError::BadSegment);
+    }
+    for page in segment.pages() {
+        // Segments that share a page share its frame
+        if memory::page_flags(page).is_none() {
+            space.map_user(page.start_address(), PAGE_SIZE, true)?;
+        }
+    }
+    let data = unsafe { core::slice::from_raw_parts_mut(segment.addr as *mut u8, segment.file_size as usize) };
+    if vfs::read(path, segment.offset, data)? < data.len() {
+        return Err(ElfError::NotExecutable);
+    }
+    Ok(())
+}
+
+// Loads the app at `path` into `space` and returns where it starts. The
+// file has to be executable by the current user.
+pub fn load(path: &str, space: &mut AddressSpace) -> Result<VirtAddr, ElfError> {
+    if !vfs::metadata(path)?.permits(users::current_uid(), users::current_gid(), Access::Execute) {
+        return Err(ElfError::Vfs(VfsError::PermissionDenied));
+    }
+    let header = read_header(path)?;
+    let entry = u64_at(&header, 24);
+    let count = u16_at(&header, 56) as usize;
+    if count > MAX_PROGRAM_HEADERS || !(USER_BASE..USER_END).contains(&entry) {
+        return Err(ElfError::NotExecutable);
+    }
+    let mut table = [0u8; PROGRAM_HEADER_LEN * MAX_PROGRAM_HEADERS];
+    let table = &mut table[..count * PROGRAM_HEADER_LEN];
+    if vfs::read(path, u64_at(&header, 32), table)? < table.len() {
+        return Err(ElfError::NotExecutable);
+    }
+    let segments = table.chunks(PROGRAM_HEADER_LEN)
+        .filter(|ph| u32_at(ph, 0) == PT_LOAD && u64_at(ph, 40) > 0)
+        .map(|ph| Segment {
+            flags: u32_at(ph, 4),
+            offset: u64_at(ph, 8),
+            addr: u64_at(ph, 16),
+            file_size: u64_at(ph, 32),
+            mem_size: u64_at(ph, 40),
+        });
+    
+    // Each page's final permissions: writable, executable
+    let mut permissions: BTreeMap<Page, (bool, bool)> = BTreeMap::new();
+    space.activate();
+    let mut result = Ok(());
+    for segment in segments {
+        result = copy_in(path, space, &segment);
+        if result.is_err() {
+            break;
+        }
+        for page in segment.pages() {
+            let (writable, executable) = permissions.entry(page).or_default();
+            *writable |= segment.flags & PF_W != 0;
+            *executable |= segment.flags & PF_X != 0;
+        }
+    }
+    for (page, (writable, executable)) in permissions {
+        let mut flags = PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE;
+        if writable {
+            flags |= PageTableFlags::WRITABLE;
+        }
+        if !executable {
+            flags |= PageTableFlags::NO_EXECUTE;
+        }
+        memory::set_page_flags(page, flags);
+    }
+    AddressSpace::activate_kernel();
+    result.map(|_| VirtAddr::new(entry))
+}
\ No newline at end of file
@@ -0,0 +1,119 @@
+// src/energy_saver.rs
+// The Energy Saver settings, kept in the com.rustos.energy preferences and
+// switched in System Preferences. On battery the desktop can run at a
+// lower frame rate and skip animations the way Reduce motion does, and on
+// any power the display can turn off after a while without input, coming
+// back at the next key press or pointer move.
+use crate::graphics::{Graphics, Color};
+use crate::kwarn;
+use crate::power;
+use crate::preferences;
+use crate::undo::{self, SetValue};
+use crate::vfs::VfsError;
+use crate::widgets::{Button, Checkbox};
+use alloc::format;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+const DOMAIN: &str = "com.rustos.energy";
+const LOWER_FRAME_RATE_KEY: &str = "EnergySaver";
+const REDUCE_ANIMATIONS_KEY: &str = "ReduceAnimations";
+const DISPLAY_SLEEP_KEY: &str = "DisplaySleepMinutes";
+
+// Minutes the display sleep button goes through, 0 for never
+const DISPLAY_SLEEP_CHOICES: [(u64, &str); 5] = [(0, "Never"), (1, "1 min"), (5, "5 min"), (15, "15 min"), (30, "30 min")];
+
+// Where the settings sit in the System Preferences window, under Login Items
+const PREFERENCE_X: usize = 20;
+const PREFERENCE_Y: usize = 380;
+const SLEEP_X: usize = 190;
+const LOWER_FRAME_RATE: Checkbox = Checkbox::new("Lower frame rate on battery");
+const REDUCE_ANIMATIONS: Checkbox = Checkbox::new("Reduce animations on battery");
+
+static LOWER_FRAME_RATE_ON: AtomicBool = AtomicBool::new(false);
+static REDUCE_ANIMATIONS_ON: AtomicBool = AtomicBool::new(false);
+static DISPLAY_SLEEP_MINUTES: AtomicU64 = AtomicU64::new(0);
+
+pub fn load() {
+    LOWER_FRAME_RATE_ON.store(preferences::get_bool(DOMAIN, LOWER_FRAME_RATE_KEY, false), Ordering::Relaxed);
+    REDUCE_ANIMATIONS_ON.store(preferences::get_bool(DOMAIN, REDUCE_ANIMATIONS_KEY, false), Ordering::Relaxed);
+    let minutes = preferences::get(DOMAIN, DISPLAY_SLEEP_KEY).and_then(|minutes| minutes.parse().ok()).unwrap_or(0);
+    DISPLAY_SLEEP_MINUTES.store(minutes, Ordering::Relaxed);
+}
+
+// Whether the desktop should run at its lower frame rate right now
+pub fn lower_frame_rate() -> bool {
+    LOWER_FRAME_RATE_ON.load(Ordering::Relaxed) && power::on_battery()
+}
+
+// Whether animations should be skipped right now
+pub fn reduce_animations() -> bool {
+    REDUCE_ANIMATIONS_ON.load(Ordering::Relaxed) && power::on_battery()
+}
+
+// How long without input before the display turns off, if it ever does
+pub fn display_sleep_ms() -> Option<u64> {
+    match DISPLAY_SLEEP_MINUTES.load(Ordering::Relaxed) {
+        0 => None,
+        minutes => Some(minutes * 60 * 1000),
+    }
+}
+
+fn store(setting: &AtomicBool, key: &str, value: bool) -> Result<(), VfsError> {
+    setting.store(value, Ordering::Relaxed);
+    preferences::set_bool(DOMAIN, key, value)
+}
+
+fn set_lower_frame_rate(on: bool) -> Result<(), VfsError> {
+    store(&LOWER_FRAME_RATE_ON, LOWER_FRAME_RATE_KEY, on)
+}
+
+fn set_reduce_animations(on: bool) -> Result<(), VfsError> {
+    store(&REDUCE_ANIMATIONS_ON, REDUCE_ANIMATIONS_KEY, on)
+}
+
+fn set_display_sleep(minutes: u64) -> Result<(), VfsError> {
+    DISPLAY_SLEEP_MINUTES.store(minutes, Ordering::Relaxed);
+    preferences::set(DOMAIN, DISPLAY_SLEEP_KEY, &format!("{}", minutes))
+}
+
+// Changes go through System Preferences' undo history
+fn change<T: Clone + Send + 'static>(change: SetValue<T>) {
+    let name = change.name;
+    if let Err(err) = undo::perform(preferences::APP, change) {
+        kwarn!("energy_saver: cannot save {}: {:?}", name, err);
+    }
+}
+
+fn toggle(name: &'static str, setting: &AtomicBool, set: fn(bool) -> Result<(), VfsError>) {
+    let old = setting.load(Ordering::Relaxed);
+    change(SetValue { name, set, old, new: !old });
+}
+
+fn display_sleep_button() -> Button {
+    let minutes = DISPLAY_SLEEP_MINUTES.load(Ordering::Relaxed);
+    let label = DISPLAY_SLEEP_CHOICES.iter().find(|(choice, _)| *choice == minutes).map_or("Custom", |(_, label)| label);
+    Button::new(label)
+}
+
+pub fn draw_preference(graphics: &mut Graphics, x: usize, y: usize) {
+    let (x, y) = (x + PREFERENCE_X, y + PREFERENCE_Y);
+    graphics.draw_text("Energy Saver", x, y, Color::BLACK);
+    Checkbox { checked: LOWER_FRAME_RATE_ON.load(Ordering::Relaxed), ..LOWER_FRAME_RATE }.draw(graphics, x, y + 18);
+    Checkbox { checked: REDUCE_ANIMATIONS_ON.load(Ordering::Relaxed), ..REDUCE_ANIMATIONS }.draw(graphics, x, y + 36);
+    graphics.draw_text("Turn display off after", x, y + 59, Color::BLACK);
+    display_sleep_button().draw(graphics, x + SLEEP_X, y + 54);
+}
+
+// `x` and `y` are relative to the window content. Display sleep steps
+// through its choices a click at a time.
+pub fn click_preference(x: usize, y: usize) {
+    if LOWER_FRAME_RATE.contains(x, y, PREFERENCE_X, PREFERENCE_Y + 18) {
+        toggle("Lower Frame Rate on Battery", &LOWER_FRAME_RATE_ON, set_lower_frame_rate);
+    } else if REDUCE_ANIMATIONS.contains(x, y, PREFERENCE_X, PREFERENCE_Y + 36) {
+        toggle("Reduce Animations on Battery", &REDUCE_ANIMATIONS_ON, set_reduce_animations);
+    } else if display_sleep_button().contains(x, y, PREFERENCE_X + SLEEP_X, PREFERENCE_Y + 54) {
+        let old = DISPLAY_SLEEP_MINUTES.load(Ordering::Relaxed);
+        let next = DISPLAY_SLEEP_CHOICES.iter().position(|(minutes, _)| *minutes == old).map_or(0, |i| (i + 1) % DISPLAY_SLEEP_CHOICES.len());
+        change(SetValue { name: "Display Sleep", set: set_display_sleep, old, new: DISPLAY_SLEEP_CHOICES[next].0 });
+    }
+}
\ No newline at end of file
@@ -0,0 +1,85 @@
+// src/entropy.rs
+// Raw entropy for seeding the kernel RNG. Prefers RDSEED, then RDRAND; on
+// CPUs without either it falls back to timing jitter between TSC reads
+// around port I/O, mixed with the RTC time.
+use crate::{cpu, rtc};
+use core::arch::x86_64::{_rdrand64_step, _rdseed64_step, _rdtsc};
+use x86_64::instructions::port::Port;
+
+const RETRIES: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    RdSeed,
+    RdRand,
+    Jitter,
+}
+
+pub fn source() -> Source {
+    let features = cpu::features();
+    if features.rdseed {
+        Source::RdSeed
+    } else if features.rdrand {
+        Source::RdRand
+    } else {
+        Source::Jitter
+    }
+}
+
+#[target_feature(enable = "rdrand")]
+unsafe fn rdrand_step() -> Option<u64> {
+    let mut value = 0;
+    for _ in 0..RETRIES {
+        if _rdrand64_step(&mut value) == 1 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+#[target_feature(enable = "rdseed")]
+unsafe fn rdseed_step() -> Option<u64> {
+    let mut value = 0;
+    for _ in 0..RETRIES {
+        if _rdseed64_step(&mut value) == 1 {
+            return Some(value);
+        }
+        core::hint::spin_loop();
+    }
+    None
+}
+
+// Both can transiently fail (the DRNG is drained); callers fall back
+pub fn rdrand64() -> Option<u64> {
+    if cpu::features().rdrand { unsafe { rdrand_step() } } else { None }
+}
+
+pub fn rdseed64() -> Option<u64> {
+    if cpu::features().rdseed { unsafe { rdseed_step() } } else { None }
+}
+
+// Only the low bits of each delta carry much; 64 samples rotated together
+// give a value worth seeding from, not one to use directly
+pub fn jitter64() -> u64 {
+    let mut delay: Port<u8> = Port::new(0x80);
+    let mut acc = rtc::unix_time();
+    for _ in 0..64 {
+        let start = unsafe { _rdtsc() };
+        unsafe { delay.write(0) };
+        let delta = unsafe { _rdtsc() }.wrapping_sub(start);
+        acc = acc.rotate_left(7) ^ delta;
+        acc = acc.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    }
+    acc
+}
+
+pub fn sample() -> u64 {
+    rdseed64().or_else(rdrand64).unwrap_or_else(jitter64)
+}
+
+pub fn fill(buf: &mut [u8]) {
+    for chunk in buf.chunks_mut(8) {
+        let bytes = sample().to_le_bytes();
+        chunk.copy_from_slice(&bytes[..chunk.len()]);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,358 @@
+// src/ext2.rs
+// Read-only ext2 driver: superblock, block group descriptors, inodes,
+// directories and direct/indirect block maps (no extents, so ext3/ext4
+// images that enable them are rejected at mount time).
+use crate::block::{read_bytes, SharedBlockDevice, SECTOR_SIZE};
+use crate::vfs::{DirEntry, FileSystem, FileType, Metadata, VfsError};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+const EXT2_MAGIC: u16 = 0xEF53;
+const ROOT_INODE: u64 = 2;
+
+const INCOMPAT_FILETYPE: u32 = 0x0002;
+const INCOMPAT_SUPPORTED: u32 = INCOMPAT_FILETYPE;
+// Block sizes run from 1 KiB (0) to 64 KiB (6)
+const MAX_LOG_BLOCK_SIZE: u32 = 6;
+
+const MODE_TYPE_MASK: u16 = 0xF000;
+const MODE_DIR: u16 = 0x4000;
+const MODE_FILE: u16 = 0x8000;
+const MODE_SYMLINK: u16 = 0xA000;
+
+#[derive(Debug, Clone)]
+pub struct Superblock {
+    pub inodes_count: u32,
+    pub blocks_count: u32,
+    pub free_blocks_count: u32,
+    pub first_data_block: u32,
+    pub block_size: usize,
+    pub blocks_per_group: u32,
+    pub inodes_per_group: u32,
+    pub inode_size: usize,
+    pub volume_name: String,
+}
+
+#[derive(Debug, Clone)]
+struct Inode {
+    mode: u16,
+    uid: u32,
+    gid: u32,
+    size: u64,
+    ctime: u32,
+    mtime: u32,
+    block: [u32; 15],
+}
+
+impl Inode {
+    fn file_type(&self) -> FileType {
+        match self.mode & MODE_TYPE_MASK {
+            MODE_DIR => FileType::Directory,
+            MODE_FILE => FileType::File,
+            MODE_SYMLINK => FileType::Symlink,
+            _ => FileType::Other,
+        }
+    }
+}
+
+pub struct Ext2 {
+    device: SharedBlockDevice,
+    superblock: Superblock,
+    inode_tables: Vec<u32>,
+    // In bytes
+    device_size: u64,
+}
+
+fn u16_at(buf: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([buf[offset], buf[offset + 1]])
+}
+
+fn u32_at(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([buf[offset], buf[offset + 1], buf[offset + 2], buf[offset + 3]])
+}
+
+pub fn probe(device: &SharedBlockDevice) -> bool {
+    let mut sb = [0u8; 2];
+    read_bytes(device, 1024 + 56, &mut sb).is_ok() && u16::from_le_bytes(sb) == EXT2_MAGIC
+}
+
+impl Ext2 {
+    pub fn mount(device: SharedBlockDevice) -> Result<Self, VfsError> {
+        let mut raw = [0u8; 1024];
+        read_bytes(&device, 1024, &mut raw)?;
+        
+        if u16_at(&raw, 56) != EXT2_MAGIC {
+            return Err(VfsError::Corrupt);
+        }
+        
+        let rev_level = u32_at(&raw, 76);
+        let incompat = if rev_level >= 1 { u32_at(&raw, 96) } else { 0 };
+        if incompat & !INCOMPAT_SUPPORTED != 0 {
+            return Err(VfsError::Unsupported);
+        }
+        
+        let log_block_size = u32_at(&raw, 24);
+        if log_block_size > MAX_LOG_BLOCK_SIZE {
+            return Err(VfsError::Corrupt);
+        }
+        let block_size = 1024usize.checked_shl(log_block_size).ok_or(VfsError::Corrupt)?;
+        
+        let name_bytes = &raw[120..136];
+        let name_len = name_bytes.iter().position(|&b| b == 0).unwrap_or(16);
+        let superblock = Superblock {
+            inodes_count: u32_at(&raw, 0),
+            blocks_count: u32_at(&raw, 4),
+            free_blocks_count: u32_at(&raw, 12),
+            first_data_block: u32_at(&raw, 20),
+            block_size,
+            blocks_per_group: u32_at(&raw, 32),
+            inodes_per_group: u32_at(&raw, 40),
+            inode_size: if rev_level >= 1 { u16_at(&raw, 88) as usize } else { 128 },
+            volume_name: String::from_utf8_lossy(&name_bytes[..name_len]).into_owned(),
+        };
+        // Nothing the superblock counts can be bigger than the disk
+        let device_size = device.lock().sector_count() * SECTOR_SIZE as u64;
+        if superblock.blocks_per_group == 0
+            || superblock.inodes_per_group == 0
+            || superblock.free_blocks_count > superblock.blocks_count
+            || superblock.blocks_count as u64 * block_size as u64 > device_size
+        {
+            return Err(VfsError::Corrupt);
+        }
+        
+        // Block group descriptors follow the superblock's block
+        let data_blocks = superblock
+            .blocks_count
+            .checked_sub(superblock.first_data_block)
+            .ok_or(VfsError::Corrupt)?;
+        let groups = (data_blocks as u64 + superblock.blocks_per_group as u64 - 1) / superblock.blocks_per_group as u64;
+        let bgdt_offset = (superblock.first_data_block as u64 + 1) * superblock.block_size as u64;
+        if bgdt_offset + groups * 32 > device_size {
+            return Err(VfsError::Corrupt);
+        }
+        let mut descriptors = vec![0u8; groups as usize * 32];
+        read_bytes(&device, bgdt_offset, &mut descriptors)?;
+        
+        let inode_tables = descriptors.chunks(32).map(|d| u32_at(d, 8)).collect();
+        
+        Ok(Self { device, superblock, inode_tables, device_size })
+    }
+    
+    pub fn superblock(&self) -> &Superblock {
+        &self.superblock
+    }
+    
+    fn read_block(&self, block: u32, buf: &mut [u8]) -> Result<(), VfsError> {
+        if block == 0 {
+            // Sparse block
+            buf.iter_mut().for_each(|b| *b = 0);
+            return Ok(());
+        }
+        let offset = block as u64 * self.superblock.block_size as u64;
+        Ok(read_bytes(&self.device, offset, buf)?)
+    }
+    
+    fn read_inode(&self, number: u64) -> Result<Inode, VfsError> {
+        if number == 0 || number > self.superblock.inodes_count as u64 {
+            return Err(VfsError::NotFound);
+        }
+        
+        let index = (number - 1) as u32;
+        let group = (index / self.superblock.inodes_per_group) as usize;
+        let slot = index % self.superblock.inodes_per_group;
+        let table = *self.inode_tables.get(group).ok_or(VfsError::Corrupt)?;
+        
+        let offset = table as u64 * self.superblock.block_size as u64
+            + slot as u64 * self.superblock.inode_size as u64;
+        let mut raw = [0u8; 128];
+        read_bytes(&self.device, offset, &mut raw)?;
+        
+        let mut block = [0u32; 15];
+        for (i, b) in block.iter_mut().enumerate() {
+            *b = u32_at(&raw, 40 + i * 4);
+        }
+        
+        let mode = u16_at(&raw, 0);
+        let mut size = u32_at(&raw, 4) as u64;
+        if mode & MODE_TYPE_MASK == MODE_FILE {
+            size |= (u32_at(&raw, 108) as u64) << 32;
+        }
+        
+        // Owner ids are split into low and high halves (osd2 holds the top 16 bits)
+        let uid = u16_at(&raw, 2) as u32 | (u16_at(&raw, 120) as u32) << 16;
+        let gid = u16_at(&raw, 24) as u32 | (u16_at(&raw, 122) as u32) << 16;
+        
+        Ok(Inode {
+            mode,
+            uid,
+            gid,
+            size,
+            ctime: u32_at(&raw, 12),
+            mtime: u32_at(&raw, 16),
+            block,
+        })
+    }
+    
+    // Maps a file-relative block index through the direct, single, double
+    // and triple indirect pointers.
+    fn map_block(&self, inode: &Inode, index: u64) -> Result<u32, VfsError> {
+        let per_block = (self.superblock.block_size / 4) as u64;
+        
+        if index < 12 {
+            return Ok(inode.block[index as usize]);
+        }
+        
+        let mut index = index - 12;
+        let mut levels = 1;
+        let mut span = per_block;
+        while index >= span {
+            index -= span;
+            levels += 1;
+            span *= per_block;
+            if levels > 3 {
+                return Err(VfsError::Corrupt);
+            }
+        }
+        
+        let mut block = inode.block[11 + levels];
+        let mut buf = vec![0u8; self.superblock.block_size];
+        for level in (0..levels).rev() {
+            if block == 0 {
+                return Ok(0);
+            }
+            self.read_block(block, &mut buf)?;
+            let divisor = per_block.pow(level as u32);
+            let slot = (index / divisor) as usize;
+            index %= divisor;
+            block = u32_at(&buf, slot * 4);
+        }
+        Ok(block)
+    }
+    
+    fn read_inode_data(&self, inode: &Inode, offset: u64, buf: &mut [u8]) -> Result<usize, VfsError> {
+        if offset >= inode.size {
+            return Ok(0);
+        }
+        
+        let block_size = self.superblock.block_size as u64;
+        let len = buf.len().min((inode.size - offset) as usize);
+        let mut block_buf = vec![0u8; block_size as usize];
+        let mut done = 0;
+        
+        while done < len {
+            let pos = offset + done as u64;
+            let block = self.map_block(inode, pos / block_size)?;
+            self.read_block(block, &mut block_buf)?;
+            
+            let start = (pos % block_size) as usize;
+            let n = (block_size as usize - start).min(len - done);
+            buf[done..done + n].copy_from_slice(&block_buf[start..start + n]);
+            done += n;
+        }
+        Ok(done)
+    }
+    
+    fn entries(&self, inode: &Inode) -> Result<Vec<DirEntry>, VfsError> {
+        if inode.size > self.device_size {
+            return Err(VfsError::Corrupt);
+        }
+        let mut data = vec![0u8; inode.size as usize];
+        let len = self.read_inode_data(inode, 0, &mut data)?;
+        data.truncate(len);
+        
+        let mut entries = Vec::new();
+        let mut pos = 0;
+        while pos + 8 <= data.len() {
+            let number = u32_at(&data, pos);
+            let rec_len = u16_at(&data, pos + 4) as usize;
+            let name_len = data[pos + 6] as usize;
+            if rec_len < 8 || pos + rec_len > data.len() || 8 + name_len > rec_len {
+                return Err(VfsError::Corrupt);
+            }
+            
+            if number != 0 {
+                let name = String::from_utf8_lossy(&data[pos + 8..pos + 8 + name_len]).into_owned();
+                let file_type = match data[pos + 7] {
+                    1 => FileType::File,
+                    2 => FileType::Directory,
+                    7 => FileType::Symlink,
+                    _ => FileType::Other,
+                };
+                entries.push(DirEntry { name, inode: number as u64, file_type });
+            }
+            pos += rec_len;
+        }
+        Ok(entries)
+    }
+}
+
+impl FileSystem for Ext2 {
+    fn fs_type(&self) -> &'static str {
+        "ext2"
+    }
+    
+    fn usage(&self) -> Option<(u64, u64)> {
+        let sb = &self.superblock;
+        let block_size = sb.block_size as u64;
+        Some((sb.blocks_count.saturating_sub(sb.free_blocks_count) as u64 * block_size, sb.blocks_count as u64 * block_size))
+    }
+    
+    fn root_inode(&self) -> u64 {
+        ROOT_INODE
+    }
+    
+    fn lookup(&mut self, dir: u64, name: &str) -> Result<u64, VfsError> {
+        let inode = self.read_inode(dir)?;
+        if inode.file_type() != FileType::Directory {
+            return Err(VfsError::NotADirectory);
+        }
+        self.entries(&inode)?
+            .into_iter()
+            .find(|e| e.name == name)
+            .map(|e| e.inode)
+            .ok_or(VfsError::NotFound)
+    }
+    
+    fn metadata(&mut self, inode: u64) -> Result<Metadata, VfsError> {
+        let raw = self.read_inode(inode)?;
+        Ok(Metadata {
+            inode,
+            file_type: raw.file_type(),
+            size: raw.size,
+            mode: raw.mode & 0o7777,
+            uid: raw.uid,
+            gid: raw.gid,
+            created: raw.ctime as u64,
+            modified: raw.mtime as u64,
+        })
+    }
+    
+    fn read_dir(&mut self, inode: u64) -> Result<Vec<DirEntry>, VfsError> {
+        let raw = self.read_inode(inode)?;
+        if raw.file_type() != FileType::Directory {
+            return Err(VfsError::NotADirectory);
+        }
+        Ok(self
+            .entries(&raw)?
+            .into_iter()
+            .filter(|e| e.name != "." && e.name != "..")
+            .collect())
+    }
+    
+    fn read(&mut self, inode: u64, offset: u64, buf: &mut [u8]) -> Result<usize, VfsError> {
+        let raw = self.read_inode(inode)?;
+        if raw.file_type() == FileType::Symlink && raw.size < 60 {
+            // Fast symlinks keep their target inside the block array
+            let mut target = [0u8; 60];
+            for (i, b) in raw.block.iter().enumerate() {
+                target[i * 4..i * 4 + 4].copy_from_slice(&b.to_le_bytes());
+            }
+            let start = (offset as usize).min(raw.size as usize);
+            let n = buf.len().min(raw.size as usize - start);
+            buf[..n].copy_from_slice(&target[start..start + n]);
+            return Ok(n);
+        }
+        self.read_inode_data(&raw, offset, buf)
+    }
+}
\ No newline at end of file
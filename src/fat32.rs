@@ -0,0 +1,608 @@
+// src/fat32.rs
+// FAT32: a read-only driver, and formatting a volume for Disk Utility's
+// Erase. Long file names are read; directories without them show their
+// 8.3 names. Formatting zeroes the reserved sectors, both FATs and the root
+// directory a few sectors per step, like the Finder's archive jobs, then
+// writes the boot sector and FSInfo. Volumes too small for FAT32's 65525
+// clusters are refused. The one other write is making the swap file: a
+// file in the root directory whose clusters are all in one run, so swap
+// can go straight to its sectors.
+use crate::block::{read_bytes, write_bytes, SharedBlockDevice, SECTOR_SIZE};
+use crate::rtc::{self, DateTime};
+use crate::vfs::{DirEntry, FileSystem, FileType, Metadata, VfsError};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+// Inodes are the byte offsets of directory entries; the root has none
+const ROOT_INODE: u64 = 0;
+const ENTRY_SIZE: usize = 32;
+const END_OF_CHAIN: u32 = 0x0FFF_FFF8;
+const CLUSTER_MASK: u32 = 0x0FFF_FFFF;
+const MIN_CLUSTERS: u64 = 65525;
+
+const ATTR_READ_ONLY: u8 = 0x01;
+const ATTR_HIDDEN: u8 = 0x02;
+const ATTR_SYSTEM: u8 = 0x04;
+const ATTR_VOLUME_ID: u8 = 0x08;
+const ATTR_DIRECTORY: u8 = 0x10;
+const ATTR_LONG_NAME: u8 = 0x0F;
+// Set in an 8.3 entry's reserved byte when the name or extension is lower case
+const LOWER_BASE: u8 = 0x08;
+const LOWER_EXTENSION: u8 = 0x10;
+
+// Formatting
+const RESERVED_SECTORS: u64 = 32;
+const FAT_COUNT: u64 = 2;
+const FSINFO_SECTOR: u64 = 1;
+const BACKUP_BOOT_SECTOR: u64 = 6;
+const ROOT_CLUSTER: u32 = 2;
+// Sectors zeroed per step
+const STEP_SECTORS: u64 = 8;
+
+#[derive(Debug, Clone)]
+struct Entry {
+    name: String,
+    attributes: u8,
+    cluster: u32,
+    size: u32,
+    created: u64,
+    modified: u64,
+}
+
+impl Entry {
+    fn is_dir(&self) -> bool {
+        self.attributes & ATTR_DIRECTORY != 0
+    }
+}
+
+pub struct Fat32 {
+    device: SharedBlockDevice,
+    cluster_size: u64,
+    // Byte offsets of the first FAT and of cluster 2
+    fat_offset: u64,
+    data_offset: u64,
+    // Bytes in each FAT
+    fat_size: u64,
+    fat_count: u64,
+    fsinfo_offset: u64,
+    root_cluster: u32,
+    cluster_count: u32,
+    // From FSInfo, if it was kept up to date
+    free_clusters: Option<u32>,
+    pub label: String,
+}
+
+fn u16_at(buf: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([buf[offset], buf[offset + 1]])
+}
+
+fn u32_at(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([buf[offset], buf[offset + 1], buf[offset + 2], buf[offset + 3]])
+}
+
+fn put_u16(buf: &mut [u8], offset: usize, value: u16) {
+    buf[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(buf: &mut [u8], offset: usize, value: u32) {
+    buf[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+fn trimmed(field: &[u8]) -> String {
+    String::from_utf8_lossy(field).trim_end().into()
+}
+
+// "README.TXT" from "README  TXT", lower-cased where Windows marked it so
+fn short_name(raw: &[u8]) -> String {
+    let mut base = trimmed(&raw[0..8]);
+    let mut extension = trimmed(&raw[8..11]);
+    if raw[12] & LOWER_BASE != 0 {
+        base = base.to_lowercase();
+    }
+    if raw[12] & LOWER_EXTENSION != 0 {
+        extension = extension.to_lowercase();
+    }
+    if extension.is_empty() {
+        base
+    } else {
+        base + "." + &extension
+    }
+}
+
+// The UTF-16 characters a long name entry holds, before the padding
+fn long_name_part(raw: &[u8]) -> Vec<u16> {
+    [1..11, 14..26, 28..32]
+        .iter()
+        .flat_map(|range| raw[range.clone()].chunks(2).map(|c| u16::from_le_bytes([c[0], c[1]])))
+        .take_while(|&c| c != 0 && c != 0xFFFF)
+        .collect()
+}
+
+fn parse_entry(raw: &[u8], name: String) -> Entry {
+    let timestamp = |time, date| DateTime::from_dos(u16_at(raw, time), u16_at(raw, date)).to_unix();
+    Entry {
+        name,
+        attributes: raw[11],
+        cluster: (u16_at(raw, 20) as u32) << 16 | u16_at(raw, 26) as u32,
+        size: u32_at(raw, 28),
+        created: timestamp(14, 16),
+        modified: timestamp(22, 24),
+    }
+}
+
+pub fn probe(device: &SharedBlockDevice) -> bool {
+    let mut boot = [0u8; 90];
+    read_bytes(device, 0, &mut boot).is_ok() && &boot[82..87] == b"FAT32" && u16_at(&boot, 11) != 0
+}
+
+impl Fat32 {
+    pub fn mount(device: SharedBlockDevice) -> Result<Self, VfsError> {
+        let mut boot = [0u8; SECTOR_SIZE];
+        read_bytes(&device, 0, &mut boot)?;
+        if &boot[82..87] != b"FAT32" || boot[510..512] != [0x55, 0xAA] {
+            return Err(VfsError::Corrupt);
+        }
+        
+        let sector_size = u16_at(&boot, 11) as u64;
+        let sectors_per_cluster = boot[13] as u64;
+        let reserved = u16_at(&boot, 14) as u64;
+        let fat_size = u32_at(&boot, 36) as u64;
+        let total = u32_at(&boot, 32) as u64;
+        let data_start = reserved + boot[16] as u64 * fat_size;
+        if sector_size == 0 || sectors_per_cluster == 0 || total <= data_start {
+            return Err(VfsError::Corrupt);
+        }
+        
+        // The boot sector's count, but no more than the FAT has entries
+        // for or the device has room for
+        let device_size = device.lock().sector_count() * SECTOR_SIZE as u64;
+        let cluster_size = sectors_per_cluster * sector_size;
+        let cluster_count = ((total - data_start) / sectors_per_cluster)
+            .min((fat_size * sector_size / 4).saturating_sub(2))
+            .min(device_size.saturating_sub(data_start * sector_size) / cluster_size)
+            .min((END_OF_CHAIN - 2) as u64);
+        if cluster_count == 0 {
+            return Err(VfsError::Corrupt);
+        }
+        
+        let fsinfo_offset = u16_at(&boot, 48) as u64 * sector_size;
+        let mut fsinfo = [0u8; SECTOR_SIZE];
+        read_bytes(&device, fsinfo_offset, &mut fsinfo)?;
+        let free_clusters = match (u32_at(&fsinfo, 0), u32_at(&fsinfo, 488)) {
+            (0x4161_5252, free) if free != u32::MAX => Some(free),
+            _ => None,
+        };
+        
+        Ok(Self {
+            device,
+            cluster_size,
+            fat_offset: reserved * sector_size,
+            data_offset: data_start * sector_size,
+            fat_size: fat_size * sector_size,
+            fat_count: boot[16] as u64,
+            fsinfo_offset,
+            root_cluster: u32_at(&boot, 44),
+            cluster_count: cluster_count as u32,
+            free_clusters,
+            label: trimmed(&boot[71..82]),
+        })
+    }
+    
+    fn next_cluster(&self, cluster: u32) -> Result<Option<u32>, VfsError> {
+        let mut raw = [0u8; 4];
+        read_bytes(&self.device, self.fat_offset + cluster as u64 * 4, &mut raw)?;
+        let next = u32::from_le_bytes(raw) & CLUSTER_MASK;
+        Ok(if (2..END_OF_CHAIN).contains(&next) { Some(next) } else { None })
+    }
+    
+    fn cluster_offset(&self, cluster: u32) -> u64 {
+        self.data_offset + (cluster as u64 - 2) * self.cluster_size
+    }
+    
+    // The clusters of a file or directory, in order; a loop in the chain
+    // ends it once it's longer than the volume has clusters
+    fn chain(&self, first: u32) -> Result<Vec<u32>, VfsError> {
+        let mut clusters = Vec::new();
+        let mut cluster = Some(first).filter(|&c| c >= 2);
+        while let Some(c) = cluster {
+            if clusters.len() > self.cluster_count as usize {
+                return Err(VfsError::Corrupt);
+            }
+            clusters.push(c);
+            cluster = self.next_cluster(c)?;
+        }
+        Ok(clusters)
+    }
+    
+    fn entry(&self, inode: u64) -> Result<Entry, VfsError> {
+        if inode == ROOT_INODE {
+            return Ok(Entry {
+                name: String::new(),
+                attributes: ATTR_DIRECTORY,
+                cluster: self.root_cluster,
+                size: 0,
+                created: 0,
+                modified: 0,
+            });
+        }
+        let mut raw = [0u8; ENTRY_SIZE];
+        read_bytes(&self.device, inode, &mut raw)?;
+        Ok(parse_entry(&raw, short_name(&raw)))
+    }
+    
+    // Returns (inode, entry) pairs for a directory's children
+    fn children(&self, dir: &Entry) -> Result<Vec<(u64, Entry)>, VfsError> {
+        let mut children = Vec::new();
+        let mut long_name: Vec<u16> = Vec::new();
+        let mut sector = [0u8; SECTOR_SIZE];
+        for cluster in self.chain(dir.cluster)? {
+            let start = self.cluster_offset(cluster);
+            for sector_offset in (0..self.cluster_size).step_by(SECTOR_SIZE) {
+                read_bytes(&self.device, start + sector_offset, &mut sector)?;
+                for (i, raw) in sector.chunks(ENTRY_SIZE).enumerate() {
+                    match (raw[0], raw[11]) {
+                        (0x00, _) => return Ok(children),
+                        (0xE5, _) => long_name.clear(),
+                        // Long name parts come last first, before their entry
+                        (_, ATTR_LONG_NAME) => {
+                            let mut part = long_name_part(raw);
+                            part.extend_from_slice(&long_name);
+                            long_name = part;
+                        }
+                        (_, attributes) if attributes & ATTR_VOLUME_ID != 0 || raw[0] == b'.' => long_name.clear(),
+                        _ => {
+                            let name = if long_name.is_empty() {
+                                short_name(raw)
+                            } else {
+                                char::decode_utf16(long_name.drain(..)).map(|c| c.unwrap_or('?')).collect()
+                            };
+                            children.push((start + sector_offset + (i * ENTRY_SIZE) as u64, parse_entry(raw, name)));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(children)
+    }
+
+    // The first cluster of a run of `count` free ones
+    fn find_free_run(&self, count: u32) -> Result<u32, VfsError> {
+        let mut sector = [0u8; SECTOR_SIZE];
+        let (mut start, mut run) = (0, 0);
+        for cluster in 2..self.cluster_count + 2 {
+            let offset = self.fat_offset + cluster as u64 * 4;
+            let within = (offset % SECTOR_SIZE as u64) as usize;
+            if within == 0 || cluster == 2 {
+                read_bytes(&self.device, offset - within as u64, &mut sector)?;
+            }
+            if u32_at(&sector, within) & CLUSTER_MASK != 0 {
+                run = 0;
+                continue;
+            }
+            if run == 0 {
+                start = cluster;
+            }
+            run += 1;
+            if run == count {
+                return Ok(start);
+            }
+        }
+        Err(VfsError::NoSpace)
+    }
+
+    // Links the run of clusters into one chain in the FAT at `fat`
+    fn write_chain(&self, fat: u64, first: u32, count: u32) -> Result<(), VfsError> {
+        let mut sector = [0u8; SECTOR_SIZE];
+        let mut loaded = None;
+        for cluster in first..first + count {
+            let offset = fat + cluster as u64 * 4;
+            let sector_start = offset - offset % SECTOR_SIZE as u64;
+            if loaded != Some(sector_start) {
+                if let Some(start) = loaded {
+                    write_bytes(&self.device, start, &sector)?;
+                }
+                read_bytes(&self.device, sector_start, &mut sector)?;
+                loaded = Some(sector_start);
+            }
+            let next = if cluster + 1 == first + count { CLUSTER_MASK } else { cluster + 1 };
+            put_u32(&mut sector, (offset - sector_start) as usize, next);
+        }
+        if let Some(start) = loaded {
+            write_bytes(&self.device, start, &sector)?;
+        }
+        Ok(())
+    }
+
+    // Where a new entry can go in the directory; it isn't grown
+    fn free_entry(&self, dir: &Entry) -> Result<u64, VfsError> {
+        let mut sector = [0u8; SECTOR_SIZE];
+        for cluster in self.chain(dir.cluster)? {
+            let start = self.cluster_offset(cluster);
+            for sector_offset in (0..self.cluster_size).step_by(SECTOR_SIZE) {
+                read_bytes(&self.device, start + sector_offset, &mut sector)?;
+                if let Some(i) = sector.chunks(ENTRY_SIZE).position(|raw| raw[0] == 0x00 || raw[0] == 0xE5) {
+                    return Ok(start + sector_offset + (i * ENTRY_SIZE) as u64);
+                }
+            }
+        }
+        Err(VfsError::NoSpace)
+    }
+
+    // Makes a hidden file of `bytes` in the root directory out of one run
+    // of clusters, or finds the one made before; the byte offset of its
+    // data on the volume. `name` is an 8.3 name without an extension.
+    pub fn preallocate(&mut self, name: &str, bytes: u64) -> Result<u64, VfsError> {
+        let root = self.entry(ROOT_INODE)?;
+        if let Some((_, entry)) = self.children(&root)?.into_iter().find(|(_, entry)| entry.name.eq_ignore_ascii_case(name)) {
+            let chain = self.chain(entry.cluster)?;
+            if (entry.size as u64) < bytes || chain.windows(2).any(|pair| pair[1] != pair[0] + 1) {
+                return Err(VfsError::AlreadyExists);
+            }
+            return Ok(self.cluster_offset(entry.cluster));
+        }
+        // Upper-casing only keeps the length for ASCII
+        if name.is_empty() || name.len() > 8 || !name.is_ascii() || bytes > u32::MAX as u64 {
+            return Err(VfsError::InvalidPath);
+        }
+
+        let count = bytes.div_ceil(self.cluster_size) as u32;
+        let first = self.find_free_run(count)?;
+        let slot = self.free_entry(&root)?;
+        for copy in 0..self.fat_count {
+            self.write_chain(self.fat_offset + copy * self.fat_size, first, count)?;
+        }
+
+        let mut entry = [b' '; ENTRY_SIZE];
+        entry[..name.len()].copy_from_slice(name.to_uppercase().as_bytes());
+        entry[11..].fill(0);
+        entry[11] = ATTR_HIDDEN | ATTR_SYSTEM;
+        let (time, date) = rtc::now().to_dos();
+        for (time_at, date_at) in [(14, 16), (22, 24)] {
+            put_u16(&mut entry, time_at, time);
+            put_u16(&mut entry, date_at, date);
+        }
+        put_u16(&mut entry, 18, date);
+        put_u16(&mut entry, 20, (first >> 16) as u16);
+        put_u16(&mut entry, 26, first as u16);
+        put_u32(&mut entry, 28, bytes as u32);
+        write_bytes(&self.device, slot, &entry)?;
+
+        if let Some(free) = self.free_clusters {
+            self.free_clusters = Some(free.saturating_sub(count));
+            write_bytes(&self.device, self.fsinfo_offset + 488, &free.saturating_sub(count).to_le_bytes())?;
+        }
+        self.device.lock().flush()?;
+        Ok(self.cluster_offset(first))
+    }
+}
+
+impl FileSystem for Fat32 {
+    fn fs_type(&self) -> &'static str {
+        "fat32"
+    }
+    
+    fn root_inode(&self) -> u64 {
+        ROOT_INODE
+    }
+    
+    fn lookup(&mut self, dir: u64, name: &str) -> Result<u64, VfsError> {
+        let dir = self.entry(dir)?;
+        if !dir.is_dir() {
+            return Err(VfsError::NotADirectory);
+        }
+        // FAT names don't keep case
+        self.children(&dir)?
+            .into_iter()
+            .find(|(_, entry)| entry.name.eq_ignore_ascii_case(name))
+            .map(|(inode, _)| inode)
+            .ok_or(VfsError::NotFound)
+    }
+    
+    fn metadata(&mut self, inode: u64) -> Result<Metadata, VfsError> {
+        let entry = self.entry(inode)?;
+        let mode = match (entry.is_dir(), entry.attributes & ATTR_READ_ONLY != 0) {
+            (true, _) => 0o755,
+            (false, true) => 0o444,
+            (false, false) => 0o644,
+        };
+        Ok(Metadata {
+            inode,
+            file_type: if entry.is_dir() { FileType::Directory } else { FileType::File },
+            size: entry.size as u64,
+            mode,
+            uid: 0,
+            gid: 0,
+            created: entry.created,
+            modified: entry.modified,
+        })
+    }
+    
+    fn read_dir(&mut self, inode: u64) -> Result<Vec<DirEntry>, VfsError> {
+        let dir = self.entry(inode)?;
+        if !dir.is_dir() {
+            return Err(VfsError::NotADirectory);
+        }
+        Ok(self
+            .children(&dir)?
+            .into_iter()
+            .map(|(inode, entry)| DirEntry {
+                file_type: if entry.is_dir() { FileType::Directory } else { FileType::File },
+                name: entry.name,
+                inode,
+            })
+            .collect())
+    }
+    
+    fn read(&mut self, inode: u64, offset: u64, buf: &mut [u8]) -> Result<usize, VfsError> {
+        let entry = self.entry(inode)?;
+        if entry.is_dir() {
+            return Err(VfsError::IsADirectory);
+        }
+        if offset >= entry.size as u64 {
+            return Ok(0);
+        }
+        
+        // Up to the end of the cluster the offset is in
+        let index = (offset / self.cluster_size) as usize;
+        let cluster = *self.chain(entry.cluster)?.get(index).ok_or(VfsError::Corrupt)?;
+        let within = offset % self.cluster_size;
+        let n = buf.len().min((entry.size as u64 - offset) as usize).min((self.cluster_size - within) as usize);
+        read_bytes(&self.device, self.cluster_offset(cluster) + within, &mut buf[..n])?;
+        Ok(n)
+    }
+    
+    fn usage(&self) -> Option<(u64, u64)> {
+        let total = self.cluster_count as u64 * self.cluster_size;
+        let free = self.free_clusters? as u64 * self.cluster_size;
+        Some((total.saturating_sub(free), total))
+    }
+}
+
+// Cluster sizes Windows picks for a volume of up to so many sectors
+fn sectors_per_cluster(sectors: u64) -> u64 {
+    match sectors {
+        0..=532_480 => 1,
+        532_481..=16_777_216 => 8,
+        16_777_217..=33_554_432 => 16,
+        33_554_433..=67_108_864 => 32,
+        _ => 64,
+    }
+}
+
+// Erasing a volume as FAT32, a step at a time
+pub struct Format {
+    device: SharedBlockDevice,
+    label: String,
+    // Sectors before the volume on its disk
+    hidden_sectors: u32,
+    sectors_per_cluster: u64,
+    fat_size: u64,
+    cluster_count: u64,
+    // Sectors zeroed so far, out of `zero_sectors()`
+    zeroed: u64,
+}
+
+impl Format {
+    // Checks the volume can hold FAT32; nothing is written until `step`
+    pub fn new(device: SharedBlockDevice, label: &str, hidden_sectors: u32) -> Result<Format, VfsError> {
+        let sectors = {
+            let device = device.lock();
+            if device.is_read_only() {
+                return Err(VfsError::ReadOnly);
+            }
+            device.sector_count()
+        };
+        if sectors > u32::MAX as u64 {
+            return Err(VfsError::Unsupported);
+        }
+        let sectors_per_cluster = sectors_per_cluster(sectors);
+        // Microsoft's estimate, which errs towards a FAT slightly too big
+        let fat_size = (sectors.saturating_sub(RESERVED_SECTORS)).div_ceil((256 * sectors_per_cluster + FAT_COUNT) / 2);
+        let cluster_count = sectors.saturating_sub(RESERVED_SECTORS + FAT_COUNT * fat_size) / sectors_per_cluster;
+        if cluster_count < MIN_CLUSTERS {
+            return Err(VfsError::NoSpace);
+        }
+        
+        // Labels are upper case and at most 11 bytes
+        let mut label: String = label.to_uppercase().chars().filter(|c| c.is_ascii_graphic() || *c == ' ').collect();
+        label.truncate(11);
+        Ok(Format { device, label, hidden_sectors, sectors_per_cluster, fat_size, cluster_count, zeroed: 0 })
+    }
+    
+    // The reserved sectors, both FATs and the root directory's cluster
+    fn zero_sectors(&self) -> u64 {
+        RESERVED_SECTORS + FAT_COUNT * self.fat_size + self.sectors_per_cluster
+    }
+    
+    // From 0 to 1
+    pub fn progress(&self) -> f32 {
+        self.zeroed as f32 / self.zero_sectors() as f32
+    }
+    
+    // Zeroes the next few sectors, or writes the filesystem's structures
+    // once they're all done; true when the volume is formatted
+    pub fn step(&mut self) -> Result<bool, VfsError> {
+        let mut device = self.device.lock();
+        if self.zeroed < self.zero_sectors() {
+            let count = STEP_SECTORS.min(self.zero_sectors() - self.zeroed);
+            device.write_sectors(self.zeroed, &vec![0u8; count as usize * SECTOR_SIZE])?;
+            self.zeroed += count;
+            return Ok(false);
+        }
+        
+        let boot = self.boot_sector(device.sector_count() as u32);
+        let fsinfo = self.fsinfo_sector();
+        for offset in [0, BACKUP_BOOT_SECTOR] {
+            device.write_sectors(offset, &boot)?;
+            device.write_sectors(offset + FSINFO_SECTOR, &fsinfo)?;
+        }
+        
+        // The media byte, the reserved entry and the root's one-cluster chain
+        let mut fat = [0u8; SECTOR_SIZE];
+        put_u32(&mut fat, 0, 0x0FFF_FFF8);
+        put_u32(&mut fat, 4, CLUSTER_MASK);
+        put_u32(&mut fat, 8, CLUSTER_MASK);
+        for copy in 0..FAT_COUNT {
+            device.write_sectors(RESERVED_SECTORS + copy * self.fat_size, &fat)?;
+        }
+        
+        // The root directory starts with the volume label
+        let mut root = [0u8; SECTOR_SIZE];
+        root[..11].copy_from_slice(&self.label_field());
+        root[11] = ATTR_VOLUME_ID;
+        let (time, date) = rtc::now().to_dos();
+        put_u16(&mut root, 22, time);
+        put_u16(&mut root, 24, date);
+        device.write_sectors(RESERVED_SECTORS + FAT_COUNT * self.fat_size, &root)?;
+        device.flush()?;
+        Ok(true)
+    }
+    
+    fn label_field(&self) -> [u8; 11] {
+        let mut field = *b"NO NAME    ";
+        if !self.label.is_empty() {
+            field = [b' '; 11];
+            field[..self.label.len()].copy_from_slice(self.label.as_bytes());
+        }
+        field
+    }
+    
+    fn boot_sector(&self, total_sectors: u32) -> [u8; SECTOR_SIZE] {
+        let mut boot = [0u8; SECTOR_SIZE];
+        boot[0..3].copy_from_slice(&[0xEB, 0x58, 0x90]);
+        boot[3..11].copy_from_slice(b"RUSTOS  ");
+        put_u16(&mut boot, 11, SECTOR_SIZE as u16);
+        boot[13] = self.sectors_per_cluster as u8;
+        put_u16(&mut boot, 14, RESERVED_SECTORS as u16);
+        boot[16] = FAT_COUNT as u8;
+        // Fixed disk
+        boot[21] = 0xF8;
+        put_u16(&mut boot, 24, 32);
+        put_u16(&mut boot, 26, 64);
+        put_u32(&mut boot, 28, self.hidden_sectors);
+        put_u32(&mut boot, 32, total_sectors);
+        put_u32(&mut boot, 36, self.fat_size as u32);
+        put_u32(&mut boot, 44, ROOT_CLUSTER);
+        put_u16(&mut boot, 48, FSINFO_SECTOR as u16);
+        put_u16(&mut boot, 50, BACKUP_BOOT_SECTOR as u16);
+        boot[64] = 0x80;
+        boot[66] = 0x29;
+        put_u32(&mut boot, 67, rtc::unix_time() as u32);
+        boot[71..82].copy_from_slice(&self.label_field());
+        boot[82..90].copy_from_slice(b"FAT32   ");
+        boot[510..512].copy_from_slice(&[0x55, 0xAA]);
+        boot
+    }
+    
+    fn fsinfo_sector(&self) -> [u8; SECTOR_SIZE] {
+        let mut fsinfo = [0u8; SECTOR_SIZE];
+        put_u32(&mut fsinfo, 0, 0x4161_5252);
+        put_u32(&mut fsinfo, 484, 0x6141_7272);
+        // Every cluster but the root's is free, and the next one after it
+        put_u32(&mut fsinfo, 488, self.cluster_count as u32 - 1);
+        put_u32(&mut fsinfo, 492, ROOT_CLUSTER + 1);
+        fsinfo[510..512].copy_from_slice(&[0x55, 0xAA]);
+        fsinfo
+    }
+}
\ No newline at end of file
@@ -0,0 +1,130 @@
+// src/file_associations.rs
+// Which app opens a file. Files are sorted into kinds by extension, and
+// each kind has the apps that can open it. The app that opens a file is
+// the one picked for that file in Get Info, or else the one picked for its
+// kind, here in System Preferences or with Get Info's "Change All". A kind
+// more than one app handles and nobody picked for asks each time. Picks
+// are kept in the com.rustos.associations domain, files by path and kinds
+// by extension.
+use crate::graphics::{Graphics, Color};
+use crate::kwarn;
+use crate::preferences;
+use crate::undo::{self, SetValue};
+use crate::vfs::VfsError;
+use crate::widgets::Button;
+
+const DOMAIN: &str = "com.rustos.associations";
+const PREFERENCE_X: usize = 300;
+const PREFERENCE_Y: usize = 314;
+const ROW_HEIGHT: usize = 20;
+const APP_X: usize = 48;
+// What the pane shows for a kind nobody picked an app for
+const ASK: Button = Button::new("Ask");
+
+pub struct Kind {
+    pub name: &'static str,
+    // Every extension of the kind, the first naming it in preferences
+    extensions: &'static [&'static str],
+    // The apps that can open it
+    pub apps: &'static [&'static str],
+}
+
+pub const KINDS: [Kind; 2] = [
+    Kind { name: "BMP image", extensions: &[".bmp"], apps: &["Preview"] },
+    Kind { name: "Markdown document", extensions: &[".md", ".markdown"], apps: &["Document Viewer", "Safari"] },
+];
+
+pub enum Handler {
+    App(&'static str),
+    // More than one app can open it and none was picked
+    Choose(&'static [&'static str]),
+    None,
+}
+
+pub fn kind_of(path: &str) -> Option<&'static Kind> {
+    let path = path.to_lowercase();
+    KINDS.iter().find(|kind| kind.extensions.iter().any(|extension| path.ends_with(extension)))
+}
+
+impl Kind {
+    pub fn extension(&self) -> &'static str {
+        self.extensions[0]
+    }
+    
+    // `app` if it's one of the kind's apps
+    fn app(&self, app: Option<&str>) -> Option<&'static str> {
+        self.apps.iter().find(|&&candidate| Some(candidate) == app).copied()
+    }
+    
+    // The app picked for the whole kind, or the only one there is
+    pub fn default_app(&self) -> Option<&'static str> {
+        match self.apps {
+            [only] => Some(only),
+            _ => self.app(preferences::get(DOMAIN, self.extension()).as_deref()),
+        }
+    }
+}
+
+pub fn handler(path: &str) -> Handler {
+    let kind = match kind_of(path) {
+        Some(kind) => kind,
+        None => return Handler::None,
+    };
+    match kind.app(preferences::get(DOMAIN, path).as_deref()).or_else(|| kind.default_app()) {
+        Some(app) => Handler::App(app),
+        None => Handler::Choose(kind.apps),
+    }
+}
+
+// The app that opens the file without asking, if there is one
+pub fn app_for(path: &str) -> Option<&'static str> {
+    match handler(path) {
+        Handler::App(app) => Some(app),
+        _ => None,
+    }
+}
+
+pub fn set_app(path: &str, app: &str) -> Result<(), VfsError> {
+    preferences::set(DOMAIN, path, app)
+}
+
+// None goes back to asking
+pub fn set_default(kind: &Kind, app: Option<&str>) -> Result<(), VfsError> {
+    preferences::set(DOMAIN, kind.extension(), app.unwrap_or(""))
+}
+
+// For undo, which needs a plain function
+fn set_default_at((index, app): (usize, Option<&'static str>)) -> Result<(), VfsError> {
+    set_default(&KINDS[index], app)
+}
+
+pub fn draw_preference(graphics: &mut Graphics, x: usize, y: usize) {
+    let (x, y) = (x + PREFERENCE_X, y + PREFERENCE_Y);
+    graphics.draw_text("Open Files With", x, y, Color::BLACK);
+    for (i, kind) in KINDS.iter().enumerate() {
+        let row_y = y + 18 + i * ROW_HEIGHT;
+        graphics.draw_text(kind.extension(), x, row_y + 5, Color::BLACK);
+        kind.default_app().map_or(ASK, Button::new).draw(graphics, x + APP_X, row_y);
+    }
+}
+
+// `x` and `y` are relative to the window content. Clicking a kind's app
+// goes to the next app that can open it, then back to asking.
+pub fn click_preference(x: usize, y: usize) {
+    for (i, kind) in KINDS.iter().enumerate() {
+        let row_y = PREFERENCE_Y + 18 + i * ROW_HEIGHT;
+        let old = kind.default_app();
+        if !old.map_or(ASK, Button::new).contains(x, y, PREFERENCE_X + APP_X, row_y) || kind.apps.len() < 2 {
+            continue;
+        }
+        let next = match old.and_then(|app| kind.apps.iter().position(|&candidate| candidate == app)) {
+            Some(index) => kind.apps.get(index + 1).copied(),
+            None => Some(kind.apps[0]),
+        };
+        let change = SetValue { name: "Open Files With", set: set_default_at, old: (i, old), new: (i, next) };
+        if let Err(err) = undo::perform(preferences::APP, change) {
+            kwarn!("file associations: cannot save: {:?}", err);
+        }
+        return;
+    }
+}
\ No newline at end of file
@@ -0,0 +1,851 @@
+// src/finder.rs
+// The Finder's view of the user's home folder: a grid of its items right
+// of the sidebar. Clicking selects an item and double-clicking opens it;
+// Enter renames it, Cmd+Backspace moves it to the Trash, and Cmd+C on an
+// item then Cmd+Option+V with a folder selected moves the item into the
+// folder. All three are moves, undone with Cmd+Z like any other change.
+// Right-clicking an item opens a menu to compress it into a .zip, extract
+// an archive next to it, or move it to the Trash, with the services that
+// take it below; archives are made and unpacked a few entries a frame
+// under a progress sheet. Images and text files show thumbnails, made in
+// the background. The arrow keys move the selection, and Space shows it
+// in Quick Look.
+//
+// Cmd+3 or the toolbar's Columns button switches to column view: the home
+// folder's items in the first column, and each folder selected fills the
+// column after it, with a preview of a selected file at the end. Up and
+// down move through a column, left and right between columns, and when
+// the columns don't fit the scroll wheel moves them sideways. Cmd+1 or
+// Icons goes back to the grid.
+use crate::archive::{self, Format, Job};
+use crate::clock;
+use crate::dock;
+use crate::graphics::{Graphics, Color};
+use crate::keyboard::{Key, KeyEvent};
+use crate::preview;
+use crate::quick_look;
+use crate::share::{self, Data, Service};
+use crate::thumbnails;
+use crate::undo::{self, Command};
+use crate::users;
+use crate::vfs::{self, DirEntry, FileType, VfsError};
+use crate::widgets::{Button, TextField};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::sync::{Mutex, MutexGuard};
+
+const APP: &str = "Finder";
+// The grid, relative to the window content
+const GRID_X: usize = 141;
+const GRID_Y: usize = 60;
+const COLUMNS: usize = 4;
+const MAX_ITEMS: usize = 12;
+const CELL_WIDTH: usize = 100;
+const CELL_HEIGHT: usize = 80;
+const LABEL_WIDTH: usize = 92;
+// A second click on the same item within this opens it
+const DOUBLE_CLICK_MS: u64 = 500;
+const MENU_WIDTH: usize = 130;
+const MENU_ROW_HEIGHT: usize = 18;
+// The progress sheet, at the top of the grid
+const SHEET_Y: usize = 44;
+const SHEET_HEIGHT: usize = 48;
+// Archive entries done per frame
+const JOB_STEPS: usize = 4;
+// Folder listings kept between changes to the file system
+const CACHED_LISTINGS: usize = 8;
+// Column view, relative to the window content, under the toolbar
+const COLUMNS_X: usize = 121;
+const COLUMNS_Y: usize = 40;
+const COLUMN_WIDTH: usize = 210;
+const ROW_HEIGHT: usize = 22;
+// Toolbar buttons that switch views
+const ICON_VIEW: Button = Button::new("Icons");
+const COLUMN_VIEW: Button = Button::new("Columns");
+const VIEW_BUTTONS_Y: usize = 11;
+
+const CANCEL: Button = Button::new("Cancel");
+
+#[derive(Clone, Copy, PartialEq)]
+enum View {
+    Icons,
+    Columns,
+}
+
+#[derive(Clone, Copy)]
+enum MenuItem {
+    Compress,
+    ExtractHere,
+    MoveToTrash,
+    Service(&'static Service),
+}
+
+impl MenuItem {
+    fn label(self) -> &'static str {
+        match self {
+            MenuItem::Compress => "Compress",
+            MenuItem::ExtractHere => "Extract Here",
+            MenuItem::MoveToTrash => "Move to Trash",
+            MenuItem::Service(service) => service.name,
+        }
+    }
+}
+
+// What the services get for an item; only images are taken
+fn service_data(path: &str, file_type: FileType) -> Option<Data> {
+    (file_type == FileType::File && preview::is_image(path)).then(|| Data::Image(String::from(path)))
+}
+
+// The right-click menu, for the selected item
+struct Menu {
+    // Relative to the window content
+    x: usize,
+    y: usize,
+    items: Vec<MenuItem>,
+}
+
+struct FinderState {
+    view: View,
+    // In column view, the folders selected in the columns left of the
+    // selection's, from the home folder down; empty in the grid
+    parents: Vec<String>,
+    // Name of the selected item, in the folder `parents` lead to
+    selected: Option<String>,
+    // The selected item's new name while it's being renamed, without the
+    // extension, which is kept
+    rename: Option<TextField>,
+    // Path copied with Cmd+C, for Cmd+Option+V to move
+    copied: Option<String>,
+    // Why the last change failed
+    status: Option<String>,
+    // The item last clicked and when, for double-clicks
+    last_click: Option<(String, u64)>,
+    menu: Option<Menu>,
+    // The archive being made or unpacked
+    job: Option<Job>,
+    // The first column shown in column view, and whether to scroll the
+    // last one into view when next drawn
+    first_column: usize,
+    reveal: bool,
+    // The content's size when last drawn
+    size: (usize, usize),
+}
+
+static STATE: Mutex<FinderState> = Mutex::new(FinderState {
+    view: View::Icons,
+    parents: Vec::new(),
+    selected: None,
+    rename: None,
+    copied: None,
+    status: None,
+    last_click: None,
+    menu: None,
+    job: None,
+    first_column: 0,
+    reveal: false,
+    size: (0, 0),
+});
+
+// What the Finder read from the VFS, kept until something changes there so
+// drawing a frame doesn't go to the disk
+struct Cache {
+    generation: u64,
+    listings: Vec<(String, Vec<DirEntry>)>,
+    volumes: Option<Vec<String>>,
+}
+
+static CACHE: Mutex<Cache> = Mutex::new(Cache { generation: 0, listings: Vec::new(), volumes: None });
+
+fn cache() -> MutexGuard<'static, Cache> {
+    let mut cache = CACHE.lock();
+    let generation = vfs::generation();
+    if cache.generation != generation {
+        cache.generation = generation;
+        cache.listings.clear();
+        cache.volumes = None;
+    }
+    cache
+}
+
+// Names of the mounted volumes, for the sidebar
+pub fn volumes() -> Vec<String> {
+    let mut cache = cache();
+    let volumes = cache.volumes.get_or_insert_with(|| {
+        vfs::mounts().iter().map(|volume| String::from(volume.path.rsplit('/').next().unwrap_or(""))).collect()
+    });
+    volumes.clone()
+}
+
+// Moves an item, which is also how it's renamed and put in the Trash
+struct MoveItem {
+    name: &'static str,
+    from: String,
+    to: String,
+}
+
+impl Command for MoveItem {
+    fn name(&self) -> &str {
+        self.name
+    }
+    
+    fn apply(&mut self) -> Result<(), VfsError> {
+        vfs::rename(&self.from, &self.to)
+    }
+    
+    fn revert(&mut self) -> Result<(), VfsError> {
+        vfs::rename(&self.to, &self.from)
+    }
+}
+
+fn home() -> String {
+    format!("/Users/{}", users::user_name(users::current_uid()))
+}
+
+// The folder the selected item is in
+fn folder(state: &FinderState) -> String {
+    state.parents.iter().fold(home(), |path, name| vfs::join(&path, name))
+}
+
+// A folder's items; dot files like the Trash stay hidden
+fn list(path: &str) -> Result<Vec<DirEntry>, VfsError> {
+    if let Some((_, entries)) = cache().listings.iter().find(|(listed, _)| listed == path) {
+        return Ok(entries.clone());
+    }
+    let mut entries = vfs::read_dir(path)?;
+    entries.retain(|entry| !entry.name.starts_with('.'));
+    
+    let mut cache = cache();
+    if cache.listings.len() == CACHED_LISTINGS {
+        cache.listings.remove(0);
+    }
+    cache.listings.push((String::from(path), entries.clone()));
+    Ok(entries)
+}
+
+// What the grid shows
+fn entries() -> Result<Vec<DirEntry>, VfsError> {
+    let mut entries = list(&home())?;
+    entries.truncate(MAX_ITEMS);
+    Ok(entries)
+}
+
+fn item_at(x: usize, y: usize) -> Option<DirEntry> {
+    if x < GRID_X || y < GRID_Y - 10 {
+        return None;
+    }
+    let (col, row) = ((x - GRID_X) / CELL_WIDTH, (y - GRID_Y + 10) / CELL_HEIGHT);
+    if col >= COLUMNS {
+        return None;
+    }
+    entries().ok()?.into_iter().nth(row * COLUMNS + col)
+}
+
+// A column in column view: a folder's items and the one chosen there
+struct Column {
+    entries: Vec<DirEntry>,
+    chosen: Option<String>,
+}
+
+// The columns from the home folder to the selection, then the selected
+// folder's own. A folder that can't be read ends them.
+fn columns(state: &FinderState) -> Vec<Column> {
+    let mut columns = Vec::new();
+    let mut path = home();
+    let chosen = state.parents.iter().chain(state.selected.iter()).map(Some).chain(core::iter::once(None));
+    for chosen in chosen {
+        let Ok(entries) = list(&path) else { break };
+        columns.push(Column { entries, chosen: chosen.cloned() });
+        match chosen.map(|name| vfs::join(&path, name)) {
+            Some(next) if vfs::metadata(&next).is_ok_and(|meta| meta.is_dir()) => path = next,
+            _ => break,
+        }
+    }
+    columns
+}
+
+// The selected item's path when it's a file, for the preview column
+fn preview_path(state: &FinderState) -> Option<String> {
+    let path = vfs::join(&folder(state), state.selected.as_ref()?);
+    vfs::metadata(&path).is_ok_and(|meta| !meta.is_dir()).then_some(path)
+}
+
+// Columns that fit across content `width` wide, the preview counting as one
+fn columns_shown(width: usize) -> usize {
+    (width.saturating_sub(COLUMNS_X + 1) / COLUMN_WIDTH).max(1)
+}
+
+// Rows that fit down content `height` high, leaving room for the status
+fn rows_shown(height: usize) -> usize {
+    (height.saturating_sub(COLUMNS_Y + 30) / ROW_HEIGHT).max(1)
+}
+
+// The first row shown in a column, far enough down to show its chosen item
+fn first_row(column: &Column, rows: usize) -> usize {
+    let chosen = column.chosen.as_ref().and_then(|name| column.entries.iter().position(|entry| &entry.name == name));
+    chosen.map_or(0, |i| (i + 1).saturating_sub(rows))
+}
+
+// In column view, the column under the point and the item there, if any
+fn column_item_at(state: &FinderState, x: usize, y: usize) -> Option<(usize, Option<DirEntry>)> {
+    let (width, height) = state.size;
+    if x < COLUMNS_X || y < COLUMNS_Y + 4 || (x - COLUMNS_X) / COLUMN_WIDTH >= columns_shown(width) {
+        return None;
+    }
+    let index = state.first_column + (x - COLUMNS_X) / COLUMN_WIDTH;
+    let mut columns = columns(state);
+    if index >= columns.len() {
+        return None;
+    }
+    let column = columns.swap_remove(index);
+    let (row, rows) = ((y - COLUMNS_Y - 4) / ROW_HEIGHT, rows_shown(height));
+    let first = first_row(&column, rows);
+    let entry = (row < rows).then(|| column.entries.into_iter().nth(first + row)).flatten();
+    Some((index, entry))
+}
+
+// Selects `name` in a column, or on a blank the folder the column shows
+fn choose(state: &mut FinderState, column: usize, name: Option<String>) {
+    state.parents.truncate(column);
+    state.selected = name.or_else(|| state.parents.pop());
+    state.reveal = true;
+}
+
+fn set_view(state: &mut FinderState, view: View) {
+    // The grid shows the home folder, so the selection goes up to what's
+    // in it
+    if view == View::Icons && !state.parents.is_empty() {
+        state.selected = Some(state.parents.remove(0));
+        state.parents.clear();
+    }
+    state.view = view;
+    state.reveal = true;
+}
+
+// Left edge of the Icons button; Columns follows it
+fn view_buttons_x(width: usize) -> usize {
+    width.saturating_sub(60 + ICON_VIEW.width() + 4 + COLUMN_VIEW.width())
+}
+
+// Shortens `text` with "..." to fit in `width`
+fn truncated(text: &str, width: usize) -> String {
+    let columns = width / 8;
+    if text.chars().count() <= columns {
+        return String::from(text);
+    }
+    let mut text: String = text.chars().take(columns.saturating_sub(3)).collect();
+    text.push_str("...");
+    text
+}
+
+// Splits "Notes.txt" into "Notes" and ".txt"; folders keep their whole name
+fn split_extension(name: &str, file_type: FileType) -> (&str, &str) {
+    match name.rfind('.') {
+        Some(dot) if dot > 0 && file_type != FileType::Directory => name.split_at(dot),
+        _ => (name, ""),
+    }
+}
+
+fn perform(state: &mut FinderState, name: &'static str, from: String, to: String) {
+    state.status = match undo::perform(APP, MoveItem { name, from, to }) {
+        Ok(()) => None,
+        Err(err) => Some(format!("Couldn't {}: {:?}", name.to_lowercase(), err)),
+    };
+}
+
+fn finish_rename(state: &mut FinderState) {
+    let (field, selected) = match (state.rename.take(), state.selected.clone()) {
+        (Some(field), Some(selected)) => (field, selected),
+        _ => return,
+    };
+    let folder = folder(state);
+    let file_type = vfs::metadata(&vfs::join(&folder, &selected)).map_or(FileType::File, |meta| meta.file_type);
+    let new_name = format!("{}{}", field.text.trim(), split_extension(&selected, file_type).1);
+    if field.text.trim().is_empty() || new_name == selected {
+        return;
+    }
+    perform(state, "Rename", vfs::join(&folder, &selected), vfs::join(&folder, &new_name));
+    state.selected = Some(new_name);
+}
+
+// The first name not taken in the Trash: "Notes.txt", "Notes 2.txt", ...
+fn trash_path(name: &str, file_type: FileType) -> Result<String, VfsError> {
+    let trash = vfs::join(&home(), ".Trash");
+    vfs::create_dir_all(&trash)?;
+    let (stem, extension) = split_extension(name, file_type);
+    let mut candidate = String::from(name);
+    let mut n = 2;
+    while vfs::metadata(&vfs::join(&trash, &candidate)).is_ok() {
+        candidate = format!("{} {}{}", stem, n, extension);
+        n += 1;
+    }
+    Ok(vfs::join(&trash, &candidate))
+}
+
+fn move_to_trash(state: &mut FinderState, name: &str, file_type: FileType) {
+    match trash_path(name, file_type) {
+        Ok(to) => {
+            perform(state, "Move to Trash", vfs::join(&folder(state), name), to);
+            state.selected = None;
+        }
+        Err(err) => state.status = Some(format!("Couldn't move to trash: {:?}", err)),
+    }
+}
+
+// Starts making "name.zip", or unpacking an archive into a folder named
+// after it, next to the item
+fn start_job(state: &mut FinderState, item: MenuItem, name: &str) {
+    let folder = folder(state);
+    let path = vfs::join(&folder, name);
+    let job = match item {
+        MenuItem::Compress => {
+            let (stem, _) = split_extension(name, vfs::metadata(&path).map_or(FileType::File, |meta| meta.file_type));
+            Job::compress(&[path], &archive::unique_path(&folder, stem, Format::Zip.extension()))
+        }
+        _ => Job::extract(&path, &archive::unique_path(&folder, archive::stem(name), "")),
+    };
+    match job {
+        Ok(job) => {
+            state.job = Some(job);
+            state.status = None;
+        }
+        Err(err) => state.status = Some(format!("Couldn't {}: {:?}", item.label().to_lowercase(), err)),
+    }
+}
+
+fn sheet_width(width: usize) -> usize {
+    width.saturating_sub(GRID_X + 10)
+}
+
+// Runs the archive job a few entries further; whether there was one, so
+// the Finder gets drawn again
+pub fn poll() -> bool {
+    let mut state = STATE.lock();
+    let job = match state.job.as_mut() {
+        Some(job) => job,
+        None => return false,
+    };
+    let mut result = Ok(false);
+    for _ in 0..JOB_STEPS {
+        result = job.step();
+        if result != Ok(false) {
+            break;
+        }
+    }
+    match result {
+        Ok(false) => dock::set_progress(APP, Some((job.progress() * 100.0) as u8)),
+        Ok(true) => {
+            let output = String::from(vfs::parent_and_name(job.output()).1);
+            state.job = None;
+            state.selected = Some(output);
+            dock::set_progress(APP, None);
+        }
+        Err(err) => {
+            let verb = job.verb;
+            state.status = Some(format!("{} failed: {:?}", verb, err));
+            if let Some(job) = state.job.take() {
+                job.cancel();
+            }
+            dock::set_progress(APP, None);
+        }
+    }
+    true
+}
+
+// Opens the menu for the item under the point; whether there was one
+pub fn right_click(x: usize, y: usize) -> bool {
+    let mut state = STATE.lock();
+    finish_rename(&mut state);
+    let entry = match state.view {
+        View::Icons => item_at(x, y),
+        View::Columns => column_item_at(&state, x, y).and_then(|(column, entry)| {
+            let entry = entry?;
+            state.parents.truncate(column);
+            Some(entry)
+        }),
+    };
+    let entry = match entry {
+        Some(entry) => entry,
+        None => {
+            state.menu = None;
+            return false;
+        }
+    };
+    let mut items = Vec::new();
+    if state.job.is_none() {
+        items.push(MenuItem::Compress);
+        if entry.file_type == FileType::File && Format::of(&entry.name).is_some() {
+            items.push(MenuItem::ExtractHere);
+        }
+    }
+    items.push(MenuItem::MoveToTrash);
+    if let Some(data) = service_data(&vfs::join(&folder(&state), &entry.name), entry.file_type) {
+        items.extend(share::for_data(&data).map(MenuItem::Service));
+    }
+    state.selected = Some(entry.name);
+    state.menu = Some(Menu { x, y, items });
+    true
+}
+
+// `x` and `y` are relative to the window content. Picks from the menu if
+// it's open, or selects the item there; on a double-click, returns its
+// path for the window manager to open.
+pub fn click(x: usize, y: usize, width: usize) -> Option<String> {
+    let mut state = STATE.lock();
+    finish_rename(&mut state);
+    if let Some(menu) = state.menu.take() {
+        let inside = x >= menu.x && x < menu.x + MENU_WIDTH && y >= menu.y + 4;
+        let picked = menu.items.get((y.saturating_sub(menu.y + 4)) / MENU_ROW_HEIGHT).filter(|_| inside);
+        if let (Some(&item), Some(name)) = (picked, state.selected.clone()) {
+            let path = vfs::join(&folder(&state), &name);
+            match item {
+                MenuItem::MoveToTrash => {
+                    let file_type = vfs::metadata(&path).map_or(FileType::File, |meta| meta.file_type);
+                    move_to_trash(&mut state, &name, file_type);
+                }
+                MenuItem::Service(service) => {
+                    if let Some(data) = service_data(&path, FileType::File) {
+                        share::request(service, data);
+                    }
+                }
+                _ => start_job(&mut state, item, &name),
+            }
+        }
+        return None;
+    }
+    if state.job.is_some() && CANCEL.contains(x, y, GRID_X + sheet_width(width) - CANCEL.width() - 10, SHEET_Y + 24) {
+        if let Some(job) = state.job.take() {
+            job.cancel();
+        }
+        dock::set_progress(APP, None);
+        return None;
+    }
+    let buttons_x = view_buttons_x(width);
+    if ICON_VIEW.contains(x, y, buttons_x, VIEW_BUTTONS_Y) {
+        set_view(&mut state, View::Icons);
+        return None;
+    }
+    if COLUMN_VIEW.contains(x, y, buttons_x + ICON_VIEW.width() + 4, VIEW_BUTTONS_Y) {
+        set_view(&mut state, View::Columns);
+        return None;
+    }
+    let entry = match state.view {
+        View::Icons => {
+            let entry = item_at(x, y);
+            state.selected = entry.as_ref().map(|entry| entry.name.clone());
+            entry?
+        }
+        View::Columns => {
+            let (column, entry) = column_item_at(&state, x, y)?;
+            choose(&mut state, column, entry.as_ref().map(|entry| entry.name.clone()));
+            entry?
+        }
+    };
+    let path = vfs::join(&folder(&state), &entry.name);
+    if quick_look::is_visible() {
+        quick_look::show(&path);
+    }
+    let now = clock::millis();
+    let double = matches!(&state.last_click, Some((last, at)) if *last == path && now - at < DOUBLE_CLICK_MS);
+    state.last_click = if double { None } else { Some((path.clone(), now)) };
+    double.then_some(path)
+}
+
+// For Get Info
+pub fn selected_path() -> Option<String> {
+    let state = STATE.lock();
+    state.selected.as_ref().map(|name| vfs::join(&folder(&state), name))
+}
+
+// In column view the wheel moves the columns sideways, a column a line
+pub fn scroll(lines: isize) {
+    let mut state = STATE.lock();
+    if state.view == View::Columns {
+        state.first_column = state.first_column.saturating_add_signed(lines);
+    }
+}
+
+// Into the name being edited, if there is one
+pub fn insert_text(ch: char) {
+    if let Some(field) = STATE.lock().rename.as_mut() {
+        field.insert(ch);
+    }
+}
+
+pub fn handle_key(event: &KeyEvent) {
+    let mut state = STATE.lock();
+    if let Some(field) = state.rename.as_mut() {
+        match event.key {
+            Key::Enter => finish_rename(&mut state),
+            Key::Escape => state.rename = None,
+            Key::Backspace => field.backspace(),
+            key => {
+                let ch = key.to_char().or_else(|| key.to_digit().and_then(|digit| char::from_digit(digit, 10)));
+                if let Some(ch) = ch {
+                    field.insert(if event.shift { ch.to_ascii_uppercase() } else { ch });
+                }
+            }
+        }
+        return;
+    }
+    match (event.key, event.cmd) {
+        (Key::Space | Key::Escape, false) if quick_look::is_visible() => {
+            quick_look::hide();
+            return;
+        }
+        (Key::ArrowLeft | Key::ArrowRight | Key::ArrowUp | Key::ArrowDown, false) => {
+            match state.view {
+                View::Icons => move_selection(&mut state, event.key),
+                View::Columns => move_through_columns(&mut state, event.key),
+            }
+            if let (true, Some(selected)) = (quick_look::is_visible(), &state.selected) {
+                quick_look::show(&vfs::join(&folder(&state), selected));
+            }
+            return;
+        }
+        (Key::Digit1, true) => {
+            set_view(&mut state, View::Icons);
+            return;
+        }
+        (Key::Digit3, true) => {
+            set_view(&mut state, View::Columns);
+            return;
+        }
+        _ => {}
+    }
+    let selected = match state.selected.clone() {
+        Some(selected) => selected,
+        None => return,
+    };
+    let path = vfs::join(&folder(&state), &selected);
+    let file_type = match vfs::metadata(&path) {
+        Ok(meta) => meta.file_type,
+        Err(_) => {
+            state.selected = None;
+            return;
+        }
+    };
+    match (event.key, event.cmd) {
+        (Key::Space, false) => quick_look::show(&path),
+        (Key::Enter, false) => {
+            let mut field = TextField::new("Name");
+            field.text = String::from(split_extension(&selected, file_type).0);
+            field.is_focused = true;
+            state.rename = Some(field);
+        }
+        (Key::Backspace, true) => move_to_trash(&mut state, &selected, file_type),
+        (Key::C, true) => state.copied = Some(path),
+        (Key::V, true) if event.alt && file_type == FileType::Directory => {
+            if let Some(from) = state.copied.take() {
+                let to = vfs::join(&path, vfs::parent_and_name(&from).1);
+                perform(&mut state, "Move", from, to);
+            }
+        }
+        _ => {}
+    }
+}
+
+// Arrows move the selection through the grid, starting from the first
+// item when nothing is selected; moves off the grid are ignored
+fn move_selection(state: &mut FinderState, key: Key) {
+    let entries = match entries() {
+        Ok(entries) if !entries.is_empty() => entries,
+        _ => return,
+    };
+    let current = state.selected.as_ref().and_then(|selected| entries.iter().position(|entry| &entry.name == selected));
+    let next = match (current, key) {
+        (None, _) => Some(0),
+        (Some(i), Key::ArrowLeft) => i.checked_sub(1),
+        (Some(i), Key::ArrowRight) => Some(i + 1),
+        (Some(i), Key::ArrowUp) => i.checked_sub(COLUMNS),
+        (Some(i), _) => Some(i + COLUMNS),
+    };
+    if let Some(entry) = next.and_then(|i| entries.get(i)) {
+        state.selected = Some(entry.name.clone());
+    }
+}
+
+// Up and down move through the selection's column, starting from the top
+// of the first when nothing is selected; right goes into the selected
+// folder and left back out to the one it's in
+fn move_through_columns(state: &mut FinderState, key: Key) {
+    let columns = columns(state);
+    let active = state.parents.len();
+    let entries = match columns.get(active) {
+        Some(column) if !column.entries.is_empty() => &column.entries,
+        _ => return,
+    };
+    let current = state.selected.as_ref().and_then(|selected| entries.iter().position(|entry| &entry.name == selected));
+    let next = match (current, key) {
+        (None, _) => entries.first(),
+        (Some(i), Key::ArrowUp) => i.checked_sub(1).and_then(|i| entries.get(i)),
+        (Some(i), Key::ArrowDown) => entries.get(i + 1),
+        (Some(_), Key::ArrowLeft) => {
+            if let Some(parent) = state.parents.pop() {
+                state.selected = Some(parent);
+            }
+            None
+        }
+        (Some(_), _) => {
+            let first = columns.get(active + 1).and_then(|column| column.entries.first());
+            if let (Some(_), Some(selected)) = (first, state.selected.clone()) {
+                state.parents.push(selected);
+            }
+            first
+        }
+    };
+    if let Some(entry) = next {
+        state.selected = Some(entry.name.clone());
+    }
+    state.reveal = true;
+}
+
+fn draw_grid(graphics: &mut Graphics, state: &FinderState, entries: &[DirEntry], x: usize, y: usize) {
+    for (i, entry) in entries.iter().enumerate() {
+        let item_x = x + GRID_X + (i % COLUMNS) * CELL_WIDTH;
+        let item_y = y + GRID_Y + (i / COLUMNS) * CELL_HEIGHT;
+        let path = vfs::join(&home(), &entry.name);
+        let modified = vfs::metadata(&path).map_or(0, |meta| meta.modified);
+        let thumbnail = entry.file_type == FileType::File
+            && thumbnails::draw(graphics, &path, modified, item_x + 34 - thumbnails::SIZE / 2, item_y - 10);
+        if !thumbnail {
+            let icon = match entry.file_type {
+                FileType::Directory => "📁",
+                _ => "📄",
+            };
+            graphics.draw_text(icon, item_x + 30, item_y, Color::BLACK);
+        }
+        
+        let selected = state.selected.as_deref() == Some(entry.name.as_str());
+        match state.rename.as_ref() {
+            Some(field) if selected => field.draw(graphics, item_x - 4, item_y + 19, LABEL_WIDTH),
+            _ if selected => {
+                let label_width = (entry.name.chars().count() * 8 + 8).min(LABEL_WIDTH);
+                graphics.draw_rounded_rect(item_x - 4, item_y + 21, label_width, 14, Color::BLUE);
+                graphics.draw_text(&entry.name, item_x, item_y + 25, Color::WHITE);
+            }
+            _ => graphics.draw_text(&entry.name, item_x, item_y + 25, Color::BLACK),
+        }
+    }
+}
+
+fn draw_columns(graphics: &mut Graphics, state: &mut FinderState, x: usize, y: usize, width: usize, height: usize) {
+    let columns = columns(state);
+    let preview = preview_path(state);
+    let shown = columns_shown(width);
+    let last_first = (columns.len() + preview.is_some() as usize).saturating_sub(shown);
+    if core::mem::take(&mut state.reveal) {
+        state.first_column = last_first;
+    }
+    state.first_column = state.first_column.min(last_first);
+    
+    let rows = rows_shown(height);
+    let active = state.parents.len();
+    let top = y + COLUMNS_Y + 4;
+    for (i, column) in columns.iter().enumerate().skip(state.first_column).take(shown) {
+        let column_x = x + COLUMNS_X + (i - state.first_column) * COLUMN_WIDTH;
+        graphics.draw_rect(column_x + COLUMN_WIDTH - 1, y + COLUMNS_Y, 1, height.saturating_sub(COLUMNS_Y + 1), Color::LIGHT_GRAY);
+        let first = first_row(column, rows);
+        for (row, entry) in column.entries.iter().enumerate().skip(first).take(rows) {
+            let row_y = top + (row - first) * ROW_HEIGHT;
+            // The selection is blue, the folders it's in gray
+            let chosen = column.chosen.as_deref() == Some(entry.name.as_str());
+            let selected = chosen && i == active;
+            if chosen {
+                let highlight = if selected { Color::BLUE } else { Color::LIGHT_GRAY };
+                graphics.draw_rounded_rect(column_x + 4, row_y, COLUMN_WIDTH - 9, ROW_HEIGHT - 2, highlight);
+            }
+            let icon = match entry.file_type {
+                FileType::Directory => "📁",
+                _ => "📄",
+            };
+            graphics.draw_text(icon, column_x + 8, row_y + 6, Color::BLACK);
+            let color = if selected { Color::WHITE } else { Color::BLACK };
+            match state.rename.as_ref() {
+                Some(field) if selected => field.draw(graphics, column_x + 26, row_y, COLUMN_WIDTH - 34),
+                _ => graphics.draw_text(&truncated(&entry.name, COLUMN_WIDTH - 50), column_x + 28, row_y + 6, color),
+            }
+            if entry.file_type == FileType::Directory {
+                graphics.draw_text(">", column_x + COLUMN_WIDTH - 18, row_y + 6, color);
+            }
+        }
+    }
+    
+    // The selected file's preview takes the column after its own
+    let slot = columns.len().checked_sub(state.first_column).filter(|&slot| slot < shown);
+    if let (Some(path), Some(slot)) = (preview, slot) {
+        draw_preview(graphics, &path, x + COLUMNS_X + slot * COLUMN_WIDTH, top);
+    }
+}
+
+// The file's thumbnail or icon and its name, with its kind, size and dates
+// below
+fn draw_preview(graphics: &mut Graphics, path: &str, x: usize, y: usize) {
+    let meta = match vfs::metadata(path) {
+        Ok(meta) => meta,
+        Err(_) => return,
+    };
+    let center = x + COLUMN_WIDTH / 2;
+    if !thumbnails::draw(graphics, path, meta.modified, center - thumbnails::SIZE / 2, y + 16) {
+        graphics.draw_text("📄", center - 8, y + 26, Color::BLACK);
+    }
+    let name = truncated(vfs::parent_and_name(path).1, COLUMN_WIDTH - 16);
+    graphics.draw_text(&name, center - name.chars().count() * 4, y + 56, Color::BLACK);
+    for (i, (label, value)) in quick_look::info(path, &meta).iter().enumerate() {
+        let row_y = y + 84 + i * 30;
+        graphics.draw_text(label, x + 8, row_y, Color::GRAY);
+        graphics.draw_text(&truncated(value, COLUMN_WIDTH - 16), x + 8, row_y + 12, Color::BLACK);
+    }
+}
+
+// Draws the Finder right of the sidebar, and the view buttons in the
+// toolbar, with `x` and `y` the window content's top-left. False if the
+// home folder can't be read.
+pub fn draw(graphics: &mut Graphics, x: usize, y: usize, width: usize, height: usize) -> bool {
+    let entries = match entries() {
+        Ok(entries) => entries,
+        Err(_) => return false,
+    };
+    let mut state = STATE.lock();
+    state.size = (width, height);
+    let buttons_x = x + view_buttons_x(width);
+    let columns_x = buttons_x + ICON_VIEW.width() + 4;
+    ICON_VIEW.draw(graphics, buttons_x, y + VIEW_BUTTONS_Y);
+    COLUMN_VIEW.draw(graphics, columns_x, y + VIEW_BUTTONS_Y);
+    let (current_x, current_width) = match state.view {
+        View::Icons => (buttons_x, ICON_VIEW.width()),
+        View::Columns => (columns_x, COLUMN_VIEW.width()),
+    };
+    graphics.draw_rect_outline(current_x, y + VIEW_BUTTONS_Y, current_width, Button::HEIGHT, Color::BLUE);
+    
+    match state.view {
+        View::Icons => draw_grid(graphics, &state, &entries, x, y),
+        View::Columns => draw_columns(graphics, &mut state, x, y, width, height),
+    }
+    if let Some(status) = &state.status {
+        graphics.draw_text(status, x + GRID_X, y + height - 20, Color::RED);
+    }
+    
+    if let Some(job) = &state.job {
+        let (sheet_x, sheet_y, sheet_width) = (x + GRID_X - 10, y + SHEET_Y, sheet_width(width));
+        graphics.draw_rounded_rect(sheet_x, sheet_y, sheet_width, SHEET_HEIGHT, Color::new(236, 236, 236));
+        graphics.draw_rect_outline(sheet_x, sheet_y, sheet_width, SHEET_HEIGHT, Color::GRAY);
+        let name = vfs::parent_and_name(job.archive()).1;
+        graphics.draw_text(&format!("{} \"{}\"...", job.verb, name), sheet_x + 10, sheet_y + 8, Color::BLACK);
+        let bar_width = sheet_width.saturating_sub(CANCEL.width() + 40);
+        graphics.draw_rounded_rect(sheet_x + 10, sheet_y + 30, bar_width, 6, Color::LIGHT_GRAY);
+        graphics.draw_rounded_rect(sheet_x + 10, sheet_y + 30, (bar_width as f32 * job.progress()) as usize, 6, Color::BLUE);
+        CANCEL.draw(graphics, sheet_x + sheet_width - CANCEL.width() - 10, sheet_y + 24);
+    }
+    
+    if let Some(menu) = &state.menu {
+        let (menu_x, menu_y) = (x + menu.x, y + menu.y);
+        let menu_height = menu.items.len() * MENU_ROW_HEIGHT + 8;
+        graphics.draw_rounded_rect(menu_x, menu_y, MENU_WIDTH, menu_height, Color::new(248, 248, 248));
+        graphics.draw_rect_outline(menu_x, menu_y, MENU_WIDTH, menu_height, Color::GRAY);
+        for (i, item) in menu.items.iter().enumerate() {
+            graphics.draw_text(item.label(), menu_x + 12, menu_y + 4 + i * MENU_ROW_HEIGHT + 5, Color::BLACK);
+        }
+    }
+    true
+}
\ No newline at end of file
@@ -0,0 +1,61 @@
+// src/focus.rs
+// Which window gets the keyboard. With click to focus, the default, it's
+// the window last clicked; with focus follows mouse, it's the window the
+// pointer moves onto, and moving onto the desktop or the dock keeps the
+// last one. Kept in the com.rustos.focus preferences and switched in
+// System Preferences, under Keyboard. Inside a window, Tab moves focus
+// through the app's controls.
+use crate::graphics::Graphics;
+use crate::kwarn;
+use crate::preferences;
+use crate::undo::{self, SetValue};
+use crate::vfs::VfsError;
+use crate::widgets::Checkbox;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+const DOMAIN: &str = "com.rustos.focus";
+const FOLLOWS_MOUSE_KEY: &str = "FollowsMouse";
+
+// Where the setting sits in the System Preferences window, under Keyboard
+const PREFERENCE_X: usize = 280;
+const PREFERENCE_Y: usize = 216;
+const FOLLOWS_MOUSE: Checkbox = Checkbox::new("Focus follows mouse");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Policy {
+    ClickToFocus,
+    FollowsMouse,
+}
+
+// Whether the policy is FollowsMouse
+static POINTER_FOCUS: AtomicBool = AtomicBool::new(false);
+
+pub fn load() {
+    POINTER_FOCUS.store(preferences::get_bool(DOMAIN, FOLLOWS_MOUSE_KEY, false), Ordering::Relaxed);
+}
+
+pub fn policy() -> Policy {
+    if POINTER_FOCUS.load(Ordering::Relaxed) { Policy::FollowsMouse } else { Policy::ClickToFocus }
+}
+
+fn set_follows_mouse(follows: bool) -> Result<(), VfsError> {
+    POINTER_FOCUS.store(follows, Ordering::Relaxed);
+    preferences::set_bool(DOMAIN, FOLLOWS_MOUSE_KEY, follows)
+}
+
+pub fn draw_preference(graphics: &mut Graphics, x: usize, y: usize) {
+    let checked = policy() == Policy::FollowsMouse;
+    Checkbox { checked, ..FOLLOWS_MOUSE }.draw(graphics, x + PREFERENCE_X, y + PREFERENCE_Y);
+}
+
+// `x` and `y` are relative to the window content. Changes go through
+// System Preferences' undo history.
+pub fn click_preference(x: usize, y: usize) {
+    if FOLLOWS_MOUSE.contains(x, y, PREFERENCE_X, PREFERENCE_Y) {
+        let old = policy() == Policy::FollowsMouse;
+        let change = SetValue { name: "Focus Follows Mouse", set: set_follows_mouse, old, new: !old };
+        if let Err(err) = undo::perform(preferences::APP, change) {
+            kwarn!("focus: cannot save Focus Follows Mouse: {:?}", err);
+        }
+    }
+}
\ No newline at end of file
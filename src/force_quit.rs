@@ -0,0 +1,129 @@
+// src/force_quit.rs
+// The Force Quit Applications window (Cmd+Alt+Esc). Lists the app behind
+// every open window, with the desktop itself last; choosing an app kills
+// it, choosing the desktop relaunches it.
+use crate::graphics::{Graphics, Color, SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::window_manager::WindowManager;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForceQuitAction {
+    // Window index in the window manager
+    Quit(usize),
+    RelaunchDesktop,
+}
+
+struct Entry {
+    name: String,
+    not_responding: bool,
+    action: ForceQuitAction,
+}
+
+pub struct ForceQuitDialog {
+    pub is_visible: bool,
+    entries: Vec<Entry>,
+    pub selected_index: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl ForceQuitDialog {
+    pub fn new() -> Self {
+        Self {
+            is_visible: false,
+            entries: Vec::new(),
+            selected_index: 0,
+            width: 320,
+            height: 280,
+        }
+    }
+    
+    pub fn show(&mut self, window_manager: &WindowManager) {
+        self.entries.clear();
+        for (i, window) in window_manager.windows().iter().enumerate() {
+            self.entries.push(Entry {
+                name: String::from(window.app_name()),
+                not_responding: window.is_hung,
+                action: ForceQuitAction::Quit(i),
+            });
+        }
+        self.entries.push(Entry {
+            name: String::from("Desktop"),
+            not_responding: false,
+            action: ForceQuitAction::RelaunchDesktop,
+        });
+        
+        // Start on an app that hung, if there is one
+        self.selected_index = self.entries.iter().position(|e| e.not_responding).unwrap_or(0);
+        self.is_visible = true;
+    }
+    
+    pub fn hide(&mut self) {
+        self.is_visible = false;
+    }
+    
+    pub fn move_selection(&mut self, direction: i32) {
+        if self.entries.is_empty() {
+            return;
+        }
+        
+        if direction > 0 {
+            self.selected_index = (self.selected_index + 1) % self.entries.len();
+        } else if direction < 0 {
+            self.selected_index = if self.selected_index == 0 {
+                self.entries.len() - 1
+            } else {
+                self.selected_index - 1
+            };
+        }
+    }
+    
+    // Closes the dialog and returns what the selected entry asks for
+    pub fn confirm(&mut self) -> Option<ForceQuitAction> {
+        self.is_visible = false;
+        self.entries.get(self.selected_index).map(|e| e.action)
+    }
+    
+    pub fn draw(&self, graphics: &mut Graphics) {
+        if !self.is_visible {
+            return;
+        }
+        
+        let x = (SCREEN_WIDTH - self.width) / 2;
+        let y = (SCREEN_HEIGHT - self.height) / 2;
+        graphics.draw_rounded_rect(x, y, self.width, self.height, Color::new(236, 236, 236));
+        graphics.draw_rect_outline(x, y, self.width, self.height, Color::GRAY);
+        graphics.draw_text("Force Quit Applications", x + 70, y + 12, Color::BLACK);
+        graphics.draw_text("If an app doesn't respond for a while,", x + 15, y + 36, Color::DARK_GRAY);
+        graphics.draw_text("select its name and click Force Quit.", x + 15, y + 50, Color::DARK_GRAY);
+        
+        // App list
+        let list_y = y + 70;
+        let list_height = self.height - 120;
+        graphics.draw_rect(x + 15, list_y, self.width - 30, list_height, Color::WHITE);
+        graphics.draw_rect_outline(x + 15, list_y, self.width - 30, list_height, Color::LIGHT_GRAY);
+        for (i, entry) in self.entries.iter().enumerate().take(list_height / 20) {
+            let row_y = list_y + 2 + i * 20;
+            let selected = i == self.selected_index;
+            if selected {
+                graphics.draw_rect(x + 16, row_y, self.width - 32, 20, Color::BLUE);
+            }
+            let color = if selected { Color::WHITE } else { Color::BLACK };
+            graphics.draw_text(&entry.name, x + 25, row_y + 6, color);
+            if entry.not_responding {
+                let color = if selected { Color::WHITE } else { Color::RED };
+                graphics.draw_text("(not responding)", x + self.width - 160, row_y + 6, color);
+            }
+        }
+        
+        // The button turns into Relaunch when the desktop is selected
+        let label = match self.entries.get(self.selected_index).map(|e| e.action) {
+            Some(ForceQuitAction::RelaunchDesktop) => "Relaunch",
+            _ => "Force Quit",
+        };
+        let button_y = y + self.height - 40;
+        graphics.draw_rounded_rect(x + self.width - 115, button_y, 100, 28, Color::BLUE);
+        graphics.draw_text(label, x + self.width - 105, button_y + 9, Color::WHITE);
+    }
+}
\ No newline at end of file
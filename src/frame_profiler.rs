@@ -0,0 +1,159 @@
+// src/frame_profiler.rs
+// Per-frame timing of the desktop loop. `profile_scope!("name")` times the
+// rest of the enclosing block and charges it to that section; `end_frame`
+// closes the frame and folds the section totals into the running figures
+// the Activity Monitor shows.
+use crate::clock::Instant;
+use alloc::vec::Vec;
+use core::time::Duration;
+use crate::sync::Mutex;
+
+const MAX_SECTIONS: usize = 8;
+// Weight of the newest frame in the running averages (1/8)
+const AVERAGE_SHIFT: u32 = 3;
+const FPS_WINDOW: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy)]
+pub struct SectionStats {
+    pub name: &'static str,
+    pub last: Duration,
+    pub average: Duration,
+    pub max: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct FrameStats {
+    pub frames: u64,
+    pub fps: u32,
+    pub frame_time: Duration,
+    pub sections: Vec<SectionStats>,
+}
+
+#[derive(Clone, Copy)]
+struct Section {
+    name: &'static str,
+    // Time spent in this section so far during the current frame
+    current: u64,
+    last: u64,
+    average: u64,
+    max: u64,
+}
+
+struct Profiler {
+    sections: [Option<Section>; MAX_SECTIONS],
+    frame_start: Option<Instant>,
+    frames: u64,
+    frame_average: u64,
+    window_start: Option<Instant>,
+    window_frames: u32,
+    fps: u32,
+}
+
+static PROFILER: Mutex<Profiler> = Mutex::new(Profiler {
+    sections: [None; MAX_SECTIONS],
+    frame_start: None,
+    frames: 0,
+    frame_average: 0,
+    window_start: None,
+    window_frames: 0,
+    fps: 0,
+});
+
+fn average(old: u64, new: u64) -> u64 {
+    if old == 0 {
+        new
+    } else {
+        old - (old >> AVERAGE_SHIFT) + (new >> AVERAGE_SHIFT)
+    }
+}
+
+// Adds `elapsed` to the named section of the current frame. Sections past
+// the table size are dropped.
+pub fn record(name: &'static str, elapsed: Duration) {
+    let nanos = elapsed.as_nanos() as u64;
+    let mut profiler = PROFILER.lock();
+    let slot = profiler
+        .sections
+        .iter()
+        .position(|s| s.map_or(true, |s| s.name == name));
+    if let Some(slot) = slot {
+        let section = profiler.sections[slot]
+            .get_or_insert(Section { name, current: 0, last: 0, average: 0, max: 0 });
+        section.current += nanos;
+    }
+}
+
+pub fn end_frame() {
+    let now = Instant::now();
+    let mut profiler = PROFILER.lock();
+    
+    for section in profiler.sections.iter_mut().flatten() {
+        section.last = section.current;
+        section.average = average(section.average, section.current);
+        section.max = section.max.max(section.current);
+        section.current = 0;
+    }
+    
+    if let Some(start) = profiler.frame_start {
+        let frame = now.duration_since(start).as_nanos() as u64;
+        profiler.frame_average = average(profiler.frame_average, frame);
+    }
+    profiler.frame_start = Some(now);
+    profiler.frames += 1;
+    
+    profiler.window_frames += 1;
+    match profiler.window_start {
+        Some(start) if now.duration_since(start) >= FPS_WINDOW => {
+            profiler.fps = profiler.window_frames;
+            profiler.window_frames = 0;
+            profiler.window_start = Some(now);
+        }
+        Some(_) => {}
+        None => profiler.window_start = Some(now),
+    }
+}
+
+pub fn stats() -> FrameStats {
+    let profiler = PROFILER.lock();
+    FrameStats {
+        frames: profiler.frames,
+        fps: profiler.fps,
+        frame_time: Duration::from_nanos(profiler.frame_average),
+        sections: profiler
+            .sections
+            .iter()
+            .flatten()
+            .map(|s| SectionStats {
+                name: s.name,
+                last: Duration::from_nanos(s.last),
+                average: Duration::from_nanos(s.average),
+                max: Duration::from_nanos(s.max),
+            })
+            .collect(),
+    }
+}
+
+// Records the time from creation to drop; made by `profile_scope!`
+pub struct ScopeTimer {
+    name: &'static str,
+    start: Instant,
+}
+
+impl ScopeTimer {
+    pub fn new(name: &'static str) -> Self {
+        Self { name, start: Instant::now() }
+    }
+}
+
+impl Drop for ScopeTimer {
+    fn drop(&mut self) {
+        record(self.name, self.start.elapsed());
+    }
+}
+
+#[macro_export]
+macro_rules! profile_scope {
+    ($name:expr) => {
+        let _profile_scope = $crate::frame_profiler::ScopeTimer::new($name);
+    };
+}
\ No newline at end of file
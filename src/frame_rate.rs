@@ -0,0 +1,53 @@
+// src/frame_rate.rs
+// Pacing for the desktop loop. While something on screen moves, frames
+// come ACTIVE_FPS times a second, or SAVER_FPS when Energy Saver lowers
+// the frame rate on battery. When nothing moves the loop idles at
+// IDLE_FPS, still often enough to poll the input devices, which don't
+// interrupt, and to keep background work going. Between frames the CPU
+// idles until the next timer tick, spinning only through the last part
+// of one.
+use crate::clock::{self, Instant};
+use crate::energy_saver;
+use crate::power;
+use core::time::Duration;
+use crate::sync::Mutex;
+
+const ACTIVE_FPS: u64 = 60;
+const SAVER_FPS: u64 = 30;
+const IDLE_FPS: u64 = 20;
+const TICK: Duration = Duration::from_nanos(1_000_000_000 / clock::TIMER_HZ);
+const DEEP_IDLE_AFTER: Duration = Duration::from_millis(30);
+
+// When the frame in progress started
+static FRAME_START: Mutex<Option<Instant>> = Mutex::new(None);
+
+// Frames a second while something moves
+pub fn target_fps() -> u64 {
+    if energy_saver::lower_frame_rate() { SAVER_FPS } else { ACTIVE_FPS }
+}
+
+// Waits until the next frame is due, counting from when the last one
+// started; a frame that ran long is followed by the next straight away
+pub fn wait(animating: bool) {
+    let fps = if animating { target_fps() } else { IDLE_FPS };
+    let interval = Duration::from_nanos(1_000_000_000 / fps);
+    let started = *FRAME_START.lock();
+    if let Some(started) = started {
+        let deadline = started + interval;
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            // With only the PIT the clock moves on ticks anyway. Waits of
+            // several ticks can afford a deep C-state's wake-up.
+            let left = deadline.duration_since(now);
+            if left > TICK || !clock::is_continuous() {
+                power::idle(left > DEEP_IDLE_AFTER);
+            } else {
+                core::hint::spin_loop();
+            }
+        }
+    }
+    *FRAME_START.lock() = Some(Instant::now());
+}
\ No newline at end of file
@@ -0,0 +1,66 @@
+// src/gdt.rs
+use crate::stack;
+use lazy_static::lazy_static;
+use x86_64::instructions::segmentation::{Segment, CS};
+use x86_64::instructions::tables::load_tss;
+use x86_64::structures::gdt::{Descriptor, GlobalDescriptorTable, SegmentSelector};
+use x86_64::structures::tss::TaskStateSegment;
+use x86_64::VirtAddr;
+
+pub const DOUBLE_FAULT_IST_INDEX: u16 = 0;
+const DOUBLE_FAULT_STACK_PAGES: u64 = 5;
+const ENTRY_STACK_PAGES: u64 = 4;
+
+lazy_static! {
+    static ref TSS: TaskStateSegment = {
+        let mut tss = TaskStateSegment::new();
+        // Double faults get their own guarded stack, so a kernel stack
+        // overflow is reported instead of triple faulting
+        tss.interrupt_stack_table[DOUBLE_FAULT_IST_INDEX as usize] =
+            stack::allocate("double fault", DOUBLE_FAULT_STACK_PAGES).expect("no memory for double fault stack");
+        // Interrupts and system calls from apps switch to this one
+        tss.privilege_stack_table[0] = stack::allocate("user entry", ENTRY_STACK_PAGES).expect("no memory for user entry stack");
+        tss
+    };
+}
+
+struct Selectors {
+    code_selector: SegmentSelector,
+    user_code_selector: SegmentSelector,
+    user_data_selector: SegmentSelector,
+    tss_selector: SegmentSelector,
+}
+
+lazy_static! {
+    static ref GDT: (GlobalDescriptorTable, Selectors) = {
+        let mut gdt = GlobalDescriptorTable::new();
+        let code_selector = gdt.add_entry(Descriptor::kernel_code_segment());
+        let user_data_selector = gdt.add_entry(Descriptor::user_data_segment());
+        let user_code_selector = gdt.add_entry(Descriptor::user_code_segment());
+        let tss_selector = gdt.add_entry(Descriptor::tss_segment(&TSS));
+        (gdt, Selectors { code_selector, user_code_selector, user_data_selector, tss_selector })
+    };
+}
+
+// Needs the heap and frame allocator, so it runs after memory setup
+pub fn init() {
+    GDT.0.load();
+    unsafe {
+        CS::set_reg(GDT.1.code_selector);
+        load_tss(GDT.1.tss_selector);
+    }
+}
+
+pub fn kernel_code_selector() -> SegmentSelector {
+    GDT.1.code_selector
+}
+
+// Code and stack segments for ring 3
+pub fn user_selectors() -> (SegmentSelector, SegmentSelector) {
+    (GDT.1.user_code_selector, GDT.1.user_data_selector)
+}
+
+// Top of the stack the CPU switches to when an app is interrupted
+pub fn entry_stack_top() -> VirtAddr {
+    TSS.privilege_stack_table[0]
+}
\ No newline at end of file
@@ -0,0 +1,304 @@
+// src/get_info.rs
+// Finder's "Get Info" panel: shows a file's size, timestamps, owner and
+// permissions as reported by the VFS, or what an app is. The permissions
+// can be changed by clicking them, and "Open with" picks the app that
+// opens the file, or with "Change All", every file of its kind. A folder's
+// size adds up in the background, a few folders every frame, since it
+// means reading everything inside it.
+use crate::apps::App;
+use crate::file_associations::{self, Handler};
+use crate::widgets::Button;
+use crate::graphics::{Graphics, Color};
+use crate::login_items;
+use crate::preview;
+use crate::rtc::DateTime;
+use crate::system_info;
+use crate::users;
+use crate::vfs::{self, FileType, Metadata};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+// Folders read per frame while adding up a folder's size
+const SCAN_BATCH: usize = 8;
+// Layout, relative to the panel
+const VALUE_X: usize = 90;
+const ROW_HEIGHT: usize = 16;
+const OPEN_WITH_Y: usize = 148;
+const CHANGE_ALL_Y: usize = 164;
+const PERMISSIONS_Y: usize = 210;
+const PRIVILEGE_X: usize = 130;
+
+const CHANGE_ALL: Button = Button::new("Change All");
+
+// Read and write bits for one of owner, group and everyone, in the order
+// clicking goes through them
+const PRIVILEGES: [(u16, &str); 4] = [(0o6, "Read & Write"), (0o4, "Read only"), (0o2, "Write only"), (0o0, "No Access")];
+
+fn privilege(bits: u16) -> &'static str {
+    PRIVILEGES.iter().find(|(mask, _)| bits & 0o6 == *mask).map_or("No Access", |(_, name)| name)
+}
+
+pub fn kind(path: &str, file_type: FileType) -> &'static str {
+    match file_type {
+        FileType::Directory => "Folder",
+        FileType::Symlink => "Alias",
+        FileType::File => file_associations::kind_of(path).map_or("Document", |kind| kind.name),
+        FileType::Other => "Special file",
+    }
+}
+
+// A folder's contents being added up
+struct SizeScan {
+    // Folders still to read
+    pending: Vec<String>,
+    bytes: u64,
+    items: usize,
+}
+
+pub struct GetInfoPanel {
+    pub is_visible: bool,
+    pub path: String,
+    metadata: Option<Metadata>,
+    // Shown instead of a file when the panel is for an app
+    app: Option<&'static App>,
+    scan: Option<SizeScan>,
+    // Why the last change didn't work
+    error: Option<String>,
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl GetInfoPanel {
+    pub fn new() -> Self {
+        Self {
+            is_visible: false,
+            path: String::new(),
+            metadata: None,
+            app: None,
+            scan: None,
+            error: None,
+            x: 360,
+            y: 80,
+            width: 260,
+            height: 300,
+        }
+    }
+    
+    pub fn show(&mut self, path: &str) {
+        self.path = String::from(path);
+        self.metadata = vfs::metadata(path).ok();
+        self.app = None;
+        self.error = None;
+        self.scan = match self.metadata {
+            Some(meta) if meta.is_dir() => Some(SizeScan { pending: alloc::vec![String::from(path)], bytes: 0, items: 0 }),
+            _ => None,
+        };
+        self.is_visible = true;
+    }
+    
+    pub fn show_app(&mut self, app: &'static App) {
+        self.path = String::from(app.name);
+        self.metadata = None;
+        self.app = Some(app);
+        self.scan = None;
+        self.error = None;
+        self.is_visible = true;
+    }
+    
+    pub fn hide(&mut self) {
+        self.is_visible = false;
+        self.scan = None;
+    }
+    
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        self.is_visible && x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+    
+    // Reads a few more folders of the one being sized; whether the panel
+    // changed
+    pub fn poll(&mut self) -> bool {
+        let scan = match self.scan.as_mut() {
+            Some(scan) if !scan.pending.is_empty() => scan,
+            _ => return false,
+        };
+        for _ in 0..SCAN_BATCH {
+            let dir = match scan.pending.pop() {
+                Some(dir) => dir,
+                None => break,
+            };
+            let entries = match vfs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            for entry in entries {
+                let path = vfs::join(&dir, &entry.name);
+                scan.items += 1;
+                if entry.file_type == FileType::Directory {
+                    scan.pending.push(path);
+                } else if let Ok(meta) = vfs::metadata(&path) {
+                    scan.bytes += meta.size;
+                }
+            }
+        }
+        true
+    }
+    
+    fn size(&self, meta: &Metadata) -> String {
+        match &self.scan {
+            Some(scan) if !scan.pending.is_empty() => format!("Calculating... {}", system_info::format_size(scan.bytes)),
+            Some(scan) => format!("{} for {} items", system_info::format_size(scan.bytes), scan.items),
+            None => format!("{} bytes", meta.size),
+        }
+    }
+    
+    pub fn click(&mut self, x: usize, y: usize) {
+        if x < self.x + 26 && y < self.y + 24 {
+            self.hide();
+            return;
+        }
+        let meta = match self.metadata {
+            Some(meta) => meta,
+            None => return,
+        };
+        let row = |row_y: usize| y >= self.y + row_y - 2 && y < self.y + row_y - 2 + ROW_HEIGHT;
+        
+        let kind = file_associations::kind_of(&self.path);
+        if let Some(kind) = kind.filter(|_| row(OPEN_WITH_Y) && x >= self.x + VALUE_X) {
+            let next = match file_associations::handler(&self.path) {
+                Handler::App(app) => kind.apps.iter().position(|&candidate| candidate == app).map_or(0, |i| (i + 1) % kind.apps.len()),
+                _ => 0,
+            };
+            self.error = file_associations::set_app(&self.path, kind.apps[next])
+                .err()
+                .map(|err| format!("Couldn't change the app: {:?}", err));
+            return;
+        }
+        if let (Some(kind), Some(app)) = (kind, file_associations::app_for(&self.path)) {
+            if CHANGE_ALL.contains(x, y, self.x + VALUE_X - 4, self.y + CHANGE_ALL_Y) {
+                self.error = file_associations::set_default(kind, Some(app))
+                    .err()
+                    .map(|err| format!("Couldn't change the app: {:?}", err));
+                return;
+            }
+        }
+        
+        if x < self.x + PRIVILEGE_X {
+            return;
+        }
+        // Owner, group and everyone, each three bits further right
+        let shift = match (0..3).find(|&i| row(PERMISSIONS_Y + i * ROW_HEIGHT)) {
+            Some(i) => 6 - i * 3,
+            None => return,
+        };
+        let bits = (meta.mode >> shift) & 0o6;
+        let next = PRIVILEGES.iter().position(|(mask, _)| *mask == bits).map_or(0, |i| (i + 1) % PRIVILEGES.len());
+        let mode = (meta.mode & !(0o6 << shift)) | (PRIVILEGES[next].0 << shift);
+        self.error = vfs::chmod(&self.path, mode).err().map(|err| format!("Couldn't change permissions: {:?}", err));
+        self.metadata = vfs::metadata(&self.path).ok();
+    }
+    
+    pub fn draw(&self, graphics: &mut Graphics) {
+        if !self.is_visible {
+            return;
+        }
+        
+        graphics.draw_rounded_rect(self.x, self.y, self.width, self.height, Color::new(236, 236, 236));
+        graphics.draw_rect_outline(self.x, self.y, self.width, self.height, Color::GRAY);
+        
+        let (_, name) = vfs::parent_and_name(&self.path);
+        graphics.draw_rounded_rect(self.x + 10, self.y + 8, 12, 12, Color::RED);
+        graphics.draw_text(&format!("{} Info", name), self.x + 32, self.y + 10, Color::BLACK);
+        graphics.draw_rect(self.x + 1, self.y + 28, self.width - 2, 1, Color::LIGHT_GRAY);
+        
+        if let Some(app) = self.app {
+            self.draw_app(graphics, app);
+            return;
+        }
+        
+        let meta = match self.metadata {
+            Some(meta) => meta,
+            None => {
+                graphics.draw_text("File not found", self.x + 15, self.y + 45, Color::GRAY);
+                return;
+            }
+        };
+        
+        let icon = match meta.file_type {
+            FileType::Directory => "📁",
+            _ if preview::is_image(&self.path) => "🖼️",
+            _ => "📄",
+        };
+        graphics.draw_text(icon, self.x + self.width - 30, self.y + 40, Color::BLACK);
+        
+        let rows = [
+            ("Kind:", String::from(kind(&self.path, meta.file_type))),
+            ("Size:", self.size(&meta)),
+            ("Where:", String::from(vfs::parent_and_name(&self.path).0)),
+            ("Created:", DateTime::from_unix(meta.created).long()),
+            ("Modified:", DateTime::from_unix(meta.modified).long()),
+        ];
+        
+        let mut row_y = self.y + 40;
+        graphics.draw_text("General", self.x + 15, row_y, Color::DARK_GRAY);
+        row_y += 18;
+        for (label, value) in rows.iter() {
+            graphics.draw_text(label, self.x + 15, row_y, Color::GRAY);
+            graphics.draw_text(value, self.x + VALUE_X, row_y, Color::BLACK);
+            row_y += ROW_HEIGHT;
+        }
+        
+        // Files nobody picked an app for ask which one when opened
+        let open_with = match file_associations::handler(&self.path) {
+            Handler::App(app) => Some((app, true)),
+            Handler::Choose(_) => Some(("Ask", false)),
+            Handler::None => None,
+        };
+        if let Some((app, settled)) = open_with {
+            graphics.draw_text("Open with:", self.x + 15, self.y + OPEN_WITH_Y, Color::GRAY);
+            graphics.draw_rounded_rect(self.x + VALUE_X - 4, self.y + OPEN_WITH_Y - 4, 130, ROW_HEIGHT, Color::WHITE);
+            graphics.draw_text(app, self.x + VALUE_X, self.y + OPEN_WITH_Y, Color::BLACK);
+            if settled {
+                CHANGE_ALL.draw(graphics, self.x + VALUE_X - 4, self.y + CHANGE_ALL_Y);
+            }
+        }
+        
+        graphics.draw_text("Sharing & Permissions", self.x + 15, self.y + PERMISSIONS_Y - 18, Color::DARK_GRAY);
+        let permissions = [
+            (users::user_name(meta.uid), privilege(meta.mode >> 6)),
+            (users::group_name(meta.gid), privilege(meta.mode >> 3)),
+            ("everyone", privilege(meta.mode)),
+        ];
+        for (i, (who, access)) in permissions.iter().enumerate() {
+            let row_y = self.y + PERMISSIONS_Y + i * ROW_HEIGHT;
+            graphics.draw_text(who, self.x + 15, row_y, Color::BLACK);
+            graphics.draw_text(access, self.x + PRIVILEGE_X, row_y, Color::BLUE);
+        }
+        
+        let row_y = self.y + PERMISSIONS_Y + 3 * ROW_HEIGHT;
+        graphics.draw_text(&meta.mode_string(), self.x + 15, row_y + 8, Color::GRAY);
+        if let Some(error) = &self.error {
+            graphics.draw_text(error, self.x + 15, row_y + 28, Color::RED);
+        }
+    }
+    
+    fn draw_app(&self, graphics: &mut Graphics, app: &App) {
+        graphics.draw_text(app.icon, self.x + self.width - 30, self.y + 40, Color::BLACK);
+        let rows = [
+            ("Kind:", String::from("Application")),
+            ("Category:", String::from(app.category)),
+            ("In Dock:", String::from(if app.in_dock { "Yes" } else { "No" })),
+            ("At login:", String::from(if login_items::load().iter().any(|item| item.app == app.name) { "Opens" } else { "Doesn't open" })),
+        ];
+        let mut row_y = self.y + 40;
+        graphics.draw_text("General", self.x + 15, row_y, Color::DARK_GRAY);
+        row_y += 18;
+        for (label, value) in rows.iter() {
+            graphics.draw_text(label, self.x + 15, row_y, Color::GRAY);
+            graphics.draw_text(value, self.x + VALUE_X, row_y, Color::BLACK);
+            row_y += ROW_HEIGHT;
+        }
+    }
+}
\ No newline at end of file
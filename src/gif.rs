@@ -0,0 +1,182 @@
+// src/gif.rs
+// Animated GIF writer for screen frames: palette indices into the 16
+// standard VGA colors, which become the global color table. Frames are
+// LZW-compressed and streamed to the file as they're encoded, so only a few
+// kilobytes are held at once; the dictionary lives in a static, since it's
+// bigger than the heap.
+use crate::graphics::VGA_PALETTE;
+use crate::vfs::{self, VfsError};
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::sync::Mutex;
+
+const COLORS: usize = 16;
+const MIN_CODE_SIZE: u32 = 4;
+const CLEAR: u16 = 1 << MIN_CODE_SIZE;
+const END: u16 = CLEAR + 1;
+const FIRST_CODE: u16 = CLEAR + 2;
+const MAX_CODES: u16 = 4096;
+const MAX_CODE_SIZE: u32 = 12;
+// Pending output is written to the file once it grows past this
+const FLUSH_AT: usize = 4096;
+
+// The code for a string plus one more pixel, at `code * COLORS + pixel`;
+// 0 where there's none yet, since no string gets a code that low
+static DICTIONARY: Mutex<[u16; MAX_CODES as usize * COLORS]> = Mutex::new([0; MAX_CODES as usize * COLORS]);
+
+// Packs variable-width codes into bytes, least significant bit first, and
+// the bytes into the length-prefixed sub-blocks GIF image data comes in
+struct Packer {
+    bits: u32,
+    count: u32,
+    block: [u8; 255],
+    len: usize,
+}
+
+impl Packer {
+    fn new() -> Self {
+        Self { bits: 0, count: 0, block: [0; 255], len: 0 }
+    }
+    
+    fn put(&mut self, code: u16, size: u32, out: &mut Vec<u8>) {
+        self.bits |= (code as u32) << self.count;
+        self.count += size;
+        while self.count >= 8 {
+            self.byte(self.bits as u8, out);
+            self.bits >>= 8;
+            self.count -= 8;
+        }
+    }
+    
+    fn byte(&mut self, byte: u8, out: &mut Vec<u8>) {
+        self.block[self.len] = byte;
+        self.len += 1;
+        if self.len == self.block.len() {
+            self.end_block(out);
+        }
+    }
+    
+    fn end_block(&mut self, out: &mut Vec<u8>) {
+        if self.len > 0 {
+            out.push(self.len as u8);
+            out.extend_from_slice(&self.block[..self.len]);
+            self.len = 0;
+        }
+    }
+    
+    // Writes out the last partial byte and block
+    fn finish(&mut self, out: &mut Vec<u8>) {
+        if self.count > 0 {
+            self.byte(self.bits as u8, out);
+            self.bits = 0;
+            self.count = 0;
+        }
+        self.end_block(out);
+    }
+}
+
+pub struct GifWriter {
+    path: String,
+    offset: u64,
+    pending: Vec<u8>,
+    width: u16,
+    height: u16,
+}
+
+impl GifWriter {
+    // Creates or replaces the file and writes the header; the animation
+    // loops forever
+    pub fn create(path: &str, width: usize, height: usize) -> Result<Self, VfsError> {
+        vfs::write_file(path, &[])?;
+        let mut writer = Self {
+            path: String::from(path),
+            offset: 0,
+            pending: Vec::with_capacity(FLUSH_AT + 512),
+            width: width as u16,
+            height: height as u16,
+        };
+        writer.pending.extend_from_slice(b"GIF89a");
+        writer.pending.extend_from_slice(&writer.width.to_le_bytes());
+        writer.pending.extend_from_slice(&writer.height.to_le_bytes());
+        // Global color table of 2^(3 + 1) entries, 8 bits per primary
+        writer.pending.extend_from_slice(&[0xF3, 0, 0]);
+        for color in VGA_PALETTE {
+            writer.pending.extend_from_slice(&[color.r, color.g, color.b]);
+        }
+        writer.pending.extend_from_slice(&[0x21, 0xFF, 0x0B]);
+        writer.pending.extend_from_slice(b"NETSCAPE2.0");
+        writer.pending.extend_from_slice(&[0x03, 0x01, 0x00, 0x00, 0x00]);
+        writer.flush()?;
+        Ok(writer)
+    }
+    
+    // `pixels` are the frame's palette indices row by row; `delay` is how
+    // long it shows, in hundredths of a second
+    pub fn add_frame(&mut self, pixels: &[u8], delay: u16) -> Result<(), VfsError> {
+        if pixels.is_empty() {
+            return Ok(());
+        }
+        let delay = delay.to_le_bytes();
+        self.pending.extend_from_slice(&[0x21, 0xF9, 0x04, 0x00, delay[0], delay[1], 0x00, 0x00]);
+        self.pending.extend_from_slice(&[0x2C, 0, 0, 0, 0]);
+        self.pending.extend_from_slice(&self.width.to_le_bytes());
+        self.pending.extend_from_slice(&self.height.to_le_bytes());
+        self.pending.extend_from_slice(&[0x00, MIN_CODE_SIZE as u8]);
+        
+        let mut dictionary = DICTIONARY.lock();
+        dictionary.fill(0);
+        let mut packer = Packer::new();
+        let mut size = MIN_CODE_SIZE + 1;
+        let mut next = FIRST_CODE;
+        packer.put(CLEAR, size, &mut self.pending);
+        
+        let mut pixels = pixels.iter().map(|&pixel| pixel as usize % COLORS);
+        let mut prefix = pixels.next().unwrap_or(0) as u16;
+        for pixel in pixels {
+            let slot = prefix as usize * COLORS + pixel;
+            if dictionary[slot] != 0 {
+                prefix = dictionary[slot];
+                continue;
+            }
+            packer.put(prefix, size, &mut self.pending);
+            if next < MAX_CODES {
+                // The new code may be the next one out, so widen first
+                if next == 1 << size {
+                    size += 1;
+                }
+                dictionary[slot] = next;
+                next += 1;
+            } else {
+                packer.put(CLEAR, size, &mut self.pending);
+                dictionary.fill(0);
+                size = MIN_CODE_SIZE + 1;
+                next = FIRST_CODE;
+            }
+            prefix = pixel as u16;
+            if self.pending.len() > FLUSH_AT {
+                self.flush()?;
+            }
+        }
+        packer.put(prefix, size, &mut self.pending);
+        // The decoder widens as soon as it adds the code for `prefix`
+        if next == 1 << size && size < MAX_CODE_SIZE {
+            size += 1;
+        }
+        packer.put(END, size, &mut self.pending);
+        packer.finish(&mut self.pending);
+        self.pending.push(0);
+        self.flush()
+    }
+    
+    pub fn finish(mut self) -> Result<(), VfsError> {
+        self.pending.push(0x3B);
+        self.flush()
+    }
+    
+    fn flush(&mut self) -> Result<(), VfsError> {
+        let written = vfs::write(&self.path, self.offset, &self.pending)?;
+        self.offset += written as u64;
+        self.pending.clear();
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,179 @@
+// src/glyph_cache.rs
+// Text turned into spans of lit pixels so drawing it is a handful of fills
+// instead of a bit test per pixel. Each glyph's spans are worked out once,
+// upright or slanted; labels drawn every frame, like menu and window
+// titles, keep spans for the whole string, with runs that cross from one
+// glyph into the next joined. Spans carry no color, so a cached label
+// draws in any; the caches are still cleared when the theme changes.
+use crate::sync::Mutex;
+
+// The font is the SDK's, so apps draw the same letters
+pub use rustos_sdk::font::{font_data, GLYPH_HEIGHT, GLYPH_WIDTH};
+
+// Glyphs below this are cached; others are worked out each time
+const CACHED_CHARS: usize = 128;
+// A row of a glyph has at most four runs
+const MAX_GLYPH_SPANS: usize = GLYPH_HEIGHT * 4;
+const LABELS: usize = 32;
+// Longer labels aren't cached
+const MAX_LABEL_CHARS: usize = 80;
+const MAX_LABEL_SPANS: usize = 320;
+
+// Lit pixels from (x, y), relative to the text's top-left
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Span {
+    pub x: u16,
+    pub y: u8,
+    pub len: u16,
+}
+
+#[derive(Clone, Copy)]
+pub struct Glyph {
+    spans: [Span; MAX_GLYPH_SPANS],
+    count: usize,
+}
+
+impl Glyph {
+    pub fn spans(&self) -> &[Span] {
+        &self.spans[..self.count]
+    }
+}
+
+struct Label {
+    // Of the text; 0 for an empty slot
+    hash: u64,
+    slanted: bool,
+    last_used: u64,
+    spans: [Span; MAX_LABEL_SPANS],
+    count: usize,
+}
+
+struct Caches {
+    // Upright, then slanted
+    glyphs: [[Option<Glyph>; CACHED_CHARS]; 2],
+    labels: [Label; LABELS],
+    clock: u64,
+}
+
+const EMPTY_LABEL: Label = Label { hash: 0, slanted: false, last_used: 0, spans: [Span { x: 0, y: 0, len: 0 }; MAX_LABEL_SPANS], count: 0 };
+
+// Static rather than heap: the label spans alone are 60 KiB
+static CACHES: Mutex<Caches> = Mutex::new(Caches {
+    glyphs: [[None; CACHED_CHARS]; 2],
+    labels: [EMPTY_LABEL; LABELS],
+    clock: 0,
+});
+
+// Whether the pixel at (x, y) of the glyph is lit. The font has no italic
+// face, so slanted glyphs have their top half moved one pixel right.
+fn lit(ch: char, slanted: bool, x: usize, y: usize) -> bool {
+    let x = match slanted && y < GLYPH_HEIGHT / 2 {
+        true if x == 0 => return false,
+        true => x - 1,
+        false => x,
+    };
+    x < GLYPH_WIDTH && font_data(ch)[y] >> (7 - x) & 1 == 1
+}
+
+// Calls `f` with each run of lit pixels in a row `width` pixels wide
+fn runs(width: usize, lit: impl Fn(usize) -> bool, mut f: impl FnMut(usize, usize)) {
+    let mut start = None;
+    for x in 0..=width {
+        match (start, x < width && lit(x)) {
+            (None, true) => start = Some(x),
+            (Some(from), false) => {
+                f(from, x - from);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+}
+
+fn make_glyph(ch: char, slanted: bool) -> Glyph {
+    let mut glyph = Glyph { spans: [Span::default(); MAX_GLYPH_SPANS], count: 0 };
+    for y in 0..GLYPH_HEIGHT {
+        runs(GLYPH_WIDTH + 1, |x| lit(ch, slanted, x, y), |x, len| {
+            glyph.spans[glyph.count] = Span { x: x as u16, y: y as u8, len: len as u16 };
+            glyph.count += 1;
+        });
+    }
+    glyph
+}
+
+pub fn glyph(ch: char, slanted: bool) -> Glyph {
+    if (ch as usize) >= CACHED_CHARS {
+        return make_glyph(ch, slanted);
+    }
+    *CACHES.lock().glyphs[slanted as usize][ch as usize].get_or_insert_with(|| make_glyph(ch, slanted))
+}
+
+// FNV-1a, never 0
+fn hash(text: &str) -> u64 {
+    text.bytes().fold(0xCBF2_9CE4_8422_2325, |hash, b| (hash ^ b as u64).wrapping_mul(0x0100_0000_01B3)).max(1)
+}
+
+// Calls `f` with the label's spans, working them out the first time; false
+// if the label is too long to cache
+pub fn with_label(text: &str, slanted: bool, f: impl FnOnce(&[Span])) -> bool {
+    let mut chars = ['\0'; MAX_LABEL_CHARS];
+    let mut count = 0;
+    for ch in text.chars() {
+        if count == MAX_LABEL_CHARS {
+            return false;
+        }
+        chars[count] = ch;
+        count += 1;
+    }
+    
+    let mut caches = CACHES.lock();
+    let caches = &mut *caches;
+    caches.clock += 1;
+    let hash = hash(text);
+    let index = match caches.labels.iter().position(|label| label.hash == hash && label.slanted == slanted) {
+        Some(index) => index,
+        None => {
+            let index = (0..LABELS).min_by_key(|&i| caches.labels[i].last_used).unwrap_or(0);
+            let label = &mut caches.labels[index];
+            label.count = 0;
+            for y in 0..GLYPH_HEIGHT {
+                // A slanted glyph's last column lands on the next one's first
+                let lit_at = |x: usize| {
+                    let (i, col) = (x / GLYPH_WIDTH, x % GLYPH_WIDTH);
+                    (i < count && lit(chars[i], slanted, col, y)) || (col == 0 && i > 0 && lit(chars[i - 1], slanted, GLYPH_WIDTH, y))
+                };
+                let mut full = false;
+                runs(count * GLYPH_WIDTH + 1, lit_at, |x, len| {
+                    if label.count == MAX_LABEL_SPANS {
+                        full = true;
+                        return;
+                    }
+                    label.spans[label.count] = Span { x: x as u16, y: y as u8, len: len as u16 };
+                    label.count += 1;
+                });
+                if full {
+                    label.hash = 0;
+                    return false;
+                }
+            }
+            label.hash = hash;
+            label.slanted = slanted;
+            index
+        }
+    };
+    let label = &mut caches.labels[index];
+    label.last_used = caches.clock;
+    f(&label.spans[..label.count]);
+    true
+}
+
+// After a theme or font change
+pub fn invalidate() {
+    let mut caches = CACHES.lock();
+    for glyphs in caches.glyphs.iter_mut() {
+        glyphs.fill(None);
+    }
+    for label in caches.labels.iter_mut() {
+        label.hash = 0;
+    }
+}
\ No newline at end of file
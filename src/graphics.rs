@@ -1,38 +1,127 @@
 // src/graphics.rs
+use crate::glyph_cache::{self, GLYPH_WIDTH};
+use crate::icons::{self, Icon, ICON_SIZE};
+use rustos_sdk::Canvas;
+use alloc::boxed::Box;
+#[cfg(not(target_os = "none"))]
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(target_os = "none")]
 use volatile::Volatile;
 
 pub const SCREEN_WIDTH: usize = 640;
 pub const SCREEN_HEIGHT: usize = 480;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct Color {
-    pub r: u8,
-    pub g: u8,
-    pub b: u8,
+// Ordered dithering thresholds; a faded surface shows its pixel wherever
+// the threshold is under its opacity, so fades work on a palette screen
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+// Shared with apps, which draw with it too
+pub use rustos_sdk::Color;
+
+// What each palette index shows: the VGA's power-on palette
+pub const VGA_PALETTE: [Color; 16] = [
+    Color::new(0, 0, 0), Color::new(0, 0, 170), Color::new(0, 170, 0), Color::new(0, 170, 170),
+    Color::new(170, 0, 0), Color::new(170, 0, 170), Color::new(170, 85, 0), Color::new(170, 170, 170),
+    Color::new(85, 85, 85), Color::new(85, 85, 255), Color::new(85, 255, 85), Color::new(85, 255, 255),
+    Color::new(255, 85, 85), Color::new(255, 85, 255), Color::new(255, 255, 85), Color::new(255, 255, 255),
+];
+
+// Pixels copied off the screen to be drawn again later, as palette indices
+// row by row. Always screen-sized, since that is far more than the heap
+// holds; keep them in statics.
+pub struct Surface {
+    pixels: [u8; SCREEN_WIDTH * SCREEN_HEIGHT],
+    width: usize,
+    height: usize,
+}
+
+impl Surface {
+    pub const fn new() -> Self {
+        Self { pixels: [0; SCREEN_WIDTH * SCREEN_HEIGHT], width: 0, height: 0 }
+    }
+    
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    
+    // The captured palette indices, row by row
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels[..self.width * self.height]
+    }
+}
+
+// Where drawing lands, as palette indices row by row: the VGA's memory, or
+// plain memory for drawing off screen
+pub trait Framebuffer {
+    fn read(&self, offset: usize) -> u8;
+    fn write(&mut self, offset: usize, value: u8);
+    
+    // Sets `len` pixels from `offset`
+    fn fill(&mut self, offset: usize, len: usize, value: u8) {
+        for offset in offset..offset + len {
+            self.write(offset, value);
+        }
+    }
+}
+
+#[cfg(target_os = "none")]
+struct Vga(&'static mut [Volatile<u8>]);
+
+#[cfg(target_os = "none")]
+impl Framebuffer for Vga {
+    fn read(&self, offset: usize) -> u8 {
+        self.0[offset].read()
+    }
+    
+    fn write(&mut self, offset: usize, value: u8) {
+        self.0[offset].write(value);
+    }
+    
+    // Fast path for CPUs with ERMS: one `rep stosb` per span. The kernel is
+    // built without SSE, so string instructions are the fastest fill we have.
+    fn fill(&mut self, offset: usize, len: usize, value: u8) {
+        if !crate::cpu::features().erms {
+            self.0[offset..offset + len].iter_mut().for_each(|pixel| pixel.write(value));
+            return;
+        }
+        let dst = self.0[offset..offset + len].as_mut_ptr() as *mut u8;
+        unsafe {
+            core::arch::asm!(
+                "rep stosb",
+                inout("rdi") dst => _,
+                inout("rcx") len => _,
+                in("al") value,
+                options(nostack, preserves_flags)
+            );
+        }
+    }
 }
 
-impl Color {
-    pub const fn new(r: u8, g: u8, b: u8) -> Self {
-        Self { r, g, b }
-    }
-    
-    pub const WHITE: Color = Color::new(255, 255, 255);
-    pub const BLACK: Color = Color::new(0, 0, 0);
-    pub const GRAY: Color = Color::new(128, 128, 128);
-    pub const LIGHT_GRAY: Color = Color::new(240, 240, 245);
-    pub const DARK_GRAY: Color = Color::new(60, 60, 60);
-    pub const BLUE: Color = Color::new(0, 122, 255);
-    pub const RED: Color = Color::new(255, 59, 48);
-    pub const GREEN: Color = Color::new(52, 199, 89);
-    pub const YELLOW: Color = Color::new(255, 204, 0);
-    pub const TRANSPARENT: Color = Color::new(0, 0, 1);
+impl Framebuffer for Vec<u8> {
+    fn read(&self, offset: usize) -> u8 {
+        self[offset]
+    }
+    
+    fn write(&mut self, offset: usize, value: u8) {
+        self[offset] = value;
+    }
+    
+    fn fill(&mut self, offset: usize, len: usize, value: u8) {
+        self[offset..offset + len].fill(value);
+    }
 }
 
 pub struct Graphics {
-    framebuffer: &'static mut [Volatile<u8>],
+    framebuffer: Box<dyn Framebuffer>,
 }
 
 impl Graphics {
+    #[cfg(target_os = "none")]
     pub fn new() -> Self {
         let framebuffer = unsafe {
             core::slice::from_raw_parts_mut(
@@ -41,28 +130,97 @@ impl Graphics {
             )
         };
         
-        Self { framebuffer }
+        Self { framebuffer: Box::new(Vga(framebuffer)) }
+    }
+    
+    // Draws into memory instead of on screen, for tests on the host; the
+    // frame is screen-sized, far more than the kernel heap holds
+    #[cfg(not(target_os = "none"))]
+    pub fn headless() -> Self {
+        Self { framebuffer: Box::new(vec![0u8; SCREEN_WIDTH * SCREEN_HEIGHT]) }
     }
     
     pub fn set_pixel(&mut self, x: usize, y: usize, color: Color) {
         if x < SCREEN_WIDTH && y < SCREEN_HEIGHT {
             let offset = y * SCREEN_WIDTH + x;
             let vga_color = self.rgb_to_vga(color);
-            self.framebuffer[offset].write(vga_color);
+            self.framebuffer.write(offset, vga_color);
+        }
+    }
+    
+    // The color on screen at a point, as the display shows it
+    pub fn get_pixel(&self, x: usize, y: usize) -> Color {
+        if x >= SCREEN_WIDTH || y >= SCREEN_HEIGHT {
+            return Color::BLACK;
         }
+        VGA_PALETTE[(self.framebuffer.read(y * SCREEN_WIDTH + x) & 0x0F) as usize]
     }
     
     pub fn clear_screen(&mut self, color: Color) {
         let vga_color = self.rgb_to_vga(color);
-        for pixel in self.framebuffer.iter_mut() {
-            pixel.write(vga_color);
-        }
+        self.framebuffer.fill(0, SCREEN_WIDTH * SCREEN_HEIGHT, vga_color);
     }
     
     pub fn draw_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: Color) {
-        for dy in 0..height {
-            for dx in 0..width {
-                self.set_pixel(x + dx, y + dy, color);
+        if x >= SCREEN_WIDTH {
+            return;
+        }
+        let vga_color = self.rgb_to_vga(color);
+        let width = width.min(SCREEN_WIDTH - x);
+        for row in y..(y + height).min(SCREEN_HEIGHT) {
+            self.framebuffer.fill(row * SCREEN_WIDTH + x, width, vga_color);
+        }
+    }
+    
+    // Copies the on-screen part of a rectangle into `surface`, to be drawn
+    // again later with `draw_scaled`
+    pub fn capture(&self, surface: &mut Surface, x: usize, y: usize, width: usize, height: usize) {
+        let width = width.min(SCREEN_WIDTH.saturating_sub(x));
+        let height = height.min(SCREEN_HEIGHT.saturating_sub(y));
+        for py in 0..height {
+            for px in 0..width {
+                surface.pixels[py * width + px] = self.framebuffer.read((y + py) * SCREEN_WIDTH + x + px);
+            }
+        }
+        surface.width = width;
+        surface.height = height;
+    }
+    
+    // Draws a captured surface stretched or shrunk to `width` × `height`,
+    // nearest neighbour
+    pub fn draw_scaled(&mut self, surface: &Surface, x: usize, y: usize, width: usize, height: usize) {
+        self.draw_faded(surface, x, y, width, height, 1.0);
+    }
+    
+    // `draw_scaled` at `opacity` from 0 to 1, dithered over what's on screen
+    pub fn draw_faded(&mut self, surface: &Surface, x: usize, y: usize, width: usize, height: usize, opacity: f32) {
+        let (source_width, source_height) = (surface.width, surface.height);
+        if source_width == 0 || source_height == 0 {
+            return;
+        }
+        let threshold = opacity * 16.0;
+        for dy in 0..height.min(SCREEN_HEIGHT.saturating_sub(y)) {
+            let row = (dy * source_height / height) * source_width;
+            for dx in 0..width.min(SCREEN_WIDTH.saturating_sub(x)) {
+                let (sx, sy) = (x + dx, y + dy);
+                if BAYER[sy % 4][sx % 4] as f32 + 0.5 >= threshold {
+                    continue;
+                }
+                let pixel = surface.pixels[row + dx * source_width / width];
+                self.framebuffer.write(sy * SCREEN_WIDTH + sx, pixel);
+            }
+        }
+    }
+    
+    // Draws a surface at its own size, moved `dx` pixels to the right of
+    // the left edge (left for negative); whatever falls off screen is dropped
+    pub fn draw_offset(&mut self, surface: &Surface, dx: i32) {
+        let first = (-dx).max(0) as usize;
+        let last = (SCREEN_WIDTH as i32 - dx).clamp(0, surface.width as i32) as usize;
+        for py in 0..surface.height.min(SCREEN_HEIGHT) {
+            for px in first..last {
+                let sx = (px as i32 + dx) as usize;
+                self.framebuffer.write(py * SCREEN_WIDTH + sx, surface.pixels[py * surface.width + px]);
             }
         }
     }
@@ -102,43 +260,93 @@ impl Graphics {
     }
     
     pub fn draw_text(&mut self, text: &str, x: usize, y: usize, color: Color) {
-        let mut dx = 0;
+        self.draw_glyphs(text, x, y, color, false);
+    }
+    
+    pub fn draw_text_italic(&mut self, text: &str, x: usize, y: usize, color: Color) {
+        self.draw_glyphs(text, x, y, color, true);
+    }
+
+    // Text `scale` times the font's size, each glyph pixel a square block.
+    // Emoji stay at their own size.
+    pub fn draw_text_scaled(&mut self, text: &str, x: usize, y: usize, color: Color, scale: usize) {
+        if scale <= 1 {
+            self.draw_text(text, x, y, color);
+            return;
+        }
+        let vga_color = self.rgb_to_vga(color);
+        for (column, ch) in text.chars().filter(|&ch| !icons::is_modifier(ch)).enumerate() {
+            let cell_x = x + column * GLYPH_WIDTH * scale;
+            if let Some(icon) = icons::emoji(ch) {
+                self.draw_icon(icon, cell_x, y);
+                continue;
+            }
+            let glyph = glyph_cache::glyph(ch, false);
+            for span in glyph.spans() {
+                for row in 0..scale {
+                    let span_y = y + span.y as usize * scale + row;
+                    self.draw_span(cell_x + span.x as usize * scale, span_y, span.len as usize * scale, vga_color);
+                }
+            }
+        }
+    }
+    
+    // Text drawn the same frame after frame, like menu and window titles:
+    // its spans are kept whole, so it draws as a few fills. Spans have one
+    // color, so labels with emoji are drawn as text.
+    pub fn draw_label(&mut self, text: &str, x: usize, y: usize, color: Color) {
+        if text.chars().any(|ch| icons::emoji(ch).is_some() || icons::is_modifier(ch)) {
+            self.draw_text(text, x, y, color);
+            return;
+        }
+        let vga_color = self.rgb_to_vga(color);
+        let cached = glyph_cache::with_label(text, false, |spans| {
+            for span in spans {
+                self.draw_span(x + span.x as usize, y + span.y as usize, span.len as usize, vga_color);
+            }
+        });
+        if !cached {
+            self.draw_text(text, x, y, color);
+        }
+    }
+    
+    fn draw_glyphs(&mut self, text: &str, x: usize, y: usize, color: Color, slanted: bool) {
+        let vga_color = self.rgb_to_vga(color);
+        let mut column = 0;
         for ch in text.chars() {
-            self.draw_char(ch, x + dx, y, color);
-            dx += 8;
+            if icons::is_modifier(ch) {
+                continue;
+            }
+            let cell_x = x + column * GLYPH_WIDTH;
+            column += 1;
+            if let Some(icon) = icons::emoji(ch) {
+                self.draw_icon(icon, cell_x, y);
+                continue;
+            }
+            let glyph = glyph_cache::glyph(ch, slanted);
+            for span in glyph.spans() {
+                self.draw_span(cell_x + span.x as usize, y + span.y as usize, span.len as usize, vga_color);
+            }
         }
     }
     
-    fn draw_char(&mut self, ch: char, x: usize, y: usize, color: Color) {
-        let font_data = self.get_font_data(ch);
-        for (row, &byte) in font_data.iter().enumerate() {
-            for col in 0..8 {
-                if (byte >> (7 - col)) & 1 == 1 {
+    pub fn draw_icon(&mut self, icon: &Icon, x: usize, y: usize) {
+        for row in 0..ICON_SIZE {
+            for col in 0..ICON_SIZE {
+                if let Some(color) = icon.pixel(col, row) {
                     self.set_pixel(x + col, y + row, color);
                 }
             }
         }
     }
     
-    fn get_font_data(&self, ch: char) -> &[u8] {
-        match ch {
-            'A' => &[0x18, 0x3C, 0x66, 0x66, 0x7E, 0x66, 0x66, 0x00],
-            'B' => &[0x7C, 0x66, 0x66, 0x7C, 0x66, 0x66, 0x7C, 0x00],
-            'C' => &[0x3C, 0x66, 0x60, 0x60, 0x60, 0x66, 0x3C, 0x00],
-            'D' => &[0x78, 0x6C, 0x66, 0x66, 0x66, 0x6C, 0x78, 0x00],
-            'E' => &[0x7E, 0x60, 0x60, 0x78, 0x60, 0x60, 0x7E, 0x00],
-            'F' => &[0x7E, 0x60, 0x60, 0x78, 0x60, 0x60, 0x60, 0x00],
-            'G' => &[0x3C, 0x66, 0x60, 0x6E, 0x66, 0x66, 0x3C, 0x00],
-            'H' => &[0x66, 0x66, 0x66, 0x7E, 0x66, 0x66, 0x66, 0x00],
-            'I' => &[0x3C, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, 0x00],
-            'O' => &[0x3C, 0x66, 0x66, 0x66, 0x66, 0x66, 0x3C, 0x00],
-            'R' => &[0x7C, 0x66, 0x66, 0x7C, 0x78, 0x6C, 0x66, 0x00],
-            'S' => &[0x3C, 0x66, 0x60, 0x3C, 0x06, 0x66, 0x3C, 0x00],
-            'T' => &[0x7E, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x00],
-            'U' => &[0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x3C, 0x00],
-            ' ' => &[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
-            _ => &[0xFF, 0x81, 0x81, 0x81, 0x81, 0x81, 0xFF, 0x00],
+    // A run of one row, clipped to the screen
+    fn draw_span(&mut self, x: usize, y: usize, len: usize, vga_color: u8) {
+        if x >= SCREEN_WIDTH || y >= SCREEN_HEIGHT {
+            return;
         }
+        let len = len.min(SCREEN_WIDTH - x);
+        self.framebuffer.fill(y * SCREEN_WIDTH + x, len, vga_color);
     }
     
     fn rgb_to_vga(&self, color: Color) -> u8 {
@@ -155,4 +363,84 @@ impl Graphics {
             _ => 7,
         }
     }
+}
+
+// So the SDK's widgets draw on the screen; the screen's own drawing keeps
+// its cached glyphs and emoji
+impl Canvas for Graphics {
+    fn set_pixel(&mut self, x: usize, y: usize, color: Color) {
+        Graphics::set_pixel(self, x, y, color);
+    }
+    
+    fn draw_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: Color) {
+        Graphics::draw_rect(self, x, y, width, height, color);
+    }
+    
+    fn draw_rect_outline(&mut self, x: usize, y: usize, width: usize, height: usize, color: Color) {
+        Graphics::draw_rect_outline(self, x, y, width, height, color);
+    }
+    
+    fn draw_rounded_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: Color) {
+        Graphics::draw_rounded_rect(self, x, y, width, height, color);
+    }
+    
+    fn draw_text(&mut self, text: &str, x: usize, y: usize, color: Color) {
+        Graphics::draw_text(self, text, x, y, color);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisplayMode {
+    // Pixels in graphics modes, character cells in text modes
+    pub width: usize,
+    pub height: usize,
+    pub colors: usize,
+    pub graphics: bool,
+}
+
+// Reads the mode the VGA is actually programmed with back out of the CRTC
+// and attribute controller registers
+#[cfg(target_os = "none")]
+pub fn detect_mode() -> DisplayMode {
+    use x86_64::instructions::port::Port;
+    
+    let crtc = |index: u8| -> u8 {
+        let mut select: Port<u8> = Port::new(0x3D4);
+        let mut data: Port<u8> = Port::new(0x3D5);
+        unsafe {
+            select.write(index);
+            data.read()
+        }
+    };
+    let mode_control = unsafe {
+        // Reading input status 1 resets the attribute flip-flop to the
+        // index; bit 5 keeps the palette enabled while we look
+        Port::<u8>::new(0x3DA).read();
+        Port::<u8>::new(0x3C0).write(0x10 | 0x20);
+        Port::<u8>::new(0x3C1).read()
+    };
+    
+    let graphics = mode_control & 0x01 != 0;
+    let eight_bit = mode_control & 0x40 != 0;
+    
+    // Horizontal display end counts character clocks of 8 dots; 256-colour
+    // modes take two dots per pixel
+    let char_width = if graphics { 8 } else { 1 };
+    let mut width = (crtc(0x01) as usize + 1) * char_width;
+    if eight_bit {
+        width /= 2;
+    }
+    
+    // Vertical display end is 10 bits, the top two in the overflow register
+    let overflow = crtc(0x07) as usize;
+    let display_end = crtc(0x12) as usize | (overflow & 0x02) << 7 | (overflow & 0x40) << 3;
+    let max_scan = crtc(0x09) as usize;
+    let lines_per_row = (max_scan & 0x1F) + 1;
+    let mut height = (display_end + 1) / lines_per_row;
+    if max_scan & 0x80 != 0 {
+        height /= 2;
+    }
+    
+    let colors = if eight_bit { 256 } else { 16 };
+    DisplayMode { width, height, colors, graphics }
 }
\ No newline at end of file
@@ -0,0 +1,272 @@
+// src/heap_profiler.rs
+// Kernel heap allocation tracking, compiled in with the `heap-profiler`
+// feature. Each allocation is charged to the subsystem tag active when it
+// was made (see `tagged`) and to a call site read off the frame-pointer
+// chain. The bookkeeping lives in fixed-size tables so the profiler never
+// allocates itself; without the feature every call here is a no-op.
+use alloc::vec::Vec;
+
+pub const UNTAGGED: &str = "untagged";
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TagStats {
+    pub allocs: u64,
+    pub frees: u64,
+    pub live_bytes: usize,
+    pub peak_bytes: usize,
+    pub total_bytes: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SiteStats {
+    // Return address; resolve with addr2line against the kernel binary
+    pub addr: usize,
+    pub tag: &'static str,
+    pub allocs: u64,
+    pub live_bytes: usize,
+}
+
+pub fn enabled() -> bool {
+    cfg!(feature = "heap-profiler")
+}
+
+// Runs `f` with allocations charged to `tag`. Tags nest; the innermost wins.
+pub fn tagged<R>(tag: &'static str, f: impl FnOnce() -> R) -> R {
+    #[cfg(feature = "heap-profiler")]
+    let previous = imp::enter(tag);
+    #[cfg(not(feature = "heap-profiler"))]
+    let _ = tag;
+    
+    let result = f();
+    
+    #[cfg(feature = "heap-profiler")]
+    imp::leave(previous);
+    result
+}
+
+pub fn tag_stats() -> Vec<(&'static str, TagStats)> {
+    #[cfg(feature = "heap-profiler")]
+    {
+        // Copy out under the lock, then build the Vec without holding it
+        let (tags, count) = imp::snapshot_tags();
+        tags[..count].to_vec()
+    }
+    #[cfg(not(feature = "heap-profiler"))]
+    Vec::new()
+}
+
+// Call sites with the most live bytes, largest first
+pub fn top_sites(count: usize) -> Vec<SiteStats> {
+    #[cfg(feature = "heap-profiler")]
+    {
+        let (sites, used) = imp::snapshot_sites();
+        let mut sites = sites[..used].to_vec();
+        sites.sort_by(|a, b| b.live_bytes.cmp(&a.live_bytes));
+        sites.truncate(count);
+        sites
+    }
+    #[cfg(not(feature = "heap-profiler"))]
+    {
+        let _ = count;
+        Vec::new()
+    }
+}
+
+// Allocations that could not be tracked because the live table was full
+pub fn untracked() -> u64 {
+    #[cfg(feature = "heap-profiler")]
+    {
+        imp::untracked()
+    }
+    #[cfg(not(feature = "heap-profiler"))]
+    0
+}
+
+#[cfg(feature = "heap-profiler")]
+pub fn record_alloc(ptr: *mut u8, size: usize) {
+    imp::record_alloc(ptr as usize, size);
+}
+
+#[cfg(feature = "heap-profiler")]
+pub fn record_free(ptr: *mut u8) {
+    imp::record_free(ptr as usize);
+}
+
+#[cfg(feature = "heap-profiler")]
+mod imp {
+    use super::{SiteStats, TagStats, UNTAGGED};
+    use crate::backtrace::Frames;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use crate::sync::Mutex;
+    
+    pub const MAX_TAGS: usize = 16;
+    pub const MAX_SITES: usize = 64;
+    const MAX_LIVE: usize = 4096;
+    // Frames to walk up from the allocator, enough to get past the
+    // Vec/String/Box layers of liballoc in the common case
+    const SITE_DEPTH: usize = 5;
+    
+    #[derive(Clone, Copy)]
+    struct Live {
+        ptr: usize,
+        size: usize,
+        tag: u8,
+        site: u8,
+    }
+    
+    const EMPTY: Live = Live { ptr: 0, size: 0, tag: 0, site: 0 };
+    const NO_SITE: SiteStats = SiteStats { addr: 0, tag: UNTAGGED, allocs: 0, live_bytes: 0 };
+    
+    struct Profiler {
+        tags: [(&'static str, TagStats); MAX_TAGS],
+        tag_count: usize,
+        sites: [SiteStats; MAX_SITES],
+        site_count: usize,
+        live: [Live; MAX_LIVE],
+        untracked: u64,
+    }
+    
+    static PROFILER: Mutex<Profiler> = Mutex::new(Profiler {
+        tags: [(UNTAGGED, TagStats { allocs: 0, frees: 0, live_bytes: 0, peak_bytes: 0, total_bytes: 0 }); MAX_TAGS],
+        tag_count: 1,
+        sites: [NO_SITE; MAX_SITES],
+        site_count: 0,
+        live: [EMPTY; MAX_LIVE],
+        untracked: 0,
+    });
+    static CURRENT_TAG: AtomicUsize = AtomicUsize::new(0);
+    
+    pub fn enter(tag: &'static str) -> usize {
+        let mut profiler = PROFILER.lock();
+        let count = profiler.tag_count;
+        let index = match profiler.tags[..count].iter().position(|(name, _)| *name == tag) {
+            Some(index) => index,
+            // Out of slots: charge the rest to "untagged"
+            None if count == MAX_TAGS => 0,
+            None => {
+                profiler.tags[count] = (tag, TagStats::default());
+                profiler.tag_count += 1;
+                count
+            }
+        };
+        CURRENT_TAG.swap(index, Ordering::Relaxed)
+    }
+    
+    pub fn leave(previous: usize) {
+        CURRENT_TAG.store(previous, Ordering::Relaxed);
+    }
+    
+    fn call_site() -> usize {
+        Frames::here().take(SITE_DEPTH).last().unwrap_or(0)
+    }
+    
+    fn slot(ptr: usize) -> usize {
+        (ptr >> 4).wrapping_mul(0x9E37_79B9) % MAX_LIVE
+    }
+    
+    impl Profiler {
+        fn site_index(&mut self, addr: usize, tag: usize) -> usize {
+            if let Some(index) = self.sites[..self.site_count].iter().position(|s| s.addr == addr) {
+                return index;
+            }
+            // The last slot collects everything once the table fills up
+            if self.site_count == MAX_SITES - 1 {
+                return MAX_SITES - 1;
+            }
+            let index = self.site_count;
+            self.sites[index] = SiteStats { addr, tag: self.tags[tag].0, allocs: 0, live_bytes: 0 };
+            self.site_count += 1;
+            index
+        }
+        
+        fn insert(&mut self, entry: Live) -> bool {
+            let mut i = slot(entry.ptr);
+            for _ in 0..MAX_LIVE {
+                if self.live[i].ptr == 0 {
+                    self.live[i] = entry;
+                    return true;
+                }
+                i = (i + 1) % MAX_LIVE;
+            }
+            false
+        }
+        
+        // Linear probing with backward-shift deletion, so lookups can stop
+        // at the first empty slot
+        fn remove(&mut self, ptr: usize) -> Option<Live> {
+            let mut i = slot(ptr);
+            let mut probes = 0;
+            while self.live[i].ptr != ptr {
+                probes += 1;
+                if self.live[i].ptr == 0 || probes == MAX_LIVE {
+                    return None;
+                }
+                i = (i + 1) % MAX_LIVE;
+            }
+            
+            let removed = self.live[i];
+            self.live[i] = EMPTY;
+            let mut j = i;
+            loop {
+                j = (j + 1) % MAX_LIVE;
+                if self.live[j].ptr == 0 {
+                    break;
+                }
+                let home = slot(self.live[j].ptr);
+                let stays = if i <= j { i < home && home <= j } else { i < home || home <= j };
+                if !stays {
+                    self.live[i] = self.live[j];
+                    self.live[j] = EMPTY;
+                    i = j;
+                }
+            }
+            Some(removed)
+        }
+    }
+    
+    pub fn record_alloc(ptr: usize, size: usize) {
+        let tag = CURRENT_TAG.load(Ordering::Relaxed);
+        let addr = call_site();
+        let mut profiler = PROFILER.lock();
+        
+        let stats = &mut profiler.tags[tag].1;
+        stats.allocs += 1;
+        stats.total_bytes += size as u64;
+        stats.live_bytes += size;
+        stats.peak_bytes = stats.peak_bytes.max(stats.live_bytes);
+        
+        let site = profiler.site_index(addr, tag);
+        profiler.sites[site].allocs += 1;
+        profiler.sites[site].live_bytes += size;
+        
+        let entry = Live { ptr, size, tag: tag as u8, site: site as u8 };
+        if !profiler.insert(entry) {
+            profiler.untracked += 1;
+        }
+    }
+    
+    pub fn record_free(ptr: usize) {
+        let mut profiler = PROFILER.lock();
+        if let Some(entry) = profiler.remove(ptr) {
+            let stats = &mut profiler.tags[entry.tag as usize].1;
+            stats.frees += 1;
+            stats.live_bytes -= entry.size;
+            profiler.sites[entry.site as usize].live_bytes -= entry.size;
+        }
+    }
+    
+    pub fn snapshot_tags() -> ([(&'static str, TagStats); MAX_TAGS], usize) {
+        let profiler = PROFILER.lock();
+        (profiler.tags, profiler.tag_count)
+    }
+    
+    pub fn snapshot_sites() -> ([SiteStats; MAX_SITES], usize) {
+        let profiler = PROFILER.lock();
+        let used = if profiler.sites[MAX_SITES - 1].allocs > 0 { MAX_SITES } else { profiler.site_count };
+        (profiler.sites, used)
+    }
+    
+    pub fn untracked() -> u64 {
+        PROFILER.lock().untracked
+    }
+}
\ No newline at end of file
@@ -0,0 +1,448 @@
+// src/hibernate.rs
+// Experimental hibernation, on with the hibernate boot flag. Shutting down
+// writes the session to a sleep image on the first writable FAT32 volume,
+// made and written like the swap file, on the disk itself: every window's
+// app, title, frame and state, and what it showed on screen. The next boot
+// reopens the windows where they were and paints what they showed while
+// the apps start, then marks the image used.
+//
+// Apps outside the kernel are saved whole: their registers and every page
+// of their memory, heap and stack included, and their surfaces. They come
+// back as new processes that carry on where they were. Those with pipes or
+// files open are left out, as nothing could give those back. The kernel's
+// own heap, page tables and drivers aren't saved: they belong to the kernel
+// that boots next, so the apps built into it come back in their launch
+// state behind their old frames.
+use crate::apps;
+use crate::block::{self, SharedBlockDevice, SECTOR_SIZE};
+use crate::bootargs;
+use crate::display_server::{self, Rect, Request};
+use crate::fat32::Fat32;
+use crate::graphics::{Graphics, Surface, VGA_PALETTE};
+use crate::partition;
+use crate::task::{self, Pid};
+use crate::userspace::{self, Hibernated, REGISTERS};
+use crate::vfs::{self, VfsError};
+use crate::window_manager::{WindowManager, WindowState};
+use crate::{kinfo, kwarn, memory};
+use alloc::string::String;
+use alloc::vec;
+use core::str;
+use crate::sync::Mutex;
+use x86_64::structures::paging::{Page, PageTableFlags};
+use x86_64::VirtAddr;
+
+const IMAGE_FILE: &str = "SLEEPIMG";
+const PAGE_SIZE: usize = 4096;
+// 8 MiB, a dozen screens of windows and a few apps' memory
+const PAGES: usize = 2048;
+const SECTORS_PER_PAGE: u64 = (PAGE_SIZE / SECTOR_SIZE) as u64;
+const MAGIC: &[u8; 4] = b"RHIB";
+const VERSION: u32 = 2;
+// The first page holds the header and a record per window, back to front;
+// the second a record per app outside the kernel. The windows' pixels
+// follow, as palette indices row by row, then the apps' memory.
+const RECORDS_AT: usize = 64;
+const RECORD_SIZE: usize = 128;
+const MAX_WINDOWS: usize = (PAGE_SIZE - RECORDS_AT) / RECORD_SIZE;
+const APP_LEN: usize = 32;
+const TITLE_LEN: usize = 64;
+const NO_FOCUS: u32 = u32::MAX;
+const APPS_PAGE: usize = 1;
+const APP_RECORD_SIZE: usize = 1024;
+const MAX_APPS: usize = PAGE_SIZE / APP_RECORD_SIZE;
+const REGISTERS_AT: usize = APP_LEN;
+const SURFACES_AT: usize = 256;
+const SURFACE_SIZE: usize = 64;
+const MAX_APP_SURFACES: usize = (APP_RECORD_SIZE - SURFACES_AT) / SURFACE_SIZE;
+// An app's memory is a directory of where each page goes and its flags,
+// then the pages
+const ENTRY_SIZE: usize = 16;
+const ENTRIES_PER_PAGE: usize = PAGE_SIZE / ENTRY_SIZE;
+
+const STATES: [WindowState; 6] = [
+    WindowState::Normal,
+    WindowState::Minimized,
+    WindowState::Maximized,
+    WindowState::Fullscreen,
+    WindowState::TiledLeft,
+    WindowState::TiledRight,
+];
+
+// Screen-sized, far more than the heap holds
+static SURFACE: Mutex<Surface> = Mutex::new(Surface::new());
+
+pub fn enabled() -> bool {
+    bootargs::flag("hibernate")
+}
+
+struct Image {
+    // The whole disk, uncached
+    device: SharedBlockDevice,
+    first_sector: u64,
+}
+
+impl Image {
+    // Makes or finds the image
+    fn find() -> Result<Image, VfsError> {
+        for volume in partition::volumes() {
+            if vfs::probe(&volume.device) != Some("fat32") || volume.device.lock().is_read_only() {
+                continue;
+            }
+            let disk = match block::get(volume.disk) {
+                Some(disk) => disk,
+                None => continue,
+            };
+            let offset = Fat32::mount(volume.device.clone()).and_then(|mut fs| fs.preallocate(IMAGE_FILE, (PAGES * PAGE_SIZE) as u64))?;
+            let start = volume.partition.as_ref().map_or(0, |partition| partition.start);
+            return Ok(Image { device: disk, first_sector: start + offset / SECTOR_SIZE as u64 });
+        }
+        Err(VfsError::NotFound)
+    }
+    
+    fn write_page(&self, page: usize, buf: &[u8; PAGE_SIZE]) -> Result<(), VfsError> {
+        self.device.lock().write_sectors(self.first_sector + page as u64 * SECTORS_PER_PAGE, buf)?;
+        Ok(())
+    }
+    
+    fn read_page(&self, page: usize, buf: &mut [u8; PAGE_SIZE]) -> Result<(), VfsError> {
+        self.device.lock().read_sectors(self.first_sector + page as u64 * SECTORS_PER_PAGE, buf)?;
+        Ok(())
+    }
+    
+    fn flush(&self) -> Result<(), VfsError> {
+        self.device.lock().flush()?;
+        Ok(())
+    }
+}
+
+fn put_u16(buf: &mut [u8], at: usize, value: usize) {
+    buf[at..at + 2].copy_from_slice(&(value.min(u16::MAX as usize) as u16).to_le_bytes());
+}
+
+fn put_u32(buf: &mut [u8], at: usize, value: u32) {
+    buf[at..at + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+fn put_u64(buf: &mut [u8], at: usize, value: u64) {
+    buf[at..at + 8].copy_from_slice(&value.to_le_bytes());
+}
+
+fn u16_at(buf: &[u8], at: usize) -> usize {
+    u16::from_le_bytes([buf[at], buf[at + 1]]) as usize
+}
+
+fn u32_at(buf: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([buf[at], buf[at + 1], buf[at + 2], buf[at + 3]])
+}
+
+fn u64_at(buf: &[u8], at: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&buf[at..at + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+// Zero-padded, cut at a character boundary to fit
+fn put_str(buf: &mut [u8], text: &str) {
+    let mut len = text.len().min(buf.len());
+    while !text.is_char_boundary(len) {
+        len -= 1;
+    }
+    buf[..len].copy_from_slice(&text.as_bytes()[..len]);
+}
+
+fn str_at(buf: &[u8]) -> &str {
+    let len = buf.iter().position(|&byte| byte == 0).unwrap_or(buf.len());
+    str::from_utf8(&buf[..len]).unwrap_or("")
+}
+
+// Writes the session to the image; how many windows went in. `graphics`
+// is the screen as last drawn, where the windows' pixels come from.
+// Windows that didn't come from the app registry can't be reopened and
+// are left out.
+pub fn save(window_manager: &WindowManager, graphics: &Graphics) -> Result<usize, VfsError> {
+    let image = Image::find()?;
+    let mut header = [0u8; PAGE_SIZE];
+    header[..4].copy_from_slice(MAGIC);
+    put_u32(&mut header, 4, VERSION);
+    
+    let mut surface = SURFACE.lock();
+    let mut page = APPS_PAGE + 1;
+    let mut saved = 0;
+    let mut focused = NO_FOCUS;
+    for (i, window) in window_manager.windows().iter().enumerate() {
+        let app = match window.app {
+            Some(app) if saved < MAX_WINDOWS => apps::get(app).name,
+            _ => continue,
+        };
+        let record = &mut header[RECORDS_AT + saved * RECORD_SIZE..][..RECORD_SIZE];
+        put_str(&mut record[..APP_LEN], app);
+        put_str(&mut record[APP_LEN..APP_LEN + TITLE_LEN], &window.title);
+        let (x, y, width, height) = window.normal_frame();
+        for (at, value) in [(96, x), (98, y), (100, width), (102, height)] {
+            put_u16(record, at, value);
+        }
+        record[104] = STATES.iter().position(|&state| state == window.state).unwrap_or(0) as u8;
+        record[105] = window.is_hidden as u8;
+        
+        // What it shows on screen, if it fits in what's left
+        if !window.is_minimized() && !window.is_hidden {
+            graphics.capture(&mut surface, window.x, window.y, window.width, window.height);
+            let pages = surface.pixels().len().div_ceil(PAGE_SIZE);
+            if page + pages <= PAGES {
+                let mut buf = [0u8; PAGE_SIZE];
+                for (offset, chunk) in surface.pixels().chunks(PAGE_SIZE).enumerate() {
+                    buf[..chunk.len()].copy_from_slice(chunk);
+                    image.write_page(page + offset, &buf)?;
+                }
+                put_u32(record, 108, page as u32);
+                for (at, value) in [(112, window.x), (114, window.y), (116, surface.width()), (118, surface.height())] {
+                    put_u16(record, at, value);
+                }
+                page += pages;
+            }
+        }
+        if window_manager.focused_index() == Some(i) {
+            focused = saved as u32;
+        }
+        saved += 1;
+    }
+    drop(surface);
+    put_u32(&mut header, 8, saved as u32);
+    put_u32(&mut header, 12, focused);
+    let apps = save_apps(&image, window_manager, page)?;
+    put_u32(&mut header, 16, apps as u32);
+    // The header goes last, so a shutdown cut short leaves no image
+    image.write_page(0, &header)?;
+    image.flush()?;
+    Ok(saved)
+}
+
+// Writes a record per app outside the kernel to the apps page and their
+// memory from `page` on; how many apps went in
+fn save_apps(image: &Image, window_manager: &WindowManager, mut page: usize) -> Result<usize, VfsError> {
+    let mut records = [0u8; PAGE_SIZE];
+    let mut saved = 0;
+    for app in userspace::hibernated() {
+        let surfaces = display_server::surfaces(app.pid);
+        if saved == MAX_APPS || surfaces.len() > MAX_APP_SURFACES {
+            kwarn!("hibernate: pid {} left out", app.pid);
+            continue;
+        }
+        let (pages, count) = match save_memory(image, app.pid, page)? {
+            Some(written) => written,
+            None => {
+                kwarn!("hibernate: no room for the memory of pid {}", app.pid);
+                continue;
+            }
+        };
+        let record = &mut records[saved * APP_RECORD_SIZE..][..APP_RECORD_SIZE];
+        let name = task::list().into_iter().find(|process| process.pid == app.pid).map_or(String::new(), |process| process.name);
+        put_str(&mut record[..APP_LEN], &name);
+        for (i, &register) in app.registers.iter().enumerate() {
+            put_u64(record, REGISTERS_AT + i * 8, register);
+        }
+        put_u64(record, 192, app.mapped);
+        record[200] = app.waiting as u8;
+        record[201] = surfaces.len() as u8;
+        put_u32(record, 204, page as u32);
+        put_u32(record, 208, count as u32);
+        for (i, &(id, width, height)) in surfaces.iter().enumerate() {
+            let entry = &mut record[SURFACES_AT + i * SURFACE_SIZE..][..SURFACE_SIZE];
+            put_u32(entry, 0, id);
+            put_u16(entry, 4, width);
+            put_u16(entry, 6, height);
+            let title = window_manager.windows().iter().find(|window| window.surface == Some(id)).map_or("", |window| window.title.as_str());
+            put_str(&mut entry[8..], title);
+        }
+        page += pages;
+        saved += 1;
+    }
+    image.write_page(APPS_PAGE, &records)?;
+    Ok(saved)
+}
+
+// Writes the memory of `pid` from `first` on: the pages used, and how many
+// of them hold the memory; None if it doesn't fit in what's left
+fn save_memory(image: &Image, pid: Pid, first: usize) -> Result<Option<(usize, usize)>, VfsError> {
+    let count = task::with_address_space(pid, |space| space.page_counts().0).unwrap_or(0);
+    let directory = count.div_ceil(ENTRIES_PER_PAGE);
+    if first + directory + count > PAGES {
+        return Ok(None);
+    }
+    let mut entries = [0u8; PAGE_SIZE];
+    let mut written = 0;
+    let mut result = Ok(());
+    task::with_address_space(pid, |space| space.user_pages(|page, frame, flags| {
+        if result.is_err() || written == count {
+            return;
+        }
+        let at = written % ENTRIES_PER_PAGE * ENTRY_SIZE;
+        put_u64(&mut entries, at, page.start_address().as_u64());
+        put_u64(&mut entries, at + 8, flags.bits());
+        // Through the kernel's view of the frame; the app's tables aren't live
+        let contents = unsafe { &*memory::phys_to_virt(frame.start_address()).as_ptr::<[u8; PAGE_SIZE]>() };
+        result = image.write_page(first + directory + written, contents);
+        written += 1;
+        if result.is_ok() && (written % ENTRIES_PER_PAGE == 0 || written == count) {
+            result = image.write_page(first + (written - 1) / ENTRIES_PER_PAGE, &entries);
+            entries.fill(0);
+        }
+    }));
+    result?;
+    Ok(Some((directory + written, written)))
+}
+
+// Puts an app back in a new process: its memory, its surfaces under their
+// old ids, then its registers
+fn resume_app(image: &Image, record: &[u8]) -> Result<Pid, VfsError> {
+    let pid = task::spawn(str_at(&record[..APP_LEN])).map_err(|_| VfsError::NoSpace)?;
+    let surfaces = (record[201] as usize).min(MAX_APP_SURFACES);
+    for i in 0..surfaces {
+        let entry = &record[SURFACES_AT + i * SURFACE_SIZE..][..SURFACE_SIZE];
+        let (id, width, height) = (u32_at(entry, 0), u16_at(entry, 4), u16_at(entry, 6));
+        if !userspace::restore_surface(pid, id, str_at(&entry[8..]).into(), width, height) {
+            kwarn!("hibernate: cannot give pid {} its surface {}", pid, id);
+        }
+    }
+    if let Err(err) = restore_memory(image, pid, u32_at(record, 204) as usize, u32_at(record, 208) as usize) {
+        // The display server drops its surfaces once it's gone
+        let _ = task::exit(pid, -1);
+        task::wait(pid);
+        return Err(err);
+    }
+    // The surfaces' buffers came back with the memory
+    for i in 0..surfaces {
+        let entry = &record[SURFACES_AT + i * SURFACE_SIZE..][..SURFACE_SIZE];
+        let damage = vec![Rect { x: 0, y: 0, width: u16_at(entry, 4), height: u16_at(entry, 6) }];
+        let _ = display_server::request(pid, Request::Commit { surface: u32_at(entry, 0), damage });
+    }
+    
+    let mut registers = [0u64; REGISTERS];
+    for (i, register) in registers.iter_mut().enumerate() {
+        *register = u64_at(record, REGISTERS_AT + i * 8);
+    }
+    let app = Hibernated { pid, registers, mapped: u64_at(record, 192), waiting: record[200] != 0 };
+    if !userspace::resume(pid, &app) {
+        let _ = task::exit(pid, -1);
+        task::wait(pid);
+        return Err(VfsError::Corrupt);
+    }
+    Ok(pid)
+}
+
+fn restore_memory(image: &Image, pid: Pid, first: usize, count: usize) -> Result<(), VfsError> {
+    let directory = count.div_ceil(ENTRIES_PER_PAGE);
+    if first + directory + count > PAGES {
+        return Err(VfsError::Corrupt);
+    }
+    let mut entries = [0u8; PAGE_SIZE];
+    let mut contents = [0u8; PAGE_SIZE];
+    for i in 0..count {
+        if i % ENTRIES_PER_PAGE == 0 {
+            image.read_page(first + i / ENTRIES_PER_PAGE, &mut entries)?;
+        }
+        image.read_page(first + directory + i, &mut contents)?;
+        let at = i % ENTRIES_PER_PAGE * ENTRY_SIZE;
+        let addr = VirtAddr::try_new(u64_at(&entries, at)).map_err(|_| VfsError::Corrupt)?;
+        let flags = PageTableFlags::from_bits_truncate(u64_at(&entries, at + 8));
+        match task::with_address_space(pid, |space| space.restore_page(Page::containing_address(addr), flags, &contents)) {
+            Some(Ok(())) => {}
+            _ => return Err(VfsError::NoSpace),
+        }
+    }
+    Ok(())
+}
+
+// Paints a window's saved pixels where it was
+fn paint(image: &Image, graphics: &mut Graphics, record: &[u8]) -> Result<(), VfsError> {
+    let page = u32_at(record, 108) as usize;
+    let (x, y, width, height) = (u16_at(record, 112), u16_at(record, 114), u16_at(record, 116), u16_at(record, 118));
+    if page == 0 || width == 0 {
+        return Ok(());
+    }
+    let pages = (width * height).div_ceil(PAGE_SIZE);
+    if page + pages > PAGES {
+        return Err(VfsError::Corrupt);
+    }
+    let mut buf = [0u8; PAGE_SIZE];
+    for offset in 0..pages {
+        image.read_page(page + offset, &mut buf)?;
+        for (i, &index) in buf.iter().enumerate().take(width * height - offset * PAGE_SIZE) {
+            let pixel = offset * PAGE_SIZE + i;
+            graphics.set_pixel(x + pixel % width, y + pixel / width, VGA_PALETTE[(index & 0x0F) as usize]);
+        }
+    }
+    Ok(())
+}
+
+// With the hibernate flag and an unused image, reopens the session it
+// holds; false when the session should start as usual
+pub fn resume(window_manager: &mut WindowManager, graphics: &mut Graphics) -> bool {
+    if !enabled() {
+        return false;
+    }
+    let image = match Image::find() {
+        Ok(image) => image,
+        Err(err) => {
+            kwarn!("hibernate: no sleep image: {:?}", err);
+            return false;
+        }
+    };
+    let mut header = [0u8; PAGE_SIZE];
+    if image.read_page(0, &mut header).is_err() || &header[..4] != MAGIC || u32_at(&header, 4) != VERSION {
+        return false;
+    }
+    
+    let count = (u32_at(&header, 8) as usize).min(MAX_WINDOWS);
+    let focused = u32_at(&header, 12) as usize;
+    let mut focus = None;
+    for i in 0..count {
+        let record = &header[RECORDS_AT + i * RECORD_SIZE..][..RECORD_SIZE];
+        let app = match apps::find(str_at(&record[..APP_LEN])) {
+            Some(app) => app,
+            None => {
+                kwarn!("hibernate: no app named {}", str_at(&record[..APP_LEN]));
+                continue;
+            }
+        };
+        let mut window = app.window();
+        window.title = str_at(&record[APP_LEN..APP_LEN + TITLE_LEN]).into();
+        (window.x, window.y, window.width, window.height) = (u16_at(record, 96), u16_at(record, 98), u16_at(record, 100), u16_at(record, 102));
+        window.set_state(STATES.get(record[104] as usize).copied().unwrap_or(WindowState::Normal));
+        window.animation = None;
+        window.is_hidden = record[105] != 0;
+        window_manager.add_window(window);
+        if i == focused {
+            focus = Some(window_manager.windows().len() - 1);
+        }
+        if let Err(err) = paint(&image, graphics, record) {
+            kwarn!("hibernate: cannot read a window's pixels: {:?}", err);
+        }
+    }
+    if let Some(index) = focus {
+        window_manager.focus_window(index);
+    }
+    
+    let mut records = [0u8; PAGE_SIZE];
+    let apps = (u32_at(&header, 16) as usize).min(MAX_APPS);
+    if apps > 0 {
+        if let Err(err) = image.read_page(APPS_PAGE, &mut records) {
+            kwarn!("hibernate: cannot read the apps: {:?}", err);
+        } else {
+            for i in 0..apps {
+                if let Err(err) = resume_app(&image, &records[i * APP_RECORD_SIZE..][..APP_RECORD_SIZE]) {
+                    kwarn!("hibernate: cannot resume an app: {:?}", err);
+                }
+            }
+        }
+    }
+    
+    // Used once; the next boot starts afresh unless this session hibernates
+    header[..4].fill(0);
+    if let Err(err) = image.write_page(0, &header).and_then(|_| image.flush()) {
+        kwarn!("hibernate: cannot mark the sleep image used: {:?}", err);
+    }
+    kinfo!("hibernate: resumed {} windows and {} apps", count, apps);
+    true
+}
\ No newline at end of file
@@ -0,0 +1,93 @@
+// src/hpet.rs
+// The HPET, found through ACPI: a counter running at 10 MHz or more and
+// comparators that can stand in for the PIT. With legacy replacement
+// routing timer 0 raises IRQ 0 where the PIT did, so the timer interrupt
+// keeps its handler and vector. Only 64-bit counters are used; a 32-bit
+// one would wrap every few minutes.
+use crate::{acpi, memory};
+use core::ptr;
+use core::sync::atomic::{AtomicU64, Ordering};
+use x86_64::PhysAddr;
+
+// Registers
+const CAPABILITIES: u64 = 0x000;
+const CONFIG: u64 = 0x010;
+const MAIN_COUNTER: u64 = 0x0F0;
+const TIMER0_CONFIG: u64 = 0x100;
+const TIMER0_COMPARATOR: u64 = 0x108;
+
+const COUNTER_64_BIT: u64 = 1 << 13;
+const LEGACY_ROUTE_CAPABLE: u64 = 1 << 15;
+const ENABLE: u64 = 1 << 0;
+const LEGACY_ROUTE: u64 = 1 << 1;
+const TIMER_INTERRUPT: u64 = 1 << 2;
+const TIMER_PERIODIC: u64 = 1 << 3;
+const TIMER_PERIODIC_CAPABLE: u64 = 1 << 4;
+// The next comparator write sets the period rather than the first deadline
+const TIMER_SET_PERIOD: u64 = 1 << 6;
+const TIMER_32_BIT: u64 = 1 << 8;
+
+const FEMTOSECONDS: u64 = 1_000_000_000_000_000;
+// The slowest tick the specification allows, 100 ns
+const MAX_PERIOD: u64 = 100_000_000;
+
+// The registers' virtual address, 0 until `init` finds a usable HPET
+static BASE: AtomicU64 = AtomicU64::new(0);
+static HZ: AtomicU64 = AtomicU64::new(0);
+
+fn read(register: u64) -> u64 {
+    unsafe { ptr::read_volatile((BASE.load(Ordering::Relaxed) + register) as *const u64) }
+}
+
+fn write(register: u64, value: u64) {
+    unsafe { ptr::write_volatile((BASE.load(Ordering::Relaxed) + register) as *mut u64, value) }
+}
+
+// Starts the counter from zero; false without a usable HPET
+pub fn init() -> bool {
+    let base = match acpi::hpet().and_then(|addr| memory::map_mmio(PhysAddr::new(addr))) {
+        Some(base) => base,
+        None => return false,
+    };
+    BASE.store(base.as_u64(), Ordering::Relaxed);
+    let capabilities = read(CAPABILITIES);
+    let period = capabilities >> 32;
+    if period == 0 || period > MAX_PERIOD || capabilities & COUNTER_64_BIT == 0 {
+        BASE.store(0, Ordering::Relaxed);
+        return false;
+    }
+    write(CONFIG, read(CONFIG) & !(ENABLE | LEGACY_ROUTE));
+    write(MAIN_COUNTER, 0);
+    write(CONFIG, read(CONFIG) | ENABLE);
+    HZ.store(FEMTOSECONDS / period, Ordering::Relaxed);
+    true
+}
+
+pub fn is_running() -> bool {
+    HZ.load(Ordering::Relaxed) != 0
+}
+
+pub fn hz() -> u64 {
+    HZ.load(Ordering::Relaxed)
+}
+
+// Counts since `init`
+pub fn counter() -> u64 {
+    if is_running() { read(MAIN_COUNTER) } else { 0 }
+}
+
+// Has timer 0 raise IRQ 0 `hz` times a second in the PIT's place; false
+// when it can't, and the PIT keeps the job
+pub fn take_over_timer(hz: u64) -> bool {
+    if !is_running() || read(CAPABILITIES) & LEGACY_ROUTE_CAPABLE == 0 || read(TIMER0_CONFIG) & TIMER_PERIODIC_CAPABLE == 0 {
+        return false;
+    }
+    let period = HZ.load(Ordering::Relaxed) / hz;
+    write(CONFIG, read(CONFIG) & !ENABLE);
+    let config = read(TIMER0_CONFIG) & !TIMER_32_BIT;
+    write(TIMER0_CONFIG, config | TIMER_INTERRUPT | TIMER_PERIODIC | TIMER_SET_PERIOD);
+    write(TIMER0_COMPARATOR, read(MAIN_COUNTER) + period);
+    write(TIMER0_COMPARATOR, period);
+    write(CONFIG, read(CONFIG) | ENABLE | LEGACY_ROUTE);
+    true
+}
\ No newline at end of file
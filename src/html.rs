@@ -0,0 +1,328 @@
+// src/html.rs
+// Turns HTML into a StyledText for Safari: headings, paragraphs and the
+// other block elements, nested lists, block quotes, preformatted text,
+// rules, and bold, italic, code and link styling. There's no CSS, so the
+// layout is the document's structure; scripts, styles, forms and images
+// are left out.
+use crate::styled_text::{Block, Paragraph, Span, Style, StyledText};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+// Elements whose content isn't shown
+const HIDDEN: [&str; 10] = ["head", "script", "style", "noscript", "template", "svg", "iframe", "object", "select", "textarea"];
+// Elements that start and end a paragraph
+const BLOCKS: [&str; 22] = [
+    "p", "div", "section", "article", "header", "footer", "nav", "main", "aside", "table", "tr", "form", "figure",
+    "figcaption", "dl", "dt", "dd", "address", "details", "summary", "center", "fieldset",
+];
+
+struct Builder {
+    document: StyledText,
+    paragraph: Option<Paragraph>,
+    // Block for the next paragraph started by text
+    heading: Option<u8>,
+    bold: usize,
+    italic: usize,
+    code: usize,
+    link: usize,
+    quote: usize,
+    // Numbered lists hold the next number
+    lists: Vec<Option<usize>>,
+    // Text inside <pre>, split into lines when it closes
+    pre: Option<String>,
+    // Whitespace was seen since the last word
+    space: bool,
+}
+
+impl Builder {
+    fn style(&self) -> Style {
+        Style { bold: self.bold > 0 || self.heading.is_some(), italic: self.italic > 0, code: self.code > 0, link: self.link > 0 }
+    }
+    
+    fn finish(&mut self) {
+        if let Some(mut paragraph) = self.paragraph.take() {
+            if let Some(last) = paragraph.spans.last_mut() {
+                let trimmed = last.text.trim_end().len();
+                last.text.truncate(trimmed);
+            }
+            if paragraph.spans.iter().any(|span| !span.text.is_empty()) {
+                self.document.paragraphs.push(paragraph);
+            }
+        }
+        self.space = false;
+    }
+    
+    fn start_item(&mut self) {
+        self.finish();
+        let marker = match self.lists.last_mut() {
+            Some(Some(number)) => {
+                *number += 1;
+                format!("{}.", *number - 1)
+            }
+            _ => String::from("•"),
+        };
+        let mut paragraph = Paragraph::new(Block::Body, Vec::new());
+        paragraph.depth = self.lists.len().saturating_sub(1);
+        paragraph.marker = Some(marker);
+        self.paragraph = Some(paragraph);
+    }
+    
+    fn text(&mut self, text: &str) {
+        if let Some(pre) = self.pre.as_mut() {
+            pre.push_str(text);
+            return;
+        }
+        for word in text.split(|c: char| c.is_ascii_whitespace()) {
+            if word.is_empty() {
+                self.space = true;
+                continue;
+            }
+            let style = self.style();
+            let paragraph = self.paragraph.get_or_insert_with(|| match (self.heading, self.quote) {
+                (Some(level), _) => Paragraph::new(Block::Heading(level), Vec::new()),
+                (None, 0) => Paragraph::new(Block::Body, Vec::new()),
+                (None, _) => {
+                    let mut paragraph = Paragraph::new(Block::Quote, Vec::new());
+                    paragraph.depth = 1;
+                    paragraph
+                }
+            });
+            let leading = self.space && !paragraph.spans.is_empty();
+            match paragraph.spans.last_mut() {
+                Some(span) if span.style == style => {
+                    if leading {
+                        span.text.push(' ');
+                    }
+                    span.text.push_str(word);
+                }
+                _ => {
+                    if leading {
+                        if let Some(span) = paragraph.spans.last_mut() {
+                            span.text.push(' ');
+                        }
+                    }
+                    paragraph.spans.push(Span::new(word, style));
+                }
+            }
+            self.space = false;
+        }
+    }
+    
+    fn open(&mut self, name: &str) {
+        match name {
+            "br" => self.finish(),
+            "hr" => {
+                self.finish();
+                self.document.paragraphs.push(Paragraph::new(Block::Rule, Vec::new()));
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.finish();
+                self.heading = Some(name.as_bytes()[1] - b'0');
+            }
+            "ul" | "menu" => {
+                self.finish();
+                self.lists.push(None);
+            }
+            "ol" => {
+                self.finish();
+                self.lists.push(Some(1));
+            }
+            "li" => self.start_item(),
+            "blockquote" => {
+                self.finish();
+                self.quote += 1;
+            }
+            "pre" => {
+                self.finish();
+                self.pre = Some(String::new());
+            }
+            "b" | "strong" => self.bold += 1,
+            "i" | "em" | "cite" | "var" => self.italic += 1,
+            "code" | "kbd" | "samp" | "tt" => self.code += 1,
+            "a" => self.link += 1,
+            // Table cells run on in one line
+            "td" | "th" => self.space = true,
+            _ if BLOCKS.contains(&name) => self.finish(),
+            _ => {}
+        }
+    }
+    
+    fn close(&mut self, name: &str) {
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.finish();
+                self.heading = None;
+            }
+            "ul" | "menu" | "ol" => {
+                self.finish();
+                self.lists.pop();
+            }
+            "li" => self.finish(),
+            "blockquote" => {
+                self.finish();
+                self.quote = self.quote.saturating_sub(1);
+            }
+            "pre" => {
+                let text = self.pre.take().unwrap_or_default();
+                // A newline right after <pre> isn't content
+                let text = text.strip_prefix('\n').unwrap_or(&text);
+                for line in text.trim_end().lines() {
+                    let line = line.replace('\t', "    ");
+                    self.document.paragraphs.push(Paragraph::new(Block::Code, Vec::from([Span::new(&line, Style::default())])));
+                }
+            }
+            "b" | "strong" => self.bold = self.bold.saturating_sub(1),
+            "i" | "em" | "cite" | "var" => self.italic = self.italic.saturating_sub(1),
+            "code" | "kbd" | "samp" | "tt" => self.code = self.code.saturating_sub(1),
+            "a" => self.link = self.link.saturating_sub(1),
+            _ if BLOCKS.contains(&name) => self.finish(),
+            _ => {}
+        }
+    }
+}
+
+// Decodes character references. Typographic punctuation becomes its ASCII
+// look-alike, which the system font has.
+fn decode(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = match rest[1..].find(|c: char| !c.is_ascii_alphanumeric() && c != '#') {
+            Some(end) if rest.as_bytes().get(end + 1) == Some(&b';') => end + 1,
+            _ => {
+                out.push('&');
+                rest = &rest[1..];
+                continue;
+            }
+        };
+        let name = &rest[1..end];
+        let decoded = match name {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" | "ldquo" | "rdquo" => Some('"'),
+            "apos" | "lsquo" | "rsquo" => Some('\''),
+            "nbsp" => Some(' '),
+            "ndash" | "mdash" | "minus" => Some('-'),
+            "bull" | "middot" => Some('•'),
+            "copy" => Some('©'),
+            "times" => Some('x'),
+            _ => {
+                let code = match name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => name.strip_prefix('#').and_then(|decimal| decimal.parse().ok()),
+                };
+                code.and_then(char::from_u32).map(|c| match c {
+                    '\u{2018}' | '\u{2019}' => '\'',
+                    '\u{201C}' | '\u{201D}' => '"',
+                    '\u{2013}' | '\u{2014}' => '-',
+                    '\u{A0}' => ' ',
+                    c => c,
+                })
+            }
+        };
+        match decoded {
+            Some(c) => out.push(c),
+            None if name == "hellip" => out.push_str("..."),
+            None => out.push_str(&rest[..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+// The end of the tag starting at `start`, skipping '>' inside quoted
+// attribute values
+fn tag_end(source: &str, start: usize) -> Option<usize> {
+    let mut quote = None;
+    for (offset, c) in source[start..].char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, '>') => return Some(start + offset),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn tag_name(tag: &str) -> String {
+    tag.trim_start_matches('/').split(|c: char| c.is_ascii_whitespace() || c == '/').next().unwrap_or("").to_ascii_lowercase()
+}
+
+pub fn parse(source: &str) -> StyledText {
+    let mut builder = Builder {
+        document: StyledText::default(),
+        paragraph: None,
+        heading: None,
+        bold: 0,
+        italic: 0,
+        code: 0,
+        link: 0,
+        quote: 0,
+        lists: Vec::new(),
+        pre: None,
+        space: false,
+    };
+    let lower = source.to_ascii_lowercase();
+    let mut position = 0;
+    while position < source.len() {
+        let next = match source[position..].find('<') {
+            Some(offset) => position + offset,
+            None => source.len(),
+        };
+        if next > position {
+            builder.text(&decode(&source[position..next]));
+            position = next;
+            continue;
+        }
+        if source[position..].starts_with("<!--") {
+            position = source[position..].find("-->").map_or(source.len(), |end| position + end + 3);
+            continue;
+        }
+        // A '<' that doesn't open a tag is text
+        let opens_tag = source[position + 1..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '/' || c == '!');
+        let end = match tag_end(source, position) {
+            Some(end) if opens_tag => end,
+            _ => {
+                builder.text("<");
+                position += 1;
+                continue;
+            }
+        };
+        let tag = &source[position + 1..end];
+        position = end + 1;
+        if tag.starts_with('!') || tag.starts_with('?') {
+            continue;
+        }
+        let name = tag_name(tag);
+        if tag.starts_with('/') {
+            builder.close(&name);
+        } else if HIDDEN.contains(&name.as_str()) {
+            // Skip to the matching end tag
+            let closing = format!("</{}", name);
+            position = lower[position..].find(&closing).map_or(source.len(), |offset| {
+                tag_end(source, position + offset).map_or(source.len(), |end| end + 1)
+            });
+        } else {
+            builder.open(&name);
+        }
+    }
+    builder.finish();
+    builder.document
+}
+
+// The <title>, if the document has one
+pub fn title(source: &str) -> Option<String> {
+    let lower = source.to_ascii_lowercase();
+    let start = lower.find("<title")?;
+    let start = tag_end(source, start)? + 1;
+    let end = start + lower[start..].find("</title")?;
+    let title: Vec<&str> = source[start..end].split_ascii_whitespace().collect();
+    let title = decode(&title.join(" "));
+    (!title.is_empty()).then_some(title)
+}
\ No newline at end of file
@@ -0,0 +1,351 @@
+// src/http.rs
+// HTTP/1.1 GET requests over TCP, or TLS for https:// URLs. A Request is
+// polled from the desktop loop, so a page can load while windows keep
+// drawing: it looks the host up, connects, sends the request with
+// `Connection: close` and collects the response, following redirects.
+// Bodies may be framed by Content-Length, chunked, or run to the end of
+// the connection; compressed encodings aren't asked for.
+use crate::clock::Instant;
+use crate::dns;
+use crate::net::NetError;
+use crate::stream::Stream;
+use crate::tls::TlsError;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::time::Duration;
+
+const USER_AGENT: &str = "RustOS/2.0";
+const MAX_REDIRECTS: usize = 5;
+// Responses larger than this are refused rather than held in the heap,
+// which is only 100 KiB all told
+const MAX_RESPONSE: usize = 32 * 1024;
+// For each connection, from the lookup to the last byte
+const TIMEOUT: Duration = Duration::from_secs(20);
+
+#[derive(Debug)]
+pub enum HttpError {
+    BadUrl,
+    Net(NetError),
+    Tls(TlsError),
+    BadResponse,
+    TooLarge,
+    TooManyRedirects,
+}
+
+impl From<NetError> for HttpError {
+    fn from(err: NetError) -> Self {
+        HttpError::Net(err)
+    }
+}
+
+impl From<TlsError> for HttpError {
+    fn from(err: TlsError) -> Self {
+        match err {
+            TlsError::Net(err) => HttpError::Net(err),
+            err => HttpError::Tls(err),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Url {
+    pub secure: bool,
+    pub host: String,
+    pub port: u16,
+    // Path and query, starting with '/'; the fragment is dropped
+    pub path: String,
+}
+
+impl Url {
+    pub fn parse(text: &str) -> Option<Self> {
+        let (secure, rest) = match text.split_once("://") {
+            Some((scheme, rest)) if scheme.eq_ignore_ascii_case("https") => (true, rest),
+            Some((scheme, rest)) if scheme.eq_ignore_ascii_case("http") => (false, rest),
+            _ => return None,
+        };
+        let rest = rest.split('#').next().unwrap_or("");
+        let (authority, path) = match rest.find(['/', '?']) {
+            Some(index) => (&rest[..index], &rest[index..]),
+            None => (rest, "/"),
+        };
+        // User names in URLs aren't supported
+        if authority.contains('@') {
+            return None;
+        }
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().ok()?),
+            None => (authority, if secure { 443 } else { 80 }),
+        };
+        if host.is_empty() {
+            return None;
+        }
+        let path = if path.starts_with('?') { format!("/{}", path) } else { String::from(path) };
+        Some(Self { secure, host: host.to_ascii_lowercase(), port, path })
+    }
+    
+    // Resolves a Location header or link against this URL
+    pub fn join(&self, reference: &str) -> Option<Self> {
+        if reference.contains("://") {
+            return Self::parse(reference);
+        }
+        let scheme = if self.secure { "https" } else { "http" };
+        if let Some(rest) = reference.strip_prefix("//") {
+            return Self::parse(&format!("{}://{}", scheme, rest));
+        }
+        let path = if reference.starts_with('/') {
+            String::from(reference)
+        } else {
+            // Relative to the directory of the current path
+            let base = self.path.split('?').next().unwrap_or("/");
+            let directory = &base[..base.rfind('/').map_or(0, |i| i + 1)];
+            format!("{}{}", directory, reference)
+        };
+        Some(Self { path, ..self.clone() })
+    }
+    
+    fn default_port(&self) -> bool {
+        self.port == if self.secure { 443 } else { 80 }
+    }
+}
+
+impl fmt::Display for Url {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}://{}", if self.secure { "https" } else { "http" }, self.host)?;
+        if !self.default_port() {
+            write!(f, ":{}", self.port)?;
+        }
+        write!(f, "{}", self.path)
+    }
+}
+
+pub struct Response {
+    // Where the response came from, after redirects
+    pub url: Url,
+    pub status: u16,
+    pub reason: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(key, _)| key.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str())
+    }
+    
+    // The media type without parameters, lowercased
+    pub fn content_type(&self) -> String {
+        let value = self.header("Content-Type").unwrap_or("");
+        value.split(';').next().unwrap_or("").trim().to_ascii_lowercase()
+    }
+}
+
+enum Stage {
+    Resolving(dns::Query),
+    Connecting(Stream),
+    Exchanging { stream: Stream, data: Vec<u8> },
+    Finished,
+}
+
+pub struct Request {
+    url: Url,
+    redirects: usize,
+    started: Instant,
+    stage: Stage,
+}
+
+impl Request {
+    pub fn get(url: &str) -> Result<Self, HttpError> {
+        let url = Url::parse(url).ok_or(HttpError::BadUrl)?;
+        Ok(Self::start(url, 0))
+    }
+    
+    fn start(url: Url, redirects: usize) -> Self {
+        let query = dns::Query::new(&url.host);
+        Self { url, redirects, started: Instant::now(), stage: Stage::Resolving(query) }
+    }
+    
+    // The URL being fetched, which changes as redirects are followed
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+    
+    // How much of the response body has arrived, in percent, once the
+    // headers have given its length
+    pub fn progress(&self) -> Option<u8> {
+        let data = match &self.stage {
+            Stage::Exchanging { data, .. } => data,
+            _ => return None,
+        };
+        let head_end = find(data, b"\r\n\r\n")?;
+        let head = core::str::from_utf8(&data[..head_end]).ok()?;
+        let length: usize = head.split("\r\n").skip(1).find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.trim().eq_ignore_ascii_case("Content-Length").then(|| value.trim().parse().ok())?
+        })?;
+        let received = data.len() - head_end - 4;
+        Some((received.min(length) * 100 / length.max(1)) as u8)
+    }
+    
+    // The response or error once the request is over; None while it is
+    // still going. Call `net::poll` between polls.
+    pub fn poll(&mut self) -> Option<Result<Response, HttpError>> {
+        match self.step() {
+            Ok(None) => None,
+            Ok(Some(response)) => {
+                self.finish();
+                let location = response.header("Location").and_then(|location| self.url.join(location));
+                match (response.status, location) {
+                    (301 | 302 | 303 | 307 | 308, Some(location)) => {
+                        if self.redirects == MAX_REDIRECTS {
+                            return Some(Err(HttpError::TooManyRedirects));
+                        }
+                        *self = Self::start(location, self.redirects + 1);
+                        None
+                    }
+                    _ => Some(Ok(response)),
+                }
+            }
+            Err(err) => {
+                self.finish();
+                Some(Err(err))
+            }
+        }
+    }
+    
+    fn finish(&mut self) {
+        match core::mem::replace(&mut self.stage, Stage::Finished) {
+            Stage::Connecting(stream) | Stage::Exchanging { stream, .. } => stream.close(),
+            _ => {}
+        }
+    }
+    
+    fn step(&mut self) -> Result<Option<Response>, HttpError> {
+        if self.started.elapsed() >= TIMEOUT {
+            return Err(HttpError::Net(NetError::TimedOut));
+        }
+        match &mut self.stage {
+            Stage::Resolving(query) => {
+                let addr = match query.poll() {
+                    Some(result) => result?,
+                    None => return Ok(None),
+                };
+                let mut stream = Stream::connect(addr, self.url.port, self.url.secure.then_some(self.url.host.as_str()))?;
+                let host = if self.url.default_port() { self.url.host.clone() } else { format!("{}:{}", self.url.host, self.url.port) };
+                let request = format!(
+                    "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: {}\r\nAccept: */*\r\nAccept-Encoding: identity\r\nConnection: close\r\n\r\n",
+                    self.url.path, host, USER_AGENT
+                );
+                stream.write(request.as_bytes());
+                self.stage = Stage::Connecting(stream);
+                Ok(None)
+            }
+            Stage::Connecting(stream) => {
+                if stream.is_connected()? {
+                    if let Stage::Connecting(stream) = core::mem::replace(&mut self.stage, Stage::Finished) {
+                        self.stage = Stage::Exchanging { stream, data: Vec::new() };
+                    }
+                }
+                Ok(None)
+            }
+            Stage::Exchanging { stream, data } => {
+                let received = stream.exchange()?;
+                if data.len() + received.len() > MAX_RESPONSE {
+                    return Err(HttpError::TooLarge);
+                }
+                // Grown exactly, so the buffer never doubles past the limit
+                data.reserve_exact(received.len());
+                data.extend_from_slice(&received);
+                parse_response(&self.url, data, stream.at_end())
+            }
+            Stage::Finished => Err(HttpError::BadResponse),
+        }
+    }
+}
+
+fn find(data: &[u8], needle: &[u8]) -> Option<usize> {
+    data.windows(needle.len()).position(|window| window == needle)
+}
+
+// The response, once `data` holds all of it. `at_end` says the connection
+// has closed, which ends a body without other framing.
+fn parse_response(url: &Url, data: &[u8], at_end: bool) -> Result<Option<Response>, HttpError> {
+    let head_end = match find(data, b"\r\n\r\n") {
+        Some(index) => index,
+        None if at_end => return Err(HttpError::BadResponse),
+        None => return Ok(None),
+    };
+    let head = core::str::from_utf8(&data[..head_end]).map_err(|_| HttpError::BadResponse)?;
+    let mut lines = head.split("\r\n");
+    let status_line = lines.next().ok_or(HttpError::BadResponse)?;
+    let mut parts = status_line.splitn(3, ' ');
+    if !parts.next().is_some_and(|version| version.starts_with("HTTP/1.")) {
+        return Err(HttpError::BadResponse);
+    }
+    let status: u16 = parts.next().and_then(|code| code.parse().ok()).ok_or(HttpError::BadResponse)?;
+    let reason = String::from(parts.next().unwrap_or(""));
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (String::from(key.trim()), String::from(value.trim())))
+        .collect();
+    let mut response = Response { url: url.clone(), status, reason, headers, body: Vec::new() };
+    
+    // An interim 1xx response comes before the real one
+    if (100..200).contains(&status) {
+        return parse_response(url, &data[head_end + 4..], at_end);
+    }
+    let body = &data[head_end + 4..];
+    if status == 204 || status == 304 {
+        return Ok(Some(response));
+    }
+    let chunked = response.header("Transfer-Encoding").is_some_and(|value| value.to_ascii_lowercase().contains("chunked"));
+    if chunked {
+        return Ok(dechunk(body)?.map(|body| {
+            response.body = body;
+            response
+        }));
+    }
+    if let Some(length) = response.header("Content-Length") {
+        let length: usize = length.parse().map_err(|_| HttpError::BadResponse)?;
+        if body.len() < length {
+            return if at_end { Err(HttpError::BadResponse) } else { Ok(None) };
+        }
+        response.body = body[..length].to_vec();
+        return Ok(Some(response));
+    }
+    if !at_end {
+        return Ok(None);
+    }
+    response.body = body.to_vec();
+    Ok(Some(response))
+}
+
+// Joins a chunked body, once the last chunk is in
+fn dechunk(mut data: &[u8]) -> Result<Option<Vec<u8>>, HttpError> {
+    let mut body = Vec::new();
+    loop {
+        let line_end = match find(data, b"\r\n") {
+            Some(index) => index,
+            None => return Ok(None),
+        };
+        let line = core::str::from_utf8(&data[..line_end]).map_err(|_| HttpError::BadResponse)?;
+        // Chunk extensions after ';' are ignored
+        let size = line.split(';').next().unwrap_or("").trim();
+        let size = usize::from_str_radix(size, 16).map_err(|_| HttpError::BadResponse)?;
+        data = &data[line_end + 2..];
+        if size == 0 {
+            // Trailer fields, then an empty line
+            return Ok(find(data, b"\r\n").map(|_| body));
+        }
+        // No chunk can be bigger than a whole response
+        if size > MAX_RESPONSE {
+            return Err(HttpError::BadResponse);
+        }
+        if data.len() < size + 2 {
+            return Ok(None);
+        }
+        body.extend_from_slice(&data[..size]);
+        data = &data[size + 2..];
+    }
+}
\ No newline at end of file
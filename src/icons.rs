@@ -0,0 +1,502 @@
+// src/icons.rs
+// Small bitmap icons for the emoji the UI puts in its labels, which the
+// font has no glyphs for: text draws the icon in a character cell instead
+// of the unknown-glyph box. Icons are pixel art, a letter a pixel for its
+// color and a dot where the background shows through, and they keep their
+// colors whatever color the text is. Emoji without an icon still draw as a
+// box; variation selectors and joiners take no space.
+use crate::graphics::Color;
+
+pub const ICON_SIZE: usize = 8;
+
+pub struct Icon {
+    pub rows: [&'static str; ICON_SIZE],
+}
+
+impl Icon {
+    // The color of a pixel, or None where it's clear
+    pub fn pixel(&self, x: usize, y: usize) -> Option<Color> {
+        match self.rows[y].as_bytes()[x] {
+            b'K' => Some(Color::BLACK),
+            b'W' => Some(Color::WHITE),
+            b'g' => Some(Color::GRAY),
+            b'd' => Some(Color::DARK_GRAY),
+            b'L' => Some(Color::LIGHT_GRAY),
+            b'B' => Some(Color::BLUE),
+            b'R' => Some(Color::RED),
+            b'G' => Some(Color::GREEN),
+            b'Y' => Some(Color::YELLOW),
+            _ => None,
+        }
+    }
+}
+
+const FOLDER: Icon = Icon { rows: [
+    "........",
+    "YYY.....",
+    "YYYYYYYY",
+    "YYYYYYYY",
+    "YYYYYYYY",
+    "YYYYYYYY",
+    "YYYYYYYY",
+    "........",
+] };
+
+const DOCUMENT: Icon = Icon { rows: [
+    ".ggggg..",
+    ".gWWWgg.",
+    ".gWWWWg.",
+    ".gWggWg.",
+    ".gWWWWg.",
+    ".gWggWg.",
+    ".gWWWWg.",
+    ".gggggg.",
+] };
+
+const MAGNIFIER: Icon = Icon { rows: [
+    "..ggg...",
+    ".gLLLg..",
+    ".gLLLg..",
+    ".gLLLg..",
+    "..gggK..",
+    ".....KK.",
+    "......KK",
+    "........",
+] };
+
+const DISPLAY: Icon = Icon { rows: [
+    "KKKKKKKK",
+    "KBBBBBBK",
+    "KBBBBBBK",
+    "KBBBBBBK",
+    "KKKKKKKK",
+    "...KK...",
+    "..KKKK..",
+    "........",
+] };
+
+const LAPTOP: Icon = Icon { rows: [
+    ".KKKKKK.",
+    ".KBBBBK.",
+    ".KBBBBK.",
+    ".KBBBBK.",
+    ".KKKKKK.",
+    "gggggggg",
+    "gggggggg",
+    "........",
+] };
+
+const PHONE: Icon = Icon { rows: [
+    "..KKKK..",
+    "..KBBK..",
+    "..KBBK..",
+    "..KBBK..",
+    "..KBBK..",
+    "..KBBK..",
+    "..KKKK..",
+    "..KKKK..",
+] };
+
+const APPLE: Icon = Icon { rows: [
+    "....G...",
+    "...G....",
+    ".RR.RR..",
+    "RRRRRRR.",
+    "RRRRRRR.",
+    "RRRRRRR.",
+    ".RRRRR..",
+    "..R.R...",
+] };
+
+const CRAB: Icon = Icon { rows: [
+    "R......R",
+    "RR....RR",
+    ".RRRRRR.",
+    "RRKRRKRR",
+    ".RRRRRR.",
+    "RRRRRRRR",
+    "R.R..R.R",
+    "........",
+] };
+
+const BATTERY: Icon = Icon { rows: [
+    "........",
+    "KKKKKKK.",
+    "KGGGGGKK",
+    "KGGGGGKK",
+    "KGGGGGKK",
+    "KKKKKKK.",
+    "........",
+    "........",
+] };
+
+const SIGNAL: Icon = Icon { rows: [
+    "......B.",
+    "......B.",
+    "....B.B.",
+    "....B.B.",
+    "..B.B.B.",
+    "..B.B.B.",
+    "B.B.B.B.",
+    "B.B.B.B.",
+] };
+
+const DISC: Icon = Icon { rows: [
+    "..gggg..",
+    ".gLLLLg.",
+    "gLLBBLLg",
+    "gLBKKBLg",
+    "gLBKKBLg",
+    "gLLBBLLg",
+    ".gLLLLg.",
+    "..gggg..",
+] };
+
+const NOTE: Icon = Icon { rows: [
+    "...KKKKK",
+    "...KKKKK",
+    "...K...K",
+    "...K...K",
+    "...K...K",
+    ".KKK.KKK",
+    "KKKKKKKK",
+    ".KK..KK.",
+] };
+
+const GLOBE: Icon = Icon { rows: [
+    "..BBBB..",
+    ".BGGBBB.",
+    "BGGGBBGB",
+    "BBGBBBGB",
+    "BBBBBGGB",
+    "BBBBGGBB",
+    ".BBBGBB.",
+    "..BBBB..",
+] };
+
+const GEAR: Icon = Icon { rows: [
+    "...gg...",
+    ".g.gg.g.",
+    "..gggg..",
+    "gggKKggg",
+    "gggKKggg",
+    "..gggg..",
+    ".g.gg.g.",
+    "...gg...",
+] };
+
+const TRASH: Icon = Icon { rows: [
+    "..gggg..",
+    "gggggggg",
+    ".gLgLgL.",
+    ".gLgLgL.",
+    ".gLgLgL.",
+    ".gLgLgL.",
+    ".gLgLgL.",
+    "..gggg..",
+] };
+
+const PICTURE: Icon = Icon { rows: [
+    "gggggggg",
+    "gBBBBYYg",
+    "gBBBBYYg",
+    "gBBBBBBg",
+    "gBBGBBBg",
+    "gBGGGBBg",
+    "gGGGGGGg",
+    "gggggggg",
+] };
+
+const LOCK: Icon = Icon { rows: [
+    "..gggg..",
+    ".g....g.",
+    ".g....g.",
+    "YYYYYYYY",
+    "YYYYYYYY",
+    "YYYKKYYY",
+    "YYYKKYYY",
+    "YYYYYYYY",
+] };
+
+const CAMERA: Icon = Icon { rows: [
+    "..KKK...",
+    "KKKKKKKK",
+    "KKgggKKK",
+    "KgLLLgKK",
+    "KgLBLgKK",
+    "KgLLLgKK",
+    "KKgggKKK",
+    "KKKKKKKK",
+] };
+
+const ENVELOPE: Icon = Icon { rows: [
+    "........",
+    "gggggggg",
+    "ggWWWWgg",
+    "gWgWWgWg",
+    "gWWggWWg",
+    "gWWWWWWg",
+    "gggggggg",
+    "........",
+] };
+
+const PACKAGE: Icon = Icon { rows: [
+    "........",
+    ".YYYYYY.",
+    "YYYYYYYY",
+    "dddddddd",
+    "YYYddYYY",
+    "YYYddYYY",
+    "YYYYYYYY",
+    "YYYYYYYY",
+] };
+
+const CLIPBOARD: Icon = Icon { rows: [
+    "..KKKK..",
+    "gggKKggg",
+    "gWWWWWWg",
+    "gWKKKKWg",
+    "gWWWWWWg",
+    "gWKKKKWg",
+    "gWWWWWWg",
+    "gggggggg",
+] };
+
+const CHART: Icon = Icon { rows: [
+    "gggggggg",
+    "gWWWWWWg",
+    "gWWWRWWg",
+    "gWBWRWWg",
+    "gWBWRWGg",
+    "gWBWRWGg",
+    "gWWWWWWg",
+    "gggggggg",
+] };
+
+const CALENDAR: Icon = Icon { rows: [
+    "RRRRRRRR",
+    "RRRRRRRR",
+    "WWWWWWWW",
+    "WKWKWKWW",
+    "WWWWWWWW",
+    "WKWKWKWW",
+    "WWWWWWWW",
+    "gggggggg",
+] };
+
+const WARNING: Icon = Icon { rows: [
+    "...YY...",
+    "...YY...",
+    "..YKKY..",
+    "..YKKY..",
+    ".YYKKYY.",
+    ".YYYYYY.",
+    "YYYKKYYY",
+    "YYYYYYYY",
+] };
+
+const CHECK: Icon = Icon { rows: [
+    "GGGGGGGG",
+    "GGGGGGWG",
+    "GGGGGWWG",
+    "GWGGWWGG",
+    "GWWWWGGG",
+    "GGWWGGGG",
+    "GGGGGGGG",
+    "GGGGGGGG",
+] };
+
+const RAINBOW: Icon = Icon { rows: [
+    "........",
+    "..RRRR..",
+    ".RYYYYR.",
+    "RYGGGGYR",
+    "YGB..BGY",
+    "GB....BG",
+    "B......B",
+    "........",
+] };
+
+const FIRE: Icon = Icon { rows: [
+    "...R....",
+    "..RR..R.",
+    "..RRR.R.",
+    ".RRYRRR.",
+    ".RYYYRR.",
+    "RRYYYYRR",
+    "RRYYYYRR",
+    ".RRRRRR.",
+] };
+
+const SPEAKER: Icon = Icon { rows: [
+    "...K....",
+    "..KK..K.",
+    "KKKK...K",
+    "KKKK.K.K",
+    "KKKK.K.K",
+    "KKKK...K",
+    "..KK..K.",
+    "...K....",
+] };
+
+const KEYBOARD: Icon = Icon { rows: [
+    "........",
+    "gggggggg",
+    "gWgWgWgW",
+    "gggggggg",
+    "gWgWgWgW",
+    "gggggggg",
+    "gWWWWWgW",
+    "gggggggg",
+] };
+
+const HOUSE: Icon = Icon { rows: [
+    "...RR...",
+    "..RRRR..",
+    ".RRRRRR.",
+    "RRRRRRRR",
+    ".WWWWWW.",
+    ".WWggWW.",
+    ".WWggWW.",
+    ".WWggWW.",
+] };
+
+const BOOKMARK: Icon = Icon { rows: [
+    ".BBBBBB.",
+    ".BBBBBB.",
+    ".BBBBBB.",
+    ".BBBBBB.",
+    ".BBBBBB.",
+    ".BBBBBB.",
+    ".BB..BB.",
+    ".B....B.",
+] };
+
+const SPARKLES: Icon = Icon { rows: [
+    "...Y....",
+    "...Y....",
+    ".YYYYY..",
+    "...Y..Y.",
+    "...Y.YYY",
+    "......Y.",
+    ".Y......",
+    "YYY.....",
+] };
+
+const TARGET: Icon = Icon { rows: [
+    "..RRRR..",
+    ".RWWWWR.",
+    "RWRRRRWR",
+    "RWRWWRWR",
+    "RWRWWRWR",
+    "RWRRRRWR",
+    ".RWWWWR.",
+    "..RRRR..",
+] };
+
+const MOON: Icon = Icon { rows: [
+    "...BBB..",
+    "..BB....",
+    ".BB.....",
+    ".BB.....",
+    ".BB.....",
+    ".BBB..B.",
+    "..BBBBB.",
+    "...BBB..",
+] };
+
+const SUN: Icon = Icon { rows: [
+    "Y..Y..Y.",
+    ".Y...Y..",
+    "...YY...",
+    "YYYYYY.Y",
+    "..YYYY..",
+    ".Y.YY.Y.",
+    "Y.....Y.",
+    "...Y....",
+] };
+
+const CLOUD: Icon = Icon { rows: [
+    "........",
+    "...LL...",
+    "..LLLL..",
+    ".LLLLLL.",
+    "LLLLLLLL",
+    "LLLLLLLL",
+    ".gggggg.",
+    "........",
+] };
+
+const RAIN: Icon = Icon { rows: [
+    "...gg...",
+    "..gggg..",
+    ".gggggg.",
+    "gggggggg",
+    "........",
+    ".B..B..B",
+    "B..B..B.",
+    "........",
+] };
+
+const SNOWFLAKE: Icon = Icon { rows: [
+    "...B....",
+    ".B.B.B..",
+    "..BBB...",
+    "BBBBBBB.",
+    "..BBB...",
+    ".B.B.B..",
+    "...B....",
+    "........",
+] };
+
+// The icon drawn for `ch`, for the emoji that have one
+pub fn emoji(ch: char) -> Option<&'static Icon> {
+    let icon = match ch {
+        '📁' | '📂' => &FOLDER,
+        '📄' | '📝' | '📃' => &DOCUMENT,
+        '🔍' | '🔎' => &MAGNIFIER,
+        '🖥' => &DISPLAY,
+        '💻' => &LAPTOP,
+        '📱' => &PHONE,
+        '🍎' => &APPLE,
+        '🦀' => &CRAB,
+        '🔋' => &BATTERY,
+        '📶' => &SIGNAL,
+        '💽' | '💿' | '📀' => &DISC,
+        '🎵' | '🎶' => &NOTE,
+        '🌐' => &GLOBE,
+        '⚙' => &GEAR,
+        '🗑' => &TRASH,
+        '🖼' => &PICTURE,
+        '🔒' => &LOCK,
+        '📸' | '📷' => &CAMERA,
+        '📧' | '✉' => &ENVELOPE,
+        '📦' => &PACKAGE,
+        '📋' => &CLIPBOARD,
+        '📊' => &CHART,
+        '📅' | '📆' => &CALENDAR,
+        '⚠' => &WARNING,
+        '✅' => &CHECK,
+        '🌈' => &RAINBOW,
+        '🔥' => &FIRE,
+        '🔊' | '🔈' | '🔉' => &SPEAKER,
+        '⌨' => &KEYBOARD,
+        '🏠' => &HOUSE,
+        '🔖' => &BOOKMARK,
+        '✨' => &SPARKLES,
+        '🎯' => &TARGET,
+        '🌙' => &MOON,
+        '☀' => &SUN,
+        '☁' => &CLOUD,
+        '🌧' => &RAIN,
+        '❄' => &SNOWFLAKE,
+        _ => return None,
+    };
+    Some(icon)
+}
+
+// Characters that change how the one before looks rather than drawing:
+// variation selectors, as in ⚙️, and the zero width joiner
+pub fn is_modifier(ch: char) -> bool {
+    matches!(ch, '\u{FE00}'..='\u{FE0F}' | '\u{200D}')
+}
\ No newline at end of file
@@ -0,0 +1,52 @@
+// src/initramfs.rs
+// The boot archive is built from ./assets by build.rs and linked into the
+// kernel image; at boot it is unpacked into a ramfs mounted at "/".
+use crate::ramfs::RamFs;
+use crate::tar::{Archive, EntryKind};
+use crate::vfs::{self, FileType};
+use crate::{kinfo, kwarn};
+
+static INITRAMFS: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/initramfs.tar"));
+
+pub fn init() {
+    if vfs::mount("/", "initramfs", RamFs::new()).is_err() {
+        kwarn!("initramfs: root already mounted");
+        return;
+    }
+    
+    let mut files = 0;
+    let mut bytes = 0;
+    for entry in Archive::new(INITRAMFS) {
+        let path = vfs::join("/", &entry.path);
+        let (parent, _) = vfs::parent_and_name(&path);
+        if vfs::create_dir_all(parent).is_err() {
+            kwarn!("initramfs: cannot create {}", parent);
+            continue;
+        }
+        
+        let result = match entry.kind {
+            EntryKind::Directory => vfs::create_dir_all(&path),
+            EntryKind::File => vfs::write_file(&path, entry.data),
+            EntryKind::Symlink => vfs::create(&path, FileType::Symlink)
+                .and_then(|_| vfs::write(&path, 0, entry.link_target.as_bytes()).map(|_| ())),
+            EntryKind::Other => continue,
+        };
+        
+        // Still running as root here, so ownership can be handed out freely
+        let result = result
+            .and_then(|_| vfs::chmod(&path, entry.mode as u16))
+            .and_then(|_| vfs::chown(&path, entry.uid, entry.gid))
+            .and_then(|_| vfs::set_times(&path, entry.mtime, entry.mtime));
+        
+        match result {
+            Ok(()) if entry.kind == EntryKind::File => {
+                files += 1;
+                bytes += entry.data.len();
+            }
+            Ok(()) => {}
+            Err(err) => kwarn!("initramfs: {}: {:?}", path, err),
+        }
+    }
+    
+    kinfo!("initramfs: unpacked {} files ({} bytes)", files, bytes);
+}
\ No newline at end of file
@@ -0,0 +1,41 @@
+// src/input.rs
+// The input event queue. Device drivers (USB HID today) push events from
+// wherever they are polled; the desktop drains the queue once a frame and
+// dispatches the events to the focused UI. Queued events live in a pool,
+// so a burst of mouse movement doesn't churn the heap.
+use crate::keyboard::Key;
+use crate::mouse::MouseButton;
+use crate::pool::{Pool, PoolBox};
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use crate::sync::Mutex;
+use x86_64::instructions::interrupts;
+
+// Events beyond this are dropped until the desktop catches up
+const QUEUE_CAPACITY: usize = 256;
+
+#[derive(Clone, Copy)]
+pub enum InputEvent {
+    Key { key: Key, pressed: bool },
+    MouseMove { dx: i32, dy: i32 },
+    MouseButton { button: MouseButton, pressed: bool },
+    // Wheel clicks, positive away from the user
+    Scroll { delta: i32 },
+}
+
+static EVENTS: Pool<InputEvent> = Pool::new("input events", QUEUE_CAPACITY);
+static QUEUE: Mutex<VecDeque<PoolBox<InputEvent>>> = Mutex::new(VecDeque::new());
+
+pub fn push(event: InputEvent) {
+    interrupts::without_interrupts(|| {
+        let mut queue = QUEUE.lock();
+        if queue.len() < QUEUE_CAPACITY {
+            queue.push_back(EVENTS.alloc(event));
+        }
+    });
+}
+
+// Everything queued since the last call, oldest first
+pub fn drain() -> Vec<InputEvent> {
+    interrupts::without_interrupts(|| QUEUE.lock().drain(..).map(|event| *event).collect())
+}
\ No newline at end of file
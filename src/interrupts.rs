@@ -0,0 +1,467 @@
+// src/interrupts.rs
+use crate::address_space::StackFault;
+use crate::{acpi, apic, hlt_loop, println, serial_println};
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+use lazy_static::lazy_static;
+use pic8259::ChainedPics;
+use crate::sync::Mutex;
+use x86_64::structures::idt::{HandlerFunc, InterruptDescriptorTable, InterruptStackFrame, PageFaultErrorCode};
+use x86_64::PrivilegeLevel;
+
+pub const PIC_1_OFFSET: u8 = 32;
+pub const PIC_2_OFFSET: u8 = PIC_1_OFFSET + 8;
+
+pub static PICS: Mutex<ChainedPics> =
+    Mutex::new(unsafe { ChainedPics::new(PIC_1_OFFSET, PIC_2_OFFSET) });
+
+// PCI devices share lines; this many drivers can be on one
+const MAX_SHARED: usize = 4;
+
+// Message-signalled interrupts get vectors of their own from here up, one
+// per device or queue, so they are never shared
+pub const MSI_VECTOR_BASE: u8 = 0x50;
+const MSI_VECTORS: usize = 16;
+
+#[derive(Clone, Copy)]
+struct Handler {
+    owner: &'static str,
+    // True when the interrupt was its device's
+    handle: fn() -> bool,
+}
+
+// Handlers for legacy IRQ lines, installed by drivers at probe time
+static IRQ_HANDLERS: Mutex<[[Option<Handler>; MAX_SHARED]; 16]> = Mutex::new([[None; MAX_SHARED]; 16]);
+// Handlers for MSI vectors, by their offset from MSI_VECTOR_BASE
+static MSI_HANDLERS: Mutex<[Option<Handler>; MSI_VECTORS]> = Mutex::new([None; MSI_VECTORS]);
+#[allow(clippy::declare_interior_mutable_const)]
+const ZERO: AtomicU64 = AtomicU64::new(0);
+// Interrupts taken per vector, exceptions and system calls included
+static VECTOR_COUNTS: [AtomicU64; 256] = [ZERO; 256];
+// Interrupts on a line that none of its handlers claimed
+static UNCLAIMED: [AtomicU64; 16] = [ZERO; 16];
+// Per vector, the count when the last second ended and the second's count
+static LAST_SECOND: [AtomicU64; 256] = [ZERO; 256];
+static RATES: [AtomicU64; 256] = [ZERO; 256];
+
+pub struct IrqLine {
+    pub line: u8,
+    // Who handles it: "timer", "keyboard", "cascade" or the drivers' names
+    pub owners: Vec<&'static str>,
+    pub masked: bool,
+    pub count: u64,
+    // Taken in the last second
+    pub rate: u64,
+    pub unclaimed: u64,
+    // The global system interrupt it would arrive on through an I/O APIC
+    pub gsi: u32,
+}
+
+pub struct MsiVector {
+    pub vector: u8,
+    pub owner: &'static str,
+    pub count: u64,
+    // Taken in the last second
+    pub rate: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(u8)]
+pub enum InterruptIndex {
+    Timer = PIC_1_OFFSET,
+    Keyboard,
+}
+
+impl InterruptIndex {
+    fn as_u8(self) -> u8 {
+        self as u8
+    }
+    
+    fn as_usize(self) -> usize {
+        usize::from(self.as_u8())
+    }
+}
+
+macro_rules! irq_handler {
+    ($name:ident, $irq:expr) => {
+        extern "x86-interrupt" fn $name(_stack_frame: InterruptStackFrame) {
+            dispatch_irq($irq);
+        }
+    };
+}
+
+irq_handler!(irq3_handler, 3);
+irq_handler!(irq4_handler, 4);
+irq_handler!(irq5_handler, 5);
+irq_handler!(irq7_handler, 7);
+irq_handler!(irq9_handler, 9);
+irq_handler!(irq10_handler, 10);
+irq_handler!(irq11_handler, 11);
+irq_handler!(irq12_handler, 12);
+irq_handler!(irq14_handler, 14);
+irq_handler!(irq15_handler, 15);
+
+macro_rules! msi_handler {
+    ($name:ident, $index:expr) => {
+        extern "x86-interrupt" fn $name(_stack_frame: InterruptStackFrame) {
+            dispatch_msi($index);
+        }
+    };
+}
+
+msi_handler!(msi0_handler, 0);
+msi_handler!(msi1_handler, 1);
+msi_handler!(msi2_handler, 2);
+msi_handler!(msi3_handler, 3);
+msi_handler!(msi4_handler, 4);
+msi_handler!(msi5_handler, 5);
+msi_handler!(msi6_handler, 6);
+msi_handler!(msi7_handler, 7);
+msi_handler!(msi8_handler, 8);
+msi_handler!(msi9_handler, 9);
+msi_handler!(msi10_handler, 10);
+msi_handler!(msi11_handler, 11);
+msi_handler!(msi12_handler, 12);
+msi_handler!(msi13_handler, 13);
+msi_handler!(msi14_handler, 14);
+msi_handler!(msi15_handler, 15);
+
+lazy_static! {
+    static ref IDT: InterruptDescriptorTable = {
+        let mut idt = InterruptDescriptorTable::new();
+        idt.breakpoint.set_handler_fn(breakpoint_handler);
+        unsafe {
+            idt.double_fault
+                .set_handler_fn(double_fault_handler)
+                .set_stack_index(crate::gdt::DOUBLE_FAULT_IST_INDEX);
+        }
+        idt.page_fault.set_handler_fn(page_fault_handler);
+        idt.general_protection_fault.set_handler_fn(general_protection_fault_handler);
+        idt.invalid_opcode.set_handler_fn(invalid_opcode_handler);
+        idt[InterruptIndex::Timer.as_usize()].set_handler_fn(timer_interrupt_handler);
+        idt[InterruptIndex::Keyboard.as_usize()].set_handler_fn(keyboard_interrupt_handler);
+        
+        let irq = |line: usize| usize::from(PIC_1_OFFSET) + line;
+        idt[irq(3)].set_handler_fn(irq3_handler);
+        idt[irq(4)].set_handler_fn(irq4_handler);
+        idt[irq(5)].set_handler_fn(irq5_handler);
+        idt[irq(7)].set_handler_fn(irq7_handler);
+        idt[irq(9)].set_handler_fn(irq9_handler);
+        idt[irq(10)].set_handler_fn(irq10_handler);
+        idt[irq(11)].set_handler_fn(irq11_handler);
+        idt[irq(12)].set_handler_fn(irq12_handler);
+        idt[irq(14)].set_handler_fn(irq14_handler);
+        idt[irq(15)].set_handler_fn(irq15_handler);
+        
+        let msi: [HandlerFunc; MSI_VECTORS] = [
+            msi0_handler, msi1_handler, msi2_handler, msi3_handler,
+            msi4_handler, msi5_handler, msi6_handler, msi7_handler,
+            msi8_handler, msi9_handler, msi10_handler, msi11_handler,
+            msi12_handler, msi13_handler, msi14_handler, msi15_handler,
+        ];
+        for (i, handler) in msi.into_iter().enumerate() {
+            idt[usize::from(MSI_VECTOR_BASE) + i].set_handler_fn(handler);
+        }
+        idt[usize::from(apic::SPURIOUS_VECTOR)].set_handler_fn(spurious_handler);
+        
+        // System calls from apps, the only gate ring 3 may use
+        unsafe {
+            idt[usize::from(rustos_sdk::protocol::SYSCALL_VECTOR)]
+                .set_handler_addr(crate::userspace::syscall_entry())
+                .set_privilege_level(PrivilegeLevel::Ring3);
+        }
+        idt
+    };
+}
+
+pub fn init_idt() {
+    IDT.load();
+}
+
+pub fn init_pics() {
+    unsafe { PICS.lock().initialize() };
+}
+
+// Lets devices send MSIs; without it drivers stay on their legacy lines
+pub fn init_msi() -> bool {
+    apic::init()
+}
+
+// Adds `handler` to a legacy IRQ line and unmasks it on the PIC. Every
+// handler on a line is called for each interrupt, as devices sharing a
+// level-triggered line may all be asking; each returns whether its device
+// was. `owner` names the driver in `irq_lines`. False when the line can't
+// be shared any further.
+pub fn register_irq(line: u8, owner: &'static str, handle: fn() -> bool) -> bool {
+    if line < 3 || line >= 16 {
+        return false;
+    }
+    
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        let mut handlers = IRQ_HANDLERS.lock();
+        let slot = match handlers[line as usize].iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => slot,
+            None => return false,
+        };
+        *slot = Some(Handler { owner, handle });
+        unmask_irq(line);
+        true
+    })
+}
+
+// Gives `handle` a vector of its own for a device's message-signalled
+// interrupt. None when the local APIC isn't on to receive it or the
+// vectors have run out; the driver falls back to its legacy line.
+pub fn allocate_msi_vector(owner: &'static str, handle: fn() -> bool) -> Option<u8> {
+    if !apic::is_enabled() {
+        return None;
+    }
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        let mut handlers = MSI_HANDLERS.lock();
+        let index = handlers.iter().position(Option::is_none)?;
+        handlers[index] = Some(Handler { owner, handle });
+        Some(MSI_VECTOR_BASE + index as u8)
+    })
+}
+
+// Gives back a vector the device couldn't be pointed at
+pub fn free_msi_vector(vector: u8) {
+    if let Some(slot) = MSI_HANDLERS.lock().get_mut(vector.wrapping_sub(MSI_VECTOR_BASE) as usize) {
+        *slot = None;
+    }
+}
+
+// Called for vectors that don't go through `dispatch_irq`
+pub fn count(vector: u8) {
+    VECTOR_COUNTS[vector as usize].fetch_add(1, Ordering::Relaxed);
+}
+
+// Every vector taken at least once, with its count
+pub fn vector_counts() -> Vec<(u8, u64)> {
+    (0..=255u8)
+        .map(|vector| (vector, VECTOR_COUNTS[vector as usize].load(Ordering::Relaxed)))
+        .filter(|&(_, count)| count > 0)
+        .collect()
+}
+
+// Ends a second of the per-vector rates; called from the timer interrupt
+fn roll_rates() {
+    for vector in 0..256 {
+        let count = VECTOR_COUNTS[vector].load(Ordering::Relaxed);
+        let last = LAST_SECOND[vector].swap(count, Ordering::Relaxed);
+        RATES[vector].store(count - last, Ordering::Relaxed);
+    }
+}
+
+fn unmask_irq(line: u8) {
+    use x86_64::instructions::port::Port;
+    
+    let (port, bit) = if line < 8 { (0x21, line) } else { (0xA1, line - 8) };
+    let mut data: Port<u8> = Port::new(port);
+    unsafe {
+        let mask = data.read();
+        data.write(mask & !(1 << bit));
+        if line >= 8 {
+            // Make sure the cascade line to the slave PIC is open too
+            let mut master: Port<u8> = Port::new(0x21);
+            let master_mask = master.read();
+            master.write(master_mask & !(1 << 2));
+        }
+    }
+}
+
+// Every legacy IRQ line with its owners, PIC mask bit and interrupt counts
+pub fn irq_lines() -> Vec<IrqLine> {
+    use x86_64::instructions::port::Port;
+    
+    let masks = unsafe { Port::<u8>::new(0x21).read() as u16 | (Port::<u8>::new(0xA1).read() as u16) << 8 };
+    let handlers = *IRQ_HANDLERS.lock();
+    (0..16u8)
+        .map(|line| IrqLine {
+            line,
+            owners: match line {
+                0 => alloc::vec!["timer"],
+                1 => alloc::vec!["keyboard"],
+                2 => alloc::vec!["cascade"],
+                _ => handlers[line as usize].iter().flatten().map(|handler| handler.owner).collect(),
+            },
+            masked: masks & (1 << line) != 0,
+            count: VECTOR_COUNTS[(PIC_1_OFFSET + line) as usize].load(Ordering::Relaxed),
+            rate: RATES[(PIC_1_OFFSET + line) as usize].load(Ordering::Relaxed),
+            unclaimed: UNCLAIMED[line as usize].load(Ordering::Relaxed),
+            gsi: acpi::isa_gsi(line),
+        })
+        .collect()
+}
+
+// Every MSI vector handed out, with its owner and counts
+pub fn msi_vectors() -> Vec<MsiVector> {
+    let handlers = *MSI_HANDLERS.lock();
+    handlers
+        .iter()
+        .enumerate()
+        .filter_map(|(i, handler)| {
+            let vector = MSI_VECTOR_BASE + i as u8;
+            handler.map(|handler| MsiVector {
+                vector,
+                owner: handler.owner,
+                count: VECTOR_COUNTS[vector as usize].load(Ordering::Relaxed),
+                rate: RATES[vector as usize].load(Ordering::Relaxed),
+            })
+        })
+        .collect()
+}
+
+// The driver owning an MSI vector
+pub fn msi_owner(vector: u8) -> Option<&'static str> {
+    let index = vector.wrapping_sub(MSI_VECTOR_BASE) as usize;
+    MSI_HANDLERS.lock().get(index).copied().flatten().map(|handler| handler.owner)
+}
+
+fn dispatch_irq(line: u8) {
+    count(PIC_1_OFFSET + line);
+    let handlers = IRQ_HANDLERS.lock()[line as usize];
+    // Not short-circuited: every device on the line gets to clear its
+    // interrupt
+    let claimed = handlers.iter().flatten().fold(false, |claimed, handler| (handler.handle)() | claimed);
+    if !claimed {
+        UNCLAIMED[line as usize].fetch_add(1, Ordering::Relaxed);
+    }
+    
+    unsafe {
+        PICS.lock().notify_end_of_interrupt(PIC_1_OFFSET + line);
+    }
+}
+
+// Edge-triggered and never shared, so there's nothing to claim
+fn dispatch_msi(index: usize) {
+    count(MSI_VECTOR_BASE + index as u8);
+    let handler = MSI_HANDLERS.lock()[index];
+    if let Some(handler) = handler {
+        (handler.handle)();
+    }
+    apic::end_of_interrupt();
+}
+
+extern "x86-interrupt" fn spurious_handler(_stack_frame: InterruptStackFrame) {
+    count(apic::SPURIOUS_VECTOR);
+}
+
+extern "x86-interrupt" fn breakpoint_handler(stack_frame: InterruptStackFrame) {
+    count(3);
+    println!("EXCEPTION: BREAKPOINT\n{:#?}", stack_frame);
+    // This frame sits on the interrupt frame, so the walk starts at the
+    // instruction after the int3
+    for address in crate::backtrace::Frames::here().take(16) {
+        println!("  {}", crate::symbols::Address(address));
+    }
+}
+
+extern "x86-interrupt" fn double_fault_handler(
+    stack_frame: InterruptStackFrame,
+    _error_code: u64,
+) -> ! {
+    use x86_64::registers::control::Cr2;
+    
+    count(8);
+    // A page fault that cannot push its frame because the stack ran into
+    // its guard page ends up here
+    if let Some(name) = crate::stack::overflowed_stack(Cr2::read()) {
+        panic!("EXCEPTION: DOUBLE FAULT (kernel stack overflow: {} stack)\n{:#?}", name, stack_frame);
+    }
+    panic!("EXCEPTION: DOUBLE FAULT\n{:#?}", stack_frame);
+}
+
+extern "x86-interrupt" fn page_fault_handler(
+    mut stack_frame: InterruptStackFrame,
+    error_code: PageFaultErrorCode,
+) {
+    use x86_64::registers::control::Cr2;
+    
+    count(14);
+    // First touch of a memory-mapped file page, or a write to a page shared
+    // copy-on-write after fork
+    if crate::vm::handle_page_fault(Cr2::read(), error_code)
+        || crate::address_space::handle_cow_fault(Cr2::read(), error_code)
+    {
+        return;
+    }
+    
+    match crate::address_space::handle_stack_fault(Cr2::read(), error_code) {
+        Some(StackFault::Grown) => return,
+        Some(StackFault::Overflow) => {
+            if crate::userspace::fault(&mut stack_frame, "stack overflow", Cr2::read().as_u64()) {
+                return;
+            }
+            serial_println!("EXCEPTION: USER STACK OVERFLOW at {:?}", Cr2::read());
+            serial_println!("{:#?}", stack_frame);
+            hlt_loop();
+        }
+        None => {}
+    }
+    if let Some(name) = crate::stack::overflowed_stack(Cr2::read()) {
+        panic!("EXCEPTION: kernel stack overflow: {} stack hit its guard page", name);
+    }
+    
+    // A bad access from app drawing code, or from an app in user mode,
+    // only kills the app
+    if crate::userspace::fault(&mut stack_frame, "page fault", Cr2::read().as_u64()) {
+        return;
+    }
+    if crate::crash::handle_fault(&mut stack_frame, format_args!("page fault accessing {:?}", Cr2::read())) {
+        return;
+    }
+    
+    serial_println!("EXCEPTION: PAGE FAULT");
+    serial_println!("Accessed Address: {:?}", Cr2::read());
+    serial_println!("Error Code: {:?}", error_code);
+    serial_println!("{:#?}", stack_frame);
+    hlt_loop();
+}
+
+// Apps in user mode are killed for these; in the kernel they're fatal
+extern "x86-interrupt" fn general_protection_fault_handler(mut stack_frame: InterruptStackFrame, error_code: u64) {
+    count(13);
+    let rip = stack_frame.instruction_pointer.as_u64();
+    if crate::userspace::fault(&mut stack_frame, "general protection fault", rip) {
+        return;
+    }
+    panic!("EXCEPTION: GENERAL PROTECTION FAULT ({:#x})\n{:#?}", error_code, stack_frame);
+}
+
+extern "x86-interrupt" fn invalid_opcode_handler(mut stack_frame: InterruptStackFrame) {
+    count(6);
+    let rip = stack_frame.instruction_pointer.as_u64();
+    if crate::userspace::fault(&mut stack_frame, "invalid opcode", rip) {
+        return;
+    }
+    panic!("EXCEPTION: INVALID OPCODE\n{:#?}", stack_frame);
+}
+
+extern "x86-interrupt" fn timer_interrupt_handler(mut stack_frame: InterruptStackFrame) {
+    count(InterruptIndex::Timer.as_u8());
+    crate::clock::tick();
+    if crate::clock::ticks() % crate::clock::TIMER_HZ == 0 {
+        roll_rates();
+    }
+    crate::cpu_usage::tick();
+    crate::cpu_profiler::sample(&stack_frame);
+    crate::watchdog::check(&mut stack_frame);
+    crate::userspace::preempt(&mut stack_frame);
+    unsafe {
+        PICS.lock().notify_end_of_interrupt(InterruptIndex::Timer.as_u8());
+    }
+}
+
+extern "x86-interrupt" fn keyboard_interrupt_handler(_stack_frame: InterruptStackFrame) {
+    use x86_64::instructions::port::Port;
+    
+    // Drain the controller so it keeps delivering interrupts
+    let mut port = Port::new(0x60);
+    let _scancode: u8 = unsafe { port.read() };
+    count(InterruptIndex::Keyboard.as_u8());
+    
+    unsafe {
+        PICS.lock().notify_end_of_interrupt(InterruptIndex::Keyboard.as_u8());
+    }
+}
\ No newline at end of file
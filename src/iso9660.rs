@@ -0,0 +1,302 @@
+// src/iso9660.rs
+// Read-only ISO9660 driver with Rock Ridge long names. Inodes are the byte
+// offsets of directory records on the disc, so a record can be re-read from
+// its inode number alone.
+use crate::block::{read_bytes, SharedBlockDevice};
+use crate::vfs::{DirEntry, FileSystem, FileType, Metadata, VfsError};
+use crate::rtc::DateTime;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+const ISO_BLOCK_SIZE: u64 = 2048;
+const DESCRIPTOR_START: u64 = 16;
+const PRIMARY_DESCRIPTOR: u8 = 1;
+const TERMINATOR_DESCRIPTOR: u8 = 255;
+const ROOT_RECORD_OFFSET: u64 = 156;
+
+const FLAG_DIRECTORY: u8 = 0x02;
+
+#[derive(Debug, Clone)]
+struct Record {
+    extent: u32,
+    size: u32,
+    is_dir: bool,
+    name: String,
+    mode: Option<u16>,
+    uid: u32,
+    gid: u32,
+    recorded: u64,
+}
+
+pub struct Iso9660 {
+    device: SharedBlockDevice,
+    root: u64,
+    block_size: u64,
+    // In bytes; a disc is always full
+    volume_size: u64,
+    pub volume_id: String,
+}
+
+fn u32_at(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([buf[offset], buf[offset + 1], buf[offset + 2], buf[offset + 3]])
+}
+
+// Strips the ";1" version suffix and the trailing dot of extensionless names
+fn clean_iso_name(raw: &[u8]) -> String {
+    let mut name = String::from_utf8_lossy(raw).into_owned();
+    if let Some(pos) = name.find(';') {
+        name.truncate(pos);
+    }
+    if name.ends_with('.') {
+        name.pop();
+    }
+    name
+}
+
+// Walks the SUSP entries in a record's system use area looking for a Rock
+// Ridge "NM" (alternate name) entry.
+fn rock_ridge_name(system_use: &[u8]) -> Option<String> {
+    let mut name = String::new();
+    let mut pos = 0;
+    while pos + 4 <= system_use.len() {
+        let signature = &system_use[pos..pos + 2];
+        let len = system_use[pos + 2] as usize;
+        if len < 4 || pos + len > system_use.len() {
+            break;
+        }
+        if signature == b"NM" && len > 5 {
+            let flags = system_use[pos + 4];
+            // Flags 2/4 mark "." and ".." entries
+            if flags & 0x06 == 0 {
+                name.push_str(&String::from_utf8_lossy(&system_use[pos + 5..pos + len]));
+            }
+            if flags & 0x01 == 0 && !name.is_empty() {
+                return Some(name);
+            }
+        }
+        if signature == b"ST" {
+            break;
+        }
+        pos += len;
+    }
+    if name.is_empty() { None } else { Some(name) }
+}
+
+// Rock Ridge PX entry: POSIX mode, link count, uid and gid (both-endian)
+fn rock_ridge_attributes(system_use: &[u8]) -> Option<(u16, u32, u32)> {
+    let mut pos = 0;
+    while pos + 4 <= system_use.len() {
+        let signature = &system_use[pos..pos + 2];
+        let len = system_use[pos + 2] as usize;
+        if len < 4 || pos + len > system_use.len() {
+            break;
+        }
+        if signature == b"PX" && len >= 36 {
+            let entry = &system_use[pos..pos + len];
+            let mode = u32_at(entry, 4) as u16 & 0o7777;
+            return Some((mode, u32_at(entry, 20), u32_at(entry, 28)));
+        }
+        if signature == b"ST" {
+            break;
+        }
+        pos += len;
+    }
+    None
+}
+
+// Directory record date: years since 1900, month, day, hour, minute, second
+// and a GMT offset in 15 minute units
+fn record_time(date: &[u8]) -> u64 {
+    let time = DateTime {
+        year: 1900 + date[0] as u16,
+        month: date[1].max(1),
+        day: date[2].max(1),
+        hour: date[3],
+        minute: date[4],
+        second: date[5],
+    };
+    let offset = date[6] as i8 as i64 * 15 * 60;
+    (time.to_unix() as i64 - offset).max(0) as u64
+}
+
+fn parse_record(raw: &[u8]) -> Option<Record> {
+    let len = raw[0] as usize;
+    if len < 34 || len > raw.len() {
+        return None;
+    }
+    
+    let name_len = raw[32] as usize;
+    if 33 + name_len > len {
+        return None;
+    }
+    let raw_name = &raw[33..33 + name_len];
+    // System use area starts after the name, padded to an even offset
+    let su_start = 33 + name_len + (name_len + 1) % 2;
+    let system_use = if su_start < len { &raw[su_start..len] } else { &[][..] };
+    let name = if raw_name == [0] {
+        String::from(".")
+    } else if raw_name == [1] {
+        String::from("..")
+    } else {
+        rock_ridge_name(system_use).unwrap_or_else(|| clean_iso_name(raw_name))
+    };
+    let attributes = rock_ridge_attributes(system_use);
+    
+    Some(Record {
+        extent: u32_at(raw, 2),
+        size: u32_at(raw, 10),
+        is_dir: raw[25] & FLAG_DIRECTORY != 0,
+        name,
+        mode: attributes.map(|(mode, _, _)| mode),
+        uid: attributes.map_or(0, |(_, uid, _)| uid),
+        gid: attributes.map_or(0, |(_, _, gid)| gid),
+        recorded: record_time(&raw[18..25]),
+    })
+}
+
+pub fn probe(device: &SharedBlockDevice) -> bool {
+    let mut id = [0u8; 6];
+    read_bytes(device, DESCRIPTOR_START * ISO_BLOCK_SIZE, &mut id).is_ok() && &id[1..6] == b"CD001"
+}
+
+impl Iso9660 {
+    pub fn mount(device: SharedBlockDevice) -> Result<Self, VfsError> {
+        let mut descriptor = vec![0u8; ISO_BLOCK_SIZE as usize];
+        
+        for index in DESCRIPTOR_START..DESCRIPTOR_START + 32 {
+            let offset = index * ISO_BLOCK_SIZE;
+            read_bytes(&device, offset, &mut descriptor)?;
+            if &descriptor[1..6] != b"CD001" {
+                return Err(VfsError::Corrupt);
+            }
+            
+            match descriptor[0] {
+                PRIMARY_DESCRIPTOR => {
+                    let block_size = u16::from_le_bytes([descriptor[128], descriptor[129]]) as u64;
+                    let block_size = if block_size == 0 { ISO_BLOCK_SIZE } else { block_size };
+                    let volume_id = String::from_utf8_lossy(&descriptor[40..72]).trim_end().into();
+                    return Ok(Self {
+                        device,
+                        root: offset + ROOT_RECORD_OFFSET,
+                        block_size,
+                        volume_size: u32_at(&descriptor, 80) as u64 * block_size,
+                        volume_id,
+                    });
+                }
+                TERMINATOR_DESCRIPTOR => break,
+                _ => {}
+            }
+        }
+        Err(VfsError::Corrupt)
+    }
+    
+    fn record(&self, inode: u64) -> Result<Record, VfsError> {
+        let mut raw = [0u8; 255];
+        read_bytes(&self.device, inode, &mut raw[..1])?;
+        let len = (raw[0] as usize).max(34);
+        read_bytes(&self.device, inode, &mut raw[..len])?;
+        parse_record(&raw[..len]).ok_or(VfsError::Corrupt)
+    }
+    
+    // Returns (inode, record) pairs for a directory's children
+    fn children(&self, dir: &Record) -> Result<Vec<(u64, Record)>, VfsError> {
+        let start = dir.extent as u64 * self.block_size;
+        let mut data = vec![0u8; dir.size as usize];
+        read_bytes(&self.device, start, &mut data)?;
+        
+        let mut children = Vec::new();
+        let mut pos = 0;
+        while pos < data.len() {
+            let len = data[pos] as usize;
+            if len == 0 {
+                // Records never straddle a block; skip the padding
+                pos = (pos / ISO_BLOCK_SIZE as usize + 1) * ISO_BLOCK_SIZE as usize;
+                continue;
+            }
+            if pos + len > data.len() {
+                break;
+            }
+            if let Some(record) = parse_record(&data[pos..pos + len]) {
+                if record.name != "." && record.name != ".." {
+                    children.push((start + pos as u64, record));
+                }
+            }
+            pos += len;
+        }
+        Ok(children)
+    }
+}
+
+impl FileSystem for Iso9660 {
+    fn fs_type(&self) -> &'static str {
+        "iso9660"
+    }
+    
+    fn usage(&self) -> Option<(u64, u64)> {
+        Some((self.volume_size, self.volume_size))
+    }
+    
+    fn root_inode(&self) -> u64 {
+        self.root
+    }
+    
+    fn lookup(&mut self, dir: u64, name: &str) -> Result<u64, VfsError> {
+        let dir = self.record(dir)?;
+        if !dir.is_dir {
+            return Err(VfsError::NotADirectory);
+        }
+        // Plain ISO names are upper case, so match without case
+        self.children(&dir)?
+            .into_iter()
+            .find(|(_, r)| r.name == name || r.name.eq_ignore_ascii_case(name))
+            .map(|(inode, _)| inode)
+            .ok_or(VfsError::NotFound)
+    }
+    
+    fn metadata(&mut self, inode: u64) -> Result<Metadata, VfsError> {
+        let record = self.record(inode)?;
+        Ok(Metadata {
+            inode,
+            file_type: if record.is_dir { FileType::Directory } else { FileType::File },
+            size: record.size as u64,
+            // Without Rock Ridge everything is world readable
+            mode: record.mode.unwrap_or(if record.is_dir { 0o555 } else { 0o444 }),
+            uid: record.uid,
+            gid: record.gid,
+            created: record.recorded,
+            modified: record.recorded,
+        })
+    }
+    
+    fn read_dir(&mut self, inode: u64) -> Result<Vec<DirEntry>, VfsError> {
+        let dir = self.record(inode)?;
+        if !dir.is_dir {
+            return Err(VfsError::NotADirectory);
+        }
+        Ok(self
+            .children(&dir)?
+            .into_iter()
+            .map(|(inode, record)| DirEntry {
+                name: record.name,
+                inode,
+                file_type: if record.is_dir { FileType::Directory } else { FileType::File },
+            })
+            .collect())
+    }
+    
+    fn read(&mut self, inode: u64, offset: u64, buf: &mut [u8]) -> Result<usize, VfsError> {
+        let record = self.record(inode)?;
+        if record.is_dir {
+            return Err(VfsError::IsADirectory);
+        }
+        if offset >= record.size as u64 {
+            return Ok(0);
+        }
+        
+        let n = buf.len().min((record.size as u64 - offset) as usize);
+        let start = record.extent as u64 * self.block_size + offset;
+        read_bytes(&self.device, start, &mut buf[..n])?;
+        Ok(n)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,433 @@
+// src/json.rs
+// JSON for services and apps that keep or exchange structured data:
+// settings, web services, crash reports, update manifests.
+//
+// `parse` reads a whole text into a Value tree, which is looked into with
+// `get` and `at` and the `as_` accessors; they answer None rather than
+// failing when the shape isn't what was asked for, so a path into a
+// document reads as a chain of `?`. Building goes the other way:
+// `Value::object().with("name", "Finder").with("pid", 3)`, or `insert`
+// and `push` on a value already there. Display writes a value as compact
+// JSON and `pretty` as indented JSON for files people read.
+//
+// Numbers are kept as f64; ones that aren't finite are written as null.
+// Objects keep their keys in order, and a key that comes twice is found,
+// and replaced, as its first value. Nesting is limited so a hostile
+// document can't run the stack out.
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+
+const MAX_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+// Why a text isn't JSON
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonError {
+    // Something JSON doesn't allow, at this byte offset
+    Unexpected(usize),
+    // The text ended inside a value
+    Truncated,
+    TooDeep,
+}
+
+impl Value {
+    // An object with no members yet
+    pub fn object() -> Value {
+        Value::Object(Vec::new())
+    }
+
+    // An array with no elements yet
+    pub fn array() -> Value {
+        Value::Array(Vec::new())
+    }
+
+    // This object with `key` set, for building one in a single expression
+    pub fn with(mut self, key: &str, value: impl Into<Value>) -> Value {
+        self.insert(key, value);
+        self
+    }
+
+    // Sets `key` on an object, replacing the value it had. Does nothing to
+    // anything else.
+    pub fn insert(&mut self, key: &str, value: impl Into<Value>) {
+        if let Value::Object(members) = self {
+            let value = value.into();
+            match members.iter_mut().find(|(name, _)| name == key) {
+                Some((_, old)) => *old = value,
+                None => members.push((String::from(key), value)),
+            }
+        }
+    }
+
+    // Adds an element to the end of an array. Does nothing to anything else.
+    pub fn push(&mut self, value: impl Into<Value>) {
+        if let Value::Array(elements) = self {
+            elements.push(value.into());
+        }
+    }
+
+    // The member named `key`, if this is an object with one
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.members().iter().find(|(name, _)| name == key).map(|(_, value)| value)
+    }
+
+    // The `index`th element, if this is an array that long
+    pub fn at(&self, index: usize) -> Option<&Value> {
+        self.as_array().get(index)
+    }
+
+    pub fn is_null(&self) -> bool {
+        *self == Value::Null
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    // A number, or a string holding one, as services often send them
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(number) => Some(*number),
+            Value::String(text) => text.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    // A number that's whole and fits, or a string holding one
+    pub fn as_i64(&self) -> Option<i64> {
+        let number = self.as_f64()?;
+        let whole = number as i64;
+        (whole as f64 == number).then_some(whole)
+    }
+
+    // The elements, or none if this isn't an array
+    pub fn as_array(&self) -> &[Value] {
+        match self {
+            Value::Array(elements) => elements,
+            _ => &[],
+        }
+    }
+
+    // The members in order, or none if this isn't an object
+    pub fn members(&self) -> &[(String, Value)] {
+        match self {
+            Value::Object(members) => members,
+            _ => &[],
+        }
+    }
+
+    // Indented two spaces a level, a member or element a line
+    pub fn pretty(&self) -> String {
+        let mut text = String::new();
+        let _ = self.write(&mut text, Some(0));
+        text
+    }
+
+    // Compact when `indent` is None, otherwise indented from that level
+    fn write(&self, out: &mut impl Write, indent: Option<usize>) -> fmt::Result {
+        let newline = |out: &mut dyn Write, level: usize| -> fmt::Result {
+            out.write_char('\n')?;
+            (0..level).try_for_each(|_| out.write_str("  "))
+        };
+        match self {
+            Value::Null => out.write_str("null"),
+            Value::Bool(value) => write!(out, "{}", value),
+            Value::Number(number) if number.is_finite() => write!(out, "{}", number),
+            Value::Number(_) => out.write_str("null"),
+            Value::String(text) => write_string(out, text),
+            Value::Array(elements) if elements.is_empty() => out.write_str("[]"),
+            Value::Object(members) if members.is_empty() => out.write_str("{}"),
+            Value::Array(elements) => {
+                out.write_char('[')?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        out.write_char(',')?;
+                    }
+                    if let Some(level) = indent {
+                        newline(out, level + 1)?;
+                    }
+                    element.write(out, indent.map(|level| level + 1))?;
+                }
+                if let Some(level) = indent {
+                    newline(out, level)?;
+                }
+                out.write_char(']')
+            }
+            Value::Object(members) => {
+                out.write_char('{')?;
+                for (i, (name, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        out.write_char(',')?;
+                    }
+                    if let Some(level) = indent {
+                        newline(out, level + 1)?;
+                    }
+                    write_string(out, name)?;
+                    out.write_str(if indent.is_some() { ": " } else { ":" })?;
+                    value.write(out, indent.map(|level| level + 1))?;
+                }
+                if let Some(level) = indent {
+                    newline(out, level)?;
+                }
+                out.write_char('}')
+            }
+        }
+    }
+}
+
+// Compact JSON, as `to_string` and `format!` give it
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, None)
+    }
+}
+
+fn write_string(out: &mut impl Write, text: &str) -> fmt::Result {
+    out.write_char('"')?;
+    for ch in text.chars() {
+        match ch {
+            '"' => out.write_str("\\\"")?,
+            '\\' => out.write_str("\\\\")?,
+            '\n' => out.write_str("\\n")?,
+            '\r' => out.write_str("\\r")?,
+            '\t' => out.write_str("\\t")?,
+            ch if (ch as u32) < 0x20 => write!(out, "\\u{:04x}", ch as u32)?,
+            ch => out.write_char(ch)?,
+        }
+    }
+    out.write_char('"')
+}
+
+impl From<f64> for Value {
+    fn from(number: f64) -> Value {
+        Value::Number(number)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Value {
+        Value::Bool(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(text: &str) -> Value {
+        Value::String(String::from(text))
+    }
+}
+
+impl From<String> for Value {
+    fn from(text: String) -> Value {
+        Value::String(text)
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(elements: Vec<Value>) -> Value {
+        Value::Array(elements)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Value {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+macro_rules! from_number {
+    ($($t:ty),*) => {
+        $(impl From<$t> for Value {
+            fn from(number: $t) -> Value {
+                Value::Number(number as f64)
+            }
+        })*
+    };
+}
+
+from_number!(f32, i32, u32, i64, u64, usize);
+
+struct Parser<'a> {
+    text: &'a str,
+    at: usize,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.at..];
+        self.at += rest.len() - rest.trim_start_matches([' ', '\t', '\n', '\r']).len();
+    }
+
+    fn peek(&self) -> Result<u8, JsonError> {
+        self.text.as_bytes().get(self.at).copied().ok_or(JsonError::Truncated)
+    }
+
+    fn unexpected(&self) -> JsonError {
+        if self.at >= self.text.len() {
+            JsonError::Truncated
+        } else {
+            JsonError::Unexpected(self.at)
+        }
+    }
+
+    fn eat(&mut self, literal: &str) -> bool {
+        if self.text[self.at..].starts_with(literal) {
+            self.at += literal.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, literal: &str) -> Result<(), JsonError> {
+        self.skip_whitespace();
+        if self.eat(literal) {
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    fn next_char(&mut self) -> Result<char, JsonError> {
+        let ch = self.text[self.at..].chars().next().ok_or(JsonError::Truncated)?;
+        self.at += ch.len_utf8();
+        Ok(ch)
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value, JsonError> {
+        if depth > MAX_DEPTH {
+            return Err(JsonError::TooDeep);
+        }
+        self.skip_whitespace();
+        match self.peek()? {
+            b'{' => self.object(depth),
+            b'[' => self.array(depth),
+            b'"' => self.string().map(Value::String),
+            b't' if self.eat("true") => Ok(Value::Bool(true)),
+            b'f' if self.eat("false") => Ok(Value::Bool(false)),
+            b'n' if self.eat("null") => Ok(Value::Null),
+            b'-' | b'0'..=b'9' => self.number(),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn object(&mut self, depth: usize) -> Result<Value, JsonError> {
+        self.at += 1;
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.eat("}") {
+            return Ok(Value::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            let name = self.string()?;
+            self.expect(":")?;
+            members.push((name, self.value(depth + 1)?));
+            self.skip_whitespace();
+            if self.eat("}") {
+                return Ok(Value::Object(members));
+            }
+            self.expect(",")?;
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<Value, JsonError> {
+        self.at += 1;
+        let mut elements = Vec::new();
+        self.skip_whitespace();
+        if self.eat("]") {
+            return Ok(Value::Array(elements));
+        }
+        loop {
+            elements.push(self.value(depth + 1)?);
+            self.skip_whitespace();
+            if self.eat("]") {
+                return Ok(Value::Array(elements));
+            }
+            self.expect(",")?;
+        }
+    }
+
+    fn hex4(&mut self) -> Result<u32, JsonError> {
+        let digits = self.text.get(self.at..self.at + 4).ok_or(JsonError::Truncated)?;
+        let code = u32::from_str_radix(digits, 16).map_err(|_| JsonError::Unexpected(self.at))?;
+        self.at += 4;
+        Ok(code)
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        if !self.eat("\"") {
+            return Err(self.unexpected());
+        }
+        let mut text = String::new();
+        loop {
+            let start = self.at;
+            match self.next_char()? {
+                '"' => return Ok(text),
+                '\\' => {
+                    let escaped = self.next_char()?;
+                    text.push(match escaped {
+                        '"' | '\\' | '/' => escaped,
+                        'n' => '\n',
+                        't' => '\t',
+                        'r' => '\r',
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        'u' => {
+                            let mut code = self.hex4()?;
+                            // A surrogate pair is one character
+                            if (0xD800..0xDC00).contains(&code) && self.eat("\\u") {
+                                let low = self.hex4()?;
+                                code = 0x10000 + ((code - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF);
+                            }
+                            char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER)
+                        }
+                        _ => return Err(JsonError::Unexpected(start)),
+                    });
+                }
+                ch if (ch as u32) < 0x20 => return Err(JsonError::Unexpected(start)),
+                ch => text.push(ch),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, JsonError> {
+        let start = self.at;
+        let rest = &self.text.as_bytes()[self.at..];
+        let length = rest.iter().take_while(|byte| matches!(byte, b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')).count();
+        self.at += length;
+        self.text[start..self.at].parse().map(Value::Number).map_err(|_| JsonError::Unexpected(start))
+    }
+}
+
+// The value the whole text holds; anything but whitespace after it is an
+// error
+pub fn parse(text: &str) -> Result<Value, JsonError> {
+    let mut parser = Parser { text, at: 0 };
+    let value = parser.value(0)?;
+    parser.skip_whitespace();
+    if parser.at == text.len() {
+        Ok(value)
+    } else {
+        Err(JsonError::Unexpected(parser.at))
+    }
+}
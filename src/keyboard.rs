@@ -1,27 +1,8 @@
 // src/keyboard.rs
 use alloc::vec::Vec;
 
-#[derive(Clone, Copy, PartialEq)]
-pub enum Key {
-    A, B, C, D, E, F, G, H, I, J, K, L, M,
-    N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
-    Digit0, Digit1, Digit2, Digit3, Digit4,
-    Digit5, Digit6, Digit7, Digit8, Digit9,
-    Space, Enter, Backspace, Tab, Escape,
-    LeftShift, RightShift, LeftCtrl, RightCtrl,
-    LeftAlt, RightAlt, LeftCmd, RightCmd,
-    ArrowUp, ArrowDown, ArrowLeft, ArrowRight,
-    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
-}
-
-pub struct KeyEvent {
-    pub key: Key,
-    pub pressed: bool,
-    pub shift: bool,
-    pub ctrl: bool,
-    pub alt: bool,
-    pub cmd: bool,
-}
+// Shared with apps, which get them in events
+pub use rustos_sdk::event::{Key, KeyEvent, DIGITS, LETTERS};
 
 pub struct Keyboard {
     pressed_keys: Vec<Key>,
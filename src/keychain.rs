@@ -0,0 +1,297 @@
+// src/keychain.rs
+// The login keychain: passwords and other secrets apps keep for the user,
+// each under a service and an account, like Mail's under com.rustos.mail
+// and the mail user name. They're kept in the user's Library encrypted
+// with XChaCha20-Poly1305 under a key stretched from the keychain password
+// with PBKDF2-HMAC-SHA256, the salt and every nonce coming from the entropy
+// module. A wrong password or a damaged file fails the tag check. The
+// desktop unlocks it when the session starts; there's no login window yet,
+// so that uses the account's empty password and a keychain given its own
+// password stays locked until unlocked in Keychain Access or with
+// `security unlock-keychain`. While locked nothing can be read or stored.
+use crate::entropy;
+use crate::users;
+use crate::vfs::{self, VfsError};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use chacha20poly1305::aead::{AeadInPlace, KeyInit};
+use chacha20poly1305::{Key, Tag, XChaCha20Poly1305, XNonce};
+use hmac::{Mac, SimpleHmac};
+use sha2::Sha256;
+use crate::sync::Mutex;
+
+const MAGIC: &[u8; 4] = b"RKCH";
+const VERSION: u32 = 1;
+// PBKDF2 rounds for new keychains; a keychain keeps the count it was made with
+const ROUNDS: u32 = 20_000;
+// Counts read from a file outside these are taken as damage: too few to
+// trust, or so many unlocking would hang the desktop
+const MIN_ROUNDS: u32 = 1_000;
+const MAX_ROUNDS: u32 = 200_000;
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const TAG_LEN: usize = 16;
+// Magic, version, rounds, salt and nonce, all covered by the tag; the tag
+// follows, then the sealed items
+const SALT_AT: usize = 12;
+const NONCE_AT: usize = SALT_AT + SALT_LEN;
+const TAG_AT: usize = NONCE_AT + NONCE_LEN;
+const HEADER_LEN: usize = TAG_AT + TAG_LEN;
+// The account's password until there's a login window
+const LOGIN_PASSWORD: &str = "";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeychainError {
+    Locked,
+    // Or the file was changed; the tag can't tell them apart
+    WrongPassword,
+    Damaged,
+    NotFound,
+    Vfs(VfsError),
+}
+
+impl From<VfsError> for KeychainError {
+    fn from(err: VfsError) -> Self {
+        KeychainError::Vfs(err)
+    }
+}
+
+struct Item {
+    service: String,
+    account: String,
+    secret: String,
+}
+
+struct Unlocked {
+    key: [u8; KEY_LEN],
+    salt: [u8; SALT_LEN],
+    rounds: u32,
+    items: Vec<Item>,
+}
+
+static KEYCHAIN: Mutex<Option<Unlocked>> = Mutex::new(None);
+
+fn path() -> String {
+    format!("/Users/{}/Library/Keychains/login.keychain", users::user_name(users::current_uid()))
+}
+
+// PBKDF2-HMAC-SHA256 with a single output block, which is the key
+fn derive_key(password: &str, salt: &[u8], rounds: u32) -> [u8; KEY_LEN] {
+    let mac = <SimpleHmac<Sha256> as Mac>::new_from_slice(password.as_bytes()).unwrap();
+    let mut block = mac.clone();
+    block.update(salt);
+    block.update(&1u32.to_be_bytes());
+    let mut u: [u8; KEY_LEN] = block.finalize().into_bytes().into();
+    let mut key = u;
+    for _ in 1..rounds {
+        let mut next = mac.clone();
+        next.update(&u);
+        u = next.finalize().into_bytes().into();
+        for (k, byte) in key.iter_mut().zip(u) {
+            *k ^= byte;
+        }
+    }
+    key
+}
+
+// Overwritten before the memory goes back to the heap
+fn wipe(text: &mut String) {
+    // Zero bytes are still valid UTF-8
+    unsafe { text.as_bytes_mut().fill(0) };
+    text.clear();
+}
+
+fn wipe_items(items: &mut Vec<Item>) {
+    for item in items.iter_mut() {
+        wipe(&mut item.secret);
+    }
+    items.clear();
+}
+
+fn put_field(buf: &mut Vec<u8>, field: &str) {
+    buf.extend_from_slice(&(field.len() as u32).to_le_bytes());
+    buf.extend_from_slice(field.as_bytes());
+}
+
+fn take_field(buf: &[u8], at: &mut usize) -> Option<String> {
+    let len = u32::from_le_bytes(buf.get(*at..*at + 4)?.try_into().ok()?) as usize;
+    let field = buf.get(*at + 4..*at + 4 + len)?;
+    *at += 4 + len;
+    String::from_utf8(field.to_vec()).ok()
+}
+
+fn decode(buf: &[u8]) -> Option<Vec<Item>> {
+    let mut items = Vec::new();
+    let mut at = 0;
+    while at < buf.len() {
+        let service = take_field(buf, &mut at)?;
+        let account = take_field(buf, &mut at)?;
+        let secret = take_field(buf, &mut at)?;
+        items.push(Item { service, account, secret });
+    }
+    Some(items)
+}
+
+// Seals the items under a fresh nonce and writes the file
+fn save(keychain: &Unlocked) -> Result<(), KeychainError> {
+    let mut file = Vec::new();
+    file.extend_from_slice(MAGIC);
+    file.extend_from_slice(&VERSION.to_le_bytes());
+    file.extend_from_slice(&keychain.rounds.to_le_bytes());
+    file.extend_from_slice(&keychain.salt);
+    let mut nonce = [0u8; NONCE_LEN];
+    entropy::fill(&mut nonce);
+    file.extend_from_slice(&nonce);
+    
+    let mut sealed = Vec::new();
+    for item in &keychain.items {
+        put_field(&mut sealed, &item.service);
+        put_field(&mut sealed, &item.account);
+        put_field(&mut sealed, &item.secret);
+    }
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(&keychain.key));
+    let tag = cipher.encrypt_in_place_detached(XNonce::from_slice(&nonce), &file, &mut sealed).map_err(|_| KeychainError::Damaged)?;
+    file.extend_from_slice(&tag);
+    file.extend_from_slice(&sealed);
+    
+    let path = path();
+    vfs::create_dir_all(vfs::parent_and_name(&path).0)?;
+    vfs::write_file(&path, &file)?;
+    Ok(())
+}
+
+// Reads the file with `password`; a keychain that doesn't exist yet is made
+fn open(password: &str) -> Result<Unlocked, KeychainError> {
+    let mut file = match vfs::read_file(&path()) {
+        Ok(file) => file,
+        Err(VfsError::NotFound) => {
+            let mut salt = [0u8; SALT_LEN];
+            entropy::fill(&mut salt);
+            let keychain = Unlocked { key: derive_key(password, &salt, ROUNDS), salt, rounds: ROUNDS, items: Vec::new() };
+            save(&keychain)?;
+            return Ok(keychain);
+        }
+        Err(err) => return Err(err.into()),
+    };
+    if file.len() < HEADER_LEN || &file[..4] != MAGIC || file[4..8] != VERSION.to_le_bytes() {
+        return Err(KeychainError::Damaged);
+    }
+    let rounds = u32::from_le_bytes([file[8], file[9], file[10], file[11]]);
+    if !(MIN_ROUNDS..=MAX_ROUNDS).contains(&rounds) {
+        return Err(KeychainError::Damaged);
+    }
+    let mut salt = [0u8; SALT_LEN];
+    salt.copy_from_slice(&file[SALT_AT..NONCE_AT]);
+    let key = derive_key(password, &salt, rounds);
+    
+    let (header, sealed) = file.split_at_mut(HEADER_LEN);
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(&key));
+    let nonce = XNonce::from_slice(&header[NONCE_AT..TAG_AT]);
+    let tag = Tag::from_slice(&header[TAG_AT..]);
+    cipher.decrypt_in_place_detached(nonce, &header[..TAG_AT], sealed, tag).map_err(|_| KeychainError::WrongPassword)?;
+    let items = decode(sealed);
+    sealed.fill(0);
+    Ok(Unlocked { key, salt, rounds, items: items.ok_or(KeychainError::Damaged)? })
+}
+
+pub fn unlock(password: &str) -> Result<(), KeychainError> {
+    let keychain = open(password)?;
+    lock();
+    *KEYCHAIN.lock() = Some(keychain);
+    Ok(())
+}
+
+// Called as the desktop session starts
+pub fn unlock_at_login() -> Result<(), KeychainError> {
+    unlock(LOGIN_PASSWORD)
+}
+
+// Forgets the key and every secret until unlocked again
+pub fn lock() {
+    if let Some(mut keychain) = KEYCHAIN.lock().take() {
+        keychain.key.fill(0);
+        wipe_items(&mut keychain.items);
+    }
+}
+
+pub fn is_unlocked() -> bool {
+    KEYCHAIN.lock().is_some()
+}
+
+pub fn get(service: &str, account: &str) -> Result<String, KeychainError> {
+    let keychain = KEYCHAIN.lock();
+    let keychain = keychain.as_ref().ok_or(KeychainError::Locked)?;
+    keychain.items.iter()
+        .find(|item| item.service == service && item.account == account)
+        .map(|item| item.secret.clone())
+        .ok_or(KeychainError::NotFound)
+}
+
+// Adds the secret, or replaces the one kept under the same service and
+// account. If the file can't be written the keychain stays as it was.
+pub fn set(service: &str, account: &str, secret: &str) -> Result<(), KeychainError> {
+    let mut keychain = KEYCHAIN.lock();
+    let keychain = keychain.as_mut().ok_or(KeychainError::Locked)?;
+    match keychain.items.iter().position(|item| item.service == service && item.account == account) {
+        Some(index) => {
+            let mut old = core::mem::replace(&mut keychain.items[index].secret, String::from(secret));
+            if let Err(err) = save(keychain) {
+                let mut new = core::mem::replace(&mut keychain.items[index].secret, old);
+                wipe(&mut new);
+                return Err(err);
+            }
+            wipe(&mut old);
+        }
+        None => {
+            keychain.items.push(Item { service: String::from(service), account: String::from(account), secret: String::from(secret) });
+            if let Err(err) = save(keychain) {
+                if let Some(mut item) = keychain.items.pop() {
+                    wipe(&mut item.secret);
+                }
+                return Err(err);
+            }
+        }
+    }
+    Ok(())
+}
+
+// Like set, leaves the keychain as it was if the file can't be written
+pub fn remove(service: &str, account: &str) -> Result<(), KeychainError> {
+    let mut keychain = KEYCHAIN.lock();
+    let keychain = keychain.as_mut().ok_or(KeychainError::Locked)?;
+    let index = keychain.items.iter()
+        .position(|item| item.service == service && item.account == account)
+        .ok_or(KeychainError::NotFound)?;
+    let mut item = keychain.items.remove(index);
+    if let Err(err) = save(keychain) {
+        keychain.items.insert(index, item);
+        return Err(err);
+    }
+    wipe(&mut item.secret);
+    Ok(())
+}
+
+// Every item's service and account, in the order they were added; empty
+// while locked
+pub fn items() -> Vec<(String, String)> {
+    KEYCHAIN.lock().iter()
+        .flat_map(|keychain| keychain.items.iter())
+        .map(|item| (item.service.clone(), item.account.clone()))
+        .collect()
+}
+
+// Seals the keychain under a new password, with a new salt; `old` has to
+// open it. Leaves it unlocked.
+pub fn change_password(old: &str, new: &str) -> Result<(), KeychainError> {
+    let mut keychain = open(old)?;
+    keychain.key.fill(0);
+    entropy::fill(&mut keychain.salt);
+    keychain.rounds = ROUNDS;
+    keychain.key = derive_key(new, &keychain.salt, ROUNDS);
+    save(&keychain)?;
+    lock();
+    *KEYCHAIN.lock() = Some(keychain);
+    Ok(())
+}
\ No newline at end of file
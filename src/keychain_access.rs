@@ -0,0 +1,193 @@
+// src/keychain_access.rs
+// Keychain Access: what the login keychain holds, an item per row with its
+// service and account. Passwords stay hidden until Show Password, one item
+// at a time. The selected item can be deleted, and Lock forgets the key
+// until the keychain password is typed in again.
+use crate::graphics::{Graphics, Color};
+use crate::keyboard::{Key, KeyEvent};
+use crate::keychain::{self, KeychainError};
+use crate::widgets::{Button, TextField};
+use alloc::format;
+use alloc::string::String;
+use crate::sync::Mutex;
+
+const MARGIN: usize = 12;
+const TOOLBAR_Y: usize = 8;
+const HEADER_Y: usize = 44;
+const ROWS_Y: usize = 62;
+const ROW_HEIGHT: usize = 20;
+const ACCOUNT_X: usize = 180;
+const PASSWORD_X: usize = 310;
+// The password prompt while locked
+const FIELD_Y: usize = 62;
+const FIELD_WIDTH: usize = 220;
+const HIDDEN: &str = "••••••••";
+
+const LOCK: Button = Button::new("Lock");
+const UNLOCK: Button = Button::new("Unlock");
+const DELETE: Button = Button::new("Delete");
+
+struct KeychainAccessState {
+    password: TextField,
+    selected: Option<usize>,
+    // Showing the selected item's password
+    revealed: bool,
+    status: Option<String>,
+}
+
+static STATE: Mutex<KeychainAccessState> = Mutex::new(KeychainAccessState {
+    password: TextField::secure("Password"),
+    selected: None,
+    revealed: false,
+    status: None,
+});
+
+fn show_button(revealed: bool) -> Button {
+    Button::new(if revealed { "Hide Password" } else { "Show Password" })
+}
+
+fn message(err: KeychainError) -> String {
+    match err {
+        KeychainError::WrongPassword => String::from("The password is incorrect"),
+        KeychainError::Damaged => String::from("The keychain file is damaged"),
+        KeychainError::Locked => String::from("The keychain is locked"),
+        KeychainError::NotFound => String::from("The item is gone"),
+        KeychainError::Vfs(err) => format!("Couldn't read the keychain: {:?}", err),
+    }
+}
+
+fn unlock(state: &mut KeychainAccessState) {
+    state.status = keychain::unlock(&state.password.text).err().map(message);
+    state.password.clear();
+    state.selected = None;
+    state.revealed = false;
+}
+
+// Into the password while the keychain is locked
+pub fn insert_text(ch: char) {
+    if !keychain::is_unlocked() {
+        STATE.lock().password.insert(ch);
+    }
+}
+
+pub fn handle_key(event: &KeyEvent) {
+    let mut state = STATE.lock();
+    if !keychain::is_unlocked() {
+        match event.key {
+            Key::Enter => unlock(&mut state),
+            Key::Backspace => state.password.backspace(),
+            key => {
+                let ch = key.to_char().or_else(|| key.to_digit().and_then(|digit| char::from_digit(digit, 10)));
+                if let Some(ch) = ch {
+                    state.password.insert(if event.shift { ch.to_ascii_uppercase() } else { ch });
+                }
+            }
+        }
+        return;
+    }
+    let count = keychain::items().len();
+    let selected = match (event.key, state.selected) {
+        (Key::ArrowUp, Some(index)) => Some(index.saturating_sub(1)),
+        (Key::ArrowDown, Some(index)) => Some((index + 1).min(count.saturating_sub(1))),
+        (Key::ArrowUp | Key::ArrowDown, None) if count > 0 => Some(0),
+        _ => return,
+    };
+    if selected != state.selected {
+        state.selected = selected;
+        state.revealed = false;
+    }
+}
+
+// `x` and `y` are relative to the window content
+pub fn click(x: usize, y: usize) {
+    let mut state = STATE.lock();
+    let state = &mut *state;
+    if !keychain::is_unlocked() {
+        state.password.is_focused = (MARGIN..MARGIN + FIELD_WIDTH).contains(&x) && (FIELD_Y..FIELD_Y + TextField::HEIGHT).contains(&y);
+        if UNLOCK.contains(x, y, MARGIN + FIELD_WIDTH + 8, FIELD_Y) {
+            unlock(state);
+        }
+        return;
+    }
+    
+    let show = show_button(state.revealed);
+    let show_x = MARGIN + LOCK.width() + 8;
+    let delete_x = show_x + show.width() + 8;
+    if LOCK.contains(x, y, MARGIN, TOOLBAR_Y) {
+        keychain::lock();
+        state.selected = None;
+        state.revealed = false;
+        state.status = None;
+    } else if show.contains(x, y, show_x, TOOLBAR_Y) && state.selected.is_some() {
+        state.revealed = !state.revealed;
+    } else if DELETE.contains(x, y, delete_x, TOOLBAR_Y) {
+        let items = keychain::items();
+        if let Some((service, account)) = state.selected.and_then(|index| items.get(index)) {
+            state.status = keychain::remove(service, account).err().map(message);
+            state.selected = None;
+            state.revealed = false;
+        }
+    } else if y >= ROWS_Y {
+        let index = (y - ROWS_Y) / ROW_HEIGHT;
+        if index < keychain::items().len() && state.selected != Some(index) {
+            state.selected = Some(index);
+            state.revealed = false;
+        }
+    }
+}
+
+pub fn draw(graphics: &mut Graphics, x: usize, y: usize, width: usize, _height: usize) {
+    let state = STATE.lock();
+    if !keychain::is_unlocked() {
+        graphics.draw_text("🔒 The login keychain is locked.", x + MARGIN, y + 20, Color::BLACK);
+        graphics.draw_text("Enter the keychain password to unlock it.", x + MARGIN, y + 40, Color::GRAY);
+        state.password.draw(graphics, x + MARGIN, y + FIELD_Y, FIELD_WIDTH);
+        UNLOCK.draw(graphics, x + MARGIN + FIELD_WIDTH + 8, y + FIELD_Y);
+        if let Some(status) = &state.status {
+            graphics.draw_text(status, x + MARGIN, y + FIELD_Y + 32, Color::RED);
+        }
+        return;
+    }
+    
+    let show = show_button(state.revealed);
+    LOCK.draw(graphics, x + MARGIN, y + TOOLBAR_Y);
+    show.draw(graphics, x + MARGIN + LOCK.width() + 8, y + TOOLBAR_Y);
+    DELETE.draw(graphics, x + MARGIN + LOCK.width() + show.width() + 16, y + TOOLBAR_Y);
+    
+    graphics.draw_rect(x + 1, y + HEADER_Y - 4, width - 2, 16, Color::new(245, 245, 247));
+    for (label, column) in [("Name", MARGIN), ("Account", ACCOUNT_X), ("Password", PASSWORD_X)] {
+        graphics.draw_text(label, x + column, y + HEADER_Y, Color::GRAY);
+    }
+    
+    let items = keychain::items();
+    if items.is_empty() {
+        let lines = [
+            "No items. Apps keep their passwords here, or in Terminal:",
+            "",
+            "security add-generic-password -s service -a name -w pw",
+        ];
+        for (i, line) in lines.iter().enumerate() {
+            graphics.draw_text(line, x + MARGIN, y + ROWS_Y + 8 + i * 16, Color::GRAY);
+        }
+    }
+    for (i, (service, account)) in items.iter().enumerate() {
+        let row_y = y + ROWS_Y + i * ROW_HEIGHT;
+        let color = if state.selected == Some(i) {
+            graphics.draw_rect(x + 1, row_y, width - 2, ROW_HEIGHT, Color::BLUE);
+            Color::WHITE
+        } else {
+            Color::BLACK
+        };
+        let password = if state.selected == Some(i) && state.revealed {
+            keychain::get(service, account).unwrap_or_default()
+        } else {
+            String::from(HIDDEN)
+        };
+        graphics.draw_text(&format!("🔑 {}", service), x + MARGIN, row_y + 6, color);
+        graphics.draw_text(account, x + ACCOUNT_X, row_y + 6, color);
+        graphics.draw_text(&password, x + PASSWORD_X, row_y + 6, color);
+    }
+    if let Some(status) = &state.status {
+        graphics.draw_text(status, x + MARGIN, y + ROWS_Y + items.len().max(3) * ROW_HEIGHT + 12, Color::RED);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,249 @@
+// src/log.rs
+// Leveled kernel logging. Every line goes to the serial port and into an
+// in-memory ring buffer that `dmesg`, the Console app and crash reports
+// read back. Lines carry sequence numbers so a reader that fell behind the
+// ring can tell how many it missed. Recording never allocates, so it is
+// safe from interrupt handlers and the allocator's neighbours.
+use crate::{clock, serial_print};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicU8, Ordering};
+use crate::sync::Mutex;
+use x86_64::instructions::interrupts;
+
+// Bytes of log kept in memory. Override at build time with
+// LOG_BUFFER_SIZE=<bytes>.
+pub const BUFFER_SIZE: usize = match option_env!("LOG_BUFFER_SIZE") {
+    Some(size) => parse_size(size),
+    None => 16 * 1024,
+};
+// Longer lines are cut off in the buffer (serial still gets all of it)
+const MAX_LINE: usize = 240;
+// seq: u64, time: u64, level: u8, len: u8
+const HEADER: usize = 18;
+
+const fn parse_size(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    let mut value = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        assert!(bytes[i].is_ascii_digit(), "LOG_BUFFER_SIZE must be a number of bytes");
+        value = value * 10 + (bytes[i] - b'0') as usize;
+        i += 1;
+    }
+    assert!(value >= HEADER + MAX_LINE, "LOG_BUFFER_SIZE is too small");
+    value
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl Level {
+    pub const ALL: [Level; 4] = [Level::Error, Level::Warn, Level::Info, Level::Debug];
+    
+    pub fn name(self) -> &'static str {
+        match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+        }
+    }
+    
+    pub fn from_name(name: &str) -> Option<Level> {
+        Level::ALL.iter().copied().find(|level| level.name().eq_ignore_ascii_case(name))
+    }
+    
+    fn from_u8(value: u8) -> Level {
+        Level::ALL.get(value as usize).copied().unwrap_or(Level::Debug)
+    }
+}
+
+// Lines more detailed than this are dropped; set from the boot arguments
+static MAX_LEVEL: AtomicU8 = AtomicU8::new(Level::Debug as u8);
+
+pub fn set_max_level(level: Level) {
+    MAX_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+#[derive(Debug, Clone)]
+pub struct LogLine {
+    pub seq: u64,
+    pub level: Level,
+    // Nanoseconds since boot
+    pub time: u64,
+    pub text: String,
+}
+
+impl LogLine {
+    // "[   12.345678] WARN  text", as on the serial console
+    pub fn format(&self) -> String {
+        let mut line = String::new();
+        let _ = write!(line, "[{:5}.{:06}] {:<5} {}", self.time / 1_000_000_000, self.time / 1000 % 1_000_000, self.level.name(), self.text);
+        line
+    }
+}
+
+// Records packed back to back in a byte ring, oldest evicted first
+struct Ring {
+    data: [u8; BUFFER_SIZE],
+    // Offset of the oldest record and of the next write
+    head: usize,
+    tail: usize,
+    used: usize,
+    // Sequence numbers of the oldest record and of the next one written
+    first_seq: u64,
+    next_seq: u64,
+}
+
+static RING: Mutex<Ring> = Mutex::new(Ring {
+    data: [0; BUFFER_SIZE],
+    head: 0,
+    tail: 0,
+    used: 0,
+    first_seq: 1,
+    next_seq: 1,
+});
+
+impl Ring {
+    fn put(&mut self, offset: usize, bytes: &[u8]) {
+        for (i, byte) in bytes.iter().enumerate() {
+            self.data[(offset + i) % BUFFER_SIZE] = *byte;
+        }
+    }
+    
+    fn get(&self, offset: usize, out: &mut [u8]) {
+        for (i, byte) in out.iter_mut().enumerate() {
+            *byte = self.data[(offset + i) % BUFFER_SIZE];
+        }
+    }
+    
+    fn record_len(&self, offset: usize) -> usize {
+        let mut len = [0u8; 1];
+        self.get(offset + HEADER - 1, &mut len);
+        HEADER + len[0] as usize
+    }
+    
+    fn push(&mut self, level: Level, time: u64, text: &[u8]) {
+        let size = HEADER + text.len();
+        while BUFFER_SIZE - self.used < size {
+            let evicted = self.record_len(self.head);
+            self.head = (self.head + evicted) % BUFFER_SIZE;
+            self.used -= evicted;
+            self.first_seq += 1;
+        }
+        
+        let mut header = [0u8; HEADER];
+        header[..8].copy_from_slice(&self.next_seq.to_le_bytes());
+        header[8..16].copy_from_slice(&time.to_le_bytes());
+        header[16] = level as u8;
+        header[17] = text.len() as u8;
+        self.put(self.tail, &header);
+        self.put(self.tail + HEADER, text);
+        self.tail = (self.tail + size) % BUFFER_SIZE;
+        self.used += size;
+        self.next_seq += 1;
+    }
+    
+    // Lines with sequence numbers from `seq` on
+    fn read_from(&self, seq: u64) -> Vec<LogLine> {
+        let mut lines = Vec::new();
+        let mut offset = self.head;
+        for current in self.first_seq..self.next_seq {
+            let mut header = [0u8; HEADER];
+            self.get(offset, &mut header);
+            let len = header[17] as usize;
+            if current >= seq {
+                let mut text = [0u8; MAX_LINE];
+                self.get(offset + HEADER, &mut text[..len]);
+                lines.push(LogLine {
+                    seq: current,
+                    level: Level::from_u8(header[16]),
+                    time: u64::from_le_bytes(header[8..16].try_into().unwrap()),
+                    text: String::from_utf8_lossy(&text[..len]).into_owned(),
+                });
+            }
+            offset = (offset + HEADER + len) % BUFFER_SIZE;
+        }
+        lines
+    }
+}
+
+// Formats into a fixed buffer, cutting off at a char boundary
+struct LineWriter {
+    text: [u8; MAX_LINE],
+    len: usize,
+}
+
+impl Write for LineWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut take = s.len().min(MAX_LINE - self.len);
+        while !s.is_char_boundary(take) {
+            take -= 1;
+        }
+        self.text[self.len..self.len + take].copy_from_slice(&s.as_bytes()[..take]);
+        self.len += take;
+        Ok(())
+    }
+}
+
+#[doc(hidden)]
+pub fn _log(level: Level, args: fmt::Arguments) {
+    if level as u8 > MAX_LEVEL.load(Ordering::Relaxed) {
+        return;
+    }
+    let time = clock::nanos();
+    serial_print!("[{:5}.{:06}] {:<5} {}\n", time / 1_000_000_000, time / 1000 % 1_000_000, level.name(), args);
+    
+    let mut line = LineWriter { text: [0; MAX_LINE], len: 0 };
+    let _ = line.write_fmt(args);
+    interrupts::without_interrupts(|| RING.lock().push(level, time, &line.text[..line.len]));
+}
+
+// Lines from sequence number `seq` on, plus how many lines between `seq`
+// and the oldest one still buffered were overwritten before being read
+pub fn read_since(seq: u64) -> (Vec<LogLine>, u64) {
+    interrupts::without_interrupts(|| {
+        let ring = RING.lock();
+        (ring.read_from(seq), ring.first_seq.saturating_sub(seq))
+    })
+}
+
+// The last `count` lines
+pub fn tail(count: usize) -> Vec<LogLine> {
+    let next = interrupts::without_interrupts(|| RING.lock().next_seq);
+    read_since(next.saturating_sub(count as u64)).0
+}
+
+#[macro_export]
+macro_rules! klog {
+    ($level:expr, $($arg:tt)*) => {
+        $crate::log::_log($level, format_args!($($arg)*))
+    };
+}
+
+#[macro_export]
+macro_rules! kerror {
+    ($($arg:tt)*) => ($crate::klog!($crate::log::Level::Error, $($arg)*));
+}
+
+#[macro_export]
+macro_rules! kwarn {
+    ($($arg:tt)*) => ($crate::klog!($crate::log::Level::Warn, $($arg)*));
+}
+
+#[macro_export]
+macro_rules! kinfo {
+    ($($arg:tt)*) => ($crate::klog!($crate::log::Level::Info, $($arg)*));
+}
+
+#[macro_export]
+macro_rules! kdebug {
+    ($($arg:tt)*) => ($crate::klog!($crate::log::Level::Debug, $($arg)*));
+}
\ No newline at end of file
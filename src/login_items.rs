@@ -0,0 +1,133 @@
+// src/login_items.rs
+// Apps that open by themselves when the desktop starts, kept in the
+// com.rustos.loginwindow preferences as one comma-separated list, like
+// "Mail, Calendar:hidden". An item marked hidden opens hidden, as after
+// Cmd+H. Items are added and removed by right-clicking the app's Dock icon,
+// and listed with their Hide setting in System Preferences.
+use crate::graphics::{Graphics, Color};
+use crate::kwarn;
+use crate::preferences;
+use crate::undo::{self, SetValue};
+use crate::vfs::VfsError;
+use crate::widgets::{Button, Checkbox};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::sync::Mutex;
+
+const DOMAIN: &str = "com.rustos.loginwindow";
+const KEY: &str = "LoginItems";
+const HIDDEN_SUFFIX: &str = ":hidden";
+
+// Where the list sits in the System Preferences window, below Sharing
+const PREFERENCE_X: usize = 20;
+const PREFERENCE_Y: usize = 296;
+const ROW_HEIGHT: usize = 20;
+const MAX_ROWS: usize = 3;
+const HIDE_X: usize = 200;
+const REMOVE_X: usize = 260;
+const REMOVE: Button = Button::new("Remove");
+
+#[derive(Debug, Clone)]
+pub struct LoginItem {
+    pub app: String,
+    pub hidden: bool,
+}
+
+// Read once at startup and written through on every change
+static ITEMS: Mutex<Vec<LoginItem>> = Mutex::new(Vec::new());
+
+fn parse(list: &str) -> Vec<LoginItem> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| match entry.strip_suffix(HIDDEN_SUFFIX) {
+            Some(app) => LoginItem { app: String::from(app.trim()), hidden: true },
+            None => LoginItem { app: String::from(entry), hidden: false },
+        })
+        .collect()
+}
+
+fn save(items: &[LoginItem]) -> Result<(), VfsError> {
+    let list: Vec<String> = items
+        .iter()
+        .map(|item| if item.hidden { item.app.clone() + HIDDEN_SUFFIX } else { item.app.clone() })
+        .collect();
+    preferences::set(DOMAIN, KEY, &list.join(", "))
+}
+
+// Replaces the whole list; how System Preferences changes it, undoably
+fn set_items(items: Vec<LoginItem>) -> Result<(), VfsError> {
+    let mut current = ITEMS.lock();
+    *current = items;
+    save(&current)
+}
+
+// The saved items, in the order they open
+pub fn load() -> Vec<LoginItem> {
+    let items = parse(&preferences::get(DOMAIN, KEY).unwrap_or_default());
+    *ITEMS.lock() = items.clone();
+    items
+}
+
+// Adds the app to the end of the list, or takes it off; true if it was added
+pub fn toggle(app: &str) -> bool {
+    let mut items = ITEMS.lock();
+    let added = match items.iter().position(|item| item.app == app) {
+        Some(index) => {
+            items.remove(index);
+            false
+        }
+        None => {
+            items.push(LoginItem { app: String::from(app), hidden: false });
+            true
+        }
+    };
+    if let Err(err) = save(&items) {
+        kwarn!("login items: cannot save: {:?}", err);
+    }
+    added
+}
+
+pub fn draw_preference(graphics: &mut Graphics, x: usize, y: usize) {
+    let items = ITEMS.lock();
+    let (x, y) = (x + PREFERENCE_X, y + PREFERENCE_Y);
+    graphics.draw_text("Login Items", x, y, Color::BLACK);
+    if items.is_empty() {
+        graphics.draw_text("Right-click an app in the Dock to add it", x, y + 20, Color::GRAY);
+        return;
+    }
+    if items.len() > MAX_ROWS {
+        graphics.draw_text(&format!("and {} more", items.len() - MAX_ROWS), x + 100, y, Color::GRAY);
+    }
+    for (i, item) in items.iter().take(MAX_ROWS).enumerate() {
+        let row_y = y + 18 + i * ROW_HEIGHT;
+        graphics.draw_text(&item.app, x, row_y + 5, Color::BLACK);
+        Checkbox { label: "Hide", checked: item.hidden }.draw(graphics, x + HIDE_X, row_y + 3);
+        REMOVE.draw(graphics, x + REMOVE_X, row_y);
+    }
+}
+
+// `x` and `y` are relative to the window content
+pub fn click_preference(x: usize, y: usize) {
+    let old = ITEMS.lock().clone();
+    let mut new = old.clone();
+    let rows = new.len().min(MAX_ROWS);
+    for i in 0..rows {
+        let row_y = PREFERENCE_Y + 18 + i * ROW_HEIGHT;
+        let hide = Checkbox::new("Hide");
+        let name = if hide.contains(x, y, PREFERENCE_X + HIDE_X, row_y + 3) {
+            new[i].hidden = !new[i].hidden;
+            "Hide Login Item"
+        } else if REMOVE.contains(x, y, PREFERENCE_X + REMOVE_X, row_y) {
+            new.remove(i);
+            "Remove Login Item"
+        } else {
+            continue;
+        };
+        if let Err(err) = undo::perform(preferences::APP, SetValue { name, set: set_items, old, new }) {
+            kwarn!("login items: cannot save: {:?}", err);
+        }
+        return;
+    }
+}
\ No newline at end of file
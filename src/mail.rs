@@ -0,0 +1,445 @@
+// src/mail.rs
+// The Mail app: one POP3 account, checked when the desktop starts, every
+// five minutes after, and on Get Mail or Cmd+Shift+N. Messages are listed
+// newest first beside the one selected; those not opened yet are unread
+// and counted in a badge on the Dock icon. Which messages have been read is kept in
+// the user's Library. The open message's text goes to the services, so a
+// link in it can be opened in Safari.
+//
+// The account is set in the com.rustos.mail preferences, for example from
+// Terminal with `defaults write com.rustos.mail Server pop.example.com`:
+// Server and User are needed; SSL (default true) and Port (default 995, or
+// 110 without SSL) are optional. The password is kept in the login
+// keychain under com.rustos.mail and the user name. A Password preference,
+// stored as plain text, is still read when the keychain has none.
+use crate::clock::Instant;
+use crate::dock::{self, Badge};
+use crate::graphics::{Graphics, Color};
+use crate::keyboard::{Key, KeyEvent};
+use crate::mime;
+use crate::net::NetError;
+use crate::pop3::{Account, Fetch, Mailbox, Pop3Error};
+use crate::share::Data;
+use crate::styled_text::{Block, Paragraph, Span, Style, StyledText, TextView, LINE_HEIGHT};
+use crate::tls::{self, TlsError};
+use crate::vfs::{self, VfsError};
+use crate::widgets::{Button, ScrollView};
+use crate::{keychain, kwarn, preferences, users};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::time::Duration;
+use crate::sync::Mutex;
+
+const DOMAIN: &str = "com.rustos.mail";
+const CHECK_EVERY: Duration = Duration::from_secs(5 * 60);
+
+const TOOLBAR_HEIGHT: usize = 30;
+const LIST_WIDTH: usize = 200;
+const ROW_HEIGHT: usize = 36;
+// Header lines above the message body
+const HEADER_HEIGHT: usize = 58;
+const BUTTON_X: usize = 10;
+const BUTTON_Y: usize = 6;
+
+struct Message {
+    uid: String,
+    from: String,
+    subject: String,
+    date: String,
+    // The body as plain text, for the services
+    text: String,
+    body: TextView,
+}
+
+struct Mail {
+    // Newest first
+    messages: Vec<Message>,
+    selected: Option<usize>,
+    // Unique IDs of the messages opened, read from disk on first use
+    seen: Option<Vec<String>>,
+    fetch: Option<Fetch>,
+    last_check: Option<Instant>,
+    // Whether the preferences named an account at the last check
+    has_account: bool,
+    // What the last check came to
+    status: String,
+    list: ScrollView,
+    get_mail: Button,
+}
+
+static MAIL: Mutex<Mail> = Mutex::new(Mail {
+    messages: Vec::new(),
+    selected: None,
+    seen: None,
+    fetch: None,
+    last_check: None,
+    has_account: false,
+    status: String::new(),
+    list: ScrollView::new(ROW_HEIGHT),
+    get_mail: Button::new("Get Mail"),
+});
+
+fn account() -> Option<Account> {
+    let secure = preferences::get_bool(DOMAIN, "SSL", true);
+    let port = preferences::get(DOMAIN, "Port").and_then(|port| port.parse().ok());
+    let user = preferences::get(DOMAIN, "User")?;
+    let password = keychain::get(DOMAIN, &user).ok().or_else(|| preferences::get(DOMAIN, "Password")).unwrap_or_default();
+    Some(Account {
+        server: preferences::get(DOMAIN, "Server")?,
+        port: port.unwrap_or(if secure { 995 } else { 110 }),
+        secure,
+        user,
+        password,
+    })
+}
+
+fn seen_path() -> String {
+    format!("/Users/{}/Library/Mail/Seen", users::user_name(users::current_uid()))
+}
+
+// One unique ID per line
+fn read_seen() -> Vec<String> {
+    match vfs::read_file(&seen_path()) {
+        Ok(data) => String::from_utf8_lossy(&data).lines().map(String::from).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn write_seen(seen: &[String]) -> Result<(), VfsError> {
+    let path = seen_path();
+    vfs::create_dir_all(vfs::parent_and_name(&path).0)?;
+    let text: String = seen.iter().map(|uid| format!("{}\n", uid)).collect();
+    vfs::write_file(&path, text.as_bytes())
+}
+
+// "Name <address>" shows as the name, a bare address as itself
+fn sender_name(from: &str) -> String {
+    match from.split_once('<') {
+        Some((name, address)) if name.trim().is_empty() => String::from(address.trim_end_matches('>')),
+        Some((name, _)) => String::from(name.trim().trim_matches('"')),
+        None => String::from(from.trim()),
+    }
+}
+
+// "Tue, 14 Oct 2026 09:12:33 +0000" shows as "14 Oct 2026 09:12"
+fn short_date(date: &str) -> String {
+    let fields: Vec<&str> = date.split_whitespace().skip_while(|field| field.ends_with(',')).collect();
+    match fields.as_slice() {
+        [day, month, year, time, ..] => format!("{} {} {} {}", day, month, year, time.get(..5).unwrap_or(time)),
+        _ => String::from(date),
+    }
+}
+
+// Plain text as paragraphs: blank lines separate them, and lines quoted
+// with '>' make quote blocks
+fn body_text(text: &str) -> StyledText {
+    let mut paragraphs = Vec::new();
+    let mut current: Option<(Block, String)> = None;
+    for line in text.lines().map(str::trim_end) {
+        let (block, line) = match line.strip_prefix('>') {
+            Some(quoted) => (Block::Quote, quoted.trim_start_matches(['>', ' '])),
+            None => (Block::Body, line),
+        };
+        match current.as_mut() {
+            Some((kind, text)) if *kind == block && !line.is_empty() => {
+                text.push(' ');
+                text.push_str(line);
+            }
+            _ => {
+                if let Some((kind, text)) = current.take() {
+                    paragraphs.push((kind, text));
+                }
+                if !line.is_empty() {
+                    current = Some((block, String::from(line)));
+                }
+            }
+        }
+    }
+    paragraphs.extend(current);
+    let paragraphs = paragraphs
+        .into_iter()
+        .map(|(block, text)| {
+            let mut paragraph = Paragraph::new(block, Vec::from([Span::new(&text, Style::default())]));
+            if block == Block::Quote {
+                paragraph.depth = 1;
+            }
+            paragraph
+        })
+        .collect();
+    StyledText { paragraphs }
+}
+
+fn parse_message(uid: String, data: &[u8]) -> Message {
+    let message = mime::Message::parse(data);
+    let text = message.text();
+    Message {
+        uid,
+        from: sender_name(message.header("From").unwrap_or("")),
+        subject: String::from(message.header("Subject").filter(|subject| !subject.is_empty()).unwrap_or("(No Subject)")),
+        date: short_date(message.header("Date").unwrap_or("")),
+        body: TextView::new(body_text(&text)),
+        text,
+    }
+}
+
+fn error_message(err: &Pop3Error) -> String {
+    match err {
+        Pop3Error::Net(NetError::NoSuchHost) => String::from("Can't find the mail server"),
+        Pop3Error::Net(NetError::NoInterface) => String::from("Not connected to the Internet"),
+        Pop3Error::Net(NetError::Refused) => String::from("The mail server refused the connection"),
+        Pop3Error::Net(NetError::TimedOut) => String::from("The mail server didn't respond"),
+        Pop3Error::Net(err) => format!("Connection failed: {:?}", err),
+        Pop3Error::Tls(TlsError::Certificate(problem)) => tls::certificate_problem(problem),
+        Pop3Error::Tls(_) => String::from("Couldn't set up a secure connection"),
+        Pop3Error::Server(text) => format!("The server said: {}", text),
+        Pop3Error::BadResponse => String::from("The server's reply didn't make sense"),
+        Pop3Error::TooLarge => String::from("A message is too large to download"),
+    }
+}
+
+impl Mail {
+    fn seen(&mut self) -> &mut Vec<String> {
+        self.seen.get_or_insert_with(read_seen)
+    }
+    
+    fn is_unread(&mut self, index: usize) -> bool {
+        let uid = self.messages[index].uid.clone();
+        !self.seen().contains(&uid)
+    }
+    
+    fn check(&mut self) {
+        self.last_check = Some(Instant::now());
+        if self.fetch.is_some() {
+            return;
+        }
+        let account = account();
+        self.has_account = account.is_some();
+        match account {
+            Some(account) => {
+                let known = self.messages.iter().map(|message| message.uid.clone()).collect();
+                self.fetch = Some(Fetch::new(account, known));
+                self.status = String::from("Checking for new mail...");
+            }
+            None => self.status = String::from("No account set up"),
+        }
+    }
+    
+    // Keeps the messages still on the server, adds the new ones, and keeps
+    // the selection on the same message
+    fn received(&mut self, mailbox: Mailbox) {
+        let selected = self.selected.map(|index| self.messages[index].uid.clone());
+        let mut old = core::mem::take(&mut self.messages);
+        let mut new = mailbox.messages;
+        for uid in mailbox.uids.iter().rev() {
+            if let Some(index) = old.iter().position(|message| message.uid == *uid) {
+                self.messages.push(old.swap_remove(index));
+            } else if let Some(index) = new.iter().position(|(new_uid, _)| new_uid == uid) {
+                let (uid, data) = new.swap_remove(index);
+                self.messages.push(parse_message(uid, &data));
+            }
+        }
+        self.selected = selected.and_then(|uid| self.messages.iter().position(|message| message.uid == uid));
+        let unread = self.update_badge();
+        self.status = match unread {
+            0 => String::from("No unread messages"),
+            1 => String::from("1 unread message"),
+            count => format!("{} unread messages", count),
+        };
+    }
+    
+    // Shows the unread count on the Dock icon and returns it
+    fn update_badge(&mut self) -> usize {
+        let unread = (0..self.messages.len()).filter(|&index| self.is_unread(index)).count();
+        dock::set_badge("Mail", (unread > 0).then_some(Badge::Count(unread)));
+        unread
+    }
+    
+    // Selecting a message marks it read
+    fn select(&mut self, index: usize, height: usize) {
+        if index >= self.messages.len() {
+            return;
+        }
+        self.selected = Some(index);
+        let rows = self.list.rows_per_page(list_height(height));
+        if index < self.list.offset {
+            self.list.offset = index;
+        } else if index >= self.list.offset + rows {
+            self.list.offset = index + 1 - rows;
+        }
+        let uid = self.messages[index].uid.clone();
+        if !self.seen().contains(&uid) {
+            self.seen().push(uid);
+            if let Err(err) = write_seen(self.seen()) {
+                kwarn!("mail: cannot save read messages: {:?}", err);
+            }
+            self.update_badge();
+        }
+    }
+}
+
+// Checks when due and moves a check along; call after `net::poll`
+pub fn poll() {
+    let mut mail = MAIL.lock();
+    let due = mail.last_check.map_or(true, |last| last.elapsed() >= CHECK_EVERY);
+    if due {
+        mail.check();
+    }
+    let result = match mail.fetch.as_mut() {
+        Some(fetch) => fetch.poll(),
+        None => return,
+    };
+    match result {
+        Some(Ok(mailbox)) => {
+            mail.fetch = None;
+            mail.received(mailbox);
+        }
+        Some(Err(err)) => {
+            mail.fetch = None;
+            kwarn!("mail: check failed: {:?}", err);
+            mail.status = error_message(&err);
+        }
+        None => {}
+    }
+}
+
+fn list_height(height: usize) -> usize {
+    height - TOOLBAR_HEIGHT
+}
+
+fn body_height(height: usize) -> usize {
+    height - TOOLBAR_HEIGHT - HEADER_HEIGHT - 8
+}
+
+fn body_width(width: usize) -> usize {
+    width - LIST_WIDTH
+}
+
+// The open message's text, for the services
+pub fn service_data() -> Option<Data> {
+    let mail = MAIL.lock();
+    let index = mail.selected?;
+    Some(Data::Text(mail.messages[index].text.clone()))
+}
+
+// `width` and `height` are the window content size, as passed to `draw`
+pub fn scroll(rows: isize, width: usize, height: usize) {
+    let mut mail = MAIL.lock();
+    if let Some(index) = mail.selected {
+        mail.messages[index].body.scroll_by(rows, body_width(width), body_height(height));
+    }
+}
+
+pub fn handle_key(event: &KeyEvent, width: usize, height: usize) {
+    let page = (body_height(height) / LINE_HEIGHT) as isize;
+    let mut mail = MAIL.lock();
+    let selected = mail.selected;
+    match event.key {
+        Key::N if event.cmd && event.shift => mail.check(),
+        Key::ArrowUp => mail.select(selected.map_or(0, |index| index.saturating_sub(1)), height),
+        Key::ArrowDown => mail.select(selected.map_or(0, |index| index + 1), height),
+        Key::Space => {
+            drop(mail);
+            scroll(if event.shift { -page } else { page }, width, height);
+        }
+        _ => {}
+    }
+}
+
+// Get Mail, as (x, y, width, height) in the content
+pub fn controls() -> Vec<(usize, usize, usize, usize)> {
+    vec![(BUTTON_X, BUTTON_Y, MAIL.lock().get_mail.width(), Button::HEIGHT)]
+}
+
+// `x` and `y` are relative to the window content
+pub fn click(x: usize, y: usize, height: usize) {
+    let mut mail = MAIL.lock();
+    if y < TOOLBAR_HEIGHT {
+        if mail.get_mail.contains(x, y, BUTTON_X, BUTTON_Y) {
+            mail.check();
+        }
+    } else if x < LIST_WIDTH {
+        let index = mail.list.offset + (y - TOOLBAR_HEIGHT) / ROW_HEIGHT;
+        mail.select(index, height);
+    }
+}
+
+fn truncated(text: &str, width: usize) -> String {
+    let columns = width / 8;
+    if text.chars().count() <= columns {
+        return String::from(text);
+    }
+    let mut text: String = text.chars().take(columns.saturating_sub(3)).collect();
+    text.push_str("...");
+    text
+}
+
+pub fn draw(graphics: &mut Graphics, x: usize, y: usize, width: usize, height: usize) {
+    let mut mail = MAIL.lock();
+    
+    // Toolbar
+    graphics.draw_rect(x + 1, y, width - 2, TOOLBAR_HEIGHT, Color::new(245, 245, 245));
+    graphics.draw_rect(x + 1, y + TOOLBAR_HEIGHT - 1, width - 2, 1, Color::new(210, 210, 210));
+    mail.get_mail.draw(graphics, x + BUTTON_X, y + BUTTON_Y);
+    let status_x = BUTTON_X + mail.get_mail.width() + 12;
+    let status = truncated(&mail.status, width - status_x - 10);
+    graphics.draw_text(&status, x + status_x, y + BUTTON_Y + 5, Color::GRAY);
+    
+    // Message list
+    let list_y = y + TOOLBAR_HEIGHT;
+    graphics.draw_rect(x + LIST_WIDTH, list_y, 1, list_height(height), Color::new(210, 210, 210));
+    let total = mail.messages.len();
+    let rows = mail.list.visible_rows(total, list_height(height));
+    for (row, index) in rows.enumerate() {
+        let row_y = list_y + row * ROW_HEIGHT;
+        if mail.selected == Some(index) {
+            graphics.draw_rect(x + 1, row_y, LIST_WIDTH - 1, ROW_HEIGHT, Color::new(220, 232, 252));
+        }
+        if mail.is_unread(index) {
+            graphics.draw_rounded_rect(x + 6, row_y + 8, 6, 6, Color::BLUE);
+        }
+        let message = &mail.messages[index];
+        graphics.draw_text(&truncated(&message.from, LIST_WIDTH - 26), x + 18, row_y + 6, Color::BLACK);
+        graphics.draw_text(&truncated(&message.subject, LIST_WIDTH - 26), x + 18, row_y + 20, Color::GRAY);
+        graphics.draw_rect(x + 18, row_y + ROW_HEIGHT - 1, LIST_WIDTH - 24, 1, Color::new(230, 230, 230));
+    }
+    mail.list.draw_scroller(graphics, x + LIST_WIDTH - 8, list_y, list_height(height), total);
+    
+    // The selected message
+    let body_x = x + LIST_WIDTH + 1;
+    let index = match mail.selected {
+        Some(index) => index,
+        None => {
+            let lines: &[&str] = if total > 0 {
+                &["No Message Selected"]
+            } else if !mail.has_account {
+                &[
+                    "No mail account is set up. In Terminal:",
+                    "",
+                    "defaults write com.rustos.mail Server host",
+                    "defaults write com.rustos.mail User name",
+                    "security add-generic-password",
+                    "    -s com.rustos.mail -a name -w pw",
+                ]
+            } else {
+                &["No Messages"]
+            };
+            for (i, line) in lines.iter().enumerate() {
+                graphics.draw_text(line, body_x + 16, list_y + 30 + i * 16, Color::GRAY);
+            }
+            return;
+        }
+    };
+    let message = &mut mail.messages[index];
+    let text_width = body_width(width) - 32;
+    let headers = [("From: ", &message.from), ("Subject: ", &message.subject), ("Date: ", &message.date)];
+    for (i, (label, value)) in headers.iter().enumerate() {
+        let line_y = list_y + 10 + i * 16;
+        graphics.draw_text(label, body_x + 16, line_y, Color::GRAY);
+        let label_width = label.len() * 8;
+        graphics.draw_text(&truncated(value, text_width - label_width), body_x + 16 + label_width, line_y, Color::BLACK);
+    }
+    graphics.draw_rect(body_x + 16, list_y + HEADER_HEIGHT, text_width, 1, Color::new(220, 220, 220));
+    message.body.draw(graphics, body_x, list_y + HEADER_HEIGHT + 8, body_width(width) - 1, body_height(height));
+}
\ No newline at end of file
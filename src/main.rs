@@ -2,24 +2,165 @@
 #![no_std]
 #![no_main]
 #![feature(custom_test_frameworks)]
+#![feature(abi_x86_interrupt)]
 #![test_runner(crate::test_runner)]
 #![reexport_test_harness_main = "test_main"]
 
 extern crate alloc;
 
+use bootloader::{entry_point, BootInfo};
 use core::panic::PanicInfo;
 
 mod vga_buffer;
+mod serial;
+mod log;
+mod bootargs;
 mod graphics;
+mod glyph_cache;
+mod icons;
+mod bmp;
+mod gif;
+mod chrome;
 mod desktop;
 mod window_manager;
 mod mouse;
+mod input;
+mod replay;
+mod trackpad;
 mod keyboard;
 mod allocator;
+mod sync;
+mod heap_profiler;
+mod frame_profiler;
+mod cpu_profiler;
+#[cfg(feature = "bench")]
+mod bench;
+#[cfg(feature = "ui-test")]
+mod ui_test;
+mod watchdog;
+mod crash;
+mod pool;
+mod memory;
+mod vm;
+mod address_space;
+mod task;
+mod stack;
+mod backtrace;
+mod symbols;
+mod gdt;
+mod interrupts;
+mod pci;
+mod block;
+mod virtio;
+mod virtio_blk;
+mod virtio_input;
+mod usb;
+mod uhci;
+mod usb_hid;
+mod virtio_net;
+mod net;
+mod socket;
+mod tcp;
+mod dns;
+mod tls_provider;
+mod tls;
+mod stream;
+mod http;
+mod html;
+mod mime;
+mod pop3;
+mod mail;
+mod remote_console;
+mod ata;
+mod block_cache;
+mod swap;
+mod vfs;
+mod rtc;
+mod cpu;
+mod system_info;
+mod clock;
+mod entropy;
+mod rand;
+mod users;
+mod preferences;
+mod file_associations;
+mod appearance;
+mod ext2;
+mod iso9660;
+mod fat32;
+mod partition;
+mod ramfs;
+mod tar;
+mod zip;
+mod archive;
+mod initramfs;
+mod pipe;
+mod posix;
+mod shell;
+mod terminal;
+mod terminal_profile;
+mod power;
 mod animations;
+mod transitions;
+mod screen_recording;
 mod notifications;
 mod spotlight;
 mod mission_control;
+mod activity_monitor;
+mod disk_utility;
+mod get_info;
+mod force_quit;
+mod widgets;
+mod window_menu;
+mod color_picker;
+mod undo;
+mod edit_menu;
+mod clipboard;
+mod finder;
+mod open_with;
+mod save_sheet;
+mod print;
+mod print_dialog;
+mod apps;
+mod login_items;
+mod bus;
+mod dock;
+mod console;
+mod snake;
+mod preview;
+mod styled_text;
+mod markdown;
+mod document_viewer;
+mod safari;
+mod services;
+mod focus;
+mod frame_rate;
+mod app_menu;
+mod share;
+mod quick_look;
+mod thumbnails;
+mod cpu_usage;
+mod energy_saver;
+mod hibernate;
+mod keychain;
+mod keychain_access;
+mod display_server;
+mod elf;
+mod userspace;
+mod boot_slots;
+mod acpi;
+mod hpet;
+mod apic;
+mod dma;
+mod app_switcher;
+mod desktop_icons;
+mod character_picker;
+mod do_not_disturb;
+mod reminders;
+mod json;
+mod weather;
+mod config;
+mod system_config;
 
 use desktop::Desktop;
 use graphics::{Color, Graphics};
@@ -28,32 +169,164 @@ static mut DESKTOP: Option<Desktop> = None;
 
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
+    // Panics in app drawing only take the app down
+    crash::handle_panic(info);
     println!("{}", info);
+    for address in backtrace::Frames::here().take(16) {
+        println!("  {}", symbols::Address(address));
+    }
+    crash::handle_kernel_panic(info);
     hlt_loop();
 }
 
-#[no_mangle]
-pub extern "C" fn _start() -> ! {
-    // Initialize graphics mode
+entry_point!(kernel_main);
+
+fn kernel_main(boot_info: &'static BootInfo) -> ! {
+    use services::{Service, Stage, Status};
+    use x86_64::VirtAddr;
+    
+    let mut logger = || {
+        bootargs::init();
+        if let Some(level) = bootargs::log_level() {
+            log::set_max_level(level);
+        }
+        kinfo!("RustOS {} (built {})", system_info::KERNEL_VERSION, system_info::BUILD_DATE);
+        kinfo!("bootargs: {}", bootargs::cmdline());
+        Ok(())
+    };
+    // Paging, the kernel heap and the frame allocator
+    let mut memory = || {
+        let phys_mem_offset = VirtAddr::new(boot_info.physical_memory_offset);
+        let mut mapper = unsafe { memory::init(phys_mem_offset) };
+        let mut frame_allocator = unsafe { memory::BootInfoFrameAllocator::init(&boot_info.memory_map) };
+        allocator::init_heap(&mut mapper, &mut frame_allocator).map_err(|_| "heap initialization failed")?;
+        memory::install_frame_allocator(frame_allocator);
+        Ok(())
+    };
+    // Processors, interrupt routing, power management and the HPET, from the
+    // firmware; ahead of the clock so it can pick the HPET
+    let mut acpi = || acpi::init().map_err(|err| err.message());
+    let mut interrupts = || {
+        interrupts::init_idt();
+        interrupts::init_pics();
+        if !interrupts::init_msi() {
+            kwarn!("apic: local APIC is off, devices stay on their legacy interrupt lines");
+        }
+        gdt::init();
+        Ok(())
+    };
+    // CPU features come first so everything after can pick a fast path;
+    // the timer interrupt starts once the clock is calibrated
+    let mut clock = || {
+        let cpu = cpu::init();
+        kinfo!("cpu: {} ({})", cpu.brand, cpu.features.names().join(", "));
+        clock::init();
+        x86_64::instructions::interrupts::enable();
+        Ok(())
+    };
+    // Boot assets unpacked into the root file system
+    let mut initramfs = || {
+        initramfs::init();
+        Ok(())
+    };
+    let mut pci = || {
+        pci::init();
+        Ok(())
+    };
+    let mut storage = || {
+        virtio_blk::init();
+        ata::init();
+        vfs::mount_block_devices();
+        swap::init();
+        Ok(())
+    };
+    let mut input = || {
+        virtio_input::init();
+        usb::init();
+        Ok(())
+    };
+    let mut network = || if net::init() { Ok(()) } else { Err("no network card") };
+    let mut remote_console = || {
+        if bootargs::safe_mode() {
+            return Err("off in safe mode");
+        }
+        remote_console::init();
+        Ok(())
+    };
+    let mut compositor = || {
+        if let Some((width, height)) = bootargs::video() {
+            if (width, height) != (graphics::SCREEN_WIDTH, graphics::SCREEN_HEIGHT) {
+                kwarn!("video: {}x{} isn't available, using {}x{}", width, height, graphics::SCREEN_WIDTH, graphics::SCREEN_HEIGHT);
+            }
+        }
+        Graphics::new().clear_screen(Color::new(240, 240, 245));
+        Ok(())
+    };
+    let mut apps = || {
+        replay::init();
+        let mut graphics = Graphics::new();
+        unsafe {
+            DESKTOP = Some(Desktop::new());
+            if let Some(ref mut desktop) = DESKTOP {
+                desktop.init(&mut graphics);
+            }
+        }
+        Ok(())
+    };
+    
+    let mut boot = [
+        Service { name: "logger", after: &[], required: true, start: &mut logger },
+        Service { name: "memory", after: &["logger"], required: true, start: &mut memory },
+        Service { name: "acpi", after: &["memory"], required: false, start: &mut acpi },
+        Service { name: "interrupts", after: &["memory"], required: true, start: &mut interrupts },
+        Service { name: "clock", after: &["interrupts"], required: true, start: &mut clock },
+        Service { name: "initramfs", after: &["clock"], required: false, start: &mut initramfs },
+        Service { name: "pci", after: &["clock"], required: false, start: &mut pci },
+        Service { name: "storage", after: &["initramfs", "pci"], required: false, start: &mut storage },
+        Service { name: "input", after: &["pci"], required: false, start: &mut input },
+        Service { name: "network", after: &["pci"], required: false, start: &mut network },
+        Service { name: "remote console", after: &["network", "storage"], required: false, start: &mut remote_console },
+        Service { name: "compositor", after: &["storage", "input"], required: true, start: &mut compositor },
+        Service { name: "apps", after: &["compositor"], required: true, start: &mut apps },
+    ];
+    // The splash shows the stages from when there's a screen to draw on
+    services::run(&mut boot, &mut |stages: &[Stage]| {
+        if stages.iter().any(|stage| stage.name == "compositor" && stage.status == Status::Started) {
+            services::draw_splash(&mut Graphics::new(), stages);
+        }
+    });
+    
+    #[cfg(feature = "bench")]
+    unsafe {
+        if let Some(ref mut desktop) = DESKTOP {
+            bench::run(desktop, &mut Graphics::new());
+        }
+    }
+    #[cfg(feature = "ui-test")]
+    unsafe {
+        if let Some(ref mut desktop) = DESKTOP {
+            ui_test::run(desktop, &mut Graphics::new());
+        }
+    }
+    run_desktop();
+}
+
+// Main event loop. The watchdog restarts it from the top after abandoning a
+// frame that hung.
+pub fn run_desktop() -> ! {
     let mut graphics = Graphics::new();
-    graphics.clear_screen(Color::new(240, 240, 245));
+    watchdog::arm();
     
-    // Initialize desktop environment
     unsafe {
-        DESKTOP = Some(Desktop::new());
         if let Some(ref mut desktop) = DESKTOP {
-            desktop.init(&mut graphics);
             desktop.draw(&mut graphics);
             
-            // Main event loop
             loop {
                 desktop.handle_events();
                 desktop.update(&mut graphics);
-                
-                // Small delay to prevent 100% CPU usage
-                for _ in 0..100000 {
-                    core::hint::spin_loop();
-                }
+                frame_profiler::end_frame();
+                watchdog::frame_completed();
+                frame_rate::wait(desktop.is_animating());
             }
         }
     }
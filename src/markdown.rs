@@ -0,0 +1,233 @@
+// src/markdown.rs
+// Parses the common subset of Markdown found in README files into a
+// StyledText: ATX headings, paragraphs, bullet and numbered lists (nested
+// by indentation), block quotes, fenced code blocks, horizontal rules, and
+// inline bold, italic, code spans and links. Anything else is shown as
+// plain text.
+use crate::styled_text::{Block, Paragraph, Span, Style, StyledText};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+// Leading spaces per list nesting level
+const LIST_INDENT: usize = 2;
+
+pub fn parse(source: &str) -> StyledText {
+    let mut document = StyledText::default();
+    // Text lines of the paragraph being collected, joined with spaces
+    let mut pending: Option<Paragraph> = None;
+    let mut pending_text = String::new();
+    let mut in_code = false;
+    
+    for raw in source.lines() {
+        let line = raw.trim_end();
+        let trimmed = line.trim_start();
+        
+        if trimmed.starts_with("```") {
+            finish(&mut pending, &mut pending_text, &mut document);
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            let text = line.replace('\t', "    ");
+            document.paragraphs.push(Paragraph::new(Block::Code, Vec::from([Span::new(&text, Style::default())])));
+            continue;
+        }
+        
+        if trimmed.is_empty() {
+            finish(&mut pending, &mut pending_text, &mut document);
+            continue;
+        }
+        
+        if is_rule(trimmed) {
+            finish(&mut pending, &mut pending_text, &mut document);
+            document.paragraphs.push(Paragraph::new(Block::Rule, Vec::new()));
+            continue;
+        }
+        
+        if let Some((level, text)) = heading(trimmed) {
+            finish(&mut pending, &mut pending_text, &mut document);
+            document.paragraphs.push(Paragraph::new(Block::Heading(level), parse_inline(text)));
+            continue;
+        }
+        
+        let depth = (line.len() - trimmed.len()) / LIST_INDENT;
+        if let Some((marker, text)) = list_item(trimmed) {
+            finish(&mut pending, &mut pending_text, &mut document);
+            let mut paragraph = Paragraph::new(Block::Body, Vec::new());
+            paragraph.depth = depth;
+            paragraph.marker = Some(marker);
+            pending = Some(paragraph);
+            pending_text.push_str(text);
+            continue;
+        }
+        
+        if let Some(text) = trimmed.strip_prefix('>') {
+            let continues = matches!(&pending, Some(p) if p.block == Block::Quote);
+            if !continues {
+                finish(&mut pending, &mut pending_text, &mut document);
+                let mut paragraph = Paragraph::new(Block::Quote, Vec::new());
+                paragraph.depth = 1;
+                pending = Some(paragraph);
+            }
+            pending_text.push(' ');
+            pending_text.push_str(text.trim_start());
+            continue;
+        }
+        
+        // Lazy continuation of the current paragraph, list item or quote
+        if pending.is_none() {
+            pending = Some(Paragraph::new(Block::Body, Vec::new()));
+        }
+        pending_text.push(' ');
+        pending_text.push_str(trimmed);
+    }
+    finish(&mut pending, &mut pending_text, &mut document);
+    document
+}
+
+// Ends the paragraph being collected, if any
+fn finish(pending: &mut Option<Paragraph>, text: &mut String, document: &mut StyledText) {
+    if let Some(mut paragraph) = pending.take() {
+        paragraph.spans = parse_inline(text.trim());
+        document.paragraphs.push(paragraph);
+    }
+    text.clear();
+}
+
+// Three or more of the same -, * or _, optionally spaced out
+fn is_rule(line: &str) -> bool {
+    let mut marks = line.chars().filter(|c| *c != ' ');
+    let first = marks.next();
+    matches!(first, Some('-' | '*' | '_')) && marks.clone().count() >= 2 && marks.all(|c| Some(c) == first)
+}
+
+fn heading(line: &str) -> Option<(u8, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if level == 0 || level > 6 {
+        return None;
+    }
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return None;
+    }
+    // Closing hashes are decoration
+    Some((level as u8, rest.trim().trim_end_matches('#').trim_end()))
+}
+
+fn list_item(line: &str) -> Option<(String, &str)> {
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(text) = line.strip_prefix(bullet) {
+            return Some((String::from("•"), text));
+        }
+    }
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 && digits < 10 {
+        let rest = &line[digits..];
+        if let Some(text) = rest.strip_prefix(". ").or_else(|| rest.strip_prefix(") ")) {
+            return Some((format!("{}.", &line[..digits]), text));
+        }
+    }
+    None
+}
+
+// Inline markup. Unmatched delimiters are kept as text.
+pub fn parse_inline(text: &str) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut style = Style::default();
+    let mut current = String::new();
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+    
+    while i < chars.len() {
+        let ch = chars[i];
+        let rest = &chars[i..];
+        match ch {
+            '\\' if i + 1 < chars.len() => {
+                current.push(chars[i + 1]);
+                i += 2;
+            }
+            '`' => match find(&chars, i + 1, &['`']) {
+                Some(end) => {
+                    flush(&mut current, &mut spans, style);
+                    let code: String = chars[i + 1..end].iter().collect();
+                    spans.push(Span::new(&code, Style { code: true, ..style }));
+                    i = end + 1;
+                }
+                None => {
+                    current.push(ch);
+                    i += 1;
+                }
+            },
+            '*' | '_' if rest.len() >= 2 && rest[1] == ch => {
+                let closes = style.bold;
+                if closes || find(&chars, i + 2, &[ch, ch]).is_some() {
+                    flush(&mut current, &mut spans, style);
+                    style.bold = !style.bold;
+                } else {
+                    current.push_str(&format!("{}{}", ch, ch));
+                }
+                i += 2;
+            }
+            '*' | '_' => {
+                // Underscores inside words are literal, as in snake_case
+                let inside_word = ch == '_'
+                    && i > 0
+                    && chars[i - 1].is_alphanumeric()
+                    && chars.get(i + 1).is_some_and(|c| c.is_alphanumeric());
+                if !inside_word && (style.italic || find(&chars, i + 1, &[ch]).is_some()) {
+                    flush(&mut current, &mut spans, style);
+                    style.italic = !style.italic;
+                } else {
+                    current.push(ch);
+                }
+                i += 1;
+            }
+            '[' => match link(&chars, i) {
+                Some((label, end)) => {
+                    flush(&mut current, &mut spans, style);
+                    for mut span in parse_inline(&label) {
+                        span.style.link = true;
+                        span.style.bold |= style.bold;
+                        span.style.italic |= style.italic;
+                        spans.push(span);
+                    }
+                    i = end;
+                }
+                None => {
+                    current.push(ch);
+                    i += 1;
+                }
+            },
+            _ => {
+                current.push(ch);
+                i += 1;
+            }
+        }
+    }
+    flush(&mut current, &mut spans, style);
+    spans
+}
+
+fn flush(current: &mut String, spans: &mut Vec<Span>, style: Style) {
+    if !current.is_empty() {
+        spans.push(Span::new(current, style));
+        current.clear();
+    }
+}
+
+// Index of the next occurrence of `pattern` at or after `from`
+fn find(chars: &[char], from: usize, pattern: &[char]) -> Option<usize> {
+    (from..chars.len()).find(|&i| chars[i..].starts_with(pattern))
+}
+
+// `[label](target)` starting at `start`: the label and the index after it.
+// The target isn't followed anywhere yet, so only the label is kept.
+fn link(chars: &[char], start: usize) -> Option<(String, usize)> {
+    let close = find(chars, start + 1, &[']'])?;
+    if chars.get(close + 1) != Some(&'(') {
+        return None;
+    }
+    let end = find(chars, close + 2, &[')'])?;
+    Some((chars[start + 1..close].iter().collect(), end + 1))
+}
\ No newline at end of file
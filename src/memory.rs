@@ -0,0 +1,349 @@
+// src/memory.rs
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use bootloader::bootinfo::{MemoryMap, MemoryRegion, MemoryRegionType};
+use core::sync::atomic::{AtomicU64, Ordering};
+use crate::sync::Mutex;
+use x86_64::{
+    registers::control::Cr3,
+    structures::paging::{
+        mapper::{MapToError, MappedFrame, TranslateResult},
+        FrameAllocator, Mapper, OffsetPageTable, Page, PageTable, PageTableFlags, PhysFrame,
+        Size4KiB, Translate,
+    },
+    PhysAddr, VirtAddr,
+};
+
+pub const PAGE_SIZE: u64 = 4096;
+
+static PHYSICAL_MEMORY_OFFSET: Mutex<Option<VirtAddr>> = Mutex::new(None);
+// The same, for code that can't take locks; NO_OFFSET until init
+static PHYSICAL_OFFSET: AtomicU64 = AtomicU64::new(NO_OFFSET);
+const NO_OFFSET: u64 = u64::MAX;
+static FRAME_ALLOCATOR: Mutex<Option<BootInfoFrameAllocator>> = Mutex::new(None);
+// Frames returned by unmapped pages, reused before asking the boot allocator
+static FREE_FRAMES: Mutex<Vec<PhysFrame>> = Mutex::new(Vec::new());
+// Page tables the bootloader set up; every address space shares their
+// kernel entries
+static KERNEL_PAGE_TABLE: Mutex<Option<PhysFrame>> = Mutex::new(None);
+// Owner counts for frames mapped by more than one address space after a
+// fork. Frames without an entry have a single owner.
+static FRAME_REFS: Mutex<BTreeMap<u64, usize>> = Mutex::new(BTreeMap::new());
+
+/// Initialize a new OffsetPageTable.
+///
+/// The complete physical memory must be mapped to virtual memory at the
+/// passed `physical_memory_offset`. This function must only be called once.
+pub unsafe fn init(physical_memory_offset: VirtAddr) -> OffsetPageTable<'static> {
+    *PHYSICAL_MEMORY_OFFSET.lock() = Some(physical_memory_offset);
+    PHYSICAL_OFFSET.store(physical_memory_offset.as_u64(), Ordering::Release);
+    *KERNEL_PAGE_TABLE.lock() = Some(Cr3::read().0);
+    let level_4_table = active_level_4_table(physical_memory_offset);
+    OffsetPageTable::new(level_4_table, physical_memory_offset)
+}
+
+unsafe fn active_level_4_table(physical_memory_offset: VirtAddr) -> &'static mut PageTable {
+    let (level_4_table_frame, _) = Cr3::read();
+    
+    let phys = level_4_table_frame.start_address();
+    let virt = physical_memory_offset + phys.as_u64();
+    let page_table_ptr: *mut PageTable = virt.as_mut_ptr();
+    
+    &mut *page_table_ptr
+}
+
+pub fn phys_to_virt(addr: PhysAddr) -> VirtAddr {
+    let offset = PHYSICAL_MEMORY_OFFSET.lock().expect("memory::init not called");
+    offset + addr.as_u64()
+}
+
+pub fn kernel_page_table() -> PhysFrame {
+    KERNEL_PAGE_TABLE.lock().expect("memory::init not called")
+}
+
+// Builds a mapper over the page tables rooted at `level_4`. Callers must not
+// keep two of these alive for the same tables at once.
+fn mapper_for(level_4: PhysFrame) -> Option<OffsetPageTable<'static>> {
+    let offset = (*PHYSICAL_MEMORY_OFFSET.lock())?;
+    let table: *mut PageTable = (offset + level_4.start_address().as_u64()).as_mut_ptr();
+    Some(unsafe { OffsetPageTable::new(&mut *table, offset) })
+}
+
+fn active_mapper() -> Option<OffsetPageTable<'static>> {
+    mapper_for(Cr3::read().0)
+}
+
+pub fn virt_to_phys(addr: VirtAddr) -> Option<PhysAddr> {
+    active_mapper()?.translate_addr(addr)
+}
+
+// Whether `addr` is mapped in the active page tables. Takes no locks and
+// never allocates, so interrupt handlers and the allocator can ask; the
+// stack walker checks every frame with it before reading. False before
+// `init`.
+pub fn is_mapped(addr: VirtAddr) -> bool {
+    let offset = PHYSICAL_OFFSET.load(Ordering::Acquire);
+    if offset == NO_OFFSET {
+        return false;
+    }
+    let indices = [addr.p4_index(), addr.p3_index(), addr.p2_index(), addr.p1_index()];
+    let mut table = Cr3::read().0.start_address();
+    for (level, &index) in indices.iter().enumerate() {
+        let entries = unsafe { &*((offset + table.as_u64()) as *const PageTable) };
+        let entry = &entries[index];
+        if !entry.flags().contains(PageTableFlags::PRESENT) {
+            return false;
+        }
+        // 1 GiB and 2 MiB pages end the walk early
+        if level > 0 && entry.flags().contains(PageTableFlags::HUGE_PAGE) {
+            return true;
+        }
+        table = entry.addr();
+    }
+    true
+}
+
+// Device registers at `addr`, through the physical memory window. The
+// bootloader only maps the window as far as RAM goes, so a page above it
+// is mapped here, uncached.
+pub fn map_mmio(addr: PhysAddr) -> Option<VirtAddr> {
+    let virt = phys_to_virt(addr);
+    if !is_mapped(virt) {
+        let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::NO_CACHE | PageTableFlags::NO_EXECUTE;
+        map_page(Page::containing_address(virt), PhysFrame::containing_address(addr), flags).ok()?;
+    }
+    Some(virt)
+}
+
+pub fn map_page(page: Page, frame: PhysFrame, flags: PageTableFlags) -> Result<(), MapToError<Size4KiB>> {
+    map_page_in(Cr3::read().0, page, frame, flags)
+}
+
+// Maps into a possibly inactive set of page tables; the TLB is only flushed
+// when they are the live ones. The tables above the page are always made
+// writable, so only the page's own entry decides whether it can be written
+// and a read-only (copy-on-write) page can be made writable later.
+pub fn map_page_in(
+    level_4: PhysFrame,
+    page: Page,
+    frame: PhysFrame,
+    flags: PageTableFlags,
+) -> Result<(), MapToError<Size4KiB>> {
+    map_page_with(level_4, page, frame, flags, &mut GlobalFrameAllocator)
+}
+
+// As map_page_in, taking any page tables it needs from `tables` rather
+// than the kernel-wide pool
+pub fn map_page_with(
+    level_4: PhysFrame,
+    page: Page,
+    frame: PhysFrame,
+    flags: PageTableFlags,
+    tables: &mut impl FrameAllocator<Size4KiB>,
+) -> Result<(), MapToError<Size4KiB>> {
+    let mut mapper = mapper_for(level_4).ok_or(MapToError::FrameAllocationFailed)?;
+    let parent_flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE | (flags & PageTableFlags::USER_ACCESSIBLE);
+    let flush = unsafe { mapper.map_to_with_table_flags(page, frame, flags, parent_flags, tables)? };
+    if Cr3::read().0 == level_4 {
+        flush.flush();
+    } else {
+        flush.ignore();
+    }
+    Ok(())
+}
+
+// Removes the mapping and returns the frame that backed it; the caller
+// decides whether to free it.
+pub fn unmap_page(page: Page) -> Option<PhysFrame> {
+    let (frame, flush) = active_mapper()?.unmap(page).ok()?;
+    flush.flush();
+    Some(frame)
+}
+
+pub fn translate_page(page: Page) -> Option<(PhysFrame, PageTableFlags)> {
+    match active_mapper()?.translate(page.start_address()) {
+        TranslateResult::Mapped { frame: MappedFrame::Size4KiB(frame), flags, .. } => Some((frame, flags)),
+        _ => None,
+    }
+}
+
+pub fn page_flags(page: Page) -> Option<PageTableFlags> {
+    translate_page(page).map(|(_, flags)| flags)
+}
+
+pub fn set_page_flags(page: Page, flags: PageTableFlags) -> bool {
+    match active_mapper().map(|mut m| unsafe { m.update_flags(page, flags) }) {
+        Some(Ok(flush)) => {
+            flush.flush();
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Hands the boot frame allocator over to the kernel-wide pool once paging
+/// and the heap have been set up.
+pub fn install_frame_allocator(allocator: BootInfoFrameAllocator) {
+    *FRAME_ALLOCATOR.lock() = Some(allocator);
+}
+
+pub struct MemoryStats {
+    // RAM the firmware reported, in bytes
+    pub installed: u64,
+    // RAM the kernel may hand out, in bytes
+    pub usable: u64,
+    // Usable RAM not handed out yet, in bytes
+    pub available: u64,
+}
+
+pub fn stats() -> MemoryStats {
+    let recycled = FREE_FRAMES.lock().len() as u64 * PAGE_SIZE;
+    let guard = FRAME_ALLOCATOR.lock();
+    let allocator = match guard.as_ref() {
+        Some(allocator) => allocator,
+        None => return MemoryStats { installed: 0, usable: 0, available: 0 },
+    };
+    let regions = allocator.memory_map.iter();
+    let installed = regions.clone().filter(|r| is_ram(r.region_type)).map(region_size).sum();
+    let usable: u64 = regions.filter(|r| r.region_type == MemoryRegionType::Usable).map(region_size).sum();
+    let handed_out = allocator.next as u64 * PAGE_SIZE;
+    MemoryStats { installed, usable, available: usable.saturating_sub(handed_out) + recycled }
+}
+
+// The bootloader's view of physical memory, once the kernel has taken over
+// the frame allocator
+pub fn memory_map() -> Option<&'static MemoryMap> {
+    FRAME_ALLOCATOR.lock().as_ref().map(|allocator| allocator.memory_map)
+}
+
+pub fn region_size(region: &MemoryRegion) -> u64 {
+    region.range.end_addr() - region.range.start_addr()
+}
+
+// Regions backed by RAM, whatever they are used for
+fn is_ram(region_type: MemoryRegionType) -> bool {
+    !matches!(
+        region_type,
+        MemoryRegionType::Reserved
+            | MemoryRegionType::AcpiNvs
+            | MemoryRegionType::BadMemory
+            | MemoryRegionType::Empty
+    )
+}
+
+pub fn allocate_frame() -> Option<PhysFrame> {
+    if let Some(frame) = FREE_FRAMES.lock().pop() {
+        return Some(frame);
+    }
+    FRAME_ALLOCATOR.lock().as_mut()?.allocate_frame()
+}
+
+pub fn free_frame(frame: PhysFrame) {
+    FREE_FRAMES.lock().push(frame);
+}
+
+// Records one more owner of a frame, e.g. when fork shares it copy-on-write
+pub fn share_frame(frame: PhysFrame) {
+    *FRAME_REFS.lock().entry(frame.start_address().as_u64()).or_insert(1) += 1;
+}
+
+pub fn frame_refs(frame: PhysFrame) -> usize {
+    FRAME_REFS.lock().get(&frame.start_address().as_u64()).copied().unwrap_or(1)
+}
+
+// Drops one owner and frees the frame once nobody maps it any more
+pub fn release_frame(frame: PhysFrame) {
+    let mut refs = FRAME_REFS.lock();
+    let addr = frame.start_address().as_u64();
+    match refs.get_mut(&addr) {
+        Some(count) => {
+            *count -= 1;
+            if *count == 1 {
+                refs.remove(&addr);
+            }
+        }
+        None => free_frame(frame),
+    }
+}
+
+// Allocates a frame and clears it through the physical memory window
+pub fn allocate_zeroed_frame() -> Option<PhysFrame> {
+    let frame = allocate_frame()?;
+    let ptr: *mut u8 = phys_to_virt(frame.start_address()).as_mut_ptr();
+    unsafe { core::ptr::write_bytes(ptr, 0, PAGE_SIZE as usize) };
+    Some(frame)
+}
+
+// Page table frames needed while mapping come from the kernel-wide pool
+struct GlobalFrameAllocator;
+
+unsafe impl FrameAllocator<Size4KiB> for GlobalFrameAllocator {
+    fn allocate_frame(&mut self) -> Option<PhysFrame> {
+        allocate_zeroed_frame()
+    }
+}
+
+// Returns `count` physically adjacent frames, all below `limit`. Frames
+// handed out by the boot allocator are usually consecutive and come in
+// address order; any that break the run go to the free list for single
+// frames, and once one reaches `limit` no later one will do.
+pub fn allocate_contiguous_frames(count: usize, limit: u64) -> Option<PhysFrame> {
+    let mut guard = FRAME_ALLOCATOR.lock();
+    let allocator = guard.as_mut()?;
+    let mut spare = Vec::new();
+    let mut run: Vec<PhysFrame> = Vec::new();
+    while run.len() < count {
+        let frame = match allocator.allocate_frame() {
+            Some(frame) if frame.start_address().as_u64() + PAGE_SIZE <= limit => frame,
+            other => {
+                spare.extend(other);
+                spare.append(&mut run);
+                break;
+            }
+        };
+        if run.last().is_some_and(|&last| last + 1 != frame) {
+            spare.append(&mut run);
+        }
+        run.push(frame);
+    }
+    // Skipped frames aren't lost; they go back to single allocations once
+    // the boot allocator is free again
+    drop(guard);
+    FREE_FRAMES.lock().extend(spare);
+    run.first().copied()
+}
+
+/// A FrameAllocator that returns usable frames from the bootloader's memory map.
+pub struct BootInfoFrameAllocator {
+    memory_map: &'static MemoryMap,
+    next: usize,
+}
+
+impl BootInfoFrameAllocator {
+    /// Create a FrameAllocator from the passed memory map.
+    ///
+    /// The caller must guarantee that the passed memory map is valid and that
+    /// all frames marked as `USABLE` in it are really unused.
+    pub unsafe fn init(memory_map: &'static MemoryMap) -> Self {
+        BootInfoFrameAllocator {
+            memory_map,
+            next: 0,
+        }
+    }
+    
+    fn usable_frames(&self) -> impl Iterator<Item = PhysFrame> {
+        let regions = self.memory_map.iter();
+        let usable_regions = regions.filter(|r| r.region_type == MemoryRegionType::Usable);
+        let addr_ranges = usable_regions.map(|r| r.range.start_addr()..r.range.end_addr());
+        let frame_addresses = addr_ranges.flat_map(|r| r.step_by(4096));
+        frame_addresses.map(|addr| PhysFrame::containing_address(PhysAddr::new(addr)))
+    }
+}
+
+unsafe impl FrameAllocator<Size4KiB> for BootInfoFrameAllocator {
+    fn allocate_frame(&mut self) -> Option<PhysFrame> {
+        let frame = self.usable_frames().nth(self.next);
+        self.next += 1;
+        frame
+    }
+}
\ No newline at end of file
@@ -0,0 +1,236 @@
+// src/mime.rs
+// Internet mail messages (RFC 5322) and the MIME structure of their
+// bodies. Header fields come out unfolded with encoded words decoded, and
+// `text` finds the readable body: the first text/plain part, or the first
+// HTML part flattened when there is no plain one, with base64 or
+// quoted-printable undone. UTF-8 and ASCII are read as such; any other
+// character set is taken as Latin-1.
+use crate::html;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+pub struct Message<'a> {
+    pub headers: Vec<(String, String)>,
+    pub body: &'a [u8],
+}
+
+impl<'a> Message<'a> {
+    pub fn parse(data: &'a [u8]) -> Self {
+        let (headers, body) = split_head(data);
+        Self { headers, body }
+    }
+    
+    pub fn header(&self, name: &str) -> Option<&str> {
+        header(&self.headers, name)
+    }
+    
+    // The body as text, empty if there's no part that can be shown
+    pub fn text(&self) -> String {
+        text_of(&self.headers, self.body, false).or_else(|| text_of(&self.headers, self.body, true)).unwrap_or_default()
+    }
+}
+
+fn header<'h>(headers: &'h [(String, String)], name: &str) -> Option<&'h str> {
+    headers.iter().find(|(key, _)| key.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str())
+}
+
+fn find(data: &[u8], needle: &[u8]) -> Option<usize> {
+    data.windows(needle.len()).position(|window| window == needle)
+}
+
+// The header fields and the body after the blank line. Continuation lines
+// are joined to the field they continue.
+fn split_head(data: &[u8]) -> (Vec<(String, String)>, &[u8]) {
+    let (head, body) = match (find(data, b"\r\n\r\n"), find(data, b"\n\n")) {
+        (Some(crlf), Some(lf)) if lf < crlf => (&data[..lf], &data[lf + 2..]),
+        (Some(crlf), _) => (&data[..crlf], &data[crlf + 4..]),
+        (None, Some(lf)) => (&data[..lf], &data[lf + 2..]),
+        (None, None) => (data, &data[data.len()..]),
+    };
+    let mut headers: Vec<(String, String)> = Vec::new();
+    for line in String::from_utf8_lossy(head).lines() {
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            headers.push((String::from(name.trim()), String::from(value.trim())));
+        }
+    }
+    for (_, value) in headers.iter_mut() {
+        *value = decode_words(value);
+    }
+    (headers, body)
+}
+
+// A parameter of a structured field, like the boundary of a Content-Type
+fn parameter(value: &str, name: &str) -> Option<String> {
+    value.split(';').skip(1).find_map(|parameter| {
+        let (key, value) = parameter.split_once('=')?;
+        key.trim().eq_ignore_ascii_case(name).then(|| String::from(value.trim().trim_matches('"')))
+    })
+}
+
+fn decode_charset(data: &[u8], charset: &str) -> String {
+    if charset.eq_ignore_ascii_case("utf-8") || charset.eq_ignore_ascii_case("us-ascii") || charset.is_empty() {
+        String::from_utf8_lossy(data).into_owned()
+    } else {
+        data.iter().map(|&b| b as char).collect()
+    }
+}
+
+// Undoes RFC 2047 encoded words, `=?charset?B?...?=` and `=?charset?Q?...?=`.
+// Whitespace between two encoded words isn't part of the text.
+fn decode_words(value: &str) -> String {
+    let mut out = String::new();
+    let mut rest = value;
+    let mut after_word = false;
+    while let Some(start) = rest.find("=?") {
+        let word = rest[start + 2..].splitn(3, '?').collect::<Vec<_>>();
+        let decoded = match word.as_slice() {
+            [charset, encoding, text] => text.find("?=").and_then(|end| {
+                let data = match *encoding {
+                    "B" | "b" => base64(text[..end].as_bytes()),
+                    "Q" | "q" => quoted_printable(text[..end].replace('_', " ").as_bytes()),
+                    _ => return None,
+                };
+                let len = charset.len() + encoding.len() + end + 6;
+                Some((decode_charset(&data, charset), len))
+            }),
+            _ => None,
+        };
+        let between = &rest[..start];
+        match decoded {
+            Some((text, len)) => {
+                if !(after_word && between.trim().is_empty()) {
+                    out.push_str(between);
+                }
+                out.push_str(&text);
+                rest = &rest[start + len..];
+                after_word = true;
+            }
+            None => {
+                out.push_str(&rest[..start + 2]);
+                rest = &rest[start + 2..];
+                after_word = false;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn base64(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() * 3 / 4);
+    let mut bits = 0u32;
+    let mut count = 0;
+    for &b in data {
+        let value = match b {
+            b'A'..=b'Z' => b - b'A',
+            b'a'..=b'z' => b - b'a' + 26,
+            b'0'..=b'9' => b - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            // Padding, line breaks and anything else
+            _ => continue,
+        };
+        bits = (bits << 6) | value as u32;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            out.push((bits >> count) as u8);
+        }
+    }
+    out
+}
+
+fn quoted_printable(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < data.len() {
+        if data[i] != b'=' {
+            out.push(data[i]);
+            i += 1;
+            continue;
+        }
+        // A soft line break joins the lines around it
+        match (data.get(i + 1), data.get(i + 2)) {
+            (Some(b'\r'), Some(b'\n')) => i += 3,
+            (Some(b'\n'), _) => i += 2,
+            (Some(&high), Some(&low)) => match (char::from(high).to_digit(16), char::from(low).to_digit(16)) {
+                (Some(high), Some(low)) => {
+                    out.push((high * 16 + low) as u8);
+                    i += 3;
+                }
+                _ => {
+                    out.push(b'=');
+                    i += 1;
+                }
+            },
+            _ => {
+                out.push(b'=');
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+// The parts of a multipart body, without the preamble and epilogue
+fn parts<'a>(body: &'a [u8], boundary: &str) -> Vec<&'a [u8]> {
+    let delimiter = format!("--{}", boundary);
+    let mut parts = Vec::new();
+    let mut start = None;
+    let mut offset = 0;
+    for line in body.split_inclusive(|&b| b == b'\n') {
+        if line.starts_with(delimiter.as_bytes()) {
+            if let Some(start) = start {
+                parts.push(&body[start..offset]);
+            }
+            if line[delimiter.len()..].starts_with(b"--") {
+                return parts;
+            }
+            start = Some(offset + line.len());
+        }
+        offset += line.len();
+    }
+    if let Some(start) = start {
+        parts.push(&body[start..]);
+    }
+    parts
+}
+
+// The first text/plain part, or with `html` also the first text/html one
+fn text_of(headers: &[(String, String)], body: &[u8], html: bool) -> Option<String> {
+    let content_type = header(headers, "Content-Type").unwrap_or("text/plain");
+    let media_type = content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+    if media_type.starts_with("multipart/") {
+        let boundary = parameter(content_type, "boundary")?;
+        return parts(body, &boundary).into_iter().find_map(|part| {
+            let (headers, body) = split_head(part);
+            text_of(&headers, body, html)
+        });
+    }
+    if media_type != "text/plain" && !(html && media_type == "text/html") {
+        return None;
+    }
+    let encoding = header(headers, "Content-Transfer-Encoding").unwrap_or("").to_ascii_lowercase();
+    let data = match encoding.as_str() {
+        "base64" => base64(body),
+        "quoted-printable" => quoted_printable(body),
+        _ => Vec::from(body),
+    };
+    let text = decode_charset(&data, &parameter(content_type, "charset").unwrap_or_default());
+    if media_type == "text/html" {
+        let document = html::parse(&text);
+        let paragraphs: Vec<String> = document
+            .paragraphs
+            .iter()
+            .map(|paragraph| paragraph.spans.iter().map(|span| span.text.as_str()).collect())
+            .collect();
+        return Some(paragraphs.join("\n\n"));
+    }
+    Some(text)
+}
\ No newline at end of file
@@ -1,19 +1,52 @@
 // src/mission_control.rs
+// The spaces in a row along the top and the current space's windows in a
+// grid under them. Everything works from the keyboard: the arrows move a
+// highlight across the spaces and then down into the windows, Enter brings
+// the highlighted window forward, and typing narrows the windows to those
+// with what was typed in their titles.
+use crate::animations::{Animation, EasingType};
 use crate::graphics::{Graphics, Color};
-use crate::window_manager::{WindowManager};
+use crate::keyboard::Key;
+use crate::window_manager::{WindowManager, WindowState};
+use alloc::format;
+use alloc::string::String;
 use alloc::vec::Vec;
 
+// Where the window thumbnails go
+const WINDOWS_X: usize = 20;
+const WINDOWS_Y: usize = 190;
+const WINDOWS_WIDTH: usize = 600;
+const WINDOWS_HEIGHT: usize = 210;
+// Room under a thumbnail for its title
+const LABEL_HEIGHT: usize = 24;
+
+pub enum MissionControlAction {
+    // To the next space that way
+    SwitchSpace(i32),
+    // Bring the window at this index in the window manager forward
+    Focus(usize),
+    Exit,
+}
+
 pub struct MissionControl {
     pub is_visible: bool,
-    pub animation_progress: f32,
+    // Opening, from 0 to 1
+    pub animation: Animation,
     pub desktop_spaces: Vec<DesktopSpace>,
     pub current_space: usize,
+    // Typed to narrow the windows down by title
+    pub filter: String,
+    // The highlighted window, by its place among the thumbnails; None when
+    // the highlight is on the spaces
+    pub selected: Option<usize>,
 }
 
 pub struct DesktopSpace {
     pub id: usize,
     pub windows: Vec<usize>,
     pub wallpaper_color: Color,
+    // The app a full screen space belongs to; None for a desktop
+    pub fullscreen: Option<String>,
 }
 
 impl MissionControl {
@@ -23,24 +56,30 @@ impl MissionControl {
             id: 0,
             windows: Vec::new(),
             wallpaper_color: Color::new(30, 130, 180),
+            fullscreen: None,
         });
         spaces.push(DesktopSpace {
             id: 1,
             windows: Vec::new(),
             wallpaper_color: Color::new(180, 30, 130),
+            fullscreen: None,
         });
         
         Self {
             is_visible: false,
-            animation_progress: 0.0,
+            animation: Animation::new(1.0, 1.0, 0, EasingType::Linear),
             desktop_spaces: spaces,
             current_space: 0,
+            filter: String::new(),
+            selected: None,
         }
     }
     
     pub fn show(&mut self) {
         self.is_visible = true;
-        self.animation_progress = 0.0;
+        self.animation = Animation::new(0.0, 1.0, 300, EasingType::EaseOut);
+        self.filter.clear();
+        self.selected = None;
     }
     
     pub fn hide(&mut self) {
@@ -48,26 +87,24 @@ impl MissionControl {
     }
     
     pub fn update(&mut self) {
-        if self.is_visible && self.animation_progress < 1.0 {
-            self.animation_progress += 0.05;
-            if self.animation_progress > 1.0 {
-                self.animation_progress = 1.0;
-            }
+        if self.is_visible {
+            self.animation.update();
         }
     }
     
-    pub fn draw(&self, graphics: &mut Graphics, _window_manager: &WindowManager) {
+    pub fn draw(&self, graphics: &mut Graphics, window_manager: &WindowManager) {
         if !self.is_visible {
             return;
         }
         
         graphics.draw_rect(0, 0, 640, 480, Color::new(20, 20, 20));
         
-        let space_width = 200;
-        let space_height = 150;
-        let space_spacing = 220;
+        // Thumbnails shrink to fit once full screen apps add spaces
+        let space_spacing = 160.min(620 / self.desktop_spaces.len());
+        let space_width = space_spacing - 20;
+        let space_height = space_width * 3 / 4;
         let start_x = (640 - (self.desktop_spaces.len() * space_spacing - 20)) / 2;
-        let start_y = 100;
+        let start_y = 30;
         
         for (i, space) in self.desktop_spaces.iter().enumerate() {
             let x = start_x + i * space_spacing;
@@ -75,23 +112,181 @@ impl MissionControl {
             
             let border_color = if i == self.current_space { Color::BLUE } else { Color::GRAY };
             graphics.draw_rect_outline(x - 2, y - 2, space_width + 4, space_height + 4, border_color);
+            if i == self.current_space && self.selected.is_none() {
+                graphics.draw_rect_outline(x - 4, y - 4, space_width + 8, space_height + 8, Color::WHITE);
+            }
+            
+            // A full screen app fills its thumbnail and is named under it
+            if let Some(app) = &space.fullscreen {
+                let window = window_manager.windows().iter().find(|w| w.app_name() == app);
+                graphics.draw_rect(x, y, space_width, space_height, window.map_or(Color::WHITE, |w| w.background_color));
+                graphics.draw_rect(x, y, space_width, 8, Color::new(240, 240, 240));
+                let label: String = app.chars().take(space_width / 8).collect();
+                graphics.draw_text(&label, x + (space_width - label.chars().count() * 8) / 2, y + space_height + 10, Color::WHITE);
+                continue;
+            }
             graphics.draw_rect(x, y, space_width, space_height, space.wallpaper_color);
             
-            graphics.draw_rect(x + 20, y + 20, 60, 40, Color::WHITE);
-            graphics.draw_rect(x + 90, y + 30, 80, 50, Color::BLACK);
+            graphics.draw_rect(x + space_width / 10, y + space_height / 7, space_width * 3 / 10, space_height * 4 / 15, Color::WHITE);
+            graphics.draw_rect(x + space_width * 9 / 20, y + space_height / 5, space_width * 2 / 5, space_height / 3, Color::BLACK);
             
             let label = if i == self.current_space { "Current Desktop" } else { "Desktop" };
-            graphics.draw_text(label, x + 60, y + space_height + 10, Color::WHITE);
+            let label: String = label.chars().take(space_width / 8).collect();
+            graphics.draw_text(&label, x + (space_width - label.len() * 8) / 2, y + space_height + 10, Color::WHITE);
         }
         
-        graphics.draw_text("Use arrow keys to switch spaces, ESC to exit", 200, 400, Color::LIGHT_GRAY);
+        self.draw_windows(graphics, window_manager);
+        
+        let hint = if self.filter.is_empty() {
+            String::from("Arrows to move, Enter to pick, type to search, ESC to exit")
+        } else {
+            format!("Search: {}", self.filter)
+        };
+        let hint: String = hint.chars().take(78).collect();
+        graphics.draw_text(&hint, (640 - hint.chars().count() * 8) / 2, 420, Color::LIGHT_GRAY);
+    }
+    
+    // Each window's thumbnail, as big as its cell allows
+    fn draw_windows(&self, graphics: &mut Graphics, window_manager: &WindowManager) {
+        let thumbnails = self.thumbnails(window_manager);
+        if thumbnails.is_empty() {
+            let text = if self.filter.is_empty() { "No Windows" } else { "No Matching Windows" };
+            graphics.draw_text(text, (640 - text.len() * 8) / 2, WINDOWS_Y + WINDOWS_HEIGHT / 2, Color::GRAY);
+            return;
+        }
+        let (columns, rows) = grid(thumbnails.len());
+        let cell_width = WINDOWS_WIDTH / columns;
+        let cell_height = WINDOWS_HEIGHT / rows;
+        let room_width = cell_width - 16;
+        let room_height = cell_height.saturating_sub(LABEL_HEIGHT + 4).max(4);
+        for (n, &index) in thumbnails.iter().enumerate() {
+            let window = &window_manager.windows()[index];
+            let x = WINDOWS_X + (n % columns) * cell_width;
+            let y = WINDOWS_Y + (n / columns) * cell_height;
+            let (thumb_x, thumb_y, width, height) = window.draw_thumbnail(graphics, x + 8, y + 4, room_width, room_height);
+            if self.selected == Some(n) {
+                graphics.draw_rect_outline(thumb_x - 2, thumb_y - 2, width + 4, height + 4, Color::BLUE);
+                graphics.draw_rect_outline(thumb_x - 3, thumb_y - 3, width + 6, height + 6, Color::WHITE);
+            }
+            
+            let label: String = window.title.chars().take(cell_width / 8).collect();
+            let label_x = x + (cell_width - label.chars().count() * 8) / 2;
+            graphics.draw_text(&label, label_x, y + 4 + room_height + 6, Color::WHITE);
+        }
     }
     
-    pub fn switch_space(&mut self, direction: i32) {
+    // The windows shown, by index in the window manager: the current
+    // space's, back to front, with the filter somewhere in their titles
+    pub fn thumbnails(&self, window_manager: &WindowManager) -> Vec<usize> {
+        let fullscreen = self.fullscreen_app();
+        let filter = self.filter.to_lowercase();
+        window_manager.windows()
+            .iter()
+            .enumerate()
+            .filter(|(_, w)| !w.is_hidden && !w.is_pip && !w.is_minimized())
+            .filter(|(_, w)| match fullscreen {
+                Some(app) => w.app_name() == app,
+                None => w.state != WindowState::Fullscreen,
+            })
+            .filter(|(_, w)| w.title.to_lowercase().contains(&filter))
+            .map(|(i, _)| i)
+            .collect()
+    }
+    
+    // Up and down go between the spaces and the rows of windows; left and
+    // right switch spaces up top and move along the windows below
+    pub fn key(&mut self, key: Key, window_manager: &WindowManager) -> Option<MissionControlAction> {
+        let thumbnails = self.thumbnails(window_manager);
+        let (columns, _) = grid(thumbnails.len());
+        match (key, self.selected) {
+            (Key::Escape, _) if self.filter.is_empty() => return Some(MissionControlAction::Exit),
+            (Key::Escape, _) => {
+                self.filter.clear();
+                self.selected = None;
+            }
+            (Key::Enter, Some(n)) => return thumbnails.get(n).map(|&index| MissionControlAction::Focus(index)),
+            (Key::Enter, None) => return Some(MissionControlAction::Exit),
+            (Key::ArrowLeft, None) => return Some(MissionControlAction::SwitchSpace(-1)),
+            (Key::ArrowRight, None) => return Some(MissionControlAction::SwitchSpace(1)),
+            (Key::ArrowLeft, Some(n)) => self.selected = Some(n.saturating_sub(1)),
+            (Key::ArrowRight, Some(n)) => self.selected = Some((n + 1).min(thumbnails.len().saturating_sub(1))),
+            (Key::ArrowDown, None) if !thumbnails.is_empty() => self.selected = Some(0),
+            (Key::ArrowDown, Some(n)) if n + columns < thumbnails.len() => self.selected = Some(n + columns),
+            (Key::ArrowUp, Some(n)) => self.selected = n.checked_sub(columns),
+            (Key::Backspace, _) => {
+                self.filter.pop();
+                self.refilter(window_manager);
+            }
+            (key, _) => {
+                if let Some(c) = key.to_char() {
+                    self.filter.push(c);
+                    self.refilter(window_manager);
+                }
+            }
+        }
+        None
+    }
+    
+    // The first window left highlighted after the filter changes
+    fn refilter(&mut self, window_manager: &WindowManager) {
+        let any = !self.filter.is_empty() && !self.thumbnails(window_manager).is_empty();
+        self.selected = if any { Some(0) } else { None };
+    }
+    
+    // False if there's no space that way
+    pub fn switch_space(&mut self, direction: i32) -> bool {
+        self.selected = None;
         if direction > 0 && self.current_space < self.desktop_spaces.len() - 1 {
             self.current_space += 1;
         } else if direction < 0 && self.current_space > 0 {
             self.current_space -= 1;
+        } else {
+            return false;
         }
+        true
+    }
+    
+    pub fn current_wallpaper(&self) -> Color {
+        self.desktop_spaces[self.current_space].wallpaper_color
+    }
+    
+    // The app whose full screen space is showing
+    pub fn fullscreen_app(&self) -> Option<&str> {
+        self.desktop_spaces[self.current_space].fullscreen.as_deref()
+    }
+    
+    // A space for `app` gone full screen, after the others, and switches
+    // to it
+    pub fn add_fullscreen_space(&mut self, app: &str) {
+        let id = self.desktop_spaces.iter().map(|space| space.id + 1).max().unwrap_or(0);
+        self.desktop_spaces.push(DesktopSpace {
+            id,
+            windows: Vec::new(),
+            wallpaper_color: Color::BLACK,
+            fullscreen: Some(String::from(app)),
+        });
+        self.current_space = self.desktop_spaces.len() - 1;
+    }
+    
+    // Takes away `app`'s full screen space; if it was showing, the space
+    // before it shows instead
+    pub fn remove_fullscreen_space(&mut self, app: &str) {
+        let index = match self.desktop_spaces.iter().position(|space| space.fullscreen.as_deref() == Some(app)) {
+            Some(index) => index,
+            None => return,
+        };
+        self.desktop_spaces.remove(index);
+        if self.current_space >= index {
+            self.current_space = self.current_space.saturating_sub(1);
+        }
+    }
+}
+
+// Columns and rows for `count` thumbnails, as near square as they go
+fn grid(count: usize) -> (usize, usize) {
+    let mut columns = 1;
+    while columns * columns < count {
+        columns += 1;
     }
+    (columns, count.div_ceil(columns).max(1))
 }
\ No newline at end of file
@@ -1,7 +1,7 @@
 // src/mouse.rs
 use crate::graphics::Color;
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy)]
 pub enum MouseButton {
     Left,
     Right,
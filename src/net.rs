@@ -0,0 +1,538 @@
+// src/net.rs
+// The network stack: one Ethernet interface with a static IPv4 setup
+// (QEMU's user-mode network), ARP, IPv4 without fragmentation, ICMP echo,
+// UDP and TCP. Frames are polled from the desktop loop and from anything
+// waiting on a socket; datagrams for local sockets go to the socket table.
+// Packets produced while a frame is being handled are queued and sent once
+// the interface is unlocked.
+use crate::clock::Instant;
+use crate::socket;
+use crate::virtio_net;
+use crate::{kinfo, kwarn};
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+use crate::sync::Mutex;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_ARP: u16 = 0x0806;
+const ETHERNET_HEADER: usize = 14;
+const IPV4_HEADER: usize = 20;
+pub const UDP_HEADER: usize = 8;
+pub const ICMP_HEADER: usize = 8;
+
+pub const PROTOCOL_ICMP: u8 = 1;
+pub const PROTOCOL_TCP: u8 = 6;
+pub const PROTOCOL_UDP: u8 = 17;
+
+pub const ICMP_ECHO_REPLY: u8 = 0;
+pub const ICMP_DEST_UNREACHABLE: u8 = 3;
+pub const ICMP_ECHO_REQUEST: u8 = 8;
+pub const ICMP_TIME_EXCEEDED: u8 = 11;
+
+pub const DEFAULT_TTL: u8 = 64;
+const ARP_TIMEOUT: Duration = Duration::from_secs(1);
+const ARP_RETRY: Duration = Duration::from_millis(250);
+const ARP_CACHE_SIZE: usize = 16;
+// Frames handled per poll, so a flood can't stall the desktop
+const POLL_BUDGET: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Ipv4Addr(pub [u8; 4]);
+
+impl Ipv4Addr {
+    pub const UNSPECIFIED: Self = Ipv4Addr([0, 0, 0, 0]);
+    pub const BROADCAST: Self = Ipv4Addr([255, 255, 255, 255]);
+    
+    // Dotted quad, like "10.0.2.2"
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut octets = [0; 4];
+        let mut parts = text.split('.');
+        for octet in octets.iter_mut() {
+            *octet = parts.next()?.parse().ok()?;
+        }
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(Ipv4Addr(octets))
+    }
+    
+    fn to_u32(self) -> u32 {
+        u32::from_be_bytes(self.0)
+    }
+    
+    fn from_slice(bytes: &[u8]) -> Self {
+        Ipv4Addr([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+}
+
+impl fmt::Display for Ipv4Addr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let [a, b, c, d] = self.0;
+        write!(f, "{}.{}.{}.{}", a, b, c, d)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MacAddr(pub [u8; 6]);
+
+impl MacAddr {
+    pub const BROADCAST: Self = MacAddr([0xFF; 6]);
+}
+
+impl fmt::Display for MacAddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let m = self.0;
+        write!(f, "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}", m[0], m[1], m[2], m[3], m[4], m[5])
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetError {
+    NoInterface,
+    NoBuffers,
+    TooLong,
+    Unreachable,
+    AddressInUse,
+    BadSocket,
+    Closed,
+    Refused,
+    TimedOut,
+    // The name server has no address for the name
+    NoSuchHost,
+}
+
+// A network card. Drivers hand over whole Ethernet frames without the FCS.
+pub trait NetDriver: Send {
+    fn name(&self) -> &'static str;
+    fn mac(&self) -> MacAddr;
+    fn send(&mut self, frame: &[u8]) -> Result<(), NetError>;
+    fn receive(&mut self) -> Option<Vec<u8>>;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    pub ip: Ipv4Addr,
+    pub netmask: Ipv4Addr,
+    pub gateway: Ipv4Addr,
+    pub name_server: Ipv4Addr,
+}
+
+// QEMU's user-mode network hands out 10.0.2.15 behind a gateway at .2,
+// and forwards DNS queries sent to .3 to the host's resolver
+pub const DEFAULT_CONFIG: Config = Config {
+    ip: Ipv4Addr([10, 0, 2, 15]),
+    netmask: Ipv4Addr([255, 255, 255, 0]),
+    gateway: Ipv4Addr([10, 0, 2, 2]),
+    name_server: Ipv4Addr([10, 0, 2, 3]),
+};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Counters {
+    pub rx_packets: u64,
+    pub rx_bytes: u64,
+    pub tx_packets: u64,
+    pub tx_bytes: u64,
+    pub rx_dropped: u64,
+    pub tx_errors: u64,
+}
+
+#[derive(Clone, Copy)]
+struct ArpEntry {
+    ip: Ipv4Addr,
+    mac: MacAddr,
+}
+
+struct Interface {
+    driver: Box<dyn NetDriver>,
+    mac: MacAddr,
+    config: Config,
+    // Most recently used last
+    arp_cache: Vec<ArpEntry>,
+    counters: Counters,
+    next_ip_id: u16,
+}
+
+// What `netstat -i` shows
+pub struct InterfaceInfo {
+    pub name: &'static str,
+    pub driver: &'static str,
+    pub mac: MacAddr,
+    pub config: Config,
+    pub counters: Counters,
+}
+
+static INTERFACE: Mutex<Option<Interface>> = Mutex::new(None);
+// IPv4 packets waiting to go out: destination, protocol and payload
+static OUTGOING: Mutex<VecDeque<(Ipv4Addr, u8, Vec<u8>)>> = Mutex::new(VecDeque::new());
+static FLUSHING: AtomicBool = AtomicBool::new(false);
+
+pub fn init() -> bool {
+    let driver: Box<dyn NetDriver> = match virtio_net::probe() {
+        Some(card) => Box::new(card),
+        None => {
+            kwarn!("net: no network card found");
+            return false;
+        }
+    };
+    let mac = driver.mac();
+    let config = DEFAULT_CONFIG;
+    kinfo!("net: {} is {} via {}", driver.name(), config.ip, config.gateway);
+    *INTERFACE.lock() = Some(Interface {
+        driver,
+        mac,
+        config,
+        arp_cache: Vec::new(),
+        counters: Counters::default(),
+        next_ip_id: 1,
+    });
+    true
+}
+
+pub fn interface_info() -> Option<InterfaceInfo> {
+    INTERFACE.lock().as_ref().map(|iface| InterfaceInfo {
+        name: "eth0",
+        driver: iface.driver.name(),
+        mac: iface.mac,
+        config: iface.config,
+        counters: iface.counters,
+    })
+}
+
+// Readdresses the interface. What ARP learned is forgotten, since the
+// gateway may be somewhere else now.
+pub fn set_config(config: Config) {
+    if let Some(iface) = INTERFACE.lock().as_mut() {
+        if iface.config.ip != config.ip || iface.config.gateway != config.gateway {
+            kinfo!("net: {} is now {} via {}", iface.driver.name(), config.ip, config.gateway);
+        }
+        iface.config = config;
+        iface.arp_cache.clear();
+    }
+}
+
+pub fn local_ip() -> Option<Ipv4Addr> {
+    INTERFACE.lock().as_ref().map(|iface| iface.config.ip)
+}
+
+pub fn name_server() -> Option<Ipv4Addr> {
+    INTERFACE.lock().as_ref().map(|iface| iface.config.name_server)
+}
+
+// The Internet checksum (RFC 1071) over `data`, starting from `sum`
+pub fn checksum_with(mut sum: u32, data: &[u8]) -> u16 {
+    let mut chunks = data.chunks_exact(2);
+    for pair in &mut chunks {
+        sum += u16::from_be_bytes([pair[0], pair[1]]) as u32;
+    }
+    if let [last] = chunks.remainder() {
+        sum += (*last as u32) << 8;
+    }
+    while sum > 0xFFFF {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+pub fn checksum(data: &[u8]) -> u16 {
+    checksum_with(0, data)
+}
+
+// Sum of the pseudo-header UDP and TCP checksums cover
+pub fn pseudo_header_sum(src: Ipv4Addr, dst: Ipv4Addr, protocol: u8, len: usize) -> u32 {
+    let (s, d) = (src.to_u32(), dst.to_u32());
+    (s >> 16) + (s & 0xFFFF) + (d >> 16) + (d & 0xFFFF) + protocol as u32 + len as u32
+}
+
+fn be16(data: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([data[offset], data[offset + 1]])
+}
+
+// An ICMP echo request carrying `payload`
+pub fn icmp_echo(ident: u16, seq: u16, payload: &[u8]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(ICMP_HEADER + payload.len());
+    message.extend_from_slice(&[ICMP_ECHO_REQUEST, 0, 0, 0]);
+    message.extend_from_slice(&ident.to_be_bytes());
+    message.extend_from_slice(&seq.to_be_bytes());
+    message.extend_from_slice(payload);
+    let sum = checksum(&message);
+    message[2..4].copy_from_slice(&sum.to_be_bytes());
+    message
+}
+
+// The identifier and sequence number of the echo request an ICMP message
+// answers: from its own header for replies, or from the quoted IP header
+// and first eight bytes of the request for errors
+pub fn echo_id(message: &[u8]) -> Option<(u16, u16)> {
+    let at = match *message.first()? {
+        ICMP_ECHO_REPLY => 4,
+        ICMP_DEST_UNREACHABLE | ICMP_TIME_EXCEEDED => {
+            let quoted = message.get(ICMP_HEADER..)?;
+            if quoted.len() < IPV4_HEADER || quoted[9] != PROTOCOL_ICMP {
+                return None;
+            }
+            ICMP_HEADER + (quoted[0] & 0x0F) as usize * 4 + 4
+        }
+        _ => return None,
+    };
+    let id = message.get(at..at + 4)?;
+    Some((be16(id, 0), be16(id, 2)))
+}
+
+impl Interface {
+    fn on_link(&self, ip: Ipv4Addr) -> bool {
+        let mask = self.config.netmask.to_u32();
+        ip == Ipv4Addr::BROADCAST || ip.to_u32() & mask == self.config.ip.to_u32() & mask
+    }
+    
+    fn lookup(&mut self, ip: Ipv4Addr) -> Option<MacAddr> {
+        if ip == Ipv4Addr::BROADCAST {
+            return Some(MacAddr::BROADCAST);
+        }
+        let index = self.arp_cache.iter().position(|entry| entry.ip == ip)?;
+        let entry = self.arp_cache.remove(index);
+        self.arp_cache.push(entry);
+        Some(entry.mac)
+    }
+    
+    fn learn(&mut self, ip: Ipv4Addr, mac: MacAddr) {
+        self.arp_cache.retain(|entry| entry.ip != ip);
+        if self.arp_cache.len() == ARP_CACHE_SIZE {
+            self.arp_cache.remove(0);
+        }
+        self.arp_cache.push(ArpEntry { ip, mac });
+    }
+    
+    fn send_frame(&mut self, dst: MacAddr, ethertype: u16, payload: &[u8]) -> Result<(), NetError> {
+        let mut frame = Vec::with_capacity(ETHERNET_HEADER + payload.len());
+        frame.extend_from_slice(&dst.0);
+        frame.extend_from_slice(&self.mac.0);
+        frame.extend_from_slice(&ethertype.to_be_bytes());
+        frame.extend_from_slice(payload);
+        match self.driver.send(&frame) {
+            Ok(()) => {
+                self.counters.tx_packets += 1;
+                self.counters.tx_bytes += frame.len() as u64;
+                Ok(())
+            }
+            Err(err) => {
+                self.counters.tx_errors += 1;
+                Err(err)
+            }
+        }
+    }
+    
+    fn send_arp(&mut self, op: u16, target_mac: MacAddr, target_ip: Ipv4Addr) -> Result<(), NetError> {
+        let mut packet = Vec::with_capacity(28);
+        // Ethernet hardware, IPv4 protocol, 6- and 4-byte addresses
+        packet.extend_from_slice(&[0, 1, 0x08, 0x00, 6, 4]);
+        packet.extend_from_slice(&op.to_be_bytes());
+        packet.extend_from_slice(&self.mac.0);
+        packet.extend_from_slice(&self.config.ip.0);
+        packet.extend_from_slice(&target_mac.0);
+        packet.extend_from_slice(&target_ip.0);
+        let dst = if op == 1 { MacAddr::BROADCAST } else { target_mac };
+        self.send_frame(dst, ETHERTYPE_ARP, &packet)
+    }
+    
+    fn send_ipv4_to(&mut self, mac: MacAddr, dst: Ipv4Addr, protocol: u8, ttl: u8, payload: &[u8]) -> Result<(), NetError> {
+        let total = IPV4_HEADER + payload.len();
+        if total > virtio_net::MAX_FRAME - ETHERNET_HEADER {
+            return Err(NetError::TooLong);
+        }
+        let id = self.next_ip_id;
+        self.next_ip_id = id.wrapping_add(1);
+        let mut packet = Vec::with_capacity(total);
+        // Version 4, five-word header, don't fragment
+        packet.extend_from_slice(&[0x45, 0]);
+        packet.extend_from_slice(&(total as u16).to_be_bytes());
+        packet.extend_from_slice(&id.to_be_bytes());
+        packet.extend_from_slice(&[0x40, 0, ttl, protocol, 0, 0]);
+        packet.extend_from_slice(&self.config.ip.0);
+        packet.extend_from_slice(&dst.0);
+        let sum = checksum(&packet);
+        packet[10..12].copy_from_slice(&sum.to_be_bytes());
+        packet.extend_from_slice(payload);
+        self.send_frame(mac, ETHERTYPE_IPV4, &packet)
+    }
+    
+    fn handle_frame(&mut self, frame: &[u8]) {
+        self.counters.rx_packets += 1;
+        self.counters.rx_bytes += frame.len() as u64;
+        if frame.len() < ETHERNET_HEADER {
+            self.counters.rx_dropped += 1;
+            return;
+        }
+        let payload = &frame[ETHERNET_HEADER..];
+        let handled = match be16(frame, 12) {
+            ETHERTYPE_ARP => self.handle_arp(payload),
+            ETHERTYPE_IPV4 => self.handle_ipv4(payload),
+            _ => false,
+        };
+        if !handled {
+            self.counters.rx_dropped += 1;
+        }
+    }
+    
+    fn handle_arp(&mut self, packet: &[u8]) -> bool {
+        if packet.len() < 28 || be16(packet, 0) != 1 || be16(packet, 2) != ETHERTYPE_IPV4 {
+            return false;
+        }
+        let op = be16(packet, 6);
+        let mut sender_mac = [0; 6];
+        sender_mac.copy_from_slice(&packet[8..14]);
+        let sender_mac = MacAddr(sender_mac);
+        let sender_ip = Ipv4Addr::from_slice(&packet[14..18]);
+        let target_ip = Ipv4Addr::from_slice(&packet[24..28]);
+        if target_ip != self.config.ip {
+            // Only refresh entries we already have for other hosts' traffic
+            if self.arp_cache.iter().any(|entry| entry.ip == sender_ip) {
+                self.learn(sender_ip, sender_mac);
+            }
+            return true;
+        }
+        self.learn(sender_ip, sender_mac);
+        if op == 1 {
+            let _ = self.send_arp(2, sender_mac, sender_ip);
+        }
+        true
+    }
+    
+    fn handle_ipv4(&mut self, packet: &[u8]) -> bool {
+        if packet.len() < IPV4_HEADER || packet[0] >> 4 != 4 {
+            return false;
+        }
+        let header_len = (packet[0] & 0x0F) as usize * 4;
+        let total = be16(packet, 2) as usize;
+        if header_len < IPV4_HEADER || total < header_len || total > packet.len() || checksum(&packet[..header_len]) != 0 {
+            return false;
+        }
+        // Fragments aren't reassembled
+        if be16(packet, 6) & 0x3FFF != 0 {
+            return false;
+        }
+        let dst = Ipv4Addr::from_slice(&packet[16..20]);
+        if dst != self.config.ip && dst != Ipv4Addr::BROADCAST {
+            return false;
+        }
+        let ttl = packet[8];
+        let src = Ipv4Addr::from_slice(&packet[12..16]);
+        let body = &packet[header_len..total];
+        match packet[9] {
+            PROTOCOL_ICMP => self.handle_icmp(src, ttl, body),
+            PROTOCOL_UDP => self.handle_udp(src, dst, body),
+            PROTOCOL_TCP => socket::deliver_tcp(src, dst, body),
+            _ => false,
+        }
+    }
+    
+    fn handle_icmp(&mut self, src: Ipv4Addr, ttl: u8, message: &[u8]) -> bool {
+        if message.len() < ICMP_HEADER || checksum(message) != 0 {
+            return false;
+        }
+        if message[0] == ICMP_ECHO_REQUEST {
+            // The reply is the request with its type changed
+            let mut reply = message.to_vec();
+            reply[0] = ICMP_ECHO_REPLY;
+            reply[2..4].fill(0);
+            let sum = checksum(&reply);
+            reply[2..4].copy_from_slice(&sum.to_be_bytes());
+            if let Some(mac) = self.lookup(src).or_else(|| self.lookup(self.config.gateway)) {
+                let _ = self.send_ipv4_to(mac, src, PROTOCOL_ICMP, DEFAULT_TTL, &reply);
+            }
+            return true;
+        }
+        socket::deliver_icmp(src, ttl, message)
+    }
+    
+    fn handle_udp(&mut self, src: Ipv4Addr, dst: Ipv4Addr, datagram: &[u8]) -> bool {
+        if datagram.len() < UDP_HEADER {
+            return false;
+        }
+        let len = be16(datagram, 4) as usize;
+        if len < UDP_HEADER || len > datagram.len() {
+            return false;
+        }
+        let datagram = &datagram[..len];
+        // A zero checksum means the sender didn't compute one
+        if be16(datagram, 6) != 0 && checksum_with(pseudo_header_sum(src, dst, PROTOCOL_UDP, len), datagram) != 0 {
+            return false;
+        }
+        socket::deliver_udp(src, be16(datagram, 0), be16(datagram, 2), &datagram[UDP_HEADER..])
+    }
+}
+
+// Sends an IPv4 packet, resolving the next hop first. Resolving polls the
+// network while it waits, so this must not be called with the interface
+// or socket table locked.
+pub fn send_ipv4(dst: Ipv4Addr, protocol: u8, ttl: u8, payload: &[u8]) -> Result<(), NetError> {
+    let start = Instant::now();
+    let mut last_request: Option<Instant> = None;
+    loop {
+        {
+            let mut guard = INTERFACE.lock();
+            let iface = guard.as_mut().ok_or(NetError::NoInterface)?;
+            let next_hop = if iface.on_link(dst) { dst } else { iface.config.gateway };
+            if let Some(mac) = iface.lookup(next_hop) {
+                return iface.send_ipv4_to(mac, dst, protocol, ttl, payload);
+            }
+            if start.elapsed() >= ARP_TIMEOUT {
+                return Err(NetError::Unreachable);
+            }
+            if last_request.is_none_or(|sent| sent.elapsed() >= ARP_RETRY) {
+                iface.send_arp(1, MacAddr([0; 6]), next_hop)?;
+                last_request = Some(Instant::now());
+            }
+        }
+        poll();
+        core::hint::spin_loop();
+    }
+}
+
+// Queues a packet to be sent by the next `flush`
+pub fn queue_ipv4(dst: Ipv4Addr, protocol: u8, payload: Vec<u8>) {
+    OUTGOING.lock().push_back((dst, protocol, payload));
+}
+
+// Sends the queued packets. Sending can poll while it waits for ARP, and
+// that poll's own flush is skipped so packets keep their order.
+pub fn flush() {
+    if FLUSHING.swap(true, Ordering::Acquire) {
+        return;
+    }
+    loop {
+        let next = OUTGOING.lock().pop_front();
+        match next {
+            Some((dst, protocol, payload)) => {
+                if let Err(err) = send_ipv4(dst, protocol, DEFAULT_TTL, &payload) {
+                    kwarn!("net: dropped packet to {}: {:?}", dst, err);
+                }
+            }
+            None => break,
+        }
+    }
+    FLUSHING.store(false, Ordering::Release);
+}
+
+// Handles whatever frames have arrived, runs the TCP timers and sends what
+// they produced
+pub fn poll() {
+    {
+        let mut guard = INTERFACE.lock();
+        let iface = match guard.as_mut() {
+            Some(iface) => iface,
+            None => return,
+        };
+        for _ in 0..POLL_BUDGET {
+            match iface.driver.receive() {
+                Some(frame) => iface.handle_frame(&frame),
+                None => break,
+            }
+        }
+    }
+    socket::tick();
+    flush();
+}
\ No newline at end of file
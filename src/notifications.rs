@@ -1,12 +1,48 @@
 // src/notifications.rs
 use crate::graphics::{Graphics, Color};
 use crate::animations::{Animation, EasingType};
-use alloc::string::String;
+use crate::clock;
+use crate::do_not_disturb;
+use crate::pool::{Pool, PoolBox};
+use crate::widgets::{Button, Marquee};
+use alloc::format;
+use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 
+// More than this many on screen at once spill over to the heap
+const MAX_NOTIFICATIONS: usize = 8;
+// Missed while Do Not Disturb was on: how many are shown afterwards, with
+// a count standing in for the rest
+const MAX_MISSED_SHOWN: usize = 3;
+const BACKGROUND: Color = Color::new(248, 248, 248);
+// Room for text left of the icon
+const TEXT_WIDTH: usize = 224;
+// Long titles are cut off; long messages scroll so they can be read
+const TITLE: Marquee = Marquee::new(0, Color::BLACK, BACKGROUND);
+const MESSAGE: Marquee = Marquee::new(8, Color::DARK_GRAY, BACKGROUND);
+
+static NOTIFICATION_POOL: Pool<Notification> = Pool::new("notifications", MAX_NOTIFICATIONS);
+
+// What a notification's button does, handed back to the desktop when
+// it's clicked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotificationAction {
+    // Sets the reminder with this text again a few minutes on
+    Snooze(String),
+}
+
+impl NotificationAction {
+    fn button(&self) -> Button {
+        match self {
+            NotificationAction::Snooze(_) => Button::new("Snooze"),
+        }
+    }
+}
+
 pub struct Notification {
     pub title: String,
     pub message: String,
+    pub action: Option<NotificationAction>,
     pub x: f32,
     pub y: f32,
     pub width: f32,
@@ -14,6 +50,8 @@ pub struct Notification {
     pub animation: Animation,
     pub lifetime: u32,
     pub age: u32,
+    // When it was posted, so a long message starts scrolling from its start
+    shown_at: u64,
 }
 
 impl Notification {
@@ -21,13 +59,15 @@ impl Notification {
         Self {
             title,
             message,
+            action: None,
             x: 640.0,
             y: 50.0,
             width: 300.0,
             height: 80.0,
-            animation: Animation::new(640.0, 320.0, 30, EasingType::EaseOut),
+            animation: Animation::new(640.0, 320.0, 500, EasingType::Overshoot),
             lifetime: 300,
             age: 0,
+            shown_at: clock::millis(),
         }
     }
     
@@ -42,13 +82,24 @@ impl Notification {
         let w = self.width as usize;
         let h = self.height as usize;
         
-        graphics.draw_rounded_rect(x, y, w, h, Color::new(248, 248, 248));
+        graphics.draw_rounded_rect(x, y, w, h, BACKGROUND);
         graphics.draw_rect_outline(x, y, w, h, Color::new(200, 200, 200));
         
-        graphics.draw_text(&self.title, x + 15, y + 15, Color::BLACK);
-        graphics.draw_text(&self.message, x + 15, y + 35, Color::DARK_GRAY);
+        let millis = clock::millis() - self.shown_at;
+        TITLE.draw(graphics, &self.title, x + 15, y + 15, TEXT_WIDTH, millis);
+        MESSAGE.draw(graphics, &self.message, x + 15, y + 35, TEXT_WIDTH, millis);
         
         graphics.draw_rounded_rect(x + w - 50, y + 15, 30, 30, Color::BLUE);
+        if let Some(action) = &self.action {
+            let (button_x, button_y) = self.button_at();
+            action.button().draw(graphics, button_x, button_y);
+        }
+    }
+    
+    // Where its button goes, under the icon
+    fn button_at(&self) -> (usize, usize) {
+        let width = self.action.as_ref().map_or(0, |action| action.button().width());
+        ((self.x + self.width) as usize - 20 - width, self.y as usize + 54)
     }
     
     pub fn is_expired(&self) -> bool {
@@ -57,24 +108,79 @@ impl Notification {
 }
 
 pub struct NotificationCenter {
-    notifications: Vec<Notification>,
+    notifications: Vec<PoolBox<Notification>>,
+    // Posted while Do Not Disturb was on, with their titles, messages and
+    // actions
+    missed: Vec<(String, String, Option<NotificationAction>)>,
 }
 
 impl NotificationCenter {
     pub fn new() -> Self {
         Self {
-            notifications: Vec::new(),
+            notifications: Vec::with_capacity(MAX_NOTIFICATIONS),
+            missed: Vec::new(),
         }
     }
     
     pub fn show_notification(&mut self, title: String, message: String) {
-        let mut notification = Notification::new(title, message);
-        
-        let stack_offset = self.notifications.len() as f32 * 90.0;
-        notification.y += stack_offset;
-        notification.animation = Animation::new(640.0, 320.0, 30, EasingType::EaseOut);
-        
-        self.notifications.push(notification);
+        self.notify(title, message, None);
+    }
+    
+    // With a button that hands `action` back from `click`
+    pub fn show_with_action(&mut self, title: String, message: String, action: NotificationAction) {
+        self.notify(title, message, Some(action));
+    }
+    
+    // Kept for later, or dropped, while Do Not Disturb is on
+    fn notify(&mut self, title: String, message: String, action: Option<NotificationAction>) {
+        if do_not_disturb::is_active() {
+            if do_not_disturb::deliver_later() {
+                self.missed.push((title, message, action));
+            }
+            return;
+        }
+        self.post(title, message, action);
+    }
+    
+    fn post(&mut self, title: String, message: String, action: Option<NotificationAction>) {
+        crate::heap_profiler::tagged("notifications", || {
+            let mut notification = Notification::new(title, message);
+            notification.action = action;
+            
+            let stack_offset = self.notifications.len() as f32 * 90.0;
+            notification.y += stack_offset;
+            notification.animation = Animation::new(640.0, 320.0, 500, EasingType::Overshoot);
+            
+            self.notifications.push(NOTIFICATION_POOL.alloc(notification));
+        });
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.notifications.is_empty()
+    }
+    
+    // Shows what was missed once Do Not Disturb goes off: the first few,
+    // then how many more there were
+    pub fn deliver_missed(&mut self) {
+        let missed = core::mem::take(&mut self.missed);
+        let more = missed.len().saturating_sub(MAX_MISSED_SHOWN);
+        for (title, message, action) in missed.into_iter().take(MAX_MISSED_SHOWN) {
+            self.post(title, message, action);
+        }
+        if more > 0 {
+            let plural = if more == 1 { "" } else { "s" };
+            self.post("Do Not Disturb".to_string(), format!("{} more notification{} while it was on", more, plural), None);
+        }
+    }
+    
+    // The action of the notification whose button is at the point, which
+    // is dismissed
+    pub fn click(&mut self, x: usize, y: usize) -> Option<NotificationAction> {
+        let index = self.notifications.iter().position(|notification| {
+            let (button_x, button_y) = notification.button_at();
+            notification.action.as_ref().is_some_and(|action| action.button().contains(x, y, button_x, button_y))
+        })?;
+        self.notifications.remove(index).action.take()
     }
     
     pub fn update(&mut self) {
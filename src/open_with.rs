@@ -0,0 +1,126 @@
+// src/open_with.rs
+// The dialog that asks which app opens a file, for kinds of files more
+// than one app can open when nobody picked one. "Always use this app"
+// makes the choice the default for the kind so it isn't asked again.
+use crate::graphics::{Graphics, Color, SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::keyboard::Key;
+use crate::vfs;
+use crate::widgets::{Button, Checkbox};
+use alloc::format;
+use alloc::string::String;
+
+const WIDTH: usize = 260;
+const ROW_HEIGHT: usize = 22;
+// Layout, relative to the dialog
+const LIST_Y: usize = 34;
+const BUTTON_X: usize = 130;
+const OPEN_X: usize = 200;
+
+const ALWAYS: Checkbox = Checkbox::new("Always use this app");
+const CANCEL: Button = Button::new("Cancel");
+const OPEN: Button = Button::new("Open");
+
+// What the dialog was closed with
+pub struct Choice {
+    pub path: String,
+    pub app: &'static str,
+    pub always: bool,
+}
+
+pub struct OpenWithDialog {
+    pub is_visible: bool,
+    path: String,
+    apps: &'static [&'static str],
+    selected_index: usize,
+    always: bool,
+}
+
+impl OpenWithDialog {
+    pub fn new() -> Self {
+        Self { is_visible: false, path: String::new(), apps: &[], selected_index: 0, always: false }
+    }
+    
+    pub fn show(&mut self, path: &str, apps: &'static [&'static str]) {
+        self.path = String::from(path);
+        self.apps = apps;
+        self.selected_index = 0;
+        self.always = false;
+        self.is_visible = true;
+    }
+    
+    pub fn hide(&mut self) {
+        self.is_visible = false;
+    }
+    
+    fn height(&self) -> usize {
+        LIST_Y + self.apps.len() * ROW_HEIGHT + 64
+    }
+    
+    fn origin(&self) -> (usize, usize) {
+        ((SCREEN_WIDTH - WIDTH) / 2, (SCREEN_HEIGHT - self.height()) / 2)
+    }
+    
+    fn confirm(&mut self) -> Option<Choice> {
+        self.hide();
+        let app = self.apps.get(self.selected_index)?;
+        Some(Choice { path: self.path.clone(), app, always: self.always })
+    }
+    
+    pub fn draw(&self, graphics: &mut Graphics) {
+        if !self.is_visible {
+            return;
+        }
+        let (x, y) = self.origin();
+        let height = self.height();
+        graphics.draw_rounded_rect(x, y, WIDTH, height, Color::new(236, 236, 236));
+        graphics.draw_rect_outline(x, y, WIDTH, height, Color::GRAY);
+        graphics.draw_text(&format!("Open \"{}\" with:", vfs::parent_and_name(&self.path).1), x + 12, y + 12, Color::BLACK);
+        
+        for (i, app) in self.apps.iter().enumerate() {
+            let row_y = y + LIST_Y + i * ROW_HEIGHT;
+            let color = if i == self.selected_index {
+                graphics.draw_rounded_rect(x + 8, row_y, WIDTH - 16, ROW_HEIGHT - 2, Color::BLUE);
+                Color::WHITE
+            } else {
+                Color::BLACK
+            };
+            graphics.draw_text(app, x + 16, row_y + 6, color);
+        }
+        
+        let bottom = y + LIST_Y + self.apps.len() * ROW_HEIGHT;
+        Checkbox { checked: self.always, ..ALWAYS }.draw(graphics, x + 12, bottom + 8);
+        CANCEL.draw(graphics, x + BUTTON_X, bottom + 32);
+        graphics.draw_rounded_rect(x + OPEN_X, bottom + 32, OPEN.width(), Button::HEIGHT, Color::BLUE);
+        graphics.draw_text(OPEN.label, x + OPEN_X + 8, bottom + 37, Color::WHITE);
+    }
+    
+    // The dialog is modal, so clicks outside it do nothing
+    pub fn click(&mut self, px: usize, py: usize) -> Option<Choice> {
+        let (x, y) = self.origin();
+        let bottom = y + LIST_Y + self.apps.len() * ROW_HEIGHT;
+        if px < x || px >= x + WIDTH {
+            return None;
+        }
+        if py >= y + LIST_Y && py < bottom {
+            self.selected_index = (py - y - LIST_Y) / ROW_HEIGHT;
+        } else if ALWAYS.contains(px, py, x + 12, bottom + 8) {
+            self.always = !self.always;
+        } else if CANCEL.contains(px, py, x + BUTTON_X, bottom + 32) {
+            self.hide();
+        } else if OPEN.contains(px, py, x + OPEN_X, bottom + 32) {
+            return self.confirm();
+        }
+        None
+    }
+    
+    pub fn key(&mut self, key: Key) -> Option<Choice> {
+        match key {
+            Key::ArrowUp => self.selected_index = self.selected_index.saturating_sub(1),
+            Key::ArrowDown => self.selected_index = (self.selected_index + 1).min(self.apps.len().saturating_sub(1)),
+            Key::Escape => self.hide(),
+            Key::Enter => return self.confirm(),
+            _ => {}
+        }
+        None
+    }
+}
\ No newline at end of file
@@ -0,0 +1,257 @@
+// src/partition.rs
+// Partition tables and the volumes on each disk. A disk with an MBR or a
+// GPT has a volume per partition, read and written through a device that
+// offsets its sectors into the disk's; a disk without a table is one
+// volume. Logical partitions inside an MBR extended partition aren't
+// listed. Volumes go through the disk's block cache like everything else.
+use crate::block::{self, read_bytes, BlockDevice, BlockError, DeviceInfo, SharedBlockDevice, SECTOR_SIZE};
+use crate::{block_cache, zip};
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::sync::Mutex;
+
+const MBR_TABLE: usize = 446;
+const MBR_ENTRY_SIZE: usize = 16;
+const MBR_PROTECTIVE: u8 = 0xEE;
+const MBR_EXTENDED: [u8; 3] = [0x05, 0x0F, 0x85];
+const GPT_SIGNATURE: &[u8] = b"EFI PART";
+// Entries read at most, the usual size of the table
+const GPT_MAX_ENTRIES: u32 = 128;
+
+// Type GUIDs as they're stored, the first three fields little-endian
+const GPT_TYPES: [([u8; 16], &str); 4] = [
+    ([0x28, 0x73, 0x2A, 0xC1, 0x1F, 0xF8, 0xD2, 0x11, 0xBA, 0x4B, 0x00, 0xA0, 0xC9, 0x3E, 0xC9, 0x3B], "EFI System"),
+    ([0xA2, 0xA0, 0xD0, 0xEB, 0xE5, 0xB9, 0x33, 0x44, 0x87, 0xC0, 0x68, 0xB6, 0xB7, 0x26, 0x99, 0xC7], "Basic data"),
+    ([0xAF, 0x3D, 0xC6, 0x0F, 0x83, 0x84, 0x72, 0x47, 0x8E, 0x79, 0x3D, 0x69, 0xD8, 0x47, 0x7D, 0xE4], "Linux filesystem"),
+    ([0x6D, 0xFD, 0x57, 0x06, 0xAB, 0xA4, 0xC4, 0x43, 0x84, 0xE5, 0x09, 0x33, 0xC8, 0x4B, 0x4F, 0x4F], "Linux swap"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scheme {
+    None,
+    Mbr,
+    Gpt,
+}
+
+impl Scheme {
+    pub fn name(self) -> &'static str {
+        match self {
+            Scheme::None => "None",
+            Scheme::Mbr => "Master Boot Record",
+            Scheme::Gpt => "GUID Partition Map",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Partition {
+    // From 1, in table order
+    pub number: usize,
+    pub start: u64,
+    pub sectors: u64,
+    pub type_name: &'static str,
+}
+
+#[derive(Clone)]
+pub struct Volume {
+    // "vda" for a whole disk, "vda1" for a partition
+    pub name: String,
+    // Of the disk, in block::list()
+    pub disk: usize,
+    pub partition: Option<Partition>,
+    pub device: SharedBlockDevice,
+}
+
+pub struct Disk {
+    pub info: DeviceInfo,
+    pub scheme: Scheme,
+    pub volumes: Vec<Volume>,
+}
+
+// A partition as a device of its own
+struct PartitionDevice {
+    disk: SharedBlockDevice,
+    name: String,
+    start: u64,
+    sectors: u64,
+}
+
+impl BlockDevice for PartitionDevice {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    
+    fn sector_count(&self) -> u64 {
+        self.sectors
+    }
+    
+    fn read_sectors(&mut self, lba: u64, buf: &mut [u8]) -> Result<(), BlockError> {
+        block::check_request(self, lba, buf.len())?;
+        self.disk.lock().read_sectors(self.start + lba, buf)
+    }
+    
+    fn write_sectors(&mut self, lba: u64, buf: &[u8]) -> Result<(), BlockError> {
+        block::check_request(self, lba, buf.len())?;
+        self.disk.lock().write_sectors(self.start + lba, buf)
+    }
+    
+    fn flush(&mut self) -> Result<(), BlockError> {
+        self.disk.lock().flush()
+    }
+    
+    fn is_read_only(&self) -> bool {
+        self.disk.lock().is_read_only()
+    }
+}
+
+fn u32_at(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([buf[offset], buf[offset + 1], buf[offset + 2], buf[offset + 3]])
+}
+
+fn u64_at(buf: &[u8], offset: usize) -> u64 {
+    u32_at(buf, offset) as u64 | (u32_at(buf, offset + 4) as u64) << 32
+}
+
+fn mbr_type_name(kind: u8) -> &'static str {
+    match kind {
+        0x01 | 0x04 | 0x06 | 0x0E => "FAT",
+        0x0B | 0x0C => "FAT32",
+        0x07 => "NTFS or exFAT",
+        0x82 => "Linux swap",
+        0x83 => "Linux",
+        0xEF => "EFI System",
+        _ => "Unknown",
+    }
+}
+
+// "vda1", or "nvme0n1p1" when the disk's name ends in a digit
+fn partition_name(disk: &str, number: usize) -> String {
+    if disk.ends_with(|c: char| c.is_ascii_digit()) {
+        format!("{}p{}", disk, number)
+    } else {
+        format!("{}{}", disk, number)
+    }
+}
+
+// None if the first sector isn't an MBR. FAT and NTFS boot sectors end in
+// the same signature, so those are told apart by their names.
+fn parse_mbr(sector: &[u8]) -> Option<Vec<(u8, Partition)>> {
+    if sector[510..512] != [0x55, 0xAA] || &sector[82..87] == b"FAT32" || &sector[54..57] == b"FAT" || &sector[3..7] == b"NTFS" {
+        return None;
+    }
+    let mut partitions = Vec::new();
+    for (i, entry) in sector[MBR_TABLE..MBR_TABLE + 4 * MBR_ENTRY_SIZE].chunks(MBR_ENTRY_SIZE).enumerate() {
+        if entry[0] != 0 && entry[0] != 0x80 {
+            return None;
+        }
+        let (kind, start, sectors) = (entry[4], u32_at(entry, 8) as u64, u32_at(entry, 12) as u64);
+        if kind != 0 && sectors != 0 {
+            partitions.push((kind, Partition { number: i + 1, start, sectors, type_name: mbr_type_name(kind) }));
+        }
+    }
+    Some(partitions)
+}
+
+fn parse_gpt(device: &SharedBlockDevice) -> Option<Vec<Partition>> {
+    let mut header = [0u8; SECTOR_SIZE];
+    read_bytes(device, SECTOR_SIZE as u64, &mut header).ok()?;
+    if &header[0..8] != GPT_SIGNATURE {
+        return None;
+    }
+    // The header's CRC is taken with its own field zeroed
+    let header_size = u32_at(&header, 12) as usize;
+    if !(92..=SECTOR_SIZE).contains(&header_size) {
+        return None;
+    }
+    let crc = u32_at(&header, 16);
+    header[16..20].fill(0);
+    if zip::crc32(0, &header[..header_size]) != crc {
+        return None;
+    }
+    let table = u64_at(&header, 72).checked_mul(SECTOR_SIZE as u64)?;
+    let count = u32_at(&header, 80).min(GPT_MAX_ENTRIES);
+    let entry_size = u32_at(&header, 84) as u64;
+    if entry_size < 128 {
+        return None;
+    }
+    
+    let mut partitions = Vec::new();
+    let mut entry = [0u8; 128];
+    for i in 0..count {
+        let at = entry_size.checked_mul(i as u64).and_then(|offset| offset.checked_add(table))?;
+        read_bytes(device, at, &mut entry).ok()?;
+        let type_guid = &entry[0..16];
+        if type_guid.iter().all(|&b| b == 0) {
+            continue;
+        }
+        let (first, last) = (u64_at(&entry, 32), u64_at(&entry, 40));
+        if first > last || last == u64::MAX {
+            continue;
+        }
+        let type_name = GPT_TYPES.iter().find(|(guid, _)| guid == type_guid).map_or("Unknown", |(_, name)| name);
+        partitions.push(Partition {
+            number: i as usize + 1,
+            start: first,
+            sectors: last + 1 - first,
+            type_name,
+        });
+    }
+    Some(partitions)
+}
+
+// The partition table on a disk and its partitions
+fn read_table(device: &SharedBlockDevice) -> (Scheme, Vec<Partition>) {
+    let mut sector = [0u8; SECTOR_SIZE];
+    if read_bytes(device, 0, &mut sector).is_err() {
+        return (Scheme::None, Vec::new());
+    }
+    let mbr = match parse_mbr(&sector) {
+        Some(mbr) => mbr,
+        None => return (Scheme::None, Vec::new()),
+    };
+    if mbr.iter().any(|(kind, _)| *kind == MBR_PROTECTIVE) {
+        if let Some(partitions) = parse_gpt(device) {
+            return (Scheme::Gpt, partitions);
+        }
+    }
+    let partitions = mbr.into_iter().filter(|(kind, _)| !MBR_EXTENDED.contains(kind)).map(|(_, p)| p).collect();
+    (Scheme::Mbr, partitions)
+}
+
+// Every attached disk with its volumes
+pub fn disks() -> Vec<Disk> {
+    let mut disks = Vec::new();
+    for info in block::list() {
+        let device = match block_cache::cached_device(info.index) {
+            Some(device) => device,
+            None => continue,
+        };
+        let (scheme, partitions) = read_table(&device);
+        let volumes = match scheme {
+            Scheme::None => vec![Volume { name: info.name.clone(), disk: info.index, partition: None, device }],
+            _ => partitions
+                .into_iter()
+                .filter(|p| p.start.checked_add(p.sectors).is_some_and(|end| end <= info.sector_count))
+                .map(|partition| {
+                    let name = partition_name(&info.name, partition.number);
+                    let part: SharedBlockDevice = Arc::new(Mutex::new(PartitionDevice {
+                        disk: device.clone(),
+                        name: name.clone(),
+                        start: partition.start,
+                        sectors: partition.sectors,
+                    }));
+                    Volume { name, disk: info.index, partition: Some(partition), device: part }
+                })
+                .collect(),
+        };
+        disks.push(Disk { info, scheme, volumes });
+    }
+    disks
+}
+
+pub fn volumes() -> Vec<Volume> {
+    disks().into_iter().flat_map(|disk| disk.volumes).collect()
+}
\ No newline at end of file
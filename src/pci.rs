@@ -0,0 +1,337 @@
+// src/pci.rs
+use crate::{apic, memory};
+use alloc::vec::Vec;
+use core::ptr::write_volatile;
+use crate::sync::Mutex;
+use x86_64::instructions::port::Port;
+use x86_64::PhysAddr;
+
+const CONFIG_ADDRESS: u16 = 0xCF8;
+const CONFIG_DATA: u16 = 0xCFC;
+
+const COMMAND: u8 = 0x04;
+// Stops the device asserting its legacy line once it signals by message
+const COMMAND_INTX_DISABLE: u16 = 1 << 10;
+
+const CAP_MSI: u8 = 0x05;
+const CAP_MSIX: u8 = 0x11;
+const MSI_ENABLE: u16 = 1 << 0;
+const MSI_MULTIPLE_ENABLE: u16 = 0b111 << 4;
+const MSI_64_BIT: u16 = 1 << 7;
+const MSIX_TABLE_SIZE: u16 = 0x7FF;
+const MSIX_FUNCTION_MASK: u16 = 1 << 14;
+const MSIX_ENABLE: u16 = 1 << 15;
+const MSIX_ENTRY_SIZE: u64 = 16;
+
+static CONFIG_LOCK: Mutex<()> = Mutex::new(());
+static DEVICES: Mutex<Vec<PciDevice>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Clone, Copy)]
+pub struct PciDevice {
+    pub bus: u8,
+    pub device: u8,
+    pub function: u8,
+    pub vendor_id: u16,
+    pub device_id: u16,
+    pub class: u8,
+    pub subclass: u8,
+    pub prog_if: u8,
+    pub interrupt_line: u8,
+}
+
+// How a device signals once it's off its legacy line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageInterrupt {
+    Msi,
+    // Entry 0 of the table has the vector
+    MsiX,
+}
+
+impl MessageInterrupt {
+    pub fn name(self) -> &'static str {
+        match self {
+            MessageInterrupt::Msi => "MSI",
+            MessageInterrupt::MsiX => "MSI-X",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bar {
+    Io(u16),
+    Memory(u64),
+    None,
+}
+
+impl PciDevice {
+    pub fn read_u32(&self, offset: u8) -> u32 {
+        read_config(self.bus, self.device, self.function, offset)
+    }
+    
+    pub fn write_u32(&self, offset: u8, value: u32) {
+        write_config(self.bus, self.device, self.function, offset, value)
+    }
+    
+    pub fn read_u8(&self, offset: u8) -> u8 {
+        (self.read_u32(offset & 0xFC) >> ((offset & 3) * 8)) as u8
+    }
+    
+    pub fn read_u16(&self, offset: u8) -> u16 {
+        (self.read_u32(offset & 0xFC) >> ((offset & 2) * 8)) as u16
+    }
+    
+    pub fn write_u16(&self, offset: u8, value: u16) {
+        let shift = (offset & 2) * 8;
+        let old = self.read_u32(offset & 0xFC);
+        let new = (old & !(0xFFFF << shift)) | ((value as u32) << shift);
+        self.write_u32(offset & 0xFC, new);
+    }
+    
+    pub fn bar(&self, index: u8) -> Bar {
+        if index > 5 {
+            return Bar::None;
+        }
+        
+        let offset = 0x10 + index * 4;
+        let raw = self.read_u32(offset);
+        if raw == 0 {
+            Bar::None
+        } else if raw & 1 == 1 {
+            Bar::Io((raw & 0xFFFC) as u16)
+        } else if (raw >> 1) & 0b11 == 0b10 {
+            let high = self.read_u32(offset + 4) as u64;
+            Bar::Memory((high << 32) | (raw & 0xFFFF_FFF0) as u64)
+        } else {
+            Bar::Memory((raw & 0xFFFF_FFF0) as u64)
+        }
+    }
+    
+    // Config space offsets of every capability with the given ID
+    pub fn capabilities(&self, id: u8) -> Vec<u8> {
+        let mut found = Vec::new();
+        // Status bit 4: the capability list is present
+        if self.read_u16(0x06) & (1 << 4) == 0 {
+            return found;
+        }
+        let mut offset = self.read_u8(0x34) & 0xFC;
+        // The list lives in the 192 bytes after the header; bound the walk
+        // in case it loops
+        for _ in 0..48 {
+            if offset == 0 {
+                break;
+            }
+            if self.read_u8(offset) == id {
+                found.push(offset);
+            }
+            offset = self.read_u8(offset + 1) & 0xFC;
+        }
+        found
+    }
+    
+    // Allow the device to answer I/O and memory cycles and master DMA
+    pub fn enable_bus_master(&self) {
+        let command = self.read_u16(COMMAND);
+        self.write_u16(COMMAND, command | 0b111);
+    }
+    
+    fn disable_legacy_interrupt(&self) {
+        let command = self.read_u16(COMMAND);
+        self.write_u16(COMMAND, command | COMMAND_INTX_DISABLE);
+    }
+    
+    // Has the device raise `vector` by message, through MSI-X where it has
+    // both; None when it has neither and stays on its line
+    pub fn enable_message_interrupt(&self, vector: u8) -> Option<MessageInterrupt> {
+        if self.enable_msix(&[vector]) {
+            Some(MessageInterrupt::MsiX)
+        } else if self.enable_msi(vector) {
+            Some(MessageInterrupt::Msi)
+        } else {
+            None
+        }
+    }
+    
+    // Points the MSI capability's one message at `vector` on this processor
+    // and turns the legacy line off; false without the capability
+    fn enable_msi(&self, vector: u8) -> bool {
+        let cap = match self.capabilities(CAP_MSI).first() {
+            Some(&cap) => cap,
+            None => return false,
+        };
+        let control = self.read_u16(cap + 2);
+        self.write_u32(cap + 4, apic::msi_address() as u32);
+        if control & MSI_64_BIT != 0 {
+            self.write_u32(cap + 8, 0);
+            self.write_u16(cap + 12, vector as u16);
+        } else {
+            self.write_u16(cap + 8, vector as u16);
+        }
+        self.write_u16(cap + 2, (control & !MSI_MULTIPLE_ENABLE) | MSI_ENABLE);
+        self.disable_legacy_interrupt();
+        true
+    }
+    
+    // Fills the MSI-X table so entry i raises `vectors[i]` on this processor
+    // and turns MSI-X on in the legacy line's place. False without the
+    // capability, with a table too small, or with one in an I/O BAR.
+    pub fn enable_msix(&self, vectors: &[u8]) -> bool {
+        let cap = match self.capabilities(CAP_MSIX).first() {
+            Some(&cap) => cap,
+            None => return false,
+        };
+        let control = self.read_u16(cap + 2);
+        if vectors.len() > (control & MSIX_TABLE_SIZE) as usize + 1 {
+            return false;
+        }
+        let table = self.read_u32(cap + 4);
+        let base = match self.bar((table & 7) as u8) {
+            Bar::Memory(addr) => addr + (table & !7) as u64,
+            _ => return false,
+        };
+        // Masked as a whole while the entries change
+        self.write_u16(cap + 2, control | MSIX_ENABLE | MSIX_FUNCTION_MASK);
+        for (i, &vector) in vectors.iter().enumerate() {
+            let entry = match memory::map_mmio(PhysAddr::new(base + i as u64 * MSIX_ENTRY_SIZE)) {
+                Some(entry) => entry.as_mut_ptr::<u32>(),
+                None => {
+                    self.write_u16(cap + 2, control & !MSIX_ENABLE);
+                    return false;
+                }
+            };
+            // Address low and high, data, and vector control with the
+            // entry's mask bit clear
+            unsafe {
+                write_volatile(entry, apic::msi_address() as u32);
+                write_volatile(entry.add(1), 0);
+                write_volatile(entry.add(2), vector as u32);
+                write_volatile(entry.add(3), 0);
+            }
+        }
+        self.write_u16(cap + 2, (control | MSIX_ENABLE) & !MSIX_FUNCTION_MASK);
+        self.disable_legacy_interrupt();
+        true
+    }
+    
+    pub fn vendor_name(&self) -> &'static str {
+        match self.vendor_id {
+            0x8086 => "Intel",
+            0x1022 | 0x1002 => "AMD",
+            0x10DE => "NVIDIA",
+            0x1234 => "QEMU",
+            0x1AF4 | 0x1B36 => "Red Hat",
+            0x15AD => "VMware",
+            0x80EE => "VirtualBox",
+            _ => "Unknown vendor",
+        }
+    }
+    
+    pub fn class_name(&self) -> &'static str {
+        match (self.class, self.subclass) {
+            (0x01, 0x01) => "IDE controller",
+            (0x01, 0x06) => "SATA controller",
+            (0x01, _) => "Mass storage controller",
+            (0x02, _) => "Network controller",
+            (0x03, _) => "Display controller",
+            (0x04, _) => "Multimedia controller",
+            (0x06, 0x00) => "Host bridge",
+            (0x06, 0x01) => "ISA bridge",
+            (0x06, _) => "Bridge",
+            (0x0C, 0x03) => "USB controller",
+            (0x0C, _) => "Serial bus controller",
+            _ => "Unknown device",
+        }
+    }
+}
+
+fn config_address(bus: u8, device: u8, function: u8, offset: u8) -> u32 {
+    0x8000_0000
+        | (bus as u32) << 16
+        | (device as u32) << 11
+        | (function as u32) << 8
+        | (offset as u32 & 0xFC)
+}
+
+pub fn read_config(bus: u8, device: u8, function: u8, offset: u8) -> u32 {
+    let _guard = CONFIG_LOCK.lock();
+    let mut address: Port<u32> = Port::new(CONFIG_ADDRESS);
+    let mut data: Port<u32> = Port::new(CONFIG_DATA);
+    unsafe {
+        address.write(config_address(bus, device, function, offset));
+        data.read()
+    }
+}
+
+pub fn write_config(bus: u8, device: u8, function: u8, offset: u8, value: u32) {
+    let _guard = CONFIG_LOCK.lock();
+    let mut address: Port<u32> = Port::new(CONFIG_ADDRESS);
+    let mut data: Port<u32> = Port::new(CONFIG_DATA);
+    unsafe {
+        address.write(config_address(bus, device, function, offset));
+        data.write(value);
+    }
+}
+
+fn probe(bus: u8, device: u8, function: u8) -> Option<PciDevice> {
+    let id = read_config(bus, device, function, 0x00);
+    let vendor_id = id as u16;
+    if vendor_id == 0xFFFF {
+        return None;
+    }
+    
+    let class = read_config(bus, device, function, 0x08);
+    let interrupt = read_config(bus, device, function, 0x3C);
+    Some(PciDevice {
+        bus,
+        device,
+        function,
+        vendor_id,
+        device_id: (id >> 16) as u16,
+        class: (class >> 24) as u8,
+        subclass: (class >> 16) as u8,
+        prog_if: (class >> 8) as u8,
+        interrupt_line: interrupt as u8,
+    })
+}
+
+// Brute-force scan of every bus/slot/function on the legacy config mechanism
+pub fn scan() -> Vec<PciDevice> {
+    let mut devices = Vec::new();
+    
+    for bus in 0..=255u8 {
+        for device in 0..32u8 {
+            let first = match probe(bus, device, 0) {
+                Some(first) => first,
+                None => continue,
+            };
+            let header_type = (read_config(bus, device, 0, 0x0C) >> 16) as u8;
+            devices.push(first);
+            
+            if header_type & 0x80 != 0 {
+                for function in 1..8u8 {
+                    if let Some(dev) = probe(bus, device, function) {
+                        devices.push(dev);
+                    }
+                }
+            }
+        }
+    }
+    
+    devices
+}
+
+pub fn init() {
+    let devices = scan();
+    *DEVICES.lock() = devices;
+}
+
+pub fn devices() -> Vec<PciDevice> {
+    DEVICES.lock().clone()
+}
+
+pub fn find(vendor_id: u16, device_id: u16) -> Option<PciDevice> {
+    DEVICES
+        .lock()
+        .iter()
+        .find(|dev| dev.vendor_id == vendor_id && dev.device_id == device_id)
+        .copied()
+}
\ No newline at end of file
@@ -0,0 +1,105 @@
+// src/pipe.rs
+// Pipes: a byte buffer with a reading end and a writing end, which is how
+// one process's output becomes the next one's input and how the shell
+// reads what a job prints. A read takes what's there and a write what
+// fits; neither waits, so whoever would wait (an app in a system call, the
+// shell between frames) comes back when `is_ready` says so. An empty pipe
+// with no writer left is the end of the input, and writing to a pipe with
+// no reader left is an error. Ends can be cloned; the buffer goes when the
+// last one is dropped.
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use crate::sync::Mutex;
+
+pub const CAPACITY: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PipeError {
+    // Nothing to read yet, or no room to write
+    WouldBlock,
+    // Writing with nobody left to read
+    Broken,
+}
+
+struct Buffer {
+    data: VecDeque<u8>,
+    readers: usize,
+    writers: usize,
+}
+
+pub struct Reader(Arc<Mutex<Buffer>>);
+pub struct Writer(Arc<Mutex<Buffer>>);
+
+pub fn create() -> (Reader, Writer) {
+    let buffer = Arc::new(Mutex::new(Buffer { data: VecDeque::new(), readers: 1, writers: 1 }));
+    (Reader(buffer.clone()), Writer(buffer))
+}
+
+impl Reader {
+    // Ok(0) once the writers are gone and everything they wrote was read
+    pub fn read(&self, buf: &mut [u8]) -> Result<usize, PipeError> {
+        let mut buffer = self.0.lock();
+        if buffer.data.is_empty() {
+            return if buffer.writers == 0 { Ok(0) } else { Err(PipeError::WouldBlock) };
+        }
+        let count = buf.len().min(buffer.data.len());
+        for (byte, slot) in buffer.data.drain(..count).zip(buf.iter_mut()) {
+            *slot = byte;
+        }
+        Ok(count)
+    }
+
+    // Whether a read would return without WouldBlock
+    pub fn is_ready(&self) -> bool {
+        let buffer = self.0.lock();
+        !buffer.data.is_empty() || buffer.writers == 0
+    }
+}
+
+impl Writer {
+    // Writes what fits, which may be less than all of it
+    pub fn write(&self, data: &[u8]) -> Result<usize, PipeError> {
+        let mut buffer = self.0.lock();
+        if buffer.readers == 0 {
+            return Err(PipeError::Broken);
+        }
+        let count = data.len().min(CAPACITY - buffer.data.len());
+        if count == 0 && !data.is_empty() {
+            return Err(PipeError::WouldBlock);
+        }
+        buffer.data.extend(&data[..count]);
+        Ok(count)
+    }
+
+    // Whether a write would return without WouldBlock
+    pub fn is_ready(&self) -> bool {
+        let buffer = self.0.lock();
+        buffer.data.len() < CAPACITY || buffer.readers == 0
+    }
+}
+
+impl Clone for Reader {
+    fn clone(&self) -> Self {
+        self.0.lock().readers += 1;
+        Reader(self.0.clone())
+    }
+}
+
+impl Clone for Writer {
+    fn clone(&self) -> Self {
+        self.0.lock().writers += 1;
+        Writer(self.0.clone())
+    }
+}
+
+impl Drop for Reader {
+    fn drop(&mut self) {
+        self.0.lock().readers -= 1;
+    }
+}
+
+impl Drop for Writer {
+    fn drop(&mut self) {
+        self.0.lock().writers -= 1;
+    }
+}
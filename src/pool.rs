@@ -0,0 +1,142 @@
+// src/pool.rs
+// Typed object pools for high-churn UI objects (notifications, animations,
+// input events). Each pool grabs one slab of slots from the heap the first
+// time it is used and hands slots out from a free list afterwards, so the
+// render loop neither fragments the small kernel heap nor pays for a heap
+// search. An exhausted pool falls back to the heap rather than failing.
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::mem::MaybeUninit;
+use core::ops::{Deref, DerefMut};
+use core::ptr::NonNull;
+use crate::sync::Mutex;
+
+#[derive(Debug, Clone, Copy)]
+pub struct PoolStats {
+    pub name: &'static str,
+    pub capacity: usize,
+    pub in_use: usize,
+    pub peak: usize,
+    pub fallbacks: u64,
+}
+
+trait PoolInfo: Sync {
+    fn stats(&self) -> PoolStats;
+}
+
+static POOLS: Mutex<Vec<&'static dyn PoolInfo>> = Mutex::new(Vec::new());
+
+struct Slab<T> {
+    storage: Option<Box<[MaybeUninit<T>]>>,
+    free: Vec<usize>,
+    peak: usize,
+    fallbacks: u64,
+}
+
+pub struct Pool<T> {
+    name: &'static str,
+    capacity: usize,
+    slab: Mutex<Slab<T>>,
+}
+
+unsafe impl<T: Send> Sync for Pool<T> {}
+
+impl<T: Send + 'static> Pool<T> {
+    pub const fn new(name: &'static str, capacity: usize) -> Self {
+        Self {
+            name,
+            capacity,
+            slab: Mutex::new(Slab { storage: None, free: Vec::new(), peak: 0, fallbacks: 0 }),
+        }
+    }
+    
+    pub fn alloc(&'static self, value: T) -> PoolBox<T> {
+        let mut slab = self.slab.lock();
+        if slab.storage.is_none() {
+            let mut storage = Vec::with_capacity(self.capacity);
+            storage.resize_with(self.capacity, MaybeUninit::uninit);
+            slab.storage = Some(storage.into_boxed_slice());
+            // Hand out low indices first
+            slab.free = (0..self.capacity).rev().collect();
+            POOLS.lock().push(self);
+        }
+        
+        match slab.free.pop() {
+            Some(index) => {
+                let in_use = self.capacity - slab.free.len();
+                slab.peak = slab.peak.max(in_use);
+                let slot = &mut slab.storage.as_mut().unwrap()[index];
+                let ptr = NonNull::from(slot.write(value));
+                PoolBox { ptr, pool: Some(self) }
+            }
+            None => {
+                slab.fallbacks += 1;
+                let ptr = NonNull::from(Box::leak(Box::new(value)));
+                PoolBox { ptr, pool: None }
+            }
+        }
+    }
+    
+    // Called with the value already dropped
+    fn release(&self, ptr: NonNull<T>) {
+        let mut slab = self.slab.lock();
+        let base = slab.storage.as_ref().unwrap().as_ptr() as usize;
+        let index = (ptr.as_ptr() as usize - base) / core::mem::size_of::<T>().max(1);
+        slab.free.push(index);
+    }
+}
+
+impl<T: Send> PoolInfo for Pool<T> {
+    fn stats(&self) -> PoolStats {
+        let slab = self.slab.lock();
+        let in_use = if slab.storage.is_some() { self.capacity - slab.free.len() } else { 0 };
+        PoolStats {
+            name: self.name,
+            capacity: self.capacity,
+            in_use,
+            peak: slab.peak,
+            fallbacks: slab.fallbacks,
+        }
+    }
+}
+
+// Owning handle to a pooled value; returns the slot to its pool on drop
+pub struct PoolBox<T: Send + 'static> {
+    ptr: NonNull<T>,
+    // None when the pool was full and the value lives on the heap
+    pool: Option<&'static Pool<T>>,
+}
+
+unsafe impl<T: Send + 'static> Send for PoolBox<T> {}
+
+impl<T: Send + 'static> Deref for PoolBox<T> {
+    type Target = T;
+    
+    fn deref(&self) -> &T {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T: Send + 'static> DerefMut for PoolBox<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T: Send + 'static> Drop for PoolBox<T> {
+    fn drop(&mut self) {
+        match self.pool {
+            Some(pool) => {
+                unsafe { core::ptr::drop_in_place(self.ptr.as_ptr()) };
+                pool.release(self.ptr);
+            }
+            None => drop(unsafe { Box::from_raw(self.ptr.as_ptr()) }),
+        }
+    }
+}
+
+pub fn stats() -> Vec<PoolStats> {
+    // Collect the pool list first so no pool lock is taken under POOLS
+    let pools: Vec<&'static dyn PoolInfo> = POOLS.lock().clone();
+    pools.iter().map(|pool| pool.stats()).collect()
+}
\ No newline at end of file
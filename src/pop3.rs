@@ -0,0 +1,273 @@
+// src/pop3.rs
+// Fetching mail over POP3 (RFC 1939), directly over TLS or in the clear.
+// A Fetch is polled from the desktop loop like an HTTP request: it logs in
+// with USER and PASS, lists the mailbox with UIDL, downloads the messages
+// the caller doesn't have yet with RETR and quits. Nothing is deleted from
+// the server.
+use crate::clock::Instant;
+use crate::dns;
+use crate::net::NetError;
+use crate::stream::Stream;
+use crate::tls::TlsError;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+// Only the newest messages are kept track of
+const MAX_MESSAGES: usize = 50;
+const MAX_REPLY: usize = 4 * 1024 * 1024;
+// Without a byte from the server for this long the fetch is given up
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug)]
+pub enum Pop3Error {
+    Net(NetError),
+    Tls(TlsError),
+    // The server's -ERR text
+    Server(String),
+    BadResponse,
+    TooLarge,
+}
+
+impl From<NetError> for Pop3Error {
+    fn from(err: NetError) -> Self {
+        Pop3Error::Net(err)
+    }
+}
+
+impl From<TlsError> for Pop3Error {
+    fn from(err: TlsError) -> Self {
+        match err {
+            TlsError::Net(err) => Pop3Error::Net(err),
+            err => Pop3Error::Tls(err),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Account {
+    pub server: String,
+    pub port: u16,
+    // TLS from the start, as on port 995
+    pub secure: bool,
+    pub user: String,
+    pub password: String,
+}
+
+pub struct Mailbox {
+    // Unique IDs of the messages on the server, oldest first
+    pub uids: Vec<String>,
+    // The messages that were downloaded, by unique ID, as sent
+    pub messages: Vec<(String, Vec<u8>)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Greeting,
+    User,
+    Pass,
+    Uidl,
+    // Downloading the message at this index of `missing`
+    Retr(usize),
+    Quit,
+}
+
+enum Stage {
+    Resolving(dns::Query),
+    Connecting(Stream),
+    Talking(Stream),
+    Finished,
+}
+
+pub struct Fetch {
+    account: Account,
+    // Messages the caller already has
+    known: Vec<String>,
+    stage: Stage,
+    command: Command,
+    buffer: Vec<u8>,
+    // Message numbers and unique IDs still to download
+    missing: Vec<(usize, String)>,
+    mailbox: Mailbox,
+    last_activity: Instant,
+}
+
+struct Reply {
+    ok: bool,
+    status: String,
+    // The lines after the status of a multi-line reply
+    data: Vec<u8>,
+}
+
+fn find(data: &[u8], needle: &[u8]) -> Option<usize> {
+    data.windows(needle.len()).position(|window| window == needle)
+}
+
+// Takes a complete reply off the front of `buffer`. A multi-line reply
+// ends with a line holding only '.', and lines starting with '.' have had
+// another put in front.
+fn take_reply(buffer: &mut Vec<u8>, multi_line: bool) -> Option<Reply> {
+    let line_end = find(buffer, b"\r\n")?;
+    let status = String::from_utf8_lossy(&buffer[..line_end]).into_owned();
+    let ok = status.starts_with("+OK");
+    if !ok || !multi_line {
+        buffer.drain(..line_end + 2);
+        return Some(Reply { ok, status, data: Vec::new() });
+    }
+    let start = line_end + 2;
+    let end = if buffer[start..].starts_with(b".\r\n") { start } else { start + find(&buffer[start..], b"\r\n.\r\n")? + 2 };
+    let mut data = Vec::with_capacity(end - start);
+    for line in buffer[start..end].split_inclusive(|&b| b == b'\n') {
+        data.extend_from_slice(line.strip_prefix(b".").unwrap_or(line));
+    }
+    buffer.drain(..end + 3);
+    Some(Reply { ok, status, data })
+}
+
+impl Fetch {
+    pub fn new(account: Account, known: Vec<String>) -> Self {
+        let query = dns::Query::new(&account.server);
+        Self {
+            account,
+            known,
+            stage: Stage::Resolving(query),
+            command: Command::Greeting,
+            buffer: Vec::new(),
+            missing: Vec::new(),
+            mailbox: Mailbox { uids: Vec::new(), messages: Vec::new() },
+            last_activity: Instant::now(),
+        }
+    }
+    
+    // The mailbox once the fetch is over; None while it's still going.
+    // Call `net::poll` between polls.
+    pub fn poll(&mut self) -> Option<Result<Mailbox, Pop3Error>> {
+        match self.step() {
+            Ok(false) => None,
+            Ok(true) => {
+                self.finish();
+                let mailbox = core::mem::replace(&mut self.mailbox, Mailbox { uids: Vec::new(), messages: Vec::new() });
+                Some(Ok(mailbox))
+            }
+            Err(err) => {
+                self.finish();
+                Some(Err(err))
+            }
+        }
+    }
+    
+    fn finish(&mut self) {
+        match core::mem::replace(&mut self.stage, Stage::Finished) {
+            Stage::Connecting(stream) | Stage::Talking(stream) => stream.close(),
+            _ => {}
+        }
+    }
+    
+    fn send(stream: &mut Stream, line: &str) {
+        stream.write(format!("{}\r\n", line).as_bytes());
+    }
+    
+    // True once the session is over
+    fn step(&mut self) -> Result<bool, Pop3Error> {
+        if self.last_activity.elapsed() >= IDLE_TIMEOUT {
+            return Err(Pop3Error::Net(NetError::TimedOut));
+        }
+        match &mut self.stage {
+            Stage::Resolving(query) => {
+                let addr = match query.poll() {
+                    Some(result) => result?,
+                    None => return Ok(false),
+                };
+                let host = self.account.secure.then_some(self.account.server.as_str());
+                self.stage = Stage::Connecting(Stream::connect(addr, self.account.port, host)?);
+                Ok(false)
+            }
+            Stage::Connecting(stream) => {
+                if stream.is_connected()? {
+                    if let Stage::Connecting(stream) = core::mem::replace(&mut self.stage, Stage::Finished) {
+                        self.stage = Stage::Talking(stream);
+                    }
+                }
+                Ok(false)
+            }
+            Stage::Talking(stream) => {
+                let data = stream.exchange()?;
+                if !data.is_empty() {
+                    self.last_activity = Instant::now();
+                    self.buffer.extend_from_slice(&data);
+                }
+                if self.buffer.len() > MAX_REPLY {
+                    return Err(Pop3Error::TooLarge);
+                }
+                let multi_line = matches!(self.command, Command::Uidl | Command::Retr(_));
+                let reply = match take_reply(&mut self.buffer, multi_line) {
+                    Some(reply) => reply,
+                    None if stream.at_end() && self.command == Command::Quit => return Ok(true),
+                    None if stream.at_end() => return Err(Pop3Error::Net(NetError::Closed)),
+                    None => return Ok(false),
+                };
+                // The session is over either way
+                if self.command == Command::Quit {
+                    return Ok(true);
+                }
+                if !reply.ok {
+                    let text = reply.status.trim_start_matches("-ERR").trim();
+                    return Err(Pop3Error::Server(String::from(text)));
+                }
+                self.command = match self.command {
+                    Command::Greeting => {
+                        Self::send(stream, &format!("USER {}", self.account.user));
+                        Command::User
+                    }
+                    Command::User => {
+                        Self::send(stream, &format!("PASS {}", self.account.password));
+                        Command::Pass
+                    }
+                    Command::Pass => {
+                        Self::send(stream, "UIDL");
+                        Command::Uidl
+                    }
+                    Command::Uidl => {
+                        let listing = String::from_utf8_lossy(&reply.data).into_owned();
+                        let mut messages: Vec<(usize, String)> = Vec::new();
+                        for line in listing.lines() {
+                            let mut fields = line.split_whitespace();
+                            let number = fields.next().and_then(|number| number.parse().ok());
+                            match (number, fields.next()) {
+                                (Some(number), Some(uid)) => messages.push((number, String::from(uid))),
+                                _ => return Err(Pop3Error::BadResponse),
+                            }
+                        }
+                        let newest = messages.split_off(messages.len().saturating_sub(MAX_MESSAGES));
+                        self.mailbox.uids = newest.iter().map(|(_, uid)| uid.clone()).collect();
+                        self.missing = newest.into_iter().filter(|(_, uid)| !self.known.contains(uid)).collect();
+                        Self::next_message(stream, &self.missing, 0)
+                    }
+                    Command::Retr(index) => {
+                        let uid = self.missing[index].1.clone();
+                        self.mailbox.messages.push((uid, reply.data));
+                        Self::next_message(stream, &self.missing, index + 1)
+                    }
+                    Command::Quit => Command::Quit,
+                };
+                Ok(false)
+            }
+            Stage::Finished => Err(Pop3Error::BadResponse),
+        }
+    }
+    
+    // Asks for the missing message at `index`, or quits after the last
+    fn next_message(stream: &mut Stream, missing: &[(usize, String)], index: usize) -> Command {
+        match missing.get(index) {
+            Some((number, _)) => {
+                Self::send(stream, &format!("RETR {}", number));
+                Command::Retr(index)
+            }
+            None => {
+                Self::send(stream, "QUIT");
+                Command::Quit
+            }
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,229 @@
+// src/posix.rs
+// The POSIX subset behind the SDK's file calls, so simple programs port
+// over: descriptors opened on VFS paths, each read and written at its own
+// offset, directory listings in Linux's getdents64 layout, and the clocks.
+// Reads and writes on the pipes the shell sets up come through here too.
+// The userspace module checks the app's pointers and passes slices. Apps
+// have no working directory, so relative paths start at /.
+use crate::pipe::PipeError;
+use crate::task::{self, Descriptor, Pid, TaskError};
+use crate::users;
+use crate::vfs::{self, Access, FileType, VfsError};
+use crate::{clock, kinfo, rtc};
+use alloc::string::String;
+use rustos_sdk::protocol::*;
+
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+
+// What a read or write goes to, taken out of the process table so the
+// disk isn't waited on with it locked
+enum Target {
+    Pipe(Result<usize, PipeError>),
+    // The path and the offset, none to append
+    File(String, Option<u64>),
+    Wrong(Error),
+}
+
+pub fn error(err: VfsError) -> Error {
+    match err {
+        VfsError::NotFound | VfsError::InvalidPath => Error::NotFound,
+        VfsError::NotADirectory => Error::NotADirectory,
+        VfsError::IsADirectory => Error::IsADirectory,
+        VfsError::AlreadyExists => Error::AlreadyExists,
+        VfsError::PermissionDenied => Error::PermissionDenied,
+        VfsError::ReadOnly => Error::ReadOnly,
+        VfsError::NoSpace => Error::NoSpace,
+        VfsError::NotEmpty | VfsError::Unsupported => Error::InvalidArgument,
+        VfsError::Corrupt | VfsError::Io(_) => Error::Io,
+    }
+}
+
+fn task_error(err: TaskError) -> Error {
+    match err {
+        TaskError::NoMemory => Error::NoMemory,
+        TaskError::NoSuchProcess => Error::BadDescriptor,
+        TaskError::TooManyFiles => Error::TooManyFiles,
+    }
+}
+
+fn set_offset(pid: Pid, fd: usize, to: u64) {
+    task::with_file(pid, fd, |file| match file {
+        Descriptor::File { offset, .. } => *offset = to,
+        Descriptor::Directory { next, .. } => *next = to as usize,
+        _ => {}
+    });
+}
+
+pub fn open(pid: Pid, path: &str, flags: u64) -> Result<usize, Error> {
+    let access = flags & O_ACCMODE;
+    if access == O_ACCMODE {
+        return Err(Error::InvalidArgument);
+    }
+    let path = vfs::normalize(&vfs::join("/", path)).map_err(error)?;
+    let meta = match vfs::metadata(&path) {
+        Ok(_) if flags & O_CREAT != 0 && flags & O_EXCL != 0 => return Err(Error::AlreadyExists),
+        Ok(meta) => meta,
+        Err(VfsError::NotFound) if flags & O_CREAT != 0 => {
+            vfs::create(&path, FileType::File).map_err(error)?;
+            vfs::metadata(&path).map_err(error)?
+        }
+        Err(err) => return Err(error(err)),
+    };
+    let (uid, gid) = (users::current_uid(), users::current_gid());
+    let permitted = match access {
+        O_RDONLY => meta.permits(uid, gid, Access::Read),
+        O_WRONLY => meta.permits(uid, gid, Access::Write),
+        _ => meta.permits(uid, gid, Access::Read) && meta.permits(uid, gid, Access::Write),
+    };
+    if !permitted {
+        return Err(Error::PermissionDenied);
+    }
+    let file = if meta.is_dir() {
+        if access != O_RDONLY {
+            return Err(Error::IsADirectory);
+        }
+        Descriptor::Directory { path, next: 0 }
+    } else {
+        if flags & O_DIRECTORY != 0 {
+            return Err(Error::NotADirectory);
+        }
+        if flags & O_TRUNC != 0 && access != O_RDONLY {
+            vfs::write_file(&path, &[]).map_err(error)?;
+        }
+        Descriptor::File { path, flags, offset: 0 }
+    };
+    task::add_file(pid, file).map_err(task_error)
+}
+
+pub fn close(pid: Pid, fd: usize) -> Result<(), Error> {
+    task::with_file(pid, fd, |_| ()).ok_or(Error::BadDescriptor)?;
+    task::set_file(pid, fd, None).map_err(task_error)
+}
+
+// None when the pipe is empty, and the app has to wait
+pub fn read(pid: Pid, fd: usize, buf: &mut [u8]) -> Option<Result<usize, Error>> {
+    let target = task::with_file(pid, fd, |file| match file {
+        Descriptor::Input(reader) => Target::Pipe(reader.read(buf)),
+        Descriptor::File { path, flags, offset } if *flags & O_ACCMODE != O_WRONLY => Target::File(path.clone(), Some(*offset)),
+        Descriptor::Directory { .. } => Target::Wrong(Error::IsADirectory),
+        _ => Target::Wrong(Error::BadDescriptor),
+    });
+    match target.unwrap_or(Target::Wrong(Error::BadDescriptor)) {
+        Target::Pipe(Ok(count)) => Some(Ok(count)),
+        Target::Pipe(Err(_)) => None,
+        Target::File(path, offset) => {
+            let offset = offset.unwrap_or(0);
+            Some(vfs::read(&path, offset, buf).map_err(error).inspect(|&count| {
+                set_offset(pid, fd, offset + count as u64);
+            }))
+        }
+        Target::Wrong(err) => Some(Err(err)),
+    }
+}
+
+// None when the pipe is full, and the app has to wait
+pub fn write(pid: Pid, fd: usize, data: &[u8]) -> Option<Result<usize, Error>> {
+    let target = task::with_file(pid, fd, |file| match file {
+        Descriptor::Output(writer) => Target::Pipe(writer.write(data)),
+        Descriptor::File { path, flags, offset } if *flags & O_ACCMODE != O_RDONLY => {
+            Target::File(path.clone(), (*flags & O_APPEND == 0).then_some(*offset))
+        }
+        Descriptor::Directory { .. } => Target::Wrong(Error::IsADirectory),
+        _ => Target::Wrong(Error::BadDescriptor),
+    });
+    match target {
+        Some(Target::Pipe(Ok(count))) => Some(Ok(count)),
+        Some(Target::Pipe(Err(PipeError::WouldBlock))) => None,
+        Some(Target::Pipe(Err(PipeError::Broken))) => Some(Err(Error::BrokenPipe)),
+        Some(Target::File(path, offset)) => {
+            let result = match offset {
+                Some(offset) => vfs::write(&path, offset, data).map(|count| (offset, count)),
+                // Appending writes at the end as it is now
+                None => vfs::metadata(&path).and_then(|meta| Ok((meta.size, vfs::write(&path, meta.size, data)?))),
+            };
+            Some(result.map_err(error).map(|(offset, count)| {
+                set_offset(pid, fd, offset + count as u64);
+                count
+            }))
+        }
+        Some(Target::Wrong(err)) => Some(Err(err)),
+        // Output with nothing attached goes to the log
+        None if fd == STDOUT || fd == STDERR => {
+            kinfo!("pid {}: {}", pid, String::from_utf8_lossy(data).trim_end());
+            Some(Ok(data.len()))
+        }
+        None => Some(Err(Error::BadDescriptor)),
+    }
+}
+
+// A directory's offset is the number of entries listed, which is what
+// getdents gives as each one's next offset
+pub fn lseek(pid: Pid, fd: usize, offset: i64, whence: u64) -> Result<u64, Error> {
+    let (path, current) = task::with_file(pid, fd, |file| match file {
+        Descriptor::File { path, offset, .. } => Ok((Some(path.clone()), *offset)),
+        Descriptor::Directory { next, .. } => Ok((None, *next as u64)),
+        _ => Err(Error::InvalidArgument),
+    }).ok_or(Error::BadDescriptor)??;
+    let base = match (whence, path) {
+        (SEEK_SET, _) => 0,
+        (SEEK_CUR, _) => current,
+        (SEEK_END, Some(path)) => vfs::metadata(&path).map_err(error)?.size,
+        _ => return Err(Error::InvalidArgument),
+    };
+    let new = base.checked_add_signed(offset).ok_or(Error::InvalidArgument)?;
+    set_offset(pid, fd, new);
+    Ok(new)
+}
+
+// As many entries as fit, from where the last call left off
+pub fn getdents(pid: Pid, fd: usize, buf: &mut [u8]) -> Result<usize, Error> {
+    let (path, next) = task::with_file(pid, fd, |file| match file {
+        Descriptor::Directory { path, next } => Ok((path.clone(), *next)),
+        _ => Err(Error::NotADirectory),
+    }).ok_or(Error::BadDescriptor)??;
+    let entries = vfs::read_dir(&path).map_err(error)?;
+    let mut len = 0;
+    let mut index = next;
+    for entry in entries.iter().skip(next) {
+        let name = entry.name.as_bytes();
+        let record_len = (DIRENT_NAME_OFFSET + name.len() + 1).next_multiple_of(8);
+        if len + record_len > buf.len() {
+            // Not even one fits
+            if len == 0 {
+                return Err(Error::InvalidArgument);
+            }
+            break;
+        }
+        let kind = match entry.file_type {
+            FileType::File => DT_REG,
+            FileType::Directory => DT_DIR,
+            FileType::Symlink => DT_LNK,
+            FileType::Other => DT_UNKNOWN,
+        };
+        let record = &mut buf[len..len + record_len];
+        record.fill(0);
+        record[0..8].copy_from_slice(&entry.inode.to_le_bytes());
+        record[8..16].copy_from_slice(&(index as i64 + 1).to_le_bytes());
+        record[16..18].copy_from_slice(&(record_len as u16).to_le_bytes());
+        record[18] = kind;
+        record[DIRENT_NAME_OFFSET..DIRENT_NAME_OFFSET + name.len()].copy_from_slice(name);
+        len += record_len;
+        index += 1;
+    }
+    set_offset(pid, fd, index as u64);
+    Ok(len)
+}
+
+pub fn clock_gettime(clock: u64) -> Result<Timespec, Error> {
+    match clock {
+        CLOCK_REALTIME => Ok(Timespec { seconds: rtc::unix_time() as i64, nanoseconds: 0 }),
+        CLOCK_MONOTONIC => {
+            let nanos = clock::nanos();
+            Ok(Timespec {
+                seconds: (nanos / NANOS_PER_SECOND) as i64,
+                nanoseconds: (nanos % NANOS_PER_SECOND) as i64,
+            })
+        }
+        _ => Err(Error::InvalidArgument),
+    }
+}
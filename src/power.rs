@@ -0,0 +1,86 @@
+// src/power.rs
+use crate::acpi::{self, PowerManagement};
+use crate::{block_cache, bootargs, cpu, cpu_usage, hlt_loop, kerror, kinfo};
+use core::arch::asm;
+use core::sync::atomic::AtomicU64;
+use x86_64::instructions::interrupts;
+use x86_64::instructions::port::Port;
+
+// PM1 control register bits
+const SCI_EN: u16 = 1 << 0;
+const SLP_EN: u16 = 1 << 13;
+const SLP_TYP_SHIFT: u16 = 10;
+// Polls for the firmware to hand over to ACPI mode
+const ACPI_ENABLE_TRIES: usize = 100_000;
+
+// What MONITOR watches. Nothing writes it; only interrupts end the wait.
+static WAKE: AtomicU64 = AtomicU64::new(0);
+
+// Whether the machine runs on battery. Nothing reads the battery yet, so
+// it's what the power= boot argument says, and AC without one.
+pub fn on_battery() -> bool {
+    bootargs::get("power") == Some("battery")
+}
+
+// Waits for the next interrupt, leaving interrupts enabled. With MWAIT a
+// deep wait goes to the deepest C-state the CPU has, which saves the most
+// power but is the slowest to wake from, and a shallow one to C1 like hlt.
+// The time is charged to idle in the Activity Monitor.
+pub fn idle(deep: bool) {
+    let info = cpu::info();
+    cpu_usage::set_idle(true);
+    if info.features.mwait {
+        let hint = if deep { info.deep_idle_hint } else { 0 };
+        unsafe {
+            asm!("monitor", in("rax") WAKE.as_ptr(), in("ecx") 0, in("edx") 0, options(nostack, preserves_flags));
+            // STI holds interrupts off until after the next instruction, so
+            // one can't slip in between and leave MWAIT waiting for another
+            asm!("sti", "mwait", in("eax") hint, in("ecx") 0, options(nostack));
+        }
+    } else {
+        interrupts::enable_and_hlt();
+    }
+    cpu_usage::set_idle(false);
+}
+
+// Enters S5 the way the FADT and the DSDT's \_S5 say to; returns if the
+// machine is still on
+unsafe fn acpi_soft_off(power: &PowerManagement) {
+    let (slp_typ_a, slp_typ_b) = match power.s5 {
+        Some(values) => values,
+        None => return,
+    };
+    let mut control = Port::<u16>::new(power.pm1a_control);
+    if control.read() & SCI_EN == 0 && power.smi_command != 0 && power.acpi_enable != 0 {
+        Port::<u8>::new(power.smi_command).write(power.acpi_enable);
+        for _ in 0..ACPI_ENABLE_TRIES {
+            if control.read() & SCI_EN != 0 {
+                break;
+            }
+            core::hint::spin_loop();
+        }
+    }
+    control.write((slp_typ_a as u16) << SLP_TYP_SHIFT | SLP_EN);
+    if power.pm1b_control != 0 {
+        Port::<u16>::new(power.pm1b_control).write((slp_typ_b as u16) << SLP_TYP_SHIFT | SLP_EN);
+    }
+}
+
+// Flushes everything that must survive a power cycle, then powers off
+pub fn shutdown() -> ! {
+    kinfo!("power: syncing disks");
+    if let Err(err) = block_cache::sync_all() {
+        kerror!("power: sync failed: {:?}", err);
+    }
+    
+    x86_64::instructions::interrupts::disable();
+    unsafe {
+        if let Some(power) = acpi::power() {
+            acpi_soft_off(power);
+        }
+        // Without ACPI: QEMU (PIIX4 PM) and Bochs/older QEMU shutdown ports
+        Port::<u16>::new(0x604).write(0x2000);
+        Port::<u16>::new(0xB004).write(0x2000);
+    }
+    hlt_loop();
+}
\ No newline at end of file
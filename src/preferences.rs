@@ -0,0 +1,65 @@
+// src/preferences.rs
+// System-wide settings that persist across boots: one file per domain
+// under /Library/Preferences, holding `key = value` lines. Callers that
+// need a value every frame should keep their own copy.
+use crate::vfs::{self, VfsError};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+const DIRECTORY: &str = "/Library/Preferences";
+// The app whose undo history setting changes go to
+pub const APP: &str = "System Preferences";
+
+fn path(domain: &str) -> String {
+    vfs::join(DIRECTORY, domain)
+}
+
+// Every key in a domain with its value, in file order
+pub fn read(domain: &str) -> Vec<(String, String)> {
+    let data = match vfs::read_file(&path(domain)) {
+        Ok(data) => data,
+        Err(_) => return Vec::new(),
+    };
+    String::from_utf8_lossy(&data)
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (String::from(key.trim()), String::from(value.trim())))
+        .collect()
+}
+
+pub fn get(domain: &str, key: &str) -> Option<String> {
+    read(domain).into_iter().find(|(k, _)| k == key).map(|(_, value)| value)
+}
+
+pub fn get_bool(domain: &str, key: &str, default: bool) -> bool {
+    match get(domain, key).as_deref() {
+        Some("true") => true,
+        Some("false") => false,
+        _ => default,
+    }
+}
+
+pub fn set(domain: &str, key: &str, value: &str) -> Result<(), VfsError> {
+    let mut entries = read(domain);
+    match entries.iter_mut().find(|(k, _)| k == key) {
+        Some(entry) => entry.1 = String::from(value),
+        None => entries.push((String::from(key), String::from(value))),
+    }
+    vfs::create_dir_all(DIRECTORY)?;
+    let text: String = entries.iter().map(|(k, v)| format!("{} = {}\n", k, v)).collect();
+    vfs::write_file(&path(domain), text.as_bytes())
+}
+
+pub fn remove(domain: &str, key: &str) -> Result<(), VfsError> {
+    let entries = read(domain);
+    if entries.iter().all(|(k, _)| k != key) {
+        return Ok(());
+    }
+    let text: String = entries.iter().filter(|(k, _)| k != key).map(|(k, v)| format!("{} = {}\n", k, v)).collect();
+    vfs::write_file(&path(domain), text.as_bytes())
+}
+
+pub fn set_bool(domain: &str, key: &str, value: bool) -> Result<(), VfsError> {
+    set(domain, key, if value { "true" } else { "false" })
+}
\ No newline at end of file
@@ -0,0 +1,372 @@
+// src/preview.rs
+// The Preview app: shows one image at a time from a folder. Images are
+// fitted to the window (never enlarged) or shown at actual size, where
+// dragging or the arrow keys pan. Cmd+R and Cmd+L rotate by 90°, Cmd+0 and
+// Cmd+9 switch between actual size and fit, and in fit mode the left and
+// right arrows step through the other images in the folder. A rotated
+// image is an unsaved change until Cmd+S writes it back. While the
+// window can't be seen, the decoded image can be paged out to swap; it's
+// read back the next time the window draws. The image shown goes to the
+// services, to be made the wallpaper.
+use crate::bmp::{self, BmpError, Image};
+use crate::bus::{self, Message};
+use crate::share::Data;
+use crate::graphics::{Graphics, Color};
+use crate::keyboard::{Key, KeyEvent};
+use crate::swap::{self, Slot};
+use crate::vfs::{self, VfsError};
+use crate::widgets::Button;
+use crate::kwarn;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::sync::Mutex;
+
+const TOOLBAR_HEIGHT: usize = 30;
+const PAN_STEP: usize = 40;
+// Toolbar buttons, left to right, with their x offsets
+const PREVIOUS_X: usize = 10;
+const NEXT_X: usize = 38;
+const ZOOM_X: usize = 76;
+const ROTATE_X: usize = 122;
+const BUTTON_Y: usize = 6;
+const APP: &str = "Preview";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Zoom {
+    Fit,
+    Actual,
+}
+
+#[derive(Debug)]
+enum LoadError {
+    Read(VfsError),
+    Decode(BmpError),
+    // Paged out, and there wasn't the heap to read it back
+    Swapped,
+}
+
+struct PreviewState {
+    path: String,
+    // Images in the same folder, sorted by name
+    folder: Vec<String>,
+    image: Result<Image, LoadError>,
+    // The image in swap, with its width and height
+    swapped: Option<(Slot, usize, usize)>,
+    zoom: Zoom,
+    // Clockwise quarter turns
+    rotation: u8,
+    // Top-left of the view in the displayed image, in screen pixels
+    pan: (usize, usize),
+    previous: Button,
+    next: Button,
+    zoom_button: Button,
+    rotate: Button,
+}
+
+static STATE: Mutex<Option<PreviewState>> = Mutex::new(None);
+
+pub fn is_image(path: &str) -> bool {
+    path.to_lowercase().ends_with(".bmp")
+}
+
+fn load(path: &str) -> Result<Image, LoadError> {
+    let data = vfs::read_file(path).map_err(LoadError::Read)?;
+    bmp::decode(&data).map_err(LoadError::Decode)
+}
+
+fn folder_images(path: &str) -> Vec<String> {
+    let (dir, _) = vfs::parent_and_name(path);
+    let mut images: Vec<String> = vfs::read_dir(dir)
+        .map(|entries| entries.into_iter().map(|entry| vfs::join(dir, &entry.name)).filter(|p| is_image(p)).collect())
+        .unwrap_or_default();
+    images.sort();
+    images
+}
+
+pub fn open(path: &str) {
+    *STATE.lock() = Some(PreviewState {
+        path: String::from(path),
+        folder: folder_images(path),
+        image: load(path),
+        swapped: None,
+        zoom: Zoom::Fit,
+        rotation: 0,
+        pan: (0, 0),
+        previous: Button::new("<"),
+        next: Button::new(">"),
+        zoom_button: Button::new("1:1"),
+        rotate: Button::new("Rotate"),
+    });
+    bus::post(Message::Edited { app: String::from(APP), edited: false });
+}
+
+impl PreviewState {
+    // Moves `step` images through the folder, wrapping around
+    fn step(&mut self, step: isize) {
+        let count = self.folder.len() as isize;
+        let current = match self.folder.iter().position(|p| *p == self.path) {
+            Some(index) if count > 1 => index as isize,
+            _ => return,
+        };
+        self.path = self.folder[(current + step).rem_euclid(count) as usize].clone();
+        self.image = load(&self.path);
+        self.swapped = None;
+        self.set_rotation(0);
+        self.pan = (0, 0);
+    }
+    
+    fn set_rotation(&mut self, rotation: u8) {
+        self.rotation = rotation;
+        bus::post(Message::Edited { app: String::from(APP), edited: rotation != 0 });
+    }
+    
+    fn set_zoom(&mut self, zoom: Zoom) {
+        self.zoom = zoom;
+        self.pan = (0, 0);
+        self.zoom_button.label = if zoom == Zoom::Fit { "1:1" } else { "Fit" };
+    }
+    
+    fn rotate(&mut self, quarter_turns: u8) {
+        self.set_rotation((self.rotation + quarter_turns) % 4);
+        self.pan = (0, 0);
+    }
+    
+    // Reads the image back if it was paged out
+    fn page_in(&mut self) {
+        if let Some((slot, width, height)) = self.swapped.take() {
+            match page_in(&slot, width, height) {
+                Ok(image) => self.image = Ok(image),
+                Err(LoadError::Swapped) => self.swapped = Some((slot, width, height)),
+                Err(err) => self.image = Err(err),
+            }
+        }
+    }
+    
+    // Writes the image back to its file the way it's turned
+    fn save(&mut self) -> Result<(), VfsError> {
+        if self.rotation == 0 {
+            return Ok(());
+        }
+        self.page_in();
+        let image = match &self.image {
+            Ok(image) => image,
+            Err(LoadError::Read(err)) => return Err(*err),
+            Err(_) => return Err(VfsError::Unsupported),
+        };
+        let (width, height) = if self.rotation % 2 == 1 { (image.height, image.width) } else { (image.width, image.height) };
+        let mut pixels = Vec::new();
+        pixels.try_reserve_exact(width * height).map_err(|_| VfsError::NoSpace)?;
+        for y in 0..height {
+            for x in 0..width {
+                let (ix, iy) = unrotate(image, self.rotation, x, y);
+                pixels.push(image.pixel(ix, iy));
+            }
+        }
+        let rotated = Image::from_pixels(width, height, pixels);
+        vfs::write_file(&self.path, &bmp::encode(&rotated))?;
+        self.image = Ok(rotated);
+        self.set_rotation(0);
+        Ok(())
+    }
+    
+    fn pan_by(&mut self, dx: isize, dy: isize) {
+        if self.zoom == Zoom::Actual {
+            self.pan.0 = (self.pan.0 as isize + dx).max(0) as usize;
+            self.pan.1 = (self.pan.1 as isize + dy).max(0) as usize;
+        }
+    }
+}
+
+pub fn handle_key(event: &KeyEvent) {
+    let mut state = STATE.lock();
+    let state = match state.as_mut() {
+        Some(state) => state,
+        None => return,
+    };
+    match (event.key, event.cmd, state.zoom) {
+        (Key::R, true, _) => state.rotate(1),
+        (Key::L, true, _) => state.rotate(3),
+        (Key::S, true, _) => {
+            if let Err(err) = state.save() {
+                kwarn!("preview: can't save {}: {:?}", state.path, err);
+            }
+        }
+        (Key::Digit0, true, _) => state.set_zoom(Zoom::Actual),
+        (Key::Digit9, true, _) => state.set_zoom(Zoom::Fit),
+        (Key::ArrowLeft, false, Zoom::Fit) => state.step(-1),
+        (Key::ArrowRight, false, Zoom::Fit) => state.step(1),
+        (Key::ArrowLeft, false, Zoom::Actual) => state.pan_by(-(PAN_STEP as isize), 0),
+        (Key::ArrowRight, false, Zoom::Actual) => state.pan_by(PAN_STEP as isize, 0),
+        (Key::ArrowUp, false, _) => state.pan_by(0, -(PAN_STEP as isize)),
+        (Key::ArrowDown, false, _) => state.pan_by(0, PAN_STEP as isize),
+        _ => {}
+    }
+}
+
+// Saves the rotated image, for the close sheet's Save
+pub fn save() -> Result<(), VfsError> {
+    STATE.lock().as_mut().map_or(Ok(()), |state| state.save())
+}
+
+// The image shown, for the services
+pub fn service_data() -> Option<Data> {
+    STATE.lock().as_ref().map(|state| Data::Image(state.path.clone()))
+}
+
+// `x` and `y` are relative to the window content, as drawn by `draw`
+pub fn click(x: usize, y: usize) {
+    if let Some(state) = STATE.lock().as_mut() {
+        if state.previous.contains(x, y, PREVIOUS_X, BUTTON_Y) {
+            state.step(-1);
+        } else if state.next.contains(x, y, NEXT_X, BUTTON_Y) {
+            state.step(1);
+        } else if state.zoom_button.contains(x, y, ZOOM_X, BUTTON_Y) {
+            let zoom = if state.zoom == Zoom::Fit { Zoom::Actual } else { Zoom::Fit };
+            state.set_zoom(zoom);
+        } else if state.rotate.contains(x, y, ROTATE_X, BUTTON_Y) {
+            state.rotate(1);
+        }
+    }
+}
+
+// The toolbar buttons in Tab order, as (x, y, width, height) in the content
+pub fn controls() -> Vec<(usize, usize, usize, usize)> {
+    let state = STATE.lock();
+    let state = match state.as_ref() {
+        Some(state) => state,
+        None => return Vec::new(),
+    };
+    [(&state.previous, PREVIOUS_X), (&state.next, NEXT_X), (&state.zoom_button, ZOOM_X), (&state.rotate, ROTATE_X)]
+        .iter()
+        .map(|(button, x)| (*x, BUTTON_Y, button.width(), Button::HEIGHT))
+        .collect()
+}
+
+// The image follows the pointer, so the view moves the other way
+pub fn drag(dx: i32, dy: i32) {
+    if let Some(state) = STATE.lock().as_mut() {
+        state.pan_by(-dx as isize, -dy as isize);
+    }
+}
+
+// Writes the decoded image out to swap; the bytes of heap freed
+pub fn page_out() -> usize {
+    let mut state = STATE.lock();
+    let state = match state.as_mut() {
+        Some(state) => state,
+        None => return 0,
+    };
+    let (width, height, slot) = match &state.image {
+        Ok(image) => {
+            let rgb = image.pixels().iter().flat_map(|color| [color.r, color.g, color.b]);
+            (image.width, image.height, swap::page_out(image.width * image.height * 3, rgb))
+        }
+        Err(_) => return 0,
+    };
+    match slot {
+        Ok(slot) => {
+            state.swapped = Some((slot, width, height));
+            state.image = Err(LoadError::Swapped);
+            width * height * core::mem::size_of::<Color>()
+        }
+        Err(_) => 0,
+    }
+}
+
+fn page_in(slot: &Slot, width: usize, height: usize) -> Result<Image, LoadError> {
+    let mut pixels = Vec::new();
+    pixels.try_reserve_exact(width * height).map_err(|_| LoadError::Swapped)?;
+    let (mut rgb, mut filled) = ([0u8; 3], 0);
+    swap::page_in(slot, |chunk| {
+        for &byte in chunk {
+            rgb[filled] = byte;
+            filled += 1;
+            if filled == 3 {
+                pixels.push(Color::new(rgb[0], rgb[1], rgb[2]));
+                filled = 0;
+            }
+        }
+    })
+    .map_err(LoadError::Read)?;
+    Ok(Image::from_pixels(width, height, pixels))
+}
+
+// Maps a pixel of the rotated image back to the stored image
+fn unrotate(image: &Image, rotation: u8, x: usize, y: usize) -> (usize, usize) {
+    match rotation {
+        1 => (y, image.height - 1 - x),
+        2 => (image.width - 1 - x, image.height - 1 - y),
+        3 => (image.width - 1 - y, x),
+        _ => (x, y),
+    }
+}
+
+pub fn draw(graphics: &mut Graphics, x: usize, y: usize, width: usize, height: usize) {
+    let mut state = STATE.lock();
+    let state = match state.as_mut() {
+        Some(state) => state,
+        None => {
+            graphics.draw_text("Open an image from Finder", x + width / 2 - 100, y + height / 2, Color::GRAY);
+            return;
+        }
+    };
+    state.page_in();
+    
+    // Toolbar
+    graphics.draw_rect(x + 1, y, width - 2, TOOLBAR_HEIGHT, Color::new(236, 236, 236));
+    state.previous.draw(graphics, x + PREVIOUS_X, y + BUTTON_Y);
+    state.next.draw(graphics, x + NEXT_X, y + BUTTON_Y);
+    state.zoom_button.draw(graphics, x + ZOOM_X, y + BUTTON_Y);
+    state.rotate.draw(graphics, x + ROTATE_X, y + BUTTON_Y);
+    let name = vfs::parent_and_name(&state.path).1;
+    let label_x = ROTATE_X + state.rotate.width() + 12;
+    let label: String = name.chars().take(width.saturating_sub(label_x + 10) / 8).collect();
+    graphics.draw_text(&label, x + label_x, y + 11, Color::BLACK);
+    
+    // Image area
+    let (view_x, view_y) = (x + 1, y + TOOLBAR_HEIGHT);
+    let (view_width, view_height) = (width - 2, height - TOOLBAR_HEIGHT - 1);
+    graphics.draw_rect(view_x, view_y, view_width, view_height, Color::new(50, 50, 50));
+    let image = match &state.image {
+        Ok(image) => image,
+        Err(err) => {
+            let message = match err {
+                LoadError::Read(err) => format!("Cannot read image ({:?})", err),
+                LoadError::Decode(err) => format!("Cannot open image ({:?})", err),
+                LoadError::Swapped => String::from("Not enough memory to show the image"),
+            };
+            graphics.draw_text(&message, view_x + 20, view_y + 20, Color::WHITE);
+            return;
+        }
+    };
+    
+    let (rotated_width, rotated_height) = if state.rotation % 2 == 1 {
+        (image.height, image.width)
+    } else {
+        (image.width, image.height)
+    };
+    // Scale as num/den: shrink to fit, or 1:1
+    let too_big = rotated_width > view_width || rotated_height > view_height;
+    let (num, den) = match state.zoom {
+        Zoom::Fit if too_big && view_width * rotated_height < view_height * rotated_width => (view_width, rotated_width),
+        Zoom::Fit if too_big => (view_height, rotated_height),
+        _ => (1, 1),
+    };
+    let shown_width = rotated_width * num / den;
+    let shown_height = rotated_height * num / den;
+    
+    // Center what fits and keep the pan inside what doesn't
+    state.pan.0 = state.pan.0.min(shown_width.saturating_sub(view_width));
+    state.pan.1 = state.pan.1.min(shown_height.saturating_sub(view_height));
+    let left = view_x + view_width.saturating_sub(shown_width) / 2;
+    let top = view_y + view_height.saturating_sub(shown_height) / 2;
+    for sy in 0..shown_height.min(view_height) {
+        let ry = ((sy + state.pan.1) * den / num).min(rotated_height - 1);
+        for sx in 0..shown_width.min(view_width) {
+            let rx = ((sx + state.pan.0) * den / num).min(rotated_width - 1);
+            let (ix, iy) = unrotate(image, state.rotation, rx, ry);
+            graphics.set_pixel(left + sx, top + sy, image.pixel(ix, iy));
+        }
+    }
+}
\ No newline at end of file
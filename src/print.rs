@@ -0,0 +1,186 @@
+// src/print.rs
+// Printing, to a file: there are no printer drivers, so a document is laid
+// out on US Letter pages and written to the user's Documents folder, as
+// PostScript for a real printer or a viewer, or as plain text with a form
+// feed between pages. Apps hand over what they'd print as StyledText, and
+// the same layout as on screen wraps it to COLUMNS of 10 pt Courier. A
+// heading never ends a page; it moves to the top of the next one.
+use crate::styled_text::{Block, Line, StyledText};
+use crate::users;
+use crate::vfs::{self, VfsError};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+pub const COLUMNS: usize = 76;
+pub const LINES_PER_PAGE: usize = 54;
+// The layout works in 8-pixel character cells
+const CELL_WIDTH: usize = 8;
+// Page geometry, in points
+const PAGE_WIDTH: usize = 612;
+const PAGE_HEIGHT: usize = 792;
+const MARGIN: usize = 72;
+const FONT_SIZE: usize = 10;
+const ADVANCE: usize = 6;
+const LEADING: usize = 12;
+const FOOTER_SIZE: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    PostScript,
+    PlainText,
+}
+
+impl Format {
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::PostScript => "PostScript",
+            Format::PlainText => "Plain Text",
+        }
+    }
+    
+    fn extension(self) -> &'static str {
+        match self {
+            Format::PostScript => "ps",
+            Format::PlainText => "txt",
+        }
+    }
+}
+
+fn is_blank(line: &Line) -> bool {
+    line.block != Block::Rule && line.runs.is_empty()
+}
+
+// The text laid out and split into pages, without blank lines at the top
+// of a page
+pub fn paginate(text: &StyledText) -> Vec<Vec<Line>> {
+    let mut pages = Vec::new();
+    let mut page: Vec<Line> = Vec::new();
+    let mut lines = text.layout(COLUMNS * CELL_WIDTH).into_iter().peekable();
+    while let Some(line) = lines.next() {
+        if page.is_empty() && is_blank(&line) {
+            continue;
+        }
+        let last_on_page = page.len() == LINES_PER_PAGE - 1;
+        let heading = matches!(line.block, Block::Heading(_));
+        if page.len() == LINES_PER_PAGE || (last_on_page && heading && lines.peek().is_some()) {
+            pages.push(core::mem::take(&mut page));
+            if is_blank(&line) {
+                continue;
+            }
+        }
+        page.push(line);
+    }
+    if !page.is_empty() || pages.is_empty() {
+        pages.push(page);
+    }
+    pages
+}
+
+// Inside a PostScript string: backslashes and parentheses escaped, and
+// anything Courier can't show as ?
+fn ps_string(text: &str) -> String {
+    let mut escaped = String::new();
+    for ch in text.chars() {
+        match ch {
+            '\\' | '(' | ')' => {
+                escaped.push('\\');
+                escaped.push(ch);
+            }
+            ' '..='~' => escaped.push(ch),
+            _ => escaped.push('?'),
+        }
+    }
+    escaped
+}
+
+fn footer(title: &str, page: usize, pages: usize) -> String {
+    format!("{} - Page {} of {}", title, page, pages)
+}
+
+fn postscript(title: &str, pages: &[Vec<Line>]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "%!PS-Adobe-3.0");
+    let _ = writeln!(out, "%%Title: {}", ps_string(title));
+    let _ = writeln!(out, "%%Creator: RustOS");
+    let _ = writeln!(out, "%%Pages: {}", pages.len());
+    let _ = writeln!(out, "%%BoundingBox: 0 0 {} {}", PAGE_WIDTH, PAGE_HEIGHT);
+    let _ = writeln!(out, "%%EndComments");
+    let _ = writeln!(out, "/F {{ findfont exch scalefont setfont }} def");
+    for (number, page) in pages.iter().enumerate() {
+        let _ = writeln!(out, "%%Page: {} {}", number + 1, number + 1);
+        for (row, line) in page.iter().enumerate() {
+            let baseline = PAGE_HEIGHT - MARGIN - FONT_SIZE - row * LEADING;
+            if line.block == Block::Rule {
+                let y = baseline + FONT_SIZE / 2;
+                let _ = writeln!(out, "0.5 setlinewidth {} {} moveto {} 0 rlineto stroke", MARGIN, y, COLUMNS * ADVANCE);
+                continue;
+            }
+            for run in line.runs.iter() {
+                let font = match (run.style.bold, run.style.italic) {
+                    (true, true) => "Courier-BoldOblique",
+                    (true, false) => "Courier-Bold",
+                    (false, true) => "Courier-Oblique",
+                    (false, false) => "Courier",
+                };
+                let x = MARGIN + run.x / CELL_WIDTH * ADVANCE;
+                let _ = writeln!(out, "{} /{} F {} {} moveto ({}) show", FONT_SIZE, font, x, baseline, ps_string(&run.text));
+            }
+        }
+        let footer = footer(title, number + 1, pages.len());
+        let _ = writeln!(out, "{} /Courier F {} {} moveto ({}) show", FOOTER_SIZE, MARGIN, MARGIN / 2, ps_string(&footer));
+        let _ = writeln!(out, "showpage");
+    }
+    let _ = writeln!(out, "%%EOF");
+    out
+}
+
+fn plain_text(title: &str, pages: &[Vec<Line>]) -> String {
+    let mut out = String::new();
+    for (number, page) in pages.iter().enumerate() {
+        if number > 0 {
+            out.push('\x0C');
+        }
+        for line in page.iter() {
+            if line.block == Block::Rule {
+                out.extend(core::iter::repeat_n('-', COLUMNS));
+            } else {
+                out.push_str(line.text().trim_end());
+            }
+            out.push('\n');
+        }
+        // The footer sits at the bottom of a full page
+        for _ in page.len()..LINES_PER_PAGE {
+            out.push('\n');
+        }
+        let _ = writeln!(out, "\n{}", footer(title, number + 1, pages.len()));
+    }
+    out
+}
+
+// "Title.ps" in Documents, or "Title 2.ps" and so on if that's taken
+fn output_path(title: &str, format: Format) -> Result<String, VfsError> {
+    let documents = format!("/Users/{}/Documents", users::user_name(users::current_uid()));
+    vfs::create_dir_all(&documents)?;
+    let name: String = title.chars().map(|ch| if ch == '/' { '-' } else { ch }).collect();
+    let mut path = format!("{}/{}.{}", documents, name, format.extension());
+    let mut copy = 2;
+    while vfs::metadata(&path).is_ok() {
+        path = format!("{}/{} {}.{}", documents, name, copy, format.extension());
+        copy += 1;
+    }
+    Ok(path)
+}
+
+// Lays the text out and writes it; where it went and how many pages
+pub fn print(title: &str, text: &StyledText, format: Format) -> Result<(String, usize), VfsError> {
+    let pages = paginate(text);
+    let output = match format {
+        Format::PostScript => postscript(title, &pages),
+        Format::PlainText => plain_text(title, &pages),
+    };
+    let path = output_path(title, format)?;
+    vfs::write_file(&path, output.as_bytes())?;
+    Ok((path, pages.len()))
+}
\ No newline at end of file
@@ -0,0 +1,126 @@
+// src/print_dialog.rs
+// The Print dialog, for Cmd+P: what's being printed and on how many pages,
+// and the format of the file it's printed to. Up and Down pick the format,
+// Enter prints and Escape cancels.
+use crate::graphics::{Graphics, Color, SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::keyboard::Key;
+use crate::print::{self, Format};
+use crate::styled_text::StyledText;
+use crate::widgets::Button;
+use alloc::format;
+use alloc::string::String;
+
+const WIDTH: usize = 280;
+const ROW_HEIGHT: usize = 22;
+const FORMATS: [Format; 2] = [Format::PostScript, Format::PlainText];
+// Layout, relative to the dialog
+const LIST_Y: usize = 52;
+const BUTTON_Y: usize = LIST_Y + FORMATS.len() * ROW_HEIGHT + 12;
+const HEIGHT: usize = BUTTON_Y + 32;
+const CANCEL_X: usize = 140;
+const PRINT_X: usize = 210;
+
+const CANCEL: Button = Button::new("Cancel");
+const PRINT: Button = Button::new("Print");
+
+// What the dialog was closed with
+pub struct PrintJob {
+    pub title: String,
+    pub text: StyledText,
+    pub format: Format,
+}
+
+pub struct PrintDialog {
+    pub is_visible: bool,
+    title: String,
+    text: StyledText,
+    pages: usize,
+    selected_index: usize,
+}
+
+impl PrintDialog {
+    pub fn new() -> Self {
+        Self { is_visible: false, title: String::new(), text: StyledText::default(), pages: 0, selected_index: 0 }
+    }
+    
+    pub fn show(&mut self, title: &str, text: StyledText) {
+        self.title = String::from(title);
+        self.pages = print::paginate(&text).len();
+        self.text = text;
+        self.is_visible = true;
+    }
+    
+    pub fn hide(&mut self) {
+        self.is_visible = false;
+        self.text = StyledText::default();
+    }
+    
+    fn origin(&self) -> (usize, usize) {
+        ((SCREEN_WIDTH - WIDTH) / 2, (SCREEN_HEIGHT - HEIGHT) / 2)
+    }
+    
+    fn confirm(&mut self) -> Option<PrintJob> {
+        let job = PrintJob {
+            title: core::mem::take(&mut self.title),
+            text: core::mem::take(&mut self.text),
+            format: FORMATS[self.selected_index],
+        };
+        self.hide();
+        Some(job)
+    }
+    
+    pub fn draw(&self, graphics: &mut Graphics) {
+        if !self.is_visible {
+            return;
+        }
+        let (x, y) = self.origin();
+        graphics.draw_rounded_rect(x, y, WIDTH, HEIGHT, Color::new(236, 236, 236));
+        graphics.draw_rect_outline(x, y, WIDTH, HEIGHT, Color::GRAY);
+        let heading: String = format!("Print \"{}\"", self.title).chars().take((WIDTH - 24) / 8).collect();
+        graphics.draw_text(&heading, x + 12, y + 12, Color::BLACK);
+        let pages = if self.pages == 1 { String::from("1 page, US Letter") } else { format!("{} pages, US Letter", self.pages) };
+        graphics.draw_text(&pages, x + 12, y + 28, Color::GRAY);
+        
+        for (i, format) in FORMATS.iter().enumerate() {
+            let row_y = y + LIST_Y + i * ROW_HEIGHT;
+            let color = if i == self.selected_index {
+                graphics.draw_rounded_rect(x + 8, row_y, WIDTH - 16, ROW_HEIGHT - 2, Color::BLUE);
+                Color::WHITE
+            } else {
+                Color::BLACK
+            };
+            graphics.draw_text(format.name(), x + 16, row_y + 6, color);
+        }
+        
+        CANCEL.draw(graphics, x + CANCEL_X, y + BUTTON_Y);
+        graphics.draw_rounded_rect(x + PRINT_X, y + BUTTON_Y, PRINT.width(), Button::HEIGHT, Color::BLUE);
+        graphics.draw_text(PRINT.label, x + PRINT_X + 8, y + BUTTON_Y + 5, Color::WHITE);
+    }
+    
+    // The dialog is modal, so clicks outside it do nothing
+    pub fn click(&mut self, px: usize, py: usize) -> Option<PrintJob> {
+        let (x, y) = self.origin();
+        if px < x || px >= x + WIDTH {
+            return None;
+        }
+        if py >= y + LIST_Y && py < y + LIST_Y + FORMATS.len() * ROW_HEIGHT {
+            self.selected_index = (py - y - LIST_Y) / ROW_HEIGHT;
+        } else if CANCEL.contains(px, py, x + CANCEL_X, y + BUTTON_Y) {
+            self.hide();
+        } else if PRINT.contains(px, py, x + PRINT_X, y + BUTTON_Y) {
+            return self.confirm();
+        }
+        None
+    }
+    
+    pub fn key(&mut self, key: Key) -> Option<PrintJob> {
+        match key {
+            Key::ArrowUp => self.selected_index = self.selected_index.saturating_sub(1),
+            Key::ArrowDown => self.selected_index = (self.selected_index + 1).min(FORMATS.len() - 1),
+            Key::Escape => self.hide(),
+            Key::Enter => return self.confirm(),
+            _ => {}
+        }
+        None
+    }
+}
\ No newline at end of file
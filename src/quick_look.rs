@@ -0,0 +1,170 @@
+// src/quick_look.rs
+// Quick Look: Space in the Finder floats a preview of the selected item
+// over its window without opening an app. Text files show their first few
+// KB, images are decoded and fitted to the panel, and anything else shows
+// its kind, size and dates. Space or Escape puts it away; the arrow keys
+// move the Finder's selection and the preview follows. It's drawn only
+// while the Finder is in front.
+use crate::bmp::{self, Image};
+use crate::get_info;
+use crate::graphics::{Graphics, Color};
+use crate::preview;
+use crate::rtc::DateTime;
+use crate::system_info;
+use crate::vfs::{self, FileType, Metadata};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::sync::Mutex;
+
+// Read from the start of a text file
+const TEXT_BYTES: usize = 4096;
+const X: usize = 140;
+const Y: usize = 60;
+const WIDTH: usize = 360;
+const HEIGHT: usize = 340;
+const TITLE_HEIGHT: usize = 28;
+const MARGIN: usize = 12;
+const CHAR_WIDTH: usize = 8;
+const LINE_HEIGHT: usize = 12;
+const VALUE_X: usize = 90;
+
+enum Content {
+    // Already wrapped to the panel
+    Text(Vec<String>),
+    Image(Image),
+    Info(Vec<(&'static str, String)>),
+}
+
+struct Panel {
+    name: String,
+    content: Content,
+}
+
+static PANEL: Mutex<Option<Panel>> = Mutex::new(None);
+
+pub fn show(path: &str) {
+    let name = String::from(vfs::parent_and_name(path).1);
+    *PANEL.lock() = Some(Panel { name, content: load(path) });
+}
+
+pub fn hide() {
+    *PANEL.lock() = None;
+}
+
+pub fn is_visible() -> bool {
+    PANEL.lock().is_some()
+}
+
+// Images that won't decode and files that aren't text fall back to the
+// item's details
+fn load(path: &str) -> Content {
+    let meta = match vfs::metadata(path) {
+        Ok(meta) => meta,
+        Err(err) => return Content::Info(vec![("Error:", format!("{:?}", err))]),
+    };
+    if meta.file_type == FileType::File && preview::is_image(path) {
+        if let Some(image) = vfs::read_file(path).ok().and_then(|data| bmp::decode(&data).ok()) {
+            return Content::Image(image);
+        }
+    } else if meta.file_type == FileType::File {
+        let mut buf = vec![0; TEXT_BYTES.min(meta.size as usize)];
+        if let Ok(read) = vfs::read(path, 0, &mut buf) {
+            buf.truncate(read);
+            if is_text(&buf) {
+                return Content::Text(wrap(&String::from_utf8_lossy(&buf)));
+            }
+        }
+    }
+    Content::Info(info(path, &meta))
+}
+
+// No NULs and valid UTF-8, but for a character cut off at the end
+pub fn is_text(bytes: &[u8]) -> bool {
+    !bytes.contains(&0) && core::str::from_utf8(bytes).map_or_else(|err| err.error_len().is_none(), |_| true)
+}
+
+// The lines that fit, long ones broken at the panel's edge
+fn wrap(text: &str) -> Vec<String> {
+    let columns = (WIDTH - 2 * MARGIN) / CHAR_WIDTH;
+    let rows = (HEIGHT - TITLE_HEIGHT - 2 * MARGIN) / LINE_HEIGHT;
+    let mut lines = Vec::new();
+    for line in text.lines() {
+        let chars: Vec<char> = line.replace('\t', "    ").chars().collect();
+        if chars.is_empty() {
+            lines.push(String::new());
+        }
+        lines.extend(chars.chunks(columns).map(|chunk| chunk.iter().collect::<String>()));
+        if lines.len() >= rows {
+            break;
+        }
+    }
+    lines.truncate(rows);
+    lines
+}
+
+// Kind, size and dates; the Finder's preview column shows them too
+pub fn info(path: &str, meta: &Metadata) -> Vec<(&'static str, String)> {
+    let size = if meta.is_dir() { String::from("--") } else { system_info::format_size(meta.size) };
+    vec![
+        ("Kind:", String::from(get_info::kind(path, meta.file_type))),
+        ("Size:", size),
+        ("Created:", DateTime::from_unix(meta.created).long()),
+        ("Modified:", DateTime::from_unix(meta.modified).long()),
+    ]
+}
+
+pub fn draw(graphics: &mut Graphics) {
+    let panel = PANEL.lock();
+    let panel = match panel.as_ref() {
+        Some(panel) => panel,
+        None => return,
+    };
+    graphics.draw_rounded_rect(X, Y, WIDTH, HEIGHT, Color::new(248, 248, 248));
+    graphics.draw_rect_outline(X, Y, WIDTH, HEIGHT, Color::GRAY);
+    graphics.draw_text(&panel.name, X + MARGIN, Y + 10, Color::BLACK);
+    graphics.draw_rect(X + 1, Y + TITLE_HEIGHT, WIDTH - 2, 1, Color::LIGHT_GRAY);
+    
+    let top = Y + TITLE_HEIGHT + MARGIN;
+    match &panel.content {
+        Content::Text(lines) => {
+            for (i, line) in lines.iter().enumerate() {
+                graphics.draw_text(line, X + MARGIN, top + i * LINE_HEIGHT, Color::BLACK);
+            }
+        }
+        Content::Image(image) => draw_image(graphics, image, top),
+        Content::Info(rows) => {
+            for (i, (label, value)) in rows.iter().enumerate() {
+                graphics.draw_text(label, X + MARGIN, top + i * 16, Color::GRAY);
+                graphics.draw_text(value, X + VALUE_X, top + i * 16, Color::BLACK);
+            }
+        }
+    }
+}
+
+// Fitted to the panel, never enlarged, and centered
+fn draw_image(graphics: &mut Graphics, image: &Image, top: usize) {
+    if image.width == 0 || image.height == 0 {
+        return;
+    }
+    let (view_width, view_height) = (WIDTH - 2 * MARGIN, HEIGHT - TITLE_HEIGHT - 2 * MARGIN);
+    let too_big = image.width > view_width || image.height > view_height;
+    let (num, den) = if !too_big {
+        (1, 1)
+    } else if view_width * image.height < view_height * image.width {
+        (view_width, image.width)
+    } else {
+        (view_height, image.height)
+    };
+    let (shown_width, shown_height) = (image.width * num / den, image.height * num / den);
+    let left = X + MARGIN + (view_width - shown_width) / 2;
+    let top = top + (view_height - shown_height) / 2;
+    for sy in 0..shown_height {
+        let iy = (sy * den / num).min(image.height - 1);
+        for sx in 0..shown_width {
+            let ix = (sx * den / num).min(image.width - 1);
+            graphics.set_pixel(left + sx, top + sy, image.pixel(ix, iy));
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,239 @@
+// src/ramfs.rs
+// Writable in-memory filesystem used as the root and for /tmp-style data.
+use crate::vfs::{DirEntry, FileSystem, FileType, Metadata, VfsError};
+use crate::{rtc, users};
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+const ROOT_INODE: u64 = 1;
+
+struct Node {
+    name: String,
+    file_type: FileType,
+    data: Vec<u8>,
+    children: Vec<u64>,
+    mode: u16,
+    uid: u32,
+    gid: u32,
+    created: u64,
+    modified: u64,
+}
+
+impl Node {
+    // New nodes belong to whoever is running and get the usual umask 022 modes
+    fn new(name: &str, file_type: FileType) -> Self {
+        let now = rtc::unix_time();
+        Self {
+            name: String::from(name),
+            file_type,
+            data: Vec::new(),
+            children: Vec::new(),
+            mode: if file_type == FileType::Directory { 0o755 } else { 0o644 },
+            uid: users::current_uid(),
+            gid: users::current_gid(),
+            created: now,
+            modified: now,
+        }
+    }
+}
+
+pub struct RamFs {
+    nodes: BTreeMap<u64, Node>,
+    next_inode: u64,
+}
+
+impl RamFs {
+    pub fn new() -> Self {
+        let mut nodes = BTreeMap::new();
+        nodes.insert(ROOT_INODE, Node::new("", FileType::Directory));
+        Self { nodes, next_inode: ROOT_INODE + 1 }
+    }
+    
+    fn node(&self, inode: u64) -> Result<&Node, VfsError> {
+        self.nodes.get(&inode).ok_or(VfsError::NotFound)
+    }
+    
+    fn node_mut(&mut self, inode: u64) -> Result<&mut Node, VfsError> {
+        self.nodes.get_mut(&inode).ok_or(VfsError::NotFound)
+    }
+    
+    fn dir(&self, inode: u64) -> Result<&Node, VfsError> {
+        let node = self.node(inode)?;
+        if node.file_type != FileType::Directory {
+            return Err(VfsError::NotADirectory);
+        }
+        Ok(node)
+    }
+    
+    pub fn used_bytes(&self) -> usize {
+        self.nodes.values().map(|n| n.data.len()).sum()
+    }
+    
+    // Whether `inode` is `ancestor` or somewhere below it
+    fn is_within(&self, inode: u64, ancestor: u64) -> bool {
+        inode == ancestor || self.nodes.get(&ancestor).map_or(false, |node| {
+            node.children.iter().any(|&child| self.is_within(inode, child))
+        })
+    }
+}
+
+impl FileSystem for RamFs {
+    fn fs_type(&self) -> &'static str {
+        "ramfs"
+    }
+    
+    fn root_inode(&self) -> u64 {
+        ROOT_INODE
+    }
+    
+    fn lookup(&mut self, dir: u64, name: &str) -> Result<u64, VfsError> {
+        let dir = self.dir(dir)?;
+        dir.children
+            .iter()
+            .copied()
+            .find(|child| self.nodes.get(child).map_or(false, |n| n.name == name))
+            .ok_or(VfsError::NotFound)
+    }
+    
+    fn metadata(&mut self, inode: u64) -> Result<Metadata, VfsError> {
+        let node = self.node(inode)?;
+        Ok(Metadata {
+            inode,
+            file_type: node.file_type,
+            size: node.data.len() as u64,
+            mode: node.mode,
+            uid: node.uid,
+            gid: node.gid,
+            created: node.created,
+            modified: node.modified,
+        })
+    }
+    
+    fn read_dir(&mut self, inode: u64) -> Result<Vec<DirEntry>, VfsError> {
+        let dir = self.dir(inode)?;
+        Ok(dir
+            .children
+            .iter()
+            .filter_map(|child| {
+                self.nodes.get(child).map(|n| DirEntry {
+                    name: n.name.clone(),
+                    inode: *child,
+                    file_type: n.file_type,
+                })
+            })
+            .collect())
+    }
+    
+    fn read(&mut self, inode: u64, offset: u64, buf: &mut [u8]) -> Result<usize, VfsError> {
+        let node = self.node(inode)?;
+        if node.file_type == FileType::Directory {
+            return Err(VfsError::IsADirectory);
+        }
+        let start = (offset as usize).min(node.data.len());
+        let n = buf.len().min(node.data.len() - start);
+        buf[..n].copy_from_slice(&node.data[start..start + n]);
+        Ok(n)
+    }
+    
+    fn write(&mut self, inode: u64, offset: u64, buf: &[u8]) -> Result<usize, VfsError> {
+        let node = self.node_mut(inode)?;
+        if node.file_type == FileType::Directory {
+            return Err(VfsError::IsADirectory);
+        }
+        let offset = offset as usize;
+        if node.data.len() < offset + buf.len() {
+            node.data.resize(offset + buf.len(), 0);
+        }
+        node.data[offset..offset + buf.len()].copy_from_slice(buf);
+        node.modified = rtc::unix_time();
+        Ok(buf.len())
+    }
+    
+    fn truncate(&mut self, inode: u64, size: u64) -> Result<(), VfsError> {
+        let node = self.node_mut(inode)?;
+        if node.file_type == FileType::Directory {
+            return Err(VfsError::IsADirectory);
+        }
+        node.data.resize(size as usize, 0);
+        node.modified = rtc::unix_time();
+        Ok(())
+    }
+    
+    fn create(&mut self, dir: u64, name: &str, file_type: FileType) -> Result<u64, VfsError> {
+        if name.is_empty() || name.contains('/') {
+            return Err(VfsError::InvalidPath);
+        }
+        self.dir(dir)?;
+        if self.lookup(dir, name).is_ok() {
+            return Err(VfsError::AlreadyExists);
+        }
+        
+        let inode = self.next_inode;
+        self.next_inode += 1;
+        self.nodes.insert(inode, Node::new(name, file_type));
+        let dir = self.node_mut(dir)?;
+        dir.children.push(inode);
+        dir.modified = rtc::unix_time();
+        Ok(inode)
+    }
+    
+    fn remove(&mut self, dir: u64, name: &str) -> Result<(), VfsError> {
+        let inode = self.lookup(dir, name)?;
+        if !self.node(inode)?.children.is_empty() {
+            return Err(VfsError::NotEmpty);
+        }
+        self.nodes.remove(&inode);
+        let dir = self.node_mut(dir)?;
+        dir.children.retain(|&c| c != inode);
+        dir.modified = rtc::unix_time();
+        Ok(())
+    }
+    
+    fn rename(&mut self, from_dir: u64, from_name: &str, to_dir: u64, to_name: &str) -> Result<(), VfsError> {
+        if to_name.is_empty() || to_name.contains('/') {
+            return Err(VfsError::InvalidPath);
+        }
+        let inode = self.lookup(from_dir, from_name)?;
+        self.dir(to_dir)?;
+        if self.lookup(to_dir, to_name).is_ok() {
+            return Err(VfsError::AlreadyExists);
+        }
+        // A folder can't go inside itself
+        if self.is_within(to_dir, inode) {
+            return Err(VfsError::InvalidPath);
+        }
+        let now = rtc::unix_time();
+        let from = self.node_mut(from_dir)?;
+        from.children.retain(|&c| c != inode);
+        from.modified = now;
+        let to = self.node_mut(to_dir)?;
+        to.children.push(inode);
+        to.modified = now;
+        self.node_mut(inode)?.name = String::from(to_name);
+        Ok(())
+    }
+    
+    fn set_mode(&mut self, inode: u64, mode: u16) -> Result<(), VfsError> {
+        self.node_mut(inode)?.mode = mode;
+        Ok(())
+    }
+    
+    fn set_owner(&mut self, inode: u64, uid: u32, gid: u32) -> Result<(), VfsError> {
+        let node = self.node_mut(inode)?;
+        node.uid = uid;
+        node.gid = gid;
+        Ok(())
+    }
+    
+    fn set_times(&mut self, inode: u64, created: u64, modified: u64) -> Result<(), VfsError> {
+        let node = self.node_mut(inode)?;
+        node.created = created;
+        node.modified = modified;
+        Ok(())
+    }
+    
+    fn is_read_only(&self) -> bool {
+        false
+    }
+}
\ No newline at end of file
@@ -0,0 +1,138 @@
+// src/rand.rs
+// Kernel random numbers. RDRAND output is used directly when the CPU has
+// it; otherwise numbers come from a ChaCha20 keystream keyed from the
+// entropy module and rekeyed periodically.
+use crate::entropy;
+use rand_core::{CryptoRng, Error, RngCore};
+use crate::sync::Mutex;
+
+// Blocks (64 bytes each) produced before drawing a fresh key
+const RESEED_BLOCKS: u64 = 1 << 14;
+
+struct ChaCha20 {
+    key: [u32; 8],
+    counter: u64,
+    block: [u32; 16],
+    index: usize,
+}
+
+fn quarter_round(s: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    s[a] = s[a].wrapping_add(s[b]);
+    s[d] = (s[d] ^ s[a]).rotate_left(16);
+    s[c] = s[c].wrapping_add(s[d]);
+    s[b] = (s[b] ^ s[c]).rotate_left(12);
+    s[a] = s[a].wrapping_add(s[b]);
+    s[d] = (s[d] ^ s[a]).rotate_left(8);
+    s[c] = s[c].wrapping_add(s[d]);
+    s[b] = (s[b] ^ s[c]).rotate_left(7);
+}
+
+impl ChaCha20 {
+    fn seeded() -> Self {
+        let mut seed = [0u8; 32];
+        entropy::fill(&mut seed);
+        let mut key = [0u32; 8];
+        for (word, bytes) in key.iter_mut().zip(seed.chunks(4)) {
+            *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        Self { key, counter: 0, block: [0; 16], index: 16 }
+    }
+    
+    fn refill(&mut self) {
+        if self.counter >= RESEED_BLOCKS {
+            *self = Self::seeded();
+        }
+        
+        let mut input = [0u32; 16];
+        input[..4].copy_from_slice(&[0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574]);
+        input[4..12].copy_from_slice(&self.key);
+        input[12] = self.counter as u32;
+        input[13] = (self.counter >> 32) as u32;
+        
+        let mut s = input;
+        for _ in 0..10 {
+            quarter_round(&mut s, 0, 4, 8, 12);
+            quarter_round(&mut s, 1, 5, 9, 13);
+            quarter_round(&mut s, 2, 6, 10, 14);
+            quarter_round(&mut s, 3, 7, 11, 15);
+            quarter_round(&mut s, 0, 5, 10, 15);
+            quarter_round(&mut s, 1, 6, 11, 12);
+            quarter_round(&mut s, 2, 7, 8, 13);
+            quarter_round(&mut s, 3, 4, 9, 14);
+        }
+        for (out, (mixed, original)) in self.block.iter_mut().zip(s.iter().zip(input.iter())) {
+            *out = mixed.wrapping_add(*original);
+        }
+        self.counter += 1;
+        self.index = 0;
+    }
+    
+    fn next_u32(&mut self) -> u32 {
+        if self.index == 16 {
+            self.refill();
+        }
+        self.index += 1;
+        self.block[self.index - 1]
+    }
+}
+
+static GENERATOR: Mutex<Option<ChaCha20>> = Mutex::new(None);
+
+fn fallback_u64() -> u64 {
+    let mut generator = GENERATOR.lock();
+    let chacha = generator.get_or_insert_with(ChaCha20::seeded);
+    chacha.next_u32() as u64 | (chacha.next_u32() as u64) << 32
+}
+
+pub fn u64() -> u64 {
+    entropy::rdrand64().unwrap_or_else(fallback_u64)
+}
+
+pub fn u32() -> u32 {
+    u64() as u32
+}
+
+// Uniform in [low, high); rejection sampling avoids modulo bias
+pub fn range(low: u64, high: u64) -> u64 {
+    assert!(low < high, "empty range");
+    let span = high - low;
+    let zone = u64::MAX - u64::MAX % span;
+    loop {
+        let value = u64();
+        if value < zone {
+            return low + value % span;
+        }
+    }
+}
+
+pub fn fill_bytes(buf: &mut [u8]) {
+    for chunk in buf.chunks_mut(8) {
+        let bytes = u64().to_le_bytes();
+        chunk.copy_from_slice(&bytes[..chunk.len()]);
+    }
+}
+
+// Handle for code written against rand_core
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KernelRng;
+
+impl RngCore for KernelRng {
+    fn next_u32(&mut self) -> u32 {
+        u32()
+    }
+    
+    fn next_u64(&mut self) -> u64 {
+        u64()
+    }
+    
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        fill_bytes(dest)
+    }
+    
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl CryptoRng for KernelRng {}
\ No newline at end of file
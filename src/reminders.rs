@@ -0,0 +1,109 @@
+// src/reminders.rs
+// Reminders set with `remind "stand up" in 25m`, from the Terminal or
+// Spotlight. Each is kept here until it's due, then the desktop posts it
+// as a notification with a Snooze button that sets it again a few minutes
+// on. Times come from the timer, so they last as long as the session.
+use crate::clock;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::sync::Mutex;
+
+pub const SNOOZE_MS: u64 = 5 * 60 * 1000;
+// Longer than this is most likely a typo
+const MAX_DELAY_MS: u64 = 7 * 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Clone)]
+pub struct Reminder {
+    pub id: u32,
+    pub text: String,
+    // When it's due, by `clock::millis`
+    pub due: u64,
+}
+
+struct State {
+    // By when they're due
+    reminders: Vec<Reminder>,
+    next_id: u32,
+}
+
+static STATE: Mutex<State> = Mutex::new(State { reminders: Vec::new(), next_id: 1 });
+
+// How long `delay` says, as in 25m, 1h30m or 1h 30m, with s, m and h
+fn parse_delay(delay: &str) -> Option<u64> {
+    let mut total = 0u64;
+    let mut number = None::<u64>;
+    for ch in delay.chars().filter(|ch| !ch.is_whitespace()) {
+        if let Some(digit) = ch.to_digit(10) {
+            number = Some(number.unwrap_or(0).checked_mul(10)?.checked_add(digit as u64)?);
+            continue;
+        }
+        let unit = match ch {
+            's' => 1000,
+            'm' => 60 * 1000,
+            'h' => 60 * 60 * 1000,
+            _ => return None,
+        };
+        total = total.checked_add(number.take()?.checked_mul(unit)?)?;
+    }
+    // A number on its own is minutes
+    if let Some(minutes) = number {
+        total = total.checked_add(minutes.checked_mul(60 * 1000)?)?;
+    }
+    (total > 0 && total <= MAX_DELAY_MS).then_some(total)
+}
+
+// The text and delay of a request like `"stand up" in 25m`, what follows
+// the word remind; quotes around the text are optional
+pub fn parse(request: &str) -> Option<(String, u64)> {
+    let (text, delay) = request.trim().rsplit_once(" in ")?;
+    let text = text.trim();
+    let text = text.strip_prefix('"').and_then(|text| text.strip_suffix('"')).unwrap_or(text).trim();
+    if text.is_empty() {
+        return None;
+    }
+    Some((String::from(text), parse_delay(delay)?))
+}
+
+// 25 min, 1 h 30 min or 45 s
+pub fn format_delay(ms: u64) -> String {
+    let seconds = ms.div_ceil(1000);
+    match (seconds / 3600, seconds % 3600 / 60, seconds % 60) {
+        (0, 0, seconds) => format!("{} s", seconds),
+        (0, minutes, _) => format!("{} min", minutes),
+        (hours, 0, _) => format!("{} h", hours),
+        (hours, minutes, _) => format!("{} h {} min", hours, minutes),
+    }
+}
+
+// Sets a reminder for `text` after `delay_ms`; its id
+pub fn add(text: &str, delay_ms: u64) -> u32 {
+    let mut state = STATE.lock();
+    let id = state.next_id;
+    state.next_id += 1;
+    let due = clock::millis() + delay_ms;
+    let at = state.reminders.partition_point(|reminder| reminder.due <= due);
+    state.reminders.insert(at, Reminder { id, text: String::from(text), due });
+    id
+}
+
+// False if there was no such reminder
+pub fn cancel(id: u32) -> bool {
+    let mut state = STATE.lock();
+    let count = state.reminders.len();
+    state.reminders.retain(|reminder| reminder.id != id);
+    state.reminders.len() != count
+}
+
+// Those still to come, soonest first
+pub fn pending() -> Vec<Reminder> {
+    STATE.lock().reminders.clone()
+}
+
+// Takes out those that are due; called every frame
+pub fn due() -> Vec<Reminder> {
+    let now = clock::millis();
+    let mut state = STATE.lock();
+    let count = state.reminders.partition_point(|reminder| reminder.due <= now);
+    state.reminders.drain(..count).collect()
+}
\ No newline at end of file
@@ -0,0 +1,332 @@
+// src/remote_console.rs
+// Remote Console: the kernel shell over TCP port 23, telnet style, for
+// working with the system from the host without the GUI. On QEMU's user
+// network, forward a host port to it (-netdev user,hostfwd=tcp::2323-:23)
+// and run `telnet localhost 2323`. Anyone who can reach the port gets a
+// shell, so the service is off until turned on in System Preferences.
+// While a job runs in the foreground, typed lines are its input, Ctrl-C
+// interrupts it and Ctrl-Z stops it.
+use crate::graphics::{Graphics, Color};
+use crate::net;
+use crate::preferences;
+use crate::shell::Shell;
+use crate::socket::{self, Handle};
+use crate::undo::{self, SetValue};
+use crate::vfs::VfsError;
+use crate::widgets::Checkbox;
+use crate::{kinfo, kwarn};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::sync::Mutex;
+
+const DOMAIN: &str = "com.rustos.sharing";
+const KEY: &str = "RemoteConsole";
+const PORT: u16 = 23;
+const MAX_SESSIONS: usize = 4;
+
+// Telnet commands and the options we offer: we echo, and go-aheads are
+// suppressed, which puts clients in character-at-a-time mode
+const IAC: u8 = 255;
+const DONT: u8 = 254;
+const DO: u8 = 253;
+const WONT: u8 = 252;
+const WILL: u8 = 251;
+const SB: u8 = 250;
+const SE: u8 = 240;
+const OPTION_ECHO: u8 = 1;
+const OPTION_SUPPRESS_GO_AHEAD: u8 = 3;
+
+// Where the setting sits in the System Preferences window, below the
+// category grid
+const PREFERENCE_X: usize = 20;
+const PREFERENCE_Y: usize = 236;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Telnet {
+    Data,
+    // After IAC
+    Command,
+    // After IAC and WILL, WONT, DO or DONT
+    Option,
+    // Inside IAC SB ... IAC SE, and just after an IAC in there
+    Subnegotiation,
+    SubnegotiationIac,
+}
+
+struct Session {
+    socket: Handle,
+    shell: Shell,
+    line: String,
+    telnet: Telnet,
+    // A CR ends the line; the LF or NUL clients send after it is skipped
+    after_cr: bool,
+    // Output the connection's send buffer didn't have room for yet
+    pending: Vec<u8>,
+    finished: bool,
+    // A foreground job has the prompt waiting
+    running: bool,
+}
+
+impl Session {
+    fn new(socket: Handle) -> Self {
+        let mut session = Self {
+            socket,
+            shell: Shell::new(),
+            line: String::new(),
+            telnet: Telnet::Data,
+            after_cr: false,
+            pending: Vec::new(),
+            finished: false,
+            running: false,
+        };
+        session.write(&[IAC, WILL, OPTION_ECHO, IAC, WILL, OPTION_SUPPRESS_GO_AHEAD]);
+        session.write(b"RustOS remote console. Type `help` for commands, `exit` to leave.\r\n\r\n");
+        session.prompt();
+        session
+    }
+    
+    fn write(&mut self, data: &[u8]) {
+        self.pending.extend_from_slice(data);
+    }
+    
+    fn prompt(&mut self) {
+        let prompt = self.shell.prompt();
+        self.write(prompt.as_bytes());
+    }
+    
+    fn execute(&mut self) {
+        self.write(b"\r\n");
+        let line = core::mem::take(&mut self.line);
+        if self.running {
+            self.shell.input_line(&line);
+            return;
+        }
+        match line.trim() {
+            "exit" | "logout" => {
+                self.finished = true;
+                return;
+            }
+            "clear" => self.write(b"\x1b[2J\x1b[H"),
+            _ => {
+                for text in self.shell.execute(&line) {
+                    self.write(text.as_bytes());
+                    self.write(b"\r\n");
+                }
+            }
+        }
+        self.running = self.shell.is_busy();
+        if !self.running {
+            self.prompt();
+        }
+    }
+    
+    // Passes on what jobs printed, and prompts again once the foreground
+    // one is done
+    fn poll_shell(&mut self) {
+        for text in self.shell.poll() {
+            self.write(text.as_bytes());
+            self.write(b"\r\n");
+        }
+        if self.running && !self.shell.is_busy() {
+            self.running = false;
+            self.prompt();
+        }
+    }
+    
+    fn input(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.telnet = match (self.telnet, byte) {
+                (Telnet::Data, IAC) => Telnet::Command,
+                (Telnet::Data, _) => {
+                    self.key(byte);
+                    Telnet::Data
+                }
+                (Telnet::Command, WILL | WONT | DO | DONT) => Telnet::Option,
+                (Telnet::Command, SB) => Telnet::Subnegotiation,
+                // IAC IAC is a literal 255, which isn't text we use
+                (Telnet::Command, _) | (Telnet::Option, _) => Telnet::Data,
+                (Telnet::Subnegotiation, IAC) => Telnet::SubnegotiationIac,
+                (Telnet::SubnegotiationIac, SE) => Telnet::Data,
+                (Telnet::Subnegotiation, _) | (Telnet::SubnegotiationIac, _) => Telnet::Subnegotiation,
+            };
+        }
+    }
+    
+    fn key(&mut self, byte: u8) {
+        let after_cr = core::mem::replace(&mut self.after_cr, byte == b'\r');
+        match byte {
+            b'\n' | 0 if after_cr => {}
+            b'\r' | b'\n' => self.execute(),
+            // Backspace and delete
+            0x08 | 0x7F => {
+                if self.line.pop().is_some() {
+                    self.write(b"\x08 \x08");
+                }
+            }
+            0x03 if self.running => {
+                self.line.clear();
+                self.write(b"^C\r\n");
+                self.shell.interrupt();
+            }
+            // Ctrl-Z stops the foreground job
+            0x1A if self.running => {
+                self.write(b"^Z\r\n");
+                self.shell.suspend();
+            }
+            // Ctrl-C drops the line
+            0x03 => {
+                self.line.clear();
+                self.write(b"^C\r\n");
+                self.prompt();
+            }
+            // Ctrl-D on an empty line ends the job's input, or leaves
+            0x04 if self.running && self.line.is_empty() => self.shell.close_input(),
+            0x04 if self.line.is_empty() => self.finished = true,
+            0x20..=0x7E => {
+                self.line.push(byte as char);
+                self.write(&[byte]);
+            }
+            _ => {}
+        }
+    }
+    
+    // Sends what the connection takes. False once the session is over.
+    fn flush(&mut self) -> bool {
+        if !self.pending.is_empty() {
+            match socket::send(self.socket, &self.pending) {
+                Ok(sent) => {
+                    self.pending.drain(..sent);
+                }
+                Err(_) => return false,
+            }
+        }
+        !(self.finished && self.pending.is_empty())
+    }
+}
+
+struct Service {
+    enabled: bool,
+    listener: Option<Handle>,
+    sessions: Vec<Session>,
+    checkbox: Checkbox,
+}
+
+static SERVICE: Mutex<Service> = Mutex::new(Service {
+    enabled: false,
+    listener: None,
+    sessions: Vec::new(),
+    checkbox: Checkbox::new("Remote Console"),
+});
+
+impl Service {
+    fn start(&mut self) {
+        if self.listener.is_some() {
+            return;
+        }
+        match socket::listen(PORT) {
+            Ok(listener) => {
+                self.listener = Some(listener);
+                let ip = net::local_ip().map_or(String::from("no network"), |ip| format!("{}", ip));
+                kinfo!("remote console: listening on {}:{}", ip, PORT);
+            }
+            Err(err) => kwarn!("remote console: cannot listen on port {}: {:?}", PORT, err),
+        }
+    }
+    
+    fn stop(&mut self) {
+        for session in self.sessions.drain(..) {
+            socket::close(session.socket);
+        }
+        if let Some(listener) = self.listener.take() {
+            socket::close(listener);
+            kinfo!("remote console: stopped");
+        }
+    }
+}
+
+pub fn init() {
+    let mut service = SERVICE.lock();
+    service.enabled = preferences::get_bool(DOMAIN, KEY, false);
+    service.checkbox.checked = service.enabled;
+    if service.enabled {
+        service.start();
+    }
+}
+
+// Starts or stops the service either way; the error is from saving the
+// setting
+pub fn set_enabled(enabled: bool) -> Result<(), VfsError> {
+    let mut service = SERVICE.lock();
+    service.enabled = enabled;
+    service.checkbox.checked = enabled;
+    if enabled {
+        service.start();
+    } else {
+        service.stop();
+    }
+    preferences::set_bool(DOMAIN, KEY, enabled)
+}
+
+// Accepts new connections and serves the open ones; called from the
+// desktop loop after the network has been polled
+pub fn poll() {
+    let mut service = SERVICE.lock();
+    let listener = match service.listener {
+        Some(listener) => listener,
+        None => return,
+    };
+    while let Ok(Some(connection)) = socket::accept(listener) {
+        if service.sessions.len() == MAX_SESSIONS {
+            let _ = socket::send(connection, b"Too many remote console sessions.\r\n");
+            socket::close(connection);
+            continue;
+        }
+        if let Ok(Some((ip, port))) = socket::remote(connection) {
+            kinfo!("remote console: connection from {}:{}", ip, port);
+        }
+        service.sessions.push(Session::new(connection));
+    }
+    
+    service.sessions.retain_mut(|session| {
+        let open = match socket::recv(session.socket) {
+            Ok(data) => {
+                session.input(&data);
+                session.poll_shell();
+                session.flush()
+            }
+            Err(_) => false,
+        };
+        if !open {
+            socket::close(session.socket);
+        }
+        open
+    });
+}
+
+pub fn draw_preference(graphics: &mut Graphics, x: usize, y: usize) {
+    let service = SERVICE.lock();
+    let (x, y) = (x + PREFERENCE_X, y + PREFERENCE_Y);
+    graphics.draw_text("Sharing", x, y, Color::BLACK);
+    service.checkbox.draw(graphics, x, y + 18);
+    let detail = match (service.enabled, service.listener, net::local_ip()) {
+        (true, Some(_), Some(ip)) => format!("telnet {} {} ({} connected)", ip, PORT, service.sessions.len()),
+        (true, _, _) => String::from("Not listening: network unavailable"),
+        (false, _, _) => String::from("Shell access over TCP port 23"),
+    };
+    graphics.draw_text(&detail, x + Checkbox::SIZE + 6, y + 36, Color::GRAY);
+}
+
+// `x` and `y` are relative to the window content
+pub fn click_preference(x: usize, y: usize) {
+    let toggled = {
+        let service = SERVICE.lock();
+        service.checkbox.contains(x, y, PREFERENCE_X, PREFERENCE_Y + 18).then_some(!service.enabled)
+    };
+    if let Some(enabled) = toggled {
+        let change = SetValue { name: "Remote Console", set: set_enabled, old: !enabled, new: enabled };
+        if let Err(err) = undo::perform(preferences::APP, change) {
+            kwarn!("remote console: cannot save setting: {:?}", err);
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,308 @@
+// src/replay.rs
+// Recording the desktop's input and playing it back. A recording is text,
+// a step a line, so tests can be written by hand as well:
+//
+//   wait 30                   let 30 frames go by
+//   key LeftCmd down          a key pressed or released
+//   type Ter                  keys pressed and released in turn, with
+//                             Shift held for capitals
+//   move -5 12                the pointer moved
+//   button Left down          a mouse button pressed or released
+//   scroll -1                 the wheel turned
+//   expect focused Terminal   the app in front; also `running` and `stopped`
+//
+// Blank lines and lines from a # are skipped. With record=PATH the input
+// from boot on goes to PATH, saved every few seconds; with replay=PATH the
+// file plays from boot and live input is ignored. Frames are the clock, so
+// a step lands on the same frame every time. Trackpad gestures aren't
+// recorded, and whatever times itself with the clock, like animations,
+// can still differ between runs.
+use crate::bootargs;
+use crate::input::InputEvent;
+use crate::keyboard::{Key, DIGITS, LETTERS};
+use crate::mouse::MouseButton;
+use crate::vfs::{self, VfsError};
+use crate::{kinfo, kwarn};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+use crate::sync::Mutex;
+
+// Frames between saves of a recording, about five seconds
+const SAVE_INTERVAL: u64 = 300;
+
+const OTHER_KEYS: [Key; 29] = [
+    Key::Space, Key::Enter, Key::Backspace, Key::Tab, Key::Escape,
+    Key::LeftShift, Key::RightShift, Key::LeftCtrl, Key::RightCtrl,
+    Key::LeftAlt, Key::RightAlt, Key::LeftCmd, Key::RightCmd,
+    Key::ArrowUp, Key::ArrowDown, Key::ArrowLeft, Key::ArrowRight,
+    Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6, Key::F7, Key::F8, Key::F9, Key::F10, Key::F11, Key::F12,
+];
+
+// What a replay checks about the desktop, by app name
+#[derive(Debug, Clone)]
+pub enum Check {
+    Focused(String),
+    Running(String),
+    Stopped(String),
+}
+
+pub enum Step {
+    Input(InputEvent),
+    Expect(Check),
+}
+
+enum Line {
+    Wait(u64),
+    Step(Step),
+}
+
+struct Recorder {
+    path: String,
+    text: String,
+    // Frame of the last step written, and of the last save
+    last_step: u64,
+    saved: u64,
+    dirty: bool,
+}
+
+struct Player {
+    name: String,
+    lines: Vec<Line>,
+    next: usize,
+    resume_at: u64,
+    passed: usize,
+    failed: usize,
+}
+
+enum Mode {
+    Off,
+    Recording(Recorder),
+    Playing(Player),
+}
+
+struct State {
+    mode: Mode,
+    // Frames since recording or playing started
+    frame: u64,
+}
+
+static STATE: Mutex<State> = Mutex::new(State { mode: Mode::Off, frame: 0 });
+// Checks passed and failed by the last replay to finish
+#[cfg(feature = "ui-test")]
+static RESULT: Mutex<Option<(usize, usize)>> = Mutex::new(None);
+
+fn key_named(name: &str) -> Option<Key> {
+    LETTERS.iter().chain(DIGITS.iter()).chain(OTHER_KEYS.iter()).copied().find(|key| format!("{:?}", key) == name)
+}
+
+fn button_named(name: &str) -> Option<MouseButton> {
+    match name {
+        "Left" => Some(MouseButton::Left),
+        "Right" => Some(MouseButton::Right),
+        "Middle" => Some(MouseButton::Middle),
+        _ => None,
+    }
+}
+
+fn pressed(word: &str) -> Option<bool> {
+    match word {
+        "down" => Some(true),
+        "up" => Some(false),
+        _ => None,
+    }
+}
+
+// The key that types `ch`, for `type`, and whether Shift is held for it
+fn key_for(ch: char) -> Option<(Key, bool)> {
+    match ch {
+        ' ' => Some((Key::Space, false)),
+        'a'..='z' => Some((LETTERS[ch as usize - 'a' as usize], false)),
+        'A'..='Z' => Some((LETTERS[ch as usize - 'A' as usize], true)),
+        '0'..='9' => Some((DIGITS[ch as usize - '0' as usize], false)),
+        _ => None,
+    }
+}
+
+fn parse_line(line: &str, lines: &mut Vec<Line>) -> Option<()> {
+    let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+    let mut words = rest.split_ascii_whitespace();
+    let input = |event| Line::Step(Step::Input(event));
+    match command {
+        "wait" => lines.push(Line::Wait(words.next()?.parse().ok()?)),
+        "key" => lines.push(input(InputEvent::Key { key: key_named(words.next()?)?, pressed: pressed(words.next()?)? })),
+        "type" => {
+            for ch in rest.chars() {
+                let (key, shift) = key_for(ch)?;
+                if shift {
+                    lines.push(input(InputEvent::Key { key: Key::LeftShift, pressed: true }));
+                }
+                lines.push(input(InputEvent::Key { key, pressed: true }));
+                lines.push(input(InputEvent::Key { key, pressed: false }));
+                if shift {
+                    lines.push(input(InputEvent::Key { key: Key::LeftShift, pressed: false }));
+                }
+            }
+        }
+        "move" => lines.push(input(InputEvent::MouseMove { dx: words.next()?.parse().ok()?, dy: words.next()?.parse().ok()? })),
+        "button" => lines.push(input(InputEvent::MouseButton { button: button_named(words.next()?)?, pressed: pressed(words.next()?)? })),
+        "scroll" => lines.push(input(InputEvent::Scroll { delta: words.next()?.parse().ok()? })),
+        "expect" => {
+            let (what, app) = rest.split_once(' ')?;
+            let app = String::from(app.trim());
+            let check = match what {
+                "focused" => Check::Focused(app),
+                "running" => Check::Running(app),
+                "stopped" => Check::Stopped(app),
+                _ => return None,
+            };
+            lines.push(Line::Step(Step::Expect(check)));
+        }
+        _ => return None,
+    }
+    Some(())
+}
+
+// The number of the first line that isn't a step if there's one
+fn parse(script: &str) -> Result<Vec<Line>, usize> {
+    let mut lines = Vec::new();
+    for (number, line) in script.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if !line.is_empty() && parse_line(line, &mut lines).is_none() {
+            return Err(number + 1);
+        }
+    }
+    Ok(lines)
+}
+
+fn write_event(text: &mut String, event: &InputEvent) {
+    let updown = |pressed: bool| if pressed { "down" } else { "up" };
+    let _ = match event {
+        InputEvent::Key { key, pressed } => writeln!(text, "key {:?} {}", key, updown(*pressed)),
+        InputEvent::MouseMove { dx, dy } => writeln!(text, "move {} {}", dx, dy),
+        InputEvent::MouseButton { button, pressed } => writeln!(text, "button {:?} {}", button, updown(*pressed)),
+        InputEvent::Scroll { delta } => writeln!(text, "scroll {}", delta),
+    };
+}
+
+// Starts recording or playing as the boot arguments ask
+pub fn init() {
+    if let Some(path) = bootargs::get("replay") {
+        match vfs::read_file(path) {
+            Ok(bytes) => {
+                play(path, &String::from_utf8_lossy(&bytes));
+            }
+            Err(err) => kwarn!("replay: can't read {}: {:?}", path, err),
+        }
+    } else if let Some(path) = bootargs::get("record") {
+        if let Err(err) = vfs::write_file(path, b"") {
+            kwarn!("replay: can't record to {}: {:?}", path, err);
+            return;
+        }
+        kinfo!("replay: recording to {}", path);
+        let recorder = Recorder { path: String::from(path), text: String::new(), last_step: 0, saved: 0, dirty: false };
+        *STATE.lock() = State { mode: Mode::Recording(recorder), frame: 0 };
+    }
+}
+
+// Plays `script` from the next frame; `name` is for the log. False if a
+// line of it isn't a step, and then nothing plays.
+pub fn play(name: &str, script: &str) -> bool {
+    let lines = match parse(script) {
+        Ok(lines) => lines,
+        Err(line) => {
+            kwarn!("replay: {} line {} isn't a step", name, line);
+            return false;
+        }
+    };
+    kinfo!("replay: playing {}", name);
+    let player = Player { name: String::from(name), lines, next: 0, resume_at: 0, passed: 0, failed: 0 };
+    *STATE.lock() = State { mode: Mode::Playing(player), frame: 0 };
+    true
+}
+
+// Whether input comes from, or goes to, a recording
+pub fn is_active() -> bool {
+    !matches!(STATE.lock().mode, Mode::Off)
+}
+
+pub fn is_playing() -> bool {
+    matches!(STATE.lock().mode, Mode::Playing(_))
+}
+
+// Called once a frame with the input that came in. Returns what the
+// desktop should handle: the same input, recorded on the way, or when a
+// replay is playing, its steps for this frame instead. A replay ends the
+// frame after its last step, once its checks are all in.
+pub fn frame(live: Vec<InputEvent>) -> Vec<Step> {
+    let mut state = STATE.lock();
+    let state = &mut *state;
+    let frame = state.frame;
+    state.frame += 1;
+    if let Mode::Playing(player) = &state.mode {
+        if player.next == player.lines.len() && frame >= player.resume_at {
+            kinfo!("replay: {} done, {} checks passed, {} failed", player.name, player.passed, player.failed);
+            #[cfg(feature = "ui-test")]
+            {
+                *RESULT.lock() = Some((player.passed, player.failed));
+            }
+            state.mode = Mode::Off;
+        }
+    }
+    match &mut state.mode {
+        Mode::Off => live.into_iter().map(Step::Input).collect(),
+        Mode::Recording(recorder) => {
+            if !live.is_empty() {
+                if frame > recorder.last_step {
+                    let _ = writeln!(recorder.text, "wait {}", frame - recorder.last_step);
+                }
+                live.iter().for_each(|event| write_event(&mut recorder.text, event));
+                recorder.last_step = frame;
+                recorder.dirty = true;
+            }
+            if recorder.dirty && frame >= recorder.saved + SAVE_INTERVAL {
+                if let Err(err) = save(recorder) {
+                    kwarn!("replay: can't save {}: {:?}", recorder.path, err);
+                }
+                recorder.saved = frame;
+            }
+            live.into_iter().map(Step::Input).collect()
+        }
+        Mode::Playing(player) => {
+            let mut steps = Vec::new();
+            while player.next < player.lines.len() && frame >= player.resume_at {
+                match core::mem::replace(&mut player.lines[player.next], Line::Wait(0)) {
+                    Line::Wait(frames) => player.resume_at = frame + frames,
+                    Line::Step(step) => steps.push(step),
+                }
+                player.next += 1;
+            }
+            steps
+        }
+    }
+}
+
+fn save(recorder: &mut Recorder) -> Result<(), VfsError> {
+    vfs::write_file(&recorder.path, recorder.text.as_bytes())?;
+    recorder.dirty = false;
+    Ok(())
+}
+
+// The outcome of a check the desktop made for a replay
+pub fn report(check: &Check, passed: bool) {
+    if let Mode::Playing(player) = &mut STATE.lock().mode {
+        if passed {
+            player.passed += 1;
+        } else {
+            player.failed += 1;
+            kwarn!("replay: {}: expected {:?}", player.name, check);
+        }
+    }
+}
+
+// Checks passed and failed by the replay that just ended, once
+#[cfg(feature = "ui-test")]
+pub fn finished() -> Option<(usize, usize)> {
+    RESULT.lock().take()
+}
\ No newline at end of file
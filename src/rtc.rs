@@ -0,0 +1,168 @@
+// src/rtc.rs
+// CMOS real-time clock. Only used for wall-clock time (file timestamps,
+// the menu bar clock); it has one-second resolution.
+use alloc::format;
+use alloc::string::String;
+use x86_64::instructions::port::Port;
+
+const CMOS_ADDRESS: u16 = 0x70;
+const CMOS_DATA: u16 = 0x71;
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl DateTime {
+    // Times before 1970 come out as 0
+    pub fn to_unix(&self) -> u64 {
+        let days = days_from_civil(self.year as i64, self.month as i64, self.day as i64);
+        let seconds = days * 86400 + self.hour as i64 * 3600 + self.minute as i64 * 60 + self.second as i64;
+        seconds.max(0) as u64
+    }
+    
+    pub fn from_unix(timestamp: u64) -> Self {
+        let days = (timestamp / 86400) as i64;
+        let secs = timestamp % 86400;
+        let (year, month, day) = civil_from_days(days);
+        Self {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+            hour: (secs / 3600) as u8,
+            minute: (secs / 60 % 60) as u8,
+            second: (secs % 60) as u8,
+        }
+    }
+    
+    // MS-DOS time and date words, as zip and FAT keep them: two-second
+    // resolution, years from 1980
+    pub fn to_dos(&self) -> (u16, u16) {
+        let time = (self.hour as u16) << 11 | (self.minute as u16) << 5 | self.second as u16 / 2;
+        let date = (self.year.saturating_sub(1980)) << 9 | (self.month as u16) << 5 | self.day as u16;
+        (time, date)
+    }
+    
+    pub fn from_dos(time: u16, date: u16) -> Self {
+        Self {
+            year: 1980 + (date >> 9),
+            month: ((date >> 5) & 0xF) as u8,
+            day: (date & 0x1F) as u8,
+            hour: (time >> 11) as u8,
+            minute: ((time >> 5) & 0x3F) as u8,
+            second: ((time & 0x1F) * 2) as u8,
+        }
+    }
+    
+    // "Jun 19 12:34", as used by `ls -l` and Get Info
+    pub fn short(&self) -> String {
+        format!("{} {:2} {:02}:{:02}", MONTHS[(self.month as usize + 11) % 12], self.day, self.hour, self.minute)
+    }
+    
+    pub fn long(&self) -> String {
+        format!(
+            "{} {}, {} at {:02}:{:02}:{:02}",
+            MONTHS[(self.month as usize + 11) % 12], self.day, self.year, self.hour, self.minute, self.second
+        )
+    }
+}
+
+// Howard Hinnant's days-from-civil algorithm
+fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = (if y >= 0 { y } else { y - 399 }) / 400;
+    let yoe = y - era * 400;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+fn civil_from_days(z: i64) -> (i64, i64, i64) {
+    let z = z + 719468;
+    let era = (if z >= 0 { z } else { z - 146096 }) / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let y = yoe + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    (if m <= 2 { y + 1 } else { y }, m, d)
+}
+
+fn read_register(reg: u8) -> u8 {
+    let mut address: Port<u8> = Port::new(CMOS_ADDRESS);
+    let mut data: Port<u8> = Port::new(CMOS_DATA);
+    unsafe {
+        // Keep NMIs enabled (bit 7 clear)
+        address.write(reg & 0x7F);
+        data.read()
+    }
+}
+
+fn update_in_progress() -> bool {
+    read_register(0x0A) & 0x80 != 0
+}
+
+fn read_raw() -> [u8; 6] {
+    while update_in_progress() {
+        core::hint::spin_loop();
+    }
+    [
+        read_register(0x00),
+        read_register(0x02),
+        read_register(0x04),
+        read_register(0x07),
+        read_register(0x08),
+        read_register(0x09),
+    ]
+}
+
+pub fn now() -> DateTime {
+    // Read twice until stable so we never see a half-updated value
+    let mut raw = read_raw();
+    loop {
+        let again = read_raw();
+        if again == raw {
+            break;
+        }
+        raw = again;
+    }
+    
+    let status_b = read_register(0x0B);
+    let bcd = status_b & 0x04 == 0;
+    let decode = |v: u8| if bcd { (v & 0x0F) + (v >> 4) * 10 } else { v };
+    
+    let [second, minute, hour_raw, day, month, year] = raw;
+    let pm = hour_raw & 0x80 != 0;
+    let mut hour = decode(hour_raw & 0x7F);
+    if status_b & 0x02 == 0 {
+        // 12-hour mode
+        hour %= 12;
+        if pm {
+            hour += 12;
+        }
+    }
+    
+    DateTime {
+        year: 2000 + decode(year) as u16,
+        month: decode(month),
+        day: decode(day),
+        hour,
+        minute: decode(minute),
+        second: decode(second),
+    }
+}
+
+pub fn unix_time() -> u64 {
+    now().to_unix()
+}
\ No newline at end of file
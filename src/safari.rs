@@ -0,0 +1,525 @@
+// src/safari.rs
+// Safari's browser state: tabs, each with its own back/forward history, and
+// bookmarks kept in the user's Library. Pages are the built-in start page
+// (drawn by the window), local Markdown files as file:// URLs, rendered
+// like the Document Viewer does, and http:// and https:// pages fetched
+// with the HTTP client. Fetches are moved along by `poll` from the desktop
+// loop, with their progress shown on the Dock icon; a certificate that
+// doesn't check out gets a warning page instead.
+//
+// Keys: Cmd+T new tab, Cmd+W close tab, Cmd+1..9 pick a tab, Cmd+Left and
+// Cmd+Right go back and forward, Cmd+R reloads, Cmd+D bookmarks the page,
+// Cmd+F finds text on the page.
+use crate::dock;
+use crate::document_viewer;
+use crate::graphics::{Graphics, Color};
+use crate::http::{self, HttpError, Response};
+use crate::keyboard::{Key, KeyEvent};
+use crate::net::NetError;
+use crate::styled_text::{Block, Paragraph, Span, Style, StyledText, TextView};
+use crate::tls::{self, TlsError};
+use crate::{html, kwarn, markdown, users};
+use crate::vfs::{self, VfsError};
+use crate::widgets::{Button, SearchBar};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::sync::Mutex;
+
+const START_URL: &str = "https://rustos.dev/docs";
+const START_TITLE: &str = "RustOS Docs";
+const FILE_SCHEME: &str = "file://";
+
+// Tab strip, toolbar and bookmarks bar above the page
+pub const CHROME_HEIGHT: usize = 70;
+const TAB_HEIGHT: usize = 22;
+const TOOLBAR_Y: usize = 22;
+const BOOKMARKS_Y: usize = 50;
+const MAX_TAB_WIDTH: usize = 140;
+const NEW_TAB_WIDTH: usize = 24;
+const BACK_X: usize = 8;
+const FORWARD_X: usize = 36;
+const ADDRESS_X: usize = 70;
+const BUTTON_Y: usize = TOOLBAR_Y + 5;
+const BOOKMARK_GAP: usize = 16;
+// Shown until the user saves bookmarks of their own
+const DEFAULT_BOOKMARKS: [(&str, &str); 3] = [
+    (START_TITLE, START_URL),
+    ("Example", "https://example.com/"),
+    ("Rust", "https://www.rust-lang.org/"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bookmark {
+    pub title: String,
+    pub url: String,
+}
+
+enum Page {
+    // Drawn by the window
+    Start,
+    Loading(http::Request),
+    View(TextView),
+}
+
+struct Tab {
+    // Visited URLs, oldest first; `position` is the one shown
+    history: Vec<String>,
+    position: usize,
+    page: Page,
+    // From the page's <title>, or the error it ended in
+    title: Option<String>,
+}
+
+impl Tab {
+    fn new(url: &str) -> Self {
+        Self { history: Vec::from([String::from(url)]), position: 0, page: load(url), title: None }
+    }
+    
+    fn url(&self) -> &str {
+        &self.history[self.position]
+    }
+    
+    fn can_go_back(&self) -> bool {
+        self.position > 0
+    }
+    
+    fn can_go_forward(&self) -> bool {
+        self.position + 1 < self.history.len()
+    }
+    
+    // Visiting a page drops the forward history
+    fn navigate(&mut self, url: &str) {
+        self.history.truncate(self.position + 1);
+        self.history.push(String::from(url));
+        self.position += 1;
+        self.show(load(url));
+    }
+    
+    fn go(&mut self, step: isize) {
+        let position = self.position as isize + step;
+        if position >= 0 && (position as usize) < self.history.len() {
+            self.position = position as usize;
+            self.reload();
+        }
+    }
+    
+    fn reload(&mut self) {
+        let page = load(self.url());
+        self.show(page);
+    }
+    
+    fn show(&mut self, page: Page) {
+        self.page = page;
+        self.title = None;
+    }
+    
+    fn title(&self) -> String {
+        self.title.clone().unwrap_or_else(|| title_for(self.url()))
+    }
+    
+    // Shows the page a fetch ended with. Redirects replace the URL in the
+    // history with the one the page came from.
+    fn loaded(&mut self, result: Result<Response, HttpError>) {
+        let (text, title) = match result {
+            Ok(response) => {
+                self.history[self.position] = response.url.to_string();
+                render(&response)
+            }
+            Err(err) => error_page(self.url(), &err),
+        };
+        self.page = Page::View(TextView::new(text));
+        self.title = title;
+    }
+}
+
+struct Browser {
+    tabs: Vec<Tab>,
+    active: usize,
+    // Read from disk on first use
+    bookmarks: Option<Vec<Bookmark>>,
+    back: Button,
+    forward: Button,
+    // Finds on whichever page is shown
+    search: SearchBar,
+    // Last shown on the Dock icon
+    progress: Option<u8>,
+}
+
+static BROWSER: Mutex<Browser> = Mutex::new(Browser {
+    tabs: Vec::new(),
+    active: 0,
+    bookmarks: None,
+    back: Button::new("<"),
+    forward: Button::new(">"),
+    search: SearchBar::new(),
+    progress: None,
+});
+
+fn load(url: &str) -> Page {
+    if url == START_URL {
+        return Page::Start;
+    }
+    if let Some(path) = url.strip_prefix(FILE_SCHEME) {
+        return Page::View(document_viewer::load(path));
+    }
+    match http::Request::get(url) {
+        Ok(request) => Page::Loading(request),
+        Err(err) => {
+            let (text, _) = error_page(url, &err);
+            Page::View(TextView::new(text))
+        }
+    }
+}
+
+// Tab and bookmark title for a page without one of its own
+fn title_for(url: &str) -> String {
+    if url == START_URL {
+        return String::from(START_TITLE);
+    }
+    match url.strip_prefix(FILE_SCHEME) {
+        Some(path) => String::from(vfs::parent_and_name(path).1),
+        None => http::Url::parse(url).map_or_else(|| String::from(url), |url| url.host),
+    }
+}
+
+// The page for a response, with its title. There is no image or script
+// support, so only text types are shown.
+fn render(response: &Response) -> (StyledText, Option<String>) {
+    let body = String::from_utf8_lossy(&response.body);
+    match response.content_type().as_str() {
+        "text/html" | "application/xhtml+xml" | "" => (html::parse(&body), html::title(&body)),
+        "text/markdown" => (markdown::parse(&body), None),
+        kind if kind.starts_with("text/") || kind.ends_with("json") || kind.ends_with("xml") => {
+            let lines = body.lines().map(|line| {
+                Paragraph::new(Block::Code, Vec::from([Span::new(&line.replace('\t', "    "), Style::default())]))
+            });
+            (StyledText { paragraphs: lines.collect() }, None)
+        }
+        kind => message_page("Safari Can't Show This Page", &format!("{} files can't be shown in Safari.", kind)),
+    }
+}
+
+fn message_page(title: &str, message: &str) -> (StyledText, Option<String>) {
+    let bold = Style { bold: true, ..Style::default() };
+    let text = StyledText {
+        paragraphs: Vec::from([
+            Paragraph::new(Block::Heading(1), Vec::from([Span::new(title, bold)])),
+            Paragraph::new(Block::Body, Vec::from([Span::new(message, Style::default())])),
+        ]),
+    };
+    (text, Some(String::from(title)))
+}
+
+fn error_page(url: &str, err: &HttpError) -> (StyledText, Option<String>) {
+    let host = http::Url::parse(url).map_or_else(String::new, |url| url.host);
+    let message = match err {
+        HttpError::Tls(TlsError::Certificate(problem)) => {
+            let message = format!(
+                "This website may be impersonating \"{}\" to steal your personal or financial information. {}",
+                host,
+                tls::certificate_problem(problem)
+            );
+            return message_page("This Connection Is Not Private", &message);
+        }
+        HttpError::BadUrl => format!("\"{}\" isn't a web address Safari can open.", url),
+        HttpError::Net(NetError::NoSuchHost) => format!("Safari can't find the server \"{}\".", host),
+        HttpError::Net(NetError::NoInterface) => String::from("You are not connected to the Internet."),
+        HttpError::Net(NetError::Refused) => format!("\"{}\" refused the connection.", host),
+        HttpError::Net(NetError::TimedOut) => format!("\"{}\" didn't respond in time.", host),
+        HttpError::Net(err) => format!("The connection to \"{}\" failed: {:?}.", host, err),
+        HttpError::Tls(_) => format!("A secure connection to \"{}\" couldn't be established.", host),
+        HttpError::BadResponse => format!("\"{}\" sent a response Safari couldn't understand.", host),
+        HttpError::TooLarge => String::from("The page is too large to open."),
+        HttpError::TooManyRedirects => format!("\"{}\" redirected too many times.", host),
+    };
+    message_page("Safari Can't Open the Page", &message)
+}
+
+pub fn can_open(path: &str) -> bool {
+    document_viewer::is_document(path)
+}
+
+fn bookmarks_path() -> String {
+    format!("/Users/{}/Library/Safari/Bookmarks", users::user_name(users::current_uid()))
+}
+
+// One bookmark per line: the URL, a tab, then the title
+fn read_bookmarks() -> Vec<Bookmark> {
+    let data = match vfs::read_file(&bookmarks_path()) {
+        Ok(data) => data,
+        Err(_) => {
+            return DEFAULT_BOOKMARKS
+                .iter()
+                .map(|(title, url)| Bookmark { title: String::from(*title), url: String::from(*url) })
+                .collect()
+        }
+    };
+    String::from_utf8_lossy(&data)
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(url, title)| Bookmark { title: String::from(title), url: String::from(url) })
+        .collect()
+}
+
+fn write_bookmarks(bookmarks: &[Bookmark]) -> Result<(), VfsError> {
+    let path = bookmarks_path();
+    vfs::create_dir_all(vfs::parent_and_name(&path).0)?;
+    let mut text = String::new();
+    for bookmark in bookmarks {
+        text.push_str(&format!("{}\t{}\n", bookmark.url, bookmark.title));
+    }
+    vfs::write_file(&path, text.as_bytes())
+}
+
+impl Browser {
+    // There is always at least one tab
+    fn tab(&mut self) -> &mut Tab {
+        if self.tabs.is_empty() {
+            self.tabs.push(Tab::new(START_URL));
+            self.active = 0;
+        }
+        &mut self.tabs[self.active]
+    }
+    
+    fn new_tab(&mut self, url: &str) {
+        self.tabs.push(Tab::new(url));
+        self.active = self.tabs.len() - 1;
+    }
+    
+    fn close_tab(&mut self) {
+        if self.active < self.tabs.len() {
+            self.tabs.remove(self.active);
+        }
+        self.active = self.active.min(self.tabs.len().saturating_sub(1));
+    }
+    
+    fn bookmarks(&mut self) -> &mut Vec<Bookmark> {
+        self.bookmarks.get_or_insert_with(read_bookmarks)
+    }
+    
+    fn is_bookmarked(&mut self, url: &str) -> bool {
+        self.bookmarks().iter().any(|bookmark| bookmark.url == url)
+    }
+    
+    // Adds the current page to the bookmarks, or removes it if it's there
+    fn toggle_bookmark(&mut self) {
+        let url = String::from(self.tab().url());
+        let title = self.tab().title();
+        let bookmarks = self.bookmarks();
+        match bookmarks.iter().position(|bookmark| bookmark.url == url) {
+            Some(index) => {
+                bookmarks.remove(index);
+            }
+            None => bookmarks.push(Bookmark { title, url }),
+        }
+        if let Err(err) = write_bookmarks(self.bookmarks()) {
+            kwarn!("safari: cannot save bookmarks: {:?}", err);
+        }
+    }
+}
+
+// Moves page loads along; call after `net::poll`
+pub fn poll() {
+    let mut browser = BROWSER.lock();
+    // The least progress of the pages loading, 0 while a length isn't known
+    let mut progress = None;
+    for tab in browser.tabs.iter_mut() {
+        if let Page::Loading(request) = &mut tab.page {
+            match request.poll() {
+                Some(result) => tab.loaded(result),
+                None => progress = Some(progress.unwrap_or(100).min(request.progress().unwrap_or(0))),
+            }
+        }
+    }
+    if progress != browser.progress {
+        browser.progress = progress;
+        dock::set_progress("Safari", progress);
+    }
+}
+
+// Opens a local file in the current tab
+pub fn open_file(path: &str) {
+    BROWSER.lock().tab().navigate(&format!("{}{}", FILE_SCHEME, path));
+}
+
+// Opens a web page in the current tab, for the Open URL service
+pub fn open_url(url: &str) {
+    BROWSER.lock().tab().navigate(url);
+}
+
+// Into the Find bar, if it's open
+pub fn insert_text(ch: char) {
+    BROWSER.lock().search.insert(ch);
+}
+
+pub fn handle_key(event: &KeyEvent, width: usize, height: usize) {
+    let mut browser = BROWSER.lock();
+    if browser.search.key(event) {
+        return;
+    }
+    match (event.key, event.cmd) {
+        (Key::T, true) => browser.new_tab(START_URL),
+        (Key::W, true) => browser.close_tab(),
+        (Key::D, true) => browser.toggle_bookmark(),
+        (Key::R, true) => browser.tab().reload(),
+        (Key::ArrowLeft, true) => browser.tab().go(-1),
+        (Key::ArrowRight, true) => browser.tab().go(1),
+        (Key::ArrowUp, false) => scroll_tab(browser.tab(), -1, width, height),
+        (Key::ArrowDown, false) => scroll_tab(browser.tab(), 1, width, height),
+        (key, true) => {
+            let digits = [Key::Digit1, Key::Digit2, Key::Digit3, Key::Digit4, Key::Digit5, Key::Digit6, Key::Digit7, Key::Digit8, Key::Digit9];
+            if let Some(index) = digits.iter().position(|digit| *digit == key) {
+                if index < browser.tabs.len() {
+                    browser.active = index;
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+// Height of the page area below the chrome, for a content height
+fn page_height(height: usize) -> usize {
+    height - CHROME_HEIGHT - 8
+}
+
+fn scroll_tab(tab: &mut Tab, rows: isize, width: usize, height: usize) {
+    if let Page::View(view) = &mut tab.page {
+        view.scroll_by(rows, width, page_height(height));
+    }
+}
+
+// `height` is the window content height, as passed to `draw`
+pub fn scroll(rows: isize, width: usize, height: usize) {
+    scroll_tab(BROWSER.lock().tab(), rows, width, height);
+}
+
+// Tab widths shrink to fit the strip, up to a maximum
+fn tab_width(count: usize, width: usize) -> usize {
+    ((width - NEW_TAB_WIDTH - 8) / count.max(1)).min(MAX_TAB_WIDTH)
+}
+
+// `x` and `y` are relative to the window content, as drawn by `draw`
+// Back and forward, as (x, y, width, height) in the content
+pub fn controls() -> Vec<(usize, usize, usize, usize)> {
+    let browser = BROWSER.lock();
+    vec![
+        (BACK_X, BUTTON_Y, browser.back.width(), Button::HEIGHT),
+        (FORWARD_X, BUTTON_Y, browser.forward.width(), Button::HEIGHT),
+    ]
+}
+
+pub fn click(x: usize, y: usize, width: usize) {
+    let mut browser = BROWSER.lock();
+    browser.tab();
+    if browser.search.click(x, y, 0, 0, width) {
+        return;
+    }
+    if y < TAB_HEIGHT {
+        let tab_width = tab_width(browser.tabs.len(), width);
+        let index = x / tab_width;
+        if index < browser.tabs.len() {
+            browser.active = index;
+        } else if x >= browser.tabs.len() * tab_width && x < browser.tabs.len() * tab_width + NEW_TAB_WIDTH {
+            browser.new_tab(START_URL);
+        }
+    } else if y < BOOKMARKS_Y {
+        if browser.back.contains(x, y, BACK_X, BUTTON_Y) {
+            browser.tab().go(-1);
+        } else if browser.forward.contains(x, y, FORWARD_X, BUTTON_Y) {
+            browser.tab().go(1);
+        } else if x >= width - 40 {
+            browser.toggle_bookmark();
+        }
+    } else if y < CHROME_HEIGHT {
+        let mut item_x = 10;
+        for bookmark in browser.bookmarks().clone() {
+            let item_width = bookmark.title.chars().count() * 8;
+            if x >= item_x && x < item_x + item_width {
+                browser.tab().navigate(&bookmark.url);
+                return;
+            }
+            item_x += item_width + BOOKMARK_GAP;
+        }
+    }
+}
+
+// Draws the tab strip, toolbar and bookmarks bar, then the page. Returns
+// false on the start page, which the window draws itself.
+pub fn draw(graphics: &mut Graphics, x: usize, y: usize, width: usize, height: usize) -> bool {
+    let mut browser = BROWSER.lock();
+    browser.tab();
+    
+    // Tab strip
+    graphics.draw_rect(x + 1, y, width - 2, TAB_HEIGHT, Color::new(222, 222, 222));
+    let tab_width = tab_width(browser.tabs.len(), width);
+    for (i, tab) in browser.tabs.iter().enumerate() {
+        let tab_x = x + i * tab_width;
+        if i == browser.active {
+            graphics.draw_rect(tab_x + 1, y + 2, tab_width - 1, TAB_HEIGHT - 2, Color::new(245, 245, 245));
+        }
+        graphics.draw_rect(tab_x + tab_width, y + 4, 1, TAB_HEIGHT - 8, Color::GRAY);
+        let title: String = tab.title().chars().take(tab_width.saturating_sub(16) / 8).collect();
+        graphics.draw_text(&title, tab_x + 8, y + 8, Color::BLACK);
+    }
+    graphics.draw_text("+", x + browser.tabs.len() * tab_width + 8, y + 8, Color::DARK_GRAY);
+    
+    // Toolbar: back, forward, address and the bookmark star
+    let url = String::from(browser.tab().url());
+    let (can_go_back, can_go_forward) = (browser.tab().can_go_back(), browser.tab().can_go_forward());
+    graphics.draw_rect(x + 1, y + TOOLBAR_Y, width - 2, BOOKMARKS_Y - TOOLBAR_Y, Color::new(245, 245, 245));
+    browser.back.draw(graphics, x + BACK_X, y + BUTTON_Y);
+    browser.forward.draw(graphics, x + FORWARD_X, y + BUTTON_Y);
+    // Grey out the arrows with nowhere to go
+    if !can_go_back {
+        graphics.draw_text("<", x + BACK_X + 8, y + BUTTON_Y + 5, Color::LIGHT_GRAY);
+    }
+    if !can_go_forward {
+        graphics.draw_text(">", x + FORWARD_X + 8, y + BUTTON_Y + 5, Color::LIGHT_GRAY);
+    }
+    let address_width = width - ADDRESS_X - 50;
+    graphics.draw_rounded_rect(x + ADDRESS_X, y + BUTTON_Y - 1, address_width, 20, Color::WHITE);
+    graphics.draw_rect_outline(x + ADDRESS_X, y + BUTTON_Y - 1, address_width, 20, Color::new(200, 200, 200));
+    let shown: String = url.chars().take((address_width - 16) / 8).collect();
+    graphics.draw_text(&shown, x + ADDRESS_X + 8, y + BUTTON_Y + 5, Color::BLACK);
+    let star = if browser.is_bookmarked(&url) { Color::YELLOW } else { Color::GRAY };
+    graphics.draw_text("*", x + width - 30, y + BUTTON_Y + 5, star);
+    
+    // Bookmarks bar
+    graphics.draw_rect(x + 1, y + BOOKMARKS_Y, width - 2, CHROME_HEIGHT - BOOKMARKS_Y, Color::new(238, 238, 238));
+    graphics.draw_rect(x + 1, y + CHROME_HEIGHT - 1, width - 2, 1, Color::new(210, 210, 210));
+    let mut item_x = x + 10;
+    for bookmark in browser.bookmarks().iter() {
+        let item_width = bookmark.title.chars().count() * 8;
+        if item_x + item_width > x + width - 10 {
+            break;
+        }
+        graphics.draw_text(&bookmark.title, item_x, y + BOOKMARKS_Y + 6, Color::DARK_GRAY);
+        item_x += item_width + BOOKMARK_GAP;
+    }
+    
+    // Page
+    let browser = &mut *browser;
+    let shown = match &mut browser.tabs[browser.active].page {
+        Page::Start => {
+            browser.search.update(0);
+            false
+        }
+        Page::Loading(request) => {
+            browser.search.update(0);
+            let message = format!("Loading {}...", request.url().host);
+            graphics.draw_text(&message, x + 20, y + CHROME_HEIGHT + 20, Color::GRAY);
+            true
+        }
+        Page::View(view) => {
+            view.find(&mut browser.search, width, page_height(height));
+            view.draw(graphics, x, y + CHROME_HEIGHT + 6, width, page_height(height));
+            true
+        }
+    };
+    // While it's open, the search bar covers the tab strip and toolbar
+    browser.search.draw(graphics, x, y, width);
+    shown
+}
\ No newline at end of file
@@ -0,0 +1,103 @@
+// src/save_sheet.rs
+// The sheet that asks about unsaved changes before a window closes. It
+// hangs from the window's title bar and keeps the rest of the desktop
+// waiting: Save has the app save and then closes, Don't Save closes and
+// throws the changes away, and Cancel leaves the window open. Enter is
+// Save, Cmd+D Don't Save and Escape Cancel.
+use crate::graphics::{Graphics, Color, SCREEN_WIDTH};
+use crate::keyboard::{Key, KeyEvent};
+use crate::widgets::Button;
+use crate::window_manager::Window;
+use alloc::format;
+use alloc::string::String;
+
+const WIDTH: usize = 300;
+const HEIGHT: usize = 84;
+const TITLE_BAR_HEIGHT: usize = 36;
+// Buttons, relative to the sheet
+const BUTTON_Y: usize = 56;
+const DONT_SAVE_X: usize = 12;
+const CANCEL_X: usize = 170;
+const SAVE_X: usize = 240;
+
+const DONT_SAVE: Button = Button::new("Don't Save");
+const CANCEL: Button = Button::new("Cancel");
+const SAVE: Button = Button::new("Save");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveChoice {
+    Save,
+    DontSave,
+    Cancel,
+}
+
+pub struct SaveSheet {
+    pub is_visible: bool,
+    // Index of the window it's for
+    pub window: usize,
+    app: String,
+    x: usize,
+    y: usize,
+}
+
+impl SaveSheet {
+    pub fn new() -> Self {
+        Self { is_visible: false, window: 0, app: String::new(), x: 0, y: 0 }
+    }
+    
+    pub fn show(&mut self, index: usize, window: &Window) {
+        self.window = index;
+        self.app = String::from(window.app_name());
+        self.x = (window.x + window.width / 2).saturating_sub(WIDTH / 2).min(SCREEN_WIDTH - WIDTH);
+        self.y = window.y + TITLE_BAR_HEIGHT;
+        self.is_visible = true;
+    }
+    
+    pub fn hide(&mut self) {
+        self.is_visible = false;
+    }
+    
+    pub fn draw(&self, graphics: &mut Graphics) {
+        if !self.is_visible {
+            return;
+        }
+        let (x, y) = (self.x, self.y);
+        graphics.draw_rect(x, y, WIDTH, HEIGHT, Color::new(236, 236, 236));
+        graphics.draw_rect_outline(x, y, WIDTH, HEIGHT, Color::GRAY);
+        let question: String = format!("Save changes to {}?", self.app).chars().take((WIDTH - 24) / 8).collect();
+        graphics.draw_text(&question, x + 12, y + 12, Color::BLACK);
+        graphics.draw_text("Changes you don't save are lost.", x + 12, y + 28, Color::GRAY);
+        
+        DONT_SAVE.draw(graphics, x + DONT_SAVE_X, y + BUTTON_Y);
+        CANCEL.draw(graphics, x + CANCEL_X, y + BUTTON_Y);
+        graphics.draw_rounded_rect(x + SAVE_X, y + BUTTON_Y, SAVE.width(), Button::HEIGHT, Color::BLUE);
+        graphics.draw_text(SAVE.label, x + SAVE_X + 8, y + BUTTON_Y + 5, Color::WHITE);
+    }
+    
+    // The sheet is modal, so clicks anywhere else do nothing
+    pub fn click(&mut self, px: usize, py: usize) -> Option<SaveChoice> {
+        let (x, y) = (self.x, self.y + BUTTON_Y);
+        let choice = if SAVE.contains(px, py, x + SAVE_X, y) {
+            SaveChoice::Save
+        } else if DONT_SAVE.contains(px, py, x + DONT_SAVE_X, y) {
+            SaveChoice::DontSave
+        } else if CANCEL.contains(px, py, x + CANCEL_X, y) {
+            SaveChoice::Cancel
+        } else {
+            return None;
+        };
+        self.hide();
+        Some(choice)
+    }
+    
+    pub fn key(&mut self, event: &KeyEvent) -> Option<SaveChoice> {
+        let choice = match event.key {
+            Key::Enter => SaveChoice::Save,
+            Key::D if event.cmd => SaveChoice::DontSave,
+            Key::Escape => SaveChoice::Cancel,
+            _ => return None,
+        };
+        self.hide();
+        Some(choice)
+    }
+}
\ No newline at end of file
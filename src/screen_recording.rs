@@ -0,0 +1,113 @@
+// src/screen_recording.rs
+// Records the screen to an animated GIF on the user's Desktop. Cmd+Shift+5
+// starts and stops a recording, and while one runs the menu bar shows a red
+// dot and the time so far; clicking it stops. Frames are taken from what's on
+// screen FPS times a second, for at most MAX_DURATION.
+use crate::clock::Instant;
+use crate::gif::GifWriter;
+use crate::graphics::{Graphics, Color, Surface, SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::rtc;
+use crate::users;
+use crate::vfs::{self, VfsError};
+use alloc::format;
+use alloc::string::String;
+use core::time::Duration;
+use crate::sync::Mutex;
+
+const FPS: u64 = 5;
+const MAX_DURATION: Duration = Duration::from_secs(60);
+// The menu bar indicator: a dot and "m:ss"
+const INDICATOR_WIDTH: usize = 52;
+
+struct Recording {
+    writer: GifWriter,
+    path: String,
+    started: Instant,
+    frames: u64,
+}
+
+static RECORDING: Mutex<Option<Recording>> = Mutex::new(None);
+// Screen-sized, so not on the heap
+static FRAME: Mutex<Surface> = Mutex::new(Surface::new());
+
+// "Screen Recording 2024-06-19 at 12.34.56.gif" on the Desktop
+fn new_path() -> Result<String, VfsError> {
+    let desktop = format!("/Users/{}/Desktop", users::user_name(users::current_uid()));
+    vfs::create_dir_all(&desktop)?;
+    let now = rtc::now();
+    Ok(format!(
+        "{}/Screen Recording {}-{:02}-{:02} at {:02}.{:02}.{:02}.gif",
+        desktop, now.year, now.month, now.day, now.hour, now.minute, now.second
+    ))
+}
+
+pub fn is_recording() -> bool {
+    RECORDING.lock().is_some()
+}
+
+// How long the current recording has been going
+pub fn elapsed() -> Option<Duration> {
+    RECORDING.lock().as_ref().map(|recording| recording.started.elapsed())
+}
+
+// The path the recording goes to
+pub fn start() -> Result<String, VfsError> {
+    let mut recording = RECORDING.lock();
+    if let Some(recording) = recording.as_ref() {
+        return Ok(recording.path.clone());
+    }
+    let path = new_path()?;
+    let writer = GifWriter::create(&path, SCREEN_WIDTH, SCREEN_HEIGHT)?;
+    *recording = Some(Recording { writer, path: path.clone(), started: Instant::now(), frames: 0 });
+    Ok(path)
+}
+
+// Finishes the file; the path it went to, or None if nothing was recording
+pub fn stop() -> Option<Result<String, VfsError>> {
+    let recording = RECORDING.lock().take()?;
+    let path = recording.path;
+    Some(recording.writer.finish().map(|()| path))
+}
+
+// Called after every desktop frame; takes a frame when one is due. Returns
+// how the recording ended if it just did, at the time limit or because the
+// file couldn't be written.
+pub fn capture(graphics: &Graphics) -> Option<Result<String, VfsError>> {
+    let mut guard = RECORDING.lock();
+    let recording = guard.as_mut()?;
+    let elapsed = recording.started.elapsed();
+    if elapsed >= MAX_DURATION {
+        drop(guard);
+        return stop();
+    }
+    if (elapsed.as_millis() as u64) < recording.frames * 1000 / FPS {
+        return None;
+    }
+    let mut frame = FRAME.lock();
+    graphics.capture(&mut frame, 0, 0, SCREEN_WIDTH, SCREEN_HEIGHT);
+    match recording.writer.add_frame(frame.pixels(), (100 / FPS) as u16) {
+        Ok(()) => {
+            recording.frames += 1;
+            None
+        }
+        Err(err) => {
+            *guard = None;
+            Some(Err(err))
+        }
+    }
+}
+
+// Drawn into the menu bar at (x, y) while recording
+pub fn draw_indicator(graphics: &mut Graphics, x: usize, y: usize) {
+    let elapsed = match elapsed() {
+        Some(elapsed) => elapsed.as_secs(),
+        None => return,
+    };
+    graphics.draw_rounded_rect(x, y, 8, 8, Color::RED);
+    graphics.draw_text(&format!("{}:{:02}", elapsed / 60, elapsed % 60), x + 14, y, Color::RED);
+}
+
+// Whether the point hits the indicator drawn at (x, y)
+pub fn indicator_contains(px: usize, py: usize, x: usize, y: usize) -> bool {
+    is_recording() && px >= x && px < x + INDICATOR_WIDTH && py >= y.saturating_sub(8) && py < y + 16
+}
\ No newline at end of file
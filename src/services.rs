@@ -0,0 +1,156 @@
+// src/services.rs
+// Boot as a list of services with declared dependencies. `run` starts each
+// service once everything it comes after has finished, and times it. A
+// required service that fails stops the boot; an optional one logs a
+// warning and the services that depend on it are skipped. The first
+// services run before the heap exists, so nothing here allocates. The
+// results are kept for the boot splash and `sysinfo`.
+use crate::graphics::{Graphics, Color, SCREEN_WIDTH};
+use crate::{clock, kerror, kinfo, kwarn};
+use alloc::vec::Vec;
+use core::arch::x86_64::_rdtsc;
+use core::time::Duration;
+use crate::sync::Mutex;
+
+const MAX_SERVICES: usize = 16;
+
+pub type StartResult = Result<(), &'static str>;
+
+pub struct Service<'a> {
+    pub name: &'static str,
+    // Services that have to finish first
+    pub after: &'static [&'static str],
+    pub required: bool,
+    pub start: &'a mut dyn FnMut() -> StartResult,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Pending,
+    Started,
+    Failed(&'static str),
+    // A service it comes after didn't start
+    Skipped,
+}
+
+impl Status {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Status::Pending => "pending",
+            Status::Started => "ok",
+            Status::Failed(_) => "failed",
+            Status::Skipped => "skipped",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Stage {
+    pub name: &'static str,
+    pub status: Status,
+    // TSC cycles; the clock isn't running yet for the first stages
+    cycles: u64,
+}
+
+impl Stage {
+    const EMPTY: Stage = Stage { name: "", status: Status::Pending, cycles: 0 };
+    
+    // None if the TSC can't be turned into time on this machine
+    pub fn duration(&self) -> Option<Duration> {
+        match clock::tsc_hz() {
+            0 => None,
+            hz => Some(Duration::from_nanos((self.cycles as u128 * 1_000_000_000 / hz as u128) as u64)),
+        }
+    }
+}
+
+struct Stages {
+    list: [Stage; MAX_SERVICES],
+    len: usize,
+}
+
+static STAGES: Mutex<Stages> = Mutex::new(Stages { list: [Stage::EMPTY; MAX_SERVICES], len: 0 });
+
+// The stages in the order they ran, each with how it went
+pub fn stages() -> Vec<Stage> {
+    let stages = STAGES.lock();
+    Vec::from(&stages.list[..stages.len])
+}
+
+fn finished(status: Status) -> bool {
+    status != Status::Pending
+}
+
+// None for a name that isn't in the list, which counts as finished
+fn status_of(services: &[Service], status: &[Status], name: &str) -> Option<Status> {
+    services.iter().position(|service| service.name == name).map(|index| status[index])
+}
+
+// Starts `services` in dependency order. `progress` is called after each
+// one with the stages so far.
+pub fn run(services: &mut [Service], progress: &mut dyn FnMut(&[Stage])) {
+    assert!(services.len() <= MAX_SERVICES, "too many boot services");
+    let mut status = [Status::Pending; MAX_SERVICES];
+    for _ in 0..services.len() {
+        // The first service waiting on nothing unfinished
+        let next = (0..services.len()).find(|&i| {
+            !finished(status[i]) && services[i].after.iter().all(|&name| status_of(services, &status, name).map_or(true, finished))
+        });
+        let index = match next {
+            Some(index) => index,
+            None => {
+                let stuck = (0..services.len()).find(|&i| !finished(status[i])).map_or("", |i| services[i].name);
+                panic!("boot: {} waits on a service that never finishes", stuck);
+            }
+        };
+        let blocked = services[index].after.iter().any(|&name| {
+            matches!(status_of(services, &status, name), Some(Status::Failed(_)) | Some(Status::Skipped))
+        });
+        let service = &mut services[index];
+        let start = unsafe { _rdtsc() };
+        status[index] = if blocked {
+            kwarn!("boot: skipping {}", service.name);
+            Status::Skipped
+        } else {
+            match (service.start)() {
+                Ok(()) => Status::Started,
+                Err(err) if service.required => {
+                    kerror!("boot: {} failed: {}", service.name, err);
+                    panic!("boot: required service {} failed: {}", service.name, err);
+                }
+                Err(err) => {
+                    kwarn!("boot: {} failed: {}", service.name, err);
+                    Status::Failed(err)
+                }
+            }
+        };
+        let stage = Stage { name: service.name, status: status[index], cycles: unsafe { _rdtsc() } - start };
+        if let Some(duration) = stage.duration() {
+            kinfo!("boot: {} {:?} in {} ms", stage.name, stage.status, duration.as_millis());
+        }
+        let mut stages = STAGES.lock();
+        let len = stages.len;
+        stages.list[len] = stage;
+        stages.len += 1;
+        let list = stages.list;
+        drop(stages);
+        progress(&list[..len + 1]);
+    }
+}
+
+// The boot splash: the logo over the stages that have run
+pub fn draw_splash(graphics: &mut Graphics, stages: &[Stage]) {
+    graphics.clear_screen(Color::BLACK);
+    graphics.draw_text("RustOS", SCREEN_WIDTH / 2 - 24, 140, Color::WHITE);
+    for (i, stage) in stages.iter().enumerate() {
+        let y = 180 + i * 14;
+        let color = match stage.status {
+            Status::Started => Color::GREEN,
+            Status::Failed(_) => Color::RED,
+            Status::Skipped => Color::YELLOW,
+            Status::Pending => Color::GRAY,
+        };
+        graphics.draw_text(stage.name, SCREEN_WIDTH / 2 - 100, y, Color::LIGHT_GRAY);
+        graphics.draw_text(stage.status.label(), SCREEN_WIDTH / 2 + 40, y, color);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,147 @@
+// src/share.rs
+// Services: actions an app offers on a kind of data for any app to use,
+// like Safari opening a URL found in some text or the desktop making an
+// image its wallpaper. An app with something to act on, like Mail's open
+// message or Preview's image, lists the services that take it in its
+// context menu and under its app menu, and `request` posts the one picked
+// over the bus; the desktop brings the app offering it forward and hands
+// it the data. The services right-clicking inside a window lists are kept
+// in a menu here; Finder puts them in its own.
+use crate::appearance;
+use crate::bus::{self, Message};
+use crate::graphics::{Graphics, Color, SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::safari;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+const WIDTH: usize = 200;
+const ROW_HEIGHT: usize = 20;
+
+#[derive(Debug, Clone)]
+pub enum Data {
+    Text(String),
+    // The path of an image file
+    Image(String),
+}
+
+pub struct Service {
+    pub name: &'static str,
+    // Brought forward before it's handed the data; None for the desktop
+    pub app: Option<&'static str>,
+    accepts: fn(&Data) -> bool,
+    perform: fn(&Data),
+}
+
+impl Service {
+    pub fn perform(&self, data: &Data) {
+        (self.perform)(data);
+    }
+}
+
+pub const SERVICES: [Service; 2] = [
+    Service { name: "Open URL in Safari", app: Some("Safari"), accepts: has_url, perform: open_url },
+    Service { name: "Set as Wallpaper", app: None, accepts: is_image, perform: set_wallpaper },
+];
+
+// The first http:// or https:// URL in the text
+fn find_url(text: &str) -> Option<&str> {
+    text.split_whitespace()
+        .find(|word| word.starts_with("http://") || word.starts_with("https://"))
+        .map(|word| word.trim_end_matches(['.', ',', ')', '>', '"']))
+}
+
+fn has_url(data: &Data) -> bool {
+    matches!(data, Data::Text(text) if find_url(text).is_some())
+}
+
+fn open_url(data: &Data) {
+    if let Data::Text(text) = data {
+        if let Some(url) = find_url(text) {
+            safari::open_url(url);
+        }
+    }
+}
+
+fn is_image(data: &Data) -> bool {
+    matches!(data, Data::Image(_))
+}
+
+fn set_wallpaper(data: &Data) {
+    if let Data::Image(path) = data {
+        appearance::set_wallpaper_from_image(path);
+    }
+}
+
+// The services that can take `data`
+pub fn for_data(data: &Data) -> impl Iterator<Item = &'static Service> + '_ {
+    SERVICES.iter().filter(move |service| (service.accepts)(data))
+}
+
+pub fn find(name: &str) -> Option<&'static Service> {
+    SERVICES.iter().find(|service| service.name == name)
+}
+
+pub fn request(service: &'static Service, data: Data) {
+    bus::post(Message::Service { name: service.name, data });
+}
+
+// The menu right-clicking inside a window opens when its app has data the
+// services take
+pub struct ServicesMenu {
+    pub is_visible: bool,
+    x: usize,
+    y: usize,
+    data: Option<Data>,
+    services: Vec<&'static Service>,
+}
+
+impl ServicesMenu {
+    pub fn new() -> Self {
+        Self { is_visible: false, x: 0, y: 0, data: None, services: Vec::new() }
+    }
+    
+    // Opens at the pointer, kept on screen; nothing opens when no service
+    // takes the data
+    pub fn show(&mut self, x: usize, y: usize, data: Data) {
+        self.services = for_data(&data).collect();
+        if self.services.is_empty() {
+            return;
+        }
+        self.x = x.min(SCREEN_WIDTH - WIDTH);
+        self.y = y.min(SCREEN_HEIGHT - self.height());
+        self.data = Some(data);
+        self.is_visible = true;
+    }
+    
+    pub fn hide(&mut self) {
+        self.is_visible = false;
+    }
+    
+    fn height(&self) -> usize {
+        ROW_HEIGHT * (self.services.len() + 1) + 8
+    }
+    
+    pub fn draw(&self, graphics: &mut Graphics) {
+        if !self.is_visible {
+            return;
+        }
+        graphics.draw_rounded_rect(self.x, self.y, WIDTH, self.height(), Color::new(248, 248, 248));
+        graphics.draw_rect_outline(self.x, self.y, WIDTH, self.height(), Color::GRAY);
+        graphics.draw_text("Services", self.x + 12, self.y + 10, Color::GRAY);
+        for (i, service) in self.services.iter().enumerate() {
+            graphics.draw_text(service.name, self.x + 12, self.y + 4 + (i + 1) * ROW_HEIGHT + 6, Color::BLACK);
+        }
+    }
+    
+    // Any click closes the menu; a click on a service requests it
+    pub fn click(&mut self, x: usize, y: usize) {
+        self.hide();
+        if !(self.x..self.x + WIDTH).contains(&x) || y < self.y + 4 + ROW_HEIGHT {
+            return;
+        }
+        let row = (y - self.y - 4) / ROW_HEIGHT - 1;
+        if let (Some(&service), Some(data)) = (self.services.get(row), self.data.take()) {
+            request(service, data);
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,1254 @@
+// src/shell.rs
+// Built-in command interpreter backing the Terminal window. Anything
+// that isn't a built-in is an app to run, found by path or under PATH;
+// apps joined by `|` read each other's output, and a trailing `&` runs
+// them in the background. Apps take no arguments yet. What a job prints
+// comes back through `poll`, as does word of it finishing.
+use crate::rtc::DateTime;
+use crate::elf;
+use crate::pipe::{self, PipeError, Reader, Writer};
+use crate::task::{self, Descriptor, Pid, ProcessState, Signal, TaskError, STDERR, STDIN, STDOUT};
+use crate::userspace;
+use crate::vfs::{self, VfsError};
+use crate::log::{self, Level};
+use crate::system_info::{self, format_size};
+use crate::clock::{self, Instant};
+use crate::dns;
+use crate::net::{self, Ipv4Addr, NetError};
+use crate::socket::{self, Datagram, Handle, Protocol};
+use crate::services::{self, Status};
+use crate::bootargs;
+use crate::bus::{self, Message};
+use crate::archive;
+use crate::keychain::{self, KeychainError};
+use crate::boot_slots::{self, Slot};
+use crate::{acpi, allocator, block_cache, cpu, cpu_profiler, heap_profiler, interrupts, memory, pci, pool, preferences, reminders, users};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+pub type Output = Vec<String>;
+
+struct Command {
+    name: &'static str,
+    help: &'static str,
+    run: fn(&mut Shell, &[&str], &mut Output),
+}
+
+const COMMANDS: &[Command] = &[
+    Command { name: "help", help: "list built-in commands", run: cmd_help },
+    Command { name: "pwd", help: "print the working directory", run: cmd_pwd },
+    Command { name: "cd", help: "change the working directory", run: cmd_cd },
+    Command { name: "ls", help: "list directory contents (-l long, -a all)", run: cmd_ls },
+    Command { name: "ps", help: "list processes", run: cmd_ps },
+    Command { name: "mount", help: "list mounted filesystems", run: cmd_mount },
+    Command { name: "sysinfo", help: "processor, memory, display, storage, kernel and boot summary", run: cmd_sysinfo },
+    Command { name: "lspci", help: "list PCI devices", run: cmd_lspci },
+    Command { name: "lsmem", help: "list the physical memory map", run: cmd_lsmem },
+    Command { name: "lsirq", help: "list IRQ lines, owners and interrupt counts and rates (-v every vector)", run: cmd_lsirq },
+    Command { name: "dmesg", help: "kernel log (-l level, -n count, -s first sequence number)", run: cmd_dmesg },
+    Command { name: "heapstat", help: "kernel heap usage by subsystem and call site", run: cmd_heapstat },
+    Command { name: "profile", help: "sample where the CPU spends its time (start, stop, export [file])", run: cmd_profile },
+    Command { name: "ping", help: "send ICMP echo requests to a host (-c count)", run: cmd_ping },
+    Command { name: "traceroute", help: "show the route packets take to a host (-m max hops)", run: cmd_traceroute },
+    Command { name: "netstat", help: "list open sockets (-i interface statistics)", run: cmd_netstat },
+    Command { name: "defaults", help: "read and write preferences (read domain [key], write domain key value)", run: cmd_defaults },
+    Command { name: "security", help: "manage the login keychain (add-, find-, delete-generic-password, dump-, lock-, unlock-keychain)", run: cmd_security },
+    Command { name: "open", help: "open a file in its app (-a app to pick the app)", run: cmd_open },
+    Command { name: "tar", help: "make, extract or list a .tar or .zip (-cf archive paths, -xf archive [-C dir], -tf archive)", run: cmd_tar },
+    Command { name: "softwareupdate", help: "list the kernel slots, or install a kernel to try at the next boot (--list, --install kernel [version])", run: cmd_softwareupdate },
+    Command { name: "remind", help: "list reminders, set one (\"text\" in 25m) or cancel one (-c id)", run: cmd_remind },
+    Command { name: "jobs", help: "list the shell's jobs", run: cmd_jobs },
+    Command { name: "fg", help: "bring a job to the foreground (%job, the latest by default)", run: cmd_fg },
+    Command { name: "bg", help: "continue a stopped job in the background (%job, the latest by default)", run: cmd_bg },
+    Command { name: "kill", help: "send a signal to processes or jobs (-SIGNAL pid|%job..., -l to list signals)", run: cmd_kill },
+    Command { name: "sync",  help: "write cached disk blocks back to disk", run: cmd_sync },
+    Command { name: "shutdown", help: "close apps, flush disks and power off", run: cmd_shutdown },
+];
+
+// Where apps named without a path are looked for
+const PATH: &[&str] = &["/bin", "/Applications"];
+
+// Apps started from one command line, joined by pipes. What they print
+// comes back through `output`; lines typed while the job is in the
+// foreground go to the first one's input.
+struct Job {
+    id: usize,
+    command: String,
+    pids: Vec<Pid>,
+    output: Reader,
+    input: Option<Writer>,
+    // Typed input the pipe didn't have room for yet
+    feed: Vec<u8>,
+    // Output after the last newline
+    partial: Vec<u8>,
+}
+
+impl Job {
+    fn signal(&self, signal: Signal) {
+        for &pid in &self.pids {
+            let _ = task::signal(pid, signal);
+        }
+    }
+
+    fn is_stopped(&self) -> bool {
+        self.pids.iter().any(|&pid| task::state(pid) == Some(ProcessState::Stopped))
+    }
+
+    fn is_finished(&self) -> bool {
+        self.pids.iter().all(|&pid| !matches!(task::state(pid), Some(ProcessState::Running | ProcessState::Stopped)))
+    }
+
+    // Moves typed input along and collects whole lines of output
+    fn pump(&mut self, out: &mut Output) {
+        if let Some(input) = &self.input {
+            match input.write(&self.feed) {
+                Ok(count) => {
+                    self.feed.drain(..count);
+                }
+                Err(PipeError::WouldBlock) => {}
+                Err(PipeError::Broken) => self.feed.clear(),
+            }
+        }
+        let mut buf = [0; 256];
+        while let Ok(count @ 1..) = self.output.read(&mut buf) {
+            self.partial.extend_from_slice(&buf[..count]);
+        }
+        while let Some(end) = self.partial.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = self.partial.drain(..=end).collect();
+            out.push(String::from(String::from_utf8_lossy(&line[..end]).trim_end_matches('\r')));
+        }
+    }
+}
+
+pub struct Shell {
+    pub cwd: String,
+    pub history: Vec<String>,
+    pub pid: Option<Pid>,
+    jobs: Vec<Job>,
+    // The id of the job typed input goes to, which has the prompt waiting
+    foreground: Option<usize>,
+}
+
+impl Shell {
+    pub fn new() -> Self {
+        Self {
+            cwd: String::from("/"),
+            history: Vec::new(),
+            pid: task::spawn("sh").ok(),
+            jobs: Vec::new(),
+            foreground: None,
+        }
+    }
+    
+    // Child processes start as a copy-on-write fork of the shell
+    pub fn spawn(&mut self, name: &str) -> Result<Pid, TaskError> {
+        task::fork(self.pid.ok_or(TaskError::NoSuchProcess)?, name)
+    }
+    
+    pub fn prompt(&self) -> String {
+        format!("RustOS:{} {}$ ", self.cwd, users::user_name(users::current_uid()))
+    }
+    
+    fn absolute(&self, path: &str) -> String {
+        let joined = if path.starts_with('/') { String::from(path) } else { vfs::join(&self.cwd, path) };
+        vfs::normalize(&joined).unwrap_or(joined)
+    }
+    
+    pub fn execute(&mut self, line: &str) -> Output {
+        let mut output = Vec::new();
+        let line = line.trim();
+        if line.is_empty() {
+            return output;
+        }
+        self.history.push(String::from(line));
+        
+        let (line, background) = match line.strip_suffix('&') {
+            Some(rest) => (rest.trim_end(), true),
+            None => (line, false),
+        };
+        let stages: Vec<Vec<&str>> = line.split('|').map(|stage| stage.split_whitespace().collect()).collect();
+        if stages.iter().any(|stage| stage.is_empty()) {
+            output.push(String::from("sh: syntax error near `|'"));
+            return output;
+        }
+        // Built-ins run right here, even with a `&`
+        if let [args] = stages.as_slice() {
+            if let Some(command) = COMMANDS.iter().find(|c| c.name == args[0]) {
+                (command.run)(self, &args[1..], &mut output);
+                return output;
+            }
+        }
+        self.start_job(line, &stages, background, &mut output);
+        output
+    }
+    
+    fn find_executable(&self, name: &str) -> Option<String> {
+        if name.contains('/') {
+            return Some(self.absolute(name)).filter(|path| elf::is_executable(path));
+        }
+        PATH.iter().map(|dir| vfs::join(dir, name)).find(|path| elf::is_executable(path))
+    }
+    
+    fn start_job(&mut self, command: &str, stages: &[Vec<&str>], background: bool, out: &mut Output) {
+        let mut paths = Vec::new();
+        for stage in stages {
+            if COMMANDS.iter().any(|c| c.name == stage[0]) {
+                out.push(format!("sh: {}: built-in commands can't be piped", stage[0]));
+                return;
+            }
+            match self.find_executable(stage[0]) {
+                Some(path) => paths.push(path),
+                None => {
+                    out.push(format!("{}: command not found", stage[0]));
+                    return;
+                }
+            }
+        }
+        let (output, output_writer) = pipe::create();
+        let (first_input, input) = pipe::create();
+        let mut stdin = Some(first_input);
+        let mut pids = Vec::new();
+        for (i, path) in paths.iter().enumerate() {
+            let pid = match userspace::launch(path) {
+                Ok(pid) => pid,
+                Err(err) => {
+                    out.push(format!("sh: {}: {:?}", path, err));
+                    for pid in pids {
+                        let _ = task::signal(pid, Signal::Kill);
+                    }
+                    return;
+                }
+            };
+            pids.push(pid);
+            let current = stdin.take();
+            let stdout = if i + 1 < paths.len() {
+                let (reader, writer) = pipe::create();
+                stdin = Some(reader);
+                writer
+            } else {
+                output_writer.clone()
+            };
+            let files = [
+                (STDIN, current.map(Descriptor::Input)),
+                (STDOUT, Some(Descriptor::Output(stdout))),
+                (STDERR, Some(Descriptor::Output(output_writer.clone()))),
+            ];
+            for (fd, file) in files {
+                let _ = task::set_file(pid, fd, file);
+            }
+        }
+        let id = (1..).find(|id| !self.jobs.iter().any(|job| job.id == *id)).unwrap_or(1);
+        if background {
+            out.push(format!("[{}] {}", id, pids.last().copied().unwrap_or(0)));
+        } else {
+            self.foreground = Some(id);
+        }
+        self.jobs.push(Job {
+            id,
+            command: String::from(command),
+            pids,
+            output,
+            input: Some(input),
+            feed: Vec::new(),
+            partial: Vec::new(),
+        });
+    }
+    
+    // What the jobs printed since the last call, and which of them finished
+    // or stopped; called every frame
+    pub fn poll(&mut self) -> Output {
+        let mut out = Vec::new();
+        let foreground = self.foreground;
+        self.jobs.retain_mut(|job| {
+            job.pump(&mut out);
+            if !job.is_finished() {
+                return true;
+            }
+            if !job.partial.is_empty() {
+                out.push(String::from_utf8_lossy(&core::mem::take(&mut job.partial)).into_owned());
+            }
+            if foreground != Some(job.id) {
+                out.push(format!("[{}]   Done                    {}", job.id, job.command));
+            }
+            false
+        });
+        if let Some(id) = self.foreground {
+            match self.jobs.iter().find(|job| job.id == id) {
+                Some(job) if job.is_stopped() => {
+                    out.push(format!("[{}]+  Stopped                 {}", job.id, job.command));
+                    self.foreground = None;
+                }
+                Some(_) => {}
+                None => self.foreground = None,
+            }
+        }
+        out
+    }
+    
+    // Whether a job has the terminal, and the prompt has to wait
+    pub fn is_busy(&self) -> bool {
+        self.foreground.is_some()
+    }
+    
+    fn foreground_job(&mut self) -> Option<&mut Job> {
+        let id = self.foreground?;
+        self.jobs.iter_mut().find(|job| job.id == id)
+    }
+    
+    // Ctrl+C. False if no job has the terminal.
+    pub fn interrupt(&mut self) -> bool {
+        self.foreground_job().map(|job| job.signal(Signal::Interrupt)).is_some()
+    }
+    
+    // Ctrl+Z; `poll` says so once the job has stopped
+    pub fn suspend(&mut self) -> bool {
+        self.foreground_job().map(|job| job.signal(Signal::Stop)).is_some()
+    }
+    
+    // A line typed while a job has the terminal
+    pub fn input_line(&mut self, line: &str) {
+        if let Some(job) = self.foreground_job() {
+            job.feed.extend_from_slice(line.as_bytes());
+            job.feed.push(b'\n');
+        }
+    }
+    
+    // Ctrl+D: the end of the foreground job's input
+    pub fn close_input(&mut self) {
+        if let Some(job) = self.foreground_job() {
+            job.input = None;
+        }
+    }
+    
+    // `%n` or `n`, or the latest job without one
+    fn job_index(&self, arg: Option<&str>) -> Result<usize, String> {
+        let index = match arg {
+            None => self.jobs.len().checked_sub(1),
+            Some(arg) => {
+                let id = arg.strip_prefix('%').unwrap_or(arg).parse::<usize>().ok();
+                self.jobs.iter().position(|job| Some(job.id) == id)
+            }
+        };
+        index.ok_or_else(|| format!("{}: no such job", arg.unwrap_or("current")))
+    }
+}
+
+// Jobs don't outlive the terminal they were started from
+impl Drop for Shell {
+    fn drop(&mut self) {
+        for job in &self.jobs {
+            job.signal(Signal::Kill);
+        }
+    }
+}
+
+fn cmd_help(_shell: &mut Shell, _args: &[&str], out: &mut Output) {
+    for command in COMMANDS {
+        out.push(format!("{:<10} {}", command.name, command.help));
+    }
+}
+
+fn error_message(err: VfsError) -> &'static str {
+    match err {
+        VfsError::NotFound => "No such file or directory",
+        VfsError::NotADirectory => "Not a directory",
+        VfsError::IsADirectory => "Is a directory",
+        VfsError::AlreadyExists => "File exists",
+        VfsError::NotEmpty => "Directory not empty",
+        VfsError::ReadOnly => "Read-only file system",
+        VfsError::PermissionDenied => "Permission denied",
+        VfsError::NoSpace => "No space left on device",
+        VfsError::InvalidPath => "Invalid path",
+        VfsError::Unsupported => "Operation not supported",
+        VfsError::Corrupt | VfsError::Io(_) => "Input/output error",
+    }
+}
+
+fn cmd_pwd(shell: &mut Shell, _args: &[&str], out: &mut Output) {
+    out.push(shell.cwd.clone());
+}
+
+fn cmd_cd(shell: &mut Shell, args: &[&str], out: &mut Output) {
+    let path = shell.absolute(args.first().copied().unwrap_or("/"));
+    match vfs::metadata(&path) {
+        Ok(meta) if meta.is_dir() => match vfs::read_dir(&path) {
+            Ok(_) => shell.cwd = path,
+            Err(err) => out.push(format!("cd: {}: {}", path, error_message(err))),
+        },
+        Ok(_) => out.push(format!("cd: {}: Not a directory", path)),
+        Err(err) => out.push(format!("cd: {}: {}", path, error_message(err))),
+    }
+}
+
+fn cmd_ls(shell: &mut Shell, args: &[&str], out: &mut Output) {
+    let mut long = false;
+    let mut all = false;
+    let mut targets = Vec::new();
+    for arg in args {
+        match arg.strip_prefix('-') {
+            Some(flags) => {
+                long |= flags.contains('l');
+                all |= flags.contains('a');
+            }
+            None => targets.push(*arg),
+        }
+    }
+    if targets.is_empty() {
+        targets.push(".");
+    }
+    
+    for target in targets {
+        let path = shell.absolute(target);
+        let meta = match vfs::metadata(&path) {
+            Ok(meta) => meta,
+            Err(err) => {
+                out.push(format!("ls: {}: {}", target, error_message(err)));
+                continue;
+            }
+        };
+        if !meta.is_dir() {
+            list_entry(out, &path, target, long);
+            continue;
+        }
+        
+        let mut entries = match vfs::read_dir(&path) {
+            Ok(entries) => entries,
+            Err(err) => {
+                out.push(format!("ls: {}: {}", target, error_message(err)));
+                continue;
+            }
+        };
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        
+        if all {
+            list_entry(out, &path, ".", long);
+            list_entry(out, vfs::parent_and_name(&path).0, "..", long);
+        }
+        for entry in entries.iter().filter(|e| all || !e.name.starts_with('.')) {
+            list_entry(out, &vfs::join(&path, &entry.name), &entry.name, long);
+        }
+    }
+}
+
+fn list_entry(out: &mut Output, path: &str, name: &str, long: bool) {
+    if !long {
+        out.push(String::from(name));
+        return;
+    }
+    match vfs::metadata(path) {
+        Ok(meta) => out.push(format!(
+            "{}  1 {:<6} {:<6} {:>8} {} {}",
+            meta.mode_string(),
+            users::user_name(meta.uid),
+            users::group_name(meta.gid),
+            meta.size,
+            DateTime::from_unix(meta.modified).short(),
+            name
+        )),
+        Err(err) => out.push(format!("ls: {}: {}", name, error_message(err))),
+    }
+}
+
+fn cmd_ps(_shell: &mut Shell, _args: &[&str], out: &mut Output) {
+    out.push(String::from("  PID  PPID USER     RSS SHARED STAT COMMAND"));
+    for process in task::list() {
+        let state = match process.state {
+            ProcessState::Running => "R",
+            ProcessState::Stopped => "T",
+            ProcessState::Zombie(_) => "Z",
+        };
+        out.push(format!(
+            "{:>5} {:>5} {:<6} {:>5} {:>6} {:<4} {}",
+            process.pid,
+            process.parent.unwrap_or(0),
+            users::user_name(process.uid),
+            process.resident_pages * 4,
+            process.shared_pages * 4,
+            state,
+            process.name
+        ));
+    }
+}
+
+fn cmd_mount(_shell: &mut Shell, _args: &[&str], out: &mut Output) {
+    for mount in vfs::mounts() {
+        let access = if mount.read_only { "ro" } else { "rw" };
+        out.push(format!("{} on {} type {} ({})", mount.source, mount.path, mount.fs_type, access));
+    }
+}
+
+fn cmd_sysinfo(_shell: &mut Shell, _args: &[&str], out: &mut Output) {
+    let info = system_info::collect();
+    out.push(format!("Kernel:     {}", info.kernel));
+    out.push(format!("Processor:  {}", info.processor));
+    out.push(format!("Features:   {}", cpu::features().names().join(" ")));
+    let tsc = if clock::uses_tsc() { format!(", TSC at {} MHz", clock::tsc_hz() / 1_000_000) } else { String::new() };
+    out.push(format!("Clock:      {}{}", clock::source().name(), tsc));
+    match acpi::tables() {
+        Some(tables) => {
+            let apic_ids: Vec<String> = acpi::processors().iter().filter(|cpu| cpu.enabled).map(|cpu| cpu.apic_id.to_string()).collect();
+            out.push(format!("Processors: {} (APIC IDs {}; only the first runs)", apic_ids.len(), apic_ids.join(" ")));
+            let sci = acpi::power().map_or(String::from("none"), |power| power.sci_irq.to_string());
+            out.push(format!("ACPI:       revision {} from {}, SCI on IRQ {}", tables.revision, tables.oem, sci));
+            for io_apic in &tables.io_apics {
+                out.push(format!("{:<11} I/O APIC {} at {:#x}, GSIs from {}", "", io_apic.id, io_apic.address, io_apic.gsi_base));
+            }
+        }
+        None => out.push(String::from("ACPI:       none")),
+    }
+    out.push(format!("Memory:     {}", info.memory));
+    out.push(format!("Heap:       {}", info.heap));
+    out.push(format!("Display:    {}", info.display));
+    out.push(format!("Arguments:  {}", bootargs::cmdline()));
+    if info.storage.is_empty() {
+        out.push(String::from("Storage:    none"));
+    }
+    for (i, device) in info.storage.iter().enumerate() {
+        let label = if i == 0 { "Storage:" } else { "" };
+        out.push(format!("{:<11} {}", label, device));
+    }
+    for (i, stage) in services::stages().iter().enumerate() {
+        let label = if i == 0 { "Boot:" } else { "" };
+        let time = stage.duration().map_or(String::from("-"), |duration| format!("{} ms", duration.as_millis()));
+        let detail = match stage.status {
+            Status::Failed(err) => format!("failed: {}", err),
+            status => String::from(status.label()),
+        };
+        out.push(format!("{:<11} {:<15} {:>7}  {}", label, stage.name, time, detail));
+    }
+}
+
+fn cmd_lspci(_shell: &mut Shell, _args: &[&str], out: &mut Output) {
+    for device in pci::devices() {
+        let irq = match device.interrupt_line {
+            0xFF => String::new(),
+            line => format!(" (irq {})", line),
+        };
+        out.push(format!(
+            "{:02x}:{:02x}.{} {:04x}:{:04x} {} {}{}",
+            device.bus,
+            device.device,
+            device.function,
+            device.vendor_id,
+            device.device_id,
+            device.vendor_name(),
+            device.class_name(),
+            irq
+        ));
+    }
+}
+
+fn cmd_lsmem(_shell: &mut Shell, _args: &[&str], out: &mut Output) {
+    let map = match memory::memory_map() {
+        Some(map) => map,
+        None => {
+            out.push(String::from("lsmem: memory map not available"));
+            return;
+        }
+    };
+    out.push(String::from("START              END                    SIZE TYPE"));
+    for region in map.iter() {
+        out.push(format!(
+            "{:#018x} {:#018x} {:>8} {:?}",
+            region.range.start_addr(),
+            region.range.end_addr(),
+            format_size(memory::region_size(region)),
+            region.region_type
+        ));
+    }
+    let stats = memory::stats();
+    out.push(format!(
+        "installed {}, usable {}, available {}, DMA buffers {}",
+        format_size(stats.installed),
+        format_size(stats.usable),
+        format_size(stats.available),
+        format_size(crate::dma::pages_in_use() as u64 * memory::PAGE_SIZE)
+    ));
+}
+
+fn vector_name(vector: u8) -> String {
+    match vector {
+        3 => String::from("breakpoint"),
+        6 => String::from("invalid opcode"),
+        8 => String::from("double fault"),
+        13 => String::from("general protection"),
+        14 => String::from("page fault"),
+        rustos_sdk::protocol::SYSCALL_VECTOR => String::from("system call"),
+        vector if (interrupts::PIC_1_OFFSET..interrupts::PIC_1_OFFSET + 16).contains(&vector) => {
+            format!("IRQ {}", vector - interrupts::PIC_1_OFFSET)
+        }
+        crate::apic::SPURIOUS_VECTOR => String::from("APIC spurious"),
+        vector => match interrupts::msi_owner(vector) {
+            Some(owner) => format!("MSI {}", owner),
+            None => String::from("-"),
+        },
+    }
+}
+
+fn cmd_lsirq(_shell: &mut Shell, args: &[&str], out: &mut Output) {
+    if args == ["-v"] {
+        out.push(String::from("VECTOR NAME                    COUNT"));
+        for (vector, count) in interrupts::vector_counts() {
+            out.push(format!("{:>#6x} {:<18} {:>10}", vector, vector_name(vector), count));
+        }
+        return;
+    }
+    let devices = pci::devices();
+    out.push(String::from("IRQ GSI OWNERS         STATE         COUNT   /SEC UNCLAIMED PCI DEVICES"));
+    for irq in interrupts::irq_lines() {
+        let routed: Vec<String> = devices
+            .iter()
+            .filter(|d| d.interrupt_line == irq.line)
+            .map(|d| format!("{:02x}:{:02x}.{}", d.bus, d.device, d.function))
+            .collect();
+        let state = if irq.masked { "masked" } else { "enabled" };
+        let owners = if irq.owners.is_empty() { String::from("-") } else { irq.owners.join(",") };
+        out.push(format!(
+            "{:>3} {:>3} {:<14} {:<8} {:>10} {:>6} {:>9} {}",
+            irq.line,
+            irq.gsi,
+            owners,
+            state,
+            irq.count,
+            irq.rate,
+            irq.unclaimed,
+            routed.join(" ")
+        ));
+    }
+    let vectors = interrupts::msi_vectors();
+    if !vectors.is_empty() {
+        out.push(String::new());
+        out.push(String::from("VECTOR OWNERS                COUNT   /SEC"));
+    }
+    for msi in vectors {
+        out.push(format!("{:>#6x} {:<14} {:>10} {:>6}", msi.vector, msi.owner, msi.count, msi.rate));
+    }
+}
+
+fn cmd_dmesg(_shell: &mut Shell, args: &[&str], out: &mut Output) {
+    let mut level = Level::Debug;
+    let mut count = None;
+    let mut since = 0;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = match *arg {
+            "-l" | "-n" | "-s" => match args.next() {
+                Some(value) => *value,
+                None => {
+                    out.push(format!("dmesg: {} needs a value", arg));
+                    return;
+                }
+            },
+            _ => {
+                out.push(format!("dmesg: unknown option {}", arg));
+                return;
+            }
+        };
+        let parsed = match *arg {
+            "-l" => Level::from_name(value).map(|value| level = value),
+            "-n" => value.parse().ok().map(|value| count = Some(value)),
+            _ => value.parse().ok().map(|value| since = value),
+        };
+        if parsed.is_none() {
+            out.push(format!("dmesg: bad value for {}: {}", arg, value));
+            return;
+        }
+    }
+    
+    // Lines are numbered from 1; anything before the first one still in
+    // the buffer has been overwritten
+    let (lines, dropped) = log::read_since(since.max(1));
+    if dropped > 0 {
+        out.push(format!("dmesg: {} lines dropped", dropped));
+    }
+    let lines: Vec<_> = lines.into_iter().filter(|line| line.level <= level).collect();
+    let skip = count.map_or(0, |count| lines.len().saturating_sub(count));
+    for line in &lines[skip..] {
+        out.push(format!("{:>5} {}", line.seq, line.format()));
+    }
+}
+
+// Samples on every timer tick; export writes collapsed stacks for
+// flamegraph tools, to profile.folded unless a file is given
+fn cmd_profile(shell: &mut Shell, args: &[&str], out: &mut Output) {
+    match args {
+        [] => {
+            let status = cpu_profiler::status();
+            let state = if status.running { "running" } else { "stopped" };
+            out.push(format!("profile: {}, {} samples, {} dropped", state, status.samples, status.dropped));
+        }
+        ["start"] => cpu_profiler::start(),
+        ["stop"] => cpu_profiler::stop(),
+        ["export"] | ["export", _] => {
+            let path = shell.absolute(args.get(1).copied().unwrap_or("profile.folded"));
+            match cpu_profiler::export(&path) {
+                Ok(samples) => out.push(format!("profile: {} samples written to {}", samples, path)),
+                Err(err) => out.push(format!("profile: {}: {}", path, error_message(err))),
+            }
+        }
+        _ => out.push(String::from("usage: profile [start | stop | export [file]]")),
+    }
+}
+
+fn cmd_heapstat(_shell: &mut Shell, _args: &[&str], out: &mut Output) {
+    let heap = allocator::usage();
+    out.push(format!("heap: {} used, {} free, {} total", heap.used, heap.free, heap.size));
+    for pool in pool::stats() {
+        out.push(format!(
+            "pool {:<18} {:>3}/{:<3} in use, peak {}, {} heap fallbacks",
+            pool.name, pool.in_use, pool.capacity, pool.peak, pool.fallbacks
+        ));
+    }
+    if !heap_profiler::enabled() {
+        out.push(String::from("allocation tracking disabled (build with --features heap-profiler)"));
+        return;
+    }
+    
+    out.push(String::from("TAG              ALLOCS    FREES     LIVE     PEAK"));
+    for (tag, stats) in heap_profiler::tag_stats() {
+        out.push(format!(
+            "{:<15} {:>7} {:>8} {:>8} {:>8}",
+            tag, stats.allocs, stats.frees, stats.live_bytes, stats.peak_bytes
+        ));
+    }
+    
+    out.push(String::from("TOP SITES (live bytes)"));
+    for site in heap_profiler::top_sites(8) {
+        out.push(format!("{:#018x} {:<15} {:>6} allocs {:>8} bytes", site.addr, site.tag, site.allocs, site.live_bytes));
+    }
+    let untracked = heap_profiler::untracked();
+    if untracked > 0 {
+        out.push(format!("{} allocations untracked (live table full)", untracked));
+    }
+}
+
+fn net_error_message(err: NetError) -> &'static str {
+    match err {
+        NetError::NoInterface => "Network is down",
+        NetError::NoBuffers => "No buffer space available",
+        NetError::TooLong => "Message too long",
+        NetError::Unreachable => "Host is unreachable",
+        NetError::AddressInUse => "Address already in use",
+        NetError::BadSocket => "Bad socket",
+        NetError::Closed => "Connection closed",
+        NetError::Refused => "Connection refused",
+        NetError::TimedOut => "Operation timed out",
+        NetError::NoSuchHost => "Unknown host",
+    }
+}
+
+const PING_PAYLOAD: usize = 56;
+const PING_INTERVAL: Duration = Duration::from_secs(1);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+const TRACEROUTE_PROBES: usize = 3;
+
+// Options of the form `-x value` followed by one address
+fn parse_target(name: &str, args: &[&str], option: &str, value: &mut usize, out: &mut Output) -> Option<Ipv4Addr> {
+    let mut target = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if *arg == option {
+            match args.next().and_then(|v| v.parse().ok()) {
+                Some(parsed) if parsed > 0 => *value = parsed,
+                _ => {
+                    out.push(format!("{}: {} needs a positive number", name, option));
+                    return None;
+                }
+            }
+        } else if target.is_none() {
+            match dns::resolve(arg) {
+                Ok(addr) => target = Some(addr),
+                Err(err) => {
+                    out.push(format!("{}: cannot resolve {}: {}", name, arg, net_error_message(err)));
+                    return None;
+                }
+            }
+        } else {
+            out.push(format!("usage: {} [{} n] host", name, option));
+            return None;
+        }
+    }
+    if target.is_none() {
+        out.push(format!("usage: {} [{} n] host", name, option));
+    }
+    target
+}
+
+// Sends echo request `seq` and waits for the reply or error that answers it
+fn probe(socket: Handle, target: Ipv4Addr, seq: u16) -> Result<Option<(Datagram, Duration)>, NetError> {
+    let ident = socket::local_port(socket)?;
+    let payload: Vec<u8> = (0..PING_PAYLOAD).map(|i| b'a' + (i % 26) as u8).collect();
+    let sent = Instant::now();
+    socket::send_to(socket, target, 0, &net::icmp_echo(ident, seq, &payload))?;
+    while sent.elapsed() < PROBE_TIMEOUT {
+        net::poll();
+        match socket::recv_from(socket)? {
+            Some(reply) if net::echo_id(&reply.data) == Some((ident, seq)) => return Ok(Some((reply, sent.elapsed()))),
+            // Answers to earlier probes that came in late
+            Some(_) => {}
+            None => core::hint::spin_loop(),
+        }
+    }
+    Ok(None)
+}
+
+fn format_ms(time: Duration) -> String {
+    let micros = time.as_micros();
+    format!("{}.{:03} ms", micros / 1000, micros % 1000)
+}
+
+fn icmp_error_name(message: &[u8]) -> &'static str {
+    match message[0] {
+        net::ICMP_TIME_EXCEEDED => "Time to live exceeded",
+        _ => "Destination unreachable",
+    }
+}
+
+fn cmd_ping(_shell: &mut Shell, args: &[&str], out: &mut Output) {
+    let mut count = 4;
+    let target = match parse_target("ping", args, "-c", &mut count, out) {
+        Some(target) => target,
+        None => return,
+    };
+    let socket = match socket::open(Protocol::Icmp, 0) {
+        Ok(socket) => socket,
+        Err(err) => {
+            out.push(format!("ping: {}", net_error_message(err)));
+            return;
+        }
+    };
+    
+    out.push(format!("PING {}: {} data bytes", target, PING_PAYLOAD));
+    let mut times = Vec::new();
+    for seq in 0..count {
+        let started = Instant::now();
+        match probe(socket, target, seq as u16) {
+            Ok(Some((reply, time))) if reply.data[0] == net::ICMP_ECHO_REPLY => {
+                out.push(format!(
+                    "{} bytes from {}: icmp_seq={} ttl={} time={}",
+                    reply.data.len(), reply.from, seq, reply.ttl, format_ms(time)
+                ));
+                times.push(time);
+            }
+            Ok(Some((reply, _))) => out.push(format!("From {} icmp_seq={} {}", reply.from, seq, icmp_error_name(&reply.data))),
+            Ok(None) => out.push(format!("Request timeout for icmp_seq {}", seq)),
+            Err(err) => out.push(format!("ping: {}", net_error_message(err))),
+        }
+        if seq + 1 < count {
+            while started.elapsed() < PING_INTERVAL {
+                net::poll();
+            }
+        }
+    }
+    socket::close(socket);
+    
+    out.push(format!("--- {} ping statistics ---", target));
+    out.push(format!(
+        "{} packets transmitted, {} packets received, {}% packet loss",
+        count,
+        times.len(),
+        (count - times.len()) * 100 / count
+    ));
+    if let (Some(min), Some(max)) = (times.iter().min(), times.iter().max()) {
+        let avg = times.iter().sum::<Duration>() / times.len() as u32;
+        out.push(format!("round-trip min/avg/max = {} / {} / {}", format_ms(*min), format_ms(avg), format_ms(*max)));
+    }
+}
+
+fn cmd_traceroute(_shell: &mut Shell, args: &[&str], out: &mut Output) {
+    let mut max_hops = 30;
+    let target = match parse_target("traceroute", args, "-m", &mut max_hops, out) {
+        Some(target) => target,
+        None => return,
+    };
+    let socket = match socket::open(Protocol::Icmp, 0) {
+        Ok(socket) => socket,
+        Err(err) => {
+            out.push(format!("traceroute: {}", net_error_message(err)));
+            return;
+        }
+    };
+    
+    out.push(format!("traceroute to {}, {} hops max", target, max_hops));
+    let mut seq = 0;
+    for ttl in 1..=max_hops.min(255) {
+        let _ = socket::set_ttl(socket, ttl as u8);
+        let mut line = format!("{:>2} ", ttl);
+        let mut hop = None;
+        let mut done = false;
+        for _ in 0..TRACEROUTE_PROBES {
+            seq += 1;
+            match probe(socket, target, seq) {
+                Ok(Some((reply, time))) => {
+                    if hop != Some(reply.from) {
+                        line.push_str(&format!(" {}", reply.from));
+                        hop = Some(reply.from);
+                    }
+                    line.push_str(&format!("  {}", format_ms(time)));
+                    // Anything but a router along the way ends the trace
+                    done |= reply.data[0] != net::ICMP_TIME_EXCEEDED;
+                    if reply.data[0] == net::ICMP_DEST_UNREACHABLE {
+                        line.push_str(" !H");
+                    }
+                }
+                Ok(None) => line.push_str(" *"),
+                Err(err) => {
+                    line.push_str(&format!(" ({})", net_error_message(err)));
+                    done = true;
+                    break;
+                }
+            }
+        }
+        out.push(line);
+        if done {
+            break;
+        }
+    }
+    socket::close(socket);
+}
+
+fn cmd_netstat(_shell: &mut Shell, args: &[&str], out: &mut Output) {
+    if args.contains(&"-i") {
+        match net::interface_info() {
+            Some(iface) => {
+                out.push(format!("{}: {} {}", iface.name, iface.driver, iface.mac));
+                out.push(format!(
+                    "    inet {} netmask {} gateway {} dns {}",
+                    iface.config.ip, iface.config.netmask, iface.config.gateway, iface.config.name_server
+                ));
+                let c = iface.counters;
+                out.push(format!("    RX packets {} bytes {} dropped {}", c.rx_packets, c.rx_bytes, c.rx_dropped));
+                out.push(format!("    TX packets {} bytes {} errors {}", c.tx_packets, c.tx_bytes, c.tx_errors));
+            }
+            None => out.push(String::from("netstat: no network interface")),
+        }
+        return;
+    }
+    
+    out.push(String::from(
+        "Proto Recv-Q Send-Q Local Address          Foreign Address        State        RX pkts  RX bytes  TX pkts  TX bytes  Drops",
+    ));
+    for info in socket::list() {
+        let c = info.counters;
+        let remote = match info.remote {
+            Some((ip, port)) => format!("{}:{}", ip, port),
+            None => String::from("*:*"),
+        };
+        out.push(format!(
+            "{:<5} {:>6} {:>6} {:<22} {:<22} {:<11} {:>8} {:>9} {:>8} {:>9} {:>6}",
+            info.protocol.name(),
+            info.recv_queue,
+            info.send_queue,
+            format!("{}:{}", info.local.0, info.local.1),
+            remote,
+            info.state.name(),
+            c.rx_packets,
+            c.rx_bytes,
+            c.tx_packets,
+            c.tx_bytes,
+            c.dropped
+        ));
+    }
+}
+
+fn cmd_defaults(_shell: &mut Shell, args: &[&str], out: &mut Output) {
+    match args {
+        ["read", domain] => {
+            for (key, value) in preferences::read(domain) {
+                out.push(format!("{} = {}", key, value));
+            }
+        }
+        ["read", domain, key] => match preferences::get(domain, key) {
+            Some(value) => out.push(value),
+            None => out.push(format!("defaults: {} has no key {}", domain, key)),
+        },
+        // Values may contain spaces
+        ["write", domain, key, value @ ..] if !value.is_empty() => {
+            if let Err(err) = preferences::set(domain, key, &value.join(" ")) {
+                out.push(format!("defaults: {}", error_message(err)));
+            }
+        }
+        _ => out.push(String::from("usage: defaults read domain [key] | defaults write domain key value")),
+    }
+}
+
+fn keychain_message(err: KeychainError) -> &'static str {
+    match err {
+        KeychainError::Locked => "The keychain is locked",
+        KeychainError::WrongPassword => "The password is incorrect",
+        KeychainError::Damaged => "The keychain file is damaged",
+        KeychainError::NotFound => "The item could not be found in the keychain",
+        KeychainError::Vfs(err) => error_message(err),
+    }
+}
+
+const SECURITY_USAGE: [&str; 6] = [
+    "usage: security add-generic-password -s service -a account -w password",
+    "       security find-generic-password -s service -a account",
+    "       security delete-generic-password -s service -a account",
+    "       security dump-keychain | lock-keychain | unlock-keychain [-p password]",
+    "       security set-keychain-password -o old -p new",
+    "find-generic-password prints the password",
+];
+
+// Every option takes a value. Passwords given on the command line end up
+// in the Terminal's history like any other argument.
+fn cmd_security(_shell: &mut Shell, args: &[&str], out: &mut Output) {
+    let (command, options) = match args {
+        [command, options @ ..] => (*command, options),
+        [] => {
+            out.extend(SECURITY_USAGE.iter().map(|line| String::from(*line)));
+            return;
+        }
+    };
+    let mut values = Vec::new();
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        match (*option, options.next()) {
+            (name @ ("-s" | "-a" | "-w" | "-p" | "-o"), Some(value)) => values.push((name, *value)),
+            (name, _) => {
+                out.push(format!("security: bad option {}", name));
+                return;
+            }
+        }
+    }
+    let value = |name: &str| values.iter().find(|(option, _)| *option == name).map(|(_, value)| *value);
+    let result = match (command, value("-s"), value("-a")) {
+        ("add-generic-password", Some(service), Some(account)) => keychain::set(service, account, value("-w").unwrap_or("")),
+        ("find-generic-password", Some(service), Some(account)) => keychain::get(service, account).map(|password| out.push(password)),
+        ("delete-generic-password", Some(service), Some(account)) => keychain::remove(service, account),
+        ("dump-keychain", None, None) => {
+            let locked = if keychain::is_unlocked() { "" } else { " (locked)" };
+            out.push(format!("login.keychain{}", locked));
+            for (service, account) in keychain::items() {
+                out.push(format!("    {}  {}", service, account));
+            }
+            Ok(())
+        }
+        ("lock-keychain", None, None) => {
+            keychain::lock();
+            Ok(())
+        }
+        ("unlock-keychain", None, None) => keychain::unlock(value("-p").unwrap_or("")),
+        ("set-keychain-password", None, None) => keychain::change_password(value("-o").unwrap_or(""), value("-p").unwrap_or("")),
+        _ => {
+            out.extend(SECURITY_USAGE.iter().map(|line| String::from(*line)));
+            return;
+        }
+    };
+    if let Err(err) = result {
+        out.push(format!("security: {}", keychain_message(err)));
+    }
+}
+
+// The desktop opens the file once it reads the bus, asking which app to
+// use if that isn't settled. App names may contain spaces, so everything
+// between -a and the path is the app.
+fn cmd_open(shell: &mut Shell, args: &[&str], out: &mut Output) {
+    let (app, path) = match args {
+        [path] => (None, *path),
+        ["-a", app @ .., path] if !app.is_empty() => (Some(app.join(" ")), *path),
+        _ => {
+            out.push(String::from("usage: open [-a app] path"));
+            return;
+        }
+    };
+    let path = shell.absolute(path);
+    match vfs::metadata(&path) {
+        Ok(_) => bus::post(Message::OpenFile { path, app }),
+        Err(err) => out.push(format!("open: {}: {}", path, error_message(err))),
+    }
+}
+
+// The archive's format comes from its extension. Jobs the Finder runs a few
+// entries a frame run to the end here.
+fn cmd_tar(shell: &mut Shell, args: &[&str], out: &mut Output) {
+    let job = match args {
+        ["-tf", path] => {
+            match archive::list(&shell.absolute(path)) {
+                Ok(paths) => out.extend(paths),
+                Err(err) => out.push(format!("tar: {}: {}", path, error_message(err))),
+            }
+            return;
+        }
+        ["-cf", path, sources @ ..] if !sources.is_empty() => {
+            let sources: Vec<String> = sources.iter().map(|source| shell.absolute(source)).collect();
+            archive::Job::compress(&sources, &shell.absolute(path))
+        }
+        ["-xf", path] => archive::Job::extract(&shell.absolute(path), &shell.cwd.clone()),
+        ["-xf", path, "-C", dir] => archive::Job::extract(&shell.absolute(path), &shell.absolute(dir)),
+        _ => {
+            out.push(String::from("usage: tar -cf archive path... | tar -xf archive [-C dir] | tar -tf archive"));
+            return;
+        }
+    };
+    let mut job = match job {
+        Ok(job) => job,
+        Err(err) => {
+            out.push(format!("tar: {}", error_message(err)));
+            return;
+        }
+    };
+    loop {
+        match job.step() {
+            Ok(false) => {}
+            Ok(true) => return,
+            Err(err) => {
+                out.push(format!("tar: {}: {}", job.archive(), error_message(err)));
+                job.cancel();
+                return;
+            }
+        }
+    }
+}
+
+fn slot_line(status: &boot_slots::Status, slot: Slot, version: &str) -> String {
+    let mut notes = Vec::new();
+    if status.booted == Some(slot) {
+        notes.push("running");
+    }
+    if status.default == Some(slot) {
+        notes.push("default");
+    }
+    if status.trial == Some(slot) {
+        notes.push("tried at the next boot");
+    }
+    let version = if version.is_empty() { "empty" } else { version };
+    if notes.is_empty() {
+        format!("Slot {}: {}", slot.name().to_uppercase(), version)
+    } else {
+        format!("Slot {}: {} ({})", slot.name().to_uppercase(), version, notes.join(", "))
+    }
+}
+
+// An installed kernel boots once; if it doesn't reach the desktop the one
+// before it boots again
+fn cmd_softwareupdate(shell: &mut Shell, args: &[&str], out: &mut Output) {
+    let result = match args {
+        ["--list"] | [] => boot_slots::status().map(|status| {
+            for (slot, version) in [Slot::A, Slot::B].into_iter().zip(&status.versions) {
+                out.push(slot_line(&status, slot, version));
+            }
+        }),
+        ["--install", path, version @ ..] if version.len() <= 1 => {
+            let version = version.first().copied().unwrap_or("update");
+            boot_slots::install(&shell.absolute(path), version).map(|slot| {
+                out.push(format!("Installed to slot {}; restart to try it", slot.name().to_uppercase()));
+            })
+        }
+        _ => {
+            out.push(String::from("usage: softwareupdate --list | --install kernel [version]"));
+            return;
+        }
+    };
+    match result {
+        Err(VfsError::NotFound) if args.len() <= 1 => out.push(String::from("softwareupdate: not started from a disk with kernel slots (make boot-disk)")),
+        Err(err) => out.push(format!("softwareupdate: {}", error_message(err))),
+        Ok(()) => {}
+    }
+}
+
+fn cmd_remind(_shell: &mut Shell, args: &[&str], out: &mut Output) {
+    match args {
+        [] => {
+            let now = clock::millis();
+            for reminder in reminders::pending() {
+                out.push(format!("{:>3}  in {:<12} {}", reminder.id, reminders::format_delay(reminder.due.saturating_sub(now)), reminder.text));
+            }
+        }
+        ["-c", id] => match id.parse() {
+            Ok(id) if reminders::cancel(id) => {}
+            _ => out.push(format!("remind: no reminder {}", id)),
+        },
+        // Text may contain spaces
+        request => match reminders::parse(&request.join(" ")) {
+            Some((text, delay)) => {
+                let id = reminders::add(&text, delay);
+                out.push(format!("Reminder {} in {}: {}", id, reminders::format_delay(delay), text));
+            }
+            None => out.push(String::from("usage: remind [\"text\" in 25m | -c id]")),
+        },
+    }
+}
+
+fn cmd_jobs(shell: &mut Shell, _args: &[&str], out: &mut Output) {
+    let latest = shell.jobs.len().saturating_sub(1);
+    for (i, job) in shell.jobs.iter().enumerate() {
+        let marker = if i == latest { '+' } else { '-' };
+        let state = if job.is_stopped() { "Stopped" } else { "Running" };
+        out.push(format!("[{}]{}  {:<24}{}", job.id, marker, state, job.command));
+    }
+}
+
+fn cmd_fg(shell: &mut Shell, args: &[&str], out: &mut Output) {
+    match shell.job_index(args.first().copied()) {
+        Ok(index) => {
+            let job = &shell.jobs[index];
+            job.signal(Signal::Continue);
+            out.push(job.command.clone());
+            shell.foreground = Some(job.id);
+        }
+        Err(message) => out.push(format!("fg: {}", message)),
+    }
+}
+
+fn cmd_bg(shell: &mut Shell, args: &[&str], out: &mut Output) {
+    match shell.job_index(args.first().copied()) {
+        Ok(index) => {
+            let job = &shell.jobs[index];
+            job.signal(Signal::Continue);
+            out.push(format!("[{}]+ {} &", job.id, job.command));
+        }
+        Err(message) => out.push(format!("bg: {}", message)),
+    }
+}
+
+// Terminate unless another signal is named
+fn cmd_kill(shell: &mut Shell, args: &[&str], out: &mut Output) {
+    let (signal, targets) = match args {
+        ["-l"] => {
+            out.push(String::from("2) SIGINT  9) SIGKILL  15) SIGTERM  18) SIGCONT  19) SIGSTOP"));
+            return;
+        }
+        [flag, targets @ ..] if flag.starts_with('-') => match Signal::parse(&flag[1..]) {
+            Some(signal) => (signal, targets),
+            None => {
+                out.push(format!("kill: {}: invalid signal", &flag[1..]));
+                return;
+            }
+        },
+        targets => (Signal::Terminate, targets),
+    };
+    if targets.is_empty() {
+        out.push(String::from("usage: kill [-SIGNAL] pid|%job..."));
+        return;
+    }
+    for target in targets {
+        if target.starts_with('%') {
+            match shell.job_index(Some(target)) {
+                Ok(index) => shell.jobs[index].signal(signal),
+                Err(message) => out.push(format!("kill: {}", message)),
+            }
+            continue;
+        }
+        let result = target.parse::<Pid>().map_err(|_| "arguments must be process or job IDs")
+            .and_then(|pid| task::signal(pid, signal).map_err(|_| "No such process"));
+        if let Err(message) = result {
+            out.push(format!("kill: {}: {}", target, message));
+        }
+    }
+}
+
+fn cmd_sync(_shell: &mut Shell, _args: &[&str], out: &mut Output) {
+    if let Err(err) = block_cache::sync_all() {
+        out.push(format!("sync: {:?}", err));
+    }
+}
+
+// The desktop asks about unsaved changes on screen first; cancelling
+// there cancels the shutdown
+fn cmd_shutdown(_shell: &mut Shell, _args: &[&str], _out: &mut Output) {
+    bus::post(Message::ShutDown);
+}
\ No newline at end of file
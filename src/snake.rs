@@ -0,0 +1,255 @@
+// src/snake.rs
+// The Snake game. Arrow keys steer, Space pauses and Enter starts a new
+// game; clicking the board turns the snake towards the click. The snake
+// moves on a fixed step timed by the monotonic clock, not the frame rate,
+// and the game pauses itself whenever its window loses focus.
+use crate::clock::Instant;
+use crate::graphics::{Graphics, Color};
+use crate::keyboard::Key;
+use crate::rand;
+use crate::widgets::Button;
+use alloc::collections::VecDeque;
+use alloc::format;
+use core::time::Duration;
+use crate::sync::Mutex;
+
+const COLUMNS: i32 = 24;
+const ROWS: i32 = 16;
+const CELL: usize = 12;
+const TOOLBAR_HEIGHT: usize = 30;
+const START_LENGTH: usize = 3;
+// Each food eaten shortens the step, down to the minimum
+const START_STEP: Duration = Duration::from_millis(160);
+const MIN_STEP: Duration = Duration::from_millis(70);
+const STEP_SPEEDUP: Duration = Duration::from_millis(5);
+// Steps caught up in one frame; beyond that the game skips ahead
+const MAX_CATCH_UP: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    fn offset(self) -> (i32, i32) {
+        match self {
+            Direction::Up => (0, -1),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+        }
+    }
+    
+    fn is_opposite(self, other: Direction) -> bool {
+        let (a, b) = (self.offset(), other.offset());
+        a.0 == -b.0 && a.1 == -b.1
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Ready,
+    Running,
+    Paused,
+    GameOver,
+}
+
+struct SnakeState {
+    phase: Phase,
+    // Head first
+    body: VecDeque<(i32, i32)>,
+    direction: Direction,
+    // Applied on the next step, so two quick turns can't reverse the snake
+    next_direction: Direction,
+    food: (i32, i32),
+    score: u32,
+    high_score: u32,
+    last_step: Option<Instant>,
+    new_game: Button,
+}
+
+static STATE: Mutex<SnakeState> = Mutex::new(SnakeState {
+    phase: Phase::Ready,
+    body: VecDeque::new(),
+    direction: Direction::Right,
+    next_direction: Direction::Right,
+    food: (0, 0),
+    score: 0,
+    high_score: 0,
+    last_step: None,
+    new_game: Button::new("New Game"),
+});
+
+impl SnakeState {
+    fn reset(&mut self) {
+        self.body.clear();
+        for i in 0..START_LENGTH as i32 {
+            self.body.push_back((COLUMNS / 2 - i, ROWS / 2));
+        }
+        self.direction = Direction::Right;
+        self.next_direction = Direction::Right;
+        self.score = 0;
+        self.place_food();
+        self.phase = Phase::Running;
+        self.last_step = Some(Instant::now());
+    }
+    
+    fn place_food(&mut self) {
+        // The board never fills up in practice; give up rather than spin
+        for _ in 0..1000 {
+            let cell = (rand::range(0, COLUMNS as u64) as i32, rand::range(0, ROWS as u64) as i32);
+            if !self.body.contains(&cell) {
+                self.food = cell;
+                return;
+            }
+        }
+    }
+    
+    fn step_interval(&self) -> Duration {
+        START_STEP.saturating_sub(STEP_SPEEDUP * self.score).max(MIN_STEP)
+    }
+    
+    fn turn(&mut self, direction: Direction) {
+        if self.phase == Phase::Running && !direction.is_opposite(self.direction) {
+            self.next_direction = direction;
+        }
+    }
+    
+    fn step(&mut self) {
+        self.direction = self.next_direction;
+        let (dx, dy) = self.direction.offset();
+        let head = self.body[0];
+        let next = (head.0 + dx, head.1 + dy);
+        
+        let eating = next == self.food;
+        if !eating {
+            self.body.pop_back();
+        }
+        let hit_wall = next.0 < 0 || next.0 >= COLUMNS || next.1 < 0 || next.1 >= ROWS;
+        if hit_wall || self.body.contains(&next) {
+            self.phase = Phase::GameOver;
+            self.high_score = self.high_score.max(self.score);
+            return;
+        }
+        self.body.push_front(next);
+        if eating {
+            self.score += 1;
+            self.place_food();
+        }
+    }
+    
+    // Runs the steps that came due since the last frame
+    fn advance(&mut self) {
+        let now = Instant::now();
+        let mut last = self.last_step.unwrap_or(now);
+        let interval = self.step_interval();
+        let mut steps = 0;
+        while self.phase == Phase::Running && now.duration_since(last) >= interval {
+            if steps == MAX_CATCH_UP {
+                last = now;
+                break;
+            }
+            self.step();
+            last = last + interval;
+            steps += 1;
+        }
+        self.last_step = Some(last);
+    }
+    
+    fn toggle_pause(&mut self) {
+        match self.phase {
+            Phase::Running => self.phase = Phase::Paused,
+            Phase::Paused => {
+                self.phase = Phase::Running;
+                // Don't count the time spent paused
+                self.last_step = Some(Instant::now());
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn handle_key(key: Key) {
+    let mut state = STATE.lock();
+    match key {
+        Key::ArrowUp => state.turn(Direction::Up),
+        Key::ArrowDown => state.turn(Direction::Down),
+        Key::ArrowLeft => state.turn(Direction::Left),
+        Key::ArrowRight => state.turn(Direction::Right),
+        Key::Space => state.toggle_pause(),
+        Key::Enter => state.reset(),
+        _ => {}
+    }
+}
+
+// `x` and `y` are relative to the window content, as drawn by `draw`
+pub fn click(x: usize, y: usize, width: usize) {
+    let mut state = STATE.lock();
+    if state.new_game.contains(x, y, width - 100, 6) {
+        state.reset();
+        return;
+    }
+    let (board_x, board_y) = board_origin(width);
+    if state.phase != Phase::Running || x < board_x || y < board_y {
+        return;
+    }
+    // Turn across the current direction, towards the clicked cell
+    let cell = (((x - board_x) / CELL) as i32, ((y - board_y) / CELL) as i32);
+    let head = state.body[0];
+    let direction = match state.direction {
+        Direction::Left | Direction::Right if cell.1 < head.1 => Direction::Up,
+        Direction::Left | Direction::Right if cell.1 > head.1 => Direction::Down,
+        Direction::Up | Direction::Down if cell.0 < head.0 => Direction::Left,
+        Direction::Up | Direction::Down if cell.0 > head.0 => Direction::Right,
+        _ => return,
+    };
+    state.turn(direction);
+}
+
+fn board_origin(width: usize) -> (usize, usize) {
+    ((width - COLUMNS as usize * CELL) / 2, TOOLBAR_HEIGHT + 8)
+}
+
+pub fn draw(graphics: &mut Graphics, x: usize, y: usize, width: usize, _height: usize, focused: bool) {
+    let mut state = STATE.lock();
+    if state.phase == Phase::Running && !focused {
+        state.phase = Phase::Paused;
+    }
+    state.advance();
+    
+    // Score bar
+    graphics.draw_rect(x + 1, y, width - 2, TOOLBAR_HEIGHT, Color::new(236, 236, 236));
+    graphics.draw_text(&format!("Score {}", state.score), x + 12, y + 11, Color::BLACK);
+    graphics.draw_text(&format!("Best {}", state.high_score), x + 100, y + 11, Color::GRAY);
+    state.new_game.draw(graphics, x + width - 100, y + 6);
+    
+    // Board
+    let (board_x, board_y) = board_origin(width);
+    let (board_x, board_y) = (x + board_x, y + board_y);
+    let (board_width, board_height) = (COLUMNS as usize * CELL, ROWS as usize * CELL);
+    graphics.draw_rect(board_x, board_y, board_width, board_height, Color::new(28, 32, 38));
+    graphics.draw_rect_outline(board_x - 1, board_y - 1, board_width + 2, board_height + 2, Color::GRAY);
+    
+    let cell_at = |(cx, cy): (i32, i32)| (board_x + cx as usize * CELL, board_y + cy as usize * CELL);
+    if state.phase != Phase::Ready {
+        let (fx, fy) = cell_at(state.food);
+        graphics.draw_rounded_rect(fx + 2, fy + 2, CELL - 4, CELL - 4, Color::RED);
+        for (i, &segment) in state.body.iter().enumerate() {
+            let (sx, sy) = cell_at(segment);
+            let color = if i == 0 { Color::new(120, 230, 140) } else { Color::GREEN };
+            graphics.draw_rect(sx + 1, sy + 1, CELL - 2, CELL - 2, color);
+        }
+    }
+    
+    let message = match state.phase {
+        Phase::Ready => "Press Enter to play",
+        Phase::Paused => "Paused - press Space",
+        Phase::GameOver => "Game over - press Enter",
+        Phase::Running => return,
+    };
+    let text_x = board_x + (board_width - message.len() * 8) / 2;
+    graphics.draw_text(message, text_x, board_y + board_height / 2 - 4, Color::WHITE);
+}
\ No newline at end of file
@@ -0,0 +1,507 @@
+// src/socket.rs
+// The socket table. UDP sockets are bound to a local port; ICMP sockets
+// take an echo identifier as their port and receive the echo replies and
+// error reports (time exceeded, unreachable) that carry it. Received
+// datagrams wait in a short per-socket queue until read. A listening TCP
+// socket answers SYNs with new connection sockets, which wait in its
+// backlog once established until accepted; `connect` opens them the other
+// way.
+use crate::clock::Instant;
+use crate::net::{self, Ipv4Addr, NetError, PROTOCOL_ICMP, PROTOCOL_TCP, PROTOCOL_UDP, UDP_HEADER};
+use crate::rand;
+use crate::tcp::{self, Connection, Endpoint};
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use crate::sync::Mutex;
+
+// Ports from here up are handed out when binding to port 0
+const FIRST_EPHEMERAL_PORT: u16 = 49152;
+const QUEUE_LIMIT: usize = 64;
+// Connections waiting to be accepted, per listening socket
+const BACKLOG: usize = 8;
+
+pub type Handle = usize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Icmp,
+    Udp,
+    Tcp,
+}
+
+impl Protocol {
+    pub fn name(self) -> &'static str {
+        match self {
+            Protocol::Icmp => "icmp",
+            Protocol::Udp => "udp",
+            Protocol::Tcp => "tcp",
+        }
+    }
+}
+
+// UDP and ICMP sockets are always Bound; TCP ones go through the usual
+// connection states
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    Bound,
+    Listen,
+    SynSent,
+    SynReceived,
+    Established,
+    FinWait1,
+    FinWait2,
+    CloseWait,
+    Closing,
+    LastAck,
+    TimeWait,
+    Closed,
+}
+
+impl State {
+    pub fn name(self) -> &'static str {
+        match self {
+            State::Bound => "BOUND",
+            State::Listen => "LISTEN",
+            State::SynSent => "SYN_SENT",
+            State::SynReceived => "SYN_RCVD",
+            State::Established => "ESTABLISHED",
+            State::FinWait1 => "FIN_WAIT_1",
+            State::FinWait2 => "FIN_WAIT_2",
+            State::CloseWait => "CLOSE_WAIT",
+            State::Closing => "CLOSING",
+            State::LastAck => "LAST_ACK",
+            State::TimeWait => "TIME_WAIT",
+            State::Closed => "CLOSED",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Counters {
+    pub rx_packets: u64,
+    pub rx_bytes: u64,
+    pub tx_packets: u64,
+    pub tx_bytes: u64,
+    // Datagrams thrown away because the queue was full
+    pub dropped: u64,
+}
+
+pub struct Datagram {
+    pub from: Ipv4Addr,
+    pub port: u16,
+    // TTL left when the packet arrived
+    pub ttl: u8,
+    pub data: Vec<u8>,
+}
+
+struct Socket {
+    protocol: Protocol,
+    port: u16,
+    state: State,
+    // TTL for outgoing packets
+    ttl: u8,
+    queue: VecDeque<Datagram>,
+    counters: Counters,
+    connection: Option<Connection>,
+    // For listening sockets: established connections not yet accepted
+    backlog: VecDeque<Handle>,
+    // For connections: the listening socket that answered them, until
+    // they are accepted
+    listener: Option<Handle>,
+    // The application let go; the slot is freed once the connection closes
+    released: bool,
+}
+
+impl Socket {
+    fn new(protocol: Protocol, port: u16, state: State) -> Self {
+        Self {
+            protocol,
+            port,
+            state,
+            ttl: net::DEFAULT_TTL,
+            queue: VecDeque::new(),
+            counters: Counters::default(),
+            connection: None,
+            backlog: VecDeque::new(),
+            listener: None,
+            released: false,
+        }
+    }
+    
+    fn state(&self) -> State {
+        self.connection.as_ref().map_or(self.state, |c| c.state)
+    }
+}
+
+// One row of `netstat`
+pub struct SocketInfo {
+    pub protocol: Protocol,
+    pub local: (Ipv4Addr, u16),
+    pub remote: Option<Endpoint>,
+    pub state: State,
+    // Waiting to be read, and to be sent or acknowledged
+    pub recv_queue: usize,
+    pub send_queue: usize,
+    pub counters: Counters,
+}
+
+static SOCKETS: Mutex<Vec<Option<Socket>>> = Mutex::new(Vec::new());
+
+fn port_in_use(sockets: &[Option<Socket>], protocol: Protocol, port: u16) -> bool {
+    sockets.iter().flatten().any(|s| s.protocol == protocol && s.port == port)
+}
+
+fn insert(sockets: &mut Vec<Option<Socket>>, socket: Socket) -> Handle {
+    match sockets.iter().position(|s| s.is_none()) {
+        Some(handle) => {
+            sockets[handle] = Some(socket);
+            handle
+        }
+        None => {
+            sockets.push(Some(socket));
+            sockets.len() - 1
+        }
+    }
+}
+
+fn bind(protocol: Protocol, port: u16, state: State) -> Result<Handle, NetError> {
+    let mut sockets = SOCKETS.lock();
+    let port = if port == 0 {
+        (FIRST_EPHEMERAL_PORT..=u16::MAX)
+            .find(|&p| !port_in_use(&sockets, protocol, p))
+            .ok_or(NetError::AddressInUse)?
+    } else if port_in_use(&sockets, protocol, port) {
+        return Err(NetError::AddressInUse);
+    } else {
+        port
+    };
+    Ok(insert(&mut sockets, Socket::new(protocol, port, state)))
+}
+
+// Opens a datagram socket on `port`, or on a free ephemeral port if it is 0
+pub fn open(protocol: Protocol, port: u16) -> Result<Handle, NetError> {
+    bind(protocol, port, State::Bound)
+}
+
+// A TCP socket accepting connections on `port`
+pub fn listen(port: u16) -> Result<Handle, NetError> {
+    bind(Protocol::Tcp, port, State::Listen)
+}
+
+// Starts connecting to `remote` from an ephemeral port. The socket is
+// usable at once: data written before the handshake finishes goes out
+// after it. Wait for `state` to leave SynSent to know how it went.
+pub fn connect(remote: Endpoint) -> Result<Handle, NetError> {
+    let local_ip = net::local_ip().ok_or(NetError::NoInterface)?;
+    let handle = bind(Protocol::Tcp, 0, State::SynSent)?;
+    let mut out = Vec::new();
+    with_socket(handle, |s| {
+        s.connection = Some(Connection::connect((local_ip, s.port), remote, rand::u32(), &mut out));
+        s.counters.tx_packets = 1;
+    })?;
+    send_segments(remote.0, out);
+    Ok(handle)
+}
+
+// Sends the segments a connection produced. The table must be unlocked.
+fn send_segments(remote: Ipv4Addr, segments: Vec<Vec<u8>>) {
+    for segment in segments {
+        net::queue_ipv4(remote, PROTOCOL_TCP, segment);
+    }
+    net::flush();
+}
+
+// Closing a connection sends a FIN once its data is out; the socket stays
+// until the peer has closed too. Closing a listener resets the connections
+// nobody accepted.
+pub fn close(handle: Handle) {
+    let mut outgoing = Vec::new();
+    {
+        let mut sockets = SOCKETS.lock();
+        let socket = match sockets.get_mut(handle).and_then(|s| s.as_mut()) {
+            Some(socket) => socket,
+            None => return,
+        };
+        match socket.connection.as_mut() {
+            Some(connection) if !connection.is_closed() => {
+                let mut out = Vec::new();
+                connection.close(&mut out);
+                outgoing.push((connection.remote.0, out));
+                socket.released = true;
+            }
+            _ => sockets[handle] = None,
+        }
+        for slot in sockets.iter_mut() {
+            let orphan = match slot {
+                Some(socket) if socket.listener == Some(handle) => socket,
+                _ => continue,
+            };
+            if let Some(connection) = orphan.connection.as_mut() {
+                let mut out = Vec::new();
+                connection.abort(&mut out);
+                outgoing.push((connection.remote.0, out));
+            }
+            *slot = None;
+        }
+    }
+    for (remote, out) in outgoing {
+        send_segments(remote, out);
+    }
+}
+
+fn with_socket<T>(handle: Handle, f: impl FnOnce(&mut Socket) -> T) -> Result<T, NetError> {
+    SOCKETS.lock().get_mut(handle).and_then(|s| s.as_mut()).map(f).ok_or(NetError::BadSocket)
+}
+
+pub fn local_port(handle: Handle) -> Result<u16, NetError> {
+    with_socket(handle, |s| s.port)
+}
+
+pub fn state(handle: Handle) -> Result<State, NetError> {
+    with_socket(handle, |s| s.state())
+}
+
+pub fn remote(handle: Handle) -> Result<Option<Endpoint>, NetError> {
+    with_socket(handle, |s| s.connection.as_ref().map(|c| c.remote))
+}
+
+pub fn set_ttl(handle: Handle, ttl: u8) -> Result<(), NetError> {
+    with_socket(handle, |s| s.ttl = ttl)
+}
+
+// Sends `data` to `to`. ICMP sockets send `data` as a whole ICMP message
+// and ignore `port`.
+pub fn send_to(handle: Handle, to: Ipv4Addr, port: u16, data: &[u8]) -> Result<(), NetError> {
+    // The table stays unlocked while the stack resolves and sends
+    let (protocol, local_port, ttl) = with_socket(handle, |s| (s.protocol, s.port, s.ttl))?;
+    let result = match protocol {
+        Protocol::Icmp => net::send_ipv4(to, PROTOCOL_ICMP, ttl, data),
+        Protocol::Udp => {
+            let from = net::local_ip().ok_or(NetError::NoInterface)?;
+            let len = UDP_HEADER + data.len();
+            let mut datagram = Vec::with_capacity(len);
+            datagram.extend_from_slice(&local_port.to_be_bytes());
+            datagram.extend_from_slice(&port.to_be_bytes());
+            datagram.extend_from_slice(&(len as u16).to_be_bytes());
+            datagram.extend_from_slice(&[0, 0]);
+            datagram.extend_from_slice(data);
+            let sum = match net::checksum_with(net::pseudo_header_sum(from, to, PROTOCOL_UDP, len), &datagram) {
+                // All zeroes would mean "no checksum"
+                0 => 0xFFFF,
+                sum => sum,
+            };
+            datagram[6..8].copy_from_slice(&sum.to_be_bytes());
+            net::send_ipv4(to, PROTOCOL_UDP, ttl, &datagram)
+        }
+        // Connections use `send`
+        Protocol::Tcp => Err(NetError::BadSocket),
+    };
+    if result.is_ok() {
+        with_socket(handle, |s| {
+            s.counters.tx_packets += 1;
+            s.counters.tx_bytes += data.len() as u64;
+        })?;
+    }
+    result
+}
+
+// The oldest datagram waiting on the socket, if any. Callers waiting for
+// one should call `net::poll` between attempts.
+pub fn recv_from(handle: Handle) -> Result<Option<Datagram>, NetError> {
+    with_socket(handle, |s| s.queue.pop_front())
+}
+
+// The next established connection on a listening socket, if any
+pub fn accept(handle: Handle) -> Result<Option<Handle>, NetError> {
+    let mut sockets = SOCKETS.lock();
+    let listener = sockets.get_mut(handle).and_then(|s| s.as_mut()).ok_or(NetError::BadSocket)?;
+    if listener.state != State::Listen {
+        return Err(NetError::BadSocket);
+    }
+    let backlog: Vec<Handle> = listener.backlog.drain(..).collect();
+    let mut backlog = backlog.into_iter();
+    // Connections that were reset while waiting have been freed
+    for accepted in backlog.by_ref() {
+        if let Some(socket) = sockets[accepted].as_mut().filter(|s| s.listener == Some(handle)) {
+            socket.listener = None;
+            if let Some(listener) = sockets[handle].as_mut() {
+                listener.backlog.extend(backlog);
+            }
+            return Ok(Some(accepted));
+        }
+    }
+    Ok(None)
+}
+
+// Queues as much of `data` on a connection as its send buffer takes
+pub fn send(handle: Handle, data: &[u8]) -> Result<usize, NetError> {
+    let mut out = Vec::new();
+    let (written, remote) = with_socket(handle, |s| {
+        let connection = s.connection.as_mut().ok_or(NetError::BadSocket)?;
+        if connection.is_closed() {
+            return Err(NetError::Closed);
+        }
+        let written = connection.write(data, &mut out);
+        s.counters.tx_packets += out.len() as u64;
+        s.counters.tx_bytes += written as u64;
+        Ok((written, connection.remote.0))
+    })??;
+    send_segments(remote, out);
+    Ok(written)
+}
+
+// Whatever a connection has received. Empty means nothing yet; `Closed`
+// means nothing more will come.
+pub fn recv(handle: Handle) -> Result<Vec<u8>, NetError> {
+    let mut out = Vec::new();
+    let (data, remote) = with_socket(handle, |s| {
+        let connection = s.connection.as_mut().ok_or(NetError::BadSocket)?;
+        let data = connection.read(&mut out);
+        if data.is_empty() && connection.peer_closed() {
+            return Err(NetError::Closed);
+        }
+        Ok((data, connection.remote.0))
+    })??;
+    send_segments(remote, out);
+    Ok(data)
+}
+
+fn deliver(protocol: Protocol, port: u16, datagram: Datagram) -> bool {
+    let mut sockets = SOCKETS.lock();
+    let socket = match sockets.iter_mut().flatten().find(|s| s.protocol == protocol && s.port == port) {
+        Some(socket) => socket,
+        None => return false,
+    };
+    if socket.queue.len() == QUEUE_LIMIT {
+        socket.counters.dropped += 1;
+        return true;
+    }
+    socket.counters.rx_packets += 1;
+    socket.counters.rx_bytes += datagram.data.len() as u64;
+    socket.queue.push_back(datagram);
+    true
+}
+
+pub fn deliver_udp(from: Ipv4Addr, from_port: u16, port: u16, data: &[u8]) -> bool {
+    deliver(Protocol::Udp, port, Datagram { from, port: from_port, ttl: 0, data: data.to_vec() })
+}
+
+// Echo replies and errors go to the socket whose identifier they carry
+pub fn deliver_icmp(from: Ipv4Addr, ttl: u8, message: &[u8]) -> bool {
+    match net::echo_id(message) {
+        Some((ident, _)) => deliver(Protocol::Icmp, ident, Datagram { from, port: 0, ttl, data: message.to_vec() }),
+        None => false,
+    }
+}
+
+// Hands a segment to its connection, or to a listener if it opens one.
+// This runs with the interface locked, so replies are queued, not sent.
+pub fn deliver_tcp(src: Ipv4Addr, dst: Ipv4Addr, data: &[u8]) -> bool {
+    let segment = match tcp::parse(src, dst, data) {
+        Some(segment) => segment,
+        None => return false,
+    };
+    let local = (dst, segment.dst_port);
+    let remote = (src, segment.src_port);
+    let mut out = Vec::new();
+    let mut sockets = SOCKETS.lock();
+    
+    let existing = sockets.iter().position(|s| {
+        s.as_ref().and_then(|s| s.connection.as_ref()).is_some_and(|c| c.local.1 == local.1 && c.remote == remote && !c.is_closed())
+    });
+    let listener = sockets.iter().position(|s| {
+        s.as_ref().is_some_and(|s| s.protocol == Protocol::Tcp && s.state == State::Listen && s.port == local.1)
+    });
+    match (existing, listener) {
+        (Some(handle), _) => {
+            let socket = sockets[handle].as_mut().unwrap();
+            let connection = socket.connection.as_mut().unwrap();
+            let was = connection.state;
+            connection.receive(&segment, &mut out);
+            let established = was == State::SynReceived && connection.state == State::Established;
+            socket.counters.rx_packets += 1;
+            socket.counters.rx_bytes += segment.payload.len() as u64;
+            socket.counters.tx_packets += out.len() as u64;
+            // A finished handshake joins the listener's backlog
+            if let (true, Some(l)) = (established, socket.listener) {
+                if let Some(listener) = sockets[l].as_mut() {
+                    listener.backlog.push_back(handle);
+                }
+            }
+        }
+        (None, Some(l)) if segment.flags & (tcp::SYN | tcp::ACK | tcp::RST) == tcp::SYN => {
+            let waiting = sockets.iter().flatten().filter(|s| s.listener == Some(l)).count();
+            // A full backlog drops the SYN so the peer tries again later
+            if waiting < BACKLOG {
+                let mut socket = Socket::new(Protocol::Tcp, local.1, State::SynReceived);
+                socket.connection = Some(Connection::accept(local, remote, &segment, rand::u32(), &mut out));
+                socket.listener = Some(l);
+                socket.counters.rx_packets = 1;
+                socket.counters.tx_packets = 1;
+                insert(&mut sockets, socket);
+            }
+        }
+        _ => out.extend(tcp::reset_for(local, remote, &segment)),
+    }
+    drop(sockets);
+    for segment in out {
+        net::queue_ipv4(src, PROTOCOL_TCP, segment);
+    }
+    true
+}
+
+// Retransmissions and timeouts. Frees connections that have closed once
+// nothing holds them: released by the application, or never accepted.
+pub fn tick() {
+    let now = Instant::now();
+    let mut outgoing = Vec::new();
+    let mut sockets = SOCKETS.lock();
+    for slot in sockets.iter_mut() {
+        let socket = match slot {
+            Some(socket) => socket,
+            None => continue,
+        };
+        let connection = match socket.connection.as_mut() {
+            Some(connection) => connection,
+            None => continue,
+        };
+        let mut out = Vec::new();
+        connection.tick(now, &mut out);
+        socket.counters.tx_packets += out.len() as u64;
+        let remote = connection.remote.0;
+        if connection.is_closed() && (socket.released || socket.listener.is_some()) {
+            *slot = None;
+        }
+        outgoing.push((remote, out));
+    }
+    drop(sockets);
+    for (remote, out) in outgoing {
+        for segment in out {
+            net::queue_ipv4(remote, PROTOCOL_TCP, segment);
+        }
+    }
+}
+
+pub fn list() -> Vec<SocketInfo> {
+    let local = net::local_ip().unwrap_or(Ipv4Addr::UNSPECIFIED);
+    SOCKETS
+        .lock()
+        .iter()
+        .flatten()
+        .map(|s| {
+            let (recv_queue, send_queue) = match &s.connection {
+                Some(connection) => connection.queued(),
+                None => (s.queue.len(), 0),
+            };
+            SocketInfo {
+                protocol: s.protocol,
+                local: (local, s.port),
+                remote: s.connection.as_ref().map(|c| c.remote),
+                state: s.state(),
+                recv_queue,
+                send_queue,
+                counters: s.counters,
+            }
+        })
+        .collect()
+}
\ No newline at end of file
@@ -1,12 +1,131 @@
 // src/spotlight.rs
+// Cmd+Space search over apps and the home folder. What was opened from
+// here before ranks first: every launch is counted with its time in the
+// com.rustos.spotlight preferences, and a result scores its count weighted
+// by how recently it was last opened. The top result's name is shown
+// greyed after what was typed, and Tab takes it into the query. A query
+// like `remind "stand up" in 25m` has a result that sets the reminder.
+use crate::apps;
 use crate::graphics::{Graphics, Color};
+use crate::preferences;
+use crate::reminders;
+use crate::rtc;
+use crate::users;
+use crate::vfs::{self, FileType};
+use alloc::collections::VecDeque;
+use alloc::format;
 use alloc::string::String;
 use alloc::vec::Vec;
 
 pub struct SpotlightResult {
     pub title: String,
     pub subtitle: String,
-    pub icon: char,
+    pub icon: &'static str,
+    // Set for files, which open instead of launching
+    pub path: Option<String>,
+    // Set for a reminder to set, with its text and delay
+    pub reminder: Option<(String, u64)>,
+}
+
+impl SpotlightResult {
+    // What its launches are remembered by
+    fn key(&self) -> &str {
+        self.path.as_deref().unwrap_or(&self.title)
+    }
+}
+
+const MAX_RESULTS: usize = 4;
+// Files looked at per search, so typing stays quick in a big home folder
+const MAX_FILES_SEARCHED: usize = 200;
+
+const DOMAIN: &str = "com.rustos.spotlight";
+// Launches remembered; the lowest scoring goes to make room
+const MAX_REMEMBERED: usize = 50;
+const DAY: u64 = 24 * 60 * 60;
+
+// How often and when an app or file was last opened from Spotlight, by the
+// app's name or the file's path. Stored as `count,unix time`.
+struct Launch {
+    key: String,
+    count: u64,
+    last: u64,
+}
+
+impl Launch {
+    // Recent launches count for more, fading over a month
+    fn score(&self, now: u64) -> u64 {
+        let weight = match now.saturating_sub(self.last) {
+            age if age < DAY => 100,
+            age if age < 7 * DAY => 70,
+            age if age < 30 * DAY => 50,
+            _ => 30,
+        };
+        self.count * weight
+    }
+}
+
+fn launches() -> Vec<Launch> {
+    preferences::read(DOMAIN)
+        .into_iter()
+        .filter_map(|(key, value)| {
+            let (count, last) = value.split_once(',')?;
+            Some(Launch { key, count: count.parse().ok()?, last: last.parse().ok()? })
+        })
+        .collect()
+}
+
+// Counts a launch of the app or file known by `key`
+fn remember(key: &str) {
+    // Keys with these wouldn't read back
+    if key.contains('=') || key.contains('\n') {
+        return;
+    }
+    let now = rtc::unix_time();
+    let mut launches = launches();
+    let count = launches.iter().find(|launch| launch.key == key).map_or(0, |launch| launch.count);
+    let _ = preferences::set(DOMAIN, key, &format!("{},{}", count + 1, now));
+    launches.retain(|launch| launch.key != key);
+    if launches.len() >= MAX_REMEMBERED {
+        launches.sort_by_key(|launch| launch.score(now));
+        for launch in &launches[..launches.len() + 1 - MAX_REMEMBERED] {
+            let _ = preferences::remove(DOMAIN, &launch.key);
+        }
+    }
+}
+
+// Files and folders in the home folder whose names contain the query,
+// breadth first
+fn search_files(query: &str, limit: usize) -> Vec<SpotlightResult> {
+    let query = query.to_lowercase();
+    let mut results = Vec::new();
+    let mut pending = VecDeque::from([format!("/Users/{}", users::user_name(users::current_uid()))]);
+    let mut searched = 0;
+    while let Some(dir) = pending.pop_front() {
+        for entry in vfs::read_dir(&dir).unwrap_or_default() {
+            searched += 1;
+            if results.len() == limit || searched > MAX_FILES_SEARCHED {
+                return results;
+            }
+            // Dot files stay hidden, as in the Finder
+            if entry.name.starts_with('.') {
+                continue;
+            }
+            let path = vfs::join(&dir, &entry.name);
+            if entry.name.to_lowercase().contains(&query) {
+                results.push(SpotlightResult {
+                    title: entry.name.clone(),
+                    subtitle: dir.clone(),
+                    icon: if entry.file_type == FileType::Directory { "📁" } else { "📄" },
+                    path: Some(path.clone()),
+                    reminder: None,
+                });
+            }
+            if entry.file_type == FileType::Directory {
+                pending.push_back(path);
+            }
+        }
+    }
+    results
 }
 
 pub struct Spotlight {
@@ -40,6 +159,38 @@ impl Spotlight {
         self.update_results();
     }
     
+    // Tab: the top result's name becomes the query
+    pub fn complete(&mut self) {
+        if let Some(result) = self.results.first().filter(|result| result.reminder.is_none()) {
+            self.search_query = result.title.clone();
+            self.update_results();
+        }
+    }
+    
+    // The rest of the top result's name when it starts with the query,
+    // whatever the case
+    pub fn suggestion(&self) -> Option<&str> {
+        if self.search_query.is_empty() {
+            return None;
+        }
+        let title = &self.results.first().filter(|result| result.reminder.is_none())?.title;
+        let mut chars = title.char_indices();
+        for typed in self.search_query.chars() {
+            let (_, ch) = chars.next()?;
+            if !ch.to_lowercase().eq(typed.to_lowercase()) {
+                return None;
+            }
+        }
+        chars.next().map(|(start, _)| &title[start..])
+    }
+    
+    // Counts the selected result as launched, for ranking next time
+    pub fn record_launch(&self) {
+        if let Some(result) = self.results.get(self.selected_index).filter(|result| result.reminder.is_none()) {
+            remember(result.key());
+        }
+    }
+    
     pub fn hide(&mut self) {
         self.is_visible = false;
     }
@@ -54,56 +205,58 @@ impl Spotlight {
         self.update_results();
     }
     
+    // Apps matching the query, then files, as many as the panel has room
+    // for; those launched most and most recently go first
     fn update_results(&mut self) {
         self.results.clear();
-        
-        if self.search_query.is_empty() {
-            self.results.push(SpotlightResult {
-                title: "Terminal".to_string(),
-                subtitle: "Utilities".to_string(),
-                icon: '💻',
-            });
+        for app in apps::search(&self.search_query) {
             self.results.push(SpotlightResult {
-                title: "Finder".to_string(),
-                subtitle: "System".to_string(),
-                icon: '📁',
+                title: String::from(app.name),
+                subtitle: String::from(app.category),
+                icon: app.icon,
+                path: None,
+                reminder: None,
             });
-            self.results.push(SpotlightResult {
-                title: "System Preferences".to_string(),
-                subtitle: "System".to_string(),
-                icon: '⚙️',
+        }
+        if !self.search_query.is_empty() {
+            self.results.extend(search_files(&self.search_query, MAX_RESULTS));
+        }
+        let (launches, now) = (launches(), rtc::unix_time());
+        let score = |result: &SpotlightResult| {
+            launches.iter().find(|launch| launch.key == result.key()).map_or(0, |launch| launch.score(now))
+        };
+        // Stable, so the rest keep apps before files
+        self.results.sort_by_key(|result| core::cmp::Reverse(score(result)));
+        // A reminder goes first, as it's what the query asks for
+        let request = self.search_query.strip_prefix("remind ").and_then(reminders::parse);
+        if let Some((text, delay)) = request {
+            self.results.insert(0, SpotlightResult {
+                title: format!("Remind me: {}", text),
+                subtitle: format!("in {}", reminders::format_delay(delay)),
+                icon: "📅",
+                path: None,
+                reminder: Some((text, delay)),
             });
-        } else {
-            let query_lower = self.search_query.to_lowercase();
-            
-            if "terminal".starts_with(&query_lower) {
-                self.results.push(SpotlightResult {
-                    title: "Terminal".to_string(),
-                    subtitle: "Utilities".to_string(),
-                    icon: '💻',
-                });
-            }
-            
-            if "finder".starts_with(&query_lower) {
-                self.results.push(SpotlightResult {
-                    title: "Finder".to_string(),
-                    subtitle: "System".to_string(),
-                    icon: '📁',
-                });
-            }
-            
-            if "system".starts_with(&query_lower) || "preferences".starts_with(&query_lower) {
-                self.results.push(SpotlightResult {
-                    title: "System Preferences".to_string(),
-                    subtitle: "System".to_string(),
-                    icon: '⚙️',
-                });
-            }
         }
-        
+        self.results.truncate(MAX_RESULTS);
         self.selected_index = 0;
     }
     
+    // The app to launch for the selected result
+    pub fn selected(&self) -> Option<&str> {
+        self.results.get(self.selected_index).map(|result| result.title.as_str())
+    }
+    
+    // The reminder to set for the selected result, if it's one
+    pub fn selected_reminder(&self) -> Option<(String, u64)> {
+        self.results.get(self.selected_index).and_then(|result| result.reminder.clone())
+    }
+    
+    // The file to open for the selected result, if it's a file
+    pub fn selected_path(&self) -> Option<String> {
+        self.results.get(self.selected_index).and_then(|result| result.path.clone())
+    }
+    
     pub fn move_selection(&mut self, direction: i32) {
         if self.results.is_empty() {
             return;
@@ -136,8 +289,11 @@ impl Spotlight {
         graphics.draw_text("🔍", self.x + 30, self.y + 35, Color::GRAY);
         
         graphics.draw_text(&self.search_query, self.x + 60, self.y + 35, Color::BLACK);
+        let cursor_x = self.x + 60 + self.search_query.chars().count() * 8;
+        if let Some(rest) = self.suggestion() {
+            graphics.draw_text(rest, cursor_x, self.y + 35, Color::LIGHT_GRAY);
+        }
         
-        let cursor_x = self.x + 60 + self.search_query.len() * 8;
         graphics.draw_rect(cursor_x, self.y + 32, 2, 16, Color::BLUE);
         
         let result_start_y = self.y + 80;
@@ -148,7 +304,7 @@ impl Spotlight {
                 graphics.draw_rounded_rect(self.x + 10, result_y - 5, self.width - 20, 40, Color::BLUE);
             }
             
-            graphics.draw_text(&result.icon.to_string(), self.x + 25, result_y + 10, Color::BLACK);
+            graphics.draw_text(result.icon, self.x + 25, result_y + 10, Color::BLACK);
             
             let text_color = if i == self.selected_index { Color::WHITE } else { Color::BLACK };
             let subtitle_color = if i == self.selected_index { Color::new(200, 200, 200) } else { Color::GRAY };
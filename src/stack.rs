@@ -0,0 +1,67 @@
+// src/stack.rs
+// Kernel stacks with an unmapped guard page below each one, so running off
+// the end faults instead of silently overwriting whatever sits below. The
+// main stack, the one the kernel boots and runs the desktop on, opens the
+// window: the bootloader maps it there, guard page first, as the
+// `package.metadata.bootloader` settings in cargo.toml ask.
+use crate::memory::{self, PAGE_SIZE};
+use alloc::vec::Vec;
+use crate::sync::Mutex;
+use x86_64::structures::paging::{Page, PageTableFlags};
+use x86_64::VirtAddr;
+
+pub const KERNEL_STACK_BASE: u64 = 0x_6666_0000_0000;
+const KERNEL_STACK_WINDOW: u64 = 0x_0000_4000_0000;
+// kernel-stack-size in cargo.toml
+const MAIN_STACK_PAGES: u64 = 512;
+const MAIN_STACK_TOP: u64 = KERNEL_STACK_BASE + (MAIN_STACK_PAGES + 1) * PAGE_SIZE;
+
+struct KernelStack {
+    name: &'static str,
+    guard: u64,
+    top: u64,
+}
+
+struct Stacks {
+    next: u64,
+    stacks: Vec<KernelStack>,
+}
+
+static STACKS: Mutex<Stacks> = Mutex::new(Stacks { next: MAIN_STACK_TOP, stacks: Vec::new() });
+
+// Maps `pages` pages above a guard page and returns the (exclusive) top
+pub fn allocate(name: &'static str, pages: u64) -> Option<VirtAddr> {
+    let mut state = STACKS.lock();
+    let guard = state.next;
+    let top = guard + (pages + 1) * PAGE_SIZE;
+    if top > KERNEL_STACK_BASE + KERNEL_STACK_WINDOW {
+        return None;
+    }
+    
+    let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::NO_EXECUTE;
+    let first = Page::containing_address(VirtAddr::new(guard + PAGE_SIZE));
+    let last = Page::containing_address(VirtAddr::new(top - 1));
+    for page in Page::range_inclusive(first, last) {
+        let frame = memory::allocate_zeroed_frame()?;
+        memory::map_page(page, frame, flags).ok()?;
+    }
+    
+    state.next = top;
+    state.stacks.push(KernelStack { name, guard, top });
+    Some(VirtAddr::new(top))
+}
+
+// Name of the stack whose guard page contains `addr`. Called from fault
+// handlers, so it never waits on the lock.
+pub fn overflowed_stack(addr: VirtAddr) -> Option<&'static str> {
+    let addr = addr.as_u64();
+    if (KERNEL_STACK_BASE..KERNEL_STACK_BASE + PAGE_SIZE).contains(&addr) {
+        return Some("main");
+    }
+    let state = STACKS.try_lock()?;
+    state
+        .stacks
+        .iter()
+        .find(|s| (s.guard..s.guard + PAGE_SIZE).contains(&addr))
+        .map(|s| s.name)
+}
\ No newline at end of file
@@ -0,0 +1,92 @@
+// src/stream.rs
+// A TCP connection, optionally wrapped in TLS, for protocol clients that
+// talk over either the same way. Data written while the connection is
+// still being set up waits until it's ready; call `exchange` after
+// `net::poll` to send what's queued and collect what has arrived.
+use crate::net::{Ipv4Addr, NetError};
+use crate::socket::{self, Handle, State};
+use crate::tls::{TlsError, TlsStream};
+use alloc::vec::Vec;
+
+pub enum Stream {
+    Plain { socket: Handle, pending: Vec<u8>, eof: bool },
+    Tls(TlsStream),
+}
+
+impl Stream {
+    // Starts connecting. With `tls_host`, a TLS handshake follows and the
+    // server's certificate has to be for that name.
+    pub fn connect(addr: Ipv4Addr, port: u16, tls_host: Option<&str>) -> Result<Self, TlsError> {
+        let socket = socket::connect((addr, port))?;
+        match tls_host {
+            Some(host) => TlsStream::new(socket, host).map(Stream::Tls).map_err(|err| {
+                socket::close(socket);
+                err
+            }),
+            None => Ok(Stream::Plain { socket, pending: Vec::new(), eof: false }),
+        }
+    }
+    
+    pub fn socket(&self) -> Handle {
+        match self {
+            Stream::Plain { socket, .. } => *socket,
+            Stream::Tls(stream) => stream.socket(),
+        }
+    }
+    
+    // Whether the TCP handshake is done; an error if it failed
+    pub fn is_connected(&self) -> Result<bool, NetError> {
+        match socket::state(self.socket())? {
+            State::SynSent => Ok(false),
+            // Reset, or the SYN was never answered
+            State::Closed => Err(NetError::Refused),
+            _ => Ok(true),
+        }
+    }
+    
+    pub fn write(&mut self, data: &[u8]) {
+        match self {
+            Stream::Plain { pending, .. } => pending.extend_from_slice(data),
+            Stream::Tls(stream) => stream.write(data),
+        }
+    }
+    
+    // Sends what is pending and returns what has arrived
+    pub fn exchange(&mut self) -> Result<Vec<u8>, TlsError> {
+        match self {
+            Stream::Plain { socket, pending, eof } => {
+                if !pending.is_empty() {
+                    let sent = socket::send(*socket, pending)?;
+                    pending.drain(..sent);
+                }
+                match socket::recv(*socket) {
+                    Ok(data) => Ok(data),
+                    Err(NetError::Closed) => {
+                        *eof = true;
+                        Ok(Vec::new())
+                    }
+                    Err(err) => Err(err.into()),
+                }
+            }
+            Stream::Tls(stream) => {
+                stream.poll()?;
+                Ok(stream.read())
+            }
+        }
+    }
+    
+    // The other end has closed and everything it sent has been read
+    pub fn at_end(&self) -> bool {
+        match self {
+            Stream::Plain { eof, .. } => *eof,
+            Stream::Tls(stream) => stream.is_closed(),
+        }
+    }
+    
+    pub fn close(self) {
+        match self {
+            Stream::Plain { socket, .. } => socket::close(socket),
+            Stream::Tls(stream) => stream.close(),
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,307 @@
+// src/styled_text.rs
+// Rich text for document views: paragraphs of styled spans, word-wrapped
+// into fixed-height lines for the 8x8 font. Bold is drawn twice one pixel
+// apart and italic is slanted, since the font has neither face. Formats
+// like Markdown parse into a StyledText; views lay it out for their width
+// and draw the lines they have room for.
+use crate::graphics::{Graphics, Color};
+use crate::widgets::{self, ScrollView, SearchBar, TextMatch};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+const CHAR_WIDTH: usize = 8;
+pub const LINE_HEIGHT: usize = 14;
+const INDENT: usize = 16;
+const CODE_BACKGROUND: Color = Color::new(238, 238, 240);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Style {
+    pub bold: bool,
+    pub italic: bool,
+    pub code: bool,
+    pub link: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Span {
+    pub text: String,
+    pub style: Style,
+}
+
+impl Span {
+    pub fn new(text: &str, style: Style) -> Self {
+        Self { text: String::from(text), style }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Block {
+    Body,
+    // Level 1 to 6
+    Heading(u8),
+    // Preformatted: no wrapping at spaces, shaded background
+    Code,
+    Quote,
+    Rule,
+}
+
+#[derive(Debug, Clone)]
+pub struct Paragraph {
+    pub block: Block,
+    pub spans: Vec<Span>,
+    // Nesting depth of lists and quotes
+    pub depth: usize,
+    // List marker drawn in the indent, like "•" or "2."
+    pub marker: Option<String>,
+}
+
+impl Paragraph {
+    pub fn new(block: Block, spans: Vec<Span>) -> Self {
+        Self { block, spans, depth: 0, marker: None }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct StyledText {
+    pub paragraphs: Vec<Paragraph>,
+}
+
+// A piece of text at a column of a laid-out line
+#[derive(Debug, Clone)]
+pub struct Run {
+    pub x: usize,
+    pub text: String,
+    pub style: Style,
+}
+
+#[derive(Debug, Clone)]
+pub struct Line {
+    pub block: Block,
+    pub runs: Vec<Run>,
+}
+
+impl Line {
+    fn new(block: Block) -> Self {
+        Self { block, runs: Vec::new() }
+    }
+    
+    // The line as it reads, a character per column, for searching
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        let mut column = 0;
+        for run in self.runs.iter() {
+            while column < run.x / CHAR_WIDTH {
+                text.push(' ');
+                column += 1;
+            }
+            text.push_str(&run.text);
+            column += run.text.chars().count();
+        }
+        text
+    }
+}
+
+fn color_for(block: Block, style: Style) -> Color {
+    match (block, style.link) {
+        (_, true) => Color::BLUE,
+        (Block::Heading(_), _) => Color::new(20, 20, 20),
+        (Block::Quote, _) => Color::GRAY,
+        _ => Color::BLACK,
+    }
+}
+
+// Headings are bold and quotes italic throughout
+fn effective_style(block: Block, mut style: Style) -> Style {
+    match block {
+        Block::Heading(_) => style.bold = true,
+        Block::Quote => style.italic = true,
+        _ => {}
+    }
+    style
+}
+
+// Splits text into words, each keeping the spaces that follow it
+fn words(text: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = 0;
+    let mut in_space = false;
+    for (i, ch) in text.char_indices() {
+        if ch == ' ' {
+            in_space = true;
+        } else if in_space {
+            words.push(&text[start..i]);
+            start = i;
+            in_space = false;
+        }
+    }
+    if start < text.len() {
+        words.push(&text[start..]);
+    }
+    words
+}
+
+impl StyledText {
+    // Wraps every paragraph to `width` pixels, with blank lines between
+    // paragraphs and around headings
+    pub fn layout(&self, width: usize) -> Vec<Line> {
+        let mut lines = Vec::new();
+        for (i, paragraph) in self.paragraphs.iter().enumerate() {
+            let spaced = match (i.checked_sub(1).map(|p| &self.paragraphs[p]), paragraph.block) {
+                (None, _) => false,
+                // Consecutive list items and code lines sit together
+                (Some(previous), _) if previous.marker.is_some() && paragraph.marker.is_some() => false,
+                (Some(previous), Block::Code) => previous.block != Block::Code,
+                _ => true,
+            };
+            if spaced {
+                lines.push(Line::new(Block::Body));
+            }
+            layout_paragraph(paragraph, width, &mut lines);
+        }
+        lines
+    }
+}
+
+fn layout_paragraph(paragraph: &Paragraph, width: usize, lines: &mut Vec<Line>) {
+    let block = paragraph.block;
+    if block == Block::Rule {
+        lines.push(Line::new(Block::Rule));
+        return;
+    }
+    let indent = paragraph.depth * INDENT + if paragraph.marker.is_some() { INDENT } else { 0 };
+    let columns = (width.saturating_sub(indent) / CHAR_WIDTH).max(1);
+    
+    let mut line = Line::new(block);
+    if let Some(marker) = &paragraph.marker {
+        let x = (indent - INDENT) + INDENT.saturating_sub(marker.chars().count() * CHAR_WIDTH + 4);
+        line.runs.push(Run { x, text: marker.clone(), style: Style::default() });
+    }
+    let mut column = 0;
+    for span in paragraph.spans.iter() {
+        let style = effective_style(block, span.style);
+        // Code blocks break anywhere; other text breaks between words
+        let pieces: Vec<&str> = if block == Block::Code { Vec::from([span.text.as_str()]) } else { words(&span.text) };
+        for piece in pieces {
+            let mut rest = piece;
+            while !rest.is_empty() {
+                let len = rest.trim_end().chars().count();
+                if column > 0 && column + len > columns && block != Block::Code {
+                    lines.push(core::mem::replace(&mut line, Line::new(block)));
+                    column = 0;
+                }
+                let fits = columns - column;
+                let split = rest.char_indices().nth(fits).map_or(rest.len(), |(i, _)| i);
+                let (taken, remainder) = rest.split_at(split);
+                let text = if column == 0 && block != Block::Code { taken.trim_start() } else { taken };
+                if !text.is_empty() {
+                    line.runs.push(Run { x: indent + column * CHAR_WIDTH, text: String::from(text), style });
+                    column += text.chars().count();
+                }
+                // Spaces that don't fit just end the line
+                rest = if block != Block::Code && remainder.trim().is_empty() { "" } else { remainder };
+                if !rest.is_empty() {
+                    lines.push(core::mem::replace(&mut line, Line::new(block)));
+                    column = 0;
+                }
+            }
+        }
+    }
+    lines.push(line);
+}
+
+// A scrolling view over a StyledText, laid out for its current width
+pub struct TextView {
+    pub text: StyledText,
+    pub scroll: ScrollView,
+    // Found by the last `find`, with the current one's index
+    matches: Vec<TextMatch>,
+    current_match: Option<usize>,
+}
+
+impl TextView {
+    pub fn new(text: StyledText) -> Self {
+        Self { text, scroll: ScrollView::new(LINE_HEIGHT), matches: Vec::new(), current_match: None }
+    }
+    
+    // Text is inset from the view's edges; this is the width it wraps to
+    fn text_width(width: usize) -> usize {
+        width.saturating_sub(28)
+    }
+    
+    pub fn scroll_by(&mut self, rows: isize, width: usize, height: usize) {
+        let total = self.text.layout(Self::text_width(width)).len();
+        self.scroll.scroll_by(rows, total, height);
+    }
+    
+    // Finds the search bar's query for the next `draw` to highlight, and
+    // scrolls to the current match when it changed
+    pub fn find(&mut self, search: &mut SearchBar, width: usize, height: usize) {
+        let lines = self.text.layout(Self::text_width(width));
+        let text: Vec<String> = lines.iter().map(Line::text).collect();
+        self.matches = widgets::find_matches(&text, search.query());
+        if let Some(index) = search.update(self.matches.len()) {
+            let line = self.matches[index].line;
+            let page = self.scroll.rows_per_page(height);
+            if line < self.scroll.offset || line >= self.scroll.offset + page {
+                self.scroll.offset = line.saturating_sub(page / 2);
+                self.scroll.follow_end = false;
+            }
+        }
+        self.current_match = search.current();
+    }
+    
+    pub fn draw(&mut self, graphics: &mut Graphics, x: usize, y: usize, width: usize, height: usize) {
+        let text_width = Self::text_width(width);
+        let lines = self.text.layout(text_width);
+        let rows = self.scroll.visible_rows(lines.len(), height);
+        let first = rows.start;
+        for (row, line) in lines[rows].iter().enumerate() {
+            let highlights: Vec<(Range<usize>, bool)> = self.matches
+                .iter()
+                .enumerate()
+                .filter(|(_, found)| found.line == first + row)
+                .map(|(i, found)| (found.columns.clone(), Some(i) == self.current_match))
+                .collect();
+            draw_line(graphics, line, x + 12, y + row * LINE_HEIGHT, text_width, &highlights);
+        }
+        self.scroll.draw_scroller(graphics, x + width - 10, y, height, lines.len());
+    }
+}
+
+// Draws one laid-out line with its top-left at (x, y), marking search
+// matches by column, the current one flagged
+pub fn draw_line(graphics: &mut Graphics, line: &Line, x: usize, y: usize, width: usize, highlights: &[(Range<usize>, bool)]) {
+    match line.block {
+        Block::Code => graphics.draw_rect(x, y, width, LINE_HEIGHT, CODE_BACKGROUND),
+        Block::Rule => {
+            graphics.draw_rect(x, y + LINE_HEIGHT / 2, width, 1, Color::new(210, 210, 210));
+            return;
+        }
+        Block::Heading(1) => graphics.draw_rect(x, y + LINE_HEIGHT - 2, width, 1, Color::new(220, 220, 220)),
+        _ => {}
+    }
+    for run in line.runs.iter().filter(|run| run.style.code && line.block != Block::Code) {
+        let len = run.text.trim_end().chars().count();
+        graphics.draw_rect(x + run.x, y + 1, len * CHAR_WIDTH, LINE_HEIGHT - 2, CODE_BACKGROUND);
+    }
+    for (columns, current) in highlights.iter() {
+        widgets::draw_match(graphics, x + columns.start * CHAR_WIDTH, y + 3, columns.len(), *current);
+    }
+    for run in line.runs.iter() {
+        let (run_x, text_y) = (x + run.x, y + 3);
+        let color = color_for(line.block, run.style);
+        if run.style.italic {
+            graphics.draw_text_italic(&run.text, run_x, text_y, color);
+        } else {
+            graphics.draw_text(&run.text, run_x, text_y, color);
+        }
+        if run.style.bold {
+            graphics.draw_text(&run.text, run_x + 1, text_y, color);
+        }
+        if run.style.link {
+            let len = run.text.trim_end().chars().count();
+            graphics.draw_rect(run_x, text_y + 9, len * CHAR_WIDTH, 1, color);
+        }
+    }
+}
\ No newline at end of file
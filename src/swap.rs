@@ -0,0 +1,186 @@
+// src/swap.rs
+// Swap space and memory pressure. The swap file is made once, in one run
+// of clusters, on the first writable FAT32 volume; its pages are read and
+// written on the disk itself, past the block cache, so paging out never
+// needs the heap it's freeing. Pressure comes from the heap's free share:
+// when it rises the desktop drops cached blocks and pages out what hidden
+// windows keep, and the menu bar shows what happened for a few seconds.
+use crate::allocator;
+use crate::block::{self, SharedBlockDevice, SECTOR_SIZE};
+use crate::clock::Instant;
+use crate::fat32::Fat32;
+use crate::graphics::{Color, Graphics};
+use crate::partition;
+use crate::vfs::{self, VfsError};
+use crate::{kinfo, kwarn};
+use alloc::format;
+use alloc::string::String;
+use core::time::Duration;
+use crate::sync::Mutex;
+
+const SWAP_FILE: &str = "SWAPFILE";
+const PAGE_SIZE: usize = 4096;
+// 4 MiB
+const PAGES: usize = 1024;
+const SECTORS_PER_PAGE: u64 = (PAGE_SIZE / SECTOR_SIZE) as u64;
+// Free share of the heap, in percent, below which pressure rises
+const WARNING_FREE: usize = 25;
+const CRITICAL_FREE: usize = 10;
+// How long an event stays in the menu bar
+const EVENT_SHOWN: Duration = Duration::from_secs(4);
+
+struct SwapFile {
+    // The whole disk, uncached
+    device: SharedBlockDevice,
+    first_sector: u64,
+    used: [bool; PAGES],
+}
+
+static SWAP: Mutex<Option<SwapFile>> = Mutex::new(None);
+static EVENT: Mutex<Option<(String, Instant)>> = Mutex::new(None);
+
+// Makes or finds the swap file; without a writable FAT32 volume there's
+// no swap and only cached blocks are reclaimed
+pub fn init() {
+    for volume in partition::volumes() {
+        if vfs::probe(&volume.device) != Some("fat32") || volume.device.lock().is_read_only() {
+            continue;
+        }
+        let disk = match block::get(volume.disk) {
+            Some(disk) => disk,
+            None => continue,
+        };
+        let offset = Fat32::mount(volume.device.clone()).and_then(|mut fs| fs.preallocate(SWAP_FILE, (PAGES * PAGE_SIZE) as u64));
+        match offset {
+            Ok(offset) => {
+                let start = volume.partition.as_ref().map_or(0, |partition| partition.start);
+                *SWAP.lock() = Some(SwapFile {
+                    device: disk,
+                    first_sector: start + offset / SECTOR_SIZE as u64,
+                    used: [false; PAGES],
+                });
+                kinfo!("swap: {} KiB on {}", PAGES * PAGE_SIZE / 1024, volume.name);
+                return;
+            }
+            Err(err) => kwarn!("swap: no swap file on {}: {:?}", volume.name, err),
+        }
+    }
+}
+
+// Pages of the swap file holding something paged out; they're free again
+// once it's dropped
+#[derive(Debug)]
+pub struct Slot {
+    page: usize,
+    pages: usize,
+    len: usize,
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        if let Some(swap) = SWAP.lock().as_mut() {
+            swap.used[self.page..self.page + self.pages].fill(false);
+        }
+    }
+}
+
+// Writes `len` bytes of `data` to free pages, a page at a time
+pub fn page_out(len: usize, mut data: impl Iterator<Item = u8>) -> Result<Slot, VfsError> {
+    let mut swap = SWAP.lock();
+    let swap = swap.as_mut().ok_or(VfsError::NotFound)?;
+    let pages = len.div_ceil(PAGE_SIZE).max(1);
+    let page = (0..=PAGES.saturating_sub(pages))
+        .find(|&page| swap.used[page..page + pages].iter().all(|used| !used))
+        .ok_or(VfsError::NoSpace)?;
+    
+    let mut buf = [0u8; PAGE_SIZE];
+    let mut device = swap.device.lock();
+    for i in 0..pages {
+        buf.iter_mut().for_each(|byte| *byte = data.next().unwrap_or(0));
+        device.write_sectors(swap.first_sector + (page + i) as u64 * SECTORS_PER_PAGE, &buf)?;
+    }
+    device.flush()?;
+    drop(device);
+    swap.used[page..page + pages].fill(true);
+    Ok(Slot { page, pages, len })
+}
+
+// Reads what `slot` holds back, handing it to `f` a page at a time
+pub fn page_in(slot: &Slot, mut f: impl FnMut(&[u8])) -> Result<(), VfsError> {
+    let swap = SWAP.lock();
+    let swap = swap.as_ref().ok_or(VfsError::NotFound)?;
+    let mut buf = [0u8; PAGE_SIZE];
+    let mut device = swap.device.lock();
+    for i in 0..slot.pages {
+        device.read_sectors(swap.first_sector + (slot.page + i) as u64 * SECTORS_PER_PAGE, &mut buf)?;
+        let left = slot.len - i * PAGE_SIZE;
+        f(&buf[..left.min(PAGE_SIZE)]);
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Pressure {
+    Normal,
+    Warning,
+    Critical,
+}
+
+impl Pressure {
+    pub fn name(self) -> &'static str {
+        match self {
+            Pressure::Normal => "Normal",
+            Pressure::Warning => "Warning",
+            Pressure::Critical => "Critical",
+        }
+    }
+    
+    fn color(self) -> Color {
+        match self {
+            Pressure::Normal => Color::GREEN,
+            Pressure::Warning => Color::YELLOW,
+            Pressure::Critical => Color::RED,
+        }
+    }
+}
+
+pub fn pressure() -> Pressure {
+    let usage = allocator::usage();
+    let free = usage.free * 100 / usage.size.max(1);
+    if free < CRITICAL_FREE {
+        Pressure::Critical
+    } else if free < WARNING_FREE {
+        Pressure::Warning
+    } else {
+        Pressure::Normal
+    }
+}
+
+// Logs a memory event and shows it in the menu bar for a while
+pub fn record(event: String) {
+    kinfo!("swap: {}", event);
+    *EVENT.lock() = Some((event, Instant::now()));
+}
+
+// What the menu bar shows: the last event while it's recent, else the
+// pressure while it's raised
+pub fn indicator() -> Option<(String, Color)> {
+    let pressure = pressure();
+    if let Some((event, at)) = EVENT.lock().as_ref() {
+        if at.elapsed() < EVENT_SHOWN {
+            return Some((event.clone(), pressure.color()));
+        }
+    }
+    match pressure {
+        Pressure::Normal => None,
+        pressure => Some((format!("Mem {}", pressure.name()), pressure.color())),
+    }
+}
+
+// A dot in the pressure's color and the text, ending at `right`
+pub fn draw_indicator(graphics: &mut Graphics, right: usize, y: usize, indicator: &(String, Color)) {
+    let (text, color) = indicator;
+    let x = right.saturating_sub(text.chars().count() * 8 + 14);
+    graphics.draw_rounded_rect(x, y, 8, 8, *color);
+    graphics.draw_text(text, x + 14, y, *color);
+}
\ No newline at end of file
@@ -0,0 +1,88 @@
+// src/symbols.rs
+// Function names for kernel addresses, for backtraces and the CPU
+// profiler. `make bootimage` lists the symbols of a first build with nm
+// and builds again with the listing, which build.rs packs into space the
+// kernel always reserves, so embedding it moves nothing. A kernel built
+// any other way has an empty map and shows bare addresses. Lookups don't
+// allocate, so the panic screen can use them.
+use core::fmt;
+
+// The same as in build.rs
+const CAPACITY: usize = 1 << 20;
+const HEADER: usize = 12;
+const ENTRY: usize = 16;
+
+static MAP: [u8; CAPACITY] = *include_bytes!(concat!(env!("OUT_DIR"), "/symbols.bin"));
+
+#[derive(Debug, Clone, Copy)]
+pub struct Symbol {
+    pub name: &'static str,
+    // From the start of the function
+    pub offset: usize,
+}
+
+fn u32_at(offset: usize) -> usize {
+    u32::from_le_bytes([MAP[offset], MAP[offset + 1], MAP[offset + 2], MAP[offset + 3]]) as usize
+}
+
+fn u64_at(offset: usize) -> usize {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&MAP[offset..offset + 8]);
+    u64::from_le_bytes(bytes) as usize
+}
+
+// Symbols in the map; 0 when none was embedded
+pub fn count() -> usize {
+    if &MAP[..4] == b"RSYM" { u32_at(4) } else { 0 }
+}
+
+fn names_start() -> usize {
+    HEADER + count() * ENTRY
+}
+
+fn entry(index: usize) -> (usize, usize, usize) {
+    let at = HEADER + index * ENTRY;
+    (u64_at(at), u32_at(at + 8), u32_at(at + 12))
+}
+
+fn name(index: usize) -> &'static str {
+    let start = entry(index).2;
+    let end = if index + 1 < count() { entry(index + 1).2 } else { u32_at(8) };
+    core::str::from_utf8(&MAP[names_start() + start..names_start() + end]).unwrap_or("?")
+}
+
+// The function `address` is in. An address past the end of the nearest
+// function below it, when its size is known, is in none.
+pub fn resolve(address: usize) -> Option<Symbol> {
+    let count = count();
+    // First symbol above the address
+    let (mut low, mut high) = (0, count);
+    while low < high {
+        let middle = (low + high) / 2;
+        if entry(middle).0 <= address {
+            low = middle + 1;
+        } else {
+            high = middle;
+        }
+    }
+    let index = low.checked_sub(1)?;
+    let (start, size, _) = entry(index);
+    let offset = address - start;
+    if size != 0 && offset >= size {
+        return None;
+    }
+    Some(Symbol { name: name(index), offset })
+}
+
+// Shows as "name+0x1f", or as the bare address when it can't be resolved
+#[derive(Debug, Clone, Copy)]
+pub struct Address(pub usize);
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match resolve(self.0) {
+            Some(symbol) => write!(f, "{}+{:#x}", symbol.name, symbol.offset),
+            None => write!(f, "{:#018x}", self.0),
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,69 @@
+// src/sync.rs
+// The kernel's spin lock: spin's Mutex, counting the guards held across the
+// whole kernel. Crash isolation and the watchdog abandon the desktop loop's
+// stack without dropping anything on it, so a lock taken there would stay
+// locked for good; they only do it while no guard is held. What the
+// abandoned frames had allocated on the heap is leaked either way.
+use core::mem::ManuallyDrop;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+// Guards held, and locks being waited for
+static HELD: AtomicUsize = AtomicUsize::new(0);
+
+pub struct Mutex<T> {
+    inner: spin::Mutex<T>,
+}
+
+pub struct MutexGuard<'a, T> {
+    guard: ManuallyDrop<spin::MutexGuard<'a, T>>,
+}
+
+impl<T> Mutex<T> {
+    pub const fn new(value: T) -> Self {
+        Self { inner: spin::Mutex::new(value) }
+    }
+
+    // Counted before it's taken, so there's no moment the lock is held and
+    // the count doesn't show it
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        HELD.fetch_add(1, Ordering::Acquire);
+        MutexGuard { guard: ManuallyDrop::new(self.inner.lock()) }
+    }
+
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        HELD.fetch_add(1, Ordering::Acquire);
+        let guard = self.inner.try_lock();
+        if guard.is_none() {
+            HELD.fetch_sub(1, Ordering::Release);
+        }
+        guard.map(|guard| MutexGuard { guard: ManuallyDrop::new(guard) })
+    }
+}
+
+impl<T> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<T> Drop for MutexGuard<'_, T> {
+    // Uncounted only once the lock is free
+    fn drop(&mut self) {
+        unsafe { ManuallyDrop::drop(&mut self.guard) };
+        HELD.fetch_sub(1, Ordering::Release);
+    }
+}
+
+// Whether any code holds, or waits for, a lock right now
+pub fn locks_held() -> bool {
+    HELD.load(Ordering::Acquire) > 0
+}
@@ -0,0 +1,189 @@
+// src/system_config.rs
+// The configuration files under /etc, for settings that are quicker to
+// type than to click: /etc/desktop.conf for the appearance, the dock and
+// hot corners, and /etc/network.conf for the network's addresses. Both are
+// read at startup and again whenever they change, so an edit takes effect
+// as soon as it's saved. A file that doesn't parse is logged and changes
+// nothing; a setting left out keeps its default.
+//
+//     # /etc/desktop.conf
+//     [appearance]
+//     theme = "dark"                 # or "light"
+//     [dock]
+//     autohide = true
+//     [hot-corners]
+//     top-left = "mission-control"   # spotlight, do-not-disturb or none
+//
+//     # /etc/network.conf
+//     [ipv4]
+//     address = 10.0.2.15
+//     netmask = 255.255.255.0
+//     gateway = 10.0.2.2
+//     dns = 10.0.2.3
+use crate::appearance;
+use crate::bootargs::Theme;
+use crate::config::{self, Config};
+use crate::graphics::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::net::{self, Ipv4Addr};
+use crate::vfs::{self, VfsError, Watch};
+use crate::{kinfo, kwarn};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+use crate::sync::Mutex;
+
+pub const DESKTOP_CONF: &str = "/etc/desktop.conf";
+pub const NETWORK_CONF: &str = "/etc/network.conf";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+const CORNERS: [(Corner, &str); 4] = [
+    (Corner::TopLeft, "top-left"),
+    (Corner::TopRight, "top-right"),
+    (Corner::BottomLeft, "bottom-left"),
+    (Corner::BottomRight, "bottom-right"),
+];
+
+// What moving the pointer into a corner does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CornerAction {
+    MissionControl,
+    Spotlight,
+    DoNotDisturb,
+}
+
+impl CornerAction {
+    fn parse(name: &str) -> Option<Option<CornerAction>> {
+        match name {
+            "mission-control" => Some(Some(CornerAction::MissionControl)),
+            "spotlight" => Some(Some(CornerAction::Spotlight)),
+            "do-not-disturb" => Some(Some(CornerAction::DoNotDisturb)),
+            "none" => Some(None),
+            _ => None,
+        }
+    }
+}
+
+static DOCK_AUTOHIDE: AtomicBool = AtomicBool::new(false);
+// Indexed like CORNERS
+static HOT_CORNERS: Mutex<[Option<CornerAction>; 4]> = Mutex::new([None; 4]);
+static WATCHES: Mutex<Option<(Watch, Watch)>> = Mutex::new(None);
+
+// Whether the dock stays out of sight until the pointer reaches the bottom
+// of the screen
+pub fn dock_autohide() -> bool {
+    DOCK_AUTOHIDE.load(Ordering::Relaxed)
+}
+
+// The corner the point is in, and its action if it has one
+pub fn hot_corner(x: usize, y: usize) -> Option<(Corner, Option<CornerAction>)> {
+    let corner = match (x, y) {
+        (0, 0) => Corner::TopLeft,
+        (x, 0) if x == SCREEN_WIDTH - 1 => Corner::TopRight,
+        (0, y) if y == SCREEN_HEIGHT - 1 => Corner::BottomLeft,
+        (x, y) if x == SCREEN_WIDTH - 1 && y == SCREEN_HEIGHT - 1 => Corner::BottomRight,
+        _ => return None,
+    };
+    let index = CORNERS.iter().position(|&(candidate, _)| candidate == corner)?;
+    Some((corner, HOT_CORNERS.lock()[index]))
+}
+
+// A missing file is an empty one
+fn read(path: &str) -> Option<Config> {
+    let data = match vfs::read_file(path) {
+        Ok(data) => data,
+        Err(VfsError::NotFound) => Vec::new(),
+        Err(err) => {
+            kwarn!("config: cannot read {}: {:?}", path, err);
+            return None;
+        }
+    };
+    match config::parse(&String::from_utf8_lossy(&data)) {
+        Ok(config) => Some(config),
+        Err(err) => {
+            kwarn!("config: {} line {}: {}", path, err.line, err.message);
+            None
+        }
+    }
+}
+
+fn apply_desktop(config: &Config) {
+    match config.get_str("appearance", "theme") {
+        Some("light") => appearance::set_theme_for_boot(Theme::Light),
+        Some("dark") => appearance::set_theme_for_boot(Theme::Dark),
+        Some(other) => kwarn!("config: unknown theme {}", other),
+        None => {}
+    }
+    DOCK_AUTOHIDE.store(config.get_bool("dock", "autohide").unwrap_or(false), Ordering::Relaxed);
+    let mut corners = HOT_CORNERS.lock();
+    for (i, (_, key)) in CORNERS.iter().enumerate() {
+        corners[i] = match config.get_str("hot-corners", key).map(|name| (name, CornerAction::parse(name))) {
+            Some((_, Some(action))) => action,
+            Some((name, None)) => {
+                kwarn!("config: unknown hot corner action {}", name);
+                None
+            }
+            None => None,
+        };
+    }
+}
+
+fn apply_network(config: &Config) {
+    let address = |key: &str, default: Ipv4Addr| match config.get_str("ipv4", key) {
+        Some(text) => Ipv4Addr::parse(text).unwrap_or_else(|| {
+            kwarn!("config: {} is not an IPv4 address", text);
+            default
+        }),
+        None => default,
+    };
+    let defaults = net::DEFAULT_CONFIG;
+    net::set_config(net::Config {
+        ip: address("address", defaults.ip),
+        netmask: address("netmask", defaults.netmask),
+        gateway: address("gateway", defaults.gateway),
+        name_server: address("dns", defaults.name_server),
+    });
+}
+
+// Reads both files and starts watching them
+pub fn load() {
+    if let Some(config) = read(DESKTOP_CONF) {
+        apply_desktop(&config);
+    }
+    if let Some(config) = read(NETWORK_CONF) {
+        apply_network(&config);
+    }
+    match (vfs::watch(DESKTOP_CONF), vfs::watch(NETWORK_CONF)) {
+        (Ok(desktop), Ok(network)) => *WATCHES.lock() = Some((desktop, network)),
+        (Err(err), _) | (_, Err(err)) => kwarn!("config: cannot watch /etc: {:?}", err),
+    }
+}
+
+// Reads again whichever file changed. True when the desktop's did.
+pub fn poll() -> bool {
+    let mut watches = WATCHES.lock();
+    let (desktop, network) = match watches.as_mut() {
+        Some(watches) => watches,
+        None => return false,
+    };
+    if network.changed() {
+        kinfo!("config: {} changed", NETWORK_CONF);
+        if let Some(config) = read(NETWORK_CONF) {
+            apply_network(&config);
+        }
+    }
+    if !desktop.changed() {
+        return false;
+    }
+    kinfo!("config: {} changed", DESKTOP_CONF);
+    if let Some(config) = read(DESKTOP_CONF) {
+        apply_desktop(&config);
+    }
+    true
+}
@@ -0,0 +1,69 @@
+// src/system_info.rs
+// What the machine the kernel is running on looks like, gathered from the
+// hardware and the drivers rather than assumed. Shown in the About dialog.
+use crate::block::{self, SECTOR_SIZE};
+use crate::graphics;
+use crate::{allocator, cpu, memory, pci};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+pub const KERNEL_VERSION: &str = env!("CARGO_PKG_VERSION");
+pub const BUILD_DATE: &str = env!("BUILD_DATE");
+
+pub struct SystemInfo {
+    pub processor: String,
+    pub memory: String,
+    pub heap: String,
+    pub display: String,
+    // One entry per block device
+    pub storage: Vec<String>,
+    pub kernel: String,
+}
+
+pub fn collect() -> SystemInfo {
+    let memory = memory::stats();
+    let heap = allocator::usage();
+    SystemInfo {
+        processor: cpu::info().brand.clone(),
+        memory: format!("{} installed, {} available", format_size(memory.installed), format_size(memory.available)),
+        heap: format!("{} of {} used", format_size(heap.used as u64), format_size(heap.size as u64)),
+        display: display(),
+        storage: block::list()
+            .iter()
+            .map(|device| {
+                let size = format_size(device.sector_count * SECTOR_SIZE as u64);
+                let access = if device.read_only { ", read-only" } else { "" };
+                format!("{} ({}{})", device.name, size, access)
+            })
+            .collect(),
+        kernel: format!("RustOS {} (built {})", KERNEL_VERSION, BUILD_DATE),
+    }
+}
+
+fn display() -> String {
+    let mode = graphics::detect_mode();
+    let mut text = if mode.graphics {
+        format!("{}×{}, {} colours", mode.width, mode.height, mode.colors)
+    } else {
+        format!("{}×{} text", mode.width, mode.height)
+    };
+    if let Some(adapter) = pci::devices().iter().find(|d| d.class == 0x03) {
+        text.push_str(&format!(" ({} {})", adapter.vendor_name(), adapter.class_name()));
+    }
+    text
+}
+
+// "512 KB", "127 MB", "1.5 GB"
+pub fn format_size(bytes: u64) -> String {
+    const KB: u64 = 1024;
+    const MB: u64 = KB * 1024;
+    const GB: u64 = MB * 1024;
+    if bytes >= GB {
+        format!("{}.{} GB", bytes / GB, bytes % GB * 10 / GB)
+    } else if bytes >= MB {
+        format!("{} MB", bytes / MB)
+    } else {
+        format!("{} KB", bytes / KB)
+    }
+}
\ No newline at end of file
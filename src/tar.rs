@@ -0,0 +1,199 @@
+// src/tar.rs
+// ustar archives: a reader used for the embedded initramfs, header parsing
+// for reading archives a block at a time off the VFS, and header building
+// for writing them.
+use alloc::string::String;
+
+pub const BLOCK: usize = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    File,
+    Directory,
+    Symlink,
+    Other,
+}
+
+pub struct Entry<'a> {
+    pub path: String,
+    pub kind: EntryKind,
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub mtime: u64,
+    pub link_target: String,
+    pub data: &'a [u8],
+}
+
+// An entry's header block; its data follows in `size` bytes, padded to
+// whole blocks
+pub struct Header {
+    pub path: String,
+    // None for pax and GNU extension headers, which are skipped
+    pub kind: Option<EntryKind>,
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub mtime: u64,
+    pub size: u64,
+    pub link_target: String,
+}
+
+pub struct Archive<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+fn parse_octal(field: &[u8]) -> u64 {
+    field
+        .iter()
+        .skip_while(|&&b| b == b' ')
+        .take_while(|&&b| (b'0'..=b'7').contains(&b))
+        .fold(0, |acc, &b| acc * 8 + (b - b'0') as u64)
+}
+
+fn parse_str(field: &[u8]) -> &str {
+    let len = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    core::str::from_utf8(&field[..len]).unwrap_or("")
+}
+
+fn checksum(header: &[u8]) -> u64 {
+    header
+        .iter()
+        .enumerate()
+        .map(|(i, &b)| if (148..156).contains(&i) { b' ' as u64 } else { b as u64 })
+        .sum()
+}
+
+fn checksum_ok(header: &[u8]) -> bool {
+    checksum(header) == parse_octal(&header[148..156])
+}
+
+// Bytes taken by `size` bytes of entry data
+pub fn padded(size: u64) -> u64 {
+    size.div_ceil(BLOCK as u64) * BLOCK as u64
+}
+
+// None at the end of the archive: a zero block, or one with a bad checksum
+pub fn parse_header(header: &[u8]) -> Option<Header> {
+    if header.len() < BLOCK || header.iter().all(|&b| b == 0) || !checksum_ok(header) {
+        return None;
+    }
+    let mut path = String::new();
+    if &header[257..262] == b"ustar" {
+        let prefix = parse_str(&header[345..500]);
+        if !prefix.is_empty() {
+            path.push_str(prefix);
+            path.push('/');
+        }
+    }
+    path.push_str(parse_str(&header[0..100]));
+    
+    let kind = match header[156] {
+        b'0' | 0 => Some(EntryKind::File),
+        b'5' => Some(EntryKind::Directory),
+        b'2' => Some(EntryKind::Symlink),
+        b'x' | b'g' | b'L' | b'K' => None,
+        _ => Some(EntryKind::Other),
+    };
+    
+    Some(Header {
+        path: String::from(path.trim_start_matches("./").trim_end_matches('/')),
+        kind,
+        mode: parse_octal(&header[100..108]) as u32,
+        uid: parse_octal(&header[108..116]) as u32,
+        gid: parse_octal(&header[116..124]) as u32,
+        mtime: parse_octal(&header[136..148]),
+        size: parse_octal(&header[124..136]),
+        link_target: String::from(parse_str(&header[157..257])),
+    })
+}
+
+fn write_octal(field: &mut [u8], value: u64) {
+    // Zero-padded digits and a NUL
+    let digits = field.len() - 1;
+    for (i, byte) in field[..digits].iter_mut().enumerate() {
+        *byte = b'0' + ((value >> (3 * (digits - 1 - i))) & 7) as u8;
+    }
+    field[digits] = 0;
+}
+
+// The header block for an entry, or None if the path doesn't fit: names
+// over 100 bytes are split at a '/' into the 155-byte prefix
+pub fn build_header(path: &str, kind: EntryKind, mode: u32, size: u64, mtime: u64) -> Option<[u8; BLOCK]> {
+    let (prefix, name) = match path.len() {
+        0..=100 => ("", path),
+        _ => {
+            let split = path.as_bytes()[..path.len().min(156)].iter().rposition(|&b| b == b'/')?;
+            (&path[..split], &path[split + 1..])
+        }
+    };
+    if name.is_empty() || name.len() > 100 || prefix.len() > 155 || size >= 1 << 33 {
+        return None;
+    }
+    
+    let mut header = [0u8; BLOCK];
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    write_octal(&mut header[100..108], mode as u64 & 0o7777);
+    write_octal(&mut header[108..116], 0);
+    write_octal(&mut header[116..124], 0);
+    write_octal(&mut header[124..136], size);
+    write_octal(&mut header[136..148], mtime);
+    header[156] = match kind {
+        EntryKind::Directory => b'5',
+        EntryKind::Symlink => b'2',
+        _ => b'0',
+    };
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+    header[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+    
+    let sum = checksum(&header);
+    write_octal(&mut header[148..155], sum);
+    header[155] = b' ';
+    Some(header)
+}
+
+impl<'a> Archive<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, offset: 0 }
+    }
+}
+
+impl<'a> Iterator for Archive<'a> {
+    type Item = Entry<'a>;
+    
+    fn next(&mut self) -> Option<Entry<'a>> {
+        loop {
+            if self.offset + BLOCK > self.data.len() {
+                return None;
+            }
+            // Two zero blocks end the archive; a bad checksum ends it too
+            let header = parse_header(&self.data[self.offset..self.offset + BLOCK])?;
+            
+            let data_start = self.offset + BLOCK;
+            let data_end = (data_start + header.size as usize).min(self.data.len());
+            self.offset = data_start + padded(header.size) as usize;
+            
+            // Skip pax/GNU extension headers
+            let kind = match header.kind {
+                Some(kind) => kind,
+                None => continue,
+            };
+            if header.path.is_empty() || header.path == "." {
+                continue;
+            }
+            
+            return Some(Entry {
+                path: header.path,
+                kind,
+                mode: header.mode,
+                uid: header.uid,
+                gid: header.gid,
+                mtime: header.mtime,
+                link_target: header.link_target,
+                data: &self.data[data_start..data_end],
+            });
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,268 @@
+// src/task.rs
+// Process table. A process is an address space plus bookkeeping; there is
+// no user-mode scheduler yet, so the kernel creates and tears processes
+// down on behalf of the shell and apps. A process's descriptors are what
+// its reads and writes go to: pipes, and the files and directories it
+// opened; 0 is its input, 1 and 2 its output. Stop and Continue signals take effect at once; the others wait
+// for whoever runs the process to act on them.
+use crate::address_space::{AddressSpace, USER_STACK_LIMIT, USER_STACK_TOP};
+use crate::pipe::{Reader, Writer};
+use crate::users;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU32, Ordering};
+use crate::sync::Mutex;
+use x86_64::VirtAddr;
+
+pub type Pid = u32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessState {
+    Running,
+    // Stopped by a signal until one continues it
+    Stopped,
+    // Kept until the parent collects the exit code
+    Zombie(i32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskError {
+    NoMemory,
+    NoSuchProcess,
+    TooManyFiles,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    Interrupt,
+    Kill,
+    Terminate,
+    Continue,
+    Stop,
+}
+
+const SIGNALS: [(Signal, i32, &str); 5] = [
+    (Signal::Interrupt, 2, "INT"),
+    (Signal::Kill, 9, "KILL"),
+    (Signal::Terminate, 15, "TERM"),
+    (Signal::Continue, 18, "CONT"),
+    (Signal::Stop, 19, "STOP"),
+];
+
+impl Signal {
+    // The POSIX number, which killed processes exit with 128 more than
+    pub fn number(self) -> i32 {
+        SIGNALS.iter().find(|(signal, ..)| *signal == self).map_or(0, |(_, number, _)| *number)
+    }
+
+    // A name like INT or SIGINT, or a number
+    pub fn parse(text: &str) -> Option<Signal> {
+        let name = text.strip_prefix("SIG").unwrap_or(text);
+        SIGNALS.iter()
+            .find(|(_, number, signal_name)| name.eq_ignore_ascii_case(signal_name) || name.parse() == Ok(*number))
+            .map(|(signal, ..)| *signal)
+    }
+}
+
+// What a descriptor reads from or writes to
+pub enum Descriptor {
+    Input(Reader),
+    Output(Writer),
+    // The O_* flags it was opened with, and where the next read or write
+    // goes
+    File { path: String, flags: u64, offset: u64 },
+    // How many entries have been listed
+    Directory { path: String, next: usize },
+}
+
+const MAX_FILES: usize = 64;
+
+pub use rustos_sdk::protocol::{STDERR, STDIN, STDOUT};
+
+struct Process {
+    parent: Option<Pid>,
+    name: String,
+    uid: u32,
+    state: ProcessState,
+    address_space: Option<AddressSpace>,
+    files: Vec<Option<Descriptor>>,
+    // Signals not acted on yet, oldest first
+    pending: Vec<Signal>,
+}
+
+pub struct ProcessInfo {
+    pub pid: Pid,
+    pub parent: Option<Pid>,
+    pub name: String,
+    pub uid: u32,
+    pub state: ProcessState,
+    pub resident_pages: usize,
+    pub shared_pages: usize,
+}
+
+static PROCESSES: Mutex<BTreeMap<Pid, Process>> = Mutex::new(BTreeMap::new());
+static NEXT_PID: AtomicU32 = AtomicU32::new(1);
+
+fn insert(process: Process) -> Pid {
+    let pid = NEXT_PID.fetch_add(1, Ordering::Relaxed);
+    PROCESSES.lock().insert(pid, process);
+    pid
+}
+
+pub fn spawn(name: &str) -> Result<Pid, TaskError> {
+    let mut address_space = AddressSpace::new().ok_or(TaskError::NoMemory)?;
+    // Every process gets a growable stack at the top of the user window
+    address_space
+        .map_stack(VirtAddr::new(USER_STACK_TOP), USER_STACK_LIMIT)
+        .map_err(|_| TaskError::NoMemory)?;
+    Ok(insert(Process {
+        parent: None,
+        name: String::from(name),
+        uid: users::current_uid(),
+        state: ProcessState::Running,
+        address_space: Some(address_space),
+        files: Vec::new(),
+        pending: Vec::new(),
+    }))
+}
+
+// The child starts with the parent's memory shared copy-on-write
+pub fn fork(pid: Pid, name: &str) -> Result<Pid, TaskError> {
+    let (child_space, uid) = {
+        let mut processes = PROCESSES.lock();
+        let parent = processes
+            .get_mut(&pid)
+            .filter(|p| p.address_space.is_some())
+            .ok_or(TaskError::NoSuchProcess)?;
+        let space = parent.address_space.as_mut().ok_or(TaskError::NoSuchProcess)?;
+        (space.fork().ok_or(TaskError::NoMemory)?, parent.uid)
+    };
+    
+    Ok(insert(Process {
+        parent: Some(pid),
+        name: String::from(name),
+        uid,
+        state: ProcessState::Running,
+        address_space: Some(child_space),
+        files: Vec::new(),
+        pending: Vec::new(),
+    }))
+}
+
+// Releases the address space and closes the descriptors right away; the
+// entry stays as a zombie until `wait` collects it. Children are
+// reparented to nobody.
+pub fn exit(pid: Pid, code: i32) -> Result<(), TaskError> {
+    let (address_space, files) = {
+        let mut processes = PROCESSES.lock();
+        let process = processes.get_mut(&pid).ok_or(TaskError::NoSuchProcess)?;
+        process.state = ProcessState::Zombie(code);
+        process.pending.clear();
+        let released = (process.address_space.take(), core::mem::take(&mut process.files));
+        for child in processes.values_mut().filter(|p| p.parent == Some(pid)) {
+            child.parent = None;
+        }
+        released
+    };
+    // Dropped outside the lock, freeing (or un-sharing) its frames
+    drop(address_space);
+    drop(files);
+    Ok(())
+}
+
+// Sets or closes descriptor `fd`
+pub fn set_file(pid: Pid, fd: usize, file: Option<Descriptor>) -> Result<(), TaskError> {
+    let old = {
+        let mut processes = PROCESSES.lock();
+        let process = processes.get_mut(&pid).filter(|p| p.address_space.is_some()).ok_or(TaskError::NoSuchProcess)?;
+        if process.files.len() <= fd {
+            process.files.resize_with(fd + 1, || None);
+        }
+        core::mem::replace(&mut process.files[fd], file)
+    };
+    drop(old);
+    Ok(())
+}
+
+// Opens `file` as the lowest free descriptor
+pub fn add_file(pid: Pid, file: Descriptor) -> Result<usize, TaskError> {
+    let mut processes = PROCESSES.lock();
+    let process = processes.get_mut(&pid).filter(|p| p.address_space.is_some()).ok_or(TaskError::NoSuchProcess)?;
+    let fd = process.files.iter().position(Option::is_none).unwrap_or(process.files.len());
+    if fd == MAX_FILES {
+        return Err(TaskError::TooManyFiles);
+    }
+    if fd == process.files.len() {
+        process.files.push(None);
+    }
+    process.files[fd] = Some(file);
+    Ok(fd)
+}
+
+// None if the process has nothing open as `fd`
+pub fn with_file<R>(pid: Pid, fd: usize, f: impl FnOnce(&mut Descriptor) -> R) -> Option<R> {
+    let mut processes = PROCESSES.lock();
+    processes.get_mut(&pid)?.files.get_mut(fd)?.as_mut().map(f)
+}
+
+pub fn signal(pid: Pid, signal: Signal) -> Result<(), TaskError> {
+    let mut processes = PROCESSES.lock();
+    let process = processes.get_mut(&pid).filter(|p| p.address_space.is_some()).ok_or(TaskError::NoSuchProcess)?;
+    match signal {
+        Signal::Stop => process.state = ProcessState::Stopped,
+        Signal::Continue => process.state = ProcessState::Running,
+        signal => process.pending.push(signal),
+    }
+    Ok(())
+}
+
+// The oldest signal the process hasn't acted on
+pub fn take_signal(pid: Pid) -> Option<Signal> {
+    let mut processes = PROCESSES.lock();
+    let pending = &mut processes.get_mut(&pid)?.pending;
+    (!pending.is_empty()).then(|| pending.remove(0))
+}
+
+pub fn state(pid: Pid) -> Option<ProcessState> {
+    PROCESSES.lock().get(&pid).map(|p| p.state)
+}
+
+pub fn wait(pid: Pid) -> Option<i32> {
+    let mut processes = PROCESSES.lock();
+    match processes.get(&pid)?.state {
+        ProcessState::Zombie(code) => {
+            processes.remove(&pid);
+            Some(code)
+        }
+        ProcessState::Running | ProcessState::Stopped => None,
+    }
+}
+
+pub fn has_files(pid: Pid) -> bool {
+    PROCESSES.lock().get(&pid).map_or(false, |p| p.files.iter().any(Option::is_some))
+}
+
+pub fn with_address_space<R>(pid: Pid, f: impl FnOnce(&mut AddressSpace) -> R) -> Option<R> {
+    let mut processes = PROCESSES.lock();
+    processes.get_mut(&pid)?.address_space.as_mut().map(f)
+}
+
+pub fn list() -> Vec<ProcessInfo> {
+    PROCESSES
+        .lock()
+        .iter()
+        .map(|(pid, p)| {
+            let (resident_pages, shared_pages) = p.address_space.as_ref().map_or((0, 0), |s| s.page_counts());
+            ProcessInfo {
+                pid: *pid,
+                parent: p.parent,
+                name: p.name.clone(),
+                uid: p.uid,
+                state: p.state,
+                resident_pages,
+                shared_pages,
+            }
+        })
+        .collect()
+}
\ No newline at end of file
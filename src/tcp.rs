@@ -0,0 +1,439 @@
+// src/tcp.rs
+// TCP segments and the per-connection state machine. Out-of-order
+// segments are dropped and re-acknowledged rather than buffered, and lost
+// data is recovered go-back-N on a doubling retransmission timer; that is
+// plenty on the QEMU user network. The socket table owns the connections,
+// feeds them segments and timer ticks, and sends what they emit.
+use crate::clock::Instant;
+use crate::net::{self, Ipv4Addr, PROTOCOL_TCP};
+use crate::socket::State;
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+pub const FIN: u8 = 0x01;
+pub const SYN: u8 = 0x02;
+pub const RST: u8 = 0x04;
+pub const PSH: u8 = 0x08;
+pub const ACK: u8 = 0x10;
+
+const HEADER: usize = 20;
+const OPTION_MSS: u8 = 2;
+// Largest segment we take, and what we assume when the peer doesn't say
+const OUR_MSS: u16 = 1460;
+const DEFAULT_MSS: usize = 536;
+// Bytes buffered in each direction
+const RECEIVE_WINDOW: usize = 8192;
+const SEND_BUFFER: usize = 16384;
+const RETRANSMIT_TIMEOUT: Duration = Duration::from_secs(1);
+const MAX_RETRIES: u32 = 6;
+const TIME_WAIT: Duration = Duration::from_secs(2);
+
+pub type Endpoint = (Ipv4Addr, u16);
+
+pub struct Segment<'a> {
+    pub src_port: u16,
+    pub dst_port: u16,
+    pub seq: u32,
+    pub ack: u32,
+    pub flags: u8,
+    pub window: u16,
+    pub mss: Option<u16>,
+    pub payload: &'a [u8],
+}
+
+fn be16(data: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([data[offset], data[offset + 1]])
+}
+
+fn be32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+// Checks the checksum against the pseudo-header for `src` and `dst`
+pub fn parse(src: Ipv4Addr, dst: Ipv4Addr, data: &[u8]) -> Option<Segment<'_>> {
+    if data.len() < HEADER || net::checksum_with(net::pseudo_header_sum(src, dst, PROTOCOL_TCP, data.len()), data) != 0 {
+        return None;
+    }
+    let offset = (data[12] >> 4) as usize * 4;
+    if offset < HEADER || offset > data.len() {
+        return None;
+    }
+    // Only the MSS option matters; the rest are skipped by length
+    let mut mss = None;
+    let mut options = &data[HEADER..offset];
+    while let Some(&kind) = options.first() {
+        match kind {
+            0 => break,
+            1 => options = &options[1..],
+            _ => {
+                let len = *options.get(1)? as usize;
+                if len < 2 || len > options.len() {
+                    return None;
+                }
+                if kind == OPTION_MSS && len == 4 {
+                    mss = Some(be16(options, 2));
+                }
+                options = &options[len..];
+            }
+        }
+    }
+    Some(Segment {
+        src_port: be16(data, 0),
+        dst_port: be16(data, 2),
+        seq: be32(data, 4),
+        ack: be32(data, 8),
+        flags: data[13],
+        window: be16(data, 14),
+        mss,
+        payload: &data[offset..],
+    })
+}
+
+// A segment from `local` to `remote`, checksum included. SYNs carry our MSS.
+pub fn build(local: Endpoint, remote: Endpoint, seq: u32, ack: u32, flags: u8, window: u16, payload: &[u8]) -> Vec<u8> {
+    let options: &[u8] = if flags & SYN != 0 { &[OPTION_MSS, 4, (OUR_MSS >> 8) as u8, OUR_MSS as u8] } else { &[] };
+    let len = HEADER + options.len() + payload.len();
+    let mut segment = Vec::with_capacity(len);
+    segment.extend_from_slice(&local.1.to_be_bytes());
+    segment.extend_from_slice(&remote.1.to_be_bytes());
+    segment.extend_from_slice(&seq.to_be_bytes());
+    segment.extend_from_slice(&ack.to_be_bytes());
+    segment.push((((HEADER + options.len()) / 4) << 4) as u8);
+    segment.push(flags);
+    segment.extend_from_slice(&window.to_be_bytes());
+    segment.extend_from_slice(&[0, 0, 0, 0]);
+    segment.extend_from_slice(options);
+    segment.extend_from_slice(payload);
+    let sum = net::checksum_with(net::pseudo_header_sum(local.0, remote.0, PROTOCOL_TCP, len), &segment);
+    segment[16..18].copy_from_slice(&sum.to_be_bytes());
+    segment
+}
+
+// The reset answering a segment that belongs to no connection
+pub fn reset_for(local: Endpoint, remote: Endpoint, segment: &Segment) -> Option<Vec<u8>> {
+    if segment.flags & RST != 0 {
+        return None;
+    }
+    Some(if segment.flags & ACK != 0 {
+        build(local, remote, segment.ack, 0, RST, 0, &[])
+    } else {
+        let len = segment.payload.len() as u32 + (segment.flags & (SYN | FIN) != 0) as u32;
+        build(local, remote, 0, segment.seq.wrapping_add(len), RST | ACK, 0, &[])
+    })
+}
+
+// Sequence number comparison modulo 2^32
+fn seq_lt(a: u32, b: u32) -> bool {
+    (a.wrapping_sub(b) as i32) < 0
+}
+
+fn seq_le(a: u32, b: u32) -> bool {
+    a == b || seq_lt(a, b)
+}
+
+pub struct Connection {
+    pub local: Endpoint,
+    pub remote: Endpoint,
+    pub state: State,
+    iss: u32,
+    // Oldest unacknowledged and next sequence number to send
+    snd_una: u32,
+    snd_nxt: u32,
+    snd_wnd: usize,
+    mss: usize,
+    rcv_nxt: u32,
+    // Everything from snd_una on, sent or not
+    send_buffer: VecDeque<u8>,
+    recv_buffer: VecDeque<u8>,
+    // The application closed; a FIN follows the buffered data
+    closing: bool,
+    fin_sent: bool,
+    retransmit_at: Option<Instant>,
+    retries: u32,
+    time_wait_until: Option<Instant>,
+}
+
+impl Connection {
+    // Answers a SYN that arrived on a listening socket
+    pub fn accept(local: Endpoint, remote: Endpoint, syn: &Segment, iss: u32, out: &mut Vec<Vec<u8>>) -> Self {
+        let connection = Self {
+            local,
+            remote,
+            state: State::SynReceived,
+            iss,
+            snd_una: iss,
+            snd_nxt: iss.wrapping_add(1),
+            snd_wnd: syn.window as usize,
+            mss: syn.mss.map_or(DEFAULT_MSS, |mss| (mss as usize).min(OUR_MSS as usize)),
+            rcv_nxt: syn.seq.wrapping_add(1),
+            send_buffer: VecDeque::new(),
+            recv_buffer: VecDeque::new(),
+            closing: false,
+            fin_sent: false,
+            retransmit_at: Some(Instant::now() + RETRANSMIT_TIMEOUT),
+            retries: 0,
+            time_wait_until: None,
+        };
+        connection.send_syn_ack(out);
+        connection
+    }
+    
+    // Opens a connection to `remote` by sending a SYN
+    pub fn connect(local: Endpoint, remote: Endpoint, iss: u32, out: &mut Vec<Vec<u8>>) -> Self {
+        let connection = Self {
+            local,
+            remote,
+            state: State::SynSent,
+            iss,
+            snd_una: iss,
+            snd_nxt: iss.wrapping_add(1),
+            snd_wnd: 0,
+            mss: DEFAULT_MSS,
+            rcv_nxt: 0,
+            send_buffer: VecDeque::new(),
+            recv_buffer: VecDeque::new(),
+            closing: false,
+            fin_sent: false,
+            retransmit_at: Some(Instant::now() + RETRANSMIT_TIMEOUT),
+            retries: 0,
+            time_wait_until: None,
+        };
+        connection.send(iss, SYN, &[], out);
+        connection
+    }
+    
+    fn window(&self) -> u16 {
+        (RECEIVE_WINDOW - self.recv_buffer.len()) as u16
+    }
+    
+    fn send(&self, seq: u32, flags: u8, payload: &[u8], out: &mut Vec<Vec<u8>>) {
+        out.push(build(self.local, self.remote, seq, self.rcv_nxt, flags, self.window(), payload));
+    }
+    
+    fn send_syn_ack(&self, out: &mut Vec<Vec<u8>>) {
+        self.send(self.iss, SYN | ACK, &[], out);
+    }
+    
+    fn send_ack(&self, out: &mut Vec<Vec<u8>>) {
+        self.send(self.snd_nxt, ACK, &[], out);
+    }
+    
+    pub fn is_closed(&self) -> bool {
+        self.state == State::Closed
+    }
+    
+    // No more data will arrive once the peer's FIN is in
+    pub fn peer_closed(&self) -> bool {
+        matches!(self.state, State::CloseWait | State::LastAck | State::Closing | State::TimeWait | State::Closed)
+    }
+    
+    fn enter_time_wait(&mut self) {
+        self.state = State::TimeWait;
+        self.retransmit_at = None;
+        self.time_wait_until = Some(Instant::now() + TIME_WAIT);
+    }
+    
+    // Our SYN is out: only a SYN-ACK for it, or a reset refusing it, counts.
+    // A bare SYN (simultaneous open) is ignored and our SYN retried.
+    fn receive_syn_sent(&mut self, segment: &Segment, out: &mut Vec<Vec<u8>>) {
+        if segment.flags & ACK == 0 || segment.ack != self.snd_nxt {
+            if segment.flags & ACK != 0 {
+                out.extend(reset_for(self.local, self.remote, segment));
+            }
+            return;
+        }
+        if segment.flags & RST != 0 {
+            self.state = State::Closed;
+            return;
+        }
+        if segment.flags & SYN == 0 {
+            return;
+        }
+        self.rcv_nxt = segment.seq.wrapping_add(1);
+        self.snd_una = segment.ack;
+        self.snd_wnd = segment.window as usize;
+        self.mss = segment.mss.map_or(DEFAULT_MSS, |mss| (mss as usize).min(OUR_MSS as usize));
+        self.retransmit_at = None;
+        self.retries = 0;
+        self.state = State::Established;
+        self.send_ack(out);
+        self.output(out);
+    }
+    
+    pub fn receive(&mut self, segment: &Segment, out: &mut Vec<Vec<u8>>) {
+        if self.state == State::SynSent {
+            self.receive_syn_sent(segment, out);
+            return;
+        }
+        if segment.flags & RST != 0 {
+            // Only a reset that lines up with what we expect is believed
+            if segment.seq == self.rcv_nxt {
+                self.state = State::Closed;
+            }
+            return;
+        }
+        if segment.seq != self.rcv_nxt {
+            // Duplicates and out-of-order data get our current position
+            if self.state == State::SynReceived && segment.flags & SYN != 0 {
+                self.send_syn_ack(out);
+            } else if !segment.payload.is_empty() || segment.flags & (SYN | FIN) != 0 {
+                self.send_ack(out);
+            }
+            return;
+        }
+        if segment.flags & SYN != 0 {
+            out.extend(reset_for(self.local, self.remote, segment));
+            self.state = State::Closed;
+            return;
+        }
+        if segment.flags & ACK == 0 {
+            return;
+        }
+        
+        let ack = segment.ack;
+        if self.state == State::SynReceived {
+            if ack != self.snd_nxt {
+                out.extend(reset_for(self.local, self.remote, segment));
+                return;
+            }
+            self.state = State::Established;
+        }
+        if seq_lt(self.snd_una, ack) && seq_le(ack, self.snd_nxt) {
+            let acked = ack.wrapping_sub(self.snd_una) as usize;
+            let data = acked.min(self.send_buffer.len());
+            self.send_buffer.drain(..data);
+            self.snd_una = ack;
+            self.retries = 0;
+            self.retransmit_at = if self.snd_una == self.snd_nxt { None } else { Some(Instant::now() + RETRANSMIT_TIMEOUT) };
+        }
+        self.snd_wnd = segment.window as usize;
+        if self.fin_sent && ack == self.snd_nxt {
+            match self.state {
+                State::FinWait1 => self.state = State::FinWait2,
+                State::Closing => self.enter_time_wait(),
+                State::LastAck => self.state = State::Closed,
+                _ => {}
+            }
+        }
+        
+        let mut need_ack = false;
+        let receiving = matches!(self.state, State::Established | State::FinWait1 | State::FinWait2);
+        let mut taken = 0;
+        if receiving && !segment.payload.is_empty() {
+            taken = segment.payload.len().min(RECEIVE_WINDOW - self.recv_buffer.len());
+            self.recv_buffer.extend(&segment.payload[..taken]);
+            self.rcv_nxt = self.rcv_nxt.wrapping_add(taken as u32);
+            need_ack = true;
+        }
+        // A FIN only counts once everything before it has been taken
+        if segment.flags & FIN != 0 && taken == segment.payload.len() && receiving {
+            self.rcv_nxt = self.rcv_nxt.wrapping_add(1);
+            need_ack = true;
+            match self.state {
+                State::Established => self.state = State::CloseWait,
+                State::FinWait1 => self.state = State::Closing,
+                _ => self.enter_time_wait(),
+            }
+        }
+        if need_ack {
+            self.send_ack(out);
+        }
+        self.output(out);
+    }
+    
+    // Sends whatever new data the peer's window has room for, then the FIN
+    fn output(&mut self, out: &mut Vec<Vec<u8>>) {
+        if !matches!(self.state, State::Established | State::CloseWait | State::FinWait1 | State::Closing | State::LastAck) {
+            return;
+        }
+        while !self.fin_sent {
+            let in_flight = self.snd_nxt.wrapping_sub(self.snd_una) as usize;
+            let unsent = self.send_buffer.len() - in_flight;
+            let room = self.snd_wnd.saturating_sub(in_flight);
+            if unsent > 0 && room > 0 {
+                let len = unsent.min(room).min(self.mss);
+                let payload: Vec<u8> = self.send_buffer.range(in_flight..in_flight + len).copied().collect();
+                self.send(self.snd_nxt, ACK | PSH, &payload, out);
+                self.snd_nxt = self.snd_nxt.wrapping_add(len as u32);
+            } else if unsent == 0 && self.closing {
+                self.send(self.snd_nxt, FIN | ACK, &[], out);
+                self.snd_nxt = self.snd_nxt.wrapping_add(1);
+                self.fin_sent = true;
+                match self.state {
+                    State::Established => self.state = State::FinWait1,
+                    State::CloseWait => self.state = State::LastAck,
+                    _ => {}
+                }
+            } else {
+                break;
+            }
+            self.retransmit_at.get_or_insert(Instant::now() + RETRANSMIT_TIMEOUT);
+        }
+    }
+    
+    // Queues as much of `data` as fits in the send buffer
+    pub fn write(&mut self, data: &[u8], out: &mut Vec<Vec<u8>>) -> usize {
+        if self.closing || !matches!(self.state, State::Established | State::CloseWait | State::SynSent | State::SynReceived) {
+            return 0;
+        }
+        let len = data.len().min(SEND_BUFFER - self.send_buffer.len());
+        self.send_buffer.extend(&data[..len]);
+        self.output(out);
+        len
+    }
+    
+    pub fn read(&mut self, out: &mut Vec<Vec<u8>>) -> Vec<u8> {
+        // Tell a peer that saw a nearly full window that it has opened up
+        let was_full = (self.window() as usize) < self.mss;
+        let data: Vec<u8> = self.recv_buffer.drain(..).collect();
+        if was_full && !data.is_empty() && !self.peer_closed() {
+            self.send_ack(out);
+        }
+        data
+    }
+    
+    // Graceful close: the FIN goes out once the buffered data has
+    pub fn close(&mut self, out: &mut Vec<Vec<u8>>) {
+        self.closing = true;
+        self.output(out);
+    }
+    
+    pub fn abort(&mut self, out: &mut Vec<Vec<u8>>) {
+        if !matches!(self.state, State::Closed | State::TimeWait) {
+            self.send(self.snd_nxt, RST | ACK, &[], out);
+        }
+        self.state = State::Closed;
+    }
+    
+    pub fn tick(&mut self, now: Instant, out: &mut Vec<Vec<u8>>) {
+        if self.time_wait_until.is_some_and(|until| now >= until) {
+            self.state = State::Closed;
+            return;
+        }
+        match self.retransmit_at {
+            Some(at) if now >= at => {}
+            _ => return,
+        }
+        self.retries += 1;
+        if self.retries > MAX_RETRIES {
+            self.abort(out);
+            return;
+        }
+        self.retransmit_at = Some(now + RETRANSMIT_TIMEOUT * (1 << self.retries));
+        match self.state {
+            State::SynSent => return self.send(self.iss, SYN, &[], out),
+            State::SynReceived => return self.send_syn_ack(out),
+            _ => {}
+        }
+        // Go back to the oldest unacknowledged byte and send it all again
+        self.snd_nxt = self.snd_una;
+        self.fin_sent = false;
+        self.output(out);
+    }
+    
+    // Bytes waiting to be read, and to be sent or acknowledged
+    pub fn queued(&self) -> (usize, usize) {
+        (self.recv_buffer.len(), self.send_buffer.len())
+    }
+}
\ No newline at end of file
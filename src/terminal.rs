@@ -0,0 +1,757 @@
+// src/terminal.rs
+// The Terminal app: the kernel shell in a window. What's printed goes into
+// a grid of character cells, a row per line wrapped at the window's width
+// when it arrives, and the prompt and what's being typed follow the last
+// row. The newest MAX_SCROLLBACK rows are kept. Dragging over the cells
+// selects them, a double click selects a word and a triple click a line;
+// Cmd+C copies the selection and Cmd+V types what's on the clipboard,
+// running each complete line. While a job runs in the foreground, entered
+// lines are its input, Ctrl-C interrupts it, Ctrl-Z stops it and Ctrl-D
+// ends its input. How it looks comes from the profile in use; programs
+// pick from its ANSI colors with SGR escape codes (ESC [ ... m).
+//
+// Cmd+D splits the pane with focus side by side and Shift+Cmd+D stacks
+// the new pane under it. Each pane runs its own shell and scrolls on its
+// own; Cmd+Option+arrows move focus between them, the dividers can be
+// dragged, and `exit` or Ctrl-D at the prompt closes a pane. The search
+// bar searches the pane with focus.
+use crate::clipboard;
+use crate::clock;
+use crate::glyph_cache::GLYPH_WIDTH;
+use crate::graphics::{Graphics, Color, Surface};
+use crate::keyboard::{Key, KeyEvent};
+use crate::shell::Shell;
+use crate::terminal_profile::{self, CursorStyle, Profile, ProfileSheet, Scheme};
+use crate::widgets::{self, Frame, Orientation, ScrollView, SearchBar, SplitView};
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::sync::Mutex;
+
+const MARGIN: usize = 10;
+// Between rows, on top of the font size
+const LINE_SPACING: usize = 8;
+const SCROLLER_WIDTH: usize = 10;
+const MAX_SCROLLBACK: usize = 1000;
+const DOUBLE_CLICK_MS: u64 = 500;
+// A blinking cursor is shown and hidden this long each
+const BLINK_MS: u64 = 500;
+// The prompt's ANSI color
+const GREEN: Paint = Paint::Ansi(2);
+
+// A cell's color, looked up in the scheme when drawn so a new scheme
+// repaints what's already there
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Paint {
+    Foreground,
+    // Index into the scheme's ANSI colors
+    Ansi(u8),
+}
+
+impl Paint {
+    fn color(self, scheme: &Scheme) -> Color {
+        match self {
+            Paint::Foreground => scheme.foreground,
+            Paint::Ansi(index) => scheme.ansi[index as usize],
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Cell {
+    ch: char,
+    paint: Paint,
+}
+
+struct Row {
+    cells: Vec<Cell>,
+    // The line goes on in the next row rather than ending here
+    wrapped: bool,
+}
+
+// A cell by its row, counting the scrollback's rows and then the input's
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Point {
+    row: usize,
+    column: usize,
+}
+
+impl Point {
+    // The same cell once `rows` rows have gone off the top; None if it went
+    fn up(self, rows: usize) -> Option<Point> {
+        Some(Point { row: self.row.checked_sub(rows)?, column: self.column })
+    }
+}
+
+// A shell session and its scrollback
+struct Pane {
+    shell: Shell,
+    rows: Vec<Row>,
+    // Typed and not entered yet
+    line: String,
+    // Cells in a row, from the pane's width when last drawn
+    columns: usize,
+    // A cell's width, from the profile when last drawn; the scroll view
+    // has the row height
+    cell_width: usize,
+    scroll: ScrollView,
+    // Where the selection started and where it ends, in either order; both
+    // ends are selected
+    selection: Option<(Point, Point)>,
+    // While dragging out a selection: where it started and the pointer,
+    // relative to the pane
+    dragging: Option<(Point, i32, i32)>,
+    // The last click's cell and time, and how many clicks in a row it made
+    last_click: Option<(Point, u64, usize)>,
+    // A foreground job has the prompt waiting
+    running: bool,
+    // What the SGR codes printed so far have chosen
+    pen: Paint,
+    bold: bool,
+    // Asked to close with `exit` or Ctrl-D
+    closing: bool,
+}
+
+struct Terminal {
+    panes: Vec<Pane>,
+    layout: SplitView,
+    // Index of the pane with focus
+    focused: usize,
+    search: SearchBar,
+    profiles: Vec<Profile>,
+    // Index of the one in use
+    profile: usize,
+    sheet: ProfileSheet,
+    // The divider being dragged, by its index in the layout
+    divider: Option<usize>,
+    // The content's size when last drawn
+    size: (usize, usize),
+    // Whether the cursor was shown when last drawn, so a blink redraws
+    cursor_drawn: bool,
+}
+
+static TERMINAL: Mutex<Option<Terminal>> = Mutex::new(None);
+// What's under the content, for a background that isn't opaque
+static UNDER: Mutex<Surface> = Mutex::new(Surface::new());
+
+// Splits a line into rows of `columns` cells; an empty line is one row
+fn wrap(cells: Vec<Cell>, columns: usize) -> Vec<Row> {
+    if cells.is_empty() {
+        return alloc::vec![Row { cells, wrapped: false }];
+    }
+    let count = cells.len().div_ceil(columns);
+    cells
+        .chunks(columns)
+        .enumerate()
+        .map(|(i, chunk)| Row { cells: chunk.to_vec(), wrapped: i + 1 < count })
+        .collect()
+}
+
+fn cells(text: &str, paint: Paint) -> impl Iterator<Item = Cell> + '_ {
+    text.chars().map(move |ch| Cell { ch, paint })
+}
+
+impl Pane {
+    fn new(profile: &Profile) -> Self {
+        Self {
+            shell: Shell::new(),
+            rows: Vec::new(),
+            line: String::new(),
+            columns: 80,
+            cell_width: GLYPH_WIDTH * profile.scale(),
+            scroll: ScrollView { offset: 0, row_height: profile.font_size + LINE_SPACING, follow_end: true },
+            selection: None,
+            dragging: None,
+            last_click: None,
+            running: false,
+            pen: Paint::Foreground,
+            bold: false,
+            closing: false,
+        }
+    }
+
+    fn total(&self) -> usize {
+        self.rows.len() + self.input_rows("").len()
+    }
+
+    // The prompt, unless a job has it waiting, and the typed line
+    fn input_rows(&self, suffix: &str) -> Vec<Row> {
+        let mut input = Vec::new();
+        if !self.running {
+            input.extend(cells(&self.shell.prompt(), GREEN));
+        }
+        input.extend(cells(&self.line, Paint::Foreground));
+        input.extend(cells(suffix, Paint::Foreground));
+        wrap(input, self.columns)
+    }
+
+    fn row<'a>(&'a self, input: &'a [Row], index: usize) -> Option<&'a Row> {
+        self.rows.get(index).or_else(|| input.get(index - self.rows.len()))
+    }
+
+    fn print(&mut self, text: &str) {
+        for line in text.split('\n') {
+            let cells = self.parse(line);
+            self.rows.extend(wrap(cells, self.columns));
+        }
+        self.trim();
+    }
+
+    // A line of output as cells, with the SGR codes taken out and followed:
+    // 0 resets, 1 makes the colors bright, 30 to 37 and 90 to 97 pick one
+    // and 39 goes back to the foreground. Other escape sequences are dropped.
+    fn parse(&mut self, line: &str) -> Vec<Cell> {
+        let mut cells = Vec::new();
+        let mut chars = line.chars().peekable();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '\x1b' if chars.peek() == Some(&'[') => {
+                    chars.next();
+                    let mut parameters = String::new();
+                    let mut command = None;
+                    for ch in chars.by_ref() {
+                        if ('\x40'..='\x7e').contains(&ch) {
+                            command = Some(ch);
+                            break;
+                        }
+                        parameters.push(ch);
+                    }
+                    if command == Some('m') {
+                        self.select_graphics(&parameters);
+                    }
+                }
+                '\t' => cells.push(Cell { ch: ' ', paint: self.paint() }),
+                ch if !ch.is_control() => cells.push(Cell { ch, paint: self.paint() }),
+                _ => {}
+            }
+        }
+        cells
+    }
+
+    fn select_graphics(&mut self, parameters: &str) {
+        for parameter in parameters.split(';') {
+            match parameter.parse::<u8>().unwrap_or(0) {
+                0 => {
+                    self.pen = Paint::Foreground;
+                    self.bold = false;
+                }
+                1 => self.bold = true,
+                22 => self.bold = false,
+                code @ 30..=37 => self.pen = Paint::Ansi(code - 30),
+                39 => self.pen = Paint::Foreground,
+                code @ 90..=97 => self.pen = Paint::Ansi(code - 90 + 8),
+                _ => {}
+            }
+        }
+    }
+
+    // What's printed next is painted with
+    fn paint(&self) -> Paint {
+        match self.pen {
+            Paint::Ansi(index) if self.bold && index < 8 => Paint::Ansi(index + 8),
+            pen => pen,
+        }
+    }
+
+    // Colors chosen by one command don't carry over to the next
+    fn reset_pen(&mut self) {
+        self.pen = Paint::Foreground;
+        self.bold = false;
+    }
+
+    // Moves the input rows into the scrollback, as typed
+    fn commit_input(&mut self, suffix: &str) {
+        let rows = self.input_rows(suffix);
+        self.rows.extend(rows);
+        self.line.clear();
+        self.trim();
+    }
+
+    // Drops the oldest rows past MAX_SCROLLBACK
+    fn trim(&mut self) {
+        let excess = self.rows.len().saturating_sub(MAX_SCROLLBACK);
+        if excess == 0 {
+            return;
+        }
+        self.rows.drain(..excess);
+        self.scroll.offset = self.scroll.offset.saturating_sub(excess);
+        // The selection moves up with its text, and goes with it
+        self.selection = self.selection.and_then(|(start, end)| Some((start.up(excess)?, end.up(excess)?)));
+        self.dragging = None;
+        self.last_click = None;
+    }
+
+    fn enter(&mut self) {
+        let line = self.line.clone();
+        self.commit_input("");
+        self.scroll.follow_end = true;
+        if self.running {
+            self.shell.input_line(&line);
+            return;
+        }
+        match line.trim() {
+            "exit" | "logout" => {
+                self.closing = true;
+                return;
+            }
+            "clear" => {
+                self.rows.clear();
+                self.selection = None;
+                self.last_click = None;
+                return;
+            }
+            _ => {}
+        }
+        self.reset_pen();
+        for text in self.shell.execute(&line) {
+            self.print(&text);
+        }
+        self.running = self.shell.is_busy();
+    }
+
+    // Types `text` as if from the keyboard, entering each line it ends
+    fn paste(&mut self, text: &str) {
+        for ch in text.chars() {
+            match ch {
+                '\n' => self.enter(),
+                '\t' => self.line.push(' '),
+                ch if !ch.is_control() => self.line.push(ch),
+                _ => {}
+            }
+        }
+        self.scroll.follow_end = true;
+    }
+
+    fn key(&mut self, event: &KeyEvent) {
+        match event.key {
+            Key::C if event.cmd => {
+                if let Some(text) = self.selected_text() {
+                    clipboard::set_text(&text);
+                }
+            }
+            Key::V if event.cmd => self.paste(&clipboard::text()),
+            // Ctrl-C drops the line, and interrupts the foreground job
+            Key::C if event.ctrl => {
+                self.commit_input("^C");
+                if self.running {
+                    self.shell.interrupt();
+                }
+            }
+            // Ctrl-Z stops the foreground job
+            Key::Z if event.ctrl && self.running => {
+                self.commit_input("^Z");
+                self.shell.suspend();
+            }
+            // Ctrl-D on an empty line ends the job's input
+            Key::D if event.ctrl && self.running && self.line.is_empty() => self.shell.close_input(),
+            Key::D if event.ctrl && self.line.is_empty() => self.closing = true,
+            Key::Enter => self.enter(),
+            Key::Backspace => {
+                self.line.pop();
+            }
+            _ if event.cmd || event.ctrl => {}
+            key => {
+                let ch = key.to_char().or_else(|| key.to_digit().and_then(|digit| char::from_digit(digit, 10)));
+                if let Some(ch) = ch {
+                    self.line.push(if event.shift { ch.to_ascii_uppercase() } else { ch });
+                    self.scroll.follow_end = true;
+                }
+            }
+        }
+    }
+
+    // None when nothing is selected
+    fn selected_text(&self) -> Option<String> {
+        let (a, b) = self.selection?;
+        let (start, end) = (a.min(b), a.max(b));
+        let input = self.input_rows("");
+        let mut text = String::new();
+        for index in start.row..=end.row {
+            let Some(row) = self.row(&input, index) else { break };
+            let first = if index == start.row { start.column } else { 0 };
+            let last = if index == end.row { end.column + 1 } else { row.cells.len() };
+            text.extend(row.cells.iter().take(last).skip(first).map(|cell| cell.ch));
+            if index < end.row && !row.wrapped {
+                text.push('\n');
+            }
+        }
+        Some(text)
+    }
+
+    // The cell under (x, y) in the pane, clamped to the grid
+    fn point_at(&self, x: i32, y: i32, total: usize) -> Point {
+        let row = (y - MARGIN as i32).max(0) as usize / self.scroll.row_height + self.scroll.offset;
+        let column = (x - MARGIN as i32).max(0) as usize / self.cell_width;
+        Point { row: row.min(total.saturating_sub(1)), column: column.min(self.columns - 1) }
+    }
+
+    // The run of non-blank cells around `point`; None on a blank
+    fn word_at(&self, input: &[Row], point: Point) -> Option<(Point, Point)> {
+        let cells = &self.row(input, point.row)?.cells;
+        let blank = |column: usize| cells[column].ch.is_whitespace();
+        if point.column >= cells.len() || blank(point.column) {
+            return None;
+        }
+        let mut first = point.column;
+        while first > 0 && !blank(first - 1) {
+            first -= 1;
+        }
+        let mut last = point.column;
+        while last + 1 < cells.len() && !blank(last + 1) {
+            last += 1;
+        }
+        Some((Point { row: point.row, column: first }, Point { row: point.row, column: last }))
+    }
+
+    // The whole line `point` is on, across the rows it wrapped onto
+    fn line_at(&self, input: &[Row], point: Point) -> Option<(Point, Point)> {
+        let wrapped = |row: usize| self.row(input, row).is_some_and(|row| row.wrapped);
+        let mut first = point.row;
+        while first > 0 && wrapped(first - 1) {
+            first -= 1;
+        }
+        let mut last = point.row;
+        while wrapped(last) {
+            last += 1;
+        }
+        let len = self.row(input, last)?.cells.len();
+        Some((Point { row: first, column: 0 }, Point { row: last, column: len.saturating_sub(1) }))
+    }
+
+    // `x` and `y` are relative to the pane
+    fn click(&mut self, x: usize, y: usize) {
+        let input = self.input_rows("");
+        let total = self.rows.len() + input.len();
+        let point = self.point_at(x as i32, y as i32, total);
+        let now = clock::millis();
+        let count = match self.last_click {
+            Some((last, at, count)) if last == point && now - at < DOUBLE_CLICK_MS => count % 3 + 1,
+            _ => 1,
+        };
+        self.last_click = Some((point, now, count));
+        self.selection = match count {
+            1 => None,
+            2 => self.word_at(&input, point),
+            _ => self.line_at(&input, point),
+        };
+        self.dragging = (count == 1).then_some((point, x as i32, y as i32));
+    }
+
+    fn drag(&mut self, dx: i32, dy: i32) {
+        let Some((anchor, x, y)) = self.dragging else { return };
+        let (x, y) = (x + dx, y + dy);
+        self.dragging = Some((anchor, x, y));
+        let head = self.point_at(x, y, self.total());
+        // A click without a move selects nothing
+        if self.selection.is_some() || head != anchor {
+            self.selection = Some((anchor, head));
+        }
+    }
+
+    // Takes in what jobs printed and gives the prompt back once the
+    // foreground one is done. Whether there's anything new to draw.
+    fn poll(&mut self) -> bool {
+        let output = self.shell.poll();
+        let mut changed = !output.is_empty();
+        for text in output {
+            self.print(&text);
+        }
+        if self.running && !self.shell.is_busy() {
+            self.running = false;
+            self.reset_pen();
+            changed = true;
+        }
+        changed
+    }
+
+    // `frame` is where the pane is on screen. The focused pane gets the
+    // search bar, to find in and highlight its matches, and the cursor.
+    fn draw(&mut self, graphics: &mut Graphics, frame: Frame, profile: &Profile, search: Option<&mut SearchBar>, cursor_shown: bool) {
+        let (x, y, width, height) = frame;
+        let scheme = profile.scheme();
+        self.cell_width = GLYPH_WIDTH * profile.scale();
+        self.scroll.row_height = profile.font_size + LINE_SPACING;
+        let (cell_width, row_height) = (self.cell_width, self.scroll.row_height);
+        self.columns = (width.saturating_sub(2 * MARGIN + SCROLLER_WIDTH) / cell_width).max(1);
+        let input = self.input_rows("");
+        let total = self.rows.len() + input.len();
+        let view_height = height.saturating_sub(2 * MARGIN);
+        let top = y + MARGIN;
+        let focused = search.is_some();
+
+        // Search covers the scrollback and the input alike
+        let (matches, current) = match search {
+            Some(search) => {
+                let matches = if search.is_visible {
+                    let lines: Vec<String> = (0..total)
+                        .filter_map(|index| self.row(&input, index))
+                        .map(|row| row.cells.iter().map(|cell| cell.ch).collect())
+                        .collect();
+                    widgets::find_matches(&lines, search.query())
+                } else {
+                    Vec::new()
+                };
+                if let Some(index) = search.update(matches.len()) {
+                    let page = self.scroll.rows_per_page(view_height);
+                    self.scroll.offset = matches[index].line.saturating_sub(page / 2);
+                    self.scroll.follow_end = false;
+                }
+                (matches, search.current())
+            }
+            _ => (Vec::new(), None),
+        };
+        let selection = self.selection.map(|(a, b)| (a.min(b), a.max(b)));
+
+        let rows = self.scroll.visible_rows(total, view_height);
+        let offset = rows.start;
+        for index in rows {
+            let Some(row) = self.row(&input, index) else { break };
+            let row_y = top + (index - offset) * row_height;
+            let cell_x = |column: usize| x + MARGIN + column * cell_width;
+
+            // Selection and matches go under the text
+            if let Some((start, end)) = selection {
+                for column in 0..row.cells.len() {
+                    let point = Point { row: index, column };
+                    if start <= point && point <= end {
+                        graphics.draw_rect(cell_x(column), row_y - 2, cell_width, row_height, scheme.selection);
+                    }
+                }
+            }
+            for (i, found) in matches.iter().enumerate().filter(|(_, found)| found.line == index) {
+                let len = found.columns.len() * profile.scale();
+                widgets::draw_match(graphics, cell_x(found.columns.start), row_y, len, Some(i) == current);
+            }
+
+            // A run of cells at a time, split where the color changes
+            let mut column = 0;
+            for run in row.cells.chunk_by(|a, b| a.paint == b.paint) {
+                let text: String = run.iter().map(|cell| cell.ch).collect();
+                graphics.draw_text_scaled(&text, cell_x(column), row_y, run[0].paint.color(scheme), profile.scale());
+                column += run.len();
+            }
+
+            // The cursor, after the typed line; hollow in the panes without
+            // focus
+            if index + 1 == total {
+                let cursor_x = cell_x(row.cells.len());
+                match profile.cursor {
+                    _ if !focused => graphics.draw_rect_outline(cursor_x, row_y - 2, cell_width, row_height - 4, scheme.foreground),
+                    _ if !cursor_shown => {}
+                    CursorStyle::Block => graphics.draw_rect(cursor_x, row_y - 2, cell_width, row_height - 4, scheme.foreground),
+                    CursorStyle::Bar => graphics.draw_rect(cursor_x, row_y - 2, 2, row_height - 4, scheme.foreground),
+                    CursorStyle::Underline => graphics.draw_rect(cursor_x, row_y + profile.font_size, cell_width, 2, scheme.foreground),
+                }
+            }
+        }
+
+        self.scroll.draw_scroller(graphics, x + width - SCROLLER_WIDTH, top, view_height, total);
+    }
+}
+
+impl Terminal {
+    fn new() -> Self {
+        let (profiles, profile) = terminal_profile::load();
+        Self {
+            panes: alloc::vec![Pane::new(&profiles[profile])],
+            layout: SplitView::new(),
+            focused: 0,
+            search: SearchBar::new(),
+            profiles,
+            profile,
+            sheet: ProfileSheet::new(),
+            divider: None,
+            size: (0, 0),
+            cursor_drawn: true,
+        }
+    }
+
+    fn current(&self) -> &Profile {
+        &self.profiles[self.profile]
+    }
+
+    fn cursor_shown(&self) -> bool {
+        !self.current().blink || (clock::millis() / BLINK_MS) % 2 == 0
+    }
+
+    // Where the panes go in the content, below the search bar when it's
+    // open
+    fn layout_frame(&self) -> Frame {
+        let top = if self.search.is_visible { SearchBar::HEIGHT } else { 0 };
+        (0, top, self.size.0, self.size.1.saturating_sub(top))
+    }
+
+    fn pane_frame(&self, pane: usize) -> Frame {
+        self.layout.frames(self.layout_frame())[pane]
+    }
+
+    fn split(&mut self, orientation: Orientation) {
+        let pane = Pane::new(self.current());
+        self.panes.push(pane);
+        self.focused = self.layout.split(self.focused, orientation);
+    }
+
+    // Closes the panes asked to, unless it's the last one left
+    fn close_panes(&mut self) {
+        while let Some(index) = self.panes.iter().position(|pane| pane.closing) {
+            if !self.layout.remove(index) {
+                self.panes[index].closing = false;
+                break;
+            }
+            self.panes.remove(index);
+            if self.focused >= index && self.focused > 0 {
+                self.focused -= 1;
+            }
+        }
+    }
+
+    fn key(&mut self, event: &KeyEvent) {
+        if self.sheet.key(event) || self.search.key(event) {
+            return;
+        }
+        let direction = match event.key {
+            Key::ArrowLeft => (-1, 0),
+            Key::ArrowRight => (1, 0),
+            Key::ArrowUp => (0, -1),
+            Key::ArrowDown => (0, 1),
+            _ => (0, 0),
+        };
+        match event.key {
+            Key::D if event.cmd => self.split(if event.shift { Orientation::Stacked } else { Orientation::SideBySide }),
+            _ if event.cmd && event.alt && direction != (0, 0) => {
+                let (dx, dy) = direction;
+                if let Some(pane) = self.layout.neighbour(self.focused, dx, dy, self.layout_frame()) {
+                    self.focused = pane;
+                }
+            }
+            _ => self.panes[self.focused].key(event),
+        }
+        self.close_panes();
+    }
+
+    // `x` and `y` are relative to the content
+    fn click(&mut self, x: usize, y: usize, width: usize) {
+        self.size.0 = width;
+        if self.sheet.is_visible {
+            self.sheet.click(x, y, width, &mut self.profiles, &mut self.profile);
+            return;
+        }
+        if self.search.click(x, y, 0, 0, width) {
+            return;
+        }
+        let frame = self.layout_frame();
+        self.divider = self.layout.divider_at(x, y, frame);
+        if self.divider.is_some() {
+            return;
+        }
+        if let Some(pane) = self.layout.pane_at(x, y, frame) {
+            self.focused = pane;
+            let (pane_x, pane_y, _, _) = self.pane_frame(pane);
+            self.panes[pane].click(x - pane_x, y - pane_y);
+        }
+    }
+
+    fn drag(&mut self, dx: i32, dy: i32) {
+        match self.divider {
+            Some(divider) => {
+                let frame = self.layout_frame();
+                self.layout.move_divider(divider, dx, dy, frame);
+            }
+            None => self.panes[self.focused].drag(dx, dy),
+        }
+    }
+}
+
+// The terminal, with its first shell started the first time it's needed
+fn with<R>(f: impl FnOnce(&mut Terminal) -> R) -> R {
+    f(TERMINAL.lock().get_or_insert_with(Terminal::new))
+}
+
+pub fn handle_key(event: &KeyEvent) {
+    with(|terminal| terminal.key(event));
+}
+
+// A character from outside the keyboard, for the search bar or the line
+pub fn insert_text(ch: char) {
+    with(|terminal| {
+        if !terminal.search.insert(ch) {
+            terminal.panes[terminal.focused].paste(ch.encode_utf8(&mut [0; 4]));
+        }
+    });
+}
+
+// `x` and `y` are relative to the window content
+pub fn click(x: usize, y: usize, width: usize) {
+    with(|terminal| terminal.click(x, y, width));
+}
+
+// Pointer movement with the button held
+pub fn drag(dx: i32, dy: i32) {
+    with(|terminal| terminal.drag(dx, dy));
+}
+
+// Terminal > Preferences…
+pub fn show_preferences() {
+    with(|terminal| terminal.sheet.is_visible = true);
+}
+
+// Keeps what's under the content before the window covers it, when the
+// background lets it show through; called as the window starts drawing
+pub fn capture_background(graphics: &Graphics, x: usize, y: usize, width: usize, height: usize) {
+    let opacity = TERMINAL.lock().as_ref().map_or(1.0, |terminal| terminal.current().opacity);
+    if opacity < 1.0 {
+        graphics.capture(&mut UNDER.lock(), x, y, width, height);
+    }
+}
+
+// Scrolls the pane with focus. `height` is the window content height, as
+// passed to `draw`.
+pub fn scroll(rows: isize, height: usize) {
+    with(|terminal| {
+        terminal.size.1 = height;
+        let (_, _, _, height) = terminal.pane_frame(terminal.focused);
+        let pane = &mut terminal.panes[terminal.focused];
+        let total = pane.total();
+        pane.scroll.scroll_by(rows, total, height.saturating_sub(2 * MARGIN));
+    });
+}
+
+// Takes in what every pane's jobs printed; called from the desktop loop.
+// Whether there's anything new to draw.
+pub fn poll() -> bool {
+    let mut terminal = TERMINAL.lock();
+    let Some(terminal) = terminal.as_mut() else { return false };
+    let mut changed = terminal.cursor_shown() != terminal.cursor_drawn;
+    for pane in terminal.panes.iter_mut() {
+        changed |= pane.poll();
+    }
+    changed
+}
+
+pub fn draw(graphics: &mut Graphics, x: usize, y: usize, width: usize, height: usize) {
+    let mut terminal = TERMINAL.lock();
+    let terminal = terminal.get_or_insert_with(Terminal::new);
+    terminal.size = (width, height);
+    let profile = terminal.current().clone();
+    let scheme = profile.scheme();
+
+    // The background, with what was behind the window showing through
+    graphics.draw_rect(x + 1, y, width - 2, height - 1, scheme.background);
+    if profile.opacity < 1.0 {
+        let under = UNDER.lock();
+        graphics.draw_faded(&under, x + 1, y, under.width(), under.height(), 1.0 - profile.opacity);
+    }
+
+    terminal.cursor_drawn = terminal.cursor_shown();
+    let (_, top, _, layout_height) = terminal.layout_frame();
+    let frame = (x, y + top, width, layout_height);
+    let frames = terminal.layout.frames(frame);
+    let focused = terminal.focused;
+    for (i, pane) in terminal.panes.iter_mut().enumerate() {
+        let search = (i == focused).then_some(&mut terminal.search);
+        pane.draw(graphics, frames[i], &profile, search, terminal.cursor_drawn);
+    }
+    terminal.layout.draw_dividers(graphics, frame, scheme.selection);
+
+    terminal.search.draw(graphics, x, y, width);
+    terminal.sheet.draw(graphics, x, y, width, &terminal.profiles, terminal.profile);
+}
@@ -0,0 +1,334 @@
+// src/terminal_profile.rs
+// Terminal profiles: named looks for the Terminal, each with a font size,
+// a background opacity, a color scheme and a cursor style. A scheme is the
+// foreground and background, the selection and the 16 ANSI colors programs
+// pick with escape codes. Each setting of each profile is its own key in
+// the com.rustos.terminal domain, as `<profile>.<setting>`, and Profile
+// names the one in use. The Preferences sheet (Terminal > Preferences…)
+// changes them, and each change is saved as it's made.
+use crate::glyph_cache::GLYPH_HEIGHT;
+use crate::graphics::{Graphics, Color};
+use crate::keyboard::{Key, KeyEvent};
+use crate::preferences;
+use crate::widgets::{Button, Checkbox, Slider};
+use crate::kwarn;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+const DOMAIN: &str = "com.rustos.terminal";
+const PROFILE_KEY: &str = "Profile";
+// Whole multiples of the font's own size
+pub const FONT_SIZES: [usize; 3] = [8, 16, 24];
+// Below this the text would be hard to tell from what's behind
+const MIN_OPACITY: f32 = 0.3;
+// Each profile starts out with the scheme of the same name
+const PROFILES: [&str; 4] = ["Basic", "Solarized Dark", "Solarized Light", "Dracula"];
+
+pub struct Scheme {
+    pub name: &'static str,
+    pub foreground: Color,
+    pub background: Color,
+    pub selection: Color,
+    // Black, red, green, yellow, blue, magenta, cyan and white, then their
+    // bright versions
+    pub ansi: [Color; 16],
+}
+
+const SOLARIZED: [Color; 16] = [
+    Color::new(7, 54, 66), Color::new(220, 50, 47), Color::new(133, 153, 0), Color::new(181, 137, 0),
+    Color::new(38, 139, 210), Color::new(211, 54, 130), Color::new(42, 161, 152), Color::new(238, 232, 213),
+    Color::new(0, 43, 54), Color::new(203, 75, 22), Color::new(88, 110, 117), Color::new(101, 123, 131),
+    Color::new(131, 148, 150), Color::new(108, 113, 196), Color::new(147, 161, 161), Color::new(253, 246, 227),
+];
+
+pub const SCHEMES: [Scheme; 4] = [
+    Scheme {
+        name: "Basic",
+        foreground: Color::WHITE,
+        background: Color::new(40, 44, 52),
+        selection: Color::BLUE,
+        ansi: [
+            Color::BLACK, Color::new(205, 49, 49), Color::GREEN, Color::new(229, 229, 16),
+            Color::new(36, 114, 200), Color::new(188, 63, 188), Color::new(17, 168, 205), Color::new(229, 229, 229),
+            Color::GRAY, Color::RED, Color::new(35, 209, 139), Color::YELLOW,
+            Color::BLUE, Color::new(214, 112, 214), Color::new(41, 184, 219), Color::WHITE,
+        ],
+    },
+    Scheme {
+        name: "Solarized Dark",
+        foreground: Color::new(131, 148, 150),
+        background: Color::new(0, 43, 54),
+        selection: Color::new(7, 54, 66),
+        ansi: SOLARIZED,
+    },
+    Scheme {
+        name: "Solarized Light",
+        foreground: Color::new(101, 123, 131),
+        background: Color::new(253, 246, 227),
+        selection: Color::new(238, 232, 213),
+        ansi: SOLARIZED,
+    },
+    Scheme {
+        name: "Dracula",
+        foreground: Color::new(248, 248, 242),
+        background: Color::new(40, 42, 54),
+        selection: Color::new(68, 71, 90),
+        ansi: [
+            Color::new(33, 34, 44), Color::new(255, 85, 85), Color::new(80, 250, 123), Color::new(241, 250, 140),
+            Color::new(189, 147, 249), Color::new(255, 121, 198), Color::new(139, 233, 253), Color::new(248, 248, 242),
+            Color::new(98, 114, 164), Color::new(255, 110, 110), Color::new(105, 255, 148), Color::new(255, 255, 165),
+            Color::new(214, 172, 255), Color::new(255, 146, 223), Color::new(164, 255, 255), Color::WHITE,
+        ],
+    },
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorStyle {
+    Block,
+    Bar,
+    Underline,
+}
+
+impl CursorStyle {
+    pub const ALL: [CursorStyle; 3] = [CursorStyle::Block, CursorStyle::Bar, CursorStyle::Underline];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CursorStyle::Block => "Block",
+            CursorStyle::Bar => "Bar",
+            CursorStyle::Underline => "Underline",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Profile {
+    pub name: &'static str,
+    // In pixels, one of FONT_SIZES
+    pub font_size: usize,
+    // Of the background, from MIN_OPACITY to 1; the text stays solid
+    pub opacity: f32,
+    // Index into SCHEMES
+    pub scheme: usize,
+    pub cursor: CursorStyle,
+    pub blink: bool,
+}
+
+impl Profile {
+    // The profile as saved, with defaults for what isn't
+    fn load(index: usize, saved: &[(String, String)]) -> Profile {
+        let name = PROFILES[index];
+        let get = |setting: &str| {
+            let key = format!("{}.{}", name, setting);
+            saved.iter().find(|(k, _)| *k == key).map(|(_, value)| value.as_str())
+        };
+        Profile {
+            name,
+            font_size: get("FontSize")
+                .and_then(|value| value.parse().ok())
+                .filter(|size| FONT_SIZES.contains(size))
+                .unwrap_or(FONT_SIZES[0]),
+            opacity: get("Opacity")
+                .and_then(|value| value.parse::<f32>().ok())
+                .map_or(1.0, |opacity| opacity.clamp(MIN_OPACITY, 1.0)),
+            scheme: get("Scheme")
+                .and_then(|value| SCHEMES.iter().position(|scheme| scheme.name == value))
+                .unwrap_or(index),
+            cursor: get("Cursor")
+                .and_then(|value| CursorStyle::ALL.into_iter().find(|style| style.name() == value))
+                .unwrap_or(CursorStyle::Block),
+            blink: get("Blink").map_or(true, |value| value == "true"),
+        }
+    }
+
+    pub fn scheme(&self) -> &'static Scheme {
+        &SCHEMES[self.scheme]
+    }
+
+    // How many times the font's own size the text is drawn
+    pub fn scale(&self) -> usize {
+        self.font_size / GLYPH_HEIGHT
+    }
+
+    fn save(&self, setting: &str, value: &str) {
+        if let Err(err) = preferences::set(DOMAIN, &format!("{}.{}", self.name, setting), value) {
+            kwarn!("terminal: cannot save {} for {}: {:?}", setting, self.name, err);
+        }
+    }
+}
+
+// Every profile, and which is in use
+pub fn load() -> (Vec<Profile>, usize) {
+    let saved = preferences::read(DOMAIN);
+    let profiles: Vec<Profile> = (0..PROFILES.len()).map(|index| Profile::load(index, &saved)).collect();
+    let current = saved
+        .iter()
+        .find(|(key, _)| key == PROFILE_KEY)
+        .and_then(|(_, name)| PROFILES.iter().position(|profile| profile == name))
+        .unwrap_or(0);
+    (profiles, current)
+}
+
+// The sheet's layout, relative to its top-left corner
+const WIDTH: usize = 420;
+const HEIGHT: usize = 200;
+const LIST_WIDTH: usize = 130;
+const ROW_HEIGHT: usize = 18;
+const LIST_Y: usize = 30;
+const SETTINGS_X: usize = LIST_WIDTH + 20;
+const FONT_Y: usize = 30;
+const OPACITY_Y: usize = 58;
+const SCHEME_Y: usize = 86;
+const CURSOR_Y: usize = 114;
+const BLINK_Y: usize = 142;
+const DONE_Y: usize = HEIGHT - 28;
+
+const SMALLER: Button = Button::new("-");
+const LARGER: Button = Button::new("+");
+const PREVIOUS: Button = Button::new("<");
+const NEXT: Button = Button::new(">");
+const DONE: Button = Button::new("Done");
+const CURSOR_BUTTONS: [Button; 3] = [Button::new("Block"), Button::new("Bar"), Button::new("Underline")];
+
+// Terminal > Preferences…, hung from the top of the Terminal's content.
+// Picking a profile puts it in use; the settings on the right are that
+// profile's.
+pub struct ProfileSheet {
+    pub is_visible: bool,
+}
+
+impl ProfileSheet {
+    pub const fn new() -> Self {
+        Self { is_visible: false }
+    }
+
+    // Where the sheet goes in content `width` wide
+    fn origin(width: usize) -> usize {
+        width.saturating_sub(WIDTH) / 2
+    }
+
+    // Where each control starts across the sheet: the label column, then
+    // the controls after it
+    fn control_x() -> usize {
+        SETTINGS_X + 72
+    }
+
+    fn opacity_slider(opacity: f32) -> Slider {
+        Slider { value: (opacity - MIN_OPACITY) / (1.0 - MIN_OPACITY), width: WIDTH - Self::control_x() - 16 }
+    }
+
+    fn cursor_button_x(index: usize) -> usize {
+        let before: usize = CURSOR_BUTTONS[..index].iter().map(|button| button.width() + 4).sum();
+        SETTINGS_X + before
+    }
+
+    pub fn draw(&self, graphics: &mut Graphics, x: usize, y: usize, width: usize, profiles: &[Profile], current: usize) {
+        if !self.is_visible {
+            return;
+        }
+        let x = x + Self::origin(width);
+        graphics.draw_rect(x, y, WIDTH, HEIGHT, Color::new(236, 236, 236));
+        graphics.draw_rect_outline(x, y, WIDTH, HEIGHT, Color::GRAY);
+        graphics.draw_text("Profiles", x + 12, y + 12, Color::GRAY);
+        for (i, profile) in profiles.iter().enumerate() {
+            let row_y = y + LIST_Y + i * ROW_HEIGHT;
+            let selected = i == current;
+            if selected {
+                graphics.draw_rect(x + 8, row_y - 4, LIST_WIDTH, ROW_HEIGHT, Color::BLUE);
+            }
+            let color = if selected { Color::WHITE } else { Color::BLACK };
+            graphics.draw_text(profile.name, x + 12, row_y, color);
+        }
+
+        let profile = &profiles[current];
+        let (label_x, control_x) = (x + SETTINGS_X, x + Self::control_x());
+        graphics.draw_text("Font", label_x, y + FONT_Y + 5, Color::BLACK);
+        SMALLER.draw(graphics, control_x, y + FONT_Y);
+        graphics.draw_text(&format!("{} px", profile.font_size), control_x + SMALLER.width() + 8, y + FONT_Y + 5, Color::BLACK);
+        LARGER.draw(graphics, control_x + SMALLER.width() + 56, y + FONT_Y);
+
+        graphics.draw_text("Opacity", label_x, y + OPACITY_Y + 2, Color::BLACK);
+        Self::opacity_slider(profile.opacity).draw(graphics, control_x, y + OPACITY_Y);
+
+        graphics.draw_text("Colors", label_x, y + SCHEME_Y + 5, Color::BLACK);
+        PREVIOUS.draw(graphics, control_x, y + SCHEME_Y);
+        NEXT.draw(graphics, control_x + PREVIOUS.width() + 4, y + SCHEME_Y);
+        let name: String = profile.scheme().name.chars().take((WIDTH - Self::control_x() - 60) / 8).collect();
+        graphics.draw_text(&name, control_x + PREVIOUS.width() + NEXT.width() + 12, y + SCHEME_Y + 5, Color::BLACK);
+
+        for (i, style) in CursorStyle::ALL.iter().enumerate() {
+            let (button, button_x) = (&CURSOR_BUTTONS[i], x + Self::cursor_button_x(i));
+            if *style == profile.cursor {
+                graphics.draw_rounded_rect(button_x, y + CURSOR_Y, button.width(), Button::HEIGHT, Color::BLUE);
+                graphics.draw_text(button.label, button_x + 8, y + CURSOR_Y + 5, Color::WHITE);
+            } else {
+                button.draw(graphics, button_x, y + CURSOR_Y);
+            }
+        }
+        let blink = Checkbox { label: "Blinking cursor", checked: profile.blink };
+        blink.draw(graphics, label_x, y + BLINK_Y);
+
+        graphics.draw_rounded_rect(x + WIDTH - 12 - DONE.width(), y + DONE_Y, DONE.width(), Button::HEIGHT, Color::BLUE);
+        graphics.draw_text(DONE.label, x + WIDTH - 4 - DONE.width(), y + DONE_Y + 5, Color::WHITE);
+    }
+
+    // `px` and `py` are relative to the content, like the sheet's `draw`
+    // position. The sheet is modal, so clicks outside it do nothing.
+    pub fn click(&mut self, px: usize, py: usize, width: usize, profiles: &mut [Profile], current: &mut usize) {
+        let x = Self::origin(width);
+        if px < x || px >= x + WIDTH || py >= HEIGHT {
+            return;
+        }
+        let px = px - x;
+        if px < LIST_WIDTH + 8 {
+            let row = py.saturating_sub(LIST_Y - 4) / ROW_HEIGHT;
+            if py >= LIST_Y - 4 && row < profiles.len() && row != *current {
+                *current = row;
+                if let Err(err) = preferences::set(DOMAIN, PROFILE_KEY, profiles[row].name) {
+                    kwarn!("terminal: cannot save the profile in use: {:?}", err);
+                }
+            }
+            return;
+        }
+        let profile = &mut profiles[*current];
+        let control_x = Self::control_x();
+        let size = FONT_SIZES.iter().position(|&size| size == profile.font_size).unwrap_or(0);
+        if SMALLER.contains(px, py, control_x, FONT_Y) && size > 0 {
+            profile.font_size = FONT_SIZES[size - 1];
+            profile.save("FontSize", &format!("{}", profile.font_size));
+        } else if LARGER.contains(px, py, control_x + SMALLER.width() + 56, FONT_Y) && size + 1 < FONT_SIZES.len() {
+            profile.font_size = FONT_SIZES[size + 1];
+            profile.save("FontSize", &format!("{}", profile.font_size));
+        } else if Self::opacity_slider(profile.opacity).contains(px, py, control_x, OPACITY_Y) {
+            let slider = Self::opacity_slider(profile.opacity);
+            profile.opacity = MIN_OPACITY + slider.value_at(px, control_x) * (1.0 - MIN_OPACITY);
+            profile.save("Opacity", &format!("{:.2}", profile.opacity));
+        } else if PREVIOUS.contains(px, py, control_x, SCHEME_Y) {
+            profile.scheme = (profile.scheme + SCHEMES.len() - 1) % SCHEMES.len();
+            profile.save("Scheme", profile.scheme().name);
+        } else if NEXT.contains(px, py, control_x + PREVIOUS.width() + 4, SCHEME_Y) {
+            profile.scheme = (profile.scheme + 1) % SCHEMES.len();
+            profile.save("Scheme", profile.scheme().name);
+        } else if let Some(i) = (0..CURSOR_BUTTONS.len()).find(|&i| CURSOR_BUTTONS[i].contains(px, py, Self::cursor_button_x(i), CURSOR_Y)) {
+            profile.cursor = CursorStyle::ALL[i];
+            profile.save("Cursor", profile.cursor.name());
+        } else if Checkbox::new("Blinking cursor").contains(px, py, SETTINGS_X, BLINK_Y) {
+            profile.blink = !profile.blink;
+            profile.save("Blink", if profile.blink { "true" } else { "false" });
+        } else if DONE.contains(px, py, WIDTH - 12 - DONE.width(), DONE_Y) {
+            self.is_visible = false;
+        }
+    }
+
+    // Enter and Escape close it; whether the key was used
+    pub fn key(&mut self, event: &KeyEvent) -> bool {
+        if !self.is_visible {
+            return false;
+        }
+        if matches!(event.key, Key::Enter | Key::Escape) {
+            self.is_visible = false;
+        }
+        true
+    }
+}
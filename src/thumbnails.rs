@@ -0,0 +1,164 @@
+// src/thumbnails.rs
+// Thumbnails for the Finder's icon view: an image scaled down to fit, or a
+// miniature of a text file's first page with a dot for every character.
+// Asking for one that isn't made yet queues it and the caller draws the
+// plain icon meanwhile; `poll` makes a few each frame, so a folder full of
+// images opens without stalling the desktop. Thumbnails are kept in memory
+// by path and modification time, so a changed file gets a new one, and
+// once MAX_THUMBNAILS are kept the least recently drawn goes. Files that
+// can't have one are remembered too, so they're only read once.
+use crate::bmp::{self, Image};
+use crate::graphics::{Graphics, Color};
+use crate::preview;
+use crate::quick_look;
+use crate::vfs;
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::sync::Mutex;
+
+// Thumbnails fit in a square this size
+pub const SIZE: usize = 28;
+const MAX_THUMBNAILS: usize = 64;
+// Made per frame
+const BATCH: usize = 1;
+// Read from the start of a text file for its first page
+const PAGE_BYTES: usize = 1024;
+// The page, a pixel per character and two per line
+const PAGE_WIDTH: usize = 22;
+const PAGE_MARGIN: usize = 2;
+
+struct Thumbnail {
+    modified: u64,
+    // None for files that can't have one
+    image: Option<Image>,
+    // When it was last drawn, for eviction
+    used: u64,
+}
+
+struct Cache {
+    thumbnails: BTreeMap<String, Thumbnail>,
+    // Asked for and not made yet, oldest first
+    pending: VecDeque<(String, u64)>,
+    clock: u64,
+}
+
+static CACHE: Mutex<Cache> = Mutex::new(Cache { thumbnails: BTreeMap::new(), pending: VecDeque::new(), clock: 0 });
+
+// Draws the thumbnail of `path`, centered in the square at `x` and `y`.
+// False when there's none to draw yet, after asking for it, or none at all.
+pub fn draw(graphics: &mut Graphics, path: &str, modified: u64, x: usize, y: usize) -> bool {
+    let mut cache = CACHE.lock();
+    cache.clock += 1;
+    let clock = cache.clock;
+    match cache.thumbnails.get_mut(path) {
+        Some(thumbnail) if thumbnail.modified == modified => {
+            thumbnail.used = clock;
+            let image = match &thumbnail.image {
+                Some(image) => image,
+                None => return false,
+            };
+            let left = x + (SIZE - image.width) / 2;
+            let top = y + (SIZE - image.height) / 2;
+            for iy in 0..image.height {
+                for ix in 0..image.width {
+                    graphics.set_pixel(left + ix, top + iy, image.pixel(ix, iy));
+                }
+            }
+            true
+        }
+        _ => {
+            if !cache.pending.iter().any(|(pending, _)| pending == path) {
+                cache.pending.push_back((String::from(path), modified));
+            }
+            false
+        }
+    }
+}
+
+// Makes the next few thumbnails asked for; whether any were made, so the
+// Finder gets drawn again
+pub fn poll() -> bool {
+    let mut made = false;
+    for _ in 0..BATCH {
+        // Not held while the file is read and scaled
+        let (path, modified) = match CACHE.lock().pending.pop_front() {
+            Some(request) => request,
+            None => break,
+        };
+        let image = render(&path);
+        let mut cache = CACHE.lock();
+        if cache.thumbnails.len() >= MAX_THUMBNAILS && !cache.thumbnails.contains_key(&path) {
+            let oldest = cache.thumbnails.iter().min_by_key(|(_, thumbnail)| thumbnail.used).map(|(path, _)| path.clone());
+            if let Some(oldest) = oldest {
+                cache.thumbnails.remove(&oldest);
+            }
+        }
+        let used = cache.clock;
+        cache.thumbnails.insert(path, Thumbnail { modified, image, used });
+        made = true;
+    }
+    made
+}
+
+// Drops every thumbnail, to be made again when next drawn
+pub fn purge() {
+    let mut cache = CACHE.lock();
+    cache.thumbnails.clear();
+    cache.pending.clear();
+}
+
+fn render(path: &str) -> Option<Image> {
+    if preview::is_image(path) {
+        let image = bmp::decode(&vfs::read_file(path).ok()?).ok()?;
+        return Some(scale(&image));
+    }
+    let mut buf = vec![0; PAGE_BYTES];
+    let read = vfs::read(path, 0, &mut buf).ok()?;
+    buf.truncate(read);
+    if !quick_look::is_text(&buf) {
+        return None;
+    }
+    Some(page(&String::from_utf8_lossy(&buf)))
+}
+
+// Fitted to the square, never enlarged
+fn scale(image: &Image) -> Image {
+    let (width, height) = if image.width <= SIZE && image.height <= SIZE {
+        (image.width, image.height)
+    } else if image.width > image.height {
+        (SIZE, (image.height * SIZE / image.width).max(1))
+    } else {
+        ((image.width * SIZE / image.height).max(1), SIZE)
+    };
+    let mut pixels = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            pixels.push(image.pixel(x * image.width / width, y * image.height / height));
+        }
+    }
+    Image::from_pixels(width, height, pixels)
+}
+
+// A white page with a dark dot for each character that isn't a space
+fn page(text: &str) -> Image {
+    let mut pixels = vec![Color::WHITE; PAGE_WIDTH * SIZE];
+    for (row, line) in text.lines().take((SIZE - 2 * PAGE_MARGIN) / 2).enumerate() {
+        let y = PAGE_MARGIN + row * 2;
+        for (col, ch) in line.chars().take(PAGE_WIDTH - 2 * PAGE_MARGIN).enumerate() {
+            if !ch.is_whitespace() {
+                pixels[y * PAGE_WIDTH + PAGE_MARGIN + col] = Color::DARK_GRAY;
+            }
+        }
+    }
+    // The page's edge
+    for y in 0..SIZE {
+        for x in 0..PAGE_WIDTH {
+            if x == 0 || y == 0 || x == PAGE_WIDTH - 1 || y == SIZE - 1 {
+                pixels[y * PAGE_WIDTH + x] = Color::GRAY;
+            }
+        }
+    }
+    Image::from_pixels(PAGE_WIDTH, SIZE, pixels)
+}
\ No newline at end of file
@@ -0,0 +1,235 @@
+// src/tls.rs
+// TLS client connections over TCP sockets, using rustls' unbuffered API so
+// nothing blocks: `poll` moves whatever has arrived through the handshake
+// or decryption and sends what rustls produced. Servers are checked
+// against the Mozilla root store bundled from webpki-roots, with the time
+// taken from the RTC.
+use crate::net::NetError;
+use crate::rtc;
+use crate::socket::{self, Handle};
+use crate::tls_provider;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::time::Duration;
+use rustls::client::UnbufferedClientConnection;
+use rustls::pki_types::{ServerName, UnixTime};
+use rustls::time_provider::TimeProvider;
+use rustls::unbuffered::{ConnectionState, EncodeError, EncryptError, InsufficientSizeError, UnbufferedStatus};
+use rustls::{CertificateError, ClientConfig, RootCertStore};
+use crate::sync::Mutex;
+
+// Room for one full record; rustls asks for more when it needs it
+const RECORD_SPACE: usize = 16 * 1024 + 256;
+
+#[derive(Debug)]
+pub enum TlsError {
+    Net(NetError),
+    // The server's certificate didn't check out
+    Certificate(CertificateError),
+    Protocol(rustls::Error),
+    BadServerName,
+}
+
+impl From<NetError> for TlsError {
+    fn from(err: NetError) -> Self {
+        TlsError::Net(err)
+    }
+}
+
+impl From<rustls::Error> for TlsError {
+    fn from(err: rustls::Error) -> Self {
+        match err {
+            rustls::Error::InvalidCertificate(err) => TlsError::Certificate(err),
+            err => TlsError::Protocol(err),
+        }
+    }
+}
+
+// What's wrong with a certificate, in words for a warning page
+pub fn certificate_problem(err: &CertificateError) -> String {
+    match err {
+        CertificateError::Expired | CertificateError::ExpiredContext { .. } => {
+            String::from("The certificate has expired, or the system clock is wrong.")
+        }
+        CertificateError::NotValidYet | CertificateError::NotValidYetContext { .. } => {
+            String::from("The certificate isn't valid yet, or the system clock is wrong.")
+        }
+        CertificateError::NotValidForName | CertificateError::NotValidForNameContext { .. } => {
+            String::from("The certificate belongs to a different website.")
+        }
+        CertificateError::UnknownIssuer => String::from("The certificate wasn't issued by a trusted certificate authority."),
+        CertificateError::Revoked => String::from("The certificate has been revoked."),
+        CertificateError::BadSignature => String::from("The certificate's signature is invalid."),
+        CertificateError::UnsupportedSignatureAlgorithmContext { .. }
+        | CertificateError::UnsupportedSignatureAlgorithmForPublicKeyContext { .. } => {
+            String::from("The certificate is signed with an algorithm RustOS can't check.")
+        }
+        err => err.to_string(),
+    }
+}
+
+#[derive(Debug)]
+struct RtcTime;
+
+impl TimeProvider for RtcTime {
+    fn current_time(&self) -> Option<UnixTime> {
+        Some(UnixTime::since_unix_epoch(Duration::from_secs(rtc::unix_time())))
+    }
+}
+
+// Built on first use: parsing the root store takes a moment
+static CONFIG: Mutex<Option<Arc<ClientConfig>>> = Mutex::new(None);
+
+fn config() -> Result<Arc<ClientConfig>, TlsError> {
+    let mut config = CONFIG.lock();
+    if let Some(config) = config.as_ref() {
+        return Ok(config.clone());
+    }
+    let roots = RootCertStore { roots: webpki_roots::TLS_SERVER_ROOTS.to_vec() };
+    let built = Arc::new(
+        ClientConfig::builder_with_details(Arc::new(tls_provider::provider()), Arc::new(RtcTime))
+            .with_protocol_versions(&[&rustls::version::TLS13])?
+            .with_root_certificates(roots)
+            .with_no_client_auth(),
+    );
+    *config = Some(built.clone());
+    Ok(built)
+}
+
+pub struct TlsStream {
+    socket: Handle,
+    connection: UnbufferedClientConnection,
+    // Records received and not yet processed
+    incoming: Vec<u8>,
+    // Records produced and not yet taken by the socket
+    outgoing: Vec<u8>,
+    // Application data waiting to be encrypted, until the handshake is done
+    to_send: Vec<u8>,
+    received: Vec<u8>,
+    established: bool,
+    // The server sent close_notify, or the TCP connection ended
+    peer_closed: bool,
+}
+
+impl TlsStream {
+    // Starts a handshake with `host` over a TCP connection, which may still
+    // be connecting. The stream owns the socket from here on.
+    pub fn new(socket: Handle, host: &str) -> Result<Self, TlsError> {
+        let name = ServerName::try_from(String::from(host)).map_err(|_| TlsError::BadServerName)?;
+        let connection = UnbufferedClientConnection::new(config()?, name)?;
+        Ok(Self {
+            socket,
+            connection,
+            incoming: Vec::new(),
+            outgoing: Vec::new(),
+            to_send: Vec::new(),
+            received: Vec::new(),
+            established: false,
+            peer_closed: false,
+        })
+    }
+    
+    pub fn socket(&self) -> Handle {
+        self.socket
+    }
+    
+    // Nothing more to read
+    pub fn is_closed(&self) -> bool {
+        self.peer_closed && self.received.is_empty()
+    }
+    
+    // Queues data to go out encrypted once the handshake is done
+    pub fn write(&mut self, data: &[u8]) {
+        self.to_send.extend_from_slice(data);
+    }
+    
+    // Decrypted data received so far
+    pub fn read(&mut self) -> Vec<u8> {
+        core::mem::take(&mut self.received)
+    }
+    
+    // Call after `net::poll`
+    pub fn poll(&mut self) -> Result<(), TlsError> {
+        match socket::recv(self.socket) {
+            Ok(data) => self.incoming.extend_from_slice(&data),
+            // A server that drops the connection without close_notify has
+            // still sent everything; HTTP frames its responses anyway
+            Err(NetError::Closed) if self.established => self.peer_closed = true,
+            Err(err) => return Err(err.into()),
+        }
+        self.process()?;
+        if !self.outgoing.is_empty() {
+            let sent = socket::send(self.socket, &self.outgoing)?;
+            self.outgoing.drain(..sent);
+        }
+        Ok(())
+    }
+    
+    // Runs rustls until it needs more input or has nothing to send
+    fn process(&mut self) -> Result<(), TlsError> {
+        loop {
+            let UnbufferedStatus { mut discard, state } = self.connection.process_tls_records(&mut self.incoming);
+            let mut progress = true;
+            match state? {
+                ConnectionState::ReadTraffic(mut traffic) => {
+                    while let Some(record) = traffic.next_record() {
+                        let record = record?;
+                        discard += record.discard;
+                        self.received.extend_from_slice(record.payload);
+                    }
+                }
+                ConnectionState::EncodeTlsData(mut data) => {
+                    let start = self.outgoing.len();
+                    self.outgoing.resize(start + RECORD_SPACE, 0);
+                    let len = loop {
+                        match data.encode(&mut self.outgoing[start..]) {
+                            Ok(len) => break len,
+                            Err(EncodeError::InsufficientSize(InsufficientSizeError { required_size })) => {
+                                self.outgoing.resize(start + required_size, 0);
+                            }
+                            Err(err) => return Err(TlsError::Protocol(rustls::Error::General(err.to_string()))),
+                        }
+                    };
+                    self.outgoing.truncate(start + len);
+                }
+                // What was encoded goes out at the end of `poll`
+                ConnectionState::TransmitTlsData(data) => data.done(),
+                ConnectionState::WriteTraffic(mut traffic) => {
+                    self.established = true;
+                    if self.to_send.is_empty() {
+                        progress = false;
+                    } else {
+                        let mut space = vec![0; self.to_send.len() + RECORD_SPACE];
+                        let len = loop {
+                            match traffic.encrypt(&self.to_send, &mut space) {
+                                Ok(len) => break len,
+                                Err(EncryptError::InsufficientSize(InsufficientSizeError { required_size })) => {
+                                    space.resize(required_size, 0);
+                                }
+                                Err(err) => return Err(TlsError::Protocol(rustls::Error::General(err.to_string()))),
+                            }
+                        };
+                        self.outgoing.extend_from_slice(&space[..len]);
+                        self.to_send.clear();
+                    }
+                }
+                ConnectionState::PeerClosed | ConnectionState::Closed => {
+                    self.peer_closed = true;
+                    progress = false;
+                }
+                // Waiting for more records from the server
+                _ => progress = false,
+            }
+            self.incoming.drain(..discard);
+            if !progress {
+                return Ok(());
+            }
+        }
+    }
+    
+    pub fn close(self) {
+        socket::close(self.socket);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,492 @@
+// src/tls_provider.rs
+// The cryptography behind rustls, built from the RustCrypto crates since
+// rustls' own providers need std or C libraries. Covers what TLS 1.3
+// servers ask for in practice: X25519 and P-256 key exchange, AES-GCM and
+// ChaCha20-Poly1305, and ECDSA P-256 and RSA certificates. P-384 and
+// Ed25519 signatures aren't supported, so chains that need them fail
+// verification. Randomness comes from the kernel RNG, which the entropy
+// module seeds.
+use crate::rand::{self, KernelRng};
+use aes_gcm::aead::generic_array::GenericArray;
+use aes_gcm::aead::{AeadInPlace, KeyInit};
+use aes_gcm::{Aes128Gcm, Aes256Gcm};
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use chacha20poly1305::ChaCha20Poly1305;
+use core::marker::PhantomData;
+use hmac::digest::core_api::BlockSizeUser;
+use hmac::{Mac, SimpleHmac};
+use p256::ecdsa::signature::hazmat::PrehashVerifier;
+use rsa::pkcs1::DecodeRsaPublicKey;
+use rsa::{Pkcs1v15Sign, Pss, RsaPublicKey};
+use rustls::crypto::cipher::{
+    make_tls13_aad, AeadKey, InboundOpaqueMessage, InboundPlainMessage, Iv, MessageDecrypter, MessageEncrypter, Nonce,
+    OutboundOpaqueMessage, OutboundPlainMessage, PrefixedPayload, Tls13AeadAlgorithm, UnsupportedOperationError,
+};
+use rustls::crypto::hash::{self, HashAlgorithm};
+use rustls::crypto::tls13::HkdfUsingHmac;
+use rustls::crypto::{
+    self, ActiveKeyExchange, CipherSuiteCommon, CryptoProvider, GetRandomFailed, KeyProvider, SecureRandom, SharedSecret,
+    SupportedKxGroup, WebPkiSupportedAlgorithms,
+};
+use rustls::pki_types::{alg_id, AlgorithmIdentifier, InvalidSignature, PrivateKeyDer, SignatureVerificationAlgorithm};
+use rustls::sign::SigningKey;
+use rustls::{
+    CipherSuite, ConnectionTrafficSecrets, ContentType, Error, NamedGroup, PeerMisbehaved, ProtocolVersion, SignatureScheme,
+    SupportedCipherSuite, Tls13CipherSuite,
+};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+// Hashing
+
+struct Hash<D>(HashAlgorithm, PhantomData<fn() -> D>);
+
+struct HashContext<D>(D);
+
+impl<D: Digest + Clone + Send + Sync + 'static> hash::Hash for Hash<D> {
+    fn start(&self) -> Box<dyn hash::Context> {
+        Box::new(HashContext(D::new()))
+    }
+    
+    fn hash(&self, data: &[u8]) -> hash::Output {
+        hash::Output::new(&D::digest(data))
+    }
+    
+    fn output_len(&self) -> usize {
+        <D as Digest>::output_size()
+    }
+    
+    fn algorithm(&self) -> HashAlgorithm {
+        self.0
+    }
+}
+
+impl<D: Digest + Clone + Send + Sync + 'static> hash::Context for HashContext<D> {
+    fn fork_finish(&self) -> hash::Output {
+        hash::Output::new(&self.0.clone().finalize())
+    }
+    
+    fn fork(&self) -> Box<dyn hash::Context> {
+        Box::new(HashContext(self.0.clone()))
+    }
+    
+    fn finish(self: Box<Self>) -> hash::Output {
+        hash::Output::new(&self.0.finalize())
+    }
+    
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+}
+
+static SHA256: Hash<Sha256> = Hash(HashAlgorithm::SHA256, PhantomData);
+static SHA384: Hash<Sha384> = Hash(HashAlgorithm::SHA384, PhantomData);
+
+// HMAC, and HKDF on top of it for the TLS 1.3 key schedule
+
+struct Hmac<D>(PhantomData<fn() -> D>);
+
+struct HmacKey<D: Digest + BlockSizeUser>(SimpleHmac<D>);
+
+impl<D: Digest + BlockSizeUser + Clone + Send + Sync + 'static> crypto::hmac::Hmac for Hmac<D> {
+    fn with_key(&self, key: &[u8]) -> Box<dyn crypto::hmac::Key> {
+        // HMAC takes keys of any length
+        Box::new(HmacKey(<SimpleHmac<D> as Mac>::new_from_slice(key).unwrap()))
+    }
+    
+    fn hash_output_len(&self) -> usize {
+        <D as Digest>::output_size()
+    }
+}
+
+impl<D: Digest + BlockSizeUser + Clone + Send + Sync + 'static> crypto::hmac::Key for HmacKey<D> {
+    fn sign_concat(&self, first: &[u8], middle: &[&[u8]], last: &[u8]) -> crypto::hmac::Tag {
+        let mut mac = self.0.clone();
+        mac.update(first);
+        for part in middle {
+            mac.update(part);
+        }
+        mac.update(last);
+        crypto::hmac::Tag::new(&mac.finalize().into_bytes())
+    }
+    
+    fn tag_len(&self) -> usize {
+        <D as Digest>::output_size()
+    }
+}
+
+static HMAC_SHA256: Hmac<Sha256> = Hmac(PhantomData);
+static HMAC_SHA384: Hmac<Sha384> = Hmac(PhantomData);
+static HKDF_SHA256: HkdfUsingHmac<'static> = HkdfUsingHmac(&HMAC_SHA256);
+static HKDF_SHA384: HkdfUsingHmac<'static> = HkdfUsingHmac(&HMAC_SHA384);
+
+// Record protection. TLS 1.3 records carry their real content type as the
+// last byte of the plaintext; all three ciphers use 12-byte nonces and
+// 16-byte tags.
+
+const TAG_LEN: usize = 16;
+
+struct Aead<C>(PhantomData<fn() -> C>);
+
+struct Tls13Cipher<C> {
+    cipher: C,
+    iv: Iv,
+}
+
+impl<C: AeadInPlace + KeyInit + Send + Sync + 'static> Tls13AeadAlgorithm for Aead<C> {
+    fn encrypter(&self, key: AeadKey, iv: Iv) -> Box<dyn MessageEncrypter> {
+        Box::new(Tls13Cipher { cipher: C::new_from_slice(key.as_ref()).unwrap(), iv })
+    }
+    
+    fn decrypter(&self, key: AeadKey, iv: Iv) -> Box<dyn MessageDecrypter> {
+        Box::new(Tls13Cipher { cipher: C::new_from_slice(key.as_ref()).unwrap(), iv })
+    }
+    
+    fn key_len(&self) -> usize {
+        C::key_size()
+    }
+    
+    // Only needed to hand keys to kernel TLS offload
+    fn extract_keys(&self, _key: AeadKey, _iv: Iv) -> Result<ConnectionTrafficSecrets, UnsupportedOperationError> {
+        Err(UnsupportedOperationError)
+    }
+}
+
+impl<C: AeadInPlace + Send + Sync> MessageEncrypter for Tls13Cipher<C> {
+    fn encrypt(&mut self, msg: OutboundPlainMessage<'_>, seq: u64) -> Result<OutboundOpaqueMessage, Error> {
+        let total_len = self.encrypted_payload_len(msg.payload.len());
+        let mut payload = PrefixedPayload::with_capacity(total_len);
+        payload.extend_from_chunks(&msg.payload);
+        payload.extend_from_slice(&msg.typ.to_array());
+        let nonce = Nonce::new(&self.iv, seq).0;
+        let tag = self
+            .cipher
+            .encrypt_in_place_detached(GenericArray::from_slice(&nonce), &make_tls13_aad(total_len), payload.as_mut())
+            .map_err(|_| Error::EncryptError)?;
+        payload.extend_from_slice(&tag);
+        Ok(OutboundOpaqueMessage::new(ContentType::ApplicationData, ProtocolVersion::TLSv1_2, payload))
+    }
+    
+    fn encrypted_payload_len(&self, payload_len: usize) -> usize {
+        payload_len + 1 + TAG_LEN
+    }
+}
+
+impl<C: AeadInPlace + Send + Sync> MessageDecrypter for Tls13Cipher<C> {
+    fn decrypt<'a>(&mut self, mut msg: InboundOpaqueMessage<'a>, seq: u64) -> Result<InboundPlainMessage<'a>, Error> {
+        let payload = &mut msg.payload;
+        if payload.len() < TAG_LEN {
+            return Err(Error::DecryptError);
+        }
+        let len = payload.len() - TAG_LEN;
+        let nonce = Nonce::new(&self.iv, seq).0;
+        let aad = make_tls13_aad(payload.len());
+        let (data, tag) = payload.split_at_mut(len);
+        self.cipher
+            .decrypt_in_place_detached(GenericArray::from_slice(&nonce), &aad, data, GenericArray::from_slice(tag))
+            .map_err(|_| Error::DecryptError)?;
+        payload.truncate(len);
+        msg.into_tls13_unpadded_message()
+    }
+}
+
+static AES_128_GCM: Aead<Aes128Gcm> = Aead(PhantomData);
+static AES_256_GCM: Aead<Aes256Gcm> = Aead(PhantomData);
+static CHACHA20_POLY1305: Aead<ChaCha20Poly1305> = Aead(PhantomData);
+
+// Confidentiality limits are the record counts RFC 8446 recommends
+static TLS13_AES_128_GCM_SHA256: Tls13CipherSuite = Tls13CipherSuite {
+    common: CipherSuiteCommon {
+        suite: CipherSuite::TLS13_AES_128_GCM_SHA256,
+        hash_provider: &SHA256,
+        confidentiality_limit: 1 << 24,
+    },
+    hkdf_provider: &HKDF_SHA256,
+    aead_alg: &AES_128_GCM,
+    quic: None,
+};
+
+static TLS13_AES_256_GCM_SHA384: Tls13CipherSuite = Tls13CipherSuite {
+    common: CipherSuiteCommon {
+        suite: CipherSuite::TLS13_AES_256_GCM_SHA384,
+        hash_provider: &SHA384,
+        confidentiality_limit: 1 << 24,
+    },
+    hkdf_provider: &HKDF_SHA384,
+    aead_alg: &AES_256_GCM,
+    quic: None,
+};
+
+static TLS13_CHACHA20_POLY1305_SHA256: Tls13CipherSuite = Tls13CipherSuite {
+    common: CipherSuiteCommon {
+        suite: CipherSuite::TLS13_CHACHA20_POLY1305_SHA256,
+        hash_provider: &SHA256,
+        confidentiality_limit: u64::MAX,
+    },
+    hkdf_provider: &HKDF_SHA256,
+    aead_alg: &CHACHA20_POLY1305,
+    quic: None,
+};
+
+// Key exchange
+
+#[derive(Debug)]
+struct X25519;
+
+struct X25519Exchange {
+    secret: x25519_dalek::EphemeralSecret,
+    public: x25519_dalek::PublicKey,
+}
+
+impl SupportedKxGroup for X25519 {
+    fn start(&self) -> Result<Box<dyn ActiveKeyExchange>, Error> {
+        let secret = x25519_dalek::EphemeralSecret::random_from_rng(KernelRng);
+        let public = x25519_dalek::PublicKey::from(&secret);
+        Ok(Box::new(X25519Exchange { secret, public }))
+    }
+    
+    fn name(&self) -> NamedGroup {
+        NamedGroup::X25519
+    }
+}
+
+impl ActiveKeyExchange for X25519Exchange {
+    fn complete(self: Box<Self>, peer_pub_key: &[u8]) -> Result<SharedSecret, Error> {
+        let peer: [u8; 32] = peer_pub_key.try_into().map_err(|_| PeerMisbehaved::InvalidKeyShare)?;
+        let shared = self.secret.diffie_hellman(&x25519_dalek::PublicKey::from(peer));
+        // A low-order point from the peer gives an all-zero secret
+        if !shared.was_contributory() {
+            return Err(PeerMisbehaved::InvalidKeyShare.into());
+        }
+        Ok(SharedSecret::from(&shared.as_bytes()[..]))
+    }
+    
+    fn pub_key(&self) -> &[u8] {
+        self.public.as_bytes()
+    }
+    
+    fn group(&self) -> NamedGroup {
+        NamedGroup::X25519
+    }
+}
+
+#[derive(Debug)]
+struct P256;
+
+struct P256Exchange {
+    secret: p256::ecdh::EphemeralSecret,
+    // Uncompressed SEC1 point
+    public: Vec<u8>,
+}
+
+impl SupportedKxGroup for P256 {
+    fn start(&self) -> Result<Box<dyn ActiveKeyExchange>, Error> {
+        let secret = p256::ecdh::EphemeralSecret::random(&mut KernelRng);
+        let public = p256::EncodedPoint::from(secret.public_key()).as_bytes().to_vec();
+        Ok(Box::new(P256Exchange { secret, public }))
+    }
+    
+    fn name(&self) -> NamedGroup {
+        NamedGroup::secp256r1
+    }
+}
+
+impl ActiveKeyExchange for P256Exchange {
+    fn complete(self: Box<Self>, peer_pub_key: &[u8]) -> Result<SharedSecret, Error> {
+        let peer = p256::PublicKey::from_sec1_bytes(peer_pub_key).map_err(|_| PeerMisbehaved::InvalidKeyShare)?;
+        Ok(SharedSecret::from(&self.secret.diffie_hellman(&peer).raw_secret_bytes()[..]))
+    }
+    
+    fn pub_key(&self) -> &[u8] {
+        &self.public
+    }
+    
+    fn group(&self) -> NamedGroup {
+        NamedGroup::secp256r1
+    }
+}
+
+static KX_X25519: X25519 = X25519;
+static KX_P256: P256 = P256;
+
+// Signature verification, for certificates and the server's handshake
+// signature. `public_key` is the certificate's subjectPublicKey: a SEC1
+// point for ECDSA, a PKCS#1 RSAPublicKey for RSA.
+
+#[derive(Debug, Clone, Copy)]
+enum Scheme {
+    EcdsaP256,
+    RsaPkcs1,
+    RsaPss,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum SignatureHash {
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl SignatureHash {
+    fn digest(self, message: &[u8]) -> Vec<u8> {
+        match self {
+            SignatureHash::Sha256 => Sha256::digest(message).to_vec(),
+            SignatureHash::Sha384 => Sha384::digest(message).to_vec(),
+            SignatureHash::Sha512 => Sha512::digest(message).to_vec(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Verifier {
+    scheme: Scheme,
+    hash: SignatureHash,
+    public_key_alg: AlgorithmIdentifier,
+    signature_alg: AlgorithmIdentifier,
+}
+
+impl SignatureVerificationAlgorithm for Verifier {
+    fn verify_signature(&self, public_key: &[u8], message: &[u8], signature: &[u8]) -> Result<(), InvalidSignature> {
+        let hashed = self.hash.digest(message);
+        match self.scheme {
+            Scheme::EcdsaP256 => {
+                let key = p256::ecdsa::VerifyingKey::from_sec1_bytes(public_key).map_err(|_| InvalidSignature)?;
+                let signature = p256::ecdsa::DerSignature::try_from(signature).map_err(|_| InvalidSignature)?;
+                key.verify_prehash(&hashed, &signature).map_err(|_| InvalidSignature)
+            }
+            Scheme::RsaPkcs1 | Scheme::RsaPss => {
+                let key = RsaPublicKey::from_pkcs1_der(public_key).map_err(|_| InvalidSignature)?;
+                let result = match (self.scheme, self.hash) {
+                    (Scheme::RsaPkcs1, SignatureHash::Sha256) => key.verify(Pkcs1v15Sign::new::<Sha256>(), &hashed, signature),
+                    (Scheme::RsaPkcs1, SignatureHash::Sha384) => key.verify(Pkcs1v15Sign::new::<Sha384>(), &hashed, signature),
+                    (Scheme::RsaPkcs1, SignatureHash::Sha512) => key.verify(Pkcs1v15Sign::new::<Sha512>(), &hashed, signature),
+                    (_, SignatureHash::Sha256) => key.verify(Pss::new::<Sha256>(), &hashed, signature),
+                    (_, SignatureHash::Sha384) => key.verify(Pss::new::<Sha384>(), &hashed, signature),
+                    (_, SignatureHash::Sha512) => key.verify(Pss::new::<Sha512>(), &hashed, signature),
+                };
+                result.map_err(|_| InvalidSignature)
+            }
+        }
+    }
+    
+    fn public_key_alg_id(&self) -> AlgorithmIdentifier {
+        self.public_key_alg
+    }
+    
+    fn signature_alg_id(&self) -> AlgorithmIdentifier {
+        self.signature_alg
+    }
+}
+
+static ECDSA_P256_SHA256: Verifier = Verifier {
+    scheme: Scheme::EcdsaP256,
+    hash: SignatureHash::Sha256,
+    public_key_alg: alg_id::ECDSA_P256,
+    signature_alg: alg_id::ECDSA_SHA256,
+};
+static ECDSA_P256_SHA384: Verifier = Verifier {
+    scheme: Scheme::EcdsaP256,
+    hash: SignatureHash::Sha384,
+    public_key_alg: alg_id::ECDSA_P256,
+    signature_alg: alg_id::ECDSA_SHA384,
+};
+static RSA_PKCS1_SHA256: Verifier = Verifier {
+    scheme: Scheme::RsaPkcs1,
+    hash: SignatureHash::Sha256,
+    public_key_alg: alg_id::RSA_ENCRYPTION,
+    signature_alg: alg_id::RSA_PKCS1_SHA256,
+};
+static RSA_PKCS1_SHA384: Verifier = Verifier {
+    scheme: Scheme::RsaPkcs1,
+    hash: SignatureHash::Sha384,
+    public_key_alg: alg_id::RSA_ENCRYPTION,
+    signature_alg: alg_id::RSA_PKCS1_SHA384,
+};
+static RSA_PKCS1_SHA512: Verifier = Verifier {
+    scheme: Scheme::RsaPkcs1,
+    hash: SignatureHash::Sha512,
+    public_key_alg: alg_id::RSA_ENCRYPTION,
+    signature_alg: alg_id::RSA_PKCS1_SHA512,
+};
+static RSA_PSS_SHA256: Verifier = Verifier {
+    scheme: Scheme::RsaPss,
+    hash: SignatureHash::Sha256,
+    public_key_alg: alg_id::RSA_ENCRYPTION,
+    signature_alg: alg_id::RSA_PSS_SHA256,
+};
+static RSA_PSS_SHA384: Verifier = Verifier {
+    scheme: Scheme::RsaPss,
+    hash: SignatureHash::Sha384,
+    public_key_alg: alg_id::RSA_ENCRYPTION,
+    signature_alg: alg_id::RSA_PSS_SHA384,
+};
+static RSA_PSS_SHA512: Verifier = Verifier {
+    scheme: Scheme::RsaPss,
+    hash: SignatureHash::Sha512,
+    public_key_alg: alg_id::RSA_ENCRYPTION,
+    signature_alg: alg_id::RSA_PSS_SHA512,
+};
+
+static SIGNATURE_ALGORITHMS: WebPkiSupportedAlgorithms = WebPkiSupportedAlgorithms {
+    all: &[
+        &ECDSA_P256_SHA256,
+        &ECDSA_P256_SHA384,
+        &RSA_PKCS1_SHA256,
+        &RSA_PKCS1_SHA384,
+        &RSA_PKCS1_SHA512,
+        &RSA_PSS_SHA256,
+        &RSA_PSS_SHA384,
+        &RSA_PSS_SHA512,
+    ],
+    // What the server may sign the handshake with, most preferred first
+    mapping: &[
+        (SignatureScheme::ECDSA_NISTP256_SHA256, &[&ECDSA_P256_SHA256]),
+        (SignatureScheme::RSA_PSS_SHA256, &[&RSA_PSS_SHA256]),
+        (SignatureScheme::RSA_PSS_SHA384, &[&RSA_PSS_SHA384]),
+        (SignatureScheme::RSA_PSS_SHA512, &[&RSA_PSS_SHA512]),
+        (SignatureScheme::RSA_PKCS1_SHA256, &[&RSA_PKCS1_SHA256]),
+        (SignatureScheme::RSA_PKCS1_SHA384, &[&RSA_PKCS1_SHA384]),
+        (SignatureScheme::RSA_PKCS1_SHA512, &[&RSA_PKCS1_SHA512]),
+    ],
+};
+
+// Randomness and keys
+
+#[derive(Debug)]
+struct Random;
+
+impl SecureRandom for Random {
+    fn fill(&self, buf: &mut [u8]) -> Result<(), GetRandomFailed> {
+        rand::fill_bytes(buf);
+        Ok(())
+    }
+}
+
+// We only act as a client without certificates, so there are no private
+// keys to load
+#[derive(Debug)]
+struct NoKeys;
+
+impl KeyProvider for NoKeys {
+    fn load_private_key(&self, _key: PrivateKeyDer<'static>) -> Result<Arc<dyn SigningKey>, Error> {
+        Err(Error::General("private keys are not supported".into()))
+    }
+}
+
+static RANDOM: Random = Random;
+static NO_KEYS: NoKeys = NoKeys;
+
+pub fn provider() -> CryptoProvider {
+    CryptoProvider {
+        cipher_suites: Vec::from([
+            SupportedCipherSuite::Tls13(&TLS13_AES_128_GCM_SHA256),
+            SupportedCipherSuite::Tls13(&TLS13_CHACHA20_POLY1305_SHA256),
+            SupportedCipherSuite::Tls13(&TLS13_AES_256_GCM_SHA384),
+        ]),
+        kx_groups: Vec::from([&KX_X25519 as &'static dyn SupportedKxGroup, &KX_P256]),
+        signature_verification_algorithms: SIGNATURE_ALGORITHMS,
+        secure_random: &RANDOM,
+        key_provider: &NO_KEYS,
+    }
+}
\ No newline at end of file
@@ -0,0 +1,95 @@
+// src/trackpad.rs
+// Turns multitouch frames into desktop gestures: two fingers scroll, three
+// fingers swipe. Drivers report contact positions in thousandths of the
+// pad (PAD_UNITS) so the thresholds don't depend on the device.
+pub const PAD_UNITS: i32 = 1000;
+// Two-finger travel per line scrolled
+const UNITS_PER_LINE: i32 = 25;
+// Three-finger travel before a swipe fires
+const SWIPE_DISTANCE: i32 = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwipeDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gesture {
+    // Finger travel in lines, positive right and down
+    Scroll { dx: isize, dy: isize },
+    Swipe(SwipeDirection),
+}
+
+pub struct GestureRecognizer {
+    fingers: usize,
+    // Centroid of the contacts when the finger count last changed, and in
+    // the previous frame
+    start: (i32, i32),
+    last: (i32, i32),
+    // Scroll travel not yet worth a whole line
+    scroll_rest: (i32, i32),
+    // One swipe per touch
+    swiped: bool,
+}
+
+impl GestureRecognizer {
+    pub const fn new() -> Self {
+        Self { fingers: 0, start: (0, 0), last: (0, 0), scroll_rest: (0, 0), swiped: false }
+    }
+    
+    // Feeds one frame of contact positions; call on every sync report
+    pub fn frame(&mut self, contacts: &[(i32, i32)]) -> Option<Gesture> {
+        let fingers = contacts.len();
+        let centroid = match fingers {
+            0 => (0, 0),
+            n => {
+                let (x, y) = contacts.iter().fold((0, 0), |(sx, sy), (x, y)| (sx + x, sy + y));
+                (x / n as i32, y / n as i32)
+            }
+        };
+        
+        // Lifting or adding a finger starts a new gesture
+        if fingers != self.fingers {
+            self.fingers = fingers;
+            self.start = centroid;
+            self.last = centroid;
+            self.scroll_rest = (0, 0);
+            self.swiped = false;
+            return None;
+        }
+        let step = (centroid.0 - self.last.0, centroid.1 - self.last.1);
+        self.last = centroid;
+        
+        match fingers {
+            2 => {
+                self.scroll_rest.0 += step.0;
+                self.scroll_rest.1 += step.1;
+                let lines = (self.scroll_rest.0 / UNITS_PER_LINE, self.scroll_rest.1 / UNITS_PER_LINE);
+                self.scroll_rest.0 -= lines.0 * UNITS_PER_LINE;
+                self.scroll_rest.1 -= lines.1 * UNITS_PER_LINE;
+                if lines == (0, 0) {
+                    return None;
+                }
+                Some(Gesture::Scroll { dx: lines.0 as isize, dy: lines.1 as isize })
+            }
+            3 if !self.swiped => {
+                let (dx, dy) = (centroid.0 - self.start.0, centroid.1 - self.start.1);
+                if dx.abs().max(dy.abs()) < SWIPE_DISTANCE {
+                    return None;
+                }
+                self.swiped = true;
+                let direction = match (dx.abs() > dy.abs(), dx > 0, dy > 0) {
+                    (true, true, _) => SwipeDirection::Right,
+                    (true, false, _) => SwipeDirection::Left,
+                    (false, _, true) => SwipeDirection::Down,
+                    (false, _, false) => SwipeDirection::Up,
+                };
+                Some(Gesture::Swipe(direction))
+            }
+            _ => None,
+        }
+    }
+}
\ No newline at end of file
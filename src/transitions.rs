@@ -0,0 +1,78 @@
+// src/transitions.rs
+// Whole-screen transitions: a horizontal slide between desktop spaces and a
+// cross-fade when the appearance changes. The screen is captured once as it
+// was and once after a full redraw in the new state, and the transition
+// moves between the two pictures, so nothing else is drawn meanwhile. With
+// Reduce motion on, slides become cross-fades.
+use crate::animations::{Animation, EasingType};
+use crate::appearance;
+use crate::graphics::{Graphics, Surface, SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::sync::Mutex;
+
+const SLIDE_MS: u32 = 350;
+const CROSS_FADE_MS: u32 = 300;
+
+// Screen-sized, so not on the heap
+static FROM: Mutex<Surface> = Mutex::new(Surface::new());
+static TO: Mutex<Surface> = Mutex::new(Surface::new());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    // Forward brings the new screen in from the right
+    Slide { forward: bool },
+    CrossFade,
+}
+
+impl Kind {
+    pub fn slide(forward: bool) -> Kind {
+        if appearance::motion_reduced() { Kind::CrossFade } else { Kind::Slide { forward } }
+    }
+}
+
+pub struct Transition {
+    kind: Kind,
+    progress: Animation,
+}
+
+impl Transition {
+    // Captures the screen, lets `draw` draw the new state and captures that
+    // too, then puts the old screen back for the first frame
+    pub fn start(kind: Kind, graphics: &mut Graphics, draw: impl FnOnce(&mut Graphics)) -> Self {
+        let mut from = FROM.lock();
+        graphics.capture(&mut from, 0, 0, SCREEN_WIDTH, SCREEN_HEIGHT);
+        draw(graphics);
+        graphics.capture(&mut TO.lock(), 0, 0, SCREEN_WIDTH, SCREEN_HEIGHT);
+        graphics.draw_offset(&from, 0);
+        let progress = match kind {
+            Kind::Slide { .. } => Animation::new(0.0, 1.0, SLIDE_MS, EasingType::EaseInOut),
+            Kind::CrossFade => Animation::new(0.0, 1.0, CROSS_FADE_MS, EasingType::Linear),
+        };
+        Self { kind, progress }
+    }
+    
+    pub fn draw(&mut self, graphics: &mut Graphics) {
+        let progress = self.progress.update();
+        let (from, to) = (FROM.lock(), TO.lock());
+        match self.kind {
+            Kind::Slide { forward } => {
+                let offset = (progress * SCREEN_WIDTH as f32) as i32;
+                let width = SCREEN_WIDTH as i32;
+                if forward {
+                    graphics.draw_offset(&from, -offset);
+                    graphics.draw_offset(&to, width - offset);
+                } else {
+                    graphics.draw_offset(&from, offset);
+                    graphics.draw_offset(&to, offset - width);
+                }
+            }
+            Kind::CrossFade => {
+                graphics.draw_offset(&from, 0);
+                graphics.draw_faded(&to, 0, 0, SCREEN_WIDTH, SCREEN_HEIGHT, progress);
+            }
+        }
+    }
+    
+    pub fn is_complete(&self) -> bool {
+        self.progress.is_complete()
+    }
+}
\ No newline at end of file
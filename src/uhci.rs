@@ -0,0 +1,365 @@
+// src/uhci.rs
+// UHCI (USB 1.1) host controller, as on QEMU's PIIX3/PIIX4 (`-usb`). Every
+// frame list entry points at the control queue head, which links to one
+// queue head per interrupt pipe. Control transfers are run one at a time
+// and waited on; interrupt pipes keep a single transfer descriptor queued
+// and are checked by polling. The controller only addresses 32 bits,
+// which the DMA allocator sees to.
+use crate::clock;
+use crate::dma::{self, DmaBuffer};
+use crate::memory::PAGE_SIZE;
+use crate::pci::{self, Bar, PciDevice};
+use crate::usb::{HostController, SetupPacket, Speed, UsbDevice, UsbError};
+use crate::{kinfo, kwarn};
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ptr::{read_volatile, write_volatile};
+use core::time::Duration;
+use x86_64::instructions::port::Port;
+
+// I/O registers from BAR4
+const REG_COMMAND: u16 = 0x00;
+const REG_STATUS: u16 = 0x02;
+const REG_INTERRUPTS: u16 = 0x04;
+const REG_FRAME_NUMBER: u16 = 0x06;
+const REG_FRAME_BASE: u16 = 0x08;
+const REG_PORTS: u16 = 0x10;
+const PORT_COUNT: usize = 2;
+
+const COMMAND_RUN: u16 = 1 << 0;
+const COMMAND_HOST_RESET: u16 = 1 << 1;
+const COMMAND_GLOBAL_RESET: u16 = 1 << 2;
+const COMMAND_CONFIGURED: u16 = 1 << 6;
+const COMMAND_MAX_PACKET_64: u16 = 1 << 7;
+
+const PORT_CONNECTED: u16 = 1 << 0;
+const PORT_CONNECT_CHANGE: u16 = 1 << 1;
+const PORT_ENABLED: u16 = 1 << 2;
+const PORT_ENABLE_CHANGE: u16 = 1 << 3;
+const PORT_LOW_SPEED: u16 = 1 << 8;
+const PORT_RESET: u16 = 1 << 9;
+// Write-one-to-clear bits, masked out when writing other bits
+const PORT_CHANGE_BITS: u16 = PORT_CONNECT_CHANGE | PORT_ENABLE_CHANGE;
+
+// Legacy keyboard/mouse emulation control in PCI config space
+const PCI_LEGACY_SUPPORT: u8 = 0xC0;
+
+// Link pointer bits
+const LINK_TERMINATE: u32 = 1 << 0;
+const LINK_QUEUE_HEAD: u32 = 1 << 1;
+const LINK_DEPTH_FIRST: u32 = 1 << 2;
+
+// Transfer descriptor status bits
+const TD_ACTIVE: u32 = 1 << 23;
+const TD_STALLED: u32 = 1 << 22;
+// Data buffer, babble, CRC/timeout and bit stuffing errors
+const TD_ERRORS: u32 = 1 << 21 | 1 << 20 | 1 << 18 | 1 << 17;
+const TD_LOW_SPEED: u32 = 1 << 26;
+const TD_THREE_ERRORS: u32 = 3 << 27;
+
+const PID_SETUP: u32 = 0x2D;
+const PID_IN: u32 = 0x69;
+const PID_OUT: u32 = 0xE1;
+
+// Layout of the DMA page
+const CONTROL_QH: usize = 0x000;
+const PIPE_QHS: usize = 0x010;
+const CONTROL_TDS: usize = 0x100;
+const PIPE_TDS: usize = 0x300;
+const SETUP_BUFFER: usize = 0x400;
+const PIPE_BUFFERS: usize = 0x500;
+const CONTROL_BUFFER: usize = 0x800;
+const TD_SIZE: usize = 32;
+const MAX_CONTROL_TDS: usize = 16;
+const MAX_PIPES: usize = 8;
+const PIPE_BUFFER_SIZE: usize = 32;
+const CONTROL_BUFFER_SIZE: usize = PAGE_SIZE as usize - CONTROL_BUFFER;
+
+const CONTROL_TIMEOUT: Duration = Duration::from_millis(500);
+
+// One transaction: the PID, data toggle and a buffer in the DMA page
+struct Packet {
+    pid: u32,
+    toggle: bool,
+    buffer: usize,
+    len: usize,
+}
+
+struct Pipe {
+    device: UsbDevice,
+    endpoint: u8,
+    max_packet: u16,
+    toggle: bool,
+}
+
+pub struct Uhci {
+    name: String,
+    io_base: u16,
+    // Where the controller looks every millisecond
+    _frame_list: DmaBuffer,
+    // Queue heads, transfer descriptors and their buffers
+    dma: DmaBuffer,
+    pipes: Vec<Pipe>,
+}
+
+impl Uhci {
+    fn new(device: &PciDevice, index: usize) -> Option<Self> {
+        let io_base = match device.bar(4) {
+            Bar::Io(port) => port,
+            _ => return None,
+        };
+        device.enable_bus_master();
+        // Take the controller back from the BIOS's PS/2 emulation
+        device.write_u16(PCI_LEGACY_SUPPORT, 0x8F00);
+        
+        let frame_list = DmaBuffer::new(PAGE_SIZE as usize)?;
+        let frame_list_phys = frame_list.phys(0) as u32;
+        let entries = frame_list.as_ptr() as *mut u32;
+        
+        let this = Self {
+            name: format!("uhci{}", index),
+            io_base,
+            _frame_list: frame_list,
+            dma: DmaBuffer::new(PAGE_SIZE as usize)?,
+            pipes: Vec::new(),
+        };
+        this.write_dma(CONTROL_QH, LINK_TERMINATE);
+        this.write_dma(CONTROL_QH + 4, LINK_TERMINATE);
+        for i in 0..1024 {
+            unsafe { write_volatile(entries.add(i), this.phys(CONTROL_QH) | LINK_QUEUE_HEAD) };
+        }
+        dma::sync_for_device();
+        
+        unsafe {
+            this.write16(REG_COMMAND, COMMAND_GLOBAL_RESET);
+            clock::delay(Duration::from_millis(10));
+            this.write16(REG_COMMAND, 0);
+            this.write16(REG_COMMAND, COMMAND_HOST_RESET);
+            let deadline = clock::Instant::now() + Duration::from_millis(50);
+            while this.read16(REG_COMMAND) & COMMAND_HOST_RESET != 0 {
+                if clock::Instant::now() > deadline {
+                    return None;
+                }
+            }
+            this.write16(REG_INTERRUPTS, 0);
+            this.write16(REG_STATUS, 0xFFFF);
+            Port::<u32>::new(io_base + REG_FRAME_BASE).write(frame_list_phys);
+            this.write16(REG_FRAME_NUMBER, 0);
+            this.write16(REG_COMMAND, COMMAND_RUN | COMMAND_CONFIGURED | COMMAND_MAX_PACKET_64);
+        }
+        Some(this)
+    }
+    
+    fn phys(&self, offset: usize) -> u32 {
+        self.dma.phys(offset) as u32
+    }
+    
+    fn write_dma(&self, offset: usize, value: u32) {
+        unsafe { write_volatile(self.dma.as_ptr().add(offset) as *mut u32, value) }
+    }
+    
+    fn read_dma(&self, offset: usize) -> u32 {
+        unsafe { read_volatile(self.dma.as_ptr().add(offset) as *const u32) }
+    }
+    
+    unsafe fn read16(&self, reg: u16) -> u16 {
+        Port::<u16>::new(self.io_base + reg).read()
+    }
+    
+    unsafe fn write16(&self, reg: u16, value: u16) {
+        Port::<u16>::new(self.io_base + reg).write(value)
+    }
+    
+    fn port_status(&self, port: usize) -> u16 {
+        unsafe { self.read16(REG_PORTS + 2 * port as u16) }
+    }
+    
+    fn set_port_status(&self, port: usize, value: u16) {
+        unsafe { self.write16(REG_PORTS + 2 * port as u16, value) }
+    }
+    
+    // Fills in a transfer descriptor; `next` is the offset of the next one
+    fn write_td(&self, offset: usize, next: Option<usize>, device: &UsbDevice, endpoint: u8, packet: Packet) {
+        let link = match next {
+            Some(next) => self.phys(next) | LINK_DEPTH_FIRST,
+            None => LINK_TERMINATE,
+        };
+        let speed = if device.speed == Speed::Low { TD_LOW_SPEED } else { 0 };
+        // Lengths are encoded minus one, with 0x7FF for zero bytes
+        let max_len = (packet.len as u32).wrapping_sub(1) & 0x7FF;
+        let token = max_len << 21 | (packet.toggle as u32) << 19 | (endpoint as u32) << 15 | (device.address as u32) << 8 | packet.pid;
+        self.write_dma(offset, link);
+        self.write_dma(offset + 4, TD_ACTIVE | TD_THREE_ERRORS | speed);
+        self.write_dma(offset + 8, token);
+        self.write_dma(offset + 12, self.phys(packet.buffer));
+    }
+    
+    fn td_status(&self, offset: usize) -> u32 {
+        self.read_dma(offset + 4)
+    }
+    
+    fn td_actual_length(&self, offset: usize) -> usize {
+        ((self.td_status(offset) + 1) & 0x7FF) as usize
+    }
+}
+
+impl HostController for Uhci {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    
+    fn port_count(&self) -> usize {
+        PORT_COUNT
+    }
+    
+    fn reset_port(&mut self, port: usize) -> Option<Speed> {
+        if self.port_status(port) & PORT_CONNECTED == 0 {
+            return None;
+        }
+        self.set_port_status(port, PORT_RESET);
+        clock::delay(Duration::from_millis(50));
+        self.set_port_status(port, 0);
+        clock::delay(Duration::from_millis(10));
+        
+        // Enabling can take a few tries while the device settles
+        for _ in 0..10 {
+            let status = self.port_status(port);
+            if status & PORT_CONNECTED == 0 {
+                return None;
+            }
+            if status & PORT_ENABLED != 0 {
+                self.set_port_status(port, (status & !PORT_CHANGE_BITS) | PORT_CHANGE_BITS);
+                let speed = if status & PORT_LOW_SPEED != 0 { Speed::Low } else { Speed::Full };
+                return Some(speed);
+            }
+            self.set_port_status(port, (status & !PORT_CHANGE_BITS) | PORT_ENABLED);
+            clock::delay(Duration::from_millis(10));
+        }
+        None
+    }
+    
+    fn control_transfer(&mut self, device: &UsbDevice, setup: SetupPacket, data: &mut [u8]) -> Result<usize, UsbError> {
+        let len = (setup.length as usize).min(data.len());
+        let max_packet = device.max_packet as usize;
+        let data_tds = len.div_ceil(max_packet);
+        if len > CONTROL_BUFFER_SIZE || data_tds + 2 > MAX_CONTROL_TDS {
+            return Err(UsbError::TooLong);
+        }
+        let is_in = setup.is_in();
+        
+        unsafe {
+            core::ptr::copy_nonoverlapping(setup.to_bytes().as_ptr(), self.dma.as_ptr().add(SETUP_BUFFER), 8);
+            if !is_in {
+                core::ptr::copy_nonoverlapping(data.as_ptr(), self.dma.as_ptr().add(CONTROL_BUFFER), len);
+            }
+        }
+        
+        // Setup stage, data stage packets starting on DATA1, then a status
+        // stage in the other direction
+        let td = |i: usize| CONTROL_TDS + i * TD_SIZE;
+        let status_td = td(data_tds + 1);
+        self.write_td(td(0), Some(td(1)), device, 0, Packet { pid: PID_SETUP, toggle: false, buffer: SETUP_BUFFER, len: 8 });
+        let data_pid = if is_in { PID_IN } else { PID_OUT };
+        for i in 0..data_tds {
+            let offset = i * max_packet;
+            let packet = max_packet.min(len - offset);
+            let packet = Packet { pid: data_pid, toggle: i % 2 == 0, buffer: CONTROL_BUFFER + offset, len: packet };
+            self.write_td(td(i + 1), Some(td(i + 2)), device, 0, packet);
+        }
+        let status_pid = if is_in && len > 0 { PID_OUT } else { PID_IN };
+        self.write_td(status_td, None, device, 0, Packet { pid: status_pid, toggle: true, buffer: CONTROL_BUFFER, len: 0 });
+        dma::sync_for_device();
+        self.write_dma(CONTROL_QH + 4, self.phys(td(0)));
+        
+        let deadline = clock::Instant::now() + CONTROL_TIMEOUT;
+        let result = loop {
+            let failed = (0..=data_tds + 1).map(|i| self.td_status(td(i))).find(|s| s & TD_ACTIVE == 0 && s & (TD_STALLED | TD_ERRORS) != 0);
+            if let Some(status) = failed {
+                break Err(if status & TD_STALLED != 0 { UsbError::Stalled } else { UsbError::Transfer });
+            }
+            if self.td_status(status_td) & TD_ACTIVE == 0 {
+                break Ok(());
+            }
+            if clock::Instant::now() > deadline {
+                break Err(UsbError::Timeout);
+            }
+            core::hint::spin_loop();
+        };
+        self.write_dma(CONTROL_QH + 4, LINK_TERMINATE);
+        result?;
+        
+        if !is_in {
+            return Ok(len);
+        }
+        let received = (0..data_tds).map(|i| self.td_actual_length(td(i + 1))).sum::<usize>().min(len);
+        dma::sync_for_cpu();
+        unsafe { core::ptr::copy_nonoverlapping(self.dma.as_ptr().add(CONTROL_BUFFER), data.as_mut_ptr(), received) };
+        Ok(received)
+    }
+    
+    fn open_interrupt_in(&mut self, device: &UsbDevice, endpoint: u8, max_packet: u16) -> Result<usize, UsbError> {
+        let index = self.pipes.len();
+        if index >= MAX_PIPES || max_packet as usize > PIPE_BUFFER_SIZE {
+            return Err(UsbError::NoResources);
+        }
+        let pipe = Pipe { device: *device, endpoint, max_packet, toggle: false };
+        let qh = PIPE_QHS + index * 16;
+        let td = PIPE_TDS + index * TD_SIZE;
+        let packet = Packet { pid: PID_IN, toggle: false, buffer: PIPE_BUFFERS + index * PIPE_BUFFER_SIZE, len: max_packet as usize };
+        self.write_td(td, None, &pipe.device, endpoint, packet);
+        self.write_dma(qh, LINK_TERMINATE);
+        self.write_dma(qh + 4, self.phys(td));
+        dma::sync_for_device();
+        // Append to the horizontal chain after the control queue head
+        let previous = if index == 0 { CONTROL_QH } else { PIPE_QHS + (index - 1) * 16 };
+        self.write_dma(previous, self.phys(qh) | LINK_QUEUE_HEAD);
+        self.pipes.push(pipe);
+        Ok(index)
+    }
+    
+    fn poll_interrupt(&mut self, index: usize, buf: &mut [u8]) -> Option<usize> {
+        let td = PIPE_TDS + index * TD_SIZE;
+        let status = self.td_status(td);
+        if status & TD_ACTIVE != 0 {
+            return None;
+        }
+        let buffer = PIPE_BUFFERS + index * PIPE_BUFFER_SIZE;
+        let received = if status & (TD_STALLED | TD_ERRORS) == 0 {
+            let len = self.td_actual_length(td).min(buf.len());
+            dma::sync_for_cpu();
+            unsafe { core::ptr::copy_nonoverlapping(self.dma.as_ptr().add(buffer), buf.as_mut_ptr(), len) };
+            self.pipes[index].toggle = !self.pipes[index].toggle;
+            Some(len)
+        } else {
+            None
+        };
+        
+        // Queue the next transfer
+        let pipe = &self.pipes[index];
+        let packet = Packet { pid: PID_IN, toggle: pipe.toggle, buffer, len: pipe.max_packet as usize };
+        self.write_td(td, None, &pipe.device, pipe.endpoint, packet);
+        dma::sync_for_device();
+        self.write_dma(PIPE_QHS + index * 16 + 4, self.phys(td));
+        received
+    }
+}
+
+// Starts every UHCI controller on the PCI bus
+pub fn probe() -> Vec<Box<dyn HostController + Send>> {
+    let mut controllers: Vec<Box<dyn HostController + Send>> = Vec::new();
+    for device in pci::devices() {
+        if (device.class, device.subclass, device.prog_if) != (0x0C, 0x03, 0x00) {
+            continue;
+        }
+        match Uhci::new(&device, controllers.len()) {
+            Some(uhci) => {
+                kinfo!("uhci: {} at {:02x}:{:02x}.{} io {:#x}", uhci.name, device.bus, device.device, device.function, uhci.io_base);
+                controllers.push(Box::new(uhci));
+            }
+            None => kwarn!("uhci: failed to initialize {:02x}:{:02x}", device.bus, device.device),
+        }
+    }
+    controllers
+}
\ No newline at end of file
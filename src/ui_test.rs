@@ -0,0 +1,63 @@
+// src/ui_test.rs
+// End-to-end UI tests, built with `--features ui-test` and run with
+// `make ui-test`. Each script in ui_tests/ is replayed against the running
+// desktop frame by frame, as fast as it'll go, and its checks decide
+// whether it passed. One line per script goes out over serial, then QEMU
+// exits with a code that says whether they all passed. Scripts run one
+// after another on the same desktop, so each puts back what it changes.
+use crate::desktop::Desktop;
+use crate::graphics::Graphics;
+use crate::replay;
+use crate::serial_println;
+use x86_64::instructions::port::Port;
+
+const SCRIPTS: [(&str, &str); 2] = [
+    ("spotlight", include_str!("../ui_tests/spotlight.replay")),
+    ("quit", include_str!("../ui_tests/quit.replay")),
+];
+// A script still going after this many frames is stuck
+const MAX_FRAMES: usize = 3000;
+// isa-debug-exit turns these into QEMU exit codes 33 and 35
+const EXIT_PORT: u16 = 0xF4;
+const EXIT_SUCCESS: u32 = 0x10;
+const EXIT_FAILURE: u32 = 0x11;
+
+// Checks passed and failed, or None if the script never finished
+fn play_out(desktop: &mut Desktop, graphics: &mut Graphics) -> Option<(usize, usize)> {
+    for _ in 0..MAX_FRAMES {
+        desktop.handle_events();
+        desktop.update(graphics);
+        if let Some(result) = replay::finished() {
+            return Some(result);
+        }
+    }
+    None
+}
+
+pub fn run(desktop: &mut Desktop, graphics: &mut Graphics) -> ! {
+    let mut failures = 0;
+    for (name, script) in SCRIPTS {
+        if !replay::play(name, script) {
+            serial_println!("ui-test: {:<12} FAILED, not a valid script", name);
+            failures += 1;
+            continue;
+        }
+        match play_out(desktop, graphics) {
+            Some((passed, 0)) => serial_println!("ui-test: {:<12} ok, {} checks", name, passed),
+            Some((passed, failed)) => {
+                serial_println!("ui-test: {:<12} FAILED, {} of {} checks", name, failed, passed + failed);
+                failures += 1;
+            }
+            None => {
+                serial_println!("ui-test: {:<12} FAILED, still going after {} frames", name, MAX_FRAMES);
+                failures += 1;
+            }
+        }
+    }
+    serial_println!("ui-test: {} of {} scripts failed", failures, SCRIPTS.len());
+    let code = if failures == 0 { EXIT_SUCCESS } else { EXIT_FAILURE };
+    unsafe {
+        Port::<u32>::new(EXIT_PORT).write(code);
+    }
+    crate::hlt_loop();
+}
\ No newline at end of file
@@ -0,0 +1,113 @@
+// src/undo.rs
+// Undo and redo for apps. A change is a Command that can apply and revert
+// itself; apps make their changes through `perform`, and the Edit menu and
+// Cmd+Z / Cmd+Shift+Z step them back and forth. Every app has its own
+// history, by app name, so undo goes to the app in front.
+use crate::vfs::VfsError;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::sync::Mutex;
+
+// Oldest changes are forgotten past this
+const MAX_DEPTH: usize = 50;
+
+pub trait Command: Send {
+    // What the Edit menu calls it, as in "Undo Rename"
+    fn name(&self) -> &str;
+    
+    fn apply(&mut self) -> Result<(), VfsError>;
+    
+    fn revert(&mut self) -> Result<(), VfsError>;
+}
+
+// A setting going from one value to another through `set`
+pub struct SetValue<T> {
+    pub name: &'static str,
+    pub set: fn(T) -> Result<(), VfsError>,
+    pub old: T,
+    pub new: T,
+}
+
+impl<T: Clone + Send> Command for SetValue<T> {
+    fn name(&self) -> &str {
+        self.name
+    }
+    
+    fn apply(&mut self) -> Result<(), VfsError> {
+        (self.set)(self.new.clone())
+    }
+    
+    fn revert(&mut self) -> Result<(), VfsError> {
+        (self.set)(self.old.clone())
+    }
+}
+
+struct History {
+    app: String,
+    undo: Vec<Box<dyn Command>>,
+    redo: Vec<Box<dyn Command>>,
+}
+
+static HISTORIES: Mutex<Vec<History>> = Mutex::new(Vec::new());
+
+fn with_history<R>(app: &str, f: impl FnOnce(&mut History) -> R) -> R {
+    let mut histories = HISTORIES.lock();
+    let index = match histories.iter().position(|history| history.app == app) {
+        Some(index) => index,
+        None => {
+            histories.push(History { app: String::from(app), undo: Vec::new(), redo: Vec::new() });
+            histories.len() - 1
+        }
+    };
+    f(&mut histories[index])
+}
+
+// Applies the change and, if that worked, adds it to the app's history.
+// A new change can't be redone over, so the redo history goes.
+pub fn perform(app: &str, mut command: impl Command + 'static) -> Result<(), VfsError> {
+    command.apply()?;
+    with_history(app, |history| {
+        history.undo.push(Box::new(command));
+        if history.undo.len() > MAX_DEPTH {
+            history.undo.remove(0);
+        }
+        history.redo.clear();
+    });
+    Ok(())
+}
+
+// Reverts the app's last change; None if there's nothing to undo. A change
+// that fails to revert stays where it was.
+pub fn undo(app: &str) -> Option<Result<String, VfsError>> {
+    // Commands run unlocked, since they may use other apps' state
+    let mut command = with_history(app, |history| history.undo.pop())?;
+    let result = command.revert();
+    let name = String::from(command.name());
+    with_history(app, |history| match result {
+        Ok(()) => history.redo.push(command),
+        Err(_) => history.undo.push(command),
+    });
+    Some(result.map(|()| name))
+}
+
+// Applies the app's last undone change again; None if there's none
+pub fn redo(app: &str) -> Option<Result<String, VfsError>> {
+    let mut command = with_history(app, |history| history.redo.pop())?;
+    let result = command.apply();
+    let name = String::from(command.name());
+    with_history(app, |history| match result {
+        Ok(()) => history.undo.push(command),
+        Err(_) => history.redo.push(command),
+    });
+    Some(result.map(|()| name))
+}
+
+// For the Edit menu: what Undo and Redo would do
+pub fn undo_name(app: &str) -> Option<String> {
+    with_history(app, |history| history.undo.last().map(|command| String::from(command.name())))
+}
+
+pub fn redo_name(app: &str) -> Option<String> {
+    with_history(app, |history| history.redo.last().map(|command| String::from(command.name())))
+}
\ No newline at end of file
@@ -0,0 +1,251 @@
+// src/usb.rs
+// USB core: the host controller interface, device enumeration and the bus
+// registry. Controllers (uhci.rs) move bytes; enumeration assigns
+// addresses, reads descriptors and hands HID boot interfaces to usb_hid.
+// Only devices on root ports are found; hubs aren't supported yet.
+// Everything is polled from the desktop loop.
+use crate::usb_hid::{self, HidDevice, HidKind};
+use crate::{clock, kinfo, kwarn, uhci};
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::time::Duration;
+use crate::sync::Mutex;
+
+// Standard requests
+const REQUEST_SET_ADDRESS: u8 = 5;
+const REQUEST_GET_DESCRIPTOR: u8 = 6;
+const REQUEST_SET_CONFIGURATION: u8 = 9;
+
+const DESCRIPTOR_DEVICE: u8 = 1;
+const DESCRIPTOR_CONFIGURATION: u8 = 2;
+const DESCRIPTOR_INTERFACE: u8 = 4;
+const DESCRIPTOR_ENDPOINT: u8 = 5;
+
+const CLASS_HID: u8 = 3;
+const SUBCLASS_BOOT: u8 = 1;
+
+// Addresses 1..=127 are handed out in order across all buses
+const MAX_ADDRESS: u8 = 127;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsbError {
+    Stalled,
+    Timeout,
+    // CRC, bit stuffing, babble or buffer errors on the wire
+    Transfer,
+    TooLong,
+    NoResources,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Speed {
+    Low,
+    Full,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct UsbDevice {
+    pub address: u8,
+    pub speed: Speed,
+    // Of endpoint 0
+    pub max_packet: u16,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SetupPacket {
+    pub request_type: u8,
+    pub request: u8,
+    pub value: u16,
+    pub index: u16,
+    pub length: u16,
+}
+
+impl SetupPacket {
+    pub fn to_bytes(&self) -> [u8; 8] {
+        let mut bytes = [0u8; 8];
+        bytes[0] = self.request_type;
+        bytes[1] = self.request;
+        bytes[2..4].copy_from_slice(&self.value.to_le_bytes());
+        bytes[4..6].copy_from_slice(&self.index.to_le_bytes());
+        bytes[6..8].copy_from_slice(&self.length.to_le_bytes());
+        bytes
+    }
+    
+    pub fn is_in(&self) -> bool {
+        self.request_type & 0x80 != 0
+    }
+}
+
+// What a host controller driver provides to the core
+pub trait HostController {
+    fn name(&self) -> &str;
+    
+    fn port_count(&self) -> usize;
+    
+    // Resets and enables the port; None if nothing is attached
+    fn reset_port(&mut self, port: usize) -> Option<Speed>;
+    
+    // Runs a control transfer on endpoint 0 and returns the bytes moved in
+    // the data stage
+    fn control_transfer(&mut self, device: &UsbDevice, setup: SetupPacket, data: &mut [u8]) -> Result<usize, UsbError>;
+    
+    // Starts polling an interrupt IN endpoint; returns a pipe handle
+    fn open_interrupt_in(&mut self, device: &UsbDevice, endpoint: u8, max_packet: u16) -> Result<usize, UsbError>;
+    
+    // Copies out a completed interrupt transfer, if one arrived, and queues
+    // the next one
+    fn poll_interrupt(&mut self, pipe: usize, buf: &mut [u8]) -> Option<usize>;
+}
+
+struct Bus {
+    controller: Box<dyn HostController + Send>,
+    hid: Vec<HidDevice>,
+}
+
+static BUSES: Mutex<Vec<Bus>> = Mutex::new(Vec::new());
+
+fn get_descriptor(hc: &mut dyn HostController, device: &UsbDevice, kind: u8, len: u16) -> Result<Vec<u8>, UsbError> {
+    let mut buf = vec![0u8; len as usize];
+    let setup = SetupPacket {
+        request_type: 0x80,
+        request: REQUEST_GET_DESCRIPTOR,
+        value: (kind as u16) << 8,
+        index: 0,
+        length: len,
+    };
+    let read = hc.control_transfer(device, setup, &mut buf)?;
+    buf.truncate(read);
+    Ok(buf)
+}
+
+fn set_request(hc: &mut dyn HostController, device: &UsbDevice, request: u8, value: u16) -> Result<(), UsbError> {
+    let setup = SetupPacket { request_type: 0x00, request, value, index: 0, length: 0 };
+    hc.control_transfer(device, setup, &mut []).map(|_| ())
+}
+
+// A HID boot interface and its interrupt IN endpoint
+struct BootInterface {
+    number: u8,
+    kind: HidKind,
+    endpoint: u8,
+    max_packet: u16,
+}
+
+// Walks a configuration descriptor for HID boot interfaces
+fn boot_interfaces(config: &[u8]) -> Vec<BootInterface> {
+    let mut found = Vec::new();
+    let mut current: Option<(u8, HidKind)> = None;
+    let mut offset = 0;
+    while offset + 2 <= config.len() {
+        let len = config[offset] as usize;
+        if len < 2 || offset + len > config.len() {
+            break;
+        }
+        let descriptor = &config[offset..offset + len];
+        match descriptor[1] {
+            DESCRIPTOR_INTERFACE if len >= 9 => {
+                current = match (descriptor[5], descriptor[6], descriptor[7]) {
+                    (CLASS_HID, SUBCLASS_BOOT, 1) => Some((descriptor[2], HidKind::Keyboard)),
+                    (CLASS_HID, SUBCLASS_BOOT, 2) => Some((descriptor[2], HidKind::Mouse)),
+                    _ => None,
+                };
+            }
+            // The first interrupt IN endpoint of a boot interface
+            DESCRIPTOR_ENDPOINT if len >= 7 && descriptor[2] & 0x80 != 0 && descriptor[3] & 0x3 == 3 => {
+                if let Some((number, kind)) = current.take() {
+                    let max_packet = u16::from_le_bytes([descriptor[4], descriptor[5]]);
+                    found.push(BootInterface { number, kind, endpoint: descriptor[2] & 0xF, max_packet });
+                }
+            }
+            _ => {}
+        }
+        offset += len;
+    }
+    found
+}
+
+// Brings the device on `port` to the configured state and opens its HID
+// boot interfaces
+fn enumerate(hc: &mut dyn HostController, port: usize, address: u8) -> Result<Vec<HidDevice>, UsbError> {
+    let speed = match hc.reset_port(port) {
+        Some(speed) => speed,
+        None => return Ok(Vec::new()),
+    };
+    
+    // Endpoint 0 max packet size is in the first 8 bytes of the device
+    // descriptor, which any device can send in one packet
+    let mut device = UsbDevice { address: 0, speed, max_packet: 8 };
+    let header = get_descriptor(hc, &device, DESCRIPTOR_DEVICE, 8)?;
+    device.max_packet = header.get(7).copied().unwrap_or(8).max(8) as u16;
+    
+    set_request(hc, &device, REQUEST_SET_ADDRESS, address as u16)?;
+    device.address = address;
+    // Devices get 2 ms to start answering on the new address
+    clock::delay(Duration::from_millis(2));
+    
+    let descriptor = get_descriptor(hc, &device, DESCRIPTOR_DEVICE, 18)?;
+    if descriptor.len() < 18 {
+        return Err(UsbError::Transfer);
+    }
+    let vendor = u16::from_le_bytes([descriptor[8], descriptor[9]]);
+    let product = u16::from_le_bytes([descriptor[10], descriptor[11]]);
+    
+    let config_header = get_descriptor(hc, &device, DESCRIPTOR_CONFIGURATION, 9)?;
+    if config_header.len() < 9 {
+        return Err(UsbError::Transfer);
+    }
+    let total = u16::from_le_bytes([config_header[2], config_header[3]]);
+    let config = get_descriptor(hc, &device, DESCRIPTOR_CONFIGURATION, total)?;
+    set_request(hc, &device, REQUEST_SET_CONFIGURATION, config_header[5] as u16)?;
+    
+    let interfaces = boot_interfaces(&config);
+    kinfo!(
+        "usb: {} port {}: device {:04x}:{:04x} at address {} ({:?} speed, {} HID boot interfaces)",
+        hc.name(),
+        port + 1,
+        vendor,
+        product,
+        address,
+        speed,
+        interfaces.len()
+    );
+    
+    let mut devices = Vec::new();
+    for interface in interfaces {
+        usb_hid::configure(hc, &device, interface.number)?;
+        let pipe = hc.open_interrupt_in(&device, interface.endpoint, interface.max_packet)?;
+        devices.push(HidDevice::new(interface.kind, pipe));
+    }
+    Ok(devices)
+}
+
+pub fn init() -> usize {
+    let mut buses = BUSES.lock();
+    let mut next_address = 1;
+    for mut controller in uhci::probe() {
+        let mut hid = Vec::new();
+        for port in 0..controller.port_count() {
+            if next_address > MAX_ADDRESS {
+                break;
+            }
+            match enumerate(controller.as_mut(), port, next_address) {
+                Ok(devices) => hid.extend(devices),
+                Err(err) => kwarn!("usb: {} port {}: enumeration failed: {:?}", controller.name(), port + 1, err),
+            }
+            next_address += 1;
+        }
+        buses.push(Bus { controller, hid });
+    }
+    buses.len()
+}
+
+// Collects HID reports and turns them into input events
+pub fn poll() {
+    let mut buses = BUSES.lock();
+    for bus in buses.iter_mut() {
+        for device in bus.hid.iter_mut() {
+            device.poll(bus.controller.as_mut());
+        }
+    }
+}
\ No newline at end of file
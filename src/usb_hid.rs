@@ -0,0 +1,149 @@
+// src/usb_hid.rs
+// HID boot protocol keyboards and mice. Boot reports have a fixed layout,
+// so no report descriptor parsing is needed; each report is compared with
+// the previous one and the differences go to the input queue.
+use crate::input::{self, InputEvent};
+use crate::keyboard::{Key, LETTERS};
+use crate::mouse::MouseButton;
+use crate::usb::{HostController, SetupPacket, UsbDevice, UsbError};
+
+// HID class requests
+const REQUEST_SET_IDLE: u8 = 0x0A;
+const REQUEST_SET_PROTOCOL: u8 = 0x0B;
+const PROTOCOL_BOOT: u16 = 0;
+
+const REPORT_SIZE: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HidKind {
+    Keyboard,
+    Mouse,
+}
+
+pub struct HidDevice {
+    kind: HidKind,
+    pipe: usize,
+    last_report: [u8; REPORT_SIZE],
+}
+
+// Switches the interface to the boot protocol and asks it to report only
+// on change
+pub fn configure(hc: &mut dyn HostController, device: &UsbDevice, interface: u8) -> Result<(), UsbError> {
+    let request = |request, value| SetupPacket { request_type: 0x21, request, value, index: interface as u16, length: 0 };
+    hc.control_transfer(device, request(REQUEST_SET_PROTOCOL, PROTOCOL_BOOT), &mut [])?;
+    // SET_IDLE is optional for mice and some keyboards stall it
+    match hc.control_transfer(device, request(REQUEST_SET_IDLE, 0), &mut []) {
+        Ok(_) | Err(UsbError::Stalled) => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+impl HidDevice {
+    pub fn new(kind: HidKind, pipe: usize) -> Self {
+        Self { kind, pipe, last_report: [0; REPORT_SIZE] }
+    }
+    
+    pub fn poll(&mut self, hc: &mut dyn HostController) {
+        let mut report = [0u8; REPORT_SIZE];
+        while let Some(len) = hc.poll_interrupt(self.pipe, &mut report) {
+            match self.kind {
+                HidKind::Keyboard => self.keyboard_report(&report),
+                HidKind::Mouse => mouse_report(&report[..len], self.last_report[0]),
+            }
+            self.last_report = report;
+        }
+    }
+    
+    // Boot keyboard report: modifier bits, a reserved byte, then up to six
+    // pressed key usages
+    fn keyboard_report(&self, report: &[u8; REPORT_SIZE]) {
+        let (old, new) = (self.last_report, report);
+        for bit in 0..8 {
+            let mask = 1 << bit;
+            if old[0] & mask != new[0] & mask {
+                input::push(InputEvent::Key { key: MODIFIERS[bit], pressed: new[0] & mask != 0 });
+            }
+        }
+        // Usage 1 is the rollover error code: too many keys down, state unknown
+        if new[2..].contains(&1) {
+            return;
+        }
+        for &usage in old[2..].iter().filter(|u| **u != 0 && !new[2..].contains(u)) {
+            if let Some(key) = key_for_usage(usage) {
+                input::push(InputEvent::Key { key, pressed: false });
+            }
+        }
+        for &usage in new[2..].iter().filter(|u| **u != 0 && !old[2..].contains(u)) {
+            if let Some(key) = key_for_usage(usage) {
+                input::push(InputEvent::Key { key, pressed: true });
+            }
+        }
+    }
+}
+
+// Boot mouse report: buttons, X and Y movement, and a wheel byte on mice
+// that have one
+fn mouse_report(report: &[u8], old_buttons: u8) {
+    if report.len() < 3 {
+        return;
+    }
+    let buttons = [MouseButton::Left, MouseButton::Right, MouseButton::Middle];
+    for (bit, button) in buttons.iter().enumerate() {
+        let mask = 1 << bit;
+        if old_buttons & mask != report[0] & mask {
+            input::push(InputEvent::MouseButton { button: *button, pressed: report[0] & mask != 0 });
+        }
+    }
+    let (dx, dy) = (report[1] as i8 as i32, report[2] as i8 as i32);
+    if dx != 0 || dy != 0 {
+        input::push(InputEvent::MouseMove { dx, dy });
+    }
+    if let Some(&wheel) = report.get(3) {
+        if wheel != 0 {
+            input::push(InputEvent::Scroll { delta: wheel as i8 as i32 });
+        }
+    }
+}
+
+// Modifier byte bits, low to high
+const MODIFIERS: [Key; 8] = [
+    Key::LeftCtrl,
+    Key::LeftShift,
+    Key::LeftAlt,
+    Key::LeftCmd,
+    Key::RightCtrl,
+    Key::RightShift,
+    Key::RightAlt,
+    Key::RightCmd,
+];
+
+// Usages 0x1E..=0x27 run 1 to 9, then 0
+const DIGITS: [Key; 10] = [
+    Key::Digit1, Key::Digit2, Key::Digit3, Key::Digit4, Key::Digit5,
+    Key::Digit6, Key::Digit7, Key::Digit8, Key::Digit9, Key::Digit0,
+];
+
+const FUNCTION_KEYS: [Key; 12] = [
+    Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6,
+    Key::F7, Key::F8, Key::F9, Key::F10, Key::F11, Key::F12,
+];
+
+// Keyboard/keypad usage page IDs
+fn key_for_usage(usage: u8) -> Option<Key> {
+    let key = match usage {
+        0x04..=0x1D => LETTERS[(usage - 0x04) as usize],
+        0x1E..=0x27 => DIGITS[(usage - 0x1E) as usize],
+        0x28 => Key::Enter,
+        0x29 => Key::Escape,
+        0x2A => Key::Backspace,
+        0x2B => Key::Tab,
+        0x2C => Key::Space,
+        0x3A..=0x45 => FUNCTION_KEYS[(usage - 0x3A) as usize],
+        0x4F => Key::ArrowRight,
+        0x50 => Key::ArrowLeft,
+        0x51 => Key::ArrowDown,
+        0x52 => Key::ArrowUp,
+        _ => return None,
+    };
+    Some(key)
+}
\ No newline at end of file
@@ -0,0 +1,51 @@
+// src/users.rs
+// Fixed user database until there is a login window: root plus the single
+// desktop user. The kernel runs as root until the desktop session starts.
+use core::sync::atomic::{AtomicU32, Ordering};
+
+pub const ROOT_UID: u32 = 0;
+pub const USER_UID: u32 = 501;
+pub const WHEEL_GID: u32 = 0;
+pub const STAFF_GID: u32 = 20;
+
+static CURRENT_UID: AtomicU32 = AtomicU32::new(ROOT_UID);
+static CURRENT_GID: AtomicU32 = AtomicU32::new(WHEEL_GID);
+
+pub fn user_name(uid: u32) -> &'static str {
+    match uid {
+        ROOT_UID => "root",
+        USER_UID => "user",
+        _ => "unknown",
+    }
+}
+
+pub fn group_name(gid: u32) -> &'static str {
+    match gid {
+        WHEEL_GID => "wheel",
+        STAFF_GID => "staff",
+        _ => "unknown",
+    }
+}
+
+pub fn current_uid() -> u32 {
+    CURRENT_UID.load(Ordering::Relaxed)
+}
+
+pub fn current_gid() -> u32 {
+    CURRENT_GID.load(Ordering::Relaxed)
+}
+
+pub fn set_current(uid: u32, gid: u32) {
+    CURRENT_UID.store(uid, Ordering::Relaxed);
+    CURRENT_GID.store(gid, Ordering::Relaxed);
+}
+
+// Runs `f` with root's credentials, for work the kernel does on its own
+// behalf (crash reports, logs) while a user session is active
+pub fn as_root<R>(f: impl FnOnce() -> R) -> R {
+    let (uid, gid) = (current_uid(), current_gid());
+    set_current(ROOT_UID, WHEEL_GID);
+    let result = f();
+    set_current(uid, gid);
+    result
+}
\ No newline at end of file
@@ -0,0 +1,592 @@
+// src/userspace.rs
+// Apps outside the kernel: ELF executables built with the SDK, each in a
+// process of its own and running in ring 3. There is one CPU and no
+// scheduler, so the desktop runs them from its loop: every frame, each app
+// not waiting for an event has the CPU until it waits, exits or faults, or
+// until its time slice is up and the timer takes it back. Its registers
+// are kept here between turns. System calls come in on `int 0x80` (the
+// SDK's protocol.rs has the calls) and are served with the app's page
+// tables live, so its pointers can be used once they're checked. A fault
+// kills the app, never the kernel, and so do the signals that end a
+// process, checked before each turn; a stopped app gets no turns.
+// Reading and writing go to the process's descriptors, pipes the shell
+// set up or files the app opened; posix.rs has those calls.
+use crate::address_space::{AddressSpace, USER_BASE, USER_END, USER_STACK_TOP};
+use crate::display_server::{self, ProtocolError, Reply, Request};
+use crate::elf::{self, ElfError};
+use crate::task::{self, Descriptor, Pid, ProcessState, Signal};
+use crate::{clock, cpu_usage, crash, gdt, interrupts, memory, posix, vfs, vm, kinfo, kwarn};
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::arch::global_asm;
+use core::mem::size_of;
+use core::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use rustos_sdk::protocol::*;
+use crate::sync::Mutex;
+use x86_64::structures::idt::InterruptStackFrame;
+use x86_64::structures::paging::{Page, PageTableFlags};
+use x86_64::VirtAddr;
+
+// 20 ms at a time
+const SLICE_TICKS: u64 = 2;
+const INTERRUPT_FLAG: u64 = 1 << 9;
+// Carry, parity, adjust, zero, sign, direction and overflow
+const USER_FLAGS: u64 = 0xCD5;
+// Surfaces are mapped in the upper half of the user window, a slot each
+const SURFACES_BASE: u64 = USER_BASE + (USER_END - USER_BASE) / 2;
+const SURFACE_SLOT: u64 = 16 << 20;
+// `mmap` hands out memory from the second quarter, well above the image
+const MMAP_BASE: u64 = USER_BASE + (USER_END - USER_BASE) / 4;
+const MAX_TITLE_LEN: usize = 256;
+const MAX_PATH_LEN: usize = 4096;
+const MAX_DAMAGE: usize = 64;
+const NOBODY: Pid = 0;
+
+// Returned by the system call handler: back to the app, or back to `run`
+const RESUME: u64 = 0;
+const LEAVE: u64 = 1;
+
+// An app's registers, laid out as the entry stubs push them, under what
+// the CPU pushes on an interrupt
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+struct Frame {
+    r15: u64,
+    r14: u64,
+    r13: u64,
+    r12: u64,
+    r11: u64,
+    r10: u64,
+    r9: u64,
+    r8: u64,
+    rbp: u64,
+    rdi: u64,
+    rsi: u64,
+    rdx: u64,
+    rcx: u64,
+    rbx: u64,
+    rax: u64,
+    rip: u64,
+    cs: u64,
+    rflags: u64,
+    rsp: u64,
+    ss: u64,
+}
+
+// The CPU's part of a Frame
+const CPU_FRAME_LEN: u64 = 5 * 8;
+
+// What a sleeping app waits for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Wait {
+    // In `wait_event` with nothing to read
+    Event,
+    // Reading an empty pipe, or writing a full one, at this descriptor
+    Read(usize),
+    Write(usize),
+}
+
+struct Thread {
+    frame: Frame,
+    waiting: Option<Wait>,
+    // Where the next `mmap` goes
+    mapped: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Interruption {
+    Preempted,
+    // What went wrong, and where
+    Fault(&'static str, u64),
+}
+
+static THREADS: Mutex<BTreeMap<Pid, Thread>> = Mutex::new(BTreeMap::new());
+static RUNNING: AtomicU32 = AtomicU32::new(NOBODY);
+static DEADLINE: AtomicU64 = AtomicU64::new(0);
+// The kernel's stack while an app runs, where `run` left it
+static KERNEL_RSP: AtomicU64 = AtomicU64::new(0);
+// The CPU's part of the frame of an app taken off by an interrupt, which
+// the interrupt handler had to give up to get out of ring 3
+static INTERRUPTED: Mutex<Option<([u64; 5], Interruption)>> = Mutex::new(None);
+
+extern "C" {
+    fn userspace_enter(frame: *const Frame);
+    fn userspace_interrupted();
+    fn userspace_syscall();
+}
+
+// `enter` saves what the kernel needs kept, loads the app's registers from
+// a Frame at the top of the entry stack and returns to it. Both ways back
+// into the kernel push a Frame in the same place; `leave` gets back to
+// `run` from there.
+global_asm!(
+    ".global userspace_enter",
+    "userspace_enter:",
+    "push rbx",
+    "push rbp",
+    "push r12",
+    "push r13",
+    "push r14",
+    "push r15",
+    "pushfq",
+    "cli",
+    "mov [rip + {kernel_rsp}], rsp",
+    "mov rsp, rdi",
+    "jmp 2f",
+    "",
+    ".global userspace_syscall",
+    "userspace_syscall:",
+    "push rax", "push rbx", "push rcx", "push rdx", "push rsi", "push rdi", "push rbp",
+    "push r8", "push r9", "push r10", "push r11", "push r12", "push r13", "push r14", "push r15",
+    "mov rdi, rsp",
+    "cld",
+    "call {syscall}",
+    "test rax, rax",
+    "jnz 3f",
+    "2:",
+    "pop r15", "pop r14", "pop r13", "pop r12", "pop r11", "pop r10", "pop r9", "pop r8",
+    "pop rbp", "pop rdi", "pop rsi", "pop rdx", "pop rcx", "pop rbx", "pop rax",
+    "iretq",
+    "",
+    ".global userspace_interrupted",
+    "userspace_interrupted:",
+    "push rax", "push rbx", "push rcx", "push rdx", "push rsi", "push rdi", "push rbp",
+    "push r8", "push r9", "push r10", "push r11", "push r12", "push r13", "push r14", "push r15",
+    "mov rdi, rsp",
+    "cld",
+    "call {interrupted}",
+    "3:",
+    "mov rsp, [rip + {kernel_rsp}]",
+    "popfq",
+    "pop r15",
+    "pop r14",
+    "pop r13",
+    "pop r12",
+    "pop rbp",
+    "pop rbx",
+    "ret",
+    kernel_rsp = sym KERNEL_RSP,
+    syscall = sym syscall,
+    interrupted = sym interrupted,
+);
+
+// Where `int 0x80` goes
+pub fn syscall_entry() -> VirtAddr {
+    let entry: unsafe extern "C" fn() = userspace_syscall;
+    VirtAddr::new(entry as usize as u64)
+}
+
+// Starts the app at `path` in a new process. It first runs on the
+// desktop's next frame.
+pub fn launch(path: &str) -> Result<Pid, ElfError> {
+    let pid = task::spawn(vfs::parent_and_name(path).1).map_err(|_| ElfError::NoMemory)?;
+    let entry = match task::with_address_space(pid, |space| elf::load(path, space)) {
+        Some(Ok(entry)) => entry,
+        result => {
+            let _ = task::exit(pid, -1);
+            task::wait(pid);
+            return Err(result.map_or(ElfError::NoMemory, |result| result.unwrap_err()));
+        }
+    };
+    let (code, data) = gdt::user_selectors();
+    let frame = Frame {
+        rip: entry.as_u64(),
+        cs: code.0 as u64,
+        rflags: INTERRUPT_FLAG | 0x2,
+        // As if `_start` had been called
+        rsp: USER_STACK_TOP - 8,
+        ss: data.0 as u64,
+        ..Frame::default()
+    };
+    THREADS.lock().insert(pid, Thread { frame, waiting: None, mapped: MMAP_BASE });
+    kinfo!("userspace: started {} as pid {}", path, pid);
+    Ok(pid)
+}
+
+// What hibernation keeps of an app besides its memory: its registers,
+// where its next `mmap` goes and whether it was waiting for an event
+pub struct Hibernated {
+    pub pid: Pid,
+    pub registers: [u64; REGISTERS],
+    pub mapped: u64,
+    pub waiting: bool,
+}
+
+pub const REGISTERS: usize = size_of::<Frame>() / 8;
+
+// The apps that can be hibernated: those with nothing open, as their pipes
+// and files can't be given back
+pub fn hibernated() -> Vec<Hibernated> {
+    let threads: Vec<Hibernated> = THREADS.lock().iter()
+        .filter(|(_, thread)| matches!(thread.waiting, None | Some(Wait::Event)))
+        .map(|(pid, thread)| Hibernated {
+            pid: *pid,
+            registers: unsafe { core::mem::transmute::<Frame, [u64; REGISTERS]>(thread.frame) },
+            mapped: thread.mapped,
+            waiting: thread.waiting.is_some(),
+        })
+        .collect();
+    threads.into_iter().filter(|app| !task::has_files(app.pid)).collect()
+}
+
+// Gives a hibernated app a surface under its old id, mapped where it was
+pub fn restore_surface(pid: Pid, surface: SurfaceId, title: String, width: usize, height: usize) -> bool {
+    match display_server::restore(pid, surface, title, width, height) {
+        Ok(Reply::Surface { buffer, .. }) => map_surface(pid, surface, &buffer).is_ok(),
+        _ => false,
+    }
+}
+
+// Lets `pid`, a process with a hibernated app's memory and surfaces put
+// back, carry on from where the app was. The registers come off the disk,
+// so only the flags an app can set itself are kept, and false if it
+// would start or keep its stack outside the user window.
+pub fn resume(pid: Pid, app: &Hibernated) -> bool {
+    let mut frame = unsafe { core::mem::transmute::<[u64; REGISTERS], Frame>(app.registers) };
+    if !(USER_BASE..USER_END).contains(&frame.rip) || !(USER_BASE..=USER_END).contains(&frame.rsp) {
+        return false;
+    }
+    let (code, data) = gdt::user_selectors();
+    (frame.cs, frame.ss) = (code.0 as u64, data.0 as u64);
+    frame.rflags = frame.rflags & USER_FLAGS | INTERRUPT_FLAG | 0x2;
+    let waiting = app.waiting.then_some(Wait::Event);
+    THREADS.lock().insert(pid, Thread { frame, waiting, mapped: app.mapped });
+    kinfo!("userspace: resumed pid {}", pid);
+    true
+}
+
+fn is_ready(pid: Pid, waiting: Option<Wait>) -> bool {
+    match waiting {
+        None => true,
+        Some(Wait::Event) => display_server::has_events(pid),
+        Some(Wait::Read(fd)) => task::with_file(pid, fd, |file| match file {
+            Descriptor::Input(reader) => reader.is_ready(),
+            _ => true,
+        }).unwrap_or(true),
+        Some(Wait::Write(fd)) => task::with_file(pid, fd, |file| match file {
+            Descriptor::Output(writer) => writer.is_ready(),
+            _ => true,
+        }).unwrap_or(true),
+    }
+}
+
+// Gives each app that has something to do its turn; called every frame
+pub fn poll() {
+    let threads: Vec<(Pid, Option<Wait>)> = THREADS.lock().iter().map(|(pid, thread)| (*pid, thread.waiting)).collect();
+    for (pid, waiting) in threads {
+        match task::take_signal(pid) {
+            Some(signal @ (Signal::Interrupt | Signal::Terminate | Signal::Kill)) => {
+                kinfo!("userspace: pid {} killed by signal {}", pid, signal.number());
+                finish(pid, 128 + signal.number());
+                continue;
+            }
+            _ => {}
+        }
+        if task::state(pid) != Some(ProcessState::Stopped) && is_ready(pid, waiting) {
+            run(pid);
+        }
+    }
+}
+
+fn run(pid: Pid) {
+    let frame = match THREADS.lock().get(&pid) {
+        Some(thread) => thread.frame,
+        None => return,
+    };
+    // Quit from outside, from the Activity Monitor or the shell
+    if task::with_address_space(pid, |space| space.activate()).is_none() {
+        THREADS.lock().remove(&pid);
+        return;
+    }
+    let slot = (gdt::entry_stack_top().as_u64() - size_of::<Frame>() as u64) as *mut Frame;
+    RUNNING.store(pid, Ordering::Relaxed);
+    DEADLINE.store(clock::ticks() + SLICE_TICKS, Ordering::Relaxed);
+    cpu_usage::running(Some(pid));
+    unsafe {
+        slot.write(frame);
+        userspace_enter(slot);
+    }
+    cpu_usage::running(None);
+    RUNNING.store(NOBODY, Ordering::Relaxed);
+    AddressSpace::activate_kernel();
+}
+
+// Keeps the app's registers for its next turn
+fn park(pid: Pid, frame: &Frame, waiting: Option<Wait>) {
+    if let Some(thread) = THREADS.lock().get_mut(&pid) {
+        thread.frame = *frame;
+        thread.waiting = waiting;
+    }
+}
+
+fn finish(pid: Pid, code: i32) {
+    THREADS.lock().remove(&pid);
+    // Nobody waits for apps; the process goes right away
+    let _ = task::exit(pid, code);
+    task::wait(pid);
+    kinfo!("userspace: pid {} exited with {}", pid, code);
+}
+
+// Takes an app off the CPU from an interrupt handler: the handler returns
+// to `userspace_interrupted` in the kernel instead, with the app's
+// registers as they were. False if it wasn't an app that was interrupted.
+fn take_off(stack_frame: &mut InterruptStackFrame, why: Interruption) -> bool {
+    if stack_frame.code_segment & 3 != 3 || RUNNING.load(Ordering::Relaxed) == NOBODY {
+        return false;
+    }
+    let cpu = [
+        stack_frame.instruction_pointer.as_u64(),
+        stack_frame.code_segment,
+        stack_frame.cpu_flags,
+        stack_frame.stack_pointer.as_u64(),
+        stack_frame.stack_segment,
+    ];
+    *INTERRUPTED.lock() = Some((cpu, why));
+    let entry: unsafe extern "C" fn() = userspace_interrupted;
+    let entry = VirtAddr::new(entry as usize as u64);
+    let stack = VirtAddr::new(gdt::entry_stack_top().as_u64() - CPU_FRAME_LEN);
+    unsafe {
+        stack_frame.as_mut().update(|frame| {
+            frame.instruction_pointer = entry;
+            frame.code_segment = gdt::kernel_code_selector().0 as u64;
+            frame.cpu_flags &= !INTERRUPT_FLAG;
+            frame.stack_pointer = stack;
+            frame.stack_segment = 0;
+        });
+    }
+    true
+}
+
+// Called from the timer interrupt
+pub fn preempt(stack_frame: &mut InterruptStackFrame) {
+    if clock::ticks() >= DEADLINE.load(Ordering::Relaxed) {
+        take_off(stack_frame, Interruption::Preempted);
+    }
+}
+
+// Called from the fault handlers; true if it was an app that faulted,
+// which is then killed
+pub fn fault(stack_frame: &mut InterruptStackFrame, what: &'static str, addr: u64) -> bool {
+    take_off(stack_frame, Interruption::Fault(what, addr))
+}
+
+extern "C" fn interrupted(frame: &mut Frame) {
+    let pid = RUNNING.load(Ordering::Relaxed);
+    let (cpu, why) = INTERRUPTED.lock().take().expect("userspace: interrupted without a frame");
+    [frame.rip, frame.cs, frame.rflags, frame.rsp, frame.ss] = cpu;
+    match why {
+        Interruption::Preempted => park(pid, frame, None),
+        Interruption::Fault(what, addr) => {
+            kwarn!("userspace: pid {} killed: {} at {:#x}, rip {:#x}", pid, what, addr, frame.rip);
+            finish(pid, crash::CRASH_EXIT_CODE);
+        }
+    }
+}
+
+fn error(err: ProtocolError) -> Error {
+    match err {
+        ProtocolError::NoSuchSurface => Error::NoSuchSurface,
+        ProtocolError::BadSize => Error::BadSize,
+        ProtocolError::TooManySurfaces => Error::TooManySurfaces,
+        ProtocolError::Vm(_) => Error::NoMemory,
+    }
+}
+
+// The app's memory at [addr, addr + len), if all of it is there for it
+fn user_memory(addr: u64, len: usize, write: bool) -> Result<&'static mut [u8], Error> {
+    if addr < USER_BASE || len as u64 > USER_END - addr {
+        return Err(Error::BadAddress);
+    }
+    if len == 0 {
+        return Ok(&mut []);
+    }
+    let mut needed = PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE;
+    if write {
+        needed |= PageTableFlags::WRITABLE;
+    }
+    let first = Page::containing_address(VirtAddr::new(addr));
+    let last = Page::containing_address(VirtAddr::new(addr + len as u64 - 1));
+    if !Page::range_inclusive(first, last).all(|page| memory::page_flags(page).is_some_and(|flags| flags.contains(needed))) {
+        return Err(Error::BadAddress);
+    }
+    Ok(unsafe { core::slice::from_raw_parts_mut(addr as *mut u8, len) })
+}
+
+fn user_string(addr: u64, len: u64) -> Result<String, Error> {
+    let bytes = user_memory(addr, (len as usize).min(MAX_TITLE_LEN), false)?;
+    Ok(String::from_utf8_lossy(bytes).into_owned())
+}
+
+fn user_path(addr: u64, len: u64) -> Result<String, Error> {
+    if len as usize > MAX_PATH_LEN {
+        return Err(Error::InvalidArgument);
+    }
+    let bytes = user_memory(addr, len as usize, false)?;
+    String::from_utf8(bytes.to_vec()).map_err(|_| Error::NotFound)
+}
+
+fn write_user<T>(addr: u64, value: T) -> Result<(), Error> {
+    let memory = user_memory(addr, size_of::<T>(), true)?;
+    unsafe { core::ptr::write_unaligned(memory.as_mut_ptr() as *mut T, value) };
+    Ok(())
+}
+
+// The surface's pixels go into the app's memory as well; they stay there
+// until it exits
+fn create_surface(pid: Pid, frame: &Frame) -> Result<u64, Error> {
+    let title = user_string(frame.rdi, frame.rsi)?;
+    user_memory(frame.r8, size_of::<SurfaceInfo>(), true)?;
+    let request = Request::CreateSurface { title, width: frame.rdx as usize, height: frame.r10 as usize };
+    let (surface, buffer, stride) = match display_server::request(pid, request).map_err(error)? {
+        Reply::Surface { surface, buffer, stride } => (surface, buffer, stride),
+        Reply::Done => return Err(Error::NoSuchSurface),
+    };
+    let pixels = map_surface(pid, surface, &buffer)?;
+    write_user(frame.r8, SurfaceInfo { surface, stride, pixels: pixels as usize })?;
+    Ok(0)
+}
+
+// Maps a new surface's buffer into its slot, or gives the surface up
+fn map_surface(pid: Pid, surface: SurfaceId, buffer: &str) -> Result<u64, Error> {
+    let pixels = SURFACES_BASE + surface as u64 * SURFACE_SLOT;
+    let mapped = vm::shm_frames(buffer).ok().and_then(|frames| {
+        task::with_address_space(pid, |space| space.map_shared(VirtAddr::new(pixels), &frames, true).ok())?
+    });
+    if mapped.is_none() {
+        let _ = display_server::request(pid, Request::Destroy { surface });
+        return Err(Error::NoMemory);
+    }
+    Ok(pixels)
+}
+
+fn commit(pid: Pid, frame: &Frame) -> Result<u64, Error> {
+    let count = (frame.rdx as usize).min(MAX_DAMAGE);
+    let rects = user_memory(frame.rsi, count * size_of::<Rect>(), false)?;
+    let damage = rects.chunks(size_of::<Rect>())
+        .map(|rect| unsafe { core::ptr::read_unaligned(rect.as_ptr() as *const Rect) })
+        .collect();
+    display_server::request(pid, Request::Commit { surface: frame.rdi as SurfaceId, damage }).map_err(error)?;
+    Ok(0)
+}
+
+fn scroll(pid: Pid, frame: &Frame) -> Result<u64, Error> {
+    let area = user_memory(frame.rsi, size_of::<Rect>(), false)?;
+    let area = unsafe { core::ptr::read_unaligned(area.as_ptr() as *const Rect) };
+    let request = Request::Scroll { surface: frame.rdi as SurfaceId, area, dy: frame.rdx as isize };
+    display_server::request(pid, request).map_err(error)?;
+    Ok(0)
+}
+
+// None when the app should sleep until the pipe is ready
+fn read(pid: Pid, frame: &Frame) -> Option<Result<u64, Error>> {
+    match user_memory(frame.rsi, frame.rdx as usize, true) {
+        Ok(buf) => posix::read(pid, frame.rdi as usize, buf).map(|result| result.map(|count| count as u64)),
+        Err(err) => Some(Err(err)),
+    }
+}
+
+fn write(pid: Pid, frame: &Frame) -> Option<Result<u64, Error>> {
+    match user_memory(frame.rsi, frame.rdx as usize, false) {
+        Ok(data) => posix::write(pid, frame.rdi as usize, data).map(|result| result.map(|count| count as u64)),
+        Err(err) => Some(Err(err)),
+    }
+}
+
+// Fresh pages after the last ones handed out, with the file's contents in
+// them if it names one. A pipe gives what's in it now.
+fn mmap(pid: Pid, frame: &Frame) -> Result<u64, Error> {
+    let len = frame.rdi.checked_next_multiple_of(memory::PAGE_SIZE).ok_or(Error::NoMemory)?;
+    if len == 0 {
+        return Err(Error::InvalidArgument);
+    }
+    let addr = THREADS.lock().get(&pid).map_or(MMAP_BASE, |thread| thread.mapped);
+    if len > SURFACES_BASE - addr {
+        return Err(Error::NoMemory);
+    }
+    let fd = (frame.rsi != u64::MAX).then_some(frame.rsi as usize);
+    if let Some(fd) = fd {
+        task::with_file(pid, fd, |_| ()).ok_or(Error::BadDescriptor)?;
+    }
+    match task::with_address_space(pid, |space| space.map_user(VirtAddr::new(addr), len, true)) {
+        Some(Ok(())) => {}
+        _ => return Err(Error::NoMemory),
+    }
+    if let Some(thread) = THREADS.lock().get_mut(&pid) {
+        thread.mapped = addr + len;
+    }
+    // The app's page tables are live, so the pages are right there
+    if let Some(fd) = fd {
+        let memory = user_memory(addr, frame.rdi as usize, true)?;
+        let mut done = 0;
+        while done < memory.len() {
+            match posix::read(pid, fd, &mut memory[done..]) {
+                Some(Ok(0)) | None => break,
+                Some(Ok(count)) => done += count,
+                Some(Err(err)) => return Err(err),
+            }
+        }
+    }
+    Ok(addr)
+}
+
+fn request(pid: Pid, request: Request) -> Result<u64, Error> {
+    display_server::request(pid, request).map_err(error)?;
+    Ok(0)
+}
+
+// Entered from `userspace_syscall` with the app's registers, which go back
+// to it as they're left here
+extern "C" fn syscall(frame: &mut Frame) -> u64 {
+    interrupts::count(SYSCALL_VECTOR);
+    let pid = RUNNING.load(Ordering::Relaxed);
+    let result = match frame.rax {
+        SYS_EXIT => {
+            finish(pid, frame.rdi as i32);
+            return LEAVE;
+        }
+        SYS_CREATE_SURFACE => create_surface(pid, frame),
+        SYS_COMMIT => commit(pid, frame),
+        SYS_SCROLL => scroll(pid, frame),
+        SYS_SET_TITLE => user_string(frame.rsi, frame.rdx)
+            .and_then(|title| request(pid, Request::SetTitle { surface: frame.rdi as SurfaceId, title })),
+        SYS_DESTROY_SURFACE => request(pid, Request::Destroy { surface: frame.rdi as SurfaceId }),
+        SYS_WAIT_EVENT | SYS_POLL_EVENT => match display_server::next_event(pid) {
+            Some(event) => write_user(frame.rdi, event).map(|_| 1),
+            None if frame.rax == SYS_POLL_EVENT => Ok(0),
+            None => {
+                // Sleeps; the `int 0x80` runs again once there's an event
+                frame.rip -= 2;
+                park(pid, frame, Some(Wait::Event));
+                return LEAVE;
+            }
+        },
+        SYS_READ | SYS_WRITE => {
+            let result = if frame.rax == SYS_READ { read(pid, frame) } else { write(pid, frame) };
+            match result {
+                Some(result) => result,
+                None => {
+                    // Sleeps like `wait_event` until the pipe is ready
+                    let wait = if frame.rax == SYS_READ { Wait::Read(frame.rdi as usize) } else { Wait::Write(frame.rdi as usize) };
+                    frame.rip -= 2;
+                    park(pid, frame, Some(wait));
+                    return LEAVE;
+                }
+            }
+        }
+        SYS_OPEN => user_path(frame.rdi, frame.rsi).and_then(|path| posix::open(pid, &path, frame.rdx)).map(|fd| fd as u64),
+        SYS_CLOSE => posix::close(pid, frame.rdi as usize).map(|_| 0),
+        SYS_LSEEK => posix::lseek(pid, frame.rdi as usize, frame.rsi as i64, frame.rdx),
+        SYS_MMAP => mmap(pid, frame),
+        SYS_CLOCK_GETTIME => posix::clock_gettime(frame.rdi).and_then(|time| write_user(frame.rsi, time)).map(|_| 0),
+        SYS_GETDENTS => user_memory(frame.rsi, frame.rdx as usize, true)
+            .and_then(|buf| posix::getdents(pid, frame.rdi as usize, buf))
+            .map(|len| len as u64),
+        _ => Err(Error::NoSuchCall),
+    };
+    frame.rax = match result {
+        Ok(value) => value,
+        Err(err) => err.code() as u64,
+    };
+    RESUME
+}
\ No newline at end of file
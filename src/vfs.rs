@@ -0,0 +1,652 @@
+// src/vfs.rs
+// Virtual filesystem: a mount table of FileSystem implementations addressed
+// by absolute paths. Each filesystem exposes inodes by number; the VFS does
+// path walking and mount-point resolution on top.
+use crate::block::{BlockError, SharedBlockDevice};
+use crate::users::{self, ROOT_UID};
+use crate::partition::{self, Volume};
+use crate::{ext2, fat32, iso9660, kinfo, kwarn, vm};
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use crate::sync::Mutex;
+use core::sync::atomic::{AtomicU64, Ordering};
+use x86_64::VirtAddr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileType {
+    File,
+    Directory,
+    Symlink,
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VfsError {
+    NotFound,
+    NotADirectory,
+    IsADirectory,
+    AlreadyExists,
+    NotEmpty,
+    ReadOnly,
+    InvalidPath,
+    Unsupported,
+    Corrupt,
+    NoSpace,
+    PermissionDenied,
+    Io(BlockError),
+}
+
+impl From<BlockError> for VfsError {
+    fn from(err: BlockError) -> Self {
+        VfsError::Io(err)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    Execute,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Metadata {
+    pub inode: u64,
+    pub file_type: FileType,
+    pub size: u64,
+    // Permission bits only (0o7777), the type lives in `file_type`
+    pub mode: u16,
+    pub uid: u32,
+    pub gid: u32,
+    // Unix timestamps in seconds
+    pub created: u64,
+    pub modified: u64,
+}
+
+impl Metadata {
+    pub fn is_dir(&self) -> bool {
+        self.file_type == FileType::Directory
+    }
+    
+    pub fn permits(&self, uid: u32, gid: u32, access: Access) -> bool {
+        if uid == ROOT_UID {
+            // Root bypasses everything except executing non-executable files
+            return access != Access::Execute || self.is_dir() || self.mode & 0o111 != 0;
+        }
+        
+        let bit = match access {
+            Access::Read => 0o4,
+            Access::Write => 0o2,
+            Access::Execute => 0o1,
+        };
+        let shift = if uid == self.uid {
+            6
+        } else if gid == self.gid {
+            3
+        } else {
+            0
+        };
+        self.mode & (bit << shift) != 0
+    }
+    
+    // "drwxr-xr-x" as printed by `ls -l`
+    pub fn mode_string(&self) -> String {
+        let mut s = String::with_capacity(10);
+        s.push(match self.file_type {
+            FileType::Directory => 'd',
+            FileType::Symlink => 'l',
+            FileType::File => '-',
+            FileType::Other => '?',
+        });
+        for shift in [6, 3, 0].iter() {
+            let bits = (self.mode >> shift) & 0o7;
+            s.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+            s.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+            s.push(if bits & 0o1 != 0 { 'x' } else { '-' });
+        }
+        s
+    }
+}
+
+fn check(meta: &Metadata, access: Access) -> Result<(), VfsError> {
+    if meta.permits(users::current_uid(), users::current_gid(), access) {
+        Ok(())
+    } else {
+        Err(VfsError::PermissionDenied)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DirEntry {
+    pub name: String,
+    pub inode: u64,
+    pub file_type: FileType,
+}
+
+pub trait FileSystem: Send {
+    // Short filesystem type name, e.g. "ext2"
+    fn fs_type(&self) -> &'static str;
+    
+    fn root_inode(&self) -> u64;
+    
+    fn lookup(&mut self, dir: u64, name: &str) -> Result<u64, VfsError>;
+    
+    fn metadata(&mut self, inode: u64) -> Result<Metadata, VfsError>;
+    
+    fn read_dir(&mut self, inode: u64) -> Result<Vec<DirEntry>, VfsError>;
+    
+    fn read(&mut self, inode: u64, offset: u64, buf: &mut [u8]) -> Result<usize, VfsError>;
+    
+    fn write(&mut self, _inode: u64, _offset: u64, _buf: &[u8]) -> Result<usize, VfsError> {
+        Err(VfsError::ReadOnly)
+    }
+    
+    fn truncate(&mut self, _inode: u64, _size: u64) -> Result<(), VfsError> {
+        Err(VfsError::ReadOnly)
+    }
+    
+    fn create(&mut self, _dir: u64, _name: &str, _file_type: FileType) -> Result<u64, VfsError> {
+        Err(VfsError::ReadOnly)
+    }
+    
+    fn remove(&mut self, _dir: u64, _name: &str) -> Result<(), VfsError> {
+        Err(VfsError::ReadOnly)
+    }
+    
+    // Moves an entry to another directory of the same filesystem, or
+    // renames it in place
+    fn rename(&mut self, _from_dir: u64, _from_name: &str, _to_dir: u64, _to_name: &str) -> Result<(), VfsError> {
+        Err(VfsError::ReadOnly)
+    }
+    
+    fn set_mode(&mut self, _inode: u64, _mode: u16) -> Result<(), VfsError> {
+        Err(VfsError::ReadOnly)
+    }
+    
+    fn set_owner(&mut self, _inode: u64, _uid: u32, _gid: u32) -> Result<(), VfsError> {
+        Err(VfsError::ReadOnly)
+    }
+    
+    fn set_times(&mut self, _inode: u64, _created: u64, _modified: u64) -> Result<(), VfsError> {
+        Err(VfsError::ReadOnly)
+    }
+    
+    fn is_read_only(&self) -> bool {
+        true
+    }
+    
+    // Bytes used and the capacity, for filesystems with a fixed size
+    fn usage(&self) -> Option<(u64, u64)> {
+        None
+    }
+    
+    fn sync(&mut self) -> Result<(), VfsError> {
+        Ok(())
+    }
+}
+
+pub type SharedFileSystem = Arc<Mutex<dyn FileSystem>>;
+
+struct Mount {
+    path: String,
+    source: String,
+    fs: SharedFileSystem,
+}
+
+pub struct MountInfo {
+    pub path: String,
+    pub source: String,
+    pub fs_type: &'static str,
+    pub read_only: bool,
+    // Bytes used and the capacity, if the filesystem has a fixed size
+    pub usage: Option<(u64, u64)>,
+}
+
+static MOUNTS: Mutex<Vec<Mount>> = Mutex::new(Vec::new());
+// Watched paths, normalized, each with how many times it has changed
+static WATCHED: Mutex<Vec<(String, u64)>> = Mutex::new(Vec::new());
+// Bumped by every change made through the VFS and every mount or unmount
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+// Notices changes made to a file through the VFS: writes, and the file
+// being created, removed or renamed onto or away. Changes made to a disk
+// from outside aren't seen. Watching a path costs nothing once it changes
+// no more, so watches are kept for the boot.
+pub struct Watch {
+    path: String,
+    seen: u64,
+}
+
+impl Watch {
+    // Whether the file changed since the last call, or since the watch
+    // began
+    pub fn changed(&mut self) -> bool {
+        let count = WATCHED.lock().iter().find(|(path, _)| *path == self.path).map_or(0, |(_, count)| *count);
+        let changed = count != self.seen;
+        self.seen = count;
+        changed
+    }
+}
+
+pub fn watch(path: &str) -> Result<Watch, VfsError> {
+    let path = normalize(path)?;
+    let mut watched = WATCHED.lock();
+    let seen = match watched.iter().find(|(watched, _)| *watched == path) {
+        Some((_, count)) => *count,
+        None => {
+            watched.push((path.clone(), 0));
+            0
+        }
+    };
+    Ok(Watch { path, seen })
+}
+
+fn touched(path: &str) {
+    GENERATION.fetch_add(1, Ordering::Relaxed);
+    let mut watched = WATCHED.lock();
+    if watched.is_empty() {
+        return;
+    }
+    let path = normalize(path).unwrap_or_default();
+    if let Some((_, count)) = watched.iter_mut().find(|(watched, _)| *watched == path) {
+        *count += 1;
+    }
+}
+
+// What's read from the VFS, such as a folder's listing, can be kept while
+// this stays the same
+pub fn generation() -> u64 {
+    GENERATION.load(Ordering::Relaxed)
+}
+
+// Collapses "." / ".." and duplicate slashes; the result always starts with '/'
+pub fn normalize(path: &str) -> Result<String, VfsError> {
+    if !path.starts_with('/') {
+        return Err(VfsError::InvalidPath);
+    }
+    
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    
+    let mut normalized = String::new();
+    for part in &parts {
+        normalized.push('/');
+        normalized.push_str(part);
+    }
+    if normalized.is_empty() {
+        normalized.push('/');
+    }
+    Ok(normalized)
+}
+
+pub fn join(dir: &str, name: &str) -> String {
+    let mut path = String::from(dir.trim_end_matches('/'));
+    path.push('/');
+    path.push_str(name);
+    path
+}
+
+pub fn parent_and_name(path: &str) -> (&str, &str) {
+    match path.rfind('/') {
+        Some(0) => ("/", &path[1..]),
+        Some(i) => (&path[..i], &path[i + 1..]),
+        None => ("/", path),
+    }
+}
+
+pub fn mount<F: FileSystem + 'static>(path: &str, source: &str, fs: F) -> Result<(), VfsError> {
+    let path = normalize(path)?;
+    let mut mounts = MOUNTS.lock();
+    if mounts.iter().any(|m| m.path == path) {
+        return Err(VfsError::AlreadyExists);
+    }
+    mounts.push(Mount {
+        path,
+        source: String::from(source),
+        fs: Arc::new(Mutex::new(fs)),
+    });
+    GENERATION.fetch_add(1, Ordering::Relaxed);
+    Ok(())
+}
+
+pub fn unmount(path: &str) -> Result<(), VfsError> {
+    let path = normalize(path)?;
+    let mut mounts = MOUNTS.lock();
+    let index = mounts.iter().position(|m| m.path == path).ok_or(VfsError::NotFound)?;
+    let mount = mounts.remove(index);
+    drop(mounts);
+    GENERATION.fetch_add(1, Ordering::Relaxed);
+    
+    let result = mount.fs.lock().sync();
+    result
+}
+
+pub fn mounts() -> Vec<MountInfo> {
+    MOUNTS
+        .lock()
+        .iter()
+        .map(|m| {
+            let fs = m.fs.lock();
+            MountInfo {
+                path: m.path.clone(),
+                source: m.source.clone(),
+                fs_type: fs.fs_type(),
+                read_only: fs.is_read_only(),
+                usage: fs.usage(),
+            }
+        })
+        .collect()
+}
+
+fn covers(mount_path: &str, path: &str) -> bool {
+    mount_path == "/"
+        || path == mount_path
+        || (path.starts_with(mount_path) && path.as_bytes()[mount_path.len()] == b'/')
+}
+
+// Finds the mount with the longest prefix of `path` and the remainder
+fn find_mount(path: &str) -> Option<(SharedFileSystem, String)> {
+    let mounts = MOUNTS.lock();
+    let mount = mounts
+        .iter()
+        .filter(|m| covers(&m.path, path))
+        .max_by_key(|m| m.path.len())?;
+    
+    let rest = if mount.path == "/" { path } else { &path[mount.path.len()..] };
+    Some((mount.fs.clone(), String::from(rest)))
+}
+
+pub fn resolve(path: &str) -> Result<(SharedFileSystem, u64), VfsError> {
+    let path = normalize(path)?;
+    let (fs, rest) = find_mount(&path).ok_or(VfsError::NotFound)?;
+    
+    let inode = {
+        let mut guard = fs.lock();
+        let mut inode = guard.root_inode();
+        for part in rest.split('/').filter(|p| !p.is_empty()) {
+            let meta = guard.metadata(inode)?;
+            if !meta.is_dir() {
+                return Err(VfsError::NotADirectory);
+            }
+            check(&meta, Access::Execute)?;
+            inode = guard.lookup(inode, part)?;
+        }
+        inode
+    };
+    Ok((fs, inode))
+}
+
+pub fn metadata(path: &str) -> Result<Metadata, VfsError> {
+    let (fs, inode) = resolve(path)?;
+    let result = fs.lock().metadata(inode);
+    result
+}
+
+pub fn read_dir(path: &str) -> Result<Vec<DirEntry>, VfsError> {
+    let path = normalize(path)?;
+    let mut entries = match resolve(&path) {
+        Ok((fs, inode)) => {
+            let mut fs = fs.lock();
+            let meta = fs.metadata(inode)?;
+            if !meta.is_dir() {
+                return Err(VfsError::NotADirectory);
+            }
+            check(&meta, Access::Read)?;
+            fs.read_dir(inode)?
+        }
+        Err(VfsError::NotFound) => Vec::new(),
+        Err(err) => return Err(err),
+    };
+    
+    // Mount points show up in their parent directory even if it has no
+    // backing filesystem (e.g. /Volumes before a root is mounted)
+    let mut found_mount = false;
+    for mount in MOUNTS.lock().iter() {
+        if mount.path == path || !covers(&path, &mount.path) {
+            continue;
+        }
+        let child = mount.path[path.len()..].trim_start_matches('/');
+        let name = child.split('/').next().unwrap_or("");
+        found_mount = true;
+        if !name.is_empty() && !entries.iter().any(|e| e.name == name) {
+            entries.push(DirEntry {
+                name: String::from(name),
+                inode: 0,
+                file_type: FileType::Directory,
+            });
+        }
+    }
+    
+    if entries.is_empty() && !found_mount {
+        resolve(&path)?;
+    }
+    Ok(entries)
+}
+
+pub fn read(path: &str, offset: u64, buf: &mut [u8]) -> Result<usize, VfsError> {
+    // A memory-mapped file page faulting in under the file system lock
+    // would deadlock on it
+    vm::prefault(VirtAddr::from_ptr(buf.as_ptr()), buf.len(), true);
+    let (fs, inode) = resolve(path)?;
+    let mut fs = fs.lock();
+    let meta = fs.metadata(inode)?;
+    if meta.is_dir() {
+        return Err(VfsError::IsADirectory);
+    }
+    check(&meta, Access::Read)?;
+    fs.read(inode, offset, buf)
+}
+
+pub fn read_file(path: &str) -> Result<Vec<u8>, VfsError> {
+    let (fs, inode) = resolve(path)?;
+    let mut fs = fs.lock();
+    let meta = fs.metadata(inode)?;
+    if meta.is_dir() {
+        return Err(VfsError::IsADirectory);
+    }
+    check(&meta, Access::Read)?;
+    
+    let mut data = alloc::vec![0u8; meta.size as usize];
+    let mut done = 0;
+    while done < data.len() {
+        let n = fs.read(inode, done as u64, &mut data[done..])?;
+        if n == 0 {
+            break;
+        }
+        done += n;
+    }
+    data.truncate(done);
+    Ok(data)
+}
+
+pub fn write(path: &str, offset: u64, buf: &[u8]) -> Result<usize, VfsError> {
+    vm::prefault(VirtAddr::from_ptr(buf.as_ptr()), buf.len(), false);
+    let (fs, inode) = resolve(path)?;
+    let mut fs = fs.lock();
+    check(&fs.metadata(inode)?, Access::Write)?;
+    let written = fs.write(inode, offset, buf)?;
+    touched(path);
+    Ok(written)
+}
+
+pub fn create(path: &str, file_type: FileType) -> Result<(), VfsError> {
+    let path = normalize(path)?;
+    let (parent, name) = parent_and_name(&path);
+    let (fs, dir) = resolve(parent)?;
+    let mut fs = fs.lock();
+    let meta = fs.metadata(dir)?;
+    if !meta.is_dir() {
+        return Err(VfsError::NotADirectory);
+    }
+    check(&meta, Access::Write)?;
+    fs.create(dir, name, file_type)?;
+    touched(&path);
+    Ok(())
+}
+
+pub fn create_dir_all(path: &str) -> Result<(), VfsError> {
+    let path = normalize(path)?;
+    let mut current = String::new();
+    for part in path.split('/').filter(|p| !p.is_empty()) {
+        current.push('/');
+        current.push_str(part);
+        match metadata(&current) {
+            Ok(meta) if meta.is_dir() => {}
+            Ok(_) => return Err(VfsError::NotADirectory),
+            Err(VfsError::NotFound) => create(&current, FileType::Directory)?,
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
+
+// Creates or replaces the contents of a file
+pub fn write_file(path: &str, data: &[u8]) -> Result<(), VfsError> {
+    vm::prefault(VirtAddr::from_ptr(data.as_ptr()), data.len(), false);
+    match metadata(path) {
+        Ok(meta) if meta.is_dir() => return Err(VfsError::IsADirectory),
+        Ok(_) => {}
+        Err(VfsError::NotFound) => create(path, FileType::File)?,
+        Err(err) => return Err(err),
+    }
+    
+    let (fs, inode) = resolve(path)?;
+    let mut fs = fs.lock();
+    check(&fs.metadata(inode)?, Access::Write)?;
+    fs.truncate(inode, 0)?;
+    fs.write(inode, 0, data)?;
+    touched(path);
+    Ok(())
+}
+
+pub fn remove(path: &str) -> Result<(), VfsError> {
+    let path = normalize(path)?;
+    let (parent, name) = parent_and_name(&path);
+    let (fs, dir) = resolve(parent)?;
+    let mut fs = fs.lock();
+    check(&fs.metadata(dir)?, Access::Write)?;
+    fs.remove(dir, name)?;
+    touched(&path);
+    Ok(())
+}
+
+// Moving between filesystems isn't supported; `to` must not exist yet
+pub fn rename(from: &str, to: &str) -> Result<(), VfsError> {
+    let (from, to) = (normalize(from)?, normalize(to)?);
+    let (from_parent, from_name) = parent_and_name(&from);
+    let (to_parent, to_name) = parent_and_name(&to);
+    let (fs, from_dir) = resolve(from_parent)?;
+    let (to_fs, to_dir) = resolve(to_parent)?;
+    if !Arc::ptr_eq(&fs, &to_fs) {
+        return Err(VfsError::Unsupported);
+    }
+    let mut fs = fs.lock();
+    check(&fs.metadata(from_dir)?, Access::Write)?;
+    check(&fs.metadata(to_dir)?, Access::Write)?;
+    fs.rename(from_dir, from_name, to_dir, to_name)?;
+    touched(&from);
+    touched(&to);
+    Ok(())
+}
+
+// Only the owner (or root) may change permission bits
+pub fn chmod(path: &str, mode: u16) -> Result<(), VfsError> {
+    let (fs, inode) = resolve(path)?;
+    let mut fs = fs.lock();
+    let uid = users::current_uid();
+    if uid != ROOT_UID && uid != fs.metadata(inode)?.uid {
+        return Err(VfsError::PermissionDenied);
+    }
+    fs.set_mode(inode, mode & 0o7777)
+}
+
+pub fn chown(path: &str, uid: u32, gid: u32) -> Result<(), VfsError> {
+    if users::current_uid() != ROOT_UID {
+        return Err(VfsError::PermissionDenied);
+    }
+    let (fs, inode) = resolve(path)?;
+    let result = fs.lock().set_owner(inode, uid, gid);
+    result
+}
+
+pub fn set_times(path: &str, created: u64, modified: u64) -> Result<(), VfsError> {
+    let (fs, inode) = resolve(path)?;
+    let mut fs = fs.lock();
+    check(&fs.metadata(inode)?, Access::Write)?;
+    fs.set_times(inode, created, modified)
+}
+
+pub fn sync_all() -> Result<(), VfsError> {
+    let filesystems: Vec<SharedFileSystem> = MOUNTS.lock().iter().map(|m| m.fs.clone()).collect();
+    for fs in filesystems {
+        fs.lock().sync()?;
+    }
+    Ok(())
+}
+
+
+// The type of the filesystem on a volume, if it's one that can be mounted
+pub fn probe(device: &SharedBlockDevice) -> Option<&'static str> {
+    if ext2::probe(device) {
+        Some("ext2")
+    } else if iso9660::probe(device) {
+        Some("iso9660")
+    } else if fat32::probe(device) {
+        Some("fat32")
+    } else {
+        None
+    }
+}
+
+// The mount point of the volume, if it's mounted
+pub fn mount_point(volume: &Volume) -> Option<String> {
+    let source = format!("/dev/{}", volume.name);
+    MOUNTS.lock().iter().find(|m| m.source == source).map(|m| m.path.clone())
+}
+
+// Mounts the filesystem on a volume at /Volumes/<name>; the mount point
+pub fn mount_volume(volume: &Volume) -> Result<String, VfsError> {
+    let path = format!("/Volumes/{}", volume.name);
+    let source = format!("/dev/{}", volume.name);
+    let device = volume.device.clone();
+    match probe(&device) {
+        Some("ext2") => {
+            let fs = ext2::Ext2::mount(device)?;
+            kinfo!("vfs: mounted ext2 volume {:?} at {}", fs.superblock().volume_name, path);
+            mount(&path, &source, fs)?;
+        }
+        Some("iso9660") => {
+            let fs = iso9660::Iso9660::mount(device)?;
+            kinfo!("vfs: mounted iso9660 volume {:?} at {}", fs.volume_id, path);
+            mount(&path, &source, fs)?;
+        }
+        Some("fat32") => {
+            let fs = fat32::Fat32::mount(device)?;
+            kinfo!("vfs: mounted fat32 volume {:?} at {}", fs.label, path);
+            mount(&path, &source, fs)?;
+        }
+        _ => return Err(VfsError::Unsupported),
+    }
+    Ok(path)
+}
+
+// Mounts every recognised filesystem on the attached disks and their
+// partitions under /Volumes
+pub fn mount_block_devices() {
+    for volume in partition::volumes() {
+        match mount_volume(&volume) {
+            Ok(_) | Err(VfsError::Unsupported) => {}
+            Err(err) => kwarn!("vfs: /dev/{}: mount failed: {:?}", volume.name, err),
+        }
+    }
+}
\ No newline at end of file
@@ -2,7 +2,7 @@
 use volatile::Volatile;
 use core::fmt;
 use lazy_static::lazy_static;
-use spin::Mutex;
+use crate::sync::Mutex;
 
 lazy_static! {
     pub static ref WRITER: Mutex<Writer> = Mutex::new(Writer {
@@ -0,0 +1,313 @@
+// src/virtio.rs
+// Pieces shared by the virtio drivers: the split virtqueue, device status
+// bits, and the virtio 1.0 PCI transport for devices that have no legacy
+// interface (virtio-input, virtio-net). virtio-blk still drives its legacy
+// registers.
+use crate::dma::{self, DmaBuffer};
+use crate::memory::{self, PAGE_SIZE};
+use crate::pci::{Bar, PciDevice};
+use core::ptr::{read_volatile, write_volatile};
+use x86_64::PhysAddr;
+
+pub const VENDOR_ID: u16 = 0x1AF4;
+
+pub const STATUS_ACKNOWLEDGE: u8 = 1;
+pub const STATUS_DRIVER: u8 = 2;
+pub const STATUS_DRIVER_OK: u8 = 4;
+pub const STATUS_FEATURES_OK: u8 = 8;
+pub const STATUS_FAILED: u8 = 128;
+
+pub const DESC_F_NEXT: u16 = 1;
+pub const DESC_F_WRITE: u16 = 2;
+
+// VIRTIO_F_VERSION_1 is feature bit 32, the first bit of the high word;
+// every virtio 1.0 driver has to accept it
+const FEATURE_VERSION_1: u32 = 1;
+
+#[repr(C)]
+struct Descriptor {
+    addr: u64,
+    len: u32,
+    flags: u16,
+    next: u16,
+}
+
+pub struct Virtqueue {
+    memory: DmaBuffer,
+    size: u16,
+    desc: *mut Descriptor,
+    avail: *mut u16,
+    used: *mut u16,
+    avail_idx: u16,
+    last_used_idx: u16,
+}
+
+// The pointers are into the queue's own DMA memory
+unsafe impl Send for Virtqueue {}
+
+impl Virtqueue {
+    // Legacy layout: descriptors and available ring, then the used ring on
+    // the next page boundary
+    fn bytes_for(size: u16) -> usize {
+        let size = size as usize;
+        let page = PAGE_SIZE as usize;
+        let driver_area = 16 * size + 6 + 2 * size;
+        let device_area = 6 + 8 * size;
+        align_up(driver_area, page) + align_up(device_area, page)
+    }
+    
+    fn used_offset(size: u16) -> usize {
+        align_up(16 * size as usize + 6 + 2 * size as usize, PAGE_SIZE as usize)
+    }
+    
+    pub fn new(size: u16) -> Option<Self> {
+        let memory = DmaBuffer::new(Self::bytes_for(size))?;
+        let base = memory.as_ptr();
+        unsafe {
+            Some(Self {
+                size,
+                desc: base as *mut Descriptor,
+                avail: base.add(16 * size as usize) as *mut u16,
+                used: base.add(Self::used_offset(size)) as *mut u16,
+                memory,
+                avail_idx: 0,
+                last_used_idx: 0,
+            })
+        }
+    }
+    
+    pub fn size(&self) -> u16 {
+        self.size
+    }
+    
+    // Bus addresses of the descriptor table, available ring and used ring;
+    // the legacy interface only takes the first, as a page number
+    pub fn desc_phys(&self) -> u64 {
+        self.memory.phys(0)
+    }
+    
+    pub fn driver_phys(&self) -> u64 {
+        self.memory.phys(16 * self.size as usize)
+    }
+    
+    pub fn device_phys(&self) -> u64 {
+        self.memory.phys(Self::used_offset(self.size))
+    }
+    
+    pub unsafe fn set_descriptor(&mut self, index: u16, addr: u64, len: u32, flags: u16, next: u16) {
+        write_volatile(self.desc.add(index as usize), Descriptor { addr, len, flags, next });
+    }
+    
+    // Publish the chain starting at `head` in the available ring
+    pub unsafe fn push_avail(&mut self, head: u16) {
+        let slot = (self.avail_idx % self.size) as usize;
+        write_volatile(self.avail.add(2 + slot), head);
+        dma::sync_for_device();
+        self.avail_idx = self.avail_idx.wrapping_add(1);
+        write_volatile(self.avail.add(1), self.avail_idx);
+        dma::sync_for_device();
+    }
+    
+    fn used_idx(&self) -> u16 {
+        unsafe { read_volatile(self.used.add(1)) }
+    }
+    
+    // True once the device has returned a chain we haven't looked at
+    pub fn has_used(&self) -> bool {
+        self.used_idx() != self.last_used_idx
+    }
+    
+    // Head of the next chain the device returned, if any
+    pub fn pop_used(&mut self) -> Option<u16> {
+        self.pop_used_len().map(|(head, _)| head)
+    }
+    
+    // Like `pop_used`, with the number of bytes the device wrote
+    pub fn pop_used_len(&mut self) -> Option<(u16, u32)> {
+        if !self.has_used() {
+            return None;
+        }
+        dma::sync_for_cpu();
+        // Used elements are { id: u32, len: u32 } after flags and idx
+        let slot = (self.last_used_idx % self.size) as usize;
+        let element = unsafe { self.used.add(2 + 4 * slot) as *const u32 };
+        let head = unsafe { read_volatile(element) } as u16;
+        let len = unsafe { read_volatile(element.add(1)) };
+        self.last_used_idx = self.last_used_idx.wrapping_add(1);
+        Some((head, len))
+    }
+}
+
+// Register layout of the common configuration structure
+const COMMON_DEVICE_FEATURE_SELECT: usize = 0x00;
+const COMMON_DEVICE_FEATURE: usize = 0x04;
+const COMMON_DRIVER_FEATURE_SELECT: usize = 0x08;
+const COMMON_DRIVER_FEATURE: usize = 0x0C;
+const COMMON_MSIX_CONFIG: usize = 0x10;
+const COMMON_DEVICE_STATUS: usize = 0x14;
+const COMMON_QUEUE_SELECT: usize = 0x16;
+const COMMON_QUEUE_SIZE: usize = 0x18;
+const COMMON_QUEUE_MSIX_VECTOR: usize = 0x1A;
+const COMMON_QUEUE_ENABLE: usize = 0x1C;
+const COMMON_QUEUE_NOTIFY_OFF: usize = 0x1E;
+const COMMON_QUEUE_DESC: usize = 0x20;
+const COMMON_QUEUE_DRIVER: usize = 0x28;
+const COMMON_QUEUE_DEVICE: usize = 0x30;
+
+// An MSI-X vector register that raises nothing
+pub const NO_VECTOR: u16 = 0xFFFF;
+
+const CAP_VENDOR: u8 = 0x09;
+const CAP_COMMON_CFG: u8 = 1;
+const CAP_NOTIFY_CFG: u8 = 2;
+const CAP_DEVICE_CFG: u8 = 4;
+
+// A virtio 1.0 device found through its PCI vendor capabilities. The
+// structures live in memory BARs, reached through the physical memory
+// window like everything else.
+pub struct PciTransport {
+    common: *mut u8,
+    notify: *mut u8,
+    notify_multiplier: u32,
+    device: *mut u8,
+}
+
+// The pointers refer to the device's own registers
+unsafe impl Send for PciTransport {}
+
+impl PciTransport {
+    pub fn new(pci: &PciDevice) -> Option<Self> {
+        let mut common = None;
+        let mut notify = None;
+        let mut notify_multiplier = 0;
+        let mut device = None;
+        for offset in pci.capabilities(CAP_VENDOR) {
+            let cfg_type = pci.read_u8(offset + 3);
+            let bar = match pci.bar(pci.read_u8(offset + 4)) {
+                Bar::Memory(addr) => addr,
+                _ => continue,
+            };
+            let addr = bar + pci.read_u32(offset + 8) as u64;
+            let ptr = memory::phys_to_virt(PhysAddr::new(addr)).as_mut_ptr::<u8>();
+            match cfg_type {
+                CAP_COMMON_CFG => common = common.or(Some(ptr)),
+                CAP_NOTIFY_CFG if notify.is_none() => {
+                    notify = Some(ptr);
+                    notify_multiplier = pci.read_u32(offset + 16);
+                }
+                CAP_DEVICE_CFG => device = device.or(Some(ptr)),
+                _ => {}
+            }
+        }
+        pci.enable_bus_master();
+        Some(Self { common: common?, notify: notify?, notify_multiplier, device: device? })
+    }
+    
+    unsafe fn common8(&self, offset: usize) -> *mut u8 {
+        self.common.add(offset)
+    }
+    
+    unsafe fn common16(&self, offset: usize) -> *mut u16 {
+        self.common.add(offset) as *mut u16
+    }
+    
+    unsafe fn common32(&self, offset: usize) -> *mut u32 {
+        self.common.add(offset) as *mut u32
+    }
+    
+    pub fn set_status(&mut self, status: u8) {
+        unsafe { write_volatile(self.common8(COMMON_DEVICE_STATUS), status) }
+    }
+    
+    pub fn status(&self) -> u8 {
+        unsafe { read_volatile(self.common8(COMMON_DEVICE_STATUS)) }
+    }
+    
+    // Reset and feature negotiation. Only VERSION_1 is accepted on top of
+    // `wanted` (low 32 feature bits).
+    pub fn begin_init(&mut self, wanted: u32) -> bool {
+        self.set_status(0);
+        self.set_status(STATUS_ACKNOWLEDGE);
+        self.set_status(STATUS_ACKNOWLEDGE | STATUS_DRIVER);
+        unsafe {
+            write_volatile(self.common32(COMMON_DEVICE_FEATURE_SELECT), 0);
+            let low = read_volatile(self.common32(COMMON_DEVICE_FEATURE));
+            write_volatile(self.common32(COMMON_DEVICE_FEATURE_SELECT), 1);
+            let high = read_volatile(self.common32(COMMON_DEVICE_FEATURE));
+            if high & FEATURE_VERSION_1 == 0 {
+                self.set_status(STATUS_FAILED);
+                return false;
+            }
+            write_volatile(self.common32(COMMON_DRIVER_FEATURE_SELECT), 0);
+            write_volatile(self.common32(COMMON_DRIVER_FEATURE), low & wanted);
+            write_volatile(self.common32(COMMON_DRIVER_FEATURE_SELECT), 1);
+            write_volatile(self.common32(COMMON_DRIVER_FEATURE), FEATURE_VERSION_1);
+        }
+        self.set_status(STATUS_ACKNOWLEDGE | STATUS_DRIVER | STATUS_FEATURES_OK);
+        if self.status() & STATUS_FEATURES_OK == 0 {
+            self.set_status(STATUS_FAILED);
+            return false;
+        }
+        true
+    }
+    
+    // Allocates and enables queue `index` with up to `max_size` entries
+    pub fn setup_queue(&mut self, index: u16, max_size: u16) -> Option<Virtqueue> {
+        unsafe {
+            write_volatile(self.common16(COMMON_QUEUE_SELECT), index);
+            let size = read_volatile(self.common16(COMMON_QUEUE_SIZE)).min(max_size);
+            if size == 0 {
+                return None;
+            }
+            write_volatile(self.common16(COMMON_QUEUE_SIZE), size);
+            
+            let queue = Virtqueue::new(size)?;
+            write_volatile(self.common32(COMMON_QUEUE_DESC) as *mut u64, queue.desc_phys());
+            write_volatile(self.common32(COMMON_QUEUE_DRIVER) as *mut u64, queue.driver_phys());
+            write_volatile(self.common32(COMMON_QUEUE_DEVICE) as *mut u64, queue.device_phys());
+            write_volatile(self.common16(COMMON_QUEUE_ENABLE), 1);
+            Some(queue)
+        }
+    }
+    
+    // With MSI-X on, has queue `index` raise table entry `entry` and
+    // configuration changes raise nothing; false when the device couldn't
+    // take the entry
+    pub fn set_queue_vector(&mut self, index: u16, entry: u16) -> bool {
+        unsafe {
+            write_volatile(self.common16(COMMON_MSIX_CONFIG), NO_VECTOR);
+            write_volatile(self.common16(COMMON_QUEUE_SELECT), index);
+            write_volatile(self.common16(COMMON_QUEUE_MSIX_VECTOR), entry);
+            read_volatile(self.common16(COMMON_QUEUE_MSIX_VECTOR)) == entry
+        }
+    }
+    
+    pub fn finish_init(&mut self) {
+        self.set_status(STATUS_ACKNOWLEDGE | STATUS_DRIVER | STATUS_FEATURES_OK | STATUS_DRIVER_OK);
+    }
+    
+    pub fn notify(&mut self, index: u16) {
+        unsafe {
+            write_volatile(self.common16(COMMON_QUEUE_SELECT), index);
+            let offset = read_volatile(self.common16(COMMON_QUEUE_NOTIFY_OFF)) as usize;
+            let doorbell = self.notify.add(offset * self.notify_multiplier as usize) as *mut u16;
+            write_volatile(doorbell, index);
+        }
+    }
+    
+    pub fn read_device_u8(&self, offset: usize) -> u8 {
+        unsafe { read_volatile(self.device.add(offset)) }
+    }
+    
+    pub fn write_device_u8(&mut self, offset: usize, value: u8) {
+        unsafe { write_volatile(self.device.add(offset), value) }
+    }
+    
+    pub fn read_device_u32(&self, offset: usize) -> u32 {
+        unsafe { read_volatile(self.device.add(offset) as *const u32) }
+    }
+}
+
+pub fn align_up(value: usize, align: usize) -> usize {
+    (value + align - 1) & !(align - 1)
+}
\ No newline at end of file
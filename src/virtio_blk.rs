@@ -0,0 +1,363 @@
+// src/virtio_blk.rs
+// Legacy (virtio 0.9.5) PCI block device, which is what QEMU exposes for
+// `-drive if=virtio`. One request is in flight at a time and data goes
+// through a bounce buffer, so callers can pass ordinary heap slices.
+// Completions are polled for; the interrupt, by MSI-X where the device and
+// the local APIC allow and on the legacy line otherwise, is only counted.
+use crate::block::{self, BlockDevice, BlockError, SECTOR_SIZE};
+use crate::dma::DmaBuffer;
+use crate::memory::PAGE_SIZE;
+use crate::pci::{self, Bar, MessageInterrupt, PciDevice};
+use crate::virtio::{self, Virtqueue, DESC_F_NEXT, DESC_F_WRITE, STATUS_ACKNOWLEDGE, STATUS_DRIVER, STATUS_DRIVER_OK, STATUS_FAILED};
+use crate::{interrupts, kinfo, kwarn};
+use alloc::format;
+use alloc::string::String;
+use core::ptr::{read_volatile, write_volatile};
+use core::sync::atomic::{AtomicU16, AtomicU64, Ordering};
+use x86_64::instructions::port::Port;
+
+const VIRTIO_BLK_LEGACY_ID: u16 = 0x1001;
+
+// Legacy register offsets from BAR0
+const REG_DEVICE_FEATURES: u16 = 0x00;
+const REG_GUEST_FEATURES: u16 = 0x04;
+const REG_QUEUE_PFN: u16 = 0x08;
+const REG_QUEUE_SIZE: u16 = 0x0C;
+const REG_QUEUE_SELECT: u16 = 0x0E;
+const REG_QUEUE_NOTIFY: u16 = 0x10;
+const REG_DEVICE_STATUS: u16 = 0x12;
+const REG_ISR_STATUS: u16 = 0x13;
+// Only there with MSI-X on, which moves the device configuration after them
+const REG_CONFIG_VECTOR: u16 = 0x14;
+const REG_QUEUE_VECTOR: u16 = 0x16;
+const DEVICE_CONFIG: u16 = 0x14;
+const DEVICE_CONFIG_MSIX: u16 = 0x18;
+const NO_VECTOR: u16 = 0xFFFF;
+
+// Device configuration offsets
+const CFG_CAPACITY: u16 = 0x00;
+
+const FEATURE_RO: u32 = 1 << 5;
+const FEATURE_FLUSH: u32 = 1 << 9;
+
+const REQ_IN: u32 = 0;
+const REQ_OUT: u32 = 1;
+const REQ_FLUSH: u32 = 4;
+
+const BOUNCE_PAGES: usize = 8;
+const MAX_SECTORS_PER_REQUEST: usize = BOUNCE_PAGES * PAGE_SIZE as usize / SECTOR_SIZE;
+const COMPLETION_SPINS: u32 = 50_000_000;
+
+pub static INTERRUPT_COUNT: AtomicU64 = AtomicU64::new(0);
+static ISR_PORT: AtomicU16 = AtomicU16::new(0);
+
+#[repr(C)]
+struct RequestHeader {
+    request_type: u32,
+    reserved: u32,
+    sector: u64,
+}
+
+pub struct VirtioBlk {
+    name: String,
+    io_base: u16,
+    // Where the device configuration starts
+    config: u16,
+    // The MSI vector and how the device raises it, when it isn't on its line
+    interrupt: Option<(u8, MessageInterrupt)>,
+    queue: Virtqueue,
+    bounce: DmaBuffer,
+    capacity: u64,
+    read_only: bool,
+    supports_flush: bool,
+    // Reset after a request timed out; it takes no more
+    failed: bool,
+    pub requests: u64,
+}
+
+impl VirtioBlk {
+    pub fn new(device: &PciDevice, index: usize) -> Option<Self> {
+        let io_base = match device.bar(0) {
+            Bar::Io(port) => port,
+            _ => return None,
+        };
+        device.enable_bus_master();
+        
+        let vector = interrupts::allocate_msi_vector("virtio-blk", handle_message);
+        let mode = vector.and_then(|vector| device.enable_message_interrupt(vector));
+        let mut this = match unsafe { Self::reset(io_base, index, mode == Some(MessageInterrupt::MsiX)) } {
+            Some(this) => this,
+            None => {
+                if let Some(vector) = vector {
+                    interrupts::free_msi_vector(vector);
+                }
+                return None;
+            }
+        };
+        match (vector, mode) {
+            (Some(vector), Some(mode)) => this.interrupt = Some((vector, mode)),
+            (Some(vector), None) => interrupts::free_msi_vector(vector),
+            _ => {}
+        }
+        this.capacity = this.read_capacity();
+        Some(this)
+    }
+    
+    unsafe fn reset(io_base: u16, index: usize, msix: bool) -> Option<Self> {
+        write8(io_base, REG_DEVICE_STATUS, 0);
+        write8(io_base, REG_DEVICE_STATUS, STATUS_ACKNOWLEDGE);
+        write8(io_base, REG_DEVICE_STATUS, STATUS_ACKNOWLEDGE | STATUS_DRIVER);
+        
+        let offered = read32(io_base, REG_DEVICE_FEATURES);
+        let accepted = offered & (FEATURE_RO | FEATURE_FLUSH);
+        write32(io_base, REG_GUEST_FEATURES, accepted);
+        
+        write16(io_base, REG_QUEUE_SELECT, 0);
+        let size = read16(io_base, REG_QUEUE_SIZE);
+        if size < 3 {
+            write8(io_base, REG_DEVICE_STATUS, STATUS_FAILED);
+            return None;
+        }
+        
+        let (queue, bounce) = match (Virtqueue::new(size), DmaBuffer::new((1 + BOUNCE_PAGES) * PAGE_SIZE as usize)) {
+            (Some(queue), Some(bounce)) => (queue, bounce),
+            _ => {
+                write8(io_base, REG_DEVICE_STATUS, STATUS_FAILED);
+                return None;
+            }
+        };
+        write32(io_base, REG_QUEUE_PFN, (queue.desc_phys() / PAGE_SIZE) as u32);
+        if msix {
+            // The queue raises table entry 0; configuration changes nothing
+            write16(io_base, REG_CONFIG_VECTOR, NO_VECTOR);
+            write16(io_base, REG_QUEUE_VECTOR, 0);
+        }
+        
+        write8(
+            io_base,
+            REG_DEVICE_STATUS,
+            STATUS_ACKNOWLEDGE | STATUS_DRIVER | STATUS_DRIVER_OK,
+        );
+        
+        Some(Self {
+            name: disk_name(index),
+            io_base,
+            config: if msix { DEVICE_CONFIG_MSIX } else { DEVICE_CONFIG },
+            interrupt: None,
+            queue,
+            bounce,
+            capacity: 0,
+            read_only: accepted & FEATURE_RO != 0,
+            supports_flush: accepted & FEATURE_FLUSH != 0,
+            failed: false,
+            requests: 0,
+        })
+    }
+    
+    fn read_capacity(&self) -> u64 {
+        unsafe {
+            let low = read32(self.io_base, self.config + CFG_CAPACITY) as u64;
+            let high = read32(self.io_base, self.config + CFG_CAPACITY + 4) as u64;
+            (high << 32) | low
+        }
+    }
+    
+    // Layout of the bounce area: header at 0, status byte at 16, data from
+    // the second page onwards.
+    fn submit(&mut self, request_type: u32, sector: u64, data_len: usize) -> Result<(), BlockError> {
+        if self.failed {
+            return Err(BlockError::DeviceError);
+        }
+        let header_phys = self.bounce.phys(0);
+        let status_phys = self.bounce.phys(16);
+        let data_phys = self.bounce.phys(PAGE_SIZE as usize);
+        
+        unsafe {
+            write_volatile(
+                self.bounce.as_ptr() as *mut RequestHeader,
+                RequestHeader { request_type, reserved: 0, sector },
+            );
+            write_volatile(self.bounce.as_ptr().add(16), 0xFF);
+            
+            let q = &mut self.queue;
+            if data_len > 0 {
+                let data_flags = if request_type == REQ_IN { DESC_F_WRITE } else { 0 };
+                q.set_descriptor(0, header_phys, 16, DESC_F_NEXT, 1);
+                q.set_descriptor(1, data_phys, data_len as u32, data_flags | DESC_F_NEXT, 2);
+            } else {
+                q.set_descriptor(0, header_phys, 16, DESC_F_NEXT, 2);
+            }
+            q.set_descriptor(2, status_phys, 1, DESC_F_WRITE, 0);
+            q.push_avail(0);
+            
+            write16(self.io_base, REG_QUEUE_NOTIFY, 0);
+        }
+        self.requests += 1;
+        
+        let mut spins = 0;
+        while !self.queue.has_used() {
+            spins += 1;
+            if spins > COMPLETION_SPINS {
+                // The request is still posted; resetting the device is the
+                // only way to be sure it won't complete into the bounce
+                // buffer later, or leave the used ring a request behind
+                unsafe { write8(self.io_base, REG_DEVICE_STATUS, 0) };
+                self.failed = true;
+                kwarn!("virtio-blk: {} timed out and was reset; it takes no more requests", self.name);
+                return Err(BlockError::Timeout);
+            }
+            core::hint::spin_loop();
+        }
+        self.queue.pop_used();
+        
+        match unsafe { read_volatile(self.bounce.as_ptr().add(16)) } {
+            0 => Ok(()),
+            _ => Err(BlockError::DeviceError),
+        }
+    }
+    
+    fn bounce_data(&mut self) -> *mut u8 {
+        unsafe { self.bounce.as_ptr().add(PAGE_SIZE as usize) }
+    }
+}
+
+impl BlockDevice for VirtioBlk {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    
+    fn sector_count(&self) -> u64 {
+        self.capacity
+    }
+    
+    fn read_sectors(&mut self, lba: u64, buf: &mut [u8]) -> Result<(), BlockError> {
+        block::check_request(self, lba, buf.len())?;
+        
+        for (i, chunk) in buf.chunks_mut(MAX_SECTORS_PER_REQUEST * SECTOR_SIZE).enumerate() {
+            let sector = lba + (i * MAX_SECTORS_PER_REQUEST) as u64;
+            self.submit(REQ_IN, sector, chunk.len())?;
+            unsafe {
+                core::ptr::copy_nonoverlapping(self.bounce_data(), chunk.as_mut_ptr(), chunk.len());
+            }
+        }
+        Ok(())
+    }
+    
+    fn write_sectors(&mut self, lba: u64, buf: &[u8]) -> Result<(), BlockError> {
+        if self.read_only {
+            return Err(BlockError::ReadOnly);
+        }
+        block::check_request(self, lba, buf.len())?;
+        
+        for (i, chunk) in buf.chunks(MAX_SECTORS_PER_REQUEST * SECTOR_SIZE).enumerate() {
+            let sector = lba + (i * MAX_SECTORS_PER_REQUEST) as u64;
+            unsafe {
+                core::ptr::copy_nonoverlapping(chunk.as_ptr(), self.bounce_data(), chunk.len());
+            }
+            self.submit(REQ_OUT, sector, chunk.len())?;
+        }
+        Ok(())
+    }
+    
+    fn flush(&mut self) -> Result<(), BlockError> {
+        if !self.supports_flush {
+            return Ok(());
+        }
+        self.submit(REQ_FLUSH, 0, 0)
+    }
+    
+    fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+}
+
+// vda to vdz, then vdaa, vdab and on, as Linux names them
+fn disk_name(index: usize) -> String {
+    let mut letters = String::new();
+    let mut n = index + 1;
+    while n > 0 {
+        n -= 1;
+        letters.insert(0, (b'a' + (n % 26) as u8) as char);
+        n /= 26;
+    }
+    format!("vd{}", letters)
+}
+
+// Reading the ISR register acknowledges the interrupt and deasserts the line
+fn handle_interrupt() -> bool {
+    let port = ISR_PORT.load(Ordering::Relaxed);
+    if port == 0 {
+        return false;
+    }
+    let status = unsafe { Port::<u8>::new(port).read() };
+    if status & 1 != 0 {
+        INTERRUPT_COUNT.fetch_add(1, Ordering::Relaxed);
+    }
+    status & 1 != 0
+}
+
+// A message is always this device's, and needs no acknowledging
+fn handle_message() -> bool {
+    INTERRUPT_COUNT.fetch_add(1, Ordering::Relaxed);
+    true
+}
+
+pub fn init() -> usize {
+    let mut found = 0;
+    
+    for device in pci::devices() {
+        if device.vendor_id != virtio::VENDOR_ID || device.device_id != VIRTIO_BLK_LEGACY_ID {
+            continue;
+        }
+        
+        match VirtioBlk::new(&device, found) {
+            Some(driver) => {
+                let interrupt = match driver.interrupt {
+                    Some((vector, mode)) => format!("{} vector {:#x}", mode.name(), vector),
+                    None => format!("irq {}", device.interrupt_line),
+                };
+                kinfo!(
+                    "virtio-blk: {} at {:02x}:{:02x}.{} {} ({} sectors{})",
+                    driver.name,
+                    device.bus,
+                    device.device,
+                    device.function,
+                    interrupt,
+                    driver.capacity,
+                    if driver.read_only { ", read-only" } else { "" }
+                );
+                // Only the first controller left on its line is tracked
+                // there; the others are polled
+                if driver.interrupt.is_none() && ISR_PORT.load(Ordering::Relaxed) == 0 {
+                    ISR_PORT.store(driver.io_base + REG_ISR_STATUS, Ordering::Relaxed);
+                    interrupts::register_irq(device.interrupt_line, "virtio-blk", handle_interrupt);
+                }
+                block::register(driver);
+                found += 1;
+            }
+            None => kwarn!("virtio-blk: failed to initialize {:02x}:{:02x}", device.bus, device.device),
+        }
+    }
+    
+    found
+}
+
+unsafe fn read16(base: u16, reg: u16) -> u16 {
+    Port::<u16>::new(base + reg).read()
+}
+
+unsafe fn read32(base: u16, reg: u16) -> u32 {
+    Port::<u32>::new(base + reg).read()
+}
+
+unsafe fn write8(base: u16, reg: u16, value: u8) {
+    Port::<u8>::new(base + reg).write(value)
+}
+
+unsafe fn write16(base: u16, reg: u16, value: u16) {
+    Port::<u16>::new(base + reg).write(value)
+}
+
+unsafe fn write32(base: u16, reg: u16, value: u32) {
+    Port::<u32>::new(base + reg).write(value)
+}
\ No newline at end of file
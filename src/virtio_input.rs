@@ -0,0 +1,216 @@
+// src/virtio_input.rs
+// virtio-input devices (QEMU's virtio-multitouch-pci, virtio-tablet-pci).
+// The device sends evdev events; multitouch slots are collected into
+// frames on each sync report and fed to the trackpad gesture recognizer.
+// Events are polled from the desktop loop, so no interrupt is used.
+use crate::dma::DmaBuffer;
+use crate::memory::PAGE_SIZE;
+use crate::pci;
+use crate::trackpad::{Gesture, GestureRecognizer, PAD_UNITS};
+use crate::virtio::{self, PciTransport, Virtqueue, DESC_F_WRITE};
+use crate::{kinfo, kwarn};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ptr::read_volatile;
+use crate::sync::Mutex;
+
+const VIRTIO_INPUT_ID: u16 = 0x1052;
+
+// Device configuration: select, subsel and size, then the payload at 8
+const CFG_SELECT: usize = 0;
+const CFG_SUBSEL: usize = 1;
+const CFG_SIZE: usize = 2;
+const CFG_DATA: usize = 8;
+const CFG_ID_NAME: u8 = 0x01;
+const CFG_ABS_INFO: u8 = 0x12;
+
+// evdev event types and codes
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_ABS: u16 = 0x03;
+const SYN_REPORT: u16 = 0;
+const BTN_TOUCH: u16 = 0x14A;
+const ABS_X: u16 = 0x00;
+const ABS_Y: u16 = 0x01;
+const ABS_MT_SLOT: u16 = 0x2F;
+const ABS_MT_POSITION_X: u16 = 0x35;
+const ABS_MT_POSITION_Y: u16 = 0x36;
+const ABS_MT_TRACKING_ID: u16 = 0x39;
+
+const EVENT_QUEUE: u16 = 0;
+const MAX_EVENT_BUFFERS: u16 = 64;
+const MAX_SLOTS: usize = 5;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct InputEvent {
+    event_type: u16,
+    code: u16,
+    value: u32,
+}
+
+#[derive(Clone, Copy, Default)]
+struct Slot {
+    active: bool,
+    x: i32,
+    y: i32,
+}
+
+// Range the device reports an axis in
+#[derive(Clone, Copy)]
+struct Axis {
+    min: i32,
+    max: i32,
+}
+
+impl Axis {
+    fn normalize(&self, value: i32) -> i32 {
+        let span = (self.max - self.min).max(1) as i64;
+        ((value - self.min) as i64 * PAD_UNITS as i64 / span) as i32
+    }
+}
+
+pub struct VirtioInput {
+    name: String,
+    transport: PciTransport,
+    queue: Virtqueue,
+    events: DmaBuffer,
+    // Devices without multitouch axes report one contact through ABS_X/Y
+    multitouch: bool,
+    axes: (Axis, Axis),
+    slots: [Slot; MAX_SLOTS],
+    current_slot: usize,
+    recognizer: GestureRecognizer,
+}
+
+static DEVICES: Mutex<Vec<VirtioInput>> = Mutex::new(Vec::new());
+
+impl VirtioInput {
+    fn new(device: &pci::PciDevice) -> Option<Self> {
+        let mut transport = PciTransport::new(device)?;
+        if !transport.begin_init(0) {
+            return None;
+        }
+        
+        let name = String::from_utf8_lossy(&config(&mut transport, CFG_ID_NAME, 0)).into_owned();
+        let (multitouch, axes) = match (abs_info(&mut transport, ABS_MT_POSITION_X), abs_info(&mut transport, ABS_MT_POSITION_Y)) {
+            (Some(x), Some(y)) => (true, (x, y)),
+            _ => (false, (abs_info(&mut transport, ABS_X)?, abs_info(&mut transport, ABS_Y)?)),
+        };
+        
+        let mut queue = transport.setup_queue(EVENT_QUEUE, MAX_EVENT_BUFFERS)?;
+        let buffers = queue.size();
+        let event_size = core::mem::size_of::<InputEvent>();
+        debug_assert!(buffers as usize * event_size <= PAGE_SIZE as usize);
+        let events = DmaBuffer::new(buffers as usize * event_size)?;
+        // Every descriptor is one event buffer the device can fill
+        for i in 0..buffers {
+            unsafe {
+                queue.set_descriptor(i, events.phys(i as usize * event_size), event_size as u32, DESC_F_WRITE, 0);
+                queue.push_avail(i);
+            }
+        }
+        transport.finish_init();
+        transport.notify(EVENT_QUEUE);
+        
+        Some(Self {
+            name,
+            transport,
+            queue,
+            events,
+            multitouch,
+            axes,
+            slots: [Slot::default(); MAX_SLOTS],
+            current_slot: 0,
+            recognizer: GestureRecognizer::new(),
+        })
+    }
+    
+    // Drains the event queue, handing every buffer straight back
+    fn poll(&mut self, gestures: &mut Vec<Gesture>) {
+        let mut returned = false;
+        while let Some(head) = self.queue.pop_used() {
+            let event = unsafe { read_volatile((self.events.as_ptr() as *const InputEvent).add(head as usize)) };
+            if let Some(gesture) = self.handle_event(event) {
+                gestures.push(gesture);
+            }
+            unsafe { self.queue.push_avail(head) };
+            returned = true;
+        }
+        if returned {
+            self.transport.notify(EVENT_QUEUE);
+        }
+    }
+    
+    fn handle_event(&mut self, event: InputEvent) -> Option<Gesture> {
+        let value = event.value as i32;
+        match (event.event_type, event.code) {
+            (EV_ABS, ABS_MT_SLOT) if self.multitouch => self.current_slot = (value.max(0) as usize).min(MAX_SLOTS - 1),
+            // A tracking ID of -1 means the finger in this slot lifted
+            (EV_ABS, ABS_MT_TRACKING_ID) if self.multitouch => self.slots[self.current_slot].active = value >= 0,
+            (EV_ABS, ABS_MT_POSITION_X) if self.multitouch => self.slots[self.current_slot].x = self.axes.0.normalize(value),
+            (EV_ABS, ABS_MT_POSITION_Y) if self.multitouch => self.slots[self.current_slot].y = self.axes.1.normalize(value),
+            (EV_ABS, ABS_X) if !self.multitouch => self.slots[0].x = self.axes.0.normalize(value),
+            (EV_ABS, ABS_Y) if !self.multitouch => self.slots[0].y = self.axes.1.normalize(value),
+            (EV_KEY, BTN_TOUCH) if !self.multitouch => self.slots[0].active = value != 0,
+            (EV_SYN, SYN_REPORT) => {
+                let contacts: Vec<(i32, i32)> = self.slots.iter().filter(|s| s.active).map(|s| (s.x, s.y)).collect();
+                return self.recognizer.frame(&contacts);
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+// Reads one device configuration entry
+fn config(transport: &mut PciTransport, select: u8, subsel: u8) -> Vec<u8> {
+    transport.write_device_u8(CFG_SELECT, select);
+    transport.write_device_u8(CFG_SUBSEL, subsel);
+    let size = transport.read_device_u8(CFG_SIZE) as usize;
+    (0..size).map(|i| transport.read_device_u8(CFG_DATA + i)).collect()
+}
+
+fn abs_info(transport: &mut PciTransport, axis: u16) -> Option<Axis> {
+    transport.write_device_u8(CFG_SELECT, CFG_ABS_INFO);
+    transport.write_device_u8(CFG_SUBSEL, axis as u8);
+    if transport.read_device_u8(CFG_SIZE) == 0 {
+        return None;
+    }
+    let min = transport.read_device_u32(CFG_DATA) as i32;
+    let max = transport.read_device_u32(CFG_DATA + 4) as i32;
+    Some(Axis { min, max })
+}
+
+pub fn init() -> usize {
+    let mut devices = DEVICES.lock();
+    for device in pci::devices() {
+        if device.vendor_id != virtio::VENDOR_ID || device.device_id != VIRTIO_INPUT_ID {
+            continue;
+        }
+        match VirtioInput::new(&device) {
+            Some(input) => {
+                kinfo!(
+                    "virtio-input: \"{}\" at {:02x}:{:02x}.{} ({})",
+                    input.name,
+                    device.bus,
+                    device.device,
+                    device.function,
+                    if input.multitouch { "multitouch" } else { "single touch" }
+                );
+                devices.push(input);
+            }
+            None => kwarn!("virtio-input: failed to initialize {:02x}:{:02x}", device.bus, device.device),
+        }
+    }
+    devices.len()
+}
+
+// Gestures recognized since the last call, from every input device
+pub fn poll() -> Vec<Gesture> {
+    let mut gestures = Vec::new();
+    for device in DEVICES.lock().iter_mut() {
+        device.poll(&mut gestures);
+    }
+    gestures
+}
\ No newline at end of file
@@ -0,0 +1,169 @@
+// src/virtio_net.rs
+// virtio-net network cards (QEMU's virtio-net-pci). Every receive buffer
+// is posted up front and handed back as soon as its frame is copied out;
+// transmit buffers are reclaimed when the device returns them. Like the
+// other virtio drivers it is polled; the receive queue's interrupt, by
+// MSI-X or MSI when there's a vector for it, is only counted.
+use crate::dma::DmaBuffer;
+use crate::net::{MacAddr, NetDriver, NetError};
+use crate::pci::{self, MessageInterrupt};
+use crate::virtio::{self, PciTransport, Virtqueue, DESC_F_WRITE};
+use crate::{interrupts, kinfo, kwarn};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+// Transitional and modern device IDs
+const VIRTIO_NET_IDS: [u16; 2] = [0x1000, 0x1041];
+
+// The MAC address is in the device configuration
+const FEATURE_MAC: u32 = 1 << 5;
+const CFG_MAC: usize = 0;
+
+const RECEIVE_QUEUE: u16 = 0;
+const TRANSMIT_QUEUE: u16 = 1;
+const MAX_BUFFERS: u16 = 16;
+// Every buffer holds the virtio-net header and one Ethernet frame
+const BUFFER_SIZE: usize = 2048;
+// struct virtio_net_hdr with VERSION_1: always 12 bytes
+const HEADER_SIZE: usize = 12;
+pub const MAX_FRAME: usize = 1514;
+
+pub static INTERRUPT_COUNT: AtomicU64 = AtomicU64::new(0);
+
+// One physically contiguous run of fixed-size DMA buffers
+struct Buffers(DmaBuffer);
+
+impl Buffers {
+    fn new(count: u16) -> Option<Self> {
+        DmaBuffer::new(count as usize * BUFFER_SIZE).map(Buffers)
+    }
+    
+    fn phys(&self, index: u16) -> u64 {
+        self.0.phys(index as usize * BUFFER_SIZE)
+    }
+    
+    fn slice(&mut self, index: u16) -> &mut [u8] {
+        let start = index as usize * BUFFER_SIZE;
+        &mut self.0.as_mut_slice()[start..start + BUFFER_SIZE]
+    }
+}
+
+pub struct VirtioNet {
+    mac: MacAddr,
+    transport: PciTransport,
+    rx: Virtqueue,
+    rx_buffers: Buffers,
+    tx: Virtqueue,
+    tx_buffers: Buffers,
+    // Transmit descriptors the device has handed back
+    tx_free: Vec<u16>,
+    // The MSI vector and how the device raises it; None when polled only
+    interrupt: Option<(u8, MessageInterrupt)>,
+}
+
+impl VirtioNet {
+    fn new(device: &pci::PciDevice, vector: Option<u8>) -> Option<Self> {
+        let mut transport = PciTransport::new(device)?;
+        if !transport.begin_init(FEATURE_MAC) {
+            return None;
+        }
+        let mut mac = [0; 6];
+        for (i, byte) in mac.iter_mut().enumerate() {
+            *byte = transport.read_device_u8(CFG_MAC + i);
+        }
+        
+        let mut rx = transport.setup_queue(RECEIVE_QUEUE, MAX_BUFFERS)?;
+        let tx = transport.setup_queue(TRANSMIT_QUEUE, MAX_BUFFERS)?;
+        let mut interrupt = vector.and_then(|vector| Some((vector, device.enable_message_interrupt(vector)?)));
+        if let Some((_, MessageInterrupt::MsiX)) = interrupt {
+            if !transport.set_queue_vector(RECEIVE_QUEUE, 0) {
+                interrupt = None;
+            }
+        }
+        let rx_buffers = Buffers::new(rx.size())?;
+        let tx_buffers = Buffers::new(tx.size())?;
+        for i in 0..rx.size() {
+            unsafe {
+                rx.set_descriptor(i, rx_buffers.phys(i), BUFFER_SIZE as u32, DESC_F_WRITE, 0);
+                rx.push_avail(i);
+            }
+        }
+        transport.finish_init();
+        transport.notify(RECEIVE_QUEUE);
+        
+        let tx_free = (0..tx.size()).collect();
+        Some(Self { mac: MacAddr(mac), transport, rx, rx_buffers, tx, tx_buffers, tx_free, interrupt })
+    }
+}
+
+impl NetDriver for VirtioNet {
+    fn name(&self) -> &'static str {
+        "virtio-net"
+    }
+    
+    fn mac(&self) -> MacAddr {
+        self.mac
+    }
+    
+    fn send(&mut self, frame: &[u8]) -> Result<(), NetError> {
+        if frame.len() > MAX_FRAME {
+            return Err(NetError::TooLong);
+        }
+        while let Some(head) = self.tx.pop_used() {
+            self.tx_free.push(head);
+        }
+        let index = self.tx_free.pop().ok_or(NetError::NoBuffers)?;
+        let buffer = self.tx_buffers.slice(index);
+        // No checksum offload or segmentation, so the header is all zeroes
+        buffer[..HEADER_SIZE].fill(0);
+        buffer[HEADER_SIZE..HEADER_SIZE + frame.len()].copy_from_slice(frame);
+        unsafe {
+            self.tx.set_descriptor(index, self.tx_buffers.phys(index), (HEADER_SIZE + frame.len()) as u32, 0, 0);
+            self.tx.push_avail(index);
+        }
+        self.transport.notify(TRANSMIT_QUEUE);
+        Ok(())
+    }
+    
+    fn receive(&mut self) -> Option<Vec<u8>> {
+        let (head, len) = self.rx.pop_used_len()?;
+        let len = (len as usize).clamp(HEADER_SIZE, BUFFER_SIZE);
+        let frame = self.rx_buffers.slice(head)[HEADER_SIZE..len].to_vec();
+        unsafe { self.rx.push_avail(head) };
+        self.transport.notify(RECEIVE_QUEUE);
+        Some(frame)
+    }
+}
+
+fn handle_message() -> bool {
+    INTERRUPT_COUNT.fetch_add(1, Ordering::Relaxed);
+    true
+}
+
+// The first working card, if any
+pub fn probe() -> Option<VirtioNet> {
+    for device in pci::devices() {
+        if device.vendor_id != virtio::VENDOR_ID || !VIRTIO_NET_IDS.contains(&device.device_id) {
+            continue;
+        }
+        let vector = interrupts::allocate_msi_vector("virtio-net", handle_message);
+        let card = VirtioNet::new(&device, vector);
+        if let Some(vector) = vector.filter(|_| card.as_ref().and_then(|card| card.interrupt).is_none()) {
+            interrupts::free_msi_vector(vector);
+        }
+        match card {
+            Some(card) => {
+                let interrupt = match card.interrupt {
+                    Some((vector, mode)) => format!("{} vector {:#x}", mode.name(), vector),
+                    None => String::from("polled"),
+                };
+                kinfo!("virtio-net: {} at {:02x}:{:02x}.{} {}", card.mac, device.bus, device.device, device.function, interrupt);
+                return Some(card);
+            }
+            None => kwarn!("virtio-net: failed to initialize {:02x}:{:02x}", device.bus, device.device),
+        }
+    }
+    None
+}
\ No newline at end of file
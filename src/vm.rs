@@ -0,0 +1,336 @@
+// src/vm.rs
+// Memory-mapped files and named shared-memory regions. Mappings are carved
+// out of a dedicated window of the kernel address space; file pages are
+// filled from the VFS (and so the block cache) the first time they are
+// touched, while shared regions are backed by frames allocated up front so
+// every mapping sees the same memory.
+use crate::memory::{self, PAGE_SIZE};
+use crate::{allocator, users};
+use crate::vfs::{self, Access, VfsError};
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::sync::Mutex;
+use x86_64::structures::idt::PageFaultErrorCode;
+use x86_64::structures::paging::{Page, PageTableFlags, PhysFrame, Size4KiB};
+use x86_64::VirtAddr;
+
+pub const MMAP_BASE: u64 = 0x_5555_0000_0000;
+pub const MMAP_SIZE: u64 = 0x_0010_0000_0000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VmError {
+    InvalidArgument,
+    NoMemory,
+    NotFound,
+    AlreadyExists,
+    PermissionDenied,
+    Fs(VfsError),
+}
+
+impl From<VfsError> for VmError {
+    fn from(err: VfsError) -> Self {
+        VmError::Fs(err)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protection {
+    ReadOnly,
+    ReadWrite,
+}
+
+#[derive(Clone)]
+enum Backing {
+    // Writable file mappings are shared: dirty pages go back to the file
+    File { path: String, offset: u64 },
+    Shared(String),
+}
+
+struct Mapping {
+    start: u64,
+    len: u64,
+    protection: Protection,
+    backing: Backing,
+}
+
+impl Mapping {
+    fn pages(&self) -> impl Iterator<Item = Page> {
+        let first = Page::containing_address(VirtAddr::new(self.start));
+        let last = Page::containing_address(VirtAddr::new(self.start + self.len - 1));
+        Page::range_inclusive(first, last)
+    }
+    
+    fn flags(&self) -> PageTableFlags {
+        match self.protection {
+            Protection::ReadOnly => PageTableFlags::PRESENT,
+            Protection::ReadWrite => PageTableFlags::PRESENT | PageTableFlags::WRITABLE,
+        }
+    }
+}
+
+struct SharedRegion {
+    size: u64,
+    frames: Vec<PhysFrame>,
+    map_count: usize,
+    unlinked: bool,
+}
+
+struct VmState {
+    next: u64,
+    mappings: Vec<Mapping>,
+}
+
+static STATE: Mutex<VmState> = Mutex::new(VmState { next: MMAP_BASE, mappings: Vec::new() });
+static REGIONS: Mutex<BTreeMap<String, SharedRegion>> = Mutex::new(BTreeMap::new());
+
+fn page_align(len: u64) -> u64 {
+    (len + PAGE_SIZE - 1) / PAGE_SIZE * PAGE_SIZE
+}
+
+// Hands out address ranges with an unmapped guard page between them.
+// Ranges are not reused; the window is large enough for that not to matter.
+fn reserve(state: &mut VmState, len: u64) -> Result<u64, VmError> {
+    let start = state.next;
+    let end = start + page_align(len) + PAGE_SIZE;
+    if end > MMAP_BASE + MMAP_SIZE {
+        return Err(VmError::NoMemory);
+    }
+    state.next = end;
+    Ok(start)
+}
+
+pub fn mmap_file(path: &str, offset: u64, len: u64, protection: Protection) -> Result<VirtAddr, VmError> {
+    if len == 0 || offset % PAGE_SIZE != 0 {
+        return Err(VmError::InvalidArgument);
+    }
+    
+    let meta = vfs::metadata(path)?;
+    if meta.is_dir() {
+        return Err(VmError::Fs(VfsError::IsADirectory));
+    }
+    let (uid, gid) = (users::current_uid(), users::current_gid());
+    if !meta.permits(uid, gid, Access::Read)
+        || (protection == Protection::ReadWrite && !meta.permits(uid, gid, Access::Write))
+    {
+        return Err(VmError::PermissionDenied);
+    }
+    
+    let mut state = STATE.lock();
+    let start = reserve(&mut state, len)?;
+    state.mappings.push(Mapping {
+        start,
+        len,
+        protection,
+        backing: Backing::File { path: vfs::normalize(path)?, offset },
+    });
+    Ok(VirtAddr::new(start))
+}
+
+pub fn shm_create(name: &str, size: u64) -> Result<(), VmError> {
+    if name.is_empty() || size == 0 {
+        return Err(VmError::InvalidArgument);
+    }
+    let mut regions = REGIONS.lock();
+    if regions.contains_key(name) {
+        return Err(VmError::AlreadyExists);
+    }
+    
+    let mut frames = Vec::new();
+    for _ in 0..page_align(size) / PAGE_SIZE {
+        match memory::allocate_zeroed_frame() {
+            Some(frame) => frames.push(frame),
+            None => {
+                frames.into_iter().for_each(memory::free_frame);
+                return Err(VmError::NoMemory);
+            }
+        }
+    }
+    
+    regions.insert(String::from(name), SharedRegion { size, frames, map_count: 0, unlinked: false });
+    Ok(())
+}
+
+pub fn shm_map(name: &str, protection: Protection) -> Result<VirtAddr, VmError> {
+    let mut regions = REGIONS.lock();
+    let region = regions.get_mut(name).filter(|r| !r.unlinked).ok_or(VmError::NotFound)?;
+    
+    let mut state = STATE.lock();
+    let start = reserve(&mut state, region.size)?;
+    let mapping = Mapping {
+        start,
+        len: region.size,
+        protection,
+        backing: Backing::Shared(String::from(name)),
+    };
+    for (page, frame) in mapping.pages().zip(region.frames.iter()) {
+        memory::map_page(page, *frame, mapping.flags()).map_err(|_| VmError::NoMemory)?;
+    }
+    
+    region.map_count += 1;
+    state.mappings.push(mapping);
+    Ok(VirtAddr::new(start))
+}
+
+// The name goes away immediately; the memory lives on until the last
+// mapping of it is removed.
+pub fn shm_unlink(name: &str) -> Result<(), VmError> {
+    let mut regions = REGIONS.lock();
+    let region = regions.get_mut(name).ok_or(VmError::NotFound)?;
+    region.unlinked = true;
+    if region.map_count == 0 {
+        release_region(&mut regions, name);
+    }
+    Ok(())
+}
+
+// Frames an app still has mapped stay with the app
+fn release_region(regions: &mut BTreeMap<String, SharedRegion>, name: &str) {
+    if let Some(region) = regions.remove(name) {
+        region.frames.into_iter().for_each(memory::release_frame);
+    }
+}
+
+// The region's memory, for mapping into a process. Each mapping there has
+// to take its own reference with `memory::share_frame`.
+pub fn shm_frames(name: &str) -> Result<Vec<PhysFrame>, VmError> {
+    let regions = REGIONS.lock();
+    let region = regions.get(name).filter(|r| !r.unlinked).ok_or(VmError::NotFound)?;
+    Ok(region.frames.clone())
+}
+
+// Writes dirty pages of a writable file mapping back to the file
+pub fn msync(addr: VirtAddr) -> Result<(), VmError> {
+    let state = STATE.lock();
+    let mapping = state
+        .mappings
+        .iter()
+        .find(|m| m.start == addr.as_u64())
+        .ok_or(VmError::NotFound)?;
+    write_back(mapping)
+}
+
+fn write_back(mapping: &Mapping) -> Result<(), VmError> {
+    let (path, offset) = match &mapping.backing {
+        Backing::File { path, offset } if mapping.protection == Protection::ReadWrite => (path, *offset),
+        _ => return Ok(()),
+    };
+    
+    for page in mapping.pages() {
+        let flags = match memory::page_flags(page) {
+            Some(flags) if flags.contains(PageTableFlags::DIRTY) => flags,
+            _ => continue,
+        };
+        let page_offset = page.start_address().as_u64() - mapping.start;
+        let len = PAGE_SIZE.min(mapping.len - page_offset) as usize;
+        let data = unsafe { core::slice::from_raw_parts(page.start_address().as_ptr::<u8>(), len) };
+        vfs::write(path, offset + page_offset, data)?;
+        memory::set_page_flags(page, flags - PageTableFlags::DIRTY);
+    }
+    Ok(())
+}
+
+pub fn munmap(addr: VirtAddr) -> Result<(), VmError> {
+    let mut state = STATE.lock();
+    let index = state
+        .mappings
+        .iter()
+        .position(|m| m.start == addr.as_u64())
+        .ok_or(VmError::NotFound)?;
+    let mapping = state.mappings.remove(index);
+    drop(state);
+    
+    let result = write_back(&mapping);
+    for page in mapping.pages() {
+        if let Some(frame) = memory::unmap_page(page) {
+            // Shared frames belong to the region, not the mapping
+            if let Backing::File { .. } = mapping.backing {
+                memory::free_frame(frame);
+            }
+        }
+    }
+    
+    if let Backing::Shared(name) = &mapping.backing {
+        let mut regions = REGIONS.lock();
+        if let Some(region) = regions.get_mut(name) {
+            region.map_count -= 1;
+            if region.unlinked && region.map_count == 0 {
+                release_region(&mut regions, name);
+            }
+        }
+    }
+    result
+}
+
+// Called from the page fault handler. Returns true if the fault was a
+// first touch of a file mapping and the page has been filled in. It never
+// waits: a fault taken while the VM lock or the heap is held isn't served.
+pub fn handle_page_fault(addr: VirtAddr, error_code: PageFaultErrorCode) -> bool {
+    let addr = addr.as_u64();
+    if !(MMAP_BASE..MMAP_BASE + MMAP_SIZE).contains(&addr)
+        || error_code.contains(PageFaultErrorCode::PROTECTION_VIOLATION)
+        || allocator::is_busy()
+    {
+        return false;
+    }
+    let state = match STATE.try_lock() {
+        Some(state) => state,
+        None => return false,
+    };
+    fill(&state, addr, error_code.contains(PageFaultErrorCode::CAUSED_BY_WRITE))
+}
+
+// Fills in the file pages under a buffer before it goes to code holding
+// locks the fault handler would need, such as the VFS writing it out
+pub fn prefault(addr: VirtAddr, len: usize, write: bool) {
+    let start = addr.as_u64().max(MMAP_BASE);
+    let end = addr.as_u64().saturating_add(len as u64).min(MMAP_BASE + MMAP_SIZE);
+    if start >= end {
+        return;
+    }
+    // msync writes its pages out with the lock held; they're all mapped
+    let state = match STATE.try_lock() {
+        Some(state) => state,
+        None => return,
+    };
+    let first = Page::<Size4KiB>::containing_address(VirtAddr::new(start));
+    let last = Page::<Size4KiB>::containing_address(VirtAddr::new(end - 1));
+    for page in Page::range_inclusive(first, last) {
+        if memory::page_flags(page).is_none() {
+            fill(&state, page.start_address().as_u64(), write);
+        }
+    }
+}
+
+// Reads the page holding `addr` in from the file. The path is borrowed from
+// the mapping so nothing is allocated here for the VM's sake.
+fn fill(state: &VmState, addr: u64, write: bool) -> bool {
+    let mapping = match state.mappings.iter().find(|m| (m.start..m.start + m.len).contains(&addr)) {
+        Some(mapping) => mapping,
+        None => return false,
+    };
+    if write && mapping.protection == Protection::ReadOnly {
+        return false;
+    }
+    let (path, offset) = match &mapping.backing {
+        Backing::File { path, offset } => (path.as_str(), *offset),
+        Backing::Shared(_) => return false,
+    };
+    let page = Page::containing_address(VirtAddr::new(addr));
+    let file_offset = offset + (page.start_address().as_u64() - mapping.start);
+    
+    let frame = match memory::allocate_zeroed_frame() {
+        Some(frame) => frame,
+        None => return false,
+    };
+    // Reading past the end of the file leaves the rest of the page zeroed
+    let buf = unsafe {
+        let ptr: *mut u8 = memory::phys_to_virt(frame.start_address()).as_mut_ptr();
+        core::slice::from_raw_parts_mut(ptr, PAGE_SIZE as usize)
+    };
+    if vfs::read(path, file_offset, buf).is_err() || memory::map_page(page, frame, mapping.flags()).is_err() {
+        memory::free_frame(frame);
+        return false;
+    }
+    true
+}
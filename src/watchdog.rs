@@ -0,0 +1,137 @@
+// src/watchdog.rs
+// Hang detection for the desktop loop. The loop reports every finished
+// frame; the timer interrupt checks how long ago that was. When a frame has
+// been stuck for HANG_TIMEOUT_SECS the interrupt returns into `recover`
+// instead of the stuck code, which abandons the frame, flags the window
+// that was being drawn and restarts the loop with the "not responding"
+// dialog up. Where the frame was stuck goes to the log as a backtrace.
+// A frame stuck holding a lock can't be abandoned and stays stuck.
+use crate::backtrace::Frames;
+use crate::{allocator, clock, kwarn, symbols, sync};
+use core::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use crate::sync::Mutex;
+use x86_64::structures::idt::InterruptStackFrame;
+use x86_64::VirtAddr;
+
+pub const HANG_TIMEOUT_SECS: u64 = 3;
+// How far below the armed stack pointer a hung frame may have got and still
+// be recognised as the desktop loop's
+const MAX_LOOP_STACK: u64 = 64 * 1024;
+const NO_WINDOW: usize = usize::MAX;
+const BACKTRACE_DEPTH: usize = 16;
+
+static ARMED: AtomicBool = AtomicBool::new(false);
+static LAST_FRAME: AtomicU64 = AtomicU64::new(0);
+// Stack pointer of the desktop loop when it was armed; `recover` restarts
+// the loop from here
+static LOOP_STACK: AtomicU64 = AtomicU64::new(0);
+static DRAWING: AtomicUsize = AtomicUsize::new(NO_WINDOW);
+static HUNG_WINDOW: AtomicUsize = AtomicUsize::new(NO_WINDOW);
+// Where the hung frame was stopped, innermost first, padded with zeros
+static HUNG_AT: Mutex<[usize; BACKTRACE_DEPTH]> = Mutex::new([0; BACKTRACE_DEPTH]);
+
+// Called by the desktop loop each time it (re)starts
+#[inline(always)]
+pub fn arm() {
+    let rsp: u64;
+    unsafe { core::arch::asm!("mov {}, rsp", out(reg) rsp, options(nomem, nostack, preserves_flags)) };
+    LOOP_STACK.store(rsp, Ordering::Relaxed);
+    LAST_FRAME.store(clock::ticks(), Ordering::Relaxed);
+    DRAWING.store(NO_WINDOW, Ordering::Relaxed);
+    ARMED.store(true, Ordering::Release);
+}
+
+pub fn frame_completed() {
+    LAST_FRAME.store(clock::ticks(), Ordering::Relaxed);
+}
+
+// The window manager brackets each window's draw with these so a hang can
+// be pinned on a window
+pub fn drawing(window: Option<usize>) {
+    DRAWING.store(window.unwrap_or(NO_WINDOW), Ordering::Relaxed);
+}
+
+// Index of the window being drawn right now, if any
+pub fn current_window() -> Option<usize> {
+    match DRAWING.load(Ordering::Relaxed) {
+        NO_WINDOW => None,
+        index => Some(index),
+    }
+}
+
+// True if `rsp` lies in the part of the stack the desktop loop runs on
+pub fn on_loop_stack(rsp: u64) -> bool {
+    let loop_stack = LOOP_STACK.load(Ordering::Relaxed);
+    ARMED.load(Ordering::Acquire) && (loop_stack - MAX_LOOP_STACK..loop_stack).contains(&rsp)
+}
+
+// Makes the interrupted code resume in `entry`, as if called from the top
+// of the loop's stack. Everything the loop had on the stack is abandoned.
+pub fn redirect(stack_frame: &mut InterruptStackFrame, entry: extern "C" fn() -> !) {
+    ARMED.store(false, Ordering::Release);
+    let entry = VirtAddr::new(entry as usize as u64);
+    let stack = VirtAddr::new((LOOP_STACK.load(Ordering::Relaxed) & !0xF) - 8);
+    unsafe {
+        stack_frame.as_mut().update(|frame| {
+            frame.instruction_pointer = entry;
+            frame.stack_pointer = stack;
+        });
+    }
+}
+
+// Same as `redirect`, from ordinary code running on the loop's stack
+pub fn unwind_to(entry: extern "C" fn() -> !) -> ! {
+    ARMED.store(false, Ordering::Release);
+    let stack = LOOP_STACK.load(Ordering::Relaxed) & !0xF;
+    unsafe {
+        core::arch::asm!("mov rsp, {}", "call {}", in(reg) stack, in(reg) entry, options(noreturn));
+    }
+}
+
+// Called from the timer interrupt. Never inlined, so the hung code's
+// backtrace can be taken from here.
+#[inline(never)]
+pub fn check(stack_frame: &mut InterruptStackFrame) {
+    if !ARMED.load(Ordering::Acquire) {
+        return;
+    }
+    let stuck = clock::ticks().saturating_sub(LAST_FRAME.load(Ordering::Relaxed));
+    if stuck < HANG_TIMEOUT_SECS * clock::TIMER_HZ {
+        return;
+    }
+    
+    // Only the loop's own stack can be unwound this way, and never while it
+    // holds the heap or any other lock; otherwise try again next tick
+    if !on_loop_stack(stack_frame.stack_pointer.as_u64()) || allocator::is_busy() || sync::locks_held() {
+        return;
+    }
+    
+    HUNG_WINDOW.store(DRAWING.load(Ordering::Relaxed), Ordering::Relaxed);
+    if let Some(mut hung_at) = HUNG_AT.try_lock() {
+        let rip = stack_frame.instruction_pointer.as_u64() as usize;
+        *hung_at = [0; BACKTRACE_DEPTH];
+        for (slot, address) in hung_at.iter_mut().zip(core::iter::once(rip).chain(Frames::interrupted())) {
+            *slot = address;
+        }
+    }
+    redirect(stack_frame, recover);
+}
+
+extern "C" fn recover() -> ! {
+    let window = match HUNG_WINDOW.load(Ordering::Relaxed) {
+        NO_WINDOW => None,
+        index => Some(index),
+    };
+    kwarn!("watchdog: no frame for {}s, window {:?} not responding", HANG_TIMEOUT_SECS, window);
+    let hung_at = *HUNG_AT.lock();
+    for address in hung_at.iter().take_while(|&&address| address != 0) {
+        kwarn!("watchdog:   {}", symbols::Address(*address));
+    }
+    
+    unsafe {
+        if let Some(desktop) = crate::DESKTOP.as_mut() {
+            desktop.report_hang(window);
+        }
+    }
+    crate::run_desktop()
+}
\ No newline at end of file
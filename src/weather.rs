@@ -0,0 +1,384 @@
+// src/weather.rs
+// The weather in the menu bar, when it's turned on in System Preferences:
+// the temperature with an icon for the sky, checked every fifteen minutes
+// over HTTP. Clicking it opens a popover with the next few days. The place
+// and units are set in the com.rustos.weather preferences, for example
+// `defaults write com.rustos.weather Location Lisbon` and Units F. The
+// service is wttr.in's JSON unless a Url is set, with {location} in it
+// standing for the place; one that answers in plain text has its first
+// line shown, the temperature first.
+use crate::clock::Instant;
+use crate::graphics::{Graphics, Color};
+use crate::http::{self, HttpError};
+use crate::json::{self, Value};
+use crate::kwarn;
+use crate::net::NetError;
+use crate::preferences;
+use crate::undo::{self, SetValue};
+use crate::vfs::VfsError;
+use crate::widgets::{Button, Checkbox};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+use crate::sync::Mutex;
+
+const DOMAIN: &str = "com.rustos.weather";
+const ENABLED_KEY: &str = "ShowInMenuBar";
+const DEFAULT_LOCATION: &str = "Cupertino";
+const DEFAULT_URL: &str = "http://wttr.in/{location}?format=j1";
+const CHECK_EVERY: Duration = Duration::from_secs(15 * 60);
+// After a check that failed
+const RETRY_AFTER: Duration = Duration::from_secs(60);
+const MAX_FORECAST_DAYS: usize = 3;
+const DAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+// Where the setting sits in the System Preferences window, under Energy
+// Saver
+const PREFERENCE_X: usize = 20;
+const PREFERENCE_Y: usize = 456;
+const SHOW_IN_MENU_BAR: Checkbox = Checkbox::new("Show weather in menu bar");
+
+const INDICATOR_WIDTH: usize = 56;
+const POPOVER_WIDTH: usize = 220;
+const POPOVER_Y: usize = 26;
+const ROW_HEIGHT: usize = 18;
+const REFRESH: Button = Button::new("Refresh");
+
+struct Day {
+    name: String,
+    high: i32,
+    low: i32,
+    condition: String,
+}
+
+struct Report {
+    place: String,
+    temperature: i32,
+    condition: String,
+    // Starting today
+    forecast: Vec<Day>,
+}
+
+struct Weather {
+    request: Option<http::Request>,
+    last_check: Option<Instant>,
+    report: Option<Report>,
+    // Why the last check failed
+    error: Option<&'static str>,
+    popover_open: bool,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static WEATHER: Mutex<Weather> = Mutex::new(Weather {
+    request: None,
+    last_check: None,
+    report: None,
+    error: None,
+    popover_open: false,
+});
+
+pub fn load() {
+    ENABLED.store(preferences::get_bool(DOMAIN, ENABLED_KEY, false), Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+fn location() -> String {
+    preferences::get(DOMAIN, "Location").unwrap_or_else(|| String::from(DEFAULT_LOCATION))
+}
+
+fn fahrenheit() -> bool {
+    preferences::get(DOMAIN, "Units").is_some_and(|units| units.eq_ignore_ascii_case("F"))
+}
+
+// Spaces as +, and anything but letters, digits and a few marks escaped
+fn encode(text: &str) -> String {
+    let mut encoded = String::new();
+    for byte in text.bytes() {
+        match byte {
+            b' ' => encoded.push('+'),
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b',' => encoded.push(byte as char),
+            byte => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn url() -> String {
+    let template = preferences::get(DOMAIN, "Url").unwrap_or_else(|| String::from(DEFAULT_URL));
+    template.replace("{location}", &encode(&location()))
+}
+
+// The day of the week of a yyyy-mm-dd date
+fn day_name(date: &str) -> Option<&'static str> {
+    let mut parts = date.splitn(3, '-').map(|part| part.parse::<i32>().ok());
+    let (mut year, month, day) = (parts.next()??, parts.next()??, parts.next()??);
+    if !(1..=12).contains(&month) {
+        return None;
+    }
+    // Sakamoto's method
+    const OFFSETS: [i32; 12] = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+    if month < 3 {
+        year -= 1;
+    }
+    let weekday = (year + year / 4 - year / 100 + year / 400 + OFFSETS[month as usize - 1] + day).rem_euclid(7);
+    Some(DAYS[weekday as usize])
+}
+
+fn text_at<'a>(value: &'a Value, path: &[&str]) -> Option<&'a str> {
+    // Each step into an array takes its first element
+    let mut value = value;
+    for key in path {
+        value = value.get(key)?;
+        if let Value::Array(_) = value {
+            value = value.at(0)?;
+        }
+    }
+    value.as_str()
+}
+
+fn number_at(value: &Value, key: &str) -> Option<i32> {
+    value.get(key)?.as_f64().map(|number| number as i32)
+}
+
+// wttr.in's format=j1
+fn from_json(report: &Value, fahrenheit: bool) -> Option<Report> {
+    let (temp, max, min) = if fahrenheit { ("temp_F", "maxtempF", "mintempF") } else { ("temp_C", "maxtempC", "mintempC") };
+    let current = report.get("current_condition")?.at(0)?;
+    let place = text_at(report, &["nearest_area", "areaName", "value"]).map_or_else(location, String::from);
+    let forecast = report.get("weather").map_or(&[][..], Value::as_array).iter().take(MAX_FORECAST_DAYS).filter_map(|day| {
+        // Midday's sky stands for the day's
+        let hourly = day.get("hourly")?.as_array();
+        let noon = hourly.get(hourly.len() / 2)?;
+        Some(Day {
+            name: String::from(day_name(day.get("date")?.as_str()?)?),
+            high: number_at(day, max)?,
+            low: number_at(day, min)?,
+            condition: String::from(text_at(noon, &["weatherDesc", "value"])?),
+        })
+    });
+    Some(Report {
+        place,
+        temperature: number_at(current, temp)?,
+        condition: String::from(text_at(current, &["weatherDesc", "value"])?),
+        forecast: forecast.collect(),
+    })
+}
+
+// A line like "+12°C Partly cloudy": the first number is the temperature
+// and what follows the first space the sky
+fn from_text(text: &str) -> Option<Report> {
+    let line = text.lines().next()?.trim();
+    let start = line.find(|ch: char| ch.is_ascii_digit() || ch == '-')?;
+    let digits = line[start..].find(|ch: char| !ch.is_ascii_digit() && ch != '-').map_or(&line[start..], |end| &line[start..start + end]);
+    let condition = line.split_once(' ').map_or("", |(_, rest)| rest.trim());
+    Some(Report {
+        place: location(),
+        temperature: digits.parse().ok()?,
+        condition: String::from(condition),
+        forecast: Vec::new(),
+    })
+}
+
+fn error_message(err: &HttpError) -> &'static str {
+    match err {
+        HttpError::Net(NetError::NoInterface) => "Not connected to the Internet",
+        HttpError::Net(NetError::NoSuchHost) => "Can't find the weather service",
+        HttpError::Net(NetError::TimedOut) => "The weather service didn't respond",
+        _ => "Can't get the weather",
+    }
+}
+
+// The icon for a sky, by the words in it
+fn icon(condition: &str) -> &'static str {
+    let condition = condition.to_lowercase();
+    if condition.contains("snow") || condition.contains("sleet") || condition.contains("ice") {
+        "❄"
+    } else if condition.contains("rain") || condition.contains("drizzle") || condition.contains("shower") || condition.contains("thunder") {
+        "🌧"
+    } else if condition.contains("sun") || condition.contains("clear") {
+        "☀"
+    } else {
+        "☁"
+    }
+}
+
+impl Weather {
+    fn check(&mut self) {
+        self.last_check = Some(Instant::now());
+        match http::Request::get(&url()) {
+            Ok(request) => self.request = Some(request),
+            Err(err) => self.error = Some(error_message(&err)),
+        }
+    }
+
+    fn due(&self) -> bool {
+        let every = if self.error.is_some() { RETRY_AFTER } else { CHECK_EVERY };
+        self.request.is_none() && self.last_check.as_ref().is_none_or(|last| last.elapsed() >= every)
+    }
+}
+
+// Checks when due and moves a check along; call after `net::poll`. True
+// when there's something new to show.
+pub fn poll() -> bool {
+    if !is_enabled() {
+        return false;
+    }
+    let mut weather = WEATHER.lock();
+    if weather.due() {
+        weather.check();
+    }
+    let result = match weather.request.as_mut().map(|request| request.poll()) {
+        Some(Some(result)) => result,
+        _ => return false,
+    };
+    weather.request = None;
+    let report = match result {
+        Ok(response) if response.status == 200 => {
+            let body = String::from_utf8_lossy(&response.body);
+            match json::parse(&body) {
+                Ok(value) => from_json(&value, fahrenheit()),
+                Err(_) => from_text(&body),
+            }
+        }
+        Ok(response) => {
+            kwarn!("weather: the service answered {} {}", response.status, response.reason);
+            None
+        }
+        Err(err) => {
+            kwarn!("weather: check failed: {:?}", err);
+            weather.error = Some(error_message(&err));
+            return true;
+        }
+    };
+    weather.error = if report.is_some() { None } else { Some("The weather service sent something unexpected") };
+    if report.is_some() {
+        weather.report = report;
+    }
+    true
+}
+
+fn set_enabled(on: bool) -> Result<(), VfsError> {
+    ENABLED.store(on, Ordering::Relaxed);
+    if !on {
+        let mut weather = WEATHER.lock();
+        weather.popover_open = false;
+        weather.request = None;
+    }
+    preferences::set_bool(DOMAIN, ENABLED_KEY, on)
+}
+
+pub fn draw_preference(graphics: &mut Graphics, x: usize, y: usize) {
+    Checkbox { checked: is_enabled(), ..SHOW_IN_MENU_BAR }.draw(graphics, x + PREFERENCE_X, y + PREFERENCE_Y);
+}
+
+// `x` and `y` are relative to the window content. Changes go through
+// System Preferences' undo history.
+pub fn click_preference(x: usize, y: usize) {
+    if SHOW_IN_MENU_BAR.contains(x, y, PREFERENCE_X, PREFERENCE_Y) {
+        let old = is_enabled();
+        let change = SetValue { name: "Show Weather in Menu Bar", set: set_enabled, old, new: !old };
+        if let Err(err) = undo::perform(preferences::APP, change) {
+            kwarn!("weather: cannot save Show Weather in Menu Bar: {:?}", err);
+        }
+    }
+}
+
+fn degrees(temperature: i32) -> String {
+    format!("{}{}", temperature, if fahrenheit() { "F" } else { "C" })
+}
+
+// The icon and temperature, or dashes until the first check is back
+pub fn draw_indicator(graphics: &mut Graphics, x: usize, y: usize, color: Color) {
+    if !is_enabled() {
+        return;
+    }
+    let weather = WEATHER.lock();
+    match &weather.report {
+        Some(report) => {
+            graphics.draw_text(icon(&report.condition), x, y, Color::BLACK);
+            graphics.draw_text(&degrees(report.temperature), x + 12, y, color);
+        }
+        None => graphics.draw_text("--", x + 12, y, color),
+    }
+}
+
+// Whether the point hits the indicator drawn at (x, y)
+pub fn indicator_contains(px: usize, py: usize, x: usize, y: usize) -> bool {
+    is_enabled() && px >= x && px < x + INDICATOR_WIDTH && py + 8 >= y && py < y + 16
+}
+
+pub fn is_popover_open() -> bool {
+    WEATHER.lock().popover_open
+}
+
+pub fn toggle_popover() {
+    let mut weather = WEATHER.lock();
+    weather.popover_open = !weather.popover_open;
+}
+
+// Under the indicator at `indicator_x`, kept on screen
+fn popover_x(indicator_x: usize) -> usize {
+    indicator_x.saturating_sub(POPOVER_WIDTH / 2).min(crate::graphics::SCREEN_WIDTH - POPOVER_WIDTH - 4)
+}
+
+fn popover_height(report: Option<&Report>) -> usize {
+    let rows = report.map_or(0, |report| 1 + report.forecast.len());
+    40 + rows * ROW_HEIGHT + 16 + Button::HEIGHT
+}
+
+// Refresh checks again; a click anywhere else closes the popover
+pub fn click_popover(x: usize, y: usize, indicator_x: usize) {
+    let mut weather = WEATHER.lock();
+    let (left, height) = (popover_x(indicator_x), popover_height(weather.report.as_ref()));
+    if REFRESH.contains(x, y, left + 10, POPOVER_Y + height - Button::HEIGHT - 8) {
+        if weather.request.is_none() {
+            weather.check();
+        }
+    } else {
+        weather.popover_open = false;
+    }
+}
+
+pub fn draw_popover(graphics: &mut Graphics, indicator_x: usize) {
+    let weather = WEATHER.lock();
+    if !weather.popover_open {
+        return;
+    }
+    let (x, height) = (popover_x(indicator_x), popover_height(weather.report.as_ref()));
+    graphics.draw_rounded_rect(x, POPOVER_Y, POPOVER_WIDTH, height, Color::new(245, 245, 245));
+    graphics.draw_rect_outline(x, POPOVER_Y, POPOVER_WIDTH, height, Color::GRAY);
+    let mut y = POPOVER_Y + 10;
+    match &weather.report {
+        Some(report) => {
+            graphics.draw_text(&report.place, x + 10, y, Color::BLACK);
+            y += 20;
+            graphics.draw_text(icon(&report.condition), x + 10, y, Color::BLACK);
+            graphics.draw_text(&format!("{}  {}", degrees(report.temperature), report.condition), x + 24, y, Color::BLACK);
+            y += ROW_HEIGHT + 10;
+            for day in &report.forecast {
+                graphics.draw_text(&day.name, x + 10, y, Color::DARK_GRAY);
+                graphics.draw_text(icon(&day.condition), x + 50, y, Color::BLACK);
+                graphics.draw_text(&format!("{} / {}", degrees(day.high), degrees(day.low)), x + 66, y, Color::DARK_GRAY);
+                y += ROW_HEIGHT;
+            }
+        }
+        None => {
+            graphics.draw_text(&location(), x + 10, y, Color::BLACK);
+            y += 20;
+        }
+    }
+    let status = match (&weather.request, weather.error, &weather.last_check) {
+        (Some(_), _, _) => String::from("Checking..."),
+        (None, Some(error), _) => String::from(error),
+        (None, None, Some(last)) => format!("Updated {} min ago", last.elapsed().as_secs() / 60),
+        (None, None, None) => String::new(),
+    };
+    let status: String = status.chars().take((POPOVER_WIDTH - 20) / 8).collect();
+    graphics.draw_text(&status, x + 10, POPOVER_Y + height - Button::HEIGHT - 26, Color::GRAY);
+    REFRESH.draw(graphics, x + 10, POPOVER_Y + height - Button::HEIGHT - 8);
+}
\ No newline at end of file
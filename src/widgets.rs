@@ -0,0 +1,649 @@
+// src/widgets.rs
+// Reusable controls for app windows. Widgets only hold state and draw
+// themselves at a position the app gives them; the app owns layout and
+// routes input to them, with SplitView to divide a window into panes.
+use crate::graphics::{Graphics, Color};
+use crate::icons;
+use crate::keyboard::{Key, KeyEvent};
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+// Shared with apps, which draw them on their surfaces
+pub use rustos_sdk::widgets::{Button, Checkbox};
+
+const CHAR_WIDTH: usize = 8;
+
+// Single-line text entry with a placeholder. A secure field shows a dot
+// for each character.
+pub struct TextField {
+    pub text: String,
+    pub placeholder: &'static str,
+    pub is_focused: bool,
+    pub is_secure: bool,
+}
+
+impl TextField {
+    pub const HEIGHT: usize = 20;
+    
+    pub const fn new(placeholder: &'static str) -> Self {
+        Self { text: String::new(), placeholder, is_focused: false, is_secure: false }
+    }
+    
+    pub const fn secure(placeholder: &'static str) -> Self {
+        Self { text: String::new(), placeholder, is_focused: false, is_secure: true }
+    }
+    
+    pub fn insert(&mut self, ch: char) {
+        self.text.push(ch);
+    }
+    
+    pub fn backspace(&mut self) {
+        self.text.pop();
+    }
+    
+    pub fn clear(&mut self) {
+        self.text.clear();
+    }
+    
+    pub fn draw(&self, graphics: &mut Graphics, x: usize, y: usize, width: usize) {
+        let border = if self.is_focused { Color::BLUE } else { Color::new(200, 200, 200) };
+        graphics.draw_rect(x, y, width, Self::HEIGHT, Color::WHITE);
+        graphics.draw_rect_outline(x, y, width, Self::HEIGHT, border);
+        
+        // Show the end of the text when it doesn't fit
+        let columns = width.saturating_sub(12) / CHAR_WIDTH;
+        if self.text.is_empty() {
+            graphics.draw_text(self.placeholder, x + 6, y + 6, Color::GRAY);
+        } else {
+            let skip = self.text.chars().count().saturating_sub(columns);
+            let visible: String = self.text.chars().skip(skip).map(|ch| if self.is_secure { '•' } else { ch }).collect();
+            graphics.draw_text(&visible, x + 6, y + 6, Color::BLACK);
+        }
+        
+        if self.is_focused {
+            let cursor_x = x + 6 + self.text.chars().count().min(columns) * CHAR_WIDTH;
+            graphics.draw_rect(cursor_x, y + 4, 1, Self::HEIGHT - 8, Color::BLUE);
+        }
+    }
+}
+
+// Vertical scrolling over a list of equal-height rows. The app draws the
+// rows in `visible_rows`; the view keeps the offset and draws the scroller.
+pub struct ScrollView {
+    // First visible row
+    pub offset: usize,
+    pub row_height: usize,
+    // Keep the last row in view as rows are added
+    pub follow_end: bool,
+}
+
+impl ScrollView {
+    pub const fn new(row_height: usize) -> Self {
+        Self { offset: 0, row_height, follow_end: false }
+    }
+    
+    pub fn rows_per_page(&self, height: usize) -> usize {
+        (height / self.row_height.max(1)).max(1)
+    }
+    
+    fn max_offset(&self, total: usize, height: usize) -> usize {
+        total.saturating_sub(self.rows_per_page(height))
+    }
+    
+    pub fn scroll_by(&mut self, rows: isize, total: usize, height: usize) {
+        let offset = (self.offset as isize + rows).max(0) as usize;
+        self.offset = offset.min(self.max_offset(total, height));
+        // Scrolling back up stops following; reaching the end resumes it
+        self.follow_end = self.offset == self.max_offset(total, height);
+    }
+    
+    pub fn scroll_to_end(&mut self, total: usize, height: usize) {
+        self.offset = self.max_offset(total, height);
+    }
+    
+    // Rows to draw, clamping the offset if the content shrank
+    pub fn visible_rows(&mut self, total: usize, height: usize) -> Range<usize> {
+        if self.follow_end {
+            self.scroll_to_end(total, height);
+        }
+        self.offset = self.offset.min(self.max_offset(total, height));
+        self.offset..(self.offset + self.rows_per_page(height)).min(total)
+    }
+    
+    pub fn draw_scroller(&self, graphics: &mut Graphics, x: usize, y: usize, height: usize, total: usize) {
+        let page = self.rows_per_page(height);
+        if total <= page {
+            return;
+        }
+        let thumb = (height * page / total).max(10);
+        let thumb_y = y + (height - thumb) * self.offset / self.max_offset(total, height).max(1);
+        graphics.draw_rounded_rect(x, thumb_y, 6, thumb, Color::new(190, 190, 190));
+    }
+}
+
+// Which of a window's controls has keyboard focus, by its place in the
+// window's order. Tab moves through them and Shift+Tab back, wrapping
+// around; the app says how many there are, since that can change.
+pub struct FocusChain {
+    pub focused: Option<usize>,
+}
+
+impl FocusChain {
+    pub const fn new() -> Self {
+        Self { focused: None }
+    }
+    
+    // The focused control, if it's still one of `count`
+    pub fn current(&self, count: usize) -> Option<usize> {
+        self.focused.filter(|&index| index < count)
+    }
+    
+    pub fn advance(&mut self, count: usize, backward: bool) {
+        self.focused = match (self.current(count), backward) {
+            _ if count == 0 => None,
+            (None, false) => Some(0),
+            (None, true) => Some(count - 1),
+            (Some(index), false) => Some((index + 1) % count),
+            (Some(index), true) => Some((index + count - 1) % count),
+        };
+    }
+    
+    // The ring around the focused control's frame
+    pub fn draw_ring(graphics: &mut Graphics, x: usize, y: usize, width: usize, height: usize) {
+        let color = Color::new(120, 170, 250);
+        graphics.draw_rect_outline(x.saturating_sub(2), y.saturating_sub(2), width + 4, height + 4, color);
+        graphics.draw_rect_outline(x.saturating_sub(1), y.saturating_sub(1), width + 2, height + 2, color);
+    }
+}
+
+// Horizontal slider for a value from 0 to 1
+pub struct Slider {
+    pub value: f32,
+    pub width: usize,
+}
+
+impl Slider {
+    pub const HEIGHT: usize = 12;
+    const KNOB_WIDTH: usize = 8;
+    
+    // Whether the point hits the slider drawn at (x, y)
+    pub fn contains(&self, px: usize, py: usize, x: usize, y: usize) -> bool {
+        px >= x && px < x + self.width && py >= y && py < y + Self::HEIGHT
+    }
+    
+    // The value under `px` for the slider drawn at `x`
+    pub fn value_at(&self, px: usize, x: usize) -> f32 {
+        (px.saturating_sub(x) as f32 / (self.width - 1) as f32).min(1.0)
+    }
+    
+    pub fn draw(&self, graphics: &mut Graphics, x: usize, y: usize) {
+        let filled = ((self.width - Self::KNOB_WIDTH) as f32 * self.value) as usize;
+        graphics.draw_rect(x, y + 5, self.width, 2, Color::new(200, 200, 200));
+        graphics.draw_rect(x, y + 5, filled, 2, Color::BLUE);
+        graphics.draw_rounded_rect(x + filled, y, Self::KNOB_WIDTH, Self::HEIGHT, Color::WHITE);
+        graphics.draw_rect_outline(x + filled, y, Self::KNOB_WIDTH, Self::HEIGHT, Color::new(170, 170, 170));
+    }
+}
+
+// A line of text in a fixed width. Text that doesn't fit scrolls through
+// a character at a time, `speed` characters a second, resting at its start
+// before each pass; with a speed of 0 it's cut off instead. Characters at
+// a cut edge are faded toward the background.
+pub struct Marquee {
+    pub speed: u64,
+    pub color: Color,
+    pub background: Color,
+}
+
+impl Marquee {
+    const REST_MS: u64 = 1500;
+    // Blank characters between the end of the text and its start again
+    const GAP: usize = 4;
+    
+    pub const fn new(speed: u64, color: Color, background: Color) -> Self {
+        Self { speed, color, background }
+    }
+    
+    // Draws `text` at (x, y) as it is at `millis`, a clock the app keeps
+    pub fn draw(&self, graphics: &mut Graphics, text: &str, x: usize, y: usize, width: usize, millis: u64) {
+        let chars: Vec<char> = text.chars().filter(|&ch| !icons::is_modifier(ch)).collect();
+        let columns = width / CHAR_WIDTH;
+        if chars.len() <= columns {
+            graphics.draw_text(text, x, y, self.color);
+            return;
+        }
+        let cycle = chars.len() + Self::GAP;
+        let start = match self.speed {
+            0 => 0,
+            speed => {
+                let t = millis % (Self::REST_MS + cycle as u64 * 1000 / speed);
+                (t.saturating_sub(Self::REST_MS) * speed / 1000) as usize
+            }
+        };
+        let faded = self.color.mix(self.background, 0.6);
+        let mut buf = [0u8; 4];
+        for column in 0..columns {
+            let index = (start + column) % cycle;
+            let ch = chars.get(index).copied().unwrap_or(' ');
+            let at_edge = column == columns - 1 || (column == 0 && start > 0);
+            let color = if at_edge { faded } else { self.color };
+            graphics.draw_text(ch.encode_utf8(&mut buf), x + column * CHAR_WIDTH, y, color);
+        }
+    }
+}
+
+// Find-on-page bar (Cmd+F) an app draws over the top of its window. It
+// keeps the query and which match is current; the app finds the matches in
+// its own text, reports how many there are and highlights them.
+pub struct SearchBar {
+    pub is_visible: bool,
+    field: TextField,
+    count: usize,
+    current: usize,
+    // The current match changed and should be scrolled into view
+    reveal: bool,
+}
+
+const PREVIOUS: Button = Button::new("<");
+const NEXT: Button = Button::new(">");
+const DONE: Button = Button::new("Done");
+// Room for "999 of 999"
+const STATUS_WIDTH: usize = 80;
+
+impl SearchBar {
+    pub const HEIGHT: usize = 28;
+    
+    pub const fn new() -> Self {
+        Self { is_visible: false, field: TextField::new("Find"), count: 0, current: 0, reveal: false }
+    }
+    
+    pub fn show(&mut self) {
+        self.is_visible = true;
+        self.field.is_focused = true;
+        self.reveal = true;
+    }
+    
+    pub fn hide(&mut self) {
+        self.is_visible = false;
+        self.field.is_focused = false;
+    }
+    
+    // What to highlight; nothing while the bar is closed
+    pub fn query(&self) -> &str {
+        if self.is_visible { &self.field.text } else { "" }
+    }
+    
+    pub fn current(&self) -> Option<usize> {
+        (self.is_visible && self.current < self.count).then_some(self.current)
+    }
+    
+    fn step(&mut self, forward: bool) {
+        if self.count > 0 {
+            self.current = if forward { (self.current + 1) % self.count } else { (self.current + self.count - 1) % self.count };
+        }
+        self.reveal = true;
+    }
+    
+    fn edited(&mut self) {
+        self.current = 0;
+        self.reveal = true;
+    }
+    
+    // Cmd+F opens the bar. While it's open it takes typing, Enter and Cmd+G
+    // go to the next match (with Shift, the previous) and Esc closes it.
+    // Whether the key was used.
+    pub fn key(&mut self, event: &KeyEvent) -> bool {
+        if event.key == Key::F && event.cmd {
+            self.show();
+            return true;
+        }
+        if !self.is_visible {
+            return false;
+        }
+        match event.key {
+            Key::Escape => self.hide(),
+            Key::Enter => self.step(!event.shift),
+            Key::G if event.cmd => self.step(!event.shift),
+            Key::Backspace => {
+                self.field.backspace();
+                self.edited();
+            }
+            key if !event.cmd => {
+                let ch = key.to_char().or_else(|| key.to_digit().and_then(|digit| char::from_digit(digit, 10)));
+                match ch {
+                    Some(ch) => {
+                        self.insert(ch);
+                    }
+                    None => return false,
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+    
+    // Types `ch` into the query; false while the bar is closed
+    pub fn insert(&mut self, ch: char) -> bool {
+        if self.is_visible {
+            self.field.insert(ch);
+            self.edited();
+        }
+        self.is_visible
+    }
+    
+    // Called by the app each frame with how many matches its text has.
+    // Returns the match to scroll into view when the current one changed.
+    pub fn update(&mut self, count: usize) -> Option<usize> {
+        self.count = count;
+        if self.current >= count {
+            self.current = 0;
+        }
+        let reveal = core::mem::take(&mut self.reveal);
+        (reveal && count > 0).then_some(self.current)
+    }
+    
+    // Left edges of the field's status and the three buttons
+    fn layout(x: usize, width: usize) -> (usize, usize, usize, usize) {
+        let done_x = (x + width).saturating_sub(8 + DONE.width());
+        let next_x = done_x.saturating_sub(6 + NEXT.width());
+        let previous_x = next_x.saturating_sub(2 + PREVIOUS.width());
+        let status_x = previous_x.saturating_sub(8 + STATUS_WIDTH);
+        (status_x, previous_x, next_x, done_x)
+    }
+    
+    // Whether the click hit the bar drawn at (x, y)
+    pub fn click(&mut self, px: usize, py: usize, x: usize, y: usize, width: usize) -> bool {
+        if !self.is_visible || px < x || px >= x + width || py < y || py >= y + Self::HEIGHT {
+            return false;
+        }
+        let (_, previous_x, next_x, done_x) = Self::layout(x, width);
+        let button_y = y + 5;
+        if PREVIOUS.contains(px, py, previous_x, button_y) {
+            self.step(false);
+        } else if NEXT.contains(px, py, next_x, button_y) {
+            self.step(true);
+        } else if DONE.contains(px, py, done_x, button_y) {
+            self.hide();
+        } else {
+            self.field.is_focused = true;
+        }
+        true
+    }
+    
+    pub fn draw(&self, graphics: &mut Graphics, x: usize, y: usize, width: usize) {
+        if !self.is_visible {
+            return;
+        }
+        let (status_x, previous_x, next_x, done_x) = Self::layout(x, width);
+        graphics.draw_rect(x + 1, y, width - 2, Self::HEIGHT, Color::new(236, 236, 236));
+        graphics.draw_rect(x + 1, y + Self::HEIGHT - 1, width - 2, 1, Color::new(200, 200, 200));
+        self.field.draw(graphics, x + 8, y + 4, status_x.saturating_sub(x + 16));
+        let status = match (self.field.text.is_empty(), self.count) {
+            (true, _) => String::new(),
+            (false, 0) => String::from("Not found"),
+            (false, count) => format!("{} of {}", self.current + 1, count),
+        };
+        graphics.draw_text(&status, status_x, y + 10, Color::DARK_GRAY);
+        PREVIOUS.draw(graphics, previous_x, y + 5);
+        NEXT.draw(graphics, next_x, y + 5);
+        DONE.draw(graphics, done_x, y + 5);
+    }
+}
+
+// Where a search query occurs: the line and the character columns it covers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextMatch {
+    pub line: usize,
+    pub columns: Range<usize>,
+}
+
+// Every occurrence of `query` in `lines`, ignoring case, in reading order
+pub fn find_matches<S: AsRef<str>>(lines: &[S], query: &str) -> Vec<TextMatch> {
+    let query: Vec<char> = query.chars().map(|ch| ch.to_ascii_lowercase()).collect();
+    let mut matches = Vec::new();
+    if query.is_empty() {
+        return matches;
+    }
+    for (i, line) in lines.iter().enumerate() {
+        let chars: Vec<char> = line.as_ref().chars().map(|ch| ch.to_ascii_lowercase()).collect();
+        let mut column = 0;
+        while column + query.len() <= chars.len() {
+            if chars[column..column + query.len()] == query[..] {
+                matches.push(TextMatch { line: i, columns: column..column + query.len() });
+                column += query.len();
+            } else {
+                column += 1;
+            }
+        }
+    }
+    matches
+}
+
+// Marks a match whose text starts at (x, y); drawn before the text
+pub fn draw_match(graphics: &mut Graphics, x: usize, y: usize, len: usize, current: bool) {
+    let color = if current { Color::GREEN } else { Color::YELLOW };
+    graphics.draw_rect(x, y.saturating_sub(2), len * CHAR_WIDTH, 12, color);
+}
+// A pane's or a divider's place, as (x, y, width, height)
+pub type Frame = (usize, usize, usize, usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    // A vertical divider, with panes left and right of it
+    SideBySide,
+    // A horizontal divider, with panes above and below it
+    Stacked,
+}
+
+enum SplitNode {
+    Pane(usize),
+    Divided {
+        orientation: Orientation,
+        // The first side's share of the room
+        ratio: f32,
+        first: Box<SplitNode>,
+        second: Box<SplitNode>,
+    },
+}
+
+// A window divided into panes, and those divided again to any depth. The
+// app keeps the panes, by index from 0, and the view keeps where each one
+// goes. Dividers are numbered in the order `dividers` gives them.
+pub struct SplitView {
+    root: SplitNode,
+    count: usize,
+}
+
+impl SplitView {
+    // Between panes, with the divider line in the middle
+    const GAP: usize = 5;
+    // How far a click can miss a divider and still grab it
+    const SLOP: usize = 2;
+    const MIN_RATIO: f32 = 0.1;
+
+    // One pane, 0, filling the frame
+    pub const fn new() -> Self {
+        Self { root: SplitNode::Pane(0), count: 1 }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    // Splits `pane` in two, giving the new pane the second half; returns
+    // the new pane's index, which is the next one after the others
+    pub fn split(&mut self, pane: usize, orientation: Orientation) -> usize {
+        let new = self.count;
+        if let Some(node) = Self::find(&mut self.root, pane) {
+            *node = SplitNode::Divided {
+                orientation,
+                ratio: 0.5,
+                first: Box::new(SplitNode::Pane(pane)),
+                second: Box::new(SplitNode::Pane(new)),
+            };
+            self.count += 1;
+        }
+        new
+    }
+
+    fn find(node: &mut SplitNode, pane: usize) -> Option<&mut SplitNode> {
+        match node {
+            SplitNode::Pane(index) if *index == pane => Some(node),
+            SplitNode::Pane(_) => None,
+            SplitNode::Divided { first, second, .. } => {
+                if let Some(found) = Self::find(first, pane) {
+                    return Some(found);
+                }
+                Self::find(second, pane)
+            }
+        }
+    }
+
+    // Takes `pane` out, its sibling getting its room, and renumbers the
+    // panes after it down by one as a Vec's remove would. The last pane
+    // stays; false then.
+    pub fn remove(&mut self, pane: usize) -> bool {
+        if self.count == 1 || !Self::remove_from(&mut self.root, pane) {
+            return false;
+        }
+        Self::renumber(&mut self.root, pane);
+        self.count -= 1;
+        true
+    }
+
+    fn remove_from(node: &mut SplitNode, pane: usize) -> bool {
+        let SplitNode::Divided { first, second, .. } = node else { return false };
+        let sibling = match (&**first, &**second) {
+            (SplitNode::Pane(index), _) if *index == pane => core::mem::replace(&mut **second, SplitNode::Pane(0)),
+            (_, SplitNode::Pane(index)) if *index == pane => core::mem::replace(&mut **first, SplitNode::Pane(0)),
+            _ => return Self::remove_from(first, pane) || Self::remove_from(second, pane),
+        };
+        *node = sibling;
+        true
+    }
+
+    fn renumber(node: &mut SplitNode, removed: usize) {
+        match node {
+            SplitNode::Pane(index) if *index > removed => *index -= 1,
+            SplitNode::Pane(_) => {}
+            SplitNode::Divided { first, second, .. } => {
+                Self::renumber(first, removed);
+                Self::renumber(second, removed);
+            }
+        }
+    }
+
+    // The two sides of a divided frame and the divider between them
+    fn divide(frame: Frame, orientation: Orientation, ratio: f32) -> (Frame, Frame, Frame) {
+        let (x, y, width, height) = frame;
+        match orientation {
+            Orientation::SideBySide => {
+                let room = width.saturating_sub(Self::GAP);
+                let first = (room as f32 * ratio) as usize;
+                ((x, y, first, height), (x + first, y, Self::GAP, height), (x + first + Self::GAP, y, room - first, height))
+            }
+            Orientation::Stacked => {
+                let room = height.saturating_sub(Self::GAP);
+                let first = (room as f32 * ratio) as usize;
+                ((x, y, width, first), (x, y + first, width, Self::GAP), (x, y + first + Self::GAP, width, room - first))
+            }
+        }
+    }
+
+    fn layout(node: &SplitNode, frame: Frame, panes: &mut [Frame], dividers: &mut Vec<(Frame, Orientation)>) {
+        match node {
+            SplitNode::Pane(index) => panes[*index] = frame,
+            SplitNode::Divided { orientation, ratio, first, second } => {
+                let (first_frame, divider, second_frame) = Self::divide(frame, *orientation, *ratio);
+                dividers.push((divider, *orientation));
+                Self::layout(first, first_frame, panes, dividers);
+                Self::layout(second, second_frame, panes, dividers);
+            }
+        }
+    }
+
+    // Where each pane goes in `frame`, by index
+    pub fn frames(&self, frame: Frame) -> Vec<Frame> {
+        let mut panes = alloc::vec![(0, 0, 0, 0); self.count];
+        Self::layout(&self.root, frame, &mut panes, &mut Vec::new());
+        panes
+    }
+
+    pub fn dividers(&self, frame: Frame) -> Vec<(Frame, Orientation)> {
+        let mut dividers = Vec::new();
+        Self::layout(&self.root, frame, &mut alloc::vec![(0, 0, 0, 0); self.count], &mut dividers);
+        dividers
+    }
+
+    pub fn pane_at(&self, px: usize, py: usize, frame: Frame) -> Option<usize> {
+        self.frames(frame)
+            .iter()
+            .position(|&(x, y, width, height)| px >= x && px < x + width && py >= y && py < y + height)
+    }
+
+    pub fn divider_at(&self, px: usize, py: usize, frame: Frame) -> Option<usize> {
+        self.dividers(frame).iter().position(|&((x, y, width, height), _)| {
+            px + Self::SLOP >= x && px < x + width + Self::SLOP && py + Self::SLOP >= y && py < y + height + Self::SLOP
+        })
+    }
+
+    // Moves a divider with the pointer, as far as leaves each side a
+    // tenth of the room
+    pub fn move_divider(&mut self, divider: usize, dx: i32, dy: i32, frame: Frame) {
+        let mut divider = divider;
+        Self::move_in(&mut self.root, &mut divider, dx, dy, frame);
+    }
+
+    // `divider` counts down the dividers still to pass; true once moved
+    fn move_in(node: &mut SplitNode, divider: &mut usize, dx: i32, dy: i32, frame: Frame) -> bool {
+        let SplitNode::Divided { orientation, ratio, first, second } = node else { return false };
+        let (first_frame, _, second_frame) = Self::divide(frame, *orientation, *ratio);
+        if *divider == 0 {
+            let (delta, room) = match orientation {
+                Orientation::SideBySide => (dx, frame.2.saturating_sub(Self::GAP)),
+                Orientation::Stacked => (dy, frame.3.saturating_sub(Self::GAP)),
+            };
+            *ratio = (*ratio + delta as f32 / room.max(1) as f32).clamp(Self::MIN_RATIO, 1.0 - Self::MIN_RATIO);
+            return true;
+        }
+        *divider -= 1;
+        Self::move_in(first, divider, dx, dy, first_frame) || Self::move_in(second, divider, dx, dy, second_frame)
+    }
+
+    // The nearest pane from `pane` in the direction of (dx, dy), as
+    // arrow keys give it
+    pub fn neighbour(&self, pane: usize, dx: i32, dy: i32, frame: Frame) -> Option<usize> {
+        let frames = self.frames(frame);
+        let (x, y, width, height) = *frames.get(pane)?;
+        let center = |(x, y, width, height): Frame| ((x + width / 2) as i64, (y + height / 2) as i64);
+        let (cx, cy) = center(frames[pane]);
+        frames
+            .iter()
+            .enumerate()
+            .filter(|&(_, &(ox, oy, other_width, other_height))| match (dx.signum(), dy.signum()) {
+                (1, _) => ox >= x + width,
+                (-1, _) => ox + other_width <= x,
+                (_, 1) => oy >= y + height,
+                (_, -1) => oy + other_height <= y,
+                _ => false,
+            })
+            .min_by_key(|&(_, &other)| {
+                let (ox, oy) = center(other);
+                (ox - cx).pow(2) + (oy - cy).pow(2)
+            })
+            .map(|(index, _)| index)
+    }
+
+    // A line down the middle of each divider
+    pub fn draw_dividers(&self, graphics: &mut Graphics, frame: Frame, color: Color) {
+        for ((x, y, width, height), orientation) in self.dividers(frame) {
+            match orientation {
+                Orientation::SideBySide => graphics.draw_rect(x + width / 2, y, 1, height, color),
+                Orientation::Stacked => graphics.draw_rect(x, y + height / 2, width, 1, color),
+            }
+        }
+    }
+}
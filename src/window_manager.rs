@@ -1,12 +1,74 @@
 // Enhanced Window Manager with advanced features
 // src/window_manager.rs
-use crate::graphics::{Graphics, Color};
-use crate::animations::WindowAnimation;
+use crate::graphics::{Graphics, Color, Surface, SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::chrome::{WindowFrame, TITLE_BAR_HEIGHT};
+use crate::animations::{WindowAnimation, WINDOW_ANIMATIONS};
+use crate::apps::{self, AppId};
+use crate::pool::PoolBox;
+use crate::share::Data;
+use crate::task::{self, Pid};
+use crate::display_server::{self, SurfaceId};
+use crate::keyboard::{Key, KeyEvent};
+use crate::clock::Instant;
+use crate::widgets::FocusChain;
+use crate::styled_text::StyledText;
+use crate::vfs::VfsError;
 use alloc::vec::Vec;
 use alloc::string::String;
+use core::time::Duration;
+use crate::sync::Mutex;
 
 extern crate alloc;
 
+// The grab area in a window's bottom-right corner
+const RESIZE_HANDLE: usize = 15;
+const MIN_WIDTH: usize = 160;
+const MIN_HEIGHT: usize = 100;
+// While resizing, the app repaints at most this often; in between, its
+// last frame is stretched to fit
+const RESIZE_REPAINT_INTERVAL: Duration = Duration::from_millis(100);
+// The faintest a window can be made from its menu
+pub const MIN_OPACITY: f32 = 0.25;
+// Picture in Picture tiles stack up from the bottom-right corner, above
+// the dock
+const PIP_WIDTH: usize = 160;
+const PIP_MARGIN: usize = 12;
+// How far apart display server clients' windows open
+const SURFACE_CASCADE: usize = 24;
+const PIP_BOTTOM: usize = SCREEN_HEIGHT - DOCK_HEIGHT - PIP_MARGIN;
+// What maximized and tiled windows leave room for
+const MENU_BAR_HEIGHT: usize = 24;
+const DOCK_HEIGHT: usize = 60;
+// Minimized windows shrink into the dock, about here and this big
+const DOCK_TARGET: (f32, f32, f32, f32) = ((SCREEN_WIDTH / 2 - 32) as f32, (SCREEN_HEIGHT - DOCK_HEIGHT) as f32, 64.0, 64.0);
+
+// Where a window is. Any state can go to any other; the frame the window
+// had when it was last Normal is kept to go back to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowState {
+    Normal,
+    Minimized,
+    // Filling the screen between the menu bar and the dock
+    Maximized,
+    // Filling the screen, with the menu bar and dock hidden
+    Fullscreen,
+    TiledLeft,
+    TiledRight,
+}
+
+// A resize drag in progress; None until the first real repaint
+struct LiveResize {
+    painted: Option<Instant>,
+}
+
+// The resizing window as last painted. There's one pointer, so only one
+// window resizes at a time.
+static RESIZE_SURFACE: Mutex<Surface> = Mutex::new(Surface::new());
+// A window opening or closing is painted for real, captured with what was
+// under it, and drawn back scaled and faded; windows draw one at a time
+static ANIMATION_SURFACE: Mutex<Surface> = Mutex::new(Surface::new());
+static UNDER_SURFACE: Mutex<Surface> = Mutex::new(Surface::new());
+
 pub struct Window {
     pub title: String,
     pub x: usize,
@@ -15,11 +77,36 @@ pub struct Window {
     pub height: usize,
     pub background_color: Color,
     pub is_focused: bool,
-    pub is_minimized: bool,
-    pub is_maximized: bool,
-    pub animation: Option<WindowAnimation>,
+    pub state: WindowState,
+    // The frame in the Normal state, as (x, y, width, height)
+    normal_frame: (usize, usize, usize, usize),
+    // What leaving Minimized, and leaving Fullscreen, go back to
+    unminimized_state: WindowState,
+    windowed_state: WindowState,
+    // Set by the watchdog after this window's drawing hung
+    pub is_hung: bool,
+    // The app has changes it hasn't saved; closing asks about them first
+    pub is_edited: bool,
+    // The app process behind the window
+    pub pid: Option<Pid>,
+    // The app that opened the window, if it came from the app registry
+    pub app: Option<AppId>,
+    // The display server surface it shows, for a client's window
+    pub surface: Option<SurfaceId>,
+    // Hidden along with the rest of its app's windows; it keeps its state
+    pub is_hidden: bool,
+    pub animation: Option<PoolBox<WindowAnimation>>,
     pub shadow_offset: usize,
+    // Opacity, from MIN_OPACITY to 1
     pub transparency: f32,
+    // Shown as a small tile in the corner, on top of everything and never
+    // focused
+    pub is_pip: bool,
+    // Kept up to date while the window has focus, for the low-memory killer
+    pub last_focused: Instant,
+    live_resize: Option<LiveResize>,
+    // The app control Tab has moved to or a click landed on
+    focus: FocusChain,
 }
 
 impl Window {
@@ -32,41 +119,265 @@ impl Window {
             height,
             background_color,
             is_focused: false,
-            is_minimized: false,
-            is_maximized: false,
+            state: WindowState::Normal,
+            normal_frame: (x, y, width, height),
+            unminimized_state: WindowState::Normal,
+            windowed_state: WindowState::Normal,
+            is_hung: false,
+            is_edited: false,
+            pid: None,
+            app: None,
+            surface: None,
+            is_hidden: false,
             animation: None,
             shadow_offset: 4,
             transparency: 1.0,
+            is_pip: false,
+            last_focused: Instant::now(),
+            live_resize: None,
+            focus: FocusChain::new(),
+        }
+    }
+    
+    // Draws the window, taking the fast path during a resize drag: the
+    // last real frame stretched to the current size
+    pub fn present(&mut self, graphics: &mut Graphics) {
+        if let Some(animation) = self.animation.as_mut() {
+            let (x, y, width, height, alpha) = animation.update();
+            let complete = animation.is_complete();
+            self.draw_transformed(graphics, x, y, width, height, alpha * self.transparency);
+            if complete {
+                self.animation = None;
+            }
+            return;
+        }
+        if let Some(resize) = &self.live_resize {
+            if resize.painted.map_or(false, |painted| painted.elapsed() < RESIZE_REPAINT_INTERVAL) {
+                if !self.is_minimized() {
+                    graphics.draw_scaled(&RESIZE_SURFACE.lock(), self.x, self.y, self.width, self.height);
+                }
+                return;
+            }
+        }
+        // Translucent windows go opaque while they're resized
+        if self.transparency < 1.0 && !self.is_resizing() {
+            self.draw_transformed(graphics, self.x as f32, self.y as f32, self.width as f32, self.height as f32, self.transparency);
+            return;
+        }
+        self.draw(graphics);
+        if self.live_resize.is_some() {
+            graphics.capture(&mut RESIZE_SURFACE.lock(), self.x, self.y, self.width, self.height);
+            self.live_resize = Some(LiveResize { painted: Some(Instant::now()) });
+        }
+    }
+    
+    // Draws the window, shadow included, as if it had another frame and
+    // opacity: for animations, translucent windows and PiP tiles
+    fn draw_transformed(&self, graphics: &mut Graphics, x: f32, y: f32, width: f32, height: f32, alpha: f32) {
+        let (frame_width, frame_height) = (self.width + self.shadow_offset + 2, self.height + self.shadow_offset + 2);
+        let mut under = UNDER_SURFACE.lock();
+        let mut surface = ANIMATION_SURFACE.lock();
+        graphics.capture(&mut under, self.x, self.y, frame_width, frame_height);
+        self.draw(graphics);
+        graphics.capture(&mut surface, self.x, self.y, frame_width, frame_height);
+        graphics.draw_scaled(&under, self.x, self.y, under.width(), under.height());
+        let scaled_width = surface.width() as f32 * width / self.width as f32;
+        let scaled_height = surface.height() as f32 * height / self.height as f32;
+        graphics.draw_faded(&surface, x as usize, y as usize, scaled_width as usize, scaled_height as usize, alpha);
+    }
+    
+    pub fn is_animating(&self) -> bool {
+        self.animation.is_some()
+    }
+    
+    pub fn is_minimized(&self) -> bool {
+        self.state == WindowState::Minimized
+    }
+    
+    // Where the window goes in `state`; None for Minimized, which keeps
+    // the frame it had
+    fn frame_for(&self, state: WindowState) -> Option<(usize, usize, usize, usize)> {
+        let height = SCREEN_HEIGHT - MENU_BAR_HEIGHT - DOCK_HEIGHT;
+        match state {
+            WindowState::Normal => Some(self.normal_frame),
+            WindowState::Minimized => None,
+            WindowState::Maximized => Some((0, MENU_BAR_HEIGHT, SCREEN_WIDTH, height)),
+            WindowState::Fullscreen => Some((0, 0, SCREEN_WIDTH, SCREEN_HEIGHT)),
+            WindowState::TiledLeft => Some((0, MENU_BAR_HEIGHT, SCREEN_WIDTH / 2, height)),
+            WindowState::TiledRight => Some((SCREEN_WIDTH / 2, MENU_BAR_HEIGHT, SCREEN_WIDTH / 2, height)),
+        }
+    }
+    
+    // Moves the window into `state`, animating from where it was
+    pub fn set_state(&mut self, state: WindowState) {
+        if state == self.state {
+            return;
+        }
+        if self.state == WindowState::Normal {
+            self.normal_frame = (self.x, self.y, self.width, self.height);
+        }
+        match state {
+            WindowState::Minimized => self.unminimized_state = self.state,
+            WindowState::Fullscreen if !self.is_minimized() => self.windowed_state = self.state,
+            _ => {}
+        }
+        let frame = |x: usize, y: usize, width: usize, height: usize| (x as f32, y as f32, width as f32, height as f32);
+        let from = if self.is_minimized() { DOCK_TARGET } else { frame(self.x, self.y, self.width, self.height) };
+        let from_alpha = if self.is_minimized() { 0.0 } else { 1.0 };
+        let morph = match self.frame_for(state) {
+            Some((x, y, width, height)) => {
+                (self.x, self.y, self.width, self.height) = (x, y, width, height);
+                WindowAnimation::morph(from, frame(x, y, width, height), (from_alpha, 1.0))
+            }
+            None => WindowAnimation::morph(from, DOCK_TARGET, (1.0, 0.0)),
+        };
+        self.animation = Some(WINDOW_ANIMATIONS.alloc(morph));
+        self.live_resize = None;
+        self.state = state;
+    }
+    
+    // The frame it goes back to in the Normal state
+    pub fn normal_frame(&self) -> (usize, usize, usize, usize) {
+        if self.state == WindowState::Normal { (self.x, self.y, self.width, self.height) } else { self.normal_frame }
+    }
+    
+    // Back from Minimized or Fullscreen to the state before
+    pub fn restore(&mut self) {
+        match self.state {
+            WindowState::Minimized => self.set_state(self.unminimized_state),
+            WindowState::Fullscreen => self.set_state(self.windowed_state),
+            _ => {}
         }
     }
     
+    pub fn unminimize(&mut self) {
+        if self.is_minimized() {
+            self.restore();
+        }
+    }
+    
+    // Whether this window, drawn after `other`, hides all of it, shadow
+    // included
+    fn covers(&self, other: &Window) -> bool {
+        !self.is_minimized() && !self.is_hidden && !self.is_pip && self.transparency >= 1.0 && !self.is_animating() && !self.is_resizing()
+            && self.x <= other.x && self.y <= other.y
+            && self.x + self.width >= other.x + other.width + other.shadow_offset + 2
+            && self.y + self.height >= other.y + other.height + other.shadow_offset + 2
+    }
+    
+    // Has the app save its document, for the sheet that asks before closing
+    pub fn save(&self) -> Result<(), VfsError> {
+        if self.title.contains("Preview") {
+            crate::preview::save()
+        } else {
+            Ok(())
+        }
+    }
+    
+    // A title and the text the app would print, for Cmd+P; None for apps
+    // with nothing to print
+    pub fn printable(&self) -> Option<(String, StyledText)> {
+        if self.title.contains("Document Viewer") {
+            crate::document_viewer::printable()
+        } else if self.title.contains("Console") {
+            Some((String::from("Console"), crate::console::printable()))
+        } else {
+            None
+        }
+    }
+    
+    // Writes what the app keeps for drawing the window out to swap; the
+    // bytes of heap freed
+    fn page_out(&self) -> usize {
+        if self.title.contains("Preview") {
+            crate::preview::page_out()
+        } else {
+            0
+        }
+    }
+    
+    // Where the window sits as a PiP tile with its bottom edge at `bottom`
+    fn pip_frame(&self, bottom: usize) -> (usize, usize, usize, usize) {
+        let height = self.height * PIP_WIDTH / self.width;
+        (crate::graphics::SCREEN_WIDTH - PIP_WIDTH - PIP_MARGIN, bottom.saturating_sub(height), PIP_WIDTH, height)
+    }
+    
+    // Whether (x, y), relative to the window, is on the resize handle
+    pub fn on_resize_handle(&self, x: usize, y: usize) -> bool {
+        x + RESIZE_HANDLE >= self.width && y + RESIZE_HANDLE >= self.height
+    }
+    
+    pub fn is_resizing(&self) -> bool {
+        self.live_resize.is_some()
+    }
+    
+    // Resizing a maximized or tiled window makes it Normal at its new size
+    pub fn begin_resize(&mut self) {
+        self.state = WindowState::Normal;
+        self.live_resize = Some(LiveResize { painted: None });
+    }
+    
+    // Grows or shrinks the window by the pointer movement, within the screen
+    pub fn resize_by(&mut self, dx: i32, dy: i32) {
+        let max_width = crate::graphics::SCREEN_WIDTH.saturating_sub(self.x).max(MIN_WIDTH);
+        let max_height = crate::graphics::SCREEN_HEIGHT.saturating_sub(self.y).max(MIN_HEIGHT);
+        self.width = (self.width as i32 + dx).clamp(MIN_WIDTH as i32, max_width as i32) as usize;
+        self.height = (self.height as i32 + dy).clamp(MIN_HEIGHT as i32, max_height as i32) as usize;
+    }
+    
+    // The next frame is a real repaint at the final size
+    pub fn end_resize(&mut self) {
+        self.live_resize = None;
+    }
+    
     pub fn draw(&self, graphics: &mut Graphics) {
-        if self.is_minimized {
+        if self.is_minimized() && !self.is_animating() {
             return;
         }
         
-        let title_bar_height = 36;
+        let title_bar_height = TITLE_BAR_HEIGHT;
         
-        // Draw enhanced window shadow with blur effect
-        for i in 0..self.shadow_offset {
-            let shadow_color = Color::new(0, 0, 0);
-            graphics.draw_rounded_rect(
-                self.x + i + 2,
-                self.y + i + 2,
-                self.width,
-                self.height,
-                shadow_color
-            );
+        // A see-through Terminal shows what was there before the window
+        if self.title.contains("Terminal") {
+            crate::terminal::capture_background(graphics, self.x + 1, self.y + title_bar_height, self.width - 2, self.height - title_bar_height - 1);
         }
         
-        // Draw window background with subtle gradient
-        self.draw_background_gradient(graphics);
+        // The frame and title bar, then what's particular to the window
+        WindowFrame {
+            x: self.x,
+            y: self.y,
+            width: self.width,
+            height: self.height,
+            title: &self.title,
+            background: self.background_color,
+            shadow_offset: self.shadow_offset,
+            focused: self.is_focused,
+            edited: self.is_edited,
+        }
+        .draw(graphics);
         
-        // Draw title bar with enhanced styling
-        self.draw_title_bar(graphics, title_bar_height);
+        // Draw window controls on the right side
+        if self.title.contains("Safari") {
+            graphics.draw_text("🔒", self.x + self.width - 100, self.y + 12, Color::GREEN);
+            graphics.draw_text("⟲", self.x + self.width - 80, self.y + 12, Color::BLACK);
+            graphics.draw_text("🔖", self.x + self.width - 60, self.y + 12, Color::BLACK);
+        }
         
-        // Draw window content
-        self.draw_content(graphics, title_bar_height);
+        // Draw window content, unless it is the reason the desktop hung
+        if self.is_hung {
+            self.draw_not_responding(graphics, title_bar_height);
+        } else {
+            self.draw_content(graphics, title_bar_height);
+        }
+        
+        // Ring the control with keyboard focus
+        if self.is_focused && !self.is_hung {
+            let controls = self.controls();
+            if let Some(index) = self.focus.current(controls.len()) {
+                let (x, y, width, height) = controls[index];
+                FocusChain::draw_ring(graphics, self.x + x, self.y + title_bar_height + y, width, height);
+            }
+        }
         
         // Draw resize handle in bottom-right corner
         if self.is_focused {
@@ -80,77 +391,210 @@ impl Window {
         }
     }
     
-    fn draw_background_gradient(&self, graphics: &mut Graphics) {
-        for y in 0..self.height {
-            let intensity = 1.0 - (y as f32 / self.height as f32) * 0.05;
-            let r = (self.background_color.r as f32 * intensity) as u8;
-            let g = (self.background_color.g as f32 * intensity) as u8;
-            let b = (self.background_color.b as f32 * intensity) as u8;
-            
-            graphics.draw_rect(self.x, self.y + y, self.width, 1, Color::new(r, g, b));
+    // App name shown in the menu bar and Force Quit list
+    pub fn app_name(&self) -> &str {
+        match self.app {
+            Some(app) => apps::get(app).name,
+            None => self.title.split(" — ").next().unwrap_or(&self.title),
         }
     }
     
-    fn draw_title_bar(&self, graphics: &mut Graphics, title_bar_height: usize) {
-        let title_bar_color = if self.is_focused {
-            Color::new(240, 240, 240)
-        } else {
-            Color::new(250, 250, 250)
-        };
-        
-        // Draw title bar background
-        graphics.draw_rounded_rect(self.x, self.y, self.width, title_bar_height, title_bar_color);
-        
-        // Draw title bar separator
-        graphics.draw_rect(self.x, self.y + title_bar_height - 1, self.width, 1, Color::new(200, 200, 200));
-        
-        // Draw traffic light buttons with enhanced styling
-        let button_size = 16;
-        let button_y = self.y + 10;
-        let button_spacing = 24;
-        
-        // Close button (red) - enhanced with gradient
-        self.draw_traffic_light_button(graphics, self.x + 12, button_y, button_size, Color::new(255, 96, 96));
-        
-        // Minimize button (yellow)
-        self.draw_traffic_light_button(graphics, self.x + 12 + button_spacing, button_y, button_size, Color::new(255, 189, 68));
-        
-        // Maximize button (green)
-        self.draw_traffic_light_button(graphics, self.x + 12 + button_spacing * 2, button_y, button_size, Color::new(40, 200, 64));
-        
-        // Draw title text with enhanced typography
-        let title_x = self.x + 80;
-        let title_color = if self.is_focused { Color::BLACK } else { Color::GRAY };
-        graphics.draw_text(&self.title, title_x, self.y + 12, title_color);
-        
-        // Draw window controls on the right side
-        if self.title.contains("Safari") {
-            graphics.draw_text("🔒", self.x + self.width - 100, self.y + 12, Color::GREEN);
-            graphics.draw_text("⟲", self.x + self.width - 80, self.y + 12, Color::BLACK);
-            graphics.draw_text("🔖", self.x + self.width - 60, self.y + 12, Color::BLACK);
+    // The window in miniature, as big as fits in the box and centered in
+    // it: the title bar, then a surface's content as last committed or the
+    // window's color. Returns where it went, as x, y, width and height.
+    pub fn draw_thumbnail(&self, graphics: &mut Graphics, x: usize, y: usize, width: usize, height: usize) -> (usize, usize, usize, usize) {
+        let (window_width, window_height) = (self.width.max(1), self.height.max(1));
+        let scale = (width * 1000 / window_width).min(height * 1000 / window_height);
+        let thumb_width = (window_width * scale / 1000).clamp(4, width.max(4));
+        let thumb_height = (window_height * scale / 1000).clamp(4, height.max(4));
+        let thumb_x = x + width.saturating_sub(thumb_width) / 2;
+        let thumb_y = y + height.saturating_sub(thumb_height) / 2;
+        let title_bar = (36 * thumb_height / window_height).clamp(2, thumb_height / 2);
+        graphics.draw_rect(thumb_x, thumb_y, thumb_width, thumb_height, self.background_color);
+        graphics.draw_rect(thumb_x, thumb_y, thumb_width, title_bar, Color::new(240, 240, 240));
+        if let Some(surface) = self.surface {
+            display_server::draw_scaled(graphics, surface, thumb_x, thumb_y + title_bar, thumb_width, thumb_height - title_bar);
         }
+        (thumb_x, thumb_y, thumb_width, thumb_height)
     }
     
-    fn draw_traffic_light_button(&self, graphics: &mut Graphics, x: usize, y: usize, size: usize, color: Color) {
-        // Draw button shadow
-        graphics.draw_rounded_rect(x + 1, y + 1, size, size, Color::new(0, 0, 0));
-        
-        // Draw button background
-        graphics.draw_rounded_rect(x, y, size, size, color);
-        
-        // Draw button highlight
-        graphics.draw_rounded_rect(x + 2, y + 2, size - 6, size - 8, Color::WHITE);
+    fn draw_not_responding(&self, graphics: &mut Graphics, title_bar_height: usize) {
+        let content_y = self.y + title_bar_height;
+        graphics.draw_rect(self.x + 1, content_y, self.width - 2, self.height - title_bar_height - 1, Color::new(220, 220, 220));
+        graphics.draw_text("🌈", self.x + self.width / 2 - 4, content_y + 40, Color::BLACK);
+        graphics.draw_text("Not Responding", self.x + self.width / 2 - 56, content_y + 60, Color::GRAY);
+    }
+    
+    // Scrolling goes to the app, dispatched like drawing
+    pub fn scroll(&mut self, lines: isize) {
+        let content_height = self.height - 36;
+        match self.title.as_str() {
+            title if title.contains("Console") => crate::console::scroll(lines, content_height),
+            title if title.contains("Document Viewer") => crate::document_viewer::scroll(lines, self.width, content_height),
+            title if title.contains("Safari") => crate::safari::scroll(lines, self.width, content_height),
+            title if title.contains("Mail") => crate::mail::scroll(lines, self.width, content_height),
+            title if title.contains("Terminal") => crate::terminal::scroll(lines, content_height),
+            title if title.contains("Finder") => crate::finder::scroll(lines),
+            _ => {}
+        }
+    }
+    
+    // The app's controls that take keyboard focus, in Tab order, as
+    // (x, y, width, height) in the content
+    fn controls(&self) -> Vec<(usize, usize, usize, usize)> {
+        match self.title.as_str() {
+            _ if self.surface.is_some() => Vec::new(),
+            title if title.contains("Preview") => crate::preview::controls(),
+            title if title.contains("Safari") => crate::safari::controls(),
+            title if title.contains("Mail") => crate::mail::controls(),
+            _ => Vec::new(),
+        }
+    }
+    
+    // Key presses go to the focused window's app. Tab and Shift+Tab move
+    // focus through the app's controls, and Space or Enter presses the
+    // focused one as a click on it would.
+    pub fn key(&mut self, event: &KeyEvent) {
+        if let Some(surface) = self.surface {
+            display_server::key(surface, event);
+            return;
+        }
+        let controls = self.controls();
+        if event.key == Key::Tab && !event.cmd && !event.ctrl && !controls.is_empty() {
+            self.focus.advance(controls.len(), event.shift);
+            return;
+        }
+        if let (Key::Space | Key::Enter, Some(index)) = (event.key, self.focus.current(controls.len())) {
+            let (x, y, width, height) = controls[index];
+            self.click(x + width / 2, 36 + y + height / 2);
+            return;
+        }
+        if self.title.contains("Snake") {
+            crate::snake::handle_key(event.key);
+        } else if self.title.contains("Preview") {
+            crate::preview::handle_key(event);
+        } else if self.title.contains("Document Viewer") {
+            crate::document_viewer::handle_key(event, self.width, self.height - 36);
+        } else if self.title.contains("Safari") {
+            crate::safari::handle_key(event, self.width, self.height - 36);
+        } else if self.title.contains("Mail") {
+            crate::mail::handle_key(event, self.width, self.height - 36);
+        } else if self.title.contains("Finder") {
+            crate::finder::handle_key(event);
+        } else if self.title.contains("Keychain Access") {
+            crate::keychain_access::handle_key(event);
+        } else if self.title.contains("Terminal") {
+            crate::terminal::handle_key(event);
+        }
+    }
+    
+    // A character from outside the keyboard, for the app's text field
+    // that has the focus
+    pub fn text(&mut self, ch: char) {
+        if let Some(surface) = self.surface {
+            display_server::text(surface, ch);
+        } else if self.title.contains("Finder") {
+            crate::finder::insert_text(ch);
+        } else if self.title.contains("Keychain Access") {
+            crate::keychain_access::insert_text(ch);
+        } else if self.title.contains("Safari") {
+            crate::safari::insert_text(ch);
+        } else if self.title.contains("Document Viewer") {
+            crate::document_viewer::insert_text(ch);
+        } else if self.title.contains("Terminal") {
+            crate::terminal::insert_text(ch);
+        }
+    }
+    
+    // What the app has for the services: Mail's open message, Preview's
+    // image
+    pub fn service_data(&self) -> Option<Data> {
+        match self.title.as_str() {
+            title if title.contains("Mail") => crate::mail::service_data(),
+            title if title.contains("Preview") => crate::preview::service_data(),
+            _ => None,
+        }
+    }
+    
+    // `x` and `y` are relative to the window's top-left corner. Whether the
+    // app opened a menu for the point.
+    pub fn right_click(&mut self, x: usize, y: usize) -> bool {
+        y >= 36 && self.title.contains("Finder") && crate::finder::right_click(x, y - 36)
+    }
+    
+    // `x` and `y` are relative to the window's top-left corner. Returns the
+    // path of a file the click asked to open.
+    pub fn click(&mut self, x: usize, y: usize) -> Option<String> {
+        if y < 36 {
+            return None;
+        }
+        if let Some(surface) = self.surface {
+            display_server::click(surface, x.saturating_sub(1), y - 36);
+            return None;
+        }
+        // A click on a control focuses it, and anywhere else takes focus
+        // off the controls
+        self.focus.focused = self.controls()
+            .iter()
+            .position(|&(cx, cy, width, height)| x >= cx && x < cx + width && y - 36 >= cy && y - 36 < cy + height);
+        match self.title.as_str() {
+            title if title.contains("Finder") => return crate::finder::click(x, y - 36, self.width),
+            title if title.contains("Snake") => crate::snake::click(x, y - 36, self.width),
+            title if title.contains("Preview") => crate::preview::click(x, y - 36),
+            title if title.contains("Safari") => crate::safari::click(x, y - 36, self.width),
+            title if title.contains("Mail") => crate::mail::click(x, y - 36, self.height - 36),
+            title if title.contains("Document Viewer") => crate::document_viewer::click(x, y - 36, self.width),
+            title if title.contains("Disk Utility") => crate::disk_utility::click(x, y - 36),
+            title if title.contains("Activity Monitor") => crate::activity_monitor::click(x, y - 36),
+            title if title.contains("Keychain Access") => crate::keychain_access::click(x, y - 36),
+            title if title.contains("Terminal") => crate::terminal::click(x, y - 36, self.width),
+            title if title.contains("System Preferences") => {
+                crate::remote_console::click_preference(x, y - 36);
+                crate::appearance::click_preference(x, y - 36);
+                crate::login_items::click_preference(x, y - 36);
+                crate::file_associations::click_preference(x, y - 36);
+                crate::focus::click_preference(x, y - 36);
+                crate::energy_saver::click_preference(x, y - 36);
+                crate::do_not_disturb::click_preference(x, y - 36);
+                crate::weather::click_preference(x, y - 36);
+            }
+            _ => {}
+        }
+        None
+    }
+    
+    // Pointer movement with the button held
+    pub fn drag(&mut self, dx: i32, dy: i32) {
+        if let Some(surface) = self.surface {
+            display_server::drag(surface, dx, dy);
+        } else if self.title.contains("Preview") {
+            crate::preview::drag(dx, dy);
+        } else if self.title.contains("Terminal") {
+            crate::terminal::drag(dx, dy);
+        }
     }
     
     fn draw_content(&self, graphics: &mut Graphics) {
         let content_y = self.y + 36;
         let content_height = self.height - 36;
         
+        if let Some(surface) = self.surface {
+            display_server::draw(graphics, surface, self.x + 1, content_y, self.width - 2, content_height - 1);
+            return;
+        }
         match self.title.as_str() {
             title if title.contains("Finder") => self.draw_finder_content(graphics, content_y, content_height),
-            title if title.contains("Terminal") => self.draw_terminal_content(graphics, content_y, content_height),
+            title if title.contains("Terminal") => crate::terminal::draw(graphics, self.x, content_y, self.width, content_height),
             title if title.contains("System Preferences") => self.draw_preferences_content(graphics, content_y, content_height),
             title if title.contains("Safari") => self.draw_safari_content(graphics, content_y, content_height),
+            title if title.contains("Activity Monitor") => crate::activity_monitor::draw(graphics, self.x, content_y, self.width, content_height),
+            title if title.contains("Disk Utility") => crate::disk_utility::draw(graphics, self.x, content_y, self.width, content_height),
+            title if title.contains("Console") => crate::console::draw(graphics, self.x, content_y, self.width, content_height),
+            title if title.contains("Snake") => crate::snake::draw(graphics, self.x, content_y, self.width, content_height, self.is_focused),
+            title if title.contains("Preview") => crate::preview::draw(graphics, self.x, content_y, self.width, content_height),
+            title if title.contains("Document Viewer") => crate::document_viewer::draw(graphics, self.x, content_y, self.width, content_height),
+            title if title.contains("Mail") => crate::mail::draw(graphics, self.x, content_y, self.width, content_height),
+            title if title.contains("Keychain Access") => crate::keychain_access::draw(graphics, self.x, content_y, self.width, content_height),
             _ => self.draw_default_content(graphics, content_y, content_height),
         }
     }
@@ -159,7 +603,7 @@ impl Window {
         // Draw toolbar
         graphics.draw_rect(self.x + 1, content_y, self.width - 2, 40, Color::new(248, 248, 248));
         graphics.draw_text("⬅️ ➡️", self.x + 10, content_y + 15, Color::BLACK);
-        graphics.draw_text("📁 Home > Documents", self.x + 60, content_y + 15, Color::BLACK);
+        graphics.draw_text("📁 Home", self.x + 60, content_y + 15, Color::BLACK);
         graphics.draw_text("🔍", self.x + self.width - 40, content_y + 15, Color::BLACK);
         
         // Draw sidebar
@@ -173,10 +617,26 @@ impl Window {
             graphics.draw_text(item, self.x + 10, content_y + 75 + i * 20, Color::BLACK);
         }
         
+        // Mounted volumes
+        let volumes = crate::finder::volumes();
+        let locations_y = content_y + 85 + favorites.len() * 20;
+        if !volumes.is_empty() {
+            graphics.draw_text("LOCATIONS", self.x + 10, locations_y, Color::new(142, 142, 147));
+            for (i, name) in volumes.iter().enumerate() {
+                graphics.draw_text("💽", self.x + 10, locations_y + 20 + i * 20, Color::BLACK);
+                graphics.draw_text(name, self.x + 30, locations_y + 20 + i * 20, Color::BLACK);
+            }
+        }
+        
         // Main content area
         let main_x = self.x + sidebar_width + 1;
         let main_width = self.width - sidebar_width - 2;
         
+        // Browse the home folder, falling back to the sample files
+        if crate::finder::draw(graphics, self.x, content_y, self.width, content_height) {
+            return;
+        }
+        
         // Draw file grid
         let files = [
             ("📁", "Projects"), ("📁", "Photos"), ("📄", "Resume.pdf"), ("📊", "Budget.xlsx"),
@@ -194,36 +654,6 @@ impl Window {
         }
     }
     
-    fn draw_terminal_content(&self, graphics: &mut Graphics, content_y: usize, content_height: usize) {
-        let lines = [
-            "Last login: Thu Jun 19 12:34:56 on ttys000",
-            "RustOS:~ user$ ls -la",
-            "total 42",
-            "drwxr-xr-x   8 user  staff   256 Jun 19 12:34 .",
-            "drwxr-xr-x   3 root  admin    96 Jun 19 12:30 ..",
-            "-rw-------   1 user  staff   123 Jun 19 12:34 .bash_history",
-            "drwx------   3 user  staff    96 Jun 19 12:30 .config",
-            "drwxr-xr-x   5 user  staff   160 Jun 19 12:32 Documents",
-            "drwxr-xr-x   3 user  staff    96 Jun 19 12:30 Desktop",
-            "-rw-r--r--   1 user  staff  1024 Jun 19 12:33 README.md",
-            "RustOS:~ user$ cargo --version",
-            "cargo 1.70.0 (7fe40dc 2023-04-27)",
-            "RustOS:~ user$ █",
-        ];
-        
-        for (i, line) in lines.iter().enumerate() {
-            let line_y = content_y + 10 + i * 16;
-            let color = if line.starts_with("RustOS:") {
-                Color::GREEN
-            } else if line.contains("cargo") || line.contains("total") {
-                Color::YELLOW
-            } else {
-                Color::WHITE
-            };
-            graphics.draw_text(line, self.x + 10, line_y, color);
-        }
-    }
-    
     fn draw_preferences_content(&self, graphics: &mut Graphics, content_y: usize, _content_height: usize) {
         // Draw preference categories
         let categories = [
@@ -250,21 +680,25 @@ impl Window {
             graphics.draw_text(title, pref_x, pref_y + 70, Color::BLACK);
             graphics.draw_text(desc, pref_x - 20, pref_y + 85, Color::GRAY);
         }
+        
+        crate::remote_console::draw_preference(graphics, self.x, content_y);
+        crate::appearance::draw_preference(graphics, self.x, content_y);
+        crate::login_items::draw_preference(graphics, self.x, content_y);
+        crate::file_associations::draw_preference(graphics, self.x, content_y);
+        crate::focus::draw_preference(graphics, self.x, content_y);
+        crate::energy_saver::draw_preference(graphics, self.x, content_y);
+        crate::do_not_disturb::draw_preference(graphics, self.x, content_y);
+        crate::weather::draw_preference(graphics, self.x, content_y);
     }
     
     fn draw_safari_content(&self, graphics: &mut Graphics, content_y: usize, content_height: usize) {
-        // Draw address bar
-        graphics.draw_rounded_rect(self.x + 80, content_y + 10, self.width - 160, 30, Color::WHITE);
-        graphics.draw_rect_outline(self.x + 80, content_y + 10, self.width - 160, 30, Color::LIGHT_GRAY);
-        graphics.draw_text("https://rustos.dev/docs", self.x + 90, content_y + 25, Color::BLACK);
-        
-        // Draw tab bar
-        graphics.draw_rect(self.x + 1, content_y, self.width - 2, 40, Color::new(235, 235, 235));
-        graphics.draw_text("📄 RustOS Docs", self.x + 20, content_y + 15, Color::BLACK);
-        graphics.draw_text("+ New Tab", self.x + 150, content_y + 15, Color::GRAY);
-        
-        // Draw web content
-        let web_content_y = content_y + 50;
+        // Tabs, toolbar and pages other than the start page come from safari.rs
+        if crate::safari::draw(graphics, self.x, content_y, self.width, content_height) {
+            return;
+        }
+        
+        // Draw the start page
+        let web_content_y = content_y + crate::safari::CHROME_HEIGHT - 10;
         graphics.draw_text("RustOS Documentation", self.x + 20, web_content_y + 20, Color::BLACK);
         graphics.draw_text("Welcome to RustOS - A macOS-inspired operating system", self.x + 20, web_content_y + 45, Color::GRAY);
         
@@ -1352,10 +1786,20 @@ impl Window {
     }
 }
 
+// Exit status of a force-quit app, as if killed by SIGKILL
+pub const FORCE_QUIT_EXIT_CODE: i32 = -9;
+
 pub struct WindowManager {
     windows: Vec<Window>,
     focused_window: Option<usize>,
     next_window_id: usize,
+    // Set while a window is being resized, cleared by `draw_all`
+    redraw_requested: bool,
+    // Closed windows still playing their close animation
+    closing: Vec<Window>,
+    // The full screen app whose space is showing; None for the desktop,
+    // where full screen windows are hidden
+    space: Option<String>,
 }
 
 impl WindowManager {
@@ -1364,12 +1808,18 @@ impl WindowManager {
             windows: Vec::new(),
             focused_window: None,
             next_window_id: 0,
+            redraw_requested: false,
+            closing: Vec::new(),
+            space: None,
         }
     }
     
     pub fn add_window(&mut self, mut window: Window) {
         window.is_focused = self.windows.is_empty();
-        self.windows.push(window);
+        if window.pid.is_none() {
+            window.pid = task::spawn(window.app_name()).ok();
+        }
+        crate::heap_profiler::tagged("window_manager", || self.windows.push(window));
         if self.focused_window.is_none() {
             self.focused_window = Some(0);
         }
@@ -1380,32 +1830,275 @@ impl WindowManager {
         // Update window focus states
         for (i, window) in self.windows.iter_mut().enumerate() {
             window.is_focused = Some(i) == self.focused_window;
+            if window.is_focused {
+                window.last_focused = Instant::now();
+            }
         }
         
-        // Draw unfocused windows first (back to front)
-        for (i, window) in self.windows.iter().enumerate() {
-            if Some(i) != self.focused_window {
-                window.draw(graphics);
+        // Draw unfocused windows first (back to front), skipping any that
+        // windows above hide completely
+        let hidden: Vec<bool> = (0..self.windows.len()).map(|i| self.is_covered(i) || !self.is_shown(i)).collect();
+        for (i, window) in self.windows.iter_mut().enumerate() {
+            if Some(i) != self.focused_window && !window.is_pip && !hidden[i] {
+                crate::watchdog::drawing(Some(i));
+                crate::cpu_usage::running(window.pid);
+                window.present(graphics);
             }
         }
         
         // Draw focused window last (on top)
-        if let Some(focused_idx) = self.focused_window {
-            if let Some(window) = self.windows.get(focused_idx) {
-                window.draw(graphics);
+        if let Some(focused_idx) = self.focused_window.filter(|&i| self.is_shown(i)) {
+            if let Some(window) = self.windows.get_mut(focused_idx) {
+                crate::watchdog::drawing(Some(focused_idx));
+                crate::cpu_usage::running(window.pid);
+                window.present(graphics);
             }
         }
+        
+        // PiP tiles float above the other windows
+        let mut bottom = PIP_BOTTOM;
+        for (i, window) in self.windows.iter_mut().enumerate() {
+            if window.is_pip && !window.is_minimized() && !window.is_hidden {
+                crate::watchdog::drawing(Some(i));
+                crate::cpu_usage::running(window.pid);
+                let (x, y, width, height) = window.pip_frame(bottom);
+                window.draw_transformed(graphics, x as f32, y as f32, width as f32, height as f32, window.transparency);
+                bottom = y.saturating_sub(PIP_MARGIN);
+            }
+        }
+        crate::watchdog::drawing(None);
+        crate::cpu_usage::running(None);
+        
+        // Closing windows fade out on top of everything
+        for window in self.closing.iter_mut() {
+            window.present(graphics);
+        }
+        self.closing.retain(|window| window.is_animating());
+        self.redraw_requested = false;
+    }
+    
+    // Whether the window is in the space showing. PiP tiles are in all;
+    // hidden windows are in none.
+    fn is_shown(&self, index: usize) -> bool {
+        let window = &self.windows[index];
+        match &self.space {
+            _ if window.is_hidden => false,
+            _ if window.is_pip => true,
+            Some(app) => window.app_name() == app,
+            None => window.state != WindowState::Fullscreen,
+        }
+    }
+    
+    // Shows a full screen app's space, or the desktop for None, focusing
+    // the frontmost window there if the focused one isn't
+    pub fn show_space(&mut self, app: Option<&str>) {
+        if self.space.as_deref() == app {
+            return;
+        }
+        self.space = app.map(String::from);
+        if self.focused_window.is_none_or(|i| !self.is_shown(i)) {
+            self.focused_window = self.frontmost_shown();
+        }
     }
     
+    // The frontmost window that can take focus in the space showing
+    fn frontmost_shown(&self) -> Option<usize> {
+        (0..self.windows.len())
+            .rev()
+            .find(|&i| self.is_shown(i) && !self.windows[i].is_minimized() && !self.windows[i].is_pip)
+    }
+    
+    // Whether windows drawn after it hide the window completely
+    fn is_covered(&self, index: usize) -> bool {
+        let window = &self.windows[index];
+        if Some(index) == self.focused_window || window.is_animating() {
+            return false;
+        }
+        self.windows
+            .iter()
+            .enumerate()
+            .any(|(i, above)| (i > index || Some(i) == self.focused_window) && above.covers(window))
+    }
+    
+    // Pages out what the apps behind minimized and hidden windows keep; the
+    // bytes of heap freed
+    pub fn page_out_hidden(&self) -> usize {
+        (0..self.windows.len())
+            .filter(|&i| self.windows[i].is_minimized() || self.windows[i].is_hidden || self.is_covered(i))
+            .map(|i| self.windows[i].page_out())
+            .sum()
+    }
+    
+    // PiP windows never take focus
     pub fn focus_window(&mut self, index: usize) {
-        if index < self.windows.len() {
+        if index < self.windows.len() && !self.windows[index].is_pip {
             self.focused_window = Some(index);
         }
     }
     
     pub fn close_window(&mut self, index: usize) {
+        self.terminate(index, 0);
+    }
+    
+    // Brings the app forward, opening a window first if the app isn't
+    // running. The index of the window focused; None for a name the app
+    // registry doesn't know.
+    pub fn launch(&mut self, name: &str) -> Option<usize> {
+        let app = apps::find(name)?;
+        if let Some(index) = self.windows.iter().rposition(|w| w.app == Some(app.id())) {
+            self.bring_forward(index);
+            return Some(index);
+        }
+        let mut window = app.window();
+        window.animation = Some(WINDOW_ANIMATIONS.alloc(WindowAnimation::open(window.x as f32, window.y as f32, window.width as f32, window.height as f32)));
+        self.add_window(window);
+        self.focus_window(self.windows.len() - 1);
+        Some(self.windows.len() - 1)
+    }
+    
+    // The process of a running app
+    pub fn pid_of(&self, name: &str) -> Option<crate::task::Pid> {
+        self.windows.iter().find(|w| w.app_name() == name).and_then(|w| w.pid)
+    }
+    
+    pub fn is_running(&self, name: &str) -> bool {
+        self.windows.iter().any(|w| w.app_name() == name)
+    }
+    
+    // Gives a display server surface a window in front, cascading from the
+    // top left, with its content the surface's size
+    pub fn open_surface(&mut self, surface: SurfaceId, client: Pid, title: String, width: usize, height: usize) {
+        let offset = SURFACE_CASCADE * (surface as usize % 8);
+        let mut window = Window::new(title, 80 + offset, 60 + offset, width + 2, height + 37, Color::WHITE);
+        window.pid = Some(client);
+        window.surface = Some(surface);
+        self.add_window(window);
+        self.focus_window(self.windows.len() - 1);
+    }
+    
+    pub fn set_surface_title(&mut self, surface: SurfaceId, title: String) {
+        if let Some(window) = self.windows.iter_mut().find(|w| w.surface == Some(surface)) {
+            window.title = title;
+        }
+    }
+    
+    // The surface is gone, so the client hears nothing of its window closing
+    pub fn close_surface(&mut self, surface: SurfaceId) {
+        if let Some(index) = self.windows.iter().position(|w| w.surface == Some(surface)) {
+            self.terminate(index, 0);
+        }
+    }
+    
+    // Whether all of a running app's windows are hidden
+    pub fn is_hidden(&self, name: &str) -> bool {
+        let mut windows = self.windows.iter().filter(|w| w.app_name() == name).peekable();
+        windows.peek().is_some() && windows.all(|w| w.is_hidden)
+    }
+    
+    // Hides all of the app's windows, focusing the frontmost window left
+    pub fn hide_app(&mut self, app: AppId) {
+        for window in self.windows.iter_mut().filter(|w| w.app == Some(app)) {
+            window.is_hidden = true;
+        }
+        if self.focused_window.is_none_or(|i| !self.is_shown(i)) {
+            self.focused_window = self.frontmost_shown();
+        }
+    }
+    
+    // Brings back all of the app's hidden windows, focusing its frontmost
+    // one that isn't minimized
+    fn show_app(&mut self, app: AppId) {
+        for window in self.windows.iter_mut().filter(|w| w.app == Some(app)) {
+            window.is_hidden = false;
+        }
+        let front = (0..self.windows.len())
+            .rev()
+            .find(|&i| self.windows[i].app == Some(app) && self.is_shown(i) && !self.windows[i].is_minimized() && !self.windows[i].is_pip);
+        if let Some(index) = front {
+            self.focus_window(index);
+        }
+    }
+    
+    // Focuses the window from wherever it is: minimized, hidden with its
+    // app, or shrunk to a PiP tile
+    pub fn bring_forward(&mut self, index: usize) {
+        if index >= self.windows.len() {
+            return;
+        }
+        match self.windows[index].app {
+            Some(app) => self.show_app(app),
+            None => self.windows[index].is_hidden = false,
+        }
+        self.windows[index].unminimize();
+        self.windows[index].is_pip = false;
+        self.focus_window(index);
+    }
+    
+    // The app's windows, PiP tiles aside, the one focused last first
+    pub fn windows_of(&self, app: &str) -> Vec<usize> {
+        let mut windows: Vec<usize> = (0..self.windows.len())
+            .filter(|&i| self.windows[i].app_name() == app && !self.windows[i].is_pip)
+            .collect();
+        windows.sort_by_key(|&i| core::cmp::Reverse(self.windows[i].last_focused));
+        windows
+    }
+    
+    // Every app with windows open, with `windows_of` it, the app used last
+    // first, for the Cmd+Tab switcher
+    pub fn apps_by_recency(&self) -> Vec<(String, Vec<usize>)> {
+        let mut apps: Vec<(String, Vec<usize>)> = Vec::new();
+        for window in self.windows.iter().filter(|w| !w.is_pip) {
+            if apps.iter().all(|(app, _)| app != window.app_name()) {
+                apps.push((String::from(window.app_name()), self.windows_of(window.app_name())));
+            }
+        }
+        apps.sort_by_key(|(_, windows)| core::cmp::Reverse(self.windows[windows[0]].last_focused));
+        apps
+    }
+    
+    pub fn set_edited(&mut self, app: &str, edited: bool) {
+        for window in self.windows.iter_mut().filter(|w| w.app_name() == app) {
+            window.is_edited = edited;
+        }
+    }
+    
+    // The frontmost window with unsaved changes
+    pub fn last_edited(&self) -> Option<usize> {
+        self.windows.iter().rposition(|w| w.is_edited)
+    }
+    
+    pub fn focused_index(&self) -> Option<usize> {
+        self.focused_window
+    }
+    
+    pub fn close_all(&mut self) {
+        while !self.windows.is_empty() {
+            self.close_window(self.windows.len() - 1);
+        }
+    }
+    
+    // Closes the window and ends its app with `exit_code`. Only apps that
+    // quit normally animate out; drawing one that crashed or hung again
+    // could crash or hang again.
+    pub fn terminate(&mut self, index: usize, exit_code: i32) {
         if index < self.windows.len() {
-            self.windows.remove(index);
+            let mut window = self.windows.remove(index);
+            // A client's window goes without its process, which the
+            // client runs; the desktop is every app's parent, so it reaps
+            // them straight away
+            if let Some(surface) = window.surface {
+                display_server::closed(surface);
+            } else if let Some(pid) = window.pid {
+                if task::exit(pid, exit_code).is_ok() {
+                    task::wait(pid);
+                }
+            }
+            if exit_code == 0 && !window.is_minimized() && !window.is_hung {
+                let (x, y, width, height) = (window.x as f32, window.y as f32, window.width as f32, window.height as f32);
+                window.animation = Some(WINDOW_ANIMATIONS.alloc(WindowAnimation::close(x, y, width, height)));
+                window.live_resize = None;
+                self.closing.push(window);
+            }
             
             // Update focused window index
             if let Some(focused) = self.focused_window {
@@ -1424,9 +2117,20 @@ impl WindowManager {
         }
     }
     
+    // Quits the app behind the background window that had focus longest
+    // ago, as if force quit; its name
+    pub fn close_least_recently_used(&mut self) -> Option<String> {
+        let index = (0..self.windows.len())
+            .filter(|&i| Some(i) != self.focused_window && !self.windows[i].is_pip)
+            .min_by_key(|&i| self.windows[i].last_focused)?;
+        let name = String::from(self.windows[index].app_name());
+        self.terminate(index, FORCE_QUIT_EXIT_CODE);
+        Some(name)
+    }
+    
     pub fn minimize_window(&mut self, index: usize) {
         if let Some(window) = self.windows.get_mut(index) {
-            window.is_minimized = true;
+            window.set_state(WindowState::Minimized);
             
             // Focus next window
             if Some(index) == self.focused_window {
@@ -1435,21 +2139,38 @@ impl WindowManager {
         }
     }
     
-    pub fn maximize_window(&mut self, index: usize) {
+    // The green button: maximizes the window, or puts it back
+    pub fn toggle_maximized(&mut self, index: usize) {
+        self.toggle_state(index, WindowState::Maximized);
+    }
+    
+    // Fills the screen with the window and hides the menu bar and dock, or
+    // goes back to how it was
+    pub fn toggle_fullscreen(&mut self, index: usize) {
+        match self.windows.get_mut(index) {
+            Some(window) if window.state == WindowState::Fullscreen => window.restore(),
+            Some(window) => window.set_state(WindowState::Fullscreen),
+            None => {}
+        }
+    }
+    
+    pub fn tile(&mut self, index: usize, state: WindowState) {
+        self.toggle_state(index, state);
+    }
+    
+    // Into `state`, or back to Normal if it's already there
+    fn toggle_state(&mut self, index: usize, state: WindowState) {
         if let Some(window) = self.windows.get_mut(index) {
-            window.is_maximized = !window.is_maximized;
-            
-            if window.is_maximized {
-                // Store original position/size for restoration
-                window.x = 0;
-                window.y = 24; // Below menu bar
-                window.width = 640;
-                window.height = 456; // Above dock
-            }
-            // In a real implementation, we'd restore original size here
+            window.set_state(if window.state == state { WindowState::Normal } else { state });
         }
     }
     
+    // Whether a full screen app's space is showing, so the menu bar and
+    // dock stay hidden
+    pub fn is_fullscreen(&self) -> bool {
+        self.space.is_some()
+    }
+    
     fn focus_next_window(&mut self) {
         if self.windows.is_empty() {
             self.focused_window = None;
@@ -1458,7 +2179,7 @@ impl WindowManager {
         
         // Find next non-minimized window
         for i in 0..self.windows.len() {
-            if !self.windows[i].is_minimized {
+            if !self.windows[i].is_minimized() && !self.windows[i].is_hidden && !self.windows[i].is_pip {
                 self.focused_window = Some(i);
                 return;
             }
@@ -1467,15 +2188,196 @@ impl WindowManager {
         self.focused_window = None;
     }
     
+    pub fn set_opacity(&mut self, index: usize, opacity: f32) {
+        if let Some(window) = self.windows.get_mut(index) {
+            window.transparency = opacity.clamp(MIN_OPACITY, 1.0);
+        }
+    }
+    
+    // Shrinks the window to a PiP tile, or brings it back to its place
+    pub fn toggle_pip(&mut self, index: usize) {
+        if let Some(window) = self.windows.get_mut(index) {
+            window.is_pip = !window.is_pip;
+            if !window.is_pip {
+                self.focus_window(index);
+            } else if Some(index) == self.focused_window {
+                self.focus_next_window();
+            }
+        }
+    }
+    
+    // The PiP window whose tile is under the point
+    pub fn pip_window_at(&self, x: usize, y: usize) -> Option<usize> {
+        let mut bottom = PIP_BOTTOM;
+        for (i, window) in self.windows.iter().enumerate() {
+            if window.is_pip && !window.is_minimized() && !window.is_hidden {
+                let (tile_x, tile_y, width, height) = window.pip_frame(bottom);
+                if x >= tile_x && x < tile_x + width && y >= tile_y && y < tile_y + height {
+                    return Some(i);
+                }
+                bottom = tile_y.saturating_sub(PIP_MARGIN);
+            }
+        }
+        None
+    }
+    
+    // The window whose title bar is under the point
+    pub fn title_bar_at(&self, x: usize, y: usize) -> Option<usize> {
+        self.get_window_at_point(x, y).filter(|&index| y < self.windows[index].y + 36)
+    }
+    
+    pub fn set_hung(&mut self, index: usize, hung: bool) {
+        if let Some(window) = self.windows.get_mut(index) {
+            window.is_hung = hung;
+        }
+    }
+    
+    pub fn scroll_focused(&mut self, lines: isize) {
+        if let Some(window) = self.focused_window.and_then(|i| self.windows.get_mut(i)) {
+            window.scroll(lines);
+        }
+    }
+    
+    pub fn key_focused(&mut self, event: &KeyEvent) {
+        if let Some(window) = self.focused_window.and_then(|i| self.windows.get_mut(i)) {
+            crate::cpu_usage::running(window.pid);
+            window.key(event);
+            crate::cpu_usage::running(None);
+        }
+    }
+    
+    pub fn text_focused(&mut self, ch: char) {
+        if let Some(window) = self.focused_window.and_then(|i| self.windows.get_mut(i)) {
+            window.text(ch);
+        }
+    }
+    
+    // Focuses the window under the point and passes it the click, or starts
+    // resizing it from the corner handle. Files the click opens go to the
+    // desktop, which asks which app to use when that isn't settled; with the
+    // Option key held they go to Safari when it can show them.
+    pub fn click_at(&mut self, x: usize, y: usize, option: bool) {
+        if let Some(index) = self.get_window_at_point(x, y) {
+            self.focus_window(index);
+            let window = &mut self.windows[index];
+            if window.on_resize_handle(x - window.x, y - window.y) {
+                window.begin_resize();
+                return;
+            }
+            crate::cpu_usage::running(window.pid);
+            let opened = window.click(x - window.x, y - window.y);
+            crate::cpu_usage::running(None);
+            if let Some(path) = opened {
+                let app = option.then(|| String::from("Safari"));
+                crate::bus::post(crate::bus::Message::OpenFile { path, app });
+            }
+        }
+    }
+    
+    // Focuses the window under the point and passes it the right-click;
+    // whether the window opened a menu for it
+    pub fn right_click_at(&mut self, x: usize, y: usize) -> bool {
+        match self.get_window_at_point(x, y) {
+            Some(index) => {
+                self.focus_window(index);
+                let window = &mut self.windows[index];
+                window.right_click(x - window.x, y - window.y)
+            }
+            None => false,
+        }
+    }
+    
+    pub fn drag_focused(&mut self, dx: i32, dy: i32) {
+        if let Some(window) = self.focused_window.and_then(|i| self.windows.get_mut(i)) {
+            if window.is_resizing() {
+                window.resize_by(dx, dy);
+                self.redraw_requested = true;
+            } else {
+                window.drag(dx, dy);
+            }
+        }
+    }
+    
+    // The mouse button went up
+    pub fn release(&mut self) {
+        for window in self.windows.iter_mut().filter(|window| window.is_resizing()) {
+            window.end_resize();
+            self.redraw_requested = true;
+        }
+    }
+    
+    // Opens the file in the app that handles its type; false if none can
+    // without asking which. Opening an application bundle launches the app.
+    pub fn open_file(&mut self, path: &str) -> bool {
+        if let Some(bundle) = path.strip_suffix(".app") {
+            return self.launch(crate::vfs::parent_and_name(bundle).1).is_some();
+        }
+        match crate::file_associations::app_for(path) {
+            Some(app) => self.open_file_with(path, app),
+            None => false,
+        }
+    }
+    
+    // Opens the file in `app` and brings the app's window forward
+    pub fn open_file_with(&mut self, path: &str, app: &str) -> bool {
+        match app {
+            "Preview" => crate::preview::open(path),
+            "Document Viewer" => crate::document_viewer::open(path),
+            "Safari" if crate::safari::can_open(path) => crate::safari::open_file(path),
+            _ => return false,
+        }
+        if let Some(index) = self.windows.iter().position(|w| w.app_name() == app) {
+            let window = &mut self.windows[index];
+            window.unminimize();
+            window.is_pip = false;
+            if app == "Document Viewer" {
+                window.title = alloc::format!("{} — {}", app, crate::vfs::parent_and_name(path).1);
+            }
+            self.focus_window(index);
+        }
+        true
+    }
+    
+    pub fn window_title(&self, index: usize) -> Option<&str> {
+        self.windows.get(index).map(|w| w.title.as_str())
+    }
+    
+    pub fn windows(&self) -> &[Window] {
+        &self.windows
+    }
+    
     pub fn needs_redraw(&self) -> bool {
-        // In a real implementation, this would track dirty regions
-        false
+        // Only resizing and window animations ask for frames so far; a real
+        // implementation would track dirty regions
+        self.redraw_requested || !self.closing.is_empty() || self.windows.iter().any(Window::is_animating)
+    }
+    
+    // The window whose close button is under the point
+    pub fn close_button_at(&self, x: usize, y: usize) -> Option<usize> {
+        self.title_button_at(x, y, 0)
+    }
+    
+    pub fn minimize_button_at(&self, x: usize, y: usize) -> Option<usize> {
+        self.title_button_at(x, y, 1)
+    }
+    
+    // The green button
+    pub fn zoom_button_at(&self, x: usize, y: usize) -> Option<usize> {
+        self.title_button_at(x, y, 2)
+    }
+    
+    // The window whose `n`th title bar button from the left is under the point
+    fn title_button_at(&self, x: usize, y: usize, n: usize) -> Option<usize> {
+        let index = self.get_window_at_point(x, y)?;
+        let window = &self.windows[index];
+        let (button_x, button_y) = (window.x + 12 + n * 24, window.y + 10);
+        (x >= button_x && x < button_x + 16 && y >= button_y && y < button_y + 16).then_some(index)
     }
     
     pub fn get_window_at_point(&self, x: usize, y: usize) -> Option<usize> {
         // Check windows from front to back (reverse order)
         for (i, window) in self.windows.iter().enumerate().rev() {
-            if !window.is_minimized &&
+            if !window.is_minimized() && !window.is_pip && self.is_shown(i) &&
                x >= window.x && x < window.x + window.width &&
                y >= window.y && y < window.y + window.height {
                 return Some(i);
@@ -0,0 +1,107 @@
+// src/window_menu.rs
+// The menu a right-click on a window's title bar opens: an opacity slider,
+// Picture in Picture, tiling to either half of the screen and Close. It
+// only reports what was picked; the desktop applies it to the window.
+use crate::graphics::{Graphics, Color, SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::widgets::Slider;
+use crate::window_manager::MIN_OPACITY;
+use alloc::format;
+
+const WIDTH: usize = 210;
+const HEIGHT: usize = 132;
+const ROW_HEIGHT: usize = 20;
+const SLIDER_Y: usize = 24;
+const PIP_Y: usize = 46;
+const TILE_LEFT_Y: usize = 66;
+const TILE_RIGHT_Y: usize = 86;
+const CLOSE_Y: usize = 106;
+
+pub enum WindowMenuAction {
+    Opacity(f32),
+    PictureInPicture,
+    TileLeft,
+    TileRight,
+    Close,
+}
+
+pub struct WindowMenu {
+    pub is_visible: bool,
+    // Index of the window it's for
+    pub window: usize,
+    x: usize,
+    y: usize,
+    opacity: f32,
+    pip: bool,
+}
+
+impl WindowMenu {
+    pub fn new() -> Self {
+        Self { is_visible: false, window: 0, x: 0, y: 0, opacity: 1.0, pip: false }
+    }
+    
+    // Opens at the pointer, kept on screen
+    pub fn show(&mut self, window: usize, x: usize, y: usize, opacity: f32, pip: bool) {
+        self.window = window;
+        self.x = x.min(SCREEN_WIDTH - WIDTH);
+        self.y = y.min(SCREEN_HEIGHT - HEIGHT);
+        self.opacity = opacity;
+        self.pip = pip;
+        self.is_visible = true;
+    }
+    
+    pub fn hide(&mut self) {
+        self.is_visible = false;
+    }
+    
+    fn slider(&self) -> Slider {
+        Slider { value: (self.opacity - MIN_OPACITY) / (1.0 - MIN_OPACITY), width: WIDTH - 24 }
+    }
+    
+    pub fn draw(&self, graphics: &mut Graphics) {
+        if !self.is_visible {
+            return;
+        }
+        graphics.draw_rounded_rect(self.x, self.y, WIDTH, HEIGHT, Color::new(248, 248, 248));
+        graphics.draw_rect_outline(self.x, self.y, WIDTH, HEIGHT, Color::GRAY);
+        
+        let percent = (self.opacity * 100.0 + 0.5) as usize;
+        graphics.draw_text(&format!("Opacity {}%", percent), self.x + 12, self.y + 8, Color::BLACK);
+        self.slider().draw(graphics, self.x + 12, self.y + SLIDER_Y);
+        graphics.draw_rect(self.x + 1, self.y + PIP_Y - 4, WIDTH - 2, 1, Color::LIGHT_GRAY);
+        
+        let pip = if self.pip { "Exit Picture in Picture" } else { "Picture in Picture" };
+        graphics.draw_text(pip, self.x + 12, self.y + PIP_Y + 6, Color::BLACK);
+        graphics.draw_text("Tile Left", self.x + 12, self.y + TILE_LEFT_Y + 6, Color::BLACK);
+        graphics.draw_text("Tile Right", self.x + 12, self.y + TILE_RIGHT_Y + 6, Color::BLACK);
+        graphics.draw_text("Close", self.x + 12, self.y + CLOSE_Y + 6, Color::BLACK);
+    }
+    
+    // None for a click that doesn't pick anything; the menu stays open
+    // while the slider is used and closes otherwise
+    pub fn click(&mut self, x: usize, y: usize) -> Option<WindowMenuAction> {
+        let inside = x >= self.x && x < self.x + WIDTH && y >= self.y && y < self.y + HEIGHT;
+        if !inside {
+            self.hide();
+            return None;
+        }
+        let slider = self.slider();
+        if slider.contains(x, y, self.x + 12, self.y + SLIDER_Y) {
+            self.opacity = MIN_OPACITY + slider.value_at(x, self.x + 12) * (1.0 - MIN_OPACITY);
+            return Some(WindowMenuAction::Opacity(self.opacity));
+        }
+        let row = |row_y: usize| y >= self.y + row_y && y < self.y + row_y + ROW_HEIGHT;
+        let action = if row(PIP_Y) {
+            Some(WindowMenuAction::PictureInPicture)
+        } else if row(TILE_LEFT_Y) {
+            Some(WindowMenuAction::TileLeft)
+        } else if row(TILE_RIGHT_Y) {
+            Some(WindowMenuAction::TileRight)
+        } else if row(CLOSE_Y) {
+            Some(WindowMenuAction::Close)
+        } else {
+            return None;
+        };
+        self.hide();
+        action
+    }
+}
\ No newline at end of file
@@ -0,0 +1,172 @@
+// src/zip.rs
+// Zip archives without compression ("stored" entries): the records for
+// writing one, and reading its central directory and local headers. Entries
+// compressed with any method are listed but their data can't be read.
+// Sizes and offsets are 32 bits; there's no Zip64.
+use crate::rtc::DateTime;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+pub const LOCAL_HEADER_SIZE: usize = 30;
+pub const END_SIZE: usize = 22;
+const LOCAL_SIGNATURE: u32 = 0x0403_4b50;
+const CENTRAL_SIGNATURE: u32 = 0x0201_4b50;
+const END_SIGNATURE: u32 = 0x0605_4b50;
+const CENTRAL_HEADER_SIZE: usize = 46;
+pub const STORED: u16 = 0;
+// Names are UTF-8
+const FLAG_UTF8: u16 = 1 << 11;
+// Unix, zip 2.0
+const MADE_BY: u16 = (3 << 8) | 20;
+const VERSION_NEEDED: u16 = 10;
+const MSDOS_DIRECTORY: u32 = 0x10;
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+// CRC-32 of data following what `crc` covers; start from 0
+pub fn crc32(crc: u32, data: &[u8]) -> u32 {
+    !data.iter().fold(!crc, |crc, &b| CRC_TABLE[((crc ^ b as u32) & 0xFF) as usize] ^ (crc >> 8))
+}
+
+#[derive(Debug, Clone)]
+pub struct ZipEntry {
+    // Directories end in '/'
+    pub path: String,
+    pub mode: u32,
+    pub mtime: u64,
+    pub crc: u32,
+    pub compressed_size: u32,
+    pub size: u32,
+    pub method: u16,
+    // Of the local header
+    pub offset: u32,
+}
+
+impl ZipEntry {
+    pub fn is_dir(&self) -> bool {
+        self.path.ends_with('/')
+    }
+}
+
+fn u16_at(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+// The fields local and central headers share, from "version needed" to
+// the name length
+fn push_common(out: &mut Vec<u8>, entry: &ZipEntry) {
+    let (time, date) = DateTime::from_unix(entry.mtime).to_dos();
+    out.extend_from_slice(&VERSION_NEEDED.to_le_bytes());
+    out.extend_from_slice(&FLAG_UTF8.to_le_bytes());
+    out.extend_from_slice(&entry.method.to_le_bytes());
+    out.extend_from_slice(&time.to_le_bytes());
+    out.extend_from_slice(&date.to_le_bytes());
+    out.extend_from_slice(&entry.crc.to_le_bytes());
+    out.extend_from_slice(&entry.compressed_size.to_le_bytes());
+    out.extend_from_slice(&entry.size.to_le_bytes());
+    out.extend_from_slice(&(entry.path.len() as u16).to_le_bytes());
+}
+
+// Written before the entry's data
+pub fn local_header(entry: &ZipEntry) -> Vec<u8> {
+    let mut out = Vec::with_capacity(LOCAL_HEADER_SIZE + entry.path.len());
+    out.extend_from_slice(&LOCAL_SIGNATURE.to_le_bytes());
+    push_common(&mut out, entry);
+    out.extend_from_slice(&0u16.to_le_bytes());
+    out.extend_from_slice(entry.path.as_bytes());
+    out
+}
+
+// Written after every entry, at `offset`: the central directory and the
+// end record
+pub fn central_directory(entries: &[ZipEntry], offset: u32) -> Vec<u8> {
+    let mut out = Vec::new();
+    for entry in entries {
+        let attributes = (entry.mode & 0xFFFF) << 16 | if entry.is_dir() { MSDOS_DIRECTORY } else { 0 };
+        out.extend_from_slice(&CENTRAL_SIGNATURE.to_le_bytes());
+        out.extend_from_slice(&MADE_BY.to_le_bytes());
+        push_common(&mut out, entry);
+        // Extra field and comment lengths, disk number, internal attributes
+        out.extend_from_slice(&[0; 8]);
+        out.extend_from_slice(&attributes.to_le_bytes());
+        out.extend_from_slice(&entry.offset.to_le_bytes());
+        out.extend_from_slice(entry.path.as_bytes());
+    }
+    let size = out.len() as u32;
+    let count = entries.len() as u16;
+    out.extend_from_slice(&END_SIGNATURE.to_le_bytes());
+    out.extend_from_slice(&[0; 4]);
+    out.extend_from_slice(&count.to_le_bytes());
+    out.extend_from_slice(&count.to_le_bytes());
+    out.extend_from_slice(&size.to_le_bytes());
+    out.extend_from_slice(&offset.to_le_bytes());
+    out.extend_from_slice(&0u16.to_le_bytes());
+    out
+}
+
+// The central directory's size and offset, from the end of the archive;
+// the end record is last, but a comment may follow it
+pub fn find_central_directory(tail: &[u8]) -> Option<(u32, u32)> {
+    let start = (0..=tail.len().checked_sub(END_SIZE)?).rev().find(|&i| u32_at(tail, i) == END_SIGNATURE)?;
+    Some((u32_at(tail, start + 12), u32_at(tail, start + 16)))
+}
+
+pub fn parse_central_directory(data: &[u8]) -> Option<Vec<ZipEntry>> {
+    let mut entries = Vec::new();
+    let mut offset = 0;
+    while offset + CENTRAL_HEADER_SIZE <= data.len() {
+        if u32_at(data, offset) != CENTRAL_SIGNATURE {
+            return None;
+        }
+        let name_len = u16_at(data, offset + 28) as usize;
+        let extra_len = u16_at(data, offset + 30) as usize;
+        let comment_len = u16_at(data, offset + 32) as usize;
+        let name_start = offset + CENTRAL_HEADER_SIZE;
+        let name = data.get(name_start..name_start + name_len)?;
+        // Only entries from Unix keep a mode
+        let mode = match u16_at(data, offset + 4) >> 8 {
+            3 => u32_at(data, offset + 38) >> 16,
+            _ => 0,
+        };
+        entries.push(ZipEntry {
+            path: String::from_utf8_lossy(name).into_owned(),
+            mode,
+            mtime: DateTime::from_dos(u16_at(data, offset + 12), u16_at(data, offset + 14)).to_unix(),
+            crc: u32_at(data, offset + 16),
+            compressed_size: u32_at(data, offset + 20),
+            size: u32_at(data, offset + 24),
+            method: u16_at(data, offset + 10),
+            offset: u32_at(data, offset + 42),
+        });
+        offset = name_start + name_len + extra_len + comment_len;
+    }
+    Some(entries)
+}
+
+// Where an entry's data starts, past the local header read at its offset
+pub fn data_offset(entry: &ZipEntry, local_header: &[u8]) -> Option<u64> {
+    if local_header.len() < LOCAL_HEADER_SIZE || u32_at(local_header, 0) != LOCAL_SIGNATURE {
+        return None;
+    }
+    let name_len = u16_at(local_header, 26) as u64;
+    let extra_len = u16_at(local_header, 28) as u64;
+    Some(entry.offset as u64 + LOCAL_HEADER_SIZE as u64 + name_len + extra_len)
+}
\ No newline at end of file